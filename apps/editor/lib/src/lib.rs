@@ -0,0 +1,277 @@
+//
+// implement core of the pix editor: canvas, brush, undo/redo, pix load/save
+//
+use rust_pixel::{
+    asset::{Asset, AssetBase, AssetType},
+    render::{buffer::Buffer, cell::cellsym, image::PixAsset, style::Color},
+    util::Rect,
+};
+
+/// undo steps kept
+pub const UNDO_MAX: usize = 100;
+
+/// symbol, colors and texture painted into a cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Brush {
+    pub sym: u8,
+    pub fg: u8,
+    pub bg: u8,
+    pub tex: u8,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        // C64 full block, white on black
+        Self {
+            sym: 160,
+            fg: 1,
+            bg: 0,
+            tex: 0,
+        }
+    }
+}
+
+pub struct EditorData {
+    pub canvas: Buffer,
+    pub brush: Brush,
+    undo: Vec<Buffer>,
+    redo: Vec<Buffer>,
+    // a mouse drag is one undo step
+    in_stroke: bool,
+    stroke_saved: bool,
+    /// changed since last load or save
+    pub dirty: bool,
+}
+
+impl EditorData {
+    pub fn new(w: u16, h: u16) -> Self {
+        Self {
+            canvas: Buffer::empty(Rect::new(0, 0, w, h)),
+            brush: Brush::default(),
+            undo: vec![],
+            redo: vec![],
+            in_stroke: false,
+            stroke_saved: false,
+            dirty: false,
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.canvas.area.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.canvas.area.height
+    }
+
+    fn inside(&self, x: u16, y: u16) -> bool {
+        x < self.width() && y < self.height()
+    }
+
+    pub fn begin_stroke(&mut self) {
+        self.in_stroke = true;
+        self.stroke_saved = false;
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.in_stroke = false;
+    }
+
+    // saves the canvas for undo before a change
+    fn checkpoint(&mut self) {
+        if self.in_stroke && self.stroke_saved {
+            return;
+        }
+        self.stroke_saved = true;
+        self.undo.push(self.canvas.clone());
+        if self.undo.len() > UNDO_MAX {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.dirty = true;
+    }
+
+    pub fn cell_brush(&self, x: u16, y: u16) -> Brush {
+        let (sym, tex, fg, bg) = self.canvas.get(x, y).get_cell_info();
+        Brush {
+            sym,
+            fg: u8::from(fg),
+            bg: u8::from(bg),
+            tex,
+        }
+    }
+
+    fn set(&mut self, x: u16, y: u16, b: Brush) {
+        self.canvas
+            .get_mut(x, y)
+            .set_symbol(cellsym(b.sym))
+            .set_fg(Color::Indexed(b.fg))
+            .set_bg(Color::Indexed(b.bg))
+            .set_texture(b.tex);
+    }
+
+    /// paints the brush into a cell, returns false if nothing changed
+    pub fn paint(&mut self, x: u16, y: u16) -> bool {
+        if !self.inside(x, y) || self.cell_brush(x, y) == self.brush {
+            return false;
+        }
+        self.checkpoint();
+        self.set(x, y, self.brush);
+        true
+    }
+
+    pub fn erase(&mut self, x: u16, y: u16) -> bool {
+        if !self.inside(x, y) || self.canvas.get(x, y).is_blank() {
+            return false;
+        }
+        self.checkpoint();
+        self.canvas.get_mut(x, y).reset();
+        true
+    }
+
+    /// brush from a cell, the eyedropper
+    pub fn pick(&mut self, x: u16, y: u16) {
+        if self.inside(x, y) {
+            self.brush = self.cell_brush(x, y);
+        }
+    }
+
+    /// paints the area of cells equal to the one at x, y
+    pub fn fill(&mut self, x: u16, y: u16) -> bool {
+        if !self.inside(x, y) {
+            return false;
+        }
+        let target = self.cell_brush(x, y);
+        if target == self.brush {
+            return false;
+        }
+        self.checkpoint();
+        let mut todo = vec![(x, y)];
+        while let Some((cx, cy)) = todo.pop() {
+            if !self.inside(cx, cy) || self.cell_brush(cx, cy) != target {
+                continue;
+            }
+            self.set(cx, cy, self.brush);
+            todo.push((cx + 1, cy));
+            todo.push((cx, cy + 1));
+            if cx > 0 {
+                todo.push((cx - 1, cy));
+            }
+            if cy > 0 {
+                todo.push((cx, cy - 1));
+            }
+        }
+        true
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(b) => {
+                self.redo.push(std::mem::replace(&mut self.canvas, b));
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(b) => {
+                self.undo.push(std::mem::replace(&mut self.canvas, b));
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// whole canvas as pix text, sym,fg,tex,bg per cell
+    pub fn to_pix(&self) -> String {
+        let mut s = format!(
+            "width={},height={},texture=255\n",
+            self.width(),
+            self.height()
+        );
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let b = self.cell_brush(x, y);
+                s.push_str(&format!("{},{},{},{} ", b.sym, b.fg, b.tex, b.bg));
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// replaces the canvas by a pix file content, clears undo
+    pub fn load_pix(&mut self, data: &[u8]) -> Result<(), String> {
+        let mut pa = PixAsset::new(AssetBase::new(AssetType::ImgPix, "editor"));
+        pa.get_base().raw_data = data.to_vec();
+        pa.parse();
+        let buf = pa.get_base().parsed_buffers.pop().unwrap_or_default();
+        if buf.area.width == 0 || buf.area.height == 0 {
+            return Err("not a pix file".to_string());
+        }
+        self.canvas = buf;
+        self.undo.clear();
+        self.redo.clear();
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        self.load_pix(&data)
+    }
+
+    pub fn save(&mut self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_pix()).map_err(|e| format!("{}: {}", path, e))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_undo_and_pix_roundtrip() {
+        let mut d = EditorData::new(4, 3);
+        d.brush = Brush {
+            sym: 81,
+            fg: 5,
+            bg: 2,
+            tex: 1,
+        };
+        d.begin_stroke();
+        assert!(d.paint(0, 0));
+        assert!(d.paint(1, 0));
+        assert!(!d.paint(1, 0));
+        d.end_stroke();
+        assert!(d.paint(3, 2));
+        assert_eq!(d.cell_brush(1, 0), d.brush);
+
+        // the stroke is one step
+        assert!(d.undo());
+        assert_ne!(d.cell_brush(3, 2), d.brush);
+        assert!(d.undo());
+        assert_ne!(d.cell_brush(0, 0), d.brush);
+        assert!(!d.undo());
+        assert!(d.redo());
+        assert_eq!(d.cell_brush(1, 0), d.brush);
+
+        d.brush.fg = 7;
+        assert!(d.fill(2, 1));
+        assert_eq!(d.cell_brush(3, 2).fg, 7);
+        assert_eq!(d.cell_brush(0, 0).fg, 5);
+
+        let pix = d.to_pix();
+        let mut e = EditorData::new(1, 1);
+        e.load_pix(pix.as_bytes()).unwrap();
+        assert_eq!((e.width(), e.height()), (4, 3));
+        assert_eq!(e.cell_brush(0, 0), d.cell_brush(0, 0));
+        assert_eq!(e.cell_brush(3, 2), d.cell_brush(3, 2));
+        assert!(e.load_pix(b"hello").is_err());
+    }
+}
@@ -0,0 +1,6 @@
+mod model;
+mod render;
+
+use pixel_macro::pixel_game;
+
+pixel_game!(Editor, "apps");
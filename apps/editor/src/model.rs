@@ -0,0 +1,192 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+// pix sprite editor
+//
+// cargo pixel r editor s [file.pix]
+//
+// keys:
+//   arrows move cursor, space paint, x erase, p pick, f fill
+//   - = fg color, _ + bg color, , . symbol, < > symbol row, t texture
+//   u undo, r redo, s save, o reload
+// mouse:
+//   left paints on canvas, right picks,
+//   colors: left sets fg, right sets bg, symbols: left sets symbol
+use editor_lib::EditorData;
+use rust_pixel::{
+    context::Context,
+    event::{Event, KeyCode, MouseButton, MouseEventKind::*},
+    game::Model,
+};
+
+pub const CANVASW: u16 = 40;
+pub const CANVASH: u16 = 25;
+// 16x16 pickers right of the canvas
+pub const PICKW: u16 = 16;
+pub const GAP: u16 = 2;
+pub const DEFAULT_FILE: &str = "tmp/editor.pix";
+
+pub enum MouseArea {
+    Canvas(u16, u16),
+    Color(u8),
+    Symbol(u8),
+}
+
+pub struct EditorModel {
+    pub data: EditorData,
+    pub cursor: (u16, u16),
+    pub path: String,
+    pub msg: String,
+}
+
+impl EditorModel {
+    pub fn new() -> Self {
+        Self {
+            data: EditorData::new(CANVASW, CANVASH),
+            cursor: (0, 0),
+            path: std::env::args().nth(1).unwrap_or(DEFAULT_FILE.to_string()),
+            msg: "".to_string(),
+        }
+    }
+
+    pub fn colors_x(&self) -> u16 {
+        self.data.width() + GAP
+    }
+
+    pub fn symbols_x(&self) -> u16 {
+        self.colors_x() + PICKW + GAP
+    }
+
+    pub fn mouse_in(&self, x: u16, y: u16) -> Option<MouseArea> {
+        if x < self.data.width() && y < self.data.height() {
+            return Some(MouseArea::Canvas(x, y));
+        }
+        if y >= PICKW {
+            return None;
+        }
+        let cx = self.colors_x();
+        if x >= cx && x < cx + PICKW {
+            return Some(MouseArea::Color((y * PICKW + x - cx) as u8));
+        }
+        let sx = self.symbols_x();
+        if x >= sx && x < sx + PICKW {
+            return Some(MouseArea::Symbol((y * PICKW + x - sx) as u8));
+        }
+        None
+    }
+
+    fn load(&mut self) {
+        self.msg = match self.data.load(&self.path) {
+            Ok(_) => format!("loaded {}", self.path),
+            Err(e) => e,
+        };
+        self.cursor = (0, 0);
+    }
+
+    fn save(&mut self) {
+        self.msg = match self.data.save(&self.path) {
+            Ok(_) => format!("saved {}", self.path),
+            Err(e) => e,
+        };
+    }
+
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let (x, y) = self.cursor;
+        let nx = (x as i32 + dx).clamp(0, self.data.width() as i32 - 1);
+        let ny = (y as i32 + dy).clamp(0, self.data.height() as i32 - 1);
+        self.cursor = (nx as u16, ny as u16);
+    }
+
+    fn handle_key(&mut self, code: KeyCode) {
+        let (x, y) = self.cursor;
+        let b = &mut self.data.brush;
+        match code {
+            KeyCode::Left => self.move_cursor(-1, 0),
+            KeyCode::Right => self.move_cursor(1, 0),
+            KeyCode::Up => self.move_cursor(0, -1),
+            KeyCode::Down => self.move_cursor(0, 1),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                self.data.paint(x, y);
+            }
+            KeyCode::Char('x') => {
+                self.data.erase(x, y);
+            }
+            KeyCode::Char('p') => self.data.pick(x, y),
+            KeyCode::Char('f') => {
+                self.data.fill(x, y);
+            }
+            KeyCode::Char('-') => b.fg = b.fg.wrapping_sub(1),
+            KeyCode::Char('=') => b.fg = b.fg.wrapping_add(1),
+            KeyCode::Char('_') => b.bg = b.bg.wrapping_sub(1),
+            KeyCode::Char('+') => b.bg = b.bg.wrapping_add(1),
+            KeyCode::Char(',') => b.sym = b.sym.wrapping_sub(1),
+            KeyCode::Char('.') => b.sym = b.sym.wrapping_add(1),
+            KeyCode::Char('<') => b.sym = b.sym.wrapping_sub(PICKW as u8),
+            KeyCode::Char('>') => b.sym = b.sym.wrapping_add(PICKW as u8),
+            KeyCode::Char('t') => b.tex = (b.tex + 1) % 8,
+            KeyCode::Char('u') => {
+                self.data.undo();
+            }
+            KeyCode::Char('r') => {
+                self.data.redo();
+            }
+            KeyCode::Char('s') => self.save(),
+            KeyCode::Char('o') => self.load(),
+            _ => {}
+        }
+    }
+}
+
+impl Model for EditorModel {
+    fn init(&mut self, _ctx: &mut Context) {
+        if std::path::Path::new(&self.path).exists() {
+            self.load();
+        } else {
+            self.msg = format!("new {}", self.path);
+        }
+    }
+
+    fn handle_input(&mut self, ctx: &mut Context, _dt: f32) {
+        let es = ctx.input_events.clone();
+        for e in &es {
+            match e {
+                Event::Key(key) => self.handle_key(key.code),
+                Event::Mouse(mou) => {
+                    let area = self.mouse_in(mou.column, mou.row);
+                    match (mou.kind, area) {
+                        (Down(MouseButton::Left), Some(MouseArea::Canvas(x, y))) => {
+                            self.cursor = (x, y);
+                            self.data.begin_stroke();
+                            self.data.paint(x, y);
+                        }
+                        (Drag(MouseButton::Left), Some(MouseArea::Canvas(x, y))) => {
+                            self.cursor = (x, y);
+                            self.data.paint(x, y);
+                        }
+                        (Down(MouseButton::Right), Some(MouseArea::Canvas(x, y))) => {
+                            self.cursor = (x, y);
+                            self.data.pick(x, y);
+                        }
+                        (Up(_), _) => self.data.end_stroke(),
+                        (Down(MouseButton::Left), Some(MouseArea::Color(c))) => {
+                            self.data.brush.fg = c
+                        }
+                        (Down(MouseButton::Right), Some(MouseArea::Color(c))) => {
+                            self.data.brush.bg = c
+                        }
+                        (Down(MouseButton::Left), Some(MouseArea::Symbol(s))) => {
+                            self.data.brush.sym = s
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        ctx.input_events.clear();
+    }
+
+    fn handle_auto(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_event(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _dt: f32) {}
+}
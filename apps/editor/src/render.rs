@@ -0,0 +1,147 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+use crate::model::{EditorModel, GAP, PICKW};
+use rust_pixel::{
+    context::Context,
+    game::Render,
+    render::{
+        cell::cellsym,
+        panel::Panel,
+        sprite::Sprite,
+        style::{Color, Style},
+    },
+};
+
+pub const STATUSH: u16 = 9;
+
+pub struct EditorRender {
+    pub panel: Panel,
+}
+
+impl EditorRender {
+    pub fn new() -> Self {
+        Self {
+            panel: Panel::new(),
+        }
+    }
+
+    // a cell showing color c
+    fn color_cell(sp: &mut Sprite, x: u16, y: u16, c: u8) {
+        #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+        sp.set_graph_sym(x, y, 0, 160, Color::Indexed(c));
+        #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
+        sp.set_color_str(x, y, " ", Color::Reset, Color::Indexed(c));
+    }
+
+    pub fn draw_canvas(&mut self, ctx: &mut Context, d: &mut EditorModel) {
+        let cv = self.panel.get_sprite("canvas");
+        cv.content = d.data.canvas.clone();
+        // cursor blinks between the brush and the cell
+        if (ctx.stage / 15) % 2 == 0 {
+            let (x, y) = d.cursor;
+            let b = d.data.brush;
+            cv.content
+                .get_mut(x, y)
+                .set_symbol(cellsym(b.sym))
+                .set_fg(Color::Indexed(b.fg))
+                .set_bg(Color::Indexed(b.bg))
+                .set_texture(b.tex);
+        }
+    }
+
+    pub fn draw_pickers(&mut self, d: &mut EditorModel) {
+        let b = d.data.brush;
+        let cs = self.panel.get_sprite("colors");
+        for i in 0..=255u8 {
+            Self::color_cell(cs, i as u16 % PICKW, i as u16 / PICKW, i);
+        }
+        let ss = self.panel.get_sprite("symbols");
+        for i in 0..=255u8 {
+            let (fg, bg) = if i == b.sym {
+                (b.bg, b.fg)
+            } else {
+                (b.fg, b.bg)
+            };
+            ss.content.set_str_tex(
+                i as u16 % PICKW,
+                i as u16 / PICKW,
+                cellsym(i),
+                Style::default()
+                    .fg(Color::Indexed(fg))
+                    .bg(Color::Indexed(bg)),
+                b.tex,
+            );
+        }
+    }
+
+    pub fn draw_status(&mut self, d: &mut EditorModel) {
+        let b = d.data.brush;
+        let st = self.panel.get_sprite("status");
+        st.content.reset();
+        let dirty = if d.data.dirty { "*" } else { "" };
+        let lines = [
+            format!("{}{}", d.path, dirty),
+            format!(
+                "{}x{} cursor {},{}",
+                d.data.width(),
+                d.data.height(),
+                d.cursor.0,
+                d.cursor.1
+            ),
+            format!("sym {} tex {}", b.sym, b.tex),
+            format!("fg {} bg {}", b.fg, b.bg),
+            "spc paint x erase p pick f fill".to_string(),
+            "-= fg _+ bg ,. <> sym t tex".to_string(),
+            "u undo r redo s save o load".to_string(),
+            d.msg.clone(),
+        ];
+        for (i, l) in lines.iter().enumerate() {
+            st.set_color_str(0, i as u16, l, Color::Gray, Color::Reset);
+        }
+        // brush preview
+        st.content.set_str_tex(
+            PICKW * 2 + GAP - 1,
+            0,
+            cellsym(b.sym),
+            Style::default()
+                .fg(Color::Indexed(b.fg))
+                .bg(Color::Indexed(b.bg)),
+            b.tex,
+        );
+    }
+}
+
+impl Render for EditorRender {
+    type Model = EditorModel;
+
+    fn init(&mut self, ctx: &mut Context, d: &mut Self::Model) {
+        let (w, h) = (d.data.width(), d.data.height());
+        let sw = PICKW * 2 + GAP;
+        self.panel.add_sprite(Sprite::new(0, 0, w, h), "canvas");
+        self.panel
+            .add_sprite(Sprite::new(d.colors_x(), 0, PICKW, PICKW), "colors");
+        self.panel
+            .add_sprite(Sprite::new(d.symbols_x(), 0, PICKW, PICKW), "symbols");
+        self.panel
+            .add_sprite(Sprite::new(d.colors_x(), PICKW + 1, sw, STATUSH), "status");
+        ctx.adapter.init(
+            d.symbols_x() + PICKW,
+            h.max(PICKW + 1 + STATUSH),
+            1.0,
+            1.0,
+            "editor".to_string(),
+        );
+        self.panel.init(ctx);
+    }
+
+    fn handle_event(&mut self, _ctx: &mut Context, _d: &mut Self::Model, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _d: &mut Self::Model, _dt: f32) {}
+
+    fn draw(&mut self, ctx: &mut Context, d: &mut Self::Model, _dt: f32) {
+        self.draw_canvas(ctx, d);
+        self.draw_pickers(d);
+        self.draw_status(d);
+        self.panel.draw(ctx).unwrap();
+    }
+}
@@ -10,6 +10,9 @@ use rust_pixel::render::style::{
 use rust_pixel::util::Rand;
 use std::collections::HashMap;
 
+mod palfile;
+pub use palfile::*;
+
 static COLORS_RGB_WITH_NAME: [(&str, u8, u8, u8); 139] = [
     ("aliceblue", 240, 248, 255),
     ("antiquewhite", 250, 235, 215),
@@ -0,0 +1,195 @@
+//
+// read and write palette files: GIMP .gpl and Adobe swatch exchange .ase
+//
+use rust_pixel::render::style::{ColorPro, ColorSpace::*};
+
+/// a named color of a palette file
+pub type NamedColor = (String, ColorPro);
+
+/// parses a GIMP palette, "R G B name" per line
+pub fn parse_gpl(text: &str) -> Result<Vec<NamedColor>, String> {
+    let mut lines = text.lines();
+    if lines.next().map(|l| l.trim()) != Some("GIMP Palette") {
+        return Err("not a GIMP palette".to_string());
+    }
+    let mut colors = vec![];
+    for l in lines {
+        let l = l.trim();
+        if l.is_empty() || l.starts_with('#') || l.starts_with("Name:") || l.starts_with("Columns:")
+        {
+            // comments, Name: and Columns: headers
+            continue;
+        }
+        let mut it = l.split_whitespace();
+        let mut v = [0u8; 3];
+        for c in v.iter_mut() {
+            *c = it
+                .next()
+                .and_then(|s| s.parse().ok())
+                .ok_or(format!("bad gpl line: {}", l))?;
+        }
+        let name = it.collect::<Vec<_>>().join(" ");
+        colors.push((name, ColorPro::from_space_u8(SRGBA, v[0], v[1], v[2], 255)));
+    }
+    Ok(colors)
+}
+
+pub fn to_gpl(name: &str, colors: &[NamedColor]) -> String {
+    let mut s = format!("GIMP Palette\nName: {}\nColumns: 8\n#\n", name);
+    for (n, c) in colors {
+        let (r, g, b, _) = c.get_srgba_u8();
+        s.push_str(&format!("{:3} {:3} {:3}\t{}\n", r, g, b, n));
+    }
+    s
+}
+
+// big endian reader of ase data
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let s = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or("ase data truncated")?;
+        self.pos += n;
+        Ok(s)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f64, String> {
+        Ok(f32::from_bits(self.u32()?) as f64)
+    }
+}
+
+/// parses an Adobe swatch exchange file, groups are flattened
+pub fn parse_ase(data: &[u8]) -> Result<Vec<NamedColor>, String> {
+    let mut r = Reader { data, pos: 0 };
+    if r.take(4)? != b"ASEF" {
+        return Err("not an ase file".to_string());
+    }
+    r.take(4)?;
+    let blocks = r.u32()?;
+    let mut colors = vec![];
+    for _ in 0..blocks {
+        let btype = r.u16()?;
+        let len = r.u32()? as usize;
+        let end = r.pos + len;
+        if btype == 0x0001 {
+            let nlen = r.u16()? as usize;
+            let units: Vec<u16> = (0..nlen).map(|_| r.u16()).collect::<Result<_, _>>()?;
+            let name = String::from_utf16_lossy(&units)
+                .trim_end_matches('\0')
+                .to_string();
+            let model = r.take(4)?;
+            let c = match model {
+                b"RGB " => ColorPro::from_space_f64(SRGBA, r.f32()?, r.f32()?, r.f32()?, 1.0),
+                b"Gray" => {
+                    let g = r.f32()?;
+                    ColorPro::from_space_f64(SRGBA, g, g, g, 1.0)
+                }
+                b"CMYK" => {
+                    let (c, m, y, k) = (r.f32()?, r.f32()?, r.f32()?, r.f32()?);
+                    ColorPro::from_space_f64(
+                        SRGBA,
+                        (1.0 - c) * (1.0 - k),
+                        (1.0 - m) * (1.0 - k),
+                        (1.0 - y) * (1.0 - k),
+                        1.0,
+                    )
+                }
+                b"LAB " => {
+                    ColorPro::from_space_f64(LabA, r.f32()? * 100.0, r.f32()?, r.f32()?, 1.0)
+                }
+                _ => return Err(format!("unknown ase color model {:?}", model)),
+            };
+            colors.push((name, c));
+        }
+        r.pos = end;
+    }
+    Ok(colors)
+}
+
+/// Adobe swatch exchange file of RGB colors
+pub fn to_ase(colors: &[NamedColor]) -> Vec<u8> {
+    let mut out = b"ASEF".to_vec();
+    out.extend(1u16.to_be_bytes());
+    out.extend(0u16.to_be_bytes());
+    out.extend((colors.len() as u32).to_be_bytes());
+    for (n, c) in colors {
+        let mut name: Vec<u16> = n.encode_utf16().collect();
+        name.push(0);
+        let mut b = vec![];
+        b.extend((name.len() as u16).to_be_bytes());
+        for u in name {
+            b.extend(u.to_be_bytes());
+        }
+        b.extend(b"RGB ");
+        let (r, g, bl, _) = c.get_srgba_u8();
+        for v in [r, g, bl] {
+            b.extend((v as f32 / 255.0).to_bits().to_be_bytes());
+        }
+        // global color
+        b.extend(0u16.to_be_bytes());
+        out.extend(1u16.to_be_bytes());
+        out.extend((b.len() as u32).to_be_bytes());
+        out.extend(b);
+    }
+    out
+}
+
+/// loads a .gpl or .ase file
+pub fn load_palette_file(path: &str) -> Result<Vec<NamedColor>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    if path.to_lowercase().ends_with(".ase") {
+        parse_ase(&data)
+    } else {
+        parse_gpl(&String::from_utf8_lossy(&data))
+    }
+}
+
+/// saves colors as .gpl or .ase by the extension of path
+pub fn save_palette_file(path: &str, name: &str, colors: &[NamedColor]) -> Result<(), String> {
+    let data = if path.to_lowercase().ends_with(".ase") {
+        to_ase(colors)
+    } else {
+        to_gpl(name, colors).into_bytes()
+    };
+    std::fs::write(path, data).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpl_and_ase_roundtrip() {
+        let gpl = "GIMP Palette\nName: test\nColumns: 2\n# comment\n255   0   0\tRed\n 10 20 30\tdark blue\n";
+        let cs = parse_gpl(gpl).unwrap();
+        assert_eq!(cs.len(), 2);
+        assert_eq!(cs[1].0, "dark blue");
+        assert_eq!(cs[1].1.get_srgba_u8(), (10, 20, 30, 255));
+        assert_eq!(parse_gpl(&to_gpl("test", &cs)).unwrap()[0].0, "Red");
+        assert!(parse_gpl("hello").is_err());
+
+        let ase = to_ase(&cs);
+        let back = parse_ase(&ase).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[0].0, "Red");
+        assert_eq!(back[0].1.get_srgba_u8(), (255, 0, 0, 255));
+        assert_eq!(back[1].1.get_srgba_u8(), (10, 20, 30, 255));
+        assert!(parse_ase(&ase[..ase.len() - 3]).is_err());
+    }
+}
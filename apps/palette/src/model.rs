@@ -6,14 +6,17 @@ use log::info;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use palette_lib::{
-    find_similar_colors, golden, gradient, random, PaletteData, COLORS_WITH_NAME,
-    COLORS_WITH_NAME_RGB_INDEX,
+    find_similar_colors, golden, gradient, load_palette_file, random, save_palette_file,
+    NamedColor, PaletteData, COLORS_WITH_NAME, COLORS_WITH_NAME_RGB_INDEX,
 };
 use rust_pixel::{
     context::Context,
     event::{event_emit, Event, KeyCode, MouseButton, MouseEventKind::*},
     game::Model,
-    render::style::{ColorPro, ColorSpace, ColorSpace::*, COLOR_SPACE_COUNT},
+    render::style::{
+        extract_palette, oklch_cmp, search_color_names, ColorNameSet, ColorPro, ColorSpace,
+        ColorSpace::*, QuantizeMethod, COLOR_SPACE_COUNT,
+    },
 };
 use PaletteState::*;
 
@@ -39,6 +42,13 @@ pub const ADJY: u16 = 2;
 pub const COL_COUNT: u16 = 4;
 pub const ROW_COUNT: u16 = 19;
 pub const C_WIDTH: u16 = 19;
+// imported with i, exported with e, a .gpl or .ase path can be given as argument
+pub const PALETTE_FILE: &str = "tmp/palette.gpl";
+// dominant colors of an image, a png or jpg path can be given as argument
+pub const EXTRACT_FILE: &str = "tmp/palette.png";
+pub const EXTRACT_COUNT: usize = RANDOM_X as usize * RANDOM_Y as usize;
+// color names found by search, same grid as random
+pub const SEARCH_COUNT: usize = RANDOM_X as usize * RANDOM_Y as usize;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, FromPrimitive)]
@@ -50,6 +60,8 @@ pub enum PaletteState {
     Random,
     Gradient,
     Golden,
+    Extract,
+    Search,
 }
 
 pub enum MouseArea {
@@ -71,6 +83,12 @@ pub struct PaletteModel {
     pub random_colors: Vec<ColorPro>,
     pub picker_colors: Vec<ColorPro>,
     pub select: Select,
+    pub imported: Vec<NamedColor>,
+    pub import_page: usize,
+    pub extracted: Vec<(ColorPro, f64)>,
+    pub extract_method: QuantizeMethod,
+    pub search_query: String,
+    pub searched: Vec<(String, ColorPro)>,
 }
 
 impl PaletteModel {
@@ -90,6 +108,12 @@ impl PaletteModel {
             random_colors: vec![],
             picker_colors: vec![],
             select: Select::new(),
+            imported: vec![],
+            import_page: 0,
+            extracted: vec![],
+            extract_method: QuantizeMethod::MedianCut,
+            search_query: String::new(),
+            searched: vec![],
         }
     }
 
@@ -157,6 +181,25 @@ impl PaletteModel {
                     return Some(MouseArea::Golden(a, b));
                 }
             }
+            // same layout as random
+            Extract => {
+                if (2..=5).contains(&y) && (1..=78).contains(&x) {
+                    let a = (x - 1) / RANDOM_W;
+                    let b = y - 2;
+                    if ((b * RANDOM_X + a) as usize) < self.extracted.len() {
+                        return Some(MouseArea::Random(a, b));
+                    }
+                }
+            }
+            Search => {
+                if (2..=5).contains(&y) && (1..=78).contains(&x) {
+                    let a = (x - 1) / RANDOM_W;
+                    let b = y - 2;
+                    if ((b * RANDOM_X + a) as usize) < self.searched.len() {
+                        return Some(MouseArea::Random(a, b));
+                    }
+                }
+            }
             // Gradient(u16, u16, u16) area, x, y
             Gradient => {
                 if (2..=19).contains(&y) && (2..=58).contains(&x) {
@@ -199,6 +242,117 @@ impl PaletteModel {
         );
     }
 
+    // argv[1] if it has one of the extensions, otherwise the default
+    fn arg_file(exts: &[&str], default: &str) -> String {
+        match std::env::args().nth(1) {
+            Some(a) if exts.iter().any(|e| a.to_lowercase().ends_with(e)) => a,
+            _ => default.to_string(),
+        }
+    }
+
+    fn palette_file() -> String {
+        Self::arg_file(&[".gpl", ".ase"], PALETTE_FILE)
+    }
+
+    // quantizes the image file and sorts its dominant colors by oklch
+    fn do_extract(&mut self, context: &mut Context) {
+        if context.state != Extract as u8 {
+            return;
+        }
+        let path = Self::arg_file(&[".png", ".jpg", ".jpeg", ".gif", ".bmp"], EXTRACT_FILE);
+        self.extracted = match load_image_rgba(&path) {
+            Ok(rgba) => extract_palette(&rgba, EXTRACT_COUNT, self.extract_method),
+            Err(e) => {
+                info!("extract palette:{}", e);
+                vec![]
+            }
+        };
+        self.extracted.sort_by(|a, b| oklch_cmp(&a.0, &b.0));
+    }
+
+    // names containing the query, or for a hex code the nearest name of each set
+    fn do_search(&mut self) {
+        let q = &self.search_query;
+        self.searched = match parse_hex_color(q).filter(|_| q.starts_with('#')) {
+            Some(c) => ColorNameSet::ALL
+                .iter()
+                .filter_map(|set| {
+                    let n = format!("{}:{}", set.prefix(), c.nearest_name_in(*set).0);
+                    ColorPro::from_name(&n).map(|nc| (n, nc))
+                })
+                .collect(),
+            None => search_color_names(&self.search_query),
+        };
+        self.searched.truncate(SEARCH_COUNT);
+    }
+
+    // typing in search view, false if the key is not taken
+    fn search_key(&mut self, context: &mut Context, code: &KeyCode) -> bool {
+        match code {
+            KeyCode::Char(c) => self.search_query.push(*c),
+            KeyCode::Backspace => {
+                self.search_query.pop();
+            }
+            KeyCode::Esc => {
+                self.switch_state(context, NameA);
+                return true;
+            }
+            _ => return false,
+        }
+        self.switch_state(context, Search);
+        true
+    }
+
+    // shows a page of colors from the palette file in the random view,
+    // loading the file first time, a short page repeats its colors
+    fn import_palette(&mut self, context: &mut Context) {
+        let count = RANDOM_X as usize * RANDOM_Y as usize;
+        if self.imported.is_empty() {
+            match load_palette_file(&Self::palette_file()) {
+                Ok(cs) if !cs.is_empty() => {
+                    info!("import {} colors", cs.len());
+                    self.imported = cs;
+                    self.import_page = 0;
+                }
+                Ok(_) => return,
+                Err(e) => {
+                    info!("import palette:{}", e);
+                    return;
+                }
+            }
+        } else {
+            self.import_page = (self.import_page + 1) % self.imported.len().div_ceil(count);
+        }
+        self.switch_state(context, Random);
+        let start = self.import_page * count;
+        self.random_colors = (0..count)
+            .map(|i| self.imported[(start + i) % self.imported.len()].1)
+            .collect();
+        self.update_main_color(context);
+    }
+
+    // saves the colors shown in random, golden or gradient view
+    fn export_palette(&mut self, context: &mut Context) {
+        let cs = if context.state == Gradient as u8 {
+            &self.gradient_colors
+        } else {
+            &self.random_colors
+        };
+        let named: Vec<NamedColor> = cs
+            .iter()
+            .map(|c| {
+                let (r, g, b, _) = c.get_srgba_u8();
+                (format!("#{:02x}{:02x}{:02x}", r, g, b), *c)
+            })
+            .collect();
+        if named.is_empty() {
+            return;
+        }
+        if let Err(e) = save_palette_file(&Self::palette_file(), "rust_pixel", &named) {
+            info!("export palette:{}", e);
+        }
+    }
+
     fn do_gradient(&mut self, context: &mut Context) {
         if context.state != Gradient as u8 {
             return;
@@ -275,7 +429,7 @@ impl PaletteModel {
                 self.select.ranges[1].x = (rgb[1] * 255.0) as usize;
                 self.select.ranges[2].x = (rgb[2] * 255.0) as usize;
             }
-            Random | Golden => {}
+            Random | Golden | Extract | Search => {}
             Gradient => {}
         }
         // find similar colors by ciede2000...
@@ -324,6 +478,18 @@ impl PaletteModel {
                 self.main_color = self.random_colors
                     [self.select.cur().y * self.select.cur().width + self.select.cur().x];
             }
+            Extract => {
+                let idx = self.select.cur().y * self.select.cur().width + self.select.cur().x;
+                if let Some(c) = self.extracted.get(idx) {
+                    self.main_color = c.0;
+                }
+            }
+            Search => {
+                let idx = self.select.cur().y * self.select.cur().width + self.select.cur().x;
+                if let Some(c) = self.searched.get(idx) {
+                    self.main_color = c.1;
+                }
+            }
             Gradient => match self.select.area {
                 0..=1 => {
                     self.main_color = get_pick_color(
@@ -432,6 +598,26 @@ impl PaletteModel {
                 self.update_main_color(context);
                 event_emit("Palette.RedrawRandom");
             }
+            Extract => {
+                self.select.clear();
+                self.do_extract(context);
+                let w = RANDOM_X as usize;
+                let n = self.extracted.len();
+                self.select
+                    .add_range(SelectRange::new(w, n.div_ceil(w).max(1), n));
+                self.update_main_color(context);
+                event_emit("Palette.RedrawRandom");
+            }
+            Search => {
+                self.select.clear();
+                self.do_search();
+                let w = RANDOM_X as usize;
+                let n = self.searched.len();
+                self.select
+                    .add_range(SelectRange::new(w, n.div_ceil(w).max(1), n));
+                self.update_main_color(context);
+                event_emit("Palette.RedrawRandom");
+            }
         }
         event_emit("Palette.RedrawMenu");
         event_emit("Palette.RedrawPanel");
@@ -533,6 +719,15 @@ impl Model for PaletteModel {
                         }
                     }
                 }
+                // paste a hex code like #ff8000 to pick it in rgb picker
+                Event::Paste(text) => {
+                    if let Some(mc) = parse_hex_color(text) {
+                        self.switch_state(context, PickerB);
+                        self.update_select_by_main_color(context, mc);
+                    }
+                }
+                Event::Key(key)
+                    if context.state == Search as u8 && self.search_key(context, &key.code) => {}
                 Event::Key(key) => match key.code {
                     KeyCode::Char('1') => {
                         self.switch_state(context, NameA);
@@ -568,6 +763,19 @@ impl Model for PaletteModel {
                     KeyCode::Char('5') => {
                         self.switch_state(context, Golden);
                     }
+                    KeyCode::Char('6') => {
+                        self.switch_state(context, Extract);
+                    }
+                    KeyCode::Char('m') if context.state == Extract as u8 => {
+                        self.extract_method = match self.extract_method {
+                            QuantizeMethod::MedianCut => QuantizeMethod::KMeans,
+                            QuantizeMethod::KMeans => QuantizeMethod::MedianCut,
+                        };
+                        self.switch_state(context, Extract);
+                    }
+                    KeyCode::Char('/') => {
+                        self.switch_state(context, Search);
+                    }
                     KeyCode::Char('a') => {
                         self.add_gradient_input(context);
                     }
@@ -577,6 +785,16 @@ impl Model for PaletteModel {
                     KeyCode::Char('g') => {
                         self.do_gradient(context);
                     }
+                    KeyCode::Char('i') => {
+                        self.import_palette(context);
+                    }
+                    KeyCode::Char('e') => {
+                        self.export_palette(context);
+                    }
+                    KeyCode::Char('c') => {
+                        let (r, g, b, _) = self.main_color.get_srgba_u8();
+                        context.clipboard_set(&format!("#{:02x}{:02x}{:02x}", r, g, b));
+                    }
                     KeyCode::Up => {
                         self.select.cur().backward_y();
                         self.update_main_color(context);
@@ -601,6 +819,7 @@ impl Model for PaletteModel {
                         // context.state = PaletteState::Picker as u8;
                     }
                 },
+                _ => {}
             }
         }
         context.input_events.clear();
@@ -611,6 +830,28 @@ impl Model for PaletteModel {
     fn handle_timer(&mut self, _context: &mut Context, _dt: f32) {}
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn load_image_rgba(path: &str) -> Result<Vec<u8>, String> {
+    image::open(path)
+        .map(|img| img.to_rgba8().into_raw())
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_image_rgba(path: &str) -> Result<Vec<u8>, String> {
+    Err(format!("{}: no image files on web", path))
+}
+
+/// parses rrggbb with optional leading #
+pub fn parse_hex_color(s: &str) -> Option<ColorPro> {
+    let h = s.trim().trim_start_matches('#');
+    if h.len() != 6 || !h.is_ascii() {
+        return None;
+    }
+    let v = |i: usize| u8::from_str_radix(&h[i..i + 2], 16).ok();
+    Some(ColorPro::from_space_u8(SRGBA, v(0)?, v(2)?, v(4)?, 255))
+}
+
 pub fn get_pick_color(width: usize, x0: usize, y0: usize, x1: usize, t: usize) -> ColorPro {
     let h = 360.0 / 4.0 / width as f64 * x1 as f64;
     let s = 1.0 / width as f64 * x0 as f64;
@@ -63,13 +63,15 @@ impl PaletteRender {
 
         // creat 7 state layers
         let help_msg = [
-            "← ↑ → ↓ mouse : select named colors    n : colors list 2",
-            "← ↑ → ↓ mouse : select named colors    n : colors list 1",
+            "← ↑ → ↓ mouse : select named colors    n : colors list 2    / : search",
+            "← ↑ → ↓ mouse : select named colors    n : colors list 1    / : search",
             "tab : switch select area   ← ↑ → ↓ mouse : change value   n : rgb picker",
             "tab : switch select area   ← ↑ → ↓ mouse : change value   n : hsv picker",
             "← ↑ → ↓ mouse : select random colors",
             "a : add input color  d : delete input color  tab ← ↑ → ↓ : change value",
             "← ↑ → ↓ mouse : select PHI(golden ratio) colors",
+            "← ↑ → ↓ mouse : select image colors   m : median cut / k-means   c : copy",
+            "type a name or #rrggbb : search css x11 xkcd names   ← ↑ → ↓ : select   esc : back",
         ];
         for (i, item) in help_msg.iter().enumerate() {
             let ls = format!("{}", i);
@@ -134,6 +136,24 @@ impl PaletteRender {
             }
         }
 
+        // extracted colors in layer 7
+        for y in 0..RANDOM_Y {
+            for x in 0..RANDOM_X {
+                let pl = Sprite::new(ADJX + x * RANDOM_W, ADJY + y, RANDOM_W - 1, 1);
+                panel.add_layer_sprite(pl, "7", &format!("extract{}", y * RANDOM_X + x));
+            }
+        }
+
+        // search query and found names in layer 8
+        let pl = Sprite::new(ADJX + 1, ADJY + RANDOM_Y + 1, C_WIDTH * 4, 1);
+        panel.add_layer_sprite(pl, "8", "search_query");
+        for y in 0..RANDOM_Y {
+            for x in 0..RANDOM_X {
+                let pl = Sprite::new(ADJX + x * RANDOM_W, ADJY + y, RANDOM_W - 1, 1);
+                panel.add_layer_sprite(pl, "8", &format!("search{}", y * RANDOM_X + x));
+            }
+        }
+
         // color picker in layer 5
         for y in 0..PICKER_COUNT_Y {
             for x in 0..PICKER_COUNT_X_GRADIENT {
@@ -194,10 +214,8 @@ impl PaletteRender {
                     pl.set_hidden(true);
                 }
             }
-            Random | Golden => {
+            Random | Golden | Extract | Search => {
                 let pl = self.panel.get_layer_sprite("select", "cursor0");
-                let idx = d.select.cur().y * RANDOM_X as usize + d.select.cur().x;
-                let cr = d.random_colors[idx];
                 pl.set_color_str(0, 0, "", Color::Green, Color::Black);
                 pl.set_pos(
                     1 + d.select.cur().x as u16 * RANDOM_W,
@@ -355,7 +373,7 @@ impl PaletteRender {
 
     pub fn draw_panel(&mut self, ctx: &mut Context, d: &mut PaletteModel) {
         info!("draw_panel_clear....");
-        for i in 0..7 {
+        for i in 0..9 {
             if i != ctx.state as usize {
                 self.panel.deactive_layer(&format!("{}", i));
             } else {
@@ -403,7 +421,74 @@ impl PaletteRender {
         }
     }
 
+    // hex code and share of pixels on each extracted color
+    pub fn draw_extract(&mut self, d: &mut PaletteModel) {
+        for i in 0..EXTRACT_COUNT {
+            let pl = self.panel.get_layer_sprite("7", &format!("extract{}", i));
+            match d.extracted.get(i) {
+                Some((cr, share)) => {
+                    let (r, g, b, _) = cr.get_srgba_u8();
+                    pl.set_color_str(
+                        0,
+                        0,
+                        format!(" #{:02x}{:02x}{:02x}{:3}%", r, g, b, (share * 100.0).round()),
+                        if cr.is_dark() {
+                            Color::White
+                        } else {
+                            Color::Black
+                        },
+                        Color::from(*cr),
+                    );
+                    pl.set_hidden(false);
+                }
+                None => pl.set_hidden(true),
+            }
+        }
+    }
+
+    // query line and names found, without the set prefix
+    pub fn draw_search(&mut self, d: &mut PaletteModel) {
+        let pl = self.panel.get_layer_sprite("8", "search_query");
+        pl.set_color_str(
+            0,
+            0,
+            format!("/{:width$}", format!("{}_", d.search_query), width = C_WIDTH as usize * 4 - 1),
+            Color::White,
+            Color::Reset,
+        );
+        for i in 0..SEARCH_COUNT {
+            let pl = self.panel.get_layer_sprite("8", &format!("search{}", i));
+            match d.searched.get(i) {
+                Some((name, cr)) => {
+                    let n = name.split(':').nth(1).unwrap_or(name);
+                    let n: String = n.chars().take(RANDOM_W as usize - 2).collect();
+                    pl.set_color_str(
+                        0,
+                        0,
+                        format!(" {:width$}", n, width = RANDOM_W as usize - 2),
+                        if cr.is_dark() {
+                            Color::White
+                        } else {
+                            Color::Black
+                        },
+                        Color::from(*cr),
+                    );
+                    pl.set_hidden(false);
+                }
+                None => pl.set_hidden(true),
+            }
+        }
+    }
+
     pub fn draw_random(&mut self, ctx: &mut Context, d: &mut PaletteModel) {
+        if ctx.state == Extract as u8 {
+            self.draw_extract(d);
+            return;
+        }
+        if ctx.state == Search as u8 {
+            self.draw_search(d);
+            return;
+        }
         if ctx.state != 4 && ctx.state != 6 {
             return;
         }
@@ -583,6 +668,8 @@ impl PaletteRender {
             Random => 2,
             Gradient => 3,
             Golden => 4,
+            // no menu item, key 6 and /
+            Extract | Search => 5,
         };
         let mut xoff = 0u16;
         let mcolor = [237, 120, 245, 0, 7, 120];
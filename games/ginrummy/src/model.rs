@@ -1,13 +1,10 @@
+use cardlib::Deck;
 use ginrummy_lib::cards::*;
 use log::info;
 use rust_pixel::event::Event;
 use rust_pixel::{context::Context, event::event_emit, game::Model, util::Rand};
 
-pub const CARDW: usize = 7;
-#[cfg(any(feature = "sdl", target_arch = "wasm32"))]
-pub const CARDH: usize = 7;
-#[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
-pub const CARDH: usize = 5;
+pub use cardlib::{CARDH, CARDW};
 
 // enum GinRummyState {
 //     Normal,
@@ -19,7 +16,7 @@ pub struct GinRummyModel {
     pub rand: Rand,
     pub cards_a: GinRummyCards,
     pub cards_b: GinRummyCards,
-    pub pool: Vec<u16>,
+    pub deck: Deck,
 }
 
 impl GinRummyModel {
@@ -28,16 +25,13 @@ impl GinRummyModel {
             rand: Rand::new(),
             cards_a: GinRummyCards::new(),
             cards_b: GinRummyCards::new(),
-            pool: vec![],
+            deck: Deck::new(false),
         }
     }
 
     pub fn shuffle_tiles(&mut self) {
-        self.pool.clear();
-        for i in 1..=52u16 {
-            self.pool.push(i);
-        }
-        self.rand.shuffle(&mut self.pool);
+        self.deck = Deck::new(false);
+        self.deck.shuffle(&mut self.rand);
     }
 
     // pub fn act(&mut self, _d: Dir, _context: &mut Context) {}
@@ -52,8 +46,8 @@ impl Model for GinRummyModel {
             // let _ = self
             //     .cards_a
             //     .assign(&vec![1, 27, 40, 30, 32, 33, 34, 51, 23, 17]);
-            self.cards_a.assign(&self.pool[0..10], false).unwrap();
-            // self.cards_b.assign(&self.pool[10..20], false).unwrap();
+            self.cards_a.assign(&self.deck.cards[0..10], false).unwrap();
+            // self.cards_b.assign(&self.deck.cards[10..20], false).unwrap();
             self.cards_b
                 .assign(&vec![2, 15, 28, 3, 4, 5, 17, 18, 31, 23], true)
                 .unwrap();
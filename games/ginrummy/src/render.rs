@@ -1,8 +1,7 @@
 use crate::model::{GinRummyModel, CARDH, CARDW};
+use cardlib::CardView;
 // use log::info;
 use rust_pixel::{
-    asset::AssetType,
-    asset2sprite,
     context::Context,
     event::{event_check, event_register},
     game::Render,
@@ -13,6 +12,7 @@ use rust_pixel::{
 
 pub struct GinRummyRender {
     pub panel: Panel,
+    pub cards: CardView,
 }
 
 impl GinRummyRender {
@@ -23,12 +23,7 @@ impl GinRummyRender {
         t.add_sprite(gb, "back");
 
         //red 10张牌, black 10张牌
-        for i in 0..20 {
-            t.add_sprite(
-                Sprite::new(0, 0, CARDW as u16, CARDH as u16),
-                &format!("t{}", i),
-            );
-        }
+        let cards = CardView::new(&mut t, "t", 20);
 
         let adj = 2u16;
         let msgred = Sprite::new(0 + adj, 2, 40, 1);
@@ -38,7 +33,7 @@ impl GinRummyRender {
 
         event_register("GinRummy.RedrawTile", "draw_tile");
 
-        Self { panel: t }
+        Self { panel: t, cards }
     }
 
     pub fn draw_tile(&mut self, ctx: &mut Context, d: &mut GinRummyModel) {
@@ -81,17 +76,9 @@ impl GinRummyRender {
         }
         for p in pv {
             let (i, bi, n, xadj) = p;
-            let l = self.panel.get_sprite(&format!("t{}", i));
-            #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
-            let ext = "pix";
-            #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
-            let ext = "txt";
-            let cn = if bi == 0 {
-                format!("poker/back.{}", ext)
-            } else {
-                format!("poker/{}.{}", bi, ext)
-            };
-            asset2sprite!(l, ctx, &cn);
+            self.cards
+                .set_card(&mut self.panel, ctx, i, Some(bi as u16));
+            let l = self.cards.sprite(&mut self.panel, i);
             let x = ((i % 10) * (CARDW - 2)) as u16 + 1u16;
             l.set_pos(x + xadj, 3u16 + (n as u16 * (CARDH + 4) as u16));
         }
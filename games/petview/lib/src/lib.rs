@@ -43,11 +43,126 @@ impl PetviewData {
     }
 }
 
+/// an image of the playlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayItem {
+    pub file: String,
+    pub caption: String,
+}
+
+/// images shown in order, from a manifest or a directory
+///
+/// A manifest has one image per line, a file name and an optional
+/// caption after a |, lines starting with # are comments:
+/// ```text
+/// # petview playlist
+/// 1.pix | Harbour at night
+/// 2.pix
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Playlist {
+    pub items: Vec<PlayItem>,
+}
+
+impl Playlist {
+    /// 1.pix to n.pix without captions, the built in demo
+    pub fn numbered(n: usize) -> Self {
+        Self {
+            items: (1..=n)
+                .map(|i| PlayItem {
+                    file: format!("{}.pix", i),
+                    caption: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let items = text
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| {
+                let (file, caption) = l.split_once('|').unwrap_or((l, ""));
+                PlayItem {
+                    file: file.trim().to_string(),
+                    caption: caption.trim().to_string(),
+                }
+            })
+            .collect();
+        Self { items }
+    }
+
+    /// playlist.txt of dir if there is one, else the .pix files of dir
+    /// in number order
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_dir(dir: &str) -> Result<Self, String> {
+        let path = std::path::Path::new(dir);
+        if let Ok(text) = std::fs::read_to_string(path.join("playlist.txt")) {
+            return Ok(Self::parse(&text));
+        }
+        let mut files: Vec<String> = std::fs::read_dir(path)
+            .map_err(|e| format!("read {}: {}", dir, e))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|f| f.ends_with(".pix"))
+            .collect();
+        // 2.pix before 10.pix
+        files.sort_by_key(|f| {
+            let stem = f.trim_end_matches(".pix");
+            (stem.parse::<u32>().unwrap_or(u32::MAX), f.clone())
+        });
+        Ok(Self {
+            items: files
+                .into_iter()
+                .map(|file| PlayItem {
+                    file,
+                    caption: String::new(),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// file of item i, wrapping around
+    pub fn file(&self, i: usize) -> &str {
+        &self.items[i % self.items.len()].file
+    }
+
+    /// caption of item i, or its file name without extension
+    pub fn caption(&self, i: usize) -> &str {
+        let it = &self.items[i % self.items.len()];
+        if it.caption.is_empty() {
+            it.file.trim_end_matches(".pix")
+        } else {
+            &it.caption
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
+
     #[test]
     fn it_works() {
         // let result = PetviewData::new();
     }
+
+    #[test]
+    fn playlist_parse() {
+        let pl = Playlist::parse("# demo\n3.pix | Harbour at night\n\n 1.pix \n");
+        assert_eq!(pl.len(), 2);
+        assert_eq!(pl.file(0), "3.pix");
+        assert_eq!(pl.caption(0), "Harbour at night");
+        assert_eq!(pl.caption(3), "1");
+        assert_eq!(Playlist::numbered(25).file(24), "25.pix");
+    }
 }
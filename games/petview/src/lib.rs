@@ -2,4 +2,7 @@ mod model;
 mod render;
 
 use pixel_macro::pixel_game;
-pixel_game!(Petview);
+pixel_game!(Petview, {
+    size: (model::PETW + 2, model::PETH),
+    title: "petview",
+});
@@ -1,15 +1,39 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
-use rust_pixel::event::Event;
+use rust_pixel::event::{Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
 // use log::info;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-use petview_lib::PetviewData;
-use rust_pixel::{context::Context, game::Model};
+use petview_lib::{PetviewData, Playlist};
+use rust_pixel::{
+    context::Context,
+    game::Model,
+    render::{
+        camera::Camera,
+        transition::{Transition, TransitionKind, WipeDir},
+    },
+};
 
 pub const PETW: u16 = 50;
 pub const PETH: u16 = 30;
 
+// seconds of a GL transition
+const TRANS_SECS: f32 = 1.6;
+const MAX_ZOOM: f32 = 4.0;
+
+// effects picked at random for the next image
+const TRANS_KINDS: [TransitionKind; 7] = [
+    TransitionKind::Crossfade,
+    TransitionKind::Dissolve,
+    TransitionKind::Pixelate,
+    TransitionKind::Wipe(WipeDir::Left),
+    TransitionKind::Wipe(WipeDir::Right),
+    TransitionKind::Wipe(WipeDir::Up),
+    TransitionKind::Wipe(WipeDir::Down),
+];
+// petview's own shaders in TRANS_FS: heart, noise, zoom, bounce, ripples
+const PET_SHADERS: [usize; 6] = [1, 2, 3, 4, 5, 6];
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, FromPrimitive)]
 pub enum PetviewState {
@@ -20,46 +44,167 @@ pub enum PetviewState {
 
 pub struct PetviewModel {
     pub data: PetviewData,
+    pub playlist: Playlist,
     pub normal_stage: u32,
     pub transbuf_stage: u32,
     pub img_cur: usize,
     pub img_next: usize,
-    pub img_count: usize,
     pub trans_effect: usize,
+    pub trans: Transition,
     pub tex_ready: bool,
-    pub progress: f32,
+    /// goes on to the next image by itself, space toggles
+    pub auto: bool,
+    /// zoom and pan of the current image, applied by the panel
+    pub camera: Camera,
+    // mouse cell of the last drag event
+    drag: Option<(u16, u16)>,
 }
 
 impl PetviewModel {
     pub fn new() -> Self {
         Self {
             data: PetviewData::new(),
+            playlist: Playlist::numbered(25),
             normal_stage: 0,
             transbuf_stage: 0,
             img_cur: 0,
             img_next: 1,
-            img_count: 25,
             trans_effect: 0,
+            trans: Transition::new(TransitionKind::Crossfade, TRANS_SECS),
             tex_ready: false,
-            progress: 0.0,
+            auto: true,
+            camera: Camera::new(PETW + 2, PETH),
+            drag: None,
+        }
+    }
+
+    // playlist.txt of the assets, else their .pix files, else the demo
+    fn load_playlist(&mut self, ctx: &Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let dir = format!(
+                "{}{}assets",
+                ctx.project_path,
+                std::path::MAIN_SEPARATOR
+            );
+            match Playlist::from_dir(&dir) {
+                Ok(pl) if !pl.is_empty() => self.playlist = pl,
+                _ => {}
+            }
         }
     }
+
+    pub fn zoomed(&self) -> bool {
+        self.camera.zoom > 1.0
+    }
+
+    /// shows image i at once, without transition
+    pub fn show(&mut self, ctx: &mut Context, i: usize) {
+        let n = self.playlist.len();
+        self.img_cur = i % n;
+        self.img_next = (self.img_cur + 1) % n;
+        self.tex_ready = false;
+        self.normal_stage = 0;
+        ctx.state = PetviewState::Normal as u8;
+        self.reset_view();
+    }
+
+    /// starts the transition to the next image, or ends a running one
+    pub fn next(&mut self, ctx: &mut Context) {
+        match PetviewState::from_u8(ctx.state).unwrap() {
+            PetviewState::TransGl => {
+                let n = self.img_next;
+                self.show(ctx, n);
+            }
+            _ => self.start_trans(ctx),
+        }
+    }
+
+    fn start_trans(&mut self, ctx: &mut Context) {
+        self.reset_view();
+        ctx.state = PetviewState::TransGl as u8;
+        let n = TRANS_KINDS.len() + PET_SHADERS.len();
+        let r = ctx.rand.rand() as usize % n;
+        let kind = TRANS_KINDS[r % TRANS_KINDS.len()];
+        self.trans = Transition::new(kind, TRANS_SECS);
+        self.trans_effect = match TRANS_KINDS.get(r) {
+            Some(k) => k.shader(),
+            None => PET_SHADERS[r - TRANS_KINDS.len()],
+        };
+    }
+
+    /// zooms by factor f around the view center, 1.0 to MAX_ZOOM
+    pub fn zoom_by(&mut self, f: f32) {
+        let z = (self.camera.zoom * f).clamp(1.0, MAX_ZOOM);
+        self.camera.set_zoom(z);
+        let (x, y) = (self.camera.pos.x, self.camera.pos.y);
+        self.pan_to(x, y);
+    }
+
+    /// moves the view by dx, dy screen cells
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let z = self.camera.zoom;
+        let (x, y) = (self.camera.pos.x + dx / z, self.camera.pos.y + dy / z);
+        self.pan_to(x, y);
+    }
+
+    // keeps the view inside the screen at the current zoom
+    fn pan_to(&mut self, x: f32, y: f32) {
+        let c = &self.camera;
+        let k = 1.0 - 1.0 / c.zoom;
+        let (mx, my) = (c.view_w as f32 / 2.0 * k, c.view_h as f32 / 2.0 * k);
+        self.camera.set_pos(x.clamp(-mx, mx), y.clamp(-my, my));
+    }
+
+    pub fn reset_view(&mut self) {
+        self.camera.set_zoom(1.0);
+        self.camera.set_pos(0.0, 0.0);
+    }
 }
 
 impl Model for PetviewModel {
     fn init(&mut self, ctx: &mut Context) {
+        self.load_playlist(ctx);
         ctx.state = PetviewState::Normal as u8;
         self.normal_stage = 0;
+        self.img_next = 1 % self.playlist.len();
     }
 
     fn handle_input(&mut self, ctx: &mut Context, _dt: f32) {
         let es = ctx.input_events.clone();
         for e in &es {
             match e {
-                Event::Key(key) => match key.code {
-                    _ => {
-                        ctx.state = PetviewState::Normal as u8;
+                Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                    KeyCode::Right | KeyCode::PageDown | KeyCode::Char('n') => self.next(ctx),
+                    KeyCode::Left | KeyCode::PageUp | KeyCode::Char('p') => {
+                        let n = self.playlist.len();
+                        let i = self.img_cur + n - 1;
+                        self.show(ctx, i);
                     }
+                    KeyCode::Char(' ') => self.auto = !self.auto,
+                    KeyCode::Char('+') | KeyCode::Char('=') => self.zoom_by(1.25),
+                    KeyCode::Char('-') => self.zoom_by(0.8),
+                    KeyCode::Char('0') => self.reset_view(),
+                    KeyCode::Char('w') => self.pan(0.0, -2.0),
+                    KeyCode::Char('s') => self.pan(0.0, 2.0),
+                    KeyCode::Char('a') => self.pan(-2.0, 0.0),
+                    KeyCode::Char('d') => self.pan(2.0, 0.0),
+                    _ => {}
+                },
+                Event::Mouse(m) => match m.kind {
+                    MouseEventKind::ScrollUp => self.zoom_by(1.25),
+                    MouseEventKind::ScrollDown => self.zoom_by(0.8),
+                    MouseEventKind::Down(MouseButton::Left) => self.drag = Some((m.column, m.row)),
+                    MouseEventKind::Drag(MouseButton::Left) => {
+                        if let Some((x, y)) = self.drag {
+                            let dx = x as f32 - m.column as f32;
+                            let dy = y as f32 - m.row as f32;
+                            self.pan(dx, dy);
+                        }
+                        self.drag = Some((m.column, m.row));
+                    }
+                    MouseEventKind::Up(MouseButton::Left) => self.drag = None,
+                    _ => {}
                 },
                 _ => {}
             }
@@ -67,11 +212,14 @@ impl Model for PetviewModel {
         ctx.input_events.clear();
     }
 
-    fn handle_auto(&mut self, ctx: &mut Context, _dt: f32) {
+    fn handle_auto(&mut self, ctx: &mut Context, dt: f32) {
         let st = PetviewState::from_usize(ctx.state as usize).unwrap();
         match st {
             PetviewState::Normal => {
-                self.normal_stage += 1;
+                // holds still while zoomed or paused
+                if self.auto && !self.zoomed() && self.tex_ready {
+                    self.normal_stage += 1;
+                }
                 if self.normal_stage > 100 {
                     ctx.state = PetviewState::TransBuf as u8;
                     self.transbuf_stage = 0;
@@ -80,19 +228,13 @@ impl Model for PetviewModel {
             PetviewState::TransBuf => {
                 self.transbuf_stage += 1;
                 if self.transbuf_stage > 60 {
-                    ctx.state = PetviewState::TransGl as u8;
-                    self.trans_effect = (ctx.rand.rand() % 7) as usize;
-                    self.progress = 0.0;
-                    self.tex_ready = false;
+                    self.start_trans(ctx);
                 }
             }
             PetviewState::TransGl => {
-                self.progress += 0.01;
-                if self.progress >= 1.0 {
-                    ctx.state = PetviewState::Normal as u8;
-                    self.normal_stage = 0;
-                    self.img_cur = (self.img_cur + 1) % self.img_count;
-                    self.img_next = (self.img_cur + 1) % self.img_count;
+                if self.trans.update(dt) {
+                    let n = self.img_next;
+                    self.show(ctx, n);
                 }
             }
         }
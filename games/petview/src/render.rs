@@ -1,6 +1,6 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
-use crate::model::{PetviewModel, PetviewState, PETH, PETW};
+use crate::model::{PetviewModel, PetviewState};
 use log::info;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
@@ -115,6 +115,9 @@ impl PetviewRender {
             Color::Reset,
         );
         panel.add_pixel_sprite(p5, "pet-msg");
+
+        let p6 = Sprite::new(160u16, 420u16, PIXW, 1u16);
+        panel.add_pixel_sprite(p6, "pet-caption");
         timer_register("PetView.Timer", 0.1, "pet_timer");
         timer_fire("PetView.Timer", 1);
 
@@ -125,16 +128,15 @@ impl PetviewRender {
 impl Render for PetviewRender {
     type Model = PetviewModel;
 
-    fn init(&mut self, ctx: &mut Context, _data: &mut Self::Model) {
-        ctx.adapter
-            .init(PETW + 2, PETH, 1.0, 1.0, "petview".to_string());
+    fn init(&mut self, ctx: &mut Context, model: &mut Self::Model) {
+        // the adapter is inited by pixel_game! in lib.rs
         self.panel.init(ctx);
 
         let p1 = self.panel.get_pixel_sprite("petimg1");
-        asset2sprite!(p1, ctx, "1.pix");
+        asset2sprite!(p1, ctx, model.playlist.file(0));
 
         let p2 = self.panel.get_pixel_sprite("petimg2");
-        asset2sprite!(p2, ctx, "2.pix");
+        asset2sprite!(p2, ctx, model.playlist.file(1));
     }
 
     fn handle_event(&mut self, ctx: &mut Context, data: &mut Self::Model, _dt: f32) {}
@@ -146,25 +148,25 @@ impl Render for PetviewRender {
                 pix.set_render_texture_hidden(3, false);
             }
             let p1 = self.panel.get_pixel_sprite("petimg1");
-            asset2sprite!(p1, ctx, &format!("{}.pix", model.img_cur + 1));
+            asset2sprite!(p1, ctx, model.playlist.file(model.img_cur));
             let l1 = p1.check_asset_request(&mut ctx.asset_manager);
             if l1 {
                 ctx.adapter.draw_buffer_to_texture(&p1.content, 0);
             }
 
             let p2 = self.panel.get_pixel_sprite("petimg2");
-            asset2sprite!(p2, ctx, &format!("{}.pix", model.img_next + 1));
+            asset2sprite!(p2, ctx, model.playlist.file(model.img_next));
             let l2 = p2.check_asset_request(&mut ctx.asset_manager);
             if l2 {
                 ctx.adapter.draw_buffer_to_texture(&p2.content, 1);
             }
 
             let p3 = self.panel.get_pixel_sprite("petimg3");
-            asset2sprite!(p3, ctx, &format!("{}.pix", model.img_next + 1));
+            asset2sprite!(p3, ctx, model.playlist.file(model.img_cur));
             p3.set_hidden(true);
 
             let p4 = self.panel.get_pixel_sprite("petimg4");
-            asset2sprite!(p4, ctx, &format!("{}.pix", model.img_next + 1));
+            asset2sprite!(p4, ctx, model.playlist.file(model.img_cur));
             p4.set_hidden(true);
 
             if l1 && l2 {
@@ -179,10 +181,15 @@ impl Render for PetviewRender {
                 pix.bind_target(gl, 3);
                 match PetviewState::from_usize(ctx.state as usize).unwrap() {
                     PetviewState::Normal => {
-                        pix.set_render_texture_hidden(3, false);
+                        // zoomed, the camera moves p3 instead of the fixed texture
+                        let zoomed = model.zoomed();
+                        pix.set_render_texture_hidden(3, zoomed);
                         let p3 = self.panel.get_pixel_sprite("petimg3");
-                        p3.set_hidden(true);
-                        pix.render_trans_frame(&gl, 0, 1.0);
+                        p3.set_alpha(255);
+                        p3.set_hidden(!zoomed);
+                        // texture 1 still holds the new image until reloaded
+                        let p = if model.tex_ready { 0.0 } else { 1.0 };
+                        pix.render_trans_frame(&gl, 0, p);
                     }
                     PetviewState::TransBuf => {
                         pix.set_render_texture_hidden(3, true);
@@ -212,7 +219,7 @@ impl Render for PetviewRender {
                         pix.set_render_texture_hidden(3, false);
                         let p3 = self.panel.get_pixel_sprite("petimg3");
                         p3.set_hidden(true);
-                        pix.render_trans_frame(&gl, model.trans_effect, model.progress);
+                        pix.render_trans_frame(&gl, model.trans_effect, model.trans.progress());
                     }
                 }
             }
@@ -220,7 +227,28 @@ impl Render for PetviewRender {
         }
     }
 
-    fn draw(&mut self, ctx: &mut Context, data: &mut Self::Model, dt: f32) {
+    fn draw(&mut self, ctx: &mut Context, model: &mut Self::Model, dt: f32) {
+        let zoomed = model.zoomed();
+        if zoomed {
+            self.panel.set_camera(model.camera.clone());
+        } else {
+            self.panel.camera = None;
+        }
+
+        let n = model.playlist.len();
+        let caption = format!(
+            "{}/{} {}{}",
+            model.img_cur + 1,
+            n,
+            model.playlist.caption(model.img_cur),
+            if model.auto { "" } else { " (paused)" }
+        );
+        let pc = self.panel.get_pixel_sprite("pet-caption");
+        pc.content.reset();
+        pc.set_color_str(1, 0, &caption, Color::Rgba(205, 205, 0, 255), Color::Reset);
+        pc.set_hidden(zoomed);
+        self.panel.get_pixel_sprite("pet-msg").set_hidden(zoomed);
+
         self.panel.draw(ctx).unwrap();
     }
 }
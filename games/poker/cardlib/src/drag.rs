@@ -0,0 +1,164 @@
+use crate::CardPile;
+use rust_pixel::event::{Event, MouseButton, MouseEventKind};
+
+/// cards moved between piles with the mouse
+///
+/// A left press on a face up card takes it and the cards above it out of
+/// its pile, they follow the mouse until released. Dropped on another
+/// pile that accepts them they move there, else they go back.
+#[derive(Debug, Clone, Default)]
+pub struct CardDrag {
+    /// pile and index the cards were taken from
+    pub from: Option<(usize, usize)>,
+    pub cards: Vec<u16>,
+    /// screen position of the first dragged card
+    pub pos: (u16, u16),
+    // grab point relative to the first card
+    grab: (u16, u16),
+    // offset between the dragged cards
+    step: (u16, u16),
+}
+
+impl CardDrag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.from.is_some()
+    }
+
+    /// the dragged cards as a pile at the mouse, to draw above the others
+    pub fn pile(&self) -> Option<CardPile> {
+        self.from?;
+        let mut p = CardPile::new(self.pos.0, self.pos.1, self.step.0, self.step.1);
+        p.cards = self.cards.clone();
+        Some(p)
+    }
+
+    /// puts the dragged cards back where they came from
+    pub fn cancel(&mut self, piles: &mut [CardPile]) {
+        if let Some((p, _)) = self.from.take() {
+            piles[p].cards.append(&mut self.cards);
+        }
+    }
+
+    /// processes mouse events, can_drop(cards, to, pile) decides if
+    /// a pile takes the cards, returns (from, to) of a move
+    pub fn update<F>(
+        &mut self,
+        events: &[Event],
+        piles: &mut [CardPile],
+        can_drop: F,
+    ) -> Option<(usize, usize)>
+    where
+        F: Fn(&[u16], usize, &CardPile) -> bool,
+    {
+        let mut moved = None;
+        for e in events {
+            let m = match e {
+                Event::Mouse(m) => m,
+                _ => continue,
+            };
+            let (x, y) = (m.column, m.row);
+            match m.kind {
+                MouseEventKind::Down(MouseButton::Left) if !self.is_dragging() => {
+                    for (pi, p) in piles.iter_mut().enumerate() {
+                        if let Some(i) = p.hit(x, y) {
+                            if p.is_face_up(i) {
+                                let (cx, cy) = p.card_pos(i);
+                                self.grab = (x - cx, y - cy);
+                                self.step = (p.dx, p.dy);
+                                self.pos = (cx, cy);
+                                self.cards = p.take_from(i);
+                                self.from = Some((pi, i));
+                            }
+                            break;
+                        }
+                    }
+                }
+                MouseEventKind::Drag(MouseButton::Left) if self.is_dragging() => {
+                    self.pos = (x.saturating_sub(self.grab.0), y.saturating_sub(self.grab.1));
+                }
+                MouseEventKind::Up(MouseButton::Left) if self.is_dragging() => {
+                    let (from, _) = self.from.unwrap();
+                    let to = (0..piles.len()).find(|&t| t != from && piles[t].contains(x, y));
+                    match to {
+                        Some(t) if can_drop(&self.cards, t, &piles[t]) => {
+                            piles[t].cards.append(&mut self.cards);
+                            self.from = None;
+                            moved = Some((from, t));
+                        }
+                        _ => self.cancel(piles),
+                    }
+                }
+                _ => {}
+            }
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_pixel::event::{KeyModifiers, MouseEvent};
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn drag_between_piles() {
+        let mut a = CardPile::new(0, 0, 0, 1);
+        a.cards = vec![1, 2, 3];
+        a.face_up_from = 1;
+        let b = CardPile::new(20, 0, 0, 1);
+        let mut piles = [a, b];
+        let left = MouseButton::Left;
+
+        // face down cards stay
+        let ev = [mouse(MouseEventKind::Down(left), 1, 0)];
+        assert_eq!(
+            CardDrag::new().update(&ev, &mut piles, |_, _, _| true),
+            None
+        );
+        assert_eq!(piles[0].len(), 3);
+
+        let mut d = CardDrag::new();
+        d.update(
+            &[mouse(MouseEventKind::Down(left), 2, 1)],
+            &mut piles,
+            |_, _, _| true,
+        );
+        assert_eq!((d.cards.clone(), piles[0].len()), (vec![2, 3], 1));
+        d.update(
+            &[mouse(MouseEventKind::Drag(left), 12, 3)],
+            &mut piles,
+            |_, _, _| true,
+        );
+        assert_eq!(d.pile().unwrap().card_pos(1), (10, 4));
+
+        // refused, back home
+        let up = [mouse(MouseEventKind::Up(left), 21, 1)];
+        assert_eq!(d.update(&up, &mut piles, |c, _, _| c.len() == 1), None);
+        assert_eq!(piles[0].cards, vec![1, 2, 3]);
+
+        d.update(
+            &[mouse(MouseEventKind::Down(left), 2, 3)],
+            &mut piles,
+            |_, _, _| true,
+        );
+        assert_eq!(
+            d.update(&up, &mut piles, |c, _, _| c.len() == 1),
+            Some((0, 1))
+        );
+        assert_eq!((piles[0].len(), piles[1].cards.clone()), (2, vec![3]));
+        assert!(!d.is_dragging());
+    }
+}
@@ -0,0 +1,116 @@
+//! cardlib holds what card games share: the card size and assets, a Deck
+//! to shuffle and deal, CardPile for hands and stacks laid out on screen,
+//! CardView to draw piles with a pool of sprites and CardDrag to move
+//! cards between piles with the mouse.
+//!
+//! Cards are 1..=54 as in PokerCard::from_u8, their sprites are
+//! assets/poker/{n}.pix in graphics mode and .txt in terminal mode.
+//!
+//! # Example
+//! ```ignore
+//! let mut deck = Deck::new(false);
+//! deck.shuffle(&mut self.rand);
+//! self.hand.cards = deck.deal(5);
+//!
+//! // in Render::new
+//! let view = CardView::new(&mut panel, "card", 10);
+//!
+//! // in Render::draw
+//! view.draw(&mut self.panel, ctx, &[&model.hand, &model.table]);
+//! ```
+
+use rust_pixel::util::Rand;
+
+mod drag;
+mod pile;
+mod view;
+pub use drag::*;
+pub use pile::*;
+pub use view::*;
+
+pub const CARDW: usize = 7;
+#[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+pub const CARDH: usize = 7;
+#[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
+pub const CARDH: usize = 5;
+
+/// asset of a card, the back if None
+pub fn card_asset(card: Option<u16>) -> String {
+    #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+    let ext = "pix";
+    #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
+    let ext = "txt";
+    match card {
+        Some(c) if c > 0 => format!("poker/{}.{}", c, ext),
+        _ => format!("poker/back.{}", ext),
+    }
+}
+
+/// cards to deal from, the top is the end of cards
+#[derive(Debug, Clone, Default)]
+pub struct Deck {
+    pub cards: Vec<u16>,
+}
+
+impl Deck {
+    /// 52 cards in order, 54 with the jokers
+    pub fn new(jokers: bool) -> Self {
+        let n = if jokers { 54 } else { 52 };
+        Self {
+            cards: (1..=n).collect(),
+        }
+    }
+
+    pub fn shuffle(&mut self, rand: &mut Rand) {
+        rand.shuffle(&mut self.cards);
+    }
+
+    pub fn draw(&mut self) -> Option<u16> {
+        self.cards.pop()
+    }
+
+    /// up to n cards from the top
+    pub fn deal(&mut self, n: usize) -> Vec<u16> {
+        let at = self.cards.len().saturating_sub(n);
+        let mut v = self.cards.split_off(at);
+        v.reverse();
+        v
+    }
+
+    /// puts cards back under the deck
+    pub fn put_under(&mut self, cards: &[u16]) {
+        let mut v = cards.to_vec();
+        v.append(&mut self.cards);
+        self.cards = v;
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deck_deal() {
+        let mut d = Deck::new(false);
+        assert_eq!(d.len(), 52);
+        assert_eq!(d.deal(2), vec![52, 51]);
+        assert_eq!(d.draw(), Some(50));
+        d.put_under(&[52]);
+        assert_eq!(d.cards[0], 52);
+        let mut r = Rand::new();
+        r.srand(7);
+        d.shuffle(&mut r);
+        assert_eq!(d.len(), 50);
+        assert_eq!(d.deal(60).len(), 50);
+        assert!(d.is_empty());
+        assert_eq!(card_asset(None), card_asset(Some(0)));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{CARDH, CARDW};
+use poker_lib::PokerCard;
+
+/// a hand, a stack or a row of cards at a screen position
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardPile {
+    /// bottom to top
+    pub cards: Vec<u16>,
+    /// cards from this index on show their face
+    pub face_up_from: usize,
+    pub x: u16,
+    pub y: u16,
+    /// offset of each card over the one below, 0, 0 stacks them
+    pub dx: u16,
+    pub dy: u16,
+}
+
+impl CardPile {
+    pub fn new(x: u16, y: u16, dx: u16, dy: u16) -> Self {
+        Self {
+            x,
+            y,
+            dx,
+            dy,
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn top(&self) -> Option<u16> {
+        self.cards.last().cloned()
+    }
+
+    pub fn push(&mut self, c: u16) {
+        self.cards.push(c);
+    }
+
+    pub fn pop(&mut self) -> Option<u16> {
+        let c = self.cards.pop();
+        self.face_up_from = self.face_up_from.min(self.cards.len());
+        c
+    }
+
+    /// takes card i and all cards above it
+    pub fn take_from(&mut self, i: usize) -> Vec<u16> {
+        let v = self.cards.split_off(i.min(self.cards.len()));
+        self.face_up_from = self.face_up_from.min(self.cards.len());
+        v
+    }
+
+    pub fn is_face_up(&self, i: usize) -> bool {
+        i >= self.face_up_from
+    }
+
+    /// shows or hides the faces of all cards
+    pub fn set_face_up(&mut self, up: bool) {
+        self.face_up_from = if up { 0 } else { usize::MAX };
+    }
+
+    /// turns the top card face up, as when a solitaire stack is cleared
+    pub fn flip_top(&mut self) {
+        if !self.cards.is_empty() {
+            self.face_up_from = self.face_up_from.min(self.cards.len() - 1);
+        }
+    }
+
+    /// sorts by rank, aces high, then by suit
+    pub fn sort_by_rank(&mut self) {
+        self.cards
+            .sort_by_key(|&c| match PokerCard::from_u8(c as u8) {
+                Ok(p) => (p.get_number(), p.get_suit()),
+                Err(_) => (0, 0),
+            });
+    }
+
+    /// screen position of card i
+    pub fn card_pos(&self, i: usize) -> (u16, u16) {
+        (self.x + self.dx * i as u16, self.y + self.dy * i as u16)
+    }
+
+    /// topmost card under the cell x, y
+    pub fn hit(&self, x: u16, y: u16) -> Option<usize> {
+        (0..self.cards.len()).rev().find(|&i| {
+            let (cx, cy) = self.card_pos(i);
+            x >= cx && x < cx + CARDW as u16 && y >= cy && y < cy + CARDH as u16
+        })
+    }
+
+    /// the cell x, y is on a card or on the place of an empty pile
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        self.hit(x, y).is_some()
+            || (x >= self.x
+                && x < self.x + CARDW as u16
+                && y >= self.y
+                && y < self.y + CARDH as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pile_layout_and_hit() {
+        let mut p = CardPile::new(10, 2, 0, 2);
+        p.cards = vec![13, 1, 27];
+        p.face_up_from = 1;
+        assert_eq!(p.card_pos(2), (10, 6));
+        // the top card covers the lower ones
+        assert_eq!(p.hit(11, 6), Some(2));
+        assert_eq!(p.hit(11, 3), Some(0));
+        assert_eq!(p.hit(9, 3), None);
+        assert!(!p.is_face_up(0));
+
+        assert_eq!(p.take_from(1), vec![1, 27]);
+        assert_eq!(p.face_up_from, 1);
+        p.flip_top();
+        assert!(p.is_face_up(0));
+        p.cards = vec![1, 13, 27, 2];
+        p.sort_by_rank();
+        assert_eq!(p.cards, vec![2, 13, 1, 27]);
+        p.take_from(0);
+        assert!(p.contains(10, 2));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{card_asset, CardPile, CARDH, CARDW};
+use rust_pixel::{
+    asset::AssetType, asset2sprite, context::Context, render::panel::Panel, render::sprite::Sprite,
+};
+
+/// a pool of card sprites named prefix0, prefix1... in a panel
+#[derive(Debug, Clone)]
+pub struct CardView {
+    pub prefix: String,
+    pub count: usize,
+}
+
+impl CardView {
+    /// adds count hidden card sprites to the panel
+    pub fn new(panel: &mut Panel, prefix: &str, count: usize) -> Self {
+        for i in 0..count {
+            let mut sp = Sprite::new(0, 0, CARDW as u16, CARDH as u16);
+            sp.set_hidden(true);
+            panel.add_sprite(sp, &format!("{}{}", prefix, i));
+        }
+        Self {
+            prefix: prefix.to_string(),
+            count,
+        }
+    }
+
+    pub fn name(&self, i: usize) -> String {
+        format!("{}{}", self.prefix, i)
+    }
+
+    pub fn sprite<'a>(&self, panel: &'a mut Panel, i: usize) -> &'a mut Sprite {
+        panel.get_sprite(&self.name(i))
+    }
+
+    /// shows sprite i as a card, its back if None
+    pub fn set_card(&self, panel: &mut Panel, ctx: &mut Context, i: usize, card: Option<u16>) {
+        let sp = self.sprite(panel, i);
+        asset2sprite!(sp, ctx, &card_asset(card));
+        sp.set_hidden(false);
+    }
+
+    /// draws the piles bottom to top with the sprites in order, the rest
+    /// are hidden, returns the sprites used
+    pub fn draw(&self, panel: &mut Panel, ctx: &mut Context, piles: &[&CardPile]) -> usize {
+        let mut n = 0;
+        for p in piles {
+            for (i, &c) in p.cards.iter().enumerate() {
+                if n == self.count {
+                    return n;
+                }
+                self.set_card(panel, ctx, n, p.is_face_up(i).then_some(c));
+                let (x, y) = p.card_pos(i);
+                self.sprite(panel, n).set_pos(x, y);
+                n += 1;
+            }
+        }
+        for i in n..self.count {
+            self.sprite(panel, i).set_hidden(true);
+        }
+        n
+    }
+}
@@ -1,72 +1,199 @@
+use cardlib::Deck;
 use log::info;
-use rust_pixel::event::Event;
-use rust_pixel::{context::Context, event::event_emit, game::Model, util::Rand};
+use rust_pixel::event::{Event, KeyCode, KeyEventKind};
+use rust_pixel::{
+    context::Context,
+    event::event_emit,
+    game::{Model, Seq, Sequencer},
+    util::Rand,
+};
 use texas_lib::*;
 
-pub const CARDW: usize = 7;
-#[cfg(any(feature = "sdl", target_arch = "wasm32"))]
-pub const CARDH: usize = 7;
-#[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
-pub const CARDH: usize = 5;
+pub use cardlib::{CARDH, CARDW};
 
-// enum PokerState {
-//     Normal,
-//     OverSelf,
-//     OverBorder,
-// }
+// the board row under the hands, then the status and help lines
+pub const BOARDY: u16 = 16;
+pub const STATUSY: u16 = BOARDY + CARDH as u16 + 1;
+pub const POKERH: u16 = STATUSY + 2;
+
+// seats, red is played by the ai
+pub const RED: usize = 0;
+pub const BLACK: usize = 1;
+
+const CHIPS: u32 = 1000;
+const BIG_BLIND: u32 = 20;
+
+// seconds the ai thinks, cards fly in and the pot goes to the winner
+const AI_SECS: f32 = 0.8;
+const DEAL_SECS: f32 = 0.4;
+const WIN_SECS: f32 = 1.2;
+
+fn ease_out(t: f32) -> f32 {
+    1.0 - (1.0 - t) * (1.0 - t)
+}
 
 pub struct PokerModel {
     pub rand: Rand,
-    pub texas_cards_red: TexasCards,
-    pub texas_cards_black: TexasCards,
-    pub pool: Vec<u16>,
+    pub game: TexasGame,
+    pub deck: Deck,
+    pub seq: Sequencer<PokerModel>,
+    /// last error or news, shown in the status line
+    pub msg: String,
+    /// hole cards are in place
+    pub hole_shown: bool,
+    /// board cards in place, the others fly in
+    pub board_shown: usize,
+    /// 0.0..1.0 of the cards flying in from the deck
+    pub deal: f32,
+    /// 0.0..1.0 of the pot going to the winner
+    pub win: f32,
 }
 
 impl PokerModel {
     pub fn new() -> Self {
         Self {
             rand: Rand::new(),
-            texas_cards_red: TexasCards::new(),
-            texas_cards_black: TexasCards::new(),
-            pool: vec![],
+            game: TexasGame::new(&["RED", "BLACK"], CHIPS, BIG_BLIND),
+            deck: Deck::new(false),
+            seq: Sequencer::new(),
+            msg: String::new(),
+            hole_shown: false,
+            board_shown: 0,
+            deal: 1.0,
+            win: 0.0,
         }
     }
 
     pub fn shuffle_tiles(&mut self) {
-        self.pool.clear();
-        for i in 1..=52u16 {
-            self.pool.push(i);
+        self.deck = Deck::new(false);
+        self.deck.shuffle(&mut self.rand);
+    }
+
+    /// deals the next hand, a new game when a player is broke
+    pub fn new_hand(&mut self) {
+        self.shuffle_tiles();
+        self.msg.clear();
+        if self.game.start_hand(&self.deck.cards).is_err() {
+            self.game = TexasGame::new(&["RED", "BLACK"], CHIPS, BIG_BLIND);
+            self.game.start_hand(&self.deck.cards).unwrap();
+            self.msg = "new game".to_string();
+        }
+        self.hole_shown = false;
+        self.board_shown = 0;
+        self.win = 0.0;
+        self.seq.clear();
+        self.after_act();
+    }
+
+    /// animates new cards and the showdown after every action
+    fn after_act(&mut self) {
+        if !self.hole_shown || self.game.board.len() > self.board_shown {
+            self.deal = 0.0;
+            self.seq.start(
+                "deal",
+                Seq::new()
+                    .during(DEAL_SECS, |m: &mut PokerModel, t| m.deal = ease_out(t))
+                    .then(|m| {
+                        m.hole_shown = true;
+                        m.board_shown = m.game.board.len();
+                    }),
+            );
+        }
+        if self.game.is_over() {
+            for (s, w) in &self.game.winners {
+                info!("{} wins {}", self.game.seats[*s].name, w);
+            }
+            self.seq.start(
+                "win",
+                Seq::new()
+                    .wait_until(|m: &PokerModel| m.deal >= 1.0)
+                    .during(WIN_SECS, |m: &mut PokerModel, t| m.win = t),
+            );
+        }
+        event_emit("Poker.RedrawTile");
+    }
+
+    pub fn act(&mut self, a: Action) {
+        match self.game.act(a) {
+            Ok(_) => self.after_act(),
+            Err(e) => {
+                self.msg = e;
+                event_emit("Poker.RedrawTile");
+            }
+        }
+    }
+
+    fn ai_act(&mut self) {
+        if !self.game.is_over() && self.game.turn == RED {
+            let a = self.game.ai_action(self.rand.rand());
+            self.act(a);
         }
-        self.rand.shuffle(&mut self.pool);
     }
 
-    // pub fn act(&mut self, _d: Dir, _context: &mut Context) {}
+    fn busy(&self) -> bool {
+        self.seq.is_running("deal") || self.seq.is_running("ai")
+    }
 }
 
 impl Model for PokerModel {
     fn init(&mut self, _context: &mut Context) {
         self.rand.srand_now();
-        self.shuffle_tiles();
-        self.texas_cards_red.assign(&self.pool[0..5]).unwrap();
-        self.texas_cards_black.assign(&self.pool[5..10]).unwrap();
-        info!("red:{}", self.texas_cards_red);
-        info!("black:{}", self.texas_cards_black);
-        event_emit("Poker.RedrawTile");
+        self.new_hand();
     }
 
     fn handle_input(&mut self, context: &mut Context, _dt: f32) {
         let es = context.input_events.clone();
         for e in &es {
-            match e {
-                Event::Key(_key) => {}
-                _ => {}
+            if let Event::Key(key) = e {
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+                let g = &self.game;
+                if g.is_over() {
+                    if matches!(
+                        key.code,
+                        KeyCode::Char(' ') | KeyCode::Char('n') | KeyCode::Enter
+                    ) {
+                        self.new_hand();
+                    }
+                    continue;
+                }
+                if g.turn != BLACK || self.busy() {
+                    continue;
+                }
+                let s = &g.seats[BLACK];
+                let check = if g.to_call(BLACK) == 0 {
+                    Action::Check
+                } else {
+                    Action::Call
+                };
+                let (raise, all_in) = (g.min_raise_to(BLACK), s.bet + s.chips);
+                match key.code {
+                    KeyCode::Char('f') => self.act(Action::Fold),
+                    KeyCode::Char('c') => self.act(check),
+                    KeyCode::Char('r') => self.act(Action::Raise(raise)),
+                    KeyCode::Char('a') => self.act(Action::Raise(all_in)),
+                    _ => {}
+                }
             }
         }
         context.input_events.clear();
     }
 
-    fn handle_auto(&mut self, _context: &mut Context, _dt: f32) {}
+    fn handle_auto(&mut self, _context: &mut Context, _dt: f32) {
+        if !self.game.is_over() && self.game.turn == RED && !self.busy() {
+            self.seq.start(
+                "ai",
+                Seq::new()
+                    .wait(AI_SECS)
+                    .then(|m: &mut PokerModel| m.ai_act()),
+            );
+        }
+    }
 
     fn handle_event(&mut self, _context: &mut Context, _dt: f32) {}
-    fn handle_timer(&mut self, _context: &mut Context, _dt: f32) {}
+
+    fn handle_timer(&mut self, context: &mut Context, dt: f32) {
+        Sequencer::update_in(self, context, dt, |m| &mut m.seq);
+    }
 }
@@ -1,8 +1,7 @@
-use crate::model::{PokerModel, CARDH, CARDW};
+use crate::model::{PokerModel, BLACK, BOARDY, CARDW, POKERH, RED, STATUSY};
+use cardlib::CardView;
 // use log::info;
 use rust_pixel::{
-    asset::AssetType,
-    asset2sprite,
     context::Context,
     event::{event_check, event_register},
     game::Render,
@@ -10,9 +9,44 @@ use rust_pixel::{
     render::sprite::Sprite,
     render::style::Color,
 };
+use texas_lib::Street;
+
+// deck the cards fly in from, right of the board
+const DECKX: u16 = 66;
+const BOARDX: u16 = 22;
+
+// sprites t0..t9: hole cards of red and black, the board, the deck
+fn card_sprite(seat: usize, i: usize) -> usize {
+    seat * 2 + i
+}
+
+fn board_sprite(i: usize) -> usize {
+    4 + i
+}
+
+const DECK_SPRITE: usize = 9;
+
+fn hole_pos(seat: usize, i: usize) -> (f32, f32) {
+    ((1 + i * CARDW + seat * 40) as f32, 7.0)
+}
+
+fn lerp(a: (f32, f32), b: (f32, f32), t: f32) -> (u16, u16) {
+    (
+        (a.0 + (b.0 - a.0) * t).round() as u16,
+        (a.1 + (b.1 - a.1) * t).round() as u16,
+    )
+}
+
+fn set_text(sp: &mut Sprite, lines: &[String], fg: Color) {
+    sp.content.reset();
+    for (y, l) in lines.iter().enumerate() {
+        sp.set_color_str(0, y as u16, l, fg, Color::Reset);
+    }
+}
 
 pub struct PokerRender {
     pub panel: Panel,
+    pub cards: CardView,
 }
 
 impl PokerRender {
@@ -21,56 +55,180 @@ impl PokerRender {
 
         let gb = Sprite::new(0, 0, 80, 20);
         t.add_sprite(gb, "back");
-        //red 5 cards, black 5 cards
-        for i in 0..10 {
-            t.add_sprite(
-                Sprite::new(0, 0, CARDW as u16, CARDH as u16),
-                &format!("t{}", i),
-            );
-        }
+        // hole cards of red and black, 5 board cards, the deck
+        let cards = CardView::new(&mut t, "t", 10);
 
         let adj = 1u16;
         let msgred = Sprite::new(0 + adj, 14, 40, 1);
         t.add_sprite(msgred, "msgred");
         let msgblack = Sprite::new(40 + adj, 14, 40, 1);
         t.add_sprite(msgblack, "msgblack");
+        t.add_sprite(Sprite::new(17, 7, 19, 5), "infored");
+        t.add_sprite(Sprite::new(57, 7, 19, 5), "infoblack");
+        t.add_sprite(Sprite::new(adj, STATUSY, 80, 1), "status");
+        t.add_sprite(Sprite::new(adj, STATUSY + 1, 80, 1), "help");
+        t.add_sprite(Sprite::new(0, 0, 10, 1), "chip");
 
         event_register("Poker.RedrawTile", "draw_tile");
 
-        Self { panel: t }
+        Self { panel: t, cards }
+    }
+
+    // red shows its cards only at a showdown without folds
+    fn reveal(d: &PokerModel) -> bool {
+        let g = &d.game;
+        g.is_over() && !g.seats[RED].folded && !g.seats[BLACK].folded
     }
 
     pub fn draw_tile(&mut self, ctx: &mut Context, d: &mut PokerModel) {
-        let ts = [&d.texas_cards_red, &d.texas_cards_black];
+        let g = &d.game;
+        let reveal = Self::reveal(d);
+        for seat in [RED, BLACK] {
+            for i in 0..2 {
+                let c = g.seats[seat].hole.get(i).cloned();
+                let c = if seat == RED && !reveal { None } else { c };
+                self.cards
+                    .set_card(&mut self.panel, ctx, card_sprite(seat, i), c);
+            }
+        }
+        for i in 0..5 {
+            let c = g.board.get(i).cloned();
+            self.cards
+                .set_card(&mut self.panel, ctx, board_sprite(i), c);
+            self.cards
+                .sprite(&mut self.panel, board_sprite(i))
+                .set_hidden(c.is_none());
+        }
+        self.cards.set_card(&mut self.panel, ctx, DECK_SPRITE, None);
+    }
+
+    // moves the cards along the deal and win tweens
+    fn place(&mut self, d: &PokerModel) {
+        let deck = (DECKX as f32, BOARDY as f32);
+        let g = &d.game;
+        for seat in [RED, BLACK] {
+            let won = g.winners.iter().any(|w| w.0 == seat);
+            for i in 0..2 {
+                let to = hole_pos(seat, i);
+                let t = if d.hole_shown { 1.0 } else { d.deal };
+                let (x, mut y) = lerp(deck, to, t);
+                let sp = self.cards.sprite(&mut self.panel, card_sprite(seat, i));
+                let mut alpha = 255;
+                if d.win > 0.0 {
+                    if won {
+                        // winners hop three times
+                        let hop = (d.win * std::f32::consts::PI * 3.0).sin().abs();
+                        y -= (hop * 2.0).round() as u16;
+                    } else {
+                        alpha = (255.0 - d.win * 155.0) as u8;
+                    }
+                }
+                sp.set_pos(x, y);
+                sp.set_alpha(alpha);
+            }
+        }
+        for i in 0..5 {
+            let to = ((BOARDX as usize + i * CARDW) as f32, BOARDY as f32);
+            let t = if i < d.board_shown { 1.0 } else { d.deal };
+            let (x, y) = lerp(deck, to, t);
+            self.cards
+                .sprite(&mut self.panel, board_sprite(i))
+                .set_pos(x, y);
+        }
+        self.cards
+            .sprite(&mut self.panel, DECK_SPRITE)
+            .set_pos(DECKX, BOARDY);
+
+        // the pot slides from the status line to the winner
+        let chip = self.panel.get_sprite("chip");
+        match g.winners.first() {
+            Some(&(w, n)) if d.win > 0.0 && d.win < 1.0 => {
+                let to = if w == RED { (17.0, 9.0) } else { (57.0, 9.0) };
+                let (x, y) = lerp((36.0, STATUSY as f32), to, d.win);
+                chip.set_pos(x, y);
+                set_text(chip, &[format!("${}", n)], Color::Indexed(226));
+                chip.set_hidden(false);
+            }
+            _ => chip.set_hidden(true),
+        }
+    }
+
+    fn update_text(&mut self, d: &PokerModel) {
+        let g = &d.game;
+        let reveal = Self::reveal(d);
         let msg = ["msgred", "msgblack"];
-        for n in 0..2usize {
-            for i in 0..5 {
-                let l = self.panel.get_sprite(&format!("t{}", i + n * 5));
-                let bi = ts[n].best[i].to_u8() as usize;
-
-                #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
-                let ext = "pix";
-                #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
-                let ext = "txt";
-                let cn = if bi == 0 {
-                    format!("poker/back.{}", ext)
+        let info = ["infored", "infoblack"];
+        for seat in [RED, BLACK] {
+            let s = &g.seats[seat];
+            let mut lines = vec![
+                format!("{}{}", s.name, if g.dealer == seat { " (D)" } else { "" }),
+                format!("chips {}", s.chips),
+                format!("bet   {}", s.bet),
+            ];
+            if let Some(a) = s.last {
+                lines.push(if s.all_in {
+                    "all-in".to_string()
                 } else {
-                    format!("poker/{}.{}", bi, ext)
-                };
-                asset2sprite!(l, ctx, &cn);
-
-                let x = (i * CARDW) as u16 + 1u16 + n as u16 * 40u16;
-                l.set_pos(x, 7);
+                    a.to_string()
+                });
             }
-            let m = self.panel.get_sprite(msg[n]);
-            m.set_color_str(
-                0,
-                0,
-                format!("{:?}", ts[n].texas),
-                Color::Indexed(222),
-                Color::Reset,
+            if !g.is_over() && g.turn == seat {
+                lines.push("...".to_string());
+            }
+            set_text(
+                self.panel.get_sprite(info[seat]),
+                &lines,
+                Color::Indexed(250),
             );
+
+            // hand type, and the result with a blink while the pot moves
+            let mut m = String::new();
+            if seat == BLACK || reveal {
+                if let Some(tc) = g.hand_of(seat) {
+                    m = format!("{:?}", tc.texas);
+                }
+            }
+            if g.is_over() && d.win > 0.0 {
+                let blink = d.win >= 1.0 || (d.win * 8.0) as u32 & 1 == 0;
+                match g.winners.iter().find(|w| w.0 == seat) {
+                    Some((_, n)) if blink => m = format!("{} WIN +{}", m, n),
+                    None if blink && s.total > 0 => m = format!("{} LOSE -{}", m, s.total),
+                    _ => {}
+                }
+            }
+            set_text(self.panel.get_sprite(msg[seat]), &[m], Color::Indexed(222));
         }
+
+        let street = match g.street {
+            Street::PreFlop => "preflop",
+            Street::Flop => "flop",
+            Street::Turn => "turn",
+            Street::River => "river",
+            Street::Showdown => "showdown",
+        };
+        let turn = if !g.is_over() && g.turn == BLACK {
+            match g.to_call(BLACK) {
+                0 => "your turn".to_string(),
+                n => format!("your turn, {} to call", n),
+            }
+        } else {
+            String::new()
+        };
+        let status = format!("{:<9} pot {:<6} {} {}", street, g.pot(), turn, d.msg);
+        set_text(
+            self.panel.get_sprite("status"),
+            &[status],
+            Color::Indexed(222),
+        );
+        let help = if g.is_over() {
+            "SPACE next hand".to_string()
+        } else {
+            format!(
+                "F fold  C check/call  R raise to {}  A all-in",
+                g.min_raise_to(BLACK)
+            )
+        };
+        set_text(self.panel.get_sprite("help"), &[help], Color::Indexed(245));
     }
 }
 
@@ -80,10 +238,11 @@ impl Render for PokerRender {
     fn init(&mut self, context: &mut Context, _data: &mut Self::Model) {
         context
             .adapter
-            .init(82, 20, 1.0, 1.0, "redblack".to_string());
+            .init(82, POKERH, 1.0, 1.0, "redblack".to_string());
         self.panel.init(context);
         #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
         {
+            use rust_pixel::{asset::AssetType, asset2sprite};
             let gb = self.panel.get_sprite("back");
             asset2sprite!(gb, context, "back.txt");
         }
@@ -97,7 +256,9 @@ impl Render for PokerRender {
 
     fn handle_timer(&mut self, _context: &mut Context, _model: &mut Self::Model, _dt: f32) {}
 
-    fn draw(&mut self, ctx: &mut Context, _data: &mut Self::Model, _dt: f32) {
+    fn draw(&mut self, ctx: &mut Context, data: &mut Self::Model, _dt: f32) {
+        self.place(data);
+        self.update_text(data);
         self.panel.draw(ctx).unwrap();
     }
 }
@@ -0,0 +1,505 @@
+//! TexasGame plays hands of no limit Texas Hold'em
+//!
+//! Blinds, four betting rounds, side pots for all-in players and the
+//! showdown with TexasCards. Cards are 1..=52 as in PokerCard::from_u8,
+//! the caller shuffles the deck, so the game itself has no randomness.
+//! ai_action picks a move for the seat to act by a rough hand strength.
+//!
+//! # Example
+//! ```ignore
+//! let mut g = TexasGame::new(&["red", "black"], 1000, 20);
+//! g.start_hand(&deck)?;
+//! while !g.is_over() {
+//!     let a = g.ai_action(rand.rand());
+//!     g.act(a)?;
+//! }
+//! for (seat, won) in &g.winners { ... }
+//! ```
+
+use crate::{TexasCards, TexasType};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Street {
+    PreFlop,
+    Flop,
+    Turn,
+    River,
+    Showdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Fold,
+    Check,
+    Call,
+    /// raises the bet of this round to the amount, all chips is all-in
+    Raise(u32),
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Action::Fold => write!(f, "fold"),
+            Action::Check => write!(f, "check"),
+            Action::Call => write!(f, "call"),
+            Action::Raise(n) => write!(f, "raise to {}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Seat {
+    pub name: String,
+    pub chips: u32,
+    pub hole: Vec<u16>,
+    /// bet of this round
+    pub bet: u32,
+    /// bet of this hand, for side pots
+    pub total: u32,
+    pub folded: bool,
+    pub all_in: bool,
+    pub last: Option<Action>,
+    acted: bool,
+}
+
+impl Seat {
+    fn can_act(&self) -> bool {
+        !self.folded && !self.all_in
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TexasGame {
+    pub seats: Vec<Seat>,
+    pub board: Vec<u16>,
+    pub street: Street,
+    pub dealer: usize,
+    /// seat to act
+    pub turn: usize,
+    /// highest bet of this round
+    pub cur_bet: u32,
+    /// smallest raise allowed, the last full raise
+    pub min_raise: u32,
+    pub big_blind: u32,
+    /// chips won by seat at the showdown
+    pub winners: Vec<(usize, u32)>,
+    deck: Vec<u16>,
+}
+
+impl TexasGame {
+    pub fn new(names: &[&str], chips: u32, big_blind: u32) -> Self {
+        Self {
+            seats: names
+                .iter()
+                .map(|n| Seat {
+                    name: n.to_string(),
+                    chips,
+                    ..Default::default()
+                })
+                .collect(),
+            board: vec![],
+            street: Street::Showdown,
+            // first hand deals from seat 0
+            dealer: names.len().saturating_sub(1),
+            turn: 0,
+            cur_bet: 0,
+            min_raise: big_blind,
+            big_blind,
+            winners: vec![],
+            deck: vec![],
+        }
+    }
+
+    // next seat after i matching f, i itself if none does
+    fn next_seat(&self, i: usize, f: impl Fn(&Seat) -> bool) -> usize {
+        let n = self.seats.len();
+        (1..=n)
+            .map(|d| (i + d) % n)
+            .find(|&s| f(&self.seats[s]))
+            .unwrap_or(i)
+    }
+
+    /// moves the dealer button, posts the blinds and deals the hole cards
+    pub fn start_hand(&mut self, deck: &[u16]) -> Result<(), String> {
+        let live = self.seats.iter().filter(|s| s.chips > 0).count();
+        if live < 2 {
+            return Err("less than two players with chips".to_string());
+        }
+        if deck.len() < live * 2 + 5 {
+            return Err(format!("deck of {} cards too small", deck.len()));
+        }
+        self.deck = deck.iter().rev().cloned().collect();
+        self.board.clear();
+        self.winners.clear();
+        for s in self.seats.iter_mut() {
+            let chips = s.chips;
+            *s = Seat {
+                name: std::mem::take(&mut s.name),
+                chips,
+                folded: chips == 0,
+                ..Default::default()
+            };
+        }
+        self.dealer = self.next_seat(self.dealer, |s| s.chips > 0);
+        // heads-up the dealer posts the small blind
+        let sb = if live == 2 {
+            self.dealer
+        } else {
+            self.next_seat(self.dealer, |s| !s.folded)
+        };
+        let bb = self.next_seat(sb, |s| !s.folded);
+        for _ in 0..2 {
+            for i in 0..self.seats.len() {
+                if !self.seats[i].folded {
+                    let c = self.deck.pop().unwrap();
+                    self.seats[i].hole.push(c);
+                }
+            }
+        }
+        self.post(sb, self.big_blind / 2);
+        self.post(bb, self.big_blind);
+        self.cur_bet = self.big_blind;
+        self.min_raise = self.big_blind;
+        self.street = Street::PreFlop;
+        self.turn = bb;
+        self.advance();
+        Ok(())
+    }
+
+    // moves up to n chips of seat i into its bet
+    fn post(&mut self, i: usize, n: u32) {
+        let s = &mut self.seats[i];
+        let n = n.min(s.chips);
+        s.chips -= n;
+        s.bet += n;
+        s.total += n;
+        if s.chips == 0 {
+            s.all_in = true;
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.street == Street::Showdown
+    }
+
+    /// all chips bet in this hand
+    pub fn pot(&self) -> u32 {
+        self.seats.iter().map(|s| s.total).sum()
+    }
+
+    pub fn to_call(&self, i: usize) -> u32 {
+        let s = &self.seats[i];
+        (self.cur_bet - s.bet).min(s.chips)
+    }
+
+    /// smallest raise of seat i, all-in if short
+    pub fn min_raise_to(&self, i: usize) -> u32 {
+        let s = &self.seats[i];
+        (self.cur_bet + self.min_raise).min(s.bet + s.chips)
+    }
+
+    /// the seat to act takes action a
+    pub fn act(&mut self, a: Action) -> Result<(), String> {
+        if self.is_over() {
+            return Err("hand is over".to_string());
+        }
+        let i = self.turn;
+        let to_call = self.to_call(i);
+        match a {
+            Action::Fold => self.seats[i].folded = true,
+            Action::Check => {
+                if to_call > 0 {
+                    return Err(format!("can not check, {} to call", to_call));
+                }
+            }
+            Action::Call => self.post(i, to_call),
+            Action::Raise(to) => {
+                let s = &self.seats[i];
+                let max = s.bet + s.chips;
+                if to <= self.cur_bet {
+                    return Err(format!("raise to {} not above {}", to, self.cur_bet));
+                }
+                if to > max {
+                    return Err(format!("raise to {} over {} chips", to, max));
+                }
+                if to < self.cur_bet + self.min_raise && to < max {
+                    return Err(format!(
+                        "raise to at least {}",
+                        self.cur_bet + self.min_raise
+                    ));
+                }
+                // a full raise opens the betting again
+                if to - self.cur_bet >= self.min_raise {
+                    self.min_raise = to - self.cur_bet;
+                    for s in self.seats.iter_mut() {
+                        s.acted = false;
+                    }
+                }
+                self.cur_bet = to;
+                let n = to - self.seats[i].bet;
+                self.post(i, n);
+            }
+        }
+        self.seats[i].acted = true;
+        self.seats[i].last = Some(a);
+        self.advance();
+        Ok(())
+    }
+
+    // next seat to act, the next street or the showdown
+    fn advance(&mut self) {
+        loop {
+            if self.seats.iter().filter(|s| !s.folded).count() == 1 {
+                self.showdown();
+                return;
+            }
+            // a lone player left to act only has to match the bet
+            let cur_bet = self.cur_bet;
+            let many = self.seats.iter().filter(|s| s.can_act()).count() > 1;
+            let open = move |s: &Seat| s.can_act() && (s.bet < cur_bet || (!s.acted && many));
+            if self.seats.iter().any(open) {
+                self.turn = self.next_seat(self.turn, open);
+                return;
+            }
+            for s in self.seats.iter_mut() {
+                s.bet = 0;
+                s.acted = false;
+            }
+            self.cur_bet = 0;
+            self.min_raise = self.big_blind;
+            let n = match self.street {
+                Street::PreFlop => 3,
+                Street::Flop | Street::Turn => 1,
+                _ => {
+                    self.showdown();
+                    return;
+                }
+            };
+            for _ in 0..n {
+                let c = self.deck.pop().unwrap();
+                self.board.push(c);
+            }
+            self.street = match self.street {
+                Street::PreFlop => Street::Flop,
+                Street::Flop => Street::Turn,
+                _ => Street::River,
+            };
+            self.turn = self.dealer;
+        }
+    }
+
+    /// best five of seat i with the board, None before the flop
+    pub fn hand_of(&self, i: usize) -> Option<TexasCards> {
+        let mut cs = self.seats[i].hole.clone();
+        cs.extend_from_slice(&self.board);
+        let mut tc = TexasCards::new();
+        tc.assign(&cs).ok()?;
+        Some(tc)
+    }
+
+    // compares by type and ranks only, suits never break a tie
+    fn rank_of(&self, i: usize) -> u64 {
+        match self.hand_of(i) {
+            Some(tc) => {
+                let mut r = (tc.texas as u64) << 30;
+                for (b, c) in tc.best.iter().enumerate() {
+                    r += (c.get_number() as u64) << ((4 - b) * 6);
+                }
+                r
+            }
+            None => 0,
+        }
+    }
+
+    // splits the pot into side pots by the totals of players in the hand
+    fn showdown(&mut self) {
+        self.street = Street::Showdown;
+        let ranks: Vec<Option<u64>> = (0..self.seats.len())
+            .map(|i| (!self.seats[i].folded).then(|| self.rank_of(i)))
+            .collect();
+        let mut levels: Vec<u32> = self
+            .seats
+            .iter()
+            .filter(|s| !s.folded)
+            .map(|s| s.total)
+            .collect();
+        levels.sort_unstable();
+        levels.dedup();
+        let mut won = vec![0u32; self.seats.len()];
+        let mut prev = 0;
+        for (li, &lvl) in levels.iter().enumerate() {
+            // the last pot also takes what folded players bet above it
+            let top = if li + 1 == levels.len() {
+                u32::MAX
+            } else {
+                lvl
+            };
+            let part: u32 = self
+                .seats
+                .iter()
+                .map(|s| s.total.min(top) - s.total.min(prev))
+                .sum();
+            let best = (0..self.seats.len())
+                .filter(|&i| self.seats[i].total >= lvl)
+                .filter_map(|i| ranks[i])
+                .max()
+                .unwrap_or(0);
+            let ws: Vec<usize> = (0..self.seats.len())
+                .map(|d| (self.dealer + 1 + d) % self.seats.len())
+                .filter(|&i| self.seats[i].total >= lvl && ranks[i] == Some(best))
+                .collect();
+            for (k, &w) in ws.iter().enumerate() {
+                // odd chips go to the first winner after the dealer
+                let odd = if k == 0 { part % ws.len() as u32 } else { 0 };
+                won[w] += part / ws.len() as u32 + odd;
+            }
+            prev = lvl;
+        }
+        for (i, s) in self.seats.iter_mut().enumerate() {
+            s.chips += won[i];
+            s.bet = 0;
+        }
+        self.winners = won
+            .iter()
+            .enumerate()
+            .filter(|(_, &w)| w > 0)
+            .map(|(i, &w)| (i, w))
+            .collect();
+    }
+
+    /// 0.0..1.0, how good the cards of seat i look
+    pub fn strength(&self, i: usize) -> f32 {
+        let hole = &self.seats[i].hole;
+        match self.hand_of(i) {
+            None => {
+                let r: Vec<f32> = hole.iter().map(|&c| rank_1_52(c) as f32).collect();
+                if r.len() < 2 {
+                    return 0.0;
+                }
+                let mut s = (r[0] + r[1]) / 28.0 * 0.6;
+                if r[0] == r[1] {
+                    s += 0.3;
+                }
+                if (hole[0] - 1) / 13 == (hole[1] - 1) / 13 {
+                    s += 0.05;
+                }
+                s.min(1.0)
+            }
+            Some(tc) => match tc.texas {
+                TexasType::NoCalc | TexasType::HighCard => 0.15,
+                TexasType::OnePair => 0.45,
+                TexasType::TwoPair => 0.6,
+                TexasType::Three => 0.7,
+                TexasType::Straight => 0.8,
+                TexasType::Flush => 0.85,
+                TexasType::FullHouse => 0.92,
+                _ => 0.98,
+            },
+        }
+    }
+
+    /// a move for the seat to act, r is a random number for bluffs
+    pub fn ai_action(&self, r: u32) -> Action {
+        let i = self.turn;
+        let s = &self.seats[i];
+        let st = self.strength(i) + ((r % 100) as f32 / 100.0 - 0.5) * 0.2;
+        // raises two times in three when strong, bluffs one in four
+        let roll = (r >> 8) % 12;
+        let to_call = self.to_call(i);
+        let pot = self.pot();
+        let odds = to_call as f32 / (pot + to_call).max(1) as f32;
+        let max = s.bet + s.chips;
+        let raise = (self.cur_bet + self.min_raise.max(pot / 2)).min(max);
+        if st > 0.75 && roll >= 4 && raise > self.cur_bet {
+            Action::Raise(raise)
+        } else if to_call == 0 {
+            if st > 0.55 && roll < 3 && raise > self.cur_bet {
+                Action::Raise(self.min_raise_to(i))
+            } else {
+                Action::Check
+            }
+        } else if st >= odds + 0.15 {
+            Action::Call
+        } else {
+            Action::Fold
+        }
+    }
+}
+
+// 2..=14 of a card 1..=52, aces high
+fn rank_1_52(c: u16) -> u16 {
+    match (c - 1) % 13 + 1 {
+        1 => 14,
+        n => n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // deals hole cards in turns from seat 0, then the board
+    fn deck(hole: &[u16], board: &[u16]) -> Vec<u16> {
+        let mut d = hole.to_vec();
+        d.extend_from_slice(board);
+        d.extend((1..=52).filter(|c| !d.contains(c)).collect::<Vec<u16>>());
+        d
+    }
+
+    #[test]
+    fn texas_hand_flow() {
+        // seat 0 aces, seat 1 kings, no help on the board
+        let d = deck(&[1, 13, 14, 26], &[2 + 26, 5 + 39, 9, 7 + 13, 11 + 26]);
+        let mut g = TexasGame::new(&["red", "black"], 1000, 20);
+        g.start_hand(&d).unwrap();
+        assert_eq!((g.dealer, g.turn, g.pot()), (0, 0, 30));
+        assert!(g.act(Action::Check).is_err());
+        assert!(g.act(Action::Raise(30)).is_err());
+        g.act(Action::Raise(60)).unwrap();
+        g.act(Action::Call).unwrap();
+        assert_eq!((g.street, g.board.len(), g.turn), (Street::Flop, 3, 1));
+        g.act(Action::Check).unwrap();
+        g.act(Action::Check).unwrap();
+        g.act(Action::Check).unwrap();
+        g.act(Action::Check).unwrap();
+        assert_eq!(g.street, Street::River);
+        g.act(Action::Raise(940)).unwrap();
+        assert!(g.seats[1].all_in);
+        g.act(Action::Call).unwrap();
+        assert!(g.is_over());
+        assert_eq!(g.winners, vec![(0, 2000)]);
+        assert_eq!((g.seats[0].chips, g.seats[1].chips), (2000, 0));
+        assert!(g.start_hand(&d).is_err());
+
+        // c all-in wins the main pot, the side pot goes back to a
+        let mut g = TexasGame::new(&["a", "b", "c"], 100, 10);
+        g.seats[2].chips = 40;
+        let d = deck(
+            &[13, 27, 1, 26, 40, 14],
+            &[2 + 26, 5 + 39, 9, 7 + 13, 11 + 26],
+        );
+        g.start_hand(&d).unwrap();
+        assert_eq!(g.turn, 0);
+        g.act(Action::Raise(100)).unwrap();
+        g.act(Action::Fold).unwrap();
+        g.act(Action::Call).unwrap();
+        assert!(g.is_over());
+        assert_eq!(g.board.len(), 5);
+        let chips: Vec<u32> = g.seats.iter().map(|s| s.chips).collect();
+        assert_eq!(chips, vec![60, 95, 85]);
+
+        // the ai only takes legal actions
+        let mut r = 7u32;
+        for _ in 0..20 {
+            if g.start_hand(&d).is_err() {
+                break;
+            }
+            while !g.is_over() {
+                r = r.wrapping_mul(1103515245).wrapping_add(12345) >> 1;
+                g.act(g.ai_action(r)).unwrap();
+            }
+            assert_eq!(g.seats.iter().map(|s| s.chips).sum::<u32>(), 240);
+        }
+    }
+}
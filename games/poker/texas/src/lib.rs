@@ -7,6 +7,9 @@ use std::collections::HashSet;
 use std::fmt::{self, Display, Formatter};
 use TexasType::*;
 
+mod game;
+pub use game::*;
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TexasType {
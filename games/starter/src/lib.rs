@@ -0,0 +1,25 @@
+//! Starter is a small but complete snake game, and what
+//! `cargo pixel new mygame --full` copies to start a new game from.
+//!
+//! It shows what most games need beyond drawing:
+//! - a fixed timestep, the model runs at 60 updates per second on every
+//!   adapter, see TimestepMode::Fixed in StarterModel::init
+//! - an InputMap of actions bound to keys and gamepad buttons, loaded
+//!   from the "keys" save slot when present so players can rebind them
+//! - sound effects generated with Sound, no audio asset to ship
+//! - high scores kept in the "scores" save slot with context.save_slot
+//! - title, playing, paused and game over states
+//!
+//! The window size, title and frame rate are set by the pixel_game! config
+//! block below. Game logic stays in model.rs, render.rs only reads the model.
+
+mod model;
+mod render;
+
+use pixel_macro::pixel_game;
+
+pixel_game!(Starter, {
+    size: (model::STARTERW + 2, model::STARTERH + 4),
+    title: "starter",
+    fps: 60,
+});
@@ -0,0 +1,333 @@
+use log::info;
+use rust_pixel::{
+    audio::{Sound, Wave},
+    context::Context,
+    event::{GamepadButton, InputBinding, InputMap, KeyCode},
+    game::{Model, TimestepMode},
+    util::Rand,
+};
+use serde::{Deserialize, Serialize};
+
+pub const STARTERW: u16 = 40;
+pub const STARTERH: u16 = 20;
+
+/// seconds of a fixed model update
+pub const STEP: f32 = 1.0 / 60.0;
+const SCORES_SLOT: &str = "scores";
+const KEYS_SLOT: &str = "keys";
+const TOP_N: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StarterState {
+    Title,
+    Playing,
+    Paused,
+    Over,
+}
+
+/// what a move of the snake did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Moved,
+    Ate,
+    Died,
+}
+
+/// best scores, saved after every game that makes the list
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scores {
+    pub best: Vec<u32>,
+}
+
+impl Scores {
+    /// adds a score, returns its rank if it made the list
+    pub fn add(&mut self, score: u32) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        let rank = self
+            .best
+            .iter()
+            .position(|&b| score > b)
+            .unwrap_or(self.best.len());
+        if rank >= TOP_N {
+            return None;
+        }
+        self.best.insert(rank, score);
+        self.best.truncate(TOP_N);
+        Some(rank)
+    }
+
+    pub fn top(&self) -> u32 {
+        self.best.first().cloned().unwrap_or(0)
+    }
+}
+
+/// default bindings, arrows or wasd and the gamepad dpad to steer
+pub fn default_input() -> InputMap {
+    let mut im = InputMap::new();
+    let keys = [
+        ("left", KeyCode::Left, 'a', GamepadButton::DPadLeft),
+        ("right", KeyCode::Right, 'd', GamepadButton::DPadRight),
+        ("up", KeyCode::Up, 'w', GamepadButton::DPadUp),
+        ("down", KeyCode::Down, 's', GamepadButton::DPadDown),
+    ];
+    for (action, k, c, b) in keys {
+        im.bind(action, InputBinding::Key(k));
+        im.bind(action, InputBinding::Key(KeyCode::Char(c)));
+        im.bind(action, InputBinding::Gamepad(b));
+    }
+    im.bind("start", InputBinding::Key(KeyCode::Enter));
+    im.bind("start", InputBinding::Key(KeyCode::Char(' ')));
+    im.bind("start", InputBinding::Gamepad(GamepadButton::A));
+    im.bind("pause", InputBinding::Key(KeyCode::Char('p')));
+    im.bind("pause", InputBinding::Key(KeyCode::Esc));
+    im.bind("pause", InputBinding::Gamepad(GamepadButton::Start));
+    im.bind("mute", InputBinding::Key(KeyCode::Char('m')));
+    im
+}
+
+fn sfx_start() -> Sound {
+    Sound::new(Wave::Triangle)
+        .note("C5")
+        .slide(600.0)
+        .duration(0.1)
+}
+
+fn sfx_eat() -> Sound {
+    Sound::new(Wave::Square(0.5))
+        .note("E5")
+        .arp(&[0, 7, 12], 0.04)
+        .duration(0.12)
+}
+
+fn sfx_die() -> Sound {
+    Sound::new(Wave::Noise)
+        .freq(800.0)
+        .slide(-600.0)
+        .duration(0.4)
+}
+
+pub struct StarterModel {
+    pub state: StarterState,
+    pub input: InputMap,
+    /// head first
+    pub body: Vec<(u16, u16)>,
+    dir: (i16, i16),
+    // turns pressed between two moves, so quick taps are not lost
+    turns: Vec<(i16, i16)>,
+    pub food: (u16, u16),
+    pub score: u32,
+    pub scores: Scores,
+    /// rank of the last game in scores
+    pub rank: Option<usize>,
+    // fixed updates since the last move
+    ticks: u32,
+    rand: Rand,
+}
+
+impl StarterModel {
+    pub fn new() -> Self {
+        Self {
+            state: StarterState::Title,
+            input: default_input(),
+            body: vec![],
+            dir: (1, 0),
+            turns: vec![],
+            food: (0, 0),
+            score: 0,
+            scores: Scores::default(),
+            rank: None,
+            ticks: 0,
+            rand: Rand::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        let (x, y) = (STARTERW / 2, STARTERH / 2);
+        self.body = vec![(x, y), (x - 1, y), (x - 2, y)];
+        self.dir = (1, 0);
+        self.turns.clear();
+        self.score = 0;
+        self.rank = None;
+        self.ticks = 0;
+        self.place_food();
+    }
+
+    fn place_food(&mut self) {
+        let free: Vec<(u16, u16)> = (0..STARTERH)
+            .flat_map(|y| (0..STARTERW).map(move |x| (x, y)))
+            .filter(|c| !self.body.contains(c))
+            .collect();
+        if !free.is_empty() {
+            self.food = free[self.rand.range(0, free.len() as i32) as usize];
+        }
+    }
+
+    /// fixed updates per move, the snake speeds up as the score grows
+    pub fn move_every(&self) -> u32 {
+        8u32.saturating_sub(self.score / 50).max(3)
+    }
+
+    /// queues a turn, turning back or the same way is ignored
+    pub fn turn(&mut self, d: (i16, i16)) {
+        let last = self.turns.last().cloned().unwrap_or(self.dir);
+        if d != last && d != (-last.0, -last.1) && self.turns.len() < 2 {
+            self.turns.push(d);
+        }
+    }
+
+    /// moves the snake one cell
+    pub fn step(&mut self) -> Move {
+        if !self.turns.is_empty() {
+            self.dir = self.turns.remove(0);
+        }
+        let (hx, hy) = self.body[0];
+        let (x, y) = (hx as i16 + self.dir.0, hy as i16 + self.dir.1);
+        if x < 0 || y < 0 || x >= STARTERW as i16 || y >= STARTERH as i16 {
+            return Move::Died;
+        }
+        let head = (x as u16, y as u16);
+        let ate = head == self.food;
+        // the tail moves away unless the snake grows
+        let n = self.body.len() - usize::from(!ate);
+        if self.body[..n].contains(&head) {
+            return Move::Died;
+        }
+        self.body.insert(0, head);
+        if ate {
+            self.score += 10;
+            self.place_food();
+            Move::Ate
+        } else {
+            self.body.pop();
+            Move::Moved
+        }
+    }
+
+    fn game_over(&mut self, ctx: &mut Context) {
+        self.state = StarterState::Over;
+        self.rank = self.scores.add(self.score);
+        if self.rank.is_some() {
+            if let Err(e) = ctx.save_slot(SCORES_SLOT, &self.scores) {
+                info!("save scores failed:{}", e);
+            }
+        }
+    }
+}
+
+impl Model for StarterModel {
+    fn init(&mut self, ctx: &mut Context) {
+        // the snake runs at the same speed on every adapter
+        ctx.game_loop.mode = TimestepMode::Fixed {
+            step: STEP,
+            max_steps: 5,
+        };
+        self.rand.srand_now();
+        self.scores = ctx.load_slot(SCORES_SLOT).unwrap_or_default();
+        if let Ok(im) = ctx.load_slot::<InputMap>(KEYS_SLOT) {
+            self.input = im;
+        }
+        self.reset();
+    }
+
+    fn handle_input(&mut self, ctx: &mut Context, _dt: f32) {
+        self.input.update(&ctx.input_events);
+        ctx.input_events.clear();
+        let im = &self.input;
+        if im.just_pressed("mute") {
+            let m = ctx.audio.is_muted();
+            ctx.audio.set_muted(!m);
+        }
+        match self.state {
+            StarterState::Title | StarterState::Over => {
+                if im.just_pressed("start") {
+                    self.reset();
+                    self.state = StarterState::Playing;
+                    ctx.audio.play_sound(&sfx_start());
+                }
+            }
+            StarterState::Playing => {
+                let dirs = [
+                    ("left", (-1, 0)),
+                    ("right", (1, 0)),
+                    ("up", (0, -1)),
+                    ("down", (0, 1)),
+                ];
+                let pause = im.just_pressed("pause");
+                let turns: Vec<(i16, i16)> = dirs
+                    .iter()
+                    .filter(|(a, _)| im.just_pressed(a))
+                    .map(|(_, d)| *d)
+                    .collect();
+                for d in turns {
+                    self.turn(d);
+                }
+                if pause {
+                    self.state = StarterState::Paused;
+                }
+            }
+            StarterState::Paused => {
+                if im.just_pressed("pause") || im.just_pressed("start") {
+                    self.state = StarterState::Playing;
+                }
+            }
+        }
+    }
+
+    fn handle_auto(&mut self, ctx: &mut Context, _dt: f32) {
+        if self.state != StarterState::Playing {
+            return;
+        }
+        self.ticks += 1;
+        if self.ticks < self.move_every() {
+            return;
+        }
+        self.ticks = 0;
+        match self.step() {
+            Move::Ate => ctx.audio.play_sound(&sfx_eat()),
+            Move::Died => {
+                ctx.audio.play_sound(&sfx_die());
+                self.game_over(ctx);
+            }
+            Move::Moved => {}
+        }
+    }
+
+    fn handle_event(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _dt: f32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snake_moves_eats_and_dies() {
+        let mut m = StarterModel::new();
+        m.reset();
+        let (x, y) = m.body[0];
+        m.food = (x + 1, y);
+        assert_eq!(m.step(), Move::Ate);
+        assert_eq!((m.body.len(), m.score), (4, 10));
+        assert_ne!(m.food, (x + 1, y));
+
+        // turning back is ignored
+        m.turn((-1, 0));
+        m.food = (0, 0);
+        assert_eq!(m.step(), Move::Moved);
+        assert_eq!(m.body[0], (x + 2, y));
+        m.turn((0, -1));
+        while m.step() == Move::Moved {}
+        assert_eq!(m.body[0], (x + 2, 0));
+
+        let mut s = Scores::default();
+        assert_eq!(s.add(0), None);
+        for v in [30, 10, 50, 20, 40] {
+            s.add(v);
+        }
+        assert_eq!(s.add(5), None);
+        assert_eq!(s.add(35), Some(2));
+        assert_eq!(s.best, vec![50, 40, 35, 30, 20]);
+    }
+}
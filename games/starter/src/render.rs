@@ -0,0 +1,121 @@
+use crate::model::{StarterModel, StarterState, STARTERH, STARTERW};
+use rust_pixel::{
+    context::Context,
+    game::Render,
+    render::panel::Panel,
+    render::sprite::{BorderType, Borders, Sprite},
+    render::style::{Color, Style},
+};
+
+pub struct StarterRender {
+    pub panel: Panel,
+}
+
+// writes s centered in row y of sp
+fn center(sp: &mut Sprite, y: u16, s: &str, fg: Color) {
+    let x = sp.content.area.width.saturating_sub(s.len() as u16) / 2;
+    sp.set_color_str(x, y, s, fg, Color::Reset);
+}
+
+impl StarterRender {
+    pub fn new() -> Self {
+        let mut t = Panel::new();
+
+        let mut border = Sprite::new(0, 0, STARTERW + 2, STARTERH + 2);
+        border.set_border(
+            Borders::ALL,
+            BorderType::Rounded,
+            Style::default().fg(Color::Indexed(244)),
+        );
+        center(&mut border, 0, " STARTER ", Color::Indexed(222));
+        t.add_sprite(border, "border");
+        t.add_sprite(Sprite::new(1, 1, STARTERW, STARTERH), "board");
+        t.add_sprite(Sprite::new(1, STARTERH + 2, STARTERW, 1), "score");
+        t.add_sprite(Sprite::new(1, STARTERH + 3, STARTERW, 1), "help");
+
+        Self { panel: t }
+    }
+
+    fn draw_board(&mut self, m: &StarterModel) {
+        let b = self.panel.get_sprite("board");
+        b.content.reset();
+        if m.state == StarterState::Title {
+            center(b, STARTERH / 2 - 1, "STARTER", Color::Indexed(222));
+            center(b, STARTERH / 2 + 1, "press ENTER", Color::Indexed(250));
+            return;
+        }
+        let (fx, fy) = m.food;
+        #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
+        b.set_color_str(fx, fy, "●", Color::LightRed, Color::Reset);
+        #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+        b.set_graph_sym(fx, fy, 1, 83, Color::LightRed);
+        for (i, &(x, y)) in m.body.iter().enumerate() {
+            let c = if i == 0 {
+                Color::LightGreen
+            } else {
+                Color::Green
+            };
+            #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
+            b.set_color_str(x, y, "█", c, Color::Reset);
+            #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+            b.set_graph_sym(x, y, 1, if i == 0 { 0 } else { 102 }, c);
+        }
+        match m.state {
+            StarterState::Paused => center(b, STARTERH / 2, "PAUSED", Color::Indexed(222)),
+            StarterState::Over => {
+                center(b, STARTERH / 2 - 1, "GAME OVER", Color::LightRed);
+                let s = match m.rank {
+                    Some(0) => "new best score!".to_string(),
+                    Some(r) => format!("#{} best score", r + 1),
+                    None => String::new(),
+                };
+                center(b, STARTERH / 2 + 1, &s, Color::Indexed(222));
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_text(&mut self, ctx: &Context, m: &StarterModel) {
+        let sc = self.panel.get_sprite("score");
+        sc.content.reset();
+        sc.set_color_str(
+            0,
+            0,
+            format!("score {:<6} best {}", m.score, m.scores.top()),
+            Color::Indexed(250),
+            Color::Reset,
+        );
+        let help = match m.state {
+            StarterState::Title | StarterState::Over => "ENTER start  M mute",
+            StarterState::Playing => "arrows/WASD move  P pause  M mute",
+            StarterState::Paused => "P resume  M mute",
+        };
+        let muted = if ctx.audio.is_muted() { " (muted)" } else { "" };
+        let h = self.panel.get_sprite("help");
+        h.content.reset();
+        h.set_color_str(
+            0,
+            0,
+            format!("{}{}", help, muted),
+            Color::Indexed(245),
+            Color::Reset,
+        );
+    }
+}
+
+impl Render for StarterRender {
+    type Model = StarterModel;
+
+    fn init(&mut self, ctx: &mut Context, _m: &mut Self::Model) {
+        self.panel.init(ctx);
+    }
+
+    fn handle_event(&mut self, _ctx: &mut Context, _m: &mut Self::Model, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _m: &mut Self::Model, _dt: f32) {}
+
+    fn draw(&mut self, ctx: &mut Context, m: &mut Self::Model, _dt: f32) {
+        self.draw_board(m);
+        self.draw_text(ctx, m);
+        self.panel.draw(ctx).unwrap();
+    }
+}
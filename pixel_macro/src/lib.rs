@@ -6,20 +6,76 @@ use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 use quote::quote;
 
+// key: value in the config block
+struct ConfigField {
+    key: Ident,
+    value: Expr,
+}
+
+impl Parse for ConfigField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let key: Ident = input.parse()?;
+        input.parse::<syn::Token![:]>()?;
+        let value: Expr = input.parse()?;
+        Ok(ConfigField { key, value })
+    }
+}
+
+// name, app path and project path, then an optional { config } block
 struct VariadicInput {
-    exprs: Punctuated<Expr, syn::Token![,]>,
+    exprs: Vec<Expr>,
+    config: Vec<ConfigField>,
 }
 
 impl Parse for VariadicInput {
     fn parse(input: ParseStream) -> Result<Self> {
-        let exprs = Punctuated::parse_terminated(input)?;
-        Ok(VariadicInput { exprs })
+        let mut exprs = vec![];
+        let mut config = vec![];
+        while !input.is_empty() {
+            if input.peek(syn::token::Brace) {
+                let content;
+                syn::braced!(content in input);
+                let fields = Punctuated::<ConfigField, syn::Token![,]>::parse_terminated(&content)?;
+                config.extend(fields);
+            } else {
+                exprs.push(input.parse()?);
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(VariadicInput { exprs, config })
     }
 }
 
+/// pixel_game!(Name) makes the Game of NameModel and NameRender with
+/// init_game and run, and the wasm entry points.
+///
+/// Optional arguments: the app folder and the project path, then a config
+/// block setting up rust_pixel::game::GameConfig:
+///
+/// pixel_game!(Petview, {
+///     size: (82, 20),
+///     ratio: (1.2, 1.2),
+///     title: "PETSCII viewer",
+///     fps: 60,
+///     asset_path: ".",
+///     features: ["debug", "fixed"],
+///     on_start: setup,
+///     on_exit: save_settings,
+/// });
+///
+/// on_start and on_exit take a fn(&mut Context). A scenes list of
+/// (name, path) registers pathModel and pathRender of each scene with a
+/// SceneStack, the first one is entered at start, and the crate then needs
+/// no NameModel and NameRender:
+///
+/// pixel_game!(Arcade, {
+///     scenes: [("menu", menu::Menu), ("play", play::Play)],
+/// });
 #[proc_macro]
 pub fn pixel_game(input: TokenStream) -> TokenStream {
-    let VariadicInput { exprs } = parse_macro_input!(input as VariadicInput);
+    let VariadicInput { exprs, config } = parse_macro_input!(input as VariadicInput);
     let args_count = exprs.len();
 
     let (name, app_path, project_path) = if args_count == 1 {
@@ -75,12 +131,73 @@ pub fn pixel_game(input: TokenStream) -> TokenStream {
         }
     };
 
+    let mut cfg_stmts = vec![];
+    let mut scenes = vec![];
+    for f in &config {
+        let v = &f.value;
+        cfg_stmts.push(match f.key.to_string().as_str() {
+            "scenes" => {
+                match parse_scenes(v) {
+                    Ok(sc) => scenes = sc,
+                    Err(e) => return e.to_compile_error().into(),
+                }
+                continue;
+            }
+            "size" => quote! { let (w, h) = #v; cfg = cfg.size(w, h); },
+            "ratio" => quote! { let (rx, ry) = #v; cfg = cfg.ratio(rx, ry); },
+            "title" => quote! { cfg = cfg.title(#v); },
+            "fps" => quote! { cfg = cfg.fps(#v); },
+            "asset_path" => quote! { cfg = cfg.asset_path(#v); },
+            "features" => quote! { for f in #v { cfg = cfg.feature(f); } },
+            "on_start" => quote! { cfg = cfg.on_start(#v); },
+            "on_exit" => quote! { cfg = cfg.on_exit(#v); },
+            k => {
+                let msg = format!(
+                    "pixel_game: unknown config key {}, expected size, ratio, title, fps, asset_path, features, scenes, on_start or on_exit",
+                    k
+                );
+                return syn::Error::new(f.key.span(), msg).to_compile_error().into();
+            }
+        });
+    }
+
+    // one Model/Render pair, or a SceneStack of the scenes
+    let (uses, model_ty, render_ty, new_mr) = if scenes.is_empty() {
+        (
+            quote! { use crate::{model::#model_name, render::#render_name}; },
+            quote! { #model_name },
+            quote! { #render_name },
+            quote! {
+                let m = #model_name::new();
+                let r = #render_name::new();
+            },
+        )
+    } else {
+        let first = &scenes[0].0;
+        let adds = scenes.iter().map(|(n, m, r)| {
+            quote! { m.add(#n, rust_pixel::game::SceneOf::new(#m::new(), #r::new())); }
+        });
+        (
+            quote! {},
+            quote! { rust_pixel::game::SceneStack },
+            quote! { rust_pixel::game::SceneRender },
+            quote! {
+                let mut m = rust_pixel::game::SceneStack::new(#first);
+                #(#adds)*
+                let r = rust_pixel::game::SceneRender;
+            },
+        )
+    };
+
     let expanded = quote! {
-            use crate::{model::#model_name, render::#render_name};
+            #uses
             use rust_pixel::game::Game;
 
             #[cfg(target_arch = "wasm32")]
-            use rust_pixel::render::adapter::web::{input_events_from_web, WebAdapter};
+            use rust_pixel::render::adapter::web::{
+                gamepad_event_from_web, input_events_from_web, input_from_web, set_offscreen_canvas,
+                text_event_from_web, touch_event_from_web, WebAdapter, WebInput,
+            };
             use wasm_bindgen::prelude::*;
             #[cfg(target_arch = "wasm32")]
             use wasm_bindgen_futures::js_sys;
@@ -89,15 +206,30 @@ pub fn pixel_game(input: TokenStream) -> TokenStream {
 
             #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
             pub struct #game_name {
-                g: Game<#model_name, #render_name>,
+                g: Game<#model_ty, #render_ty>,
+                // js callbacks of the hosting page
+                #[cfg(target_arch = "wasm32")]
+                on_frame: Option<js_sys::Function>,
+                #[cfg(target_arch = "wasm32")]
+                on_message: Option<js_sys::Function>,
             }
 
             pub fn init_game() -> #game_name {
-                let m = #model_name::new();
-                let r = #render_name::new();
-                let mut g = Game::new_with_project_path(m, r, #game_name_lit, #prjpath_opt_tokens);
+                #new_mr
+                let mut cfg = rust_pixel::game::GameConfig::new();
+                if let Some(p) = #prjpath_opt_tokens {
+                    cfg = cfg.asset_path(p);
+                }
+                #(#cfg_stmts)*
+                let mut g = Game::new_with_config(m, r, #game_name_lit, cfg);
                 g.init();
-                #game_name { g }
+                #game_name {
+                    g,
+                    #[cfg(target_arch = "wasm32")]
+                    on_frame: None,
+                    #[cfg(target_arch = "wasm32")]
+                    on_message: None,
+                }
             }
 
             #[cfg(target_arch = "wasm32")]
@@ -107,20 +239,138 @@ pub fn pixel_game(input: TokenStream) -> TokenStream {
                     init_game()
                 }
 
+                // worker mode, the game draws to an OffscreenCanvas
+                pub fn new_offscreen(canvas: web_sys::OffscreenCanvas) -> Self {
+                    set_offscreen_canvas(canvas);
+                    init_game()
+                }
+
                 pub fn tick(&mut self, dt: f32) {
                     self.g.on_tick(dt);
+                    // messages posted by the game go to the page
+                    let posts = self.g.context.host.take_posts();
+                    if let Some(f) = &self.on_message {
+                        for m in posts {
+                            let _ = f.call2(&JsValue::NULL, &JsValue::from_str(&m.name), &JsValue::from_str(&m.data));
+                        }
+                    }
+                    if let Some(f) = &self.on_frame {
+                        if !self.g.context.host.paused {
+                            let _ = f.call2(&JsValue::NULL, &JsValue::from(self.g.context.stage), &JsValue::from(dt));
+                        }
+                    }
                 }
 
-                pub fn key_event(&mut self, t: u8, e: web_sys::Event) {
-                    let abase = &self
+                // control api of the hosting page, see rust_pixel::game::Host
+
+                pub fn pause(&mut self) {
+                    self.g.context.host.paused = true;
+                }
+
+                pub fn resume(&mut self) {
+                    self.g.context.host.paused = false;
+                }
+
+                pub fn is_paused(&self) -> bool {
+                    self.g.context.host.paused
+                }
+
+                // emitted as HOST_EVENT, json is passed on as text
+                pub fn send_event(&mut self, name: &str, json: &str) {
+                    self.g.context.host.receive(name, json);
+                }
+
+                // new size in cells, returns the canvas size [w, h] in
+                // css pixels, the canvas gets devicePixelRatio times that
+                pub fn resize(&mut self, cols: u16, rows: u16) -> Vec<u32> {
+                    let wa = self
                         .g
                         .context
                         .adapter
                         .as_any()
-                        .downcast_ref::<WebAdapter>()
-                        .unwrap()
-                        .base;
-                    if let Some(pe) = input_events_from_web(t, e, abase.ratio_x, abase.ratio_y) {
+                        .downcast_mut::<WebAdapter>()
+                        .unwrap();
+                    let (w, h) = wa.resize_cells(cols, rows);
+                    vec![w, h]
+                }
+
+                // f(stage, dt) after every tick while not paused, null removes it
+                pub fn set_frame_callback(&mut self, f: Option<js_sys::Function>) {
+                    self.on_frame = f;
+                }
+
+                // f(name, json) for every message the game posts
+                pub fn set_message_callback(&mut self, f: Option<js_sys::Function>) {
+                    self.on_message = f;
+                }
+
+                pub fn key_event(&mut self, t: u8, e: web_sys::Event) {
+                    let abase = self.g.context.adapter.get_base();
+                    if let Some(pe) = input_events_from_web(t, e, abase) {
+                        self.g.context.input_events.push(pe);
+                    }
+                }
+
+                // key or mouse event as a plain object of the fields read
+                // by WebInput, as posted to a worker
+                pub fn input_event(&mut self, t: u8, o: JsValue) {
+                    let abase = self.g.context.adapter.get_base();
+                    if let Some(pe) = input_from_web(t, &WebInput::from_js(&o), abase) {
+                        self.g.context.input_events.push(pe);
+                    }
+                }
+
+                pub fn touch_event(&mut self, t: u8, id: u32, x: f32, y: f32) {
+                    let abase = self.g.context.adapter.get_base();
+                    let (rx, ry) = (abase.ratio_x, abase.ratio_y);
+                    let (x, y) = abase.window_to_canvas(x as i32, y as i32);
+                    if let Some(pe) = touch_event_from_web(t, id, x as f32, y as f32, rx, ry) {
+                        self.g.context.input_events.push(pe);
+                    }
+                }
+
+                pub fn text_event(&mut self, t: u8, s: &str) {
+                    if !self.g.context.adapter.get_base().text_input {
+                        return;
+                    }
+                    if let Some(pe) = text_event_from_web(t, s) {
+                        self.g.context.input_events.push(pe);
+                    }
+                }
+
+                pub fn paste_event(&mut self, s: &str) {
+                    self.g.context.adapter.get_base().clipboard = s.to_string();
+                    let pe = rust_pixel::event::Event::Paste(s.to_string());
+                    self.g.context.input_events.push(pe);
+                }
+
+                // true if the game scales, index.js then makes the canvas
+                // as large as the window
+                pub fn resize_event(&mut self, w: u32, h: u32) -> bool {
+                    let abase = self.g.context.adapter.get_base();
+                    abase.window_resized(w, h);
+                    abase.viewport.is_some()
+                }
+
+                // fullscreenchange and pointerlockchange of the page
+                pub fn fullscreen_event(&mut self, on: bool) {
+                    self.g.context.adapter.get_base().fullscreen_changed(on);
+                }
+
+                pub fn pointer_lock_event(&mut self, on: bool) {
+                    self.g.context.adapter.get_base().pointer_lock_changed(on);
+                }
+
+                // devicePixelRatio of the page, at start and on zoom
+                // changes, the page gives the canvas dpr times its css size
+                pub fn dpr_event(&mut self, dpr: f32) {
+                    if dpr > 0.0 {
+                        self.g.context.adapter.get_base().dpr = dpr;
+                    }
+                }
+
+                pub fn gamepad_event(&mut self, id: u32, t: u8, code: u8, value: f32) {
+                    if let Some(pe) = gamepad_event_from_web(id, t, code, value) {
                         self.g.context.input_events.push(pe);
                     }
                 }
@@ -147,6 +397,15 @@ pub fn pixel_game(input: TokenStream) -> TokenStream {
                     self.g.context.asset_manager.set_data(url, data);
                 }
 
+                pub fn on_asset_failed(&mut self, url: &str) {
+                    self.g.context.asset_manager.set_failed(url);
+                }
+
+                // an IndexedDB request of host.js finished, see Storage::web_done
+                pub fn on_storage_done(&mut self, op: u8, key: &str, err: &str, data: &[u8]) {
+                    self.g.context.storage.web_done(op, key, err, data);
+                }
+
                 pub fn get_ratiox(&mut self) -> f32 {
                     self.g.context.adapter.get_base().ratio_x
                 }
@@ -159,9 +418,88 @@ pub fn pixel_game(input: TokenStream) -> TokenStream {
             pub fn run() {
                 let mut g = init_game().g;
                 g.run().unwrap();
-                g.render.panel.reset(&mut g.context);
+                g.context.adapter.reset();
             }
     };
 
     TokenStream::from(expanded)
 }
+
+// [("menu", menu::Menu), ...] to (name, menu::MenuModel, menu::MenuRender)
+fn parse_scenes(v: &Expr) -> Result<Vec<(LitStr, syn::Path, syn::Path)>> {
+    let err = || {
+        syn::Error::new_spanned(v, "pixel_game: scenes expects [(\"name\", Path), ...]")
+    };
+    let Expr::Array(arr) = v else {
+        return Err(err());
+    };
+    let mut scenes = vec![];
+    for e in &arr.elems {
+        let Expr::Tuple(t) = e else {
+            return Err(err());
+        };
+        let (2, Some(Expr::Lit(l)), Some(Expr::Path(p))) =
+            (t.elems.len(), t.elems.first(), t.elems.last())
+        else {
+            return Err(err());
+        };
+        let syn::Lit::Str(name) = &l.lit else {
+            return Err(err());
+        };
+        let suffixed = |sfx: &str| {
+            let mut path = p.path.clone();
+            let last = path.segments.last_mut().unwrap();
+            last.ident = Ident::new(&format!("{}{}", last.ident, sfx), last.ident.span());
+            path
+        };
+        scenes.push((name.clone(), suffixed("Model"), suffixed("Render")));
+    }
+    if scenes.is_empty() {
+        return Err(err());
+    }
+    Ok(scenes)
+}
+
+/// embed_assets!("assets") includes every file under the directory
+/// (relative to the crate's Cargo.toml) at compile time, and expands to
+/// &[(&str, &[u8])] of (path relative to the directory, content).
+/// Register it with AssetManager::add_embedded before loading assets:
+///
+/// ctx.asset_manager.add_embedded(embed_assets!("assets"));
+#[proc_macro]
+pub fn embed_assets(input: TokenStream) -> TokenStream {
+    let dir = parse_macro_input!(input as LitStr);
+    let root = std::path::Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default())
+        .join(dir.value());
+    let mut files = vec![];
+    let mut todo = vec![root.clone()];
+    while let Some(d) = todo.pop() {
+        let rd = match std::fs::read_dir(&d) {
+            Ok(rd) => rd,
+            Err(e) => {
+                let msg = format!("embed_assets: can not read {:?}: {}", d, e);
+                return TokenStream::from(quote! { compile_error!(#msg) });
+            }
+        };
+        for entry in rd.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                todo.push(path);
+            } else if let Ok(rel) = path.strip_prefix(&root) {
+                let name = rel.to_string_lossy().replace('\\', "/");
+                files.push((name, path.to_string_lossy().to_string()));
+            }
+        }
+    }
+    files.sort();
+    let entries = files.iter().map(|(name, abs)| {
+        quote! { (#name, include_bytes!(#abs) as &[u8]) }
+    });
+    let expanded = quote! {
+        {
+            let files: &'static [(&'static str, &'static [u8])] = &[#(#entries),*];
+            files
+        }
+    };
+    TokenStream::from(expanded)
+}
@@ -4,6 +4,8 @@
 //! here integrates some common algorithms e.g. disjoint-set data structure, astar
 pub mod union_find;
 pub mod astar;
+pub mod path;
+pub mod noise;
 mod bezier;
 pub use bezier::*;
 
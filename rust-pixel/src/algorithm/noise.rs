@@ -0,0 +1,459 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! procedural noise and map generation
+//!
+//! Noise gives perlin, simplex and worley noise from a seed, the same seed
+//! always gives the same values. Perlin and simplex are in -1.0..1.0,
+//! worley is the distance to the nearest feature point in 0.0..1.0.
+//! fbm sums octaves of any of them for more detail.
+//!
+//! cave_map grows caves with cellular automata, bsp_map splits the area
+//! into rooms joined by corridors. Both return a GenMap of solid cells,
+//! copy it into a TileMap layer with to_tilemap, or find paths on it
+//! directly as it is a CostGrid.
+//!
+//! # Example
+//! ```
+//! use rust_pixel::algorithm::noise::*;
+//! use rust_pixel::util::Rand;
+//!
+//! let n = Noise::new(42);
+//! let (x, y) = (12, 7);
+//! let h = n.fbm(NoiseKind::Simplex, x as f64 * 0.05, y as f64 * 0.05, 4);
+//! assert!((-1.0..=1.0).contains(&h));
+//!
+//! let mut rand = Rand::with_seed(7);
+//! let cave = cave_map(80, 40, 0.45, 5, &mut rand);
+//! let dungeon = bsp_map(80, 40, 8, &mut rand);
+//! assert!(dungeon.floor_count() > 0);
+//! ```
+
+use crate::algorithm::path::CostGrid;
+#[cfg(not(feature = "base"))]
+use crate::render::tilemap::TileMap;
+pub use crate::util::{Rand, Rect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseKind {
+    Perlin,
+    Simplex,
+    Worley,
+}
+
+/// seeded noise generator
+#[derive(Debug, Clone)]
+pub struct Noise {
+    pub seed: u64,
+    perm: Vec<u8>,
+}
+
+const F2: f64 = 0.366_025_403_784_438_6; // (sqrt(3) - 1) / 2
+const G2: f64 = 0.211_324_865_405_187_1; // (3 - sqrt(3)) / 6
+const GRAD2: [(f64, f64); 8] = [
+    (1.0, 1.0),
+    (-1.0, 1.0),
+    (1.0, -1.0),
+    (-1.0, -1.0),
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+];
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn grad(h: u8, x: f64, y: f64) -> f64 {
+    let g = GRAD2[(h & 7) as usize];
+    g.0 * x + g.1 * y
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut p: Vec<u8> = (0..=255).collect();
+        Rand::with_seed(seed).shuffle(&mut p);
+        let perm = p.iter().chain(p.iter()).copied().collect();
+        Self { seed, perm }
+    }
+
+    fn p(&self, i: i32) -> i32 {
+        self.perm[(i & 511) as usize] as i32
+    }
+
+    fn hash(&self, x: i32, y: i32) -> u8 {
+        self.p(self.p(x & 255) + (y & 255)) as u8
+    }
+
+    /// classic gradient noise, -1.0..1.0
+    pub fn perlin(&self, x: f64, y: f64) -> f64 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (xi, yi) = (x0 as i32, y0 as i32);
+        let (xf, yf) = (x - x0, y - y0);
+        let (u, v) = (fade(xf), fade(yf));
+        let a = lerp(
+            u,
+            grad(self.hash(xi, yi), xf, yf),
+            grad(self.hash(xi + 1, yi), xf - 1.0, yf),
+        );
+        let b = lerp(
+            u,
+            grad(self.hash(xi, yi + 1), xf, yf - 1.0),
+            grad(self.hash(xi + 1, yi + 1), xf - 1.0, yf - 1.0),
+        );
+        lerp(v, a, b).clamp(-1.0, 1.0)
+    }
+
+    /// simplex noise, fewer directional artifacts than perlin, -1.0..1.0
+    pub fn simplex(&self, x: f64, y: f64) -> f64 {
+        let s = (x + y) * F2;
+        let (i, j) = ((x + s).floor(), (y + s).floor());
+        let t = (i + j) * G2;
+        let (x0, y0) = (x - (i - t), y - (j - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let corners = [
+            (x0, y0, 0, 0),
+            (x0 - i1 as f64 + G2, y0 - j1 as f64 + G2, i1, j1),
+            (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2, 1, 1),
+        ];
+        let (i, j) = (i as i32, j as i32);
+        let n: f64 = corners
+            .iter()
+            .map(|(cx, cy, di, dj)| {
+                let t = 0.5 - cx * cx - cy * cy;
+                if t < 0.0 {
+                    0.0
+                } else {
+                    t.powi(4) * grad(self.hash(i + di, j + dj), *cx, *cy)
+                }
+            })
+            .sum();
+        (70.0 * n).clamp(-1.0, 1.0)
+    }
+
+    // feature point of a cell, in 0.0..1.0
+    fn feature(&self, x: i32, y: i32) -> (f64, f64) {
+        let mut h = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+            ^ self.seed;
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h ^= h >> 33;
+        (
+            (h & 0xffff) as f64 / 65536.0,
+            ((h >> 16) & 0xffff) as f64 / 65536.0,
+        )
+    }
+
+    /// cellular noise, distance to the nearest feature point, 0.0..1.0
+    pub fn worley(&self, x: f64, y: f64) -> f64 {
+        let (cx, cy) = (x.floor() as i32, y.floor() as i32);
+        let mut d = f64::MAX;
+        for j in cy - 1..=cy + 1 {
+            for i in cx - 1..=cx + 1 {
+                let (fx, fy) = self.feature(i, j);
+                d = d.min((i as f64 + fx - x).hypot(j as f64 + fy - y));
+            }
+        }
+        d.min(1.0)
+    }
+
+    pub fn get(&self, kind: NoiseKind, x: f64, y: f64) -> f64 {
+        match kind {
+            NoiseKind::Perlin => self.perlin(x, y),
+            NoiseKind::Simplex => self.simplex(x, y),
+            NoiseKind::Worley => self.worley(x, y),
+        }
+    }
+
+    /// fractal brownian motion, octaves of doubling frequency and halving
+    /// amplitude, in the range of kind
+    pub fn fbm(&self, kind: NoiseKind, x: f64, y: f64, octaves: u32) -> f64 {
+        let (mut sum, mut amp, mut freq, mut total) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..octaves.max(1) {
+            sum += amp * self.get(kind, x * freq, y * freq);
+            total += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        sum / total
+    }
+}
+
+/// a generated map of solid and open cells
+#[derive(Debug, Clone)]
+pub struct GenMap {
+    pub width: u16,
+    pub height: u16,
+    pub solid: Vec<bool>,
+    /// rooms of a bsp map
+    pub rooms: Vec<Rect>,
+}
+
+impl GenMap {
+    /// all solid
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            solid: vec![true; width as usize * height as usize],
+            rooms: vec![],
+        }
+    }
+
+    /// out of map is solid
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return true;
+        }
+        self.solid[y as usize * self.width as usize + x as usize]
+    }
+
+    pub fn set_solid(&mut self, x: i32, y: i32, s: bool) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        self.solid[y as usize * self.width as usize + x as usize] = s;
+    }
+
+    pub fn floor_count(&self) -> usize {
+        self.solid.iter().filter(|s| !**s).count()
+    }
+
+    fn walls_around(&self, x: i32, y: i32) -> usize {
+        let mut n = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) != (0, 0) && self.is_solid(x + dx, y + dy) {
+                    n += 1;
+                }
+            }
+        }
+        n
+    }
+
+    // floor cells 4-connected to (x, y)
+    fn region(&self, x: i32, y: i32, seen: &mut [bool]) -> Vec<usize> {
+        let w = self.width as i32;
+        let mut cells = vec![];
+        let mut stack = vec![(x, y)];
+        seen[(y * w + x) as usize] = true;
+        while let Some((x, y)) = stack.pop() {
+            cells.push((y * w + x) as usize);
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let (nx, ny) = (x + dx, y + dy);
+                if !self.is_solid(nx, ny) && !seen[(ny * w + nx) as usize] {
+                    seen[(ny * w + nx) as usize] = true;
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        cells
+    }
+
+    /// fills every floor region but the largest
+    pub fn keep_largest_region(&mut self) {
+        let mut seen = vec![false; self.solid.len()];
+        let mut largest: Vec<usize> = vec![];
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let i = y as usize * self.width as usize + x as usize;
+                if !self.solid[i] && !seen[i] {
+                    let r = self.region(x, y, &mut seen);
+                    if r.len() > largest.len() {
+                        largest = r;
+                    }
+                }
+            }
+        }
+        self.solid.iter_mut().for_each(|s| *s = true);
+        for i in largest {
+            self.solid[i] = false;
+        }
+    }
+
+    fn carve(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        for y in y0.min(y1)..=y0.max(y1) {
+            for x in x0.min(x1)..=x0.max(x1) {
+                self.set_solid(x, y, false);
+            }
+        }
+    }
+
+    /// writes the map into a TileMap layer, wall tiles on solid cells
+    /// and floor tiles on open ones, 0 clears the cell
+    #[cfg(not(feature = "base"))]
+    pub fn to_tilemap(&self, tm: &mut TileMap, layer: usize, wall: u16, floor: u16) {
+        for y in 0..self.height.min(tm.height) {
+            for x in 0..self.width.min(tm.width) {
+                let t = if self.is_solid(x as i32, y as i32) {
+                    wall
+                } else {
+                    floor
+                };
+                tm.set_tile(layer, x, y, t);
+            }
+        }
+    }
+}
+
+impl CostGrid for GenMap {
+    fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn cost(&self, x: i32, y: i32) -> Option<u32> {
+        if self.is_solid(x, y) {
+            None
+        } else {
+            Some(1)
+        }
+    }
+}
+
+/// caves by cellular automata: fill cells solid at random, then steps of
+/// the 4-5 rule smooth them, only the largest cave is kept, border is solid
+pub fn cave_map(width: u16, height: u16, fill: f32, steps: u32, rand: &mut Rand) -> GenMap {
+    let mut m = GenMap::new(width, height);
+    let (w, h) = (width as i32, height as i32);
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            m.set_solid(x, y, rand.chance(fill));
+        }
+    }
+    for _ in 0..steps {
+        let mut next = m.clone();
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let n = m.walls_around(x, y);
+                next.set_solid(x, y, n >= 5 || (m.is_solid(x, y) && n >= 4));
+            }
+        }
+        m = next;
+    }
+    m.keep_largest_region();
+    m
+}
+
+/// dungeon by binary space partition: the area is split until parts are
+/// smaller than two min_leaf, each part gets a room, sibling parts are
+/// joined by corridors, so every room is reachable
+pub fn bsp_map(width: u16, height: u16, min_leaf: u16, rand: &mut Rand) -> GenMap {
+    let mut m = GenMap::new(width, height);
+    let min = min_leaf.max(5) as i32;
+    bsp_split(&mut m, (0, 0, width as i32, height as i32), min, rand);
+    m
+}
+
+// splits area (x, y, w, h), returns the center of a room inside it
+fn bsp_split(m: &mut GenMap, area: (i32, i32, i32, i32), min: i32, rand: &mut Rand) -> (i32, i32) {
+    let (x, y, w, h) = area;
+    let (can_x, can_y) = (w >= min * 2, h >= min * 2);
+    if !can_x && !can_y {
+        // room with a margin of one cell
+        let rw = rand.range(3.min(w - 2), w - 1);
+        let rh = rand.range(3.min(h - 2), h - 1);
+        let rx = x + 1 + rand.range(0, w - 1 - rw);
+        let ry = y + 1 + rand.range(0, h - 1 - rh);
+        m.carve(rx, ry, rx + rw - 1, ry + rh - 1);
+        m.rooms
+            .push(Rect::new(rx as u16, ry as u16, rw as u16, rh as u16));
+        return (rx + rw / 2, ry + rh / 2);
+    }
+    let split_x = match (can_x, can_y) {
+        (true, false) => true,
+        (false, true) => false,
+        _ if w * 4 > h * 5 => true,
+        _ if h * 4 > w * 5 => false,
+        _ => rand.chance(0.5),
+    };
+    let (a, b) = if split_x {
+        let s = rand.range(min, w - min + 1);
+        ((x, y, s, h), (x + s, y, w - s, h))
+    } else {
+        let s = rand.range(min, h - min + 1);
+        ((x, y, w, s), (x, y + s, w, h - s))
+    };
+    let ca = bsp_split(m, a, min, rand);
+    let cb = bsp_split(m, b, min, rand);
+    // l-shaped corridor
+    m.carve(ca.0, ca.1, cb.0, ca.1);
+    m.carve(cb.0, ca.1, cb.0, cb.1);
+    if rand.chance(0.5) {
+        ca
+    } else {
+        cb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::path::{DijkstraMap, Neighbors, PointU16};
+
+    #[test]
+    fn noise_and_maps() {
+        let n = Noise::new(42);
+        assert_eq!(n.perlin(3.7, 1.2), Noise::new(42).perlin(3.7, 1.2));
+        // perlin is zero on the lattice
+        assert_eq!(n.perlin(3.0, 5.0), 0.0);
+        let mut vs = vec![];
+        for i in 0..400 {
+            let (x, y) = (i as f64 * 0.37, i as f64 * 0.21);
+            for k in [NoiseKind::Perlin, NoiseKind::Simplex] {
+                let v = n.fbm(k, x, y, 3);
+                assert!((-1.0..=1.0).contains(&v));
+                vs.push(v);
+            }
+            assert!((0.0..=1.0).contains(&n.worley(x, y)));
+        }
+        assert!(vs.iter().any(|v| *v > 0.2) && vs.iter().any(|v| *v < -0.2));
+        assert_ne!(n.simplex(0.5, 0.5), Noise::new(43).simplex(0.5, 0.5));
+
+        // every open cell can reach every other one
+        let connected = |m: &GenMap| {
+            let i = m.solid.iter().position(|s| !*s).unwrap();
+            let w = m.width as usize;
+            let p = PointU16 {
+                x: (i % w) as u16,
+                y: (i / w) as u16,
+            };
+            let dm = DijkstraMap::new(m, &[p], Neighbors::Four);
+            (0..m.solid.len()).all(|i| {
+                m.solid[i]
+                    || dm
+                        .distance(PointU16 {
+                            x: (i % w) as u16,
+                            y: (i / w) as u16,
+                        })
+                        .is_some()
+            })
+        };
+        let mut rand = Rand::with_seed(7);
+        let cave = cave_map(60, 30, 0.45, 5, &mut rand);
+        assert!(cave.floor_count() > 300);
+        assert!((0..60).all(|x| cave.is_solid(x, 0) && cave.is_solid(x, 29)));
+        assert!(connected(&cave));
+
+        let d = bsp_map(60, 30, 8, &mut rand);
+        assert!(d.rooms.len() >= 4);
+        assert!(d.rooms.iter().all(|r| r.left() > 0 && r.right() < 60));
+        assert!(connected(&d));
+    }
+
+    #[cfg(not(feature = "base"))]
+    #[test]
+    fn gen_map_to_tilemap() {
+        let d = bsp_map(60, 30, 8, &mut Rand::with_seed(7));
+        let mut tm = TileMap::new(60, 30);
+        let wall = tm.add_tile(Default::default(), crate::render::tilemap::TileFlags::SOLID);
+        let l = tm.add_layer("ground");
+        d.to_tilemap(&mut tm, l, wall, 0);
+        assert!((0..30).all(|y| (0..60).all(|x| tm.is_solid(x, y) == d.is_solid(x, y))));
+    }
+}
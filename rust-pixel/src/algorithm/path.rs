@@ -0,0 +1,498 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! path finding over grids: A*, Dijkstra maps and jump point search
+//!
+//! A grid tells the cost to enter each cell, None is a wall. CostMap
+//! holds costs set by the game, TileMap is a grid of cost 1 with solid
+//! tiles as walls. Diagonal steps cost 1.4 times a straight step and may
+//! not cut the corner of a wall. Paths include start and goal.
+//!
+//! A Dijkstra map floods the distances to one or more goals, monsters
+//! chasing the player just take map.next_step every turn.
+//! Jump point search finds the same paths as A* with 8 neighbors much
+//! faster on open maps, it treats every open cell as cost 1.
+//!
+//! # Example
+//! ```
+//! use rust_pixel::algorithm::path::*;
+//! use rust_pixel::util::PointU16;
+//!
+//! let mut cm = CostMap::new(40, 20);
+//! cm.set_cost(5, 5, None);
+//! let from = PointU16 { x: 0, y: 0 };
+//! let to = PointU16 { x: 30, y: 10 };
+//! let path = a_star_path(&cm, from, to, Neighbors::Eight).unwrap();
+//! assert_eq!(path.last(), Some(&to));
+//!
+//! // monsters step towards the player
+//! let dm = DijkstraMap::new(&cm, &[to], Neighbors::Four);
+//! let next = dm.next_step(from);
+//! ```
+
+#[cfg(not(feature = "base"))]
+use crate::render::tilemap::TileMap;
+pub use crate::util::PointU16;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// cost units of a straight and a diagonal step
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+/// a grid with the cost to enter each cell
+pub trait CostGrid {
+    fn size(&self) -> (u16, u16);
+    /// None is a wall, out of the grid too
+    fn cost(&self, x: i32, y: i32) -> Option<u32>;
+
+    fn is_open(&self, x: i32, y: i32) -> bool {
+        self.cost(x, y).is_some()
+    }
+}
+
+/// costs set by the game, 1 by default
+#[derive(Debug, Clone)]
+pub struct CostMap {
+    pub width: u16,
+    pub height: u16,
+    costs: Vec<Option<u32>>,
+}
+
+impl CostMap {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            costs: vec![Some(1); width as usize * height as usize],
+        }
+    }
+
+    /// cost of each cell by f(x, y)
+    pub fn from_fn<F: Fn(u16, u16) -> Option<u32>>(width: u16, height: u16, f: F) -> Self {
+        let mut cm = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                cm.set_cost(x, y, f(x, y));
+            }
+        }
+        cm
+    }
+
+    pub fn set_cost(&mut self, x: u16, y: u16, cost: Option<u32>) {
+        if x < self.width && y < self.height {
+            self.costs[y as usize * self.width as usize + x as usize] = cost;
+        }
+    }
+}
+
+impl CostGrid for CostMap {
+    fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn cost(&self, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        self.costs[y as usize * self.width as usize + x as usize]
+    }
+}
+
+#[cfg(not(feature = "base"))]
+impl CostGrid for TileMap {
+    fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn cost(&self, x: i32, y: i32) -> Option<u32> {
+        if self.is_solid(x, y) {
+            None
+        } else {
+            Some(1)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbors {
+    Four,
+    Eight,
+}
+
+const DIRS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+// steps from (x, y) with their costs, no corner cutting
+fn steps<G: CostGrid>(g: &G, x: i32, y: i32, nb: Neighbors) -> Vec<(i32, i32, u32)> {
+    let n = match nb {
+        Neighbors::Four => 4,
+        Neighbors::Eight => 8,
+    };
+    let mut out = vec![];
+    for (dx, dy) in &DIRS[..n] {
+        let Some(c) = g.cost(x + dx, y + dy) else {
+            continue;
+        };
+        if *dx != 0 && *dy != 0 {
+            if !g.is_open(x + dx, y) || !g.is_open(x, y + dy) {
+                continue;
+            }
+            out.push((x + dx, y + dy, c * DIAGONAL));
+        } else {
+            out.push((x + dx, y + dy, c * STRAIGHT));
+        }
+    }
+    out
+}
+
+fn heuristic(a: (i32, i32), b: (i32, i32), nb: Neighbors) -> u32 {
+    let (dx, dy) = ((a.0 - b.0).unsigned_abs(), (a.1 - b.1).unsigned_abs());
+    match nb {
+        Neighbors::Four => (dx + dy) * STRAIGHT,
+        // octile distance
+        Neighbors::Eight => dx.max(dy) * STRAIGHT + dx.min(dy) * (DIAGONAL - STRAIGHT),
+    }
+}
+
+fn point(p: (i32, i32)) -> PointU16 {
+    PointU16 {
+        x: p.0 as u16,
+        y: p.1 as u16,
+    }
+}
+
+fn backtrack(came: &[Option<usize>], w: usize, goal: usize) -> Vec<PointU16> {
+    let mut path = vec![goal];
+    while let Some(p) = came[*path.last().unwrap()] {
+        path.push(p);
+    }
+    path.reverse();
+    path.iter()
+        .map(|i| point(((i % w) as i32, (i / w) as i32)))
+        .collect()
+}
+
+/// cheapest path from start to goal, None if there is none
+pub fn a_star_path<G: CostGrid>(
+    g: &G,
+    start: PointU16,
+    goal: PointU16,
+    nb: Neighbors,
+) -> Option<Vec<PointU16>> {
+    let (w, h) = g.size();
+    let (w, size) = (w as usize, w as usize * h as usize);
+    let (s, t) = (
+        (start.x as i32, start.y as i32),
+        (goal.x as i32, goal.y as i32),
+    );
+    if !g.is_open(s.0, s.1) || !g.is_open(t.0, t.1) {
+        return None;
+    }
+    let idx = |p: (i32, i32)| p.1 as usize * w + p.0 as usize;
+    let mut best = vec![u32::MAX; size];
+    let mut came: Vec<Option<usize>> = vec![None; size];
+    let mut open = BinaryHeap::new();
+    best[idx(s)] = 0;
+    open.push(Reverse((heuristic(s, t, nb), 0u32, s)));
+    while let Some(Reverse((_, cost, p))) = open.pop() {
+        if p == t {
+            return Some(backtrack(&came, w, idx(t)));
+        }
+        if cost > best[idx(p)] {
+            continue;
+        }
+        for (x, y, c) in steps(g, p.0, p.1, nb) {
+            let n = cost + c;
+            let i = idx((x, y));
+            if n < best[i] {
+                best[i] = n;
+                came[i] = Some(idx(p));
+                open.push(Reverse((n + heuristic((x, y), t, nb), n, (x, y))));
+            }
+        }
+    }
+    None
+}
+
+/// distances of every cell to the nearest goal
+#[derive(Debug, Clone)]
+pub struct DijkstraMap {
+    pub width: u16,
+    pub height: u16,
+    pub neighbors: Neighbors,
+    dist: Vec<u32>,
+}
+
+impl DijkstraMap {
+    pub fn new<G: CostGrid>(g: &G, goals: &[PointU16], nb: Neighbors) -> Self {
+        let (width, height) = g.size();
+        let w = width as usize;
+        let mut dist = vec![u32::MAX; w * height as usize];
+        let mut open = BinaryHeap::new();
+        for p in goals {
+            if g.is_open(p.x as i32, p.y as i32) {
+                dist[p.y as usize * w + p.x as usize] = 0;
+                open.push(Reverse((0u32, p.x as i32, p.y as i32)));
+            }
+        }
+        while let Some(Reverse((d, x, y))) = open.pop() {
+            if d > dist[y as usize * w + x as usize] {
+                continue;
+            }
+            for (nx, ny, c) in steps(g, x, y, nb) {
+                let i = ny as usize * w + nx as usize;
+                if d + c < dist[i] {
+                    dist[i] = d + c;
+                    open.push(Reverse((d + c, nx, ny)));
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            neighbors: nb,
+            dist,
+        }
+    }
+
+    fn raw(&self, x: i32, y: i32) -> u32 {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return u32::MAX;
+        }
+        self.dist[y as usize * self.width as usize + x as usize]
+    }
+
+    /// cost to the nearest goal, a straight step of cost 1 is 1.0,
+    /// None if no goal can be reached
+    pub fn distance(&self, p: PointU16) -> Option<f32> {
+        match self.raw(p.x as i32, p.y as i32) {
+            u32::MAX => None,
+            d => Some(d as f32 / STRAIGHT as f32),
+        }
+    }
+
+    /// neighbor closest to a goal, None at a goal or if unreachable
+    pub fn next_step(&self, from: PointU16) -> Option<PointU16> {
+        let (x, y) = (from.x as i32, from.y as i32);
+        let here = self.raw(x, y);
+        let n = match self.neighbors {
+            Neighbors::Four => 4,
+            Neighbors::Eight => 8,
+        };
+        DIRS[..n]
+            .iter()
+            // no corner cutting, walls are never reached
+            .filter(|(dx, dy)| self.raw(x + dx, y) != u32::MAX && self.raw(x, y + dy) != u32::MAX)
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|p| self.raw(p.0, p.1) < here)
+            .min_by_key(|p| self.raw(p.0, p.1))
+            .map(point)
+    }
+
+    /// path downhill to the nearest goal
+    pub fn path_from(&self, from: PointU16) -> Option<Vec<PointU16>> {
+        self.distance(from)?;
+        let mut path = vec![from];
+        while let Some(p) = self.next_step(*path.last().unwrap()) {
+            path.push(p);
+        }
+        Some(path)
+    }
+}
+
+fn jump<G: CostGrid>(
+    g: &G,
+    x: i32,
+    y: i32,
+    dx: i32,
+    dy: i32,
+    goal: (i32, i32),
+) -> Option<(i32, i32)> {
+    let (mut x, mut y) = (x, y);
+    loop {
+        let (nx, ny) = (x + dx, y + dy);
+        if !g.is_open(nx, ny) {
+            return None;
+        }
+        if dx != 0 && dy != 0 && (!g.is_open(x + dx, y) || !g.is_open(x, y + dy)) {
+            return None;
+        }
+        if (nx, ny) == goal {
+            return Some((nx, ny));
+        }
+        if dx != 0 && dy != 0 {
+            if jump(g, nx, ny, dx, 0, goal).is_some() || jump(g, nx, ny, 0, dy, goal).is_some() {
+                return Some((nx, ny));
+            }
+        } else if dx != 0 {
+            if (g.is_open(nx, ny + 1) && !g.is_open(x, y + 1))
+                || (g.is_open(nx, ny - 1) && !g.is_open(x, y - 1))
+            {
+                return Some((nx, ny));
+            }
+        } else if (g.is_open(nx + 1, ny) && !g.is_open(nx + 1, y))
+            || (g.is_open(nx - 1, ny) && !g.is_open(nx - 1, y))
+        {
+            return Some((nx, ny));
+        }
+        (x, y) = (nx, ny);
+    }
+}
+
+// directions worth searching from p reached from parent
+fn jps_dirs<G: CostGrid>(g: &G, p: (i32, i32), parent: Option<(i32, i32)>) -> Vec<(i32, i32)> {
+    let Some(pp) = parent else {
+        return DIRS.to_vec();
+    };
+    let (x, y) = p;
+    let (dx, dy) = ((x - pp.0).signum(), (y - pp.1).signum());
+    let mut ds = vec![];
+    if dx != 0 && dy != 0 {
+        ds.extend([(0, dy), (dx, 0), (dx, dy)]);
+    } else if dx != 0 {
+        ds.push((dx, 0));
+        for oy in [1, -1] {
+            if g.is_open(x, y + oy) {
+                ds.extend([(dx, oy), (0, oy)]);
+            }
+        }
+    } else {
+        ds.push((0, dy));
+        for ox in [1, -1] {
+            if g.is_open(x + ox, y) {
+                ds.extend([(ox, dy), (ox, 0)]);
+            }
+        }
+    }
+    ds
+}
+
+/// shortest path by jump point search, 8 neighbors and uniform cost
+pub fn jps_path<G: CostGrid>(g: &G, start: PointU16, goal: PointU16) -> Option<Vec<PointU16>> {
+    let (w, h) = g.size();
+    let (w, size) = (w as usize, w as usize * h as usize);
+    let (s, t) = (
+        (start.x as i32, start.y as i32),
+        (goal.x as i32, goal.y as i32),
+    );
+    if !g.is_open(s.0, s.1) || !g.is_open(t.0, t.1) {
+        return None;
+    }
+    let idx = |p: (i32, i32)| p.1 as usize * w + p.0 as usize;
+    let mut best = vec![u32::MAX; size];
+    let mut came: Vec<Option<(i32, i32)>> = vec![None; size];
+    let mut open = BinaryHeap::new();
+    best[idx(s)] = 0;
+    open.push(Reverse((heuristic(s, t, Neighbors::Eight), 0u32, s)));
+    while let Some(Reverse((_, cost, p))) = open.pop() {
+        if p == t {
+            // fills the cells between jump points
+            let mut jumps = vec![t];
+            while let Some(q) = came[idx(*jumps.last().unwrap())] {
+                jumps.push(q);
+            }
+            jumps.reverse();
+            let mut path = vec![point(s)];
+            for pair in jumps.windows(2) {
+                let (mut a, b) = (pair[0], pair[1]);
+                let (dx, dy) = ((b.0 - a.0).signum(), (b.1 - a.1).signum());
+                while a != b {
+                    a = (a.0 + dx, a.1 + dy);
+                    path.push(point(a));
+                }
+            }
+            return Some(path);
+        }
+        if cost > best[idx(p)] {
+            continue;
+        }
+        for (dx, dy) in jps_dirs(g, p, came[idx(p)]) {
+            let Some(j) = jump(g, p.0, p.1, dx, dy, t) else {
+                continue;
+            };
+            let n = cost + heuristic(p, j, Neighbors::Eight);
+            if n < best[idx(j)] {
+                best[idx(j)] = n;
+                came[idx(j)] = Some(p);
+                open.push(Reverse((n + heuristic(j, t, Neighbors::Eight), n, j)));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cost_of(path: &[PointU16]) -> u32 {
+        path.windows(2)
+            .map(|p| {
+                if p[0].x != p[1].x && p[0].y != p[1].y {
+                    DIAGONAL
+                } else {
+                    STRAIGHT
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn paths_on_grid() {
+        // a wall at x=5 with a gap at y=9
+        let cm = CostMap::from_fn(12, 10, |x, y| if x == 5 && y < 9 { None } else { Some(1) });
+        let (s, t) = (PointU16 { x: 1, y: 1 }, PointU16 { x: 10, y: 1 });
+
+        let p4 = a_star_path(&cm, s, t, Neighbors::Four).unwrap();
+        assert_eq!((p4[0], *p4.last().unwrap()), (s, t));
+        assert_eq!(p4.len(), 26);
+        assert!(p4
+            .windows(2)
+            .all(|p| p[0].x.abs_diff(p[1].x) + p[0].y.abs_diff(p[1].y) == 1));
+
+        let p8 = a_star_path(&cm, s, t, Neighbors::Eight).unwrap();
+        let pj = jps_path(&cm, s, t).unwrap();
+        assert_eq!(cost_of(&pj), cost_of(&p8));
+        assert_eq!((pj[0], *pj.last().unwrap()), (s, t));
+        assert!(pj.iter().all(|p| cm.is_open(p.x as i32, p.y as i32)));
+
+        let dm = DijkstraMap::new(&cm, &[t], Neighbors::Eight);
+        assert_eq!(dm.distance(t), Some(0.0));
+        assert_eq!(dm.distance(s), Some(cost_of(&p8) as f32 / 10.0));
+        let pd = dm.path_from(s).unwrap();
+        assert_eq!(cost_of(&pd), cost_of(&p8));
+        assert_eq!(dm.next_step(t), None);
+
+        // expensive swamp is walked around
+        let mut swamp = CostMap::new(5, 3);
+        for x in 1..4 {
+            swamp.set_cost(x, 1, Some(9));
+        }
+        let p = a_star_path(
+            &swamp,
+            PointU16 { x: 0, y: 1 },
+            PointU16 { x: 4, y: 1 },
+            Neighbors::Four,
+        )
+        .unwrap();
+        assert!(p.iter().all(|q| q.y != 1 || q.x == 0 || q.x == 4));
+
+        let closed = CostMap::from_fn(6, 3, |x, _| if x == 3 { None } else { Some(1) });
+        let (a, b) = (PointU16 { x: 0, y: 0 }, PointU16 { x: 5, y: 2 });
+        assert!(a_star_path(&closed, a, b, Neighbors::Eight).is_none());
+        assert!(jps_path(&closed, a, b).is_none());
+        assert_eq!(
+            DijkstraMap::new(&closed, &[b], Neighbors::Four).distance(a),
+            None
+        );
+    }
+}
@@ -3,26 +3,53 @@
 
 //! asset provides the resource manager.
 //! It supports async load. It calls JavaScript methods to load resources asynchronously when runs in wasm mode.
+//! Assets can also come from a pixel-pack bundle or be embedded by embed_assets!.
+//! Use progress or a progress callback to follow loading, a failed load
+//! sets the asset state to Failed instead of leaving the sprite empty forever.
 //! https://www.reddit.com/r/rust/comments/8ymzwg/common_data_and_behavior/
+//!
+//! On desktop, enable_hot_reload polls loaded .pix/.txt/.esc files for changes,
+//! re-parses them and emits the "Asset.Reload" event, renders registered
+//! to it can call asset2sprite! again to pick up the new content.
 
 #[cfg(not(target_arch = "wasm32"))]
 use crate::util::get_abs_path;
 use crate::{
     render::buffer::Buffer,
-    render::image::{EscAsset, PixAsset, SeqFrameAsset},
-    render::sprite::Sprite,
+    render::image::{AnsAsset, AseAsset, EscAsset, PixAsset, PngAsset, SeqFrameAsset, XpAsset},
+    render::sprite::{LoopMode, Sprite},
+    render::tilemap::{TiledMap, TiledMapAsset},
 };
 use std::collections::HashMap;
+
+/// pixel-pack asset bundles
+mod pack;
+pub use pack::{pack_key, Pack, PackBuilder};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
-#[cfg(not(target_arch = "wasm32"))]
 use log::info;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::event::event_emit;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant, SystemTime};
+
+/// seconds between two polls of asset files
+#[cfg(not(target_arch = "wasm32"))]
+const HOT_RELOAD_INTERVAL: f32 = 0.5;
+
+// modify times of watched files, polled at HOT_RELOAD_INTERVAL
+#[cfg(not(target_arch = "wasm32"))]
+struct HotReload {
+    mtimes: HashMap<String, SystemTime>,
+    last_check: Instant,
+}
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum AssetState {
     Loading,
     Parsing,
     Ready,
+    Failed,
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -30,6 +57,20 @@ pub enum AssetType {
     ImgPix,
     ImgEsc,
     ImgSsf,
+    ImgAse,
+    ImgPng,
+    ImgAns,
+    ImgXp,
+    TiledMap,
+}
+
+/// named frame sequence of an asset, e.g. tags of an Aseprite file
+/// frames are (frame index, duration in seconds)
+#[derive(Debug, Clone)]
+pub struct FrameTag {
+    pub name: String,
+    pub frames: Vec<(usize, f32)>,
+    pub mode: LoopMode,
 }
 
 pub struct AssetBase {
@@ -76,6 +117,16 @@ pub trait Asset {
 
     fn get_base(&mut self) -> &mut AssetBase;
 
+    /// named animations, only Aseprite asset has them now
+    fn get_frame_tags(&mut self) -> Vec<FrameTag> {
+        vec![]
+    }
+
+    /// only TiledMap asset returns the loaded map
+    fn get_tiled_map(&mut self) -> Option<&mut TiledMap> {
+        None
+    }
+
     fn set_data(&mut self, data: &[u8]) {
         let bs = self.get_base();
         bs.raw_data.clear();
@@ -99,6 +150,15 @@ pub trait Asset {
 pub struct AssetManager {
     pub assets: Vec<Box<dyn Asset>>,
     pub assets_index: HashMap<String, usize>,
+    on_progress: Option<Box<dyn FnMut(usize, usize)>>,
+    // files from embed_assets! and mounted bundles,
+    // searched before files or web requests
+    embedded: HashMap<String, &'static [u8]>,
+    packs: Vec<Pack>,
+    /// locations re-parsed by the last hot reload
+    pub reloaded: Vec<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_reload: Option<HotReload>,
 }
 
 impl Default for AssetManager {
@@ -112,6 +172,148 @@ impl AssetManager {
         Self {
             assets: vec![],
             assets_index: HashMap::new(),
+            on_progress: None,
+            embedded: HashMap::new(),
+            packs: vec![],
+            reloaded: vec![],
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_reload: None,
+        }
+    }
+
+    /// mounts a pixel-pack, later loads read files from it first
+    pub fn mount_pack(&mut self, data: &[u8]) -> Result<(), String> {
+        let p = Pack::from_bytes(data)?;
+        info!("asset mount pack:{} files", p.names().len());
+        self.packs.push(p);
+        Ok(())
+    }
+
+    /// registers files included by pixel_macro::embed_assets!
+    pub fn add_embedded(&mut self, files: &[(&str, &'static [u8])]) {
+        for (name, data) in files {
+            self.embedded.insert(name.to_string(), *data);
+        }
+    }
+
+    fn read_bundled(&self, loc: &str) -> Option<Vec<u8>> {
+        let key = pack_key(loc);
+        if let Some(data) = self.embedded.get(&key) {
+            return Some(data.to_vec());
+        }
+        self.packs.iter().rev().find_map(|p| p.get(&key))
+    }
+
+    /// callback(done, total) is called every time an asset gets ready or fails
+    pub fn set_progress_callback<F>(&mut self, f: F)
+    where
+        F: FnMut(usize, usize) + 'static,
+    {
+        self.on_progress = Some(Box::new(f));
+    }
+
+    /// (done, total), failed assets count as done
+    pub fn progress(&mut self) -> (usize, usize) {
+        let done = self
+            .assets
+            .iter_mut()
+            .map(|a| a.get_state())
+            .filter(|st| matches!(st, AssetState::Ready | AssetState::Failed))
+            .count();
+        (done, self.assets.len())
+    }
+
+    /// true when no asset is still loading
+    pub fn is_loaded(&mut self) -> bool {
+        let (done, total) = self.progress();
+        done == total
+    }
+
+    pub fn get_state(&mut self, loc: &str) -> Option<AssetState> {
+        self.get(loc).map(|a| a.get_state())
+    }
+
+    /// locations of assets failed to load
+    pub fn failed(&mut self) -> Vec<String> {
+        self.assets
+            .iter_mut()
+            .filter_map(|a| {
+                (a.get_state() == AssetState::Failed).then(|| a.get_base().location.clone())
+            })
+            .collect()
+    }
+
+    fn notify_progress(&mut self) {
+        if self.on_progress.is_some() {
+            let (done, total) = self.progress();
+            if let Some(f) = &mut self.on_progress {
+                f(done, total);
+            }
+        }
+    }
+
+    /// starts watching loaded .pix/.txt/.esc files, desktop only
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn enable_hot_reload(&mut self) {
+        if self.hot_reload.is_none() {
+            info!("asset hot reload enabled");
+            self.hot_reload = Some(HotReload {
+                mtimes: HashMap::new(),
+                last_check: Instant::now(),
+            });
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn disable_hot_reload(&mut self) {
+        self.hot_reload = None;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_reloadable(loc: &str) -> bool {
+        let ll = loc.to_lowercase();
+        ll.ends_with(".pix") || ll.ends_with(".txt") || ll.ends_with(".esc")
+    }
+
+    /// called by Game every tick, re-parses changed files and
+    /// emits "Asset.Reload" if any
+    pub fn check_hot_reload(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Some(hr) = &mut self.hot_reload else {
+                return;
+            };
+            if hr.last_check.elapsed() < Duration::from_secs_f32(HOT_RELOAD_INTERVAL) {
+                return;
+            }
+            hr.last_check = Instant::now();
+            let mut changed = vec![];
+            for loc in self.assets_index.keys().filter(|l| Self::is_reloadable(l)) {
+                let Ok(mt) = std::fs::metadata(get_abs_path(loc)).and_then(|m| m.modified())
+                else {
+                    continue;
+                };
+                // first poll only records the time
+                if let Some(old) = hr.mtimes.insert(loc.clone(), mt) {
+                    if old != mt {
+                        changed.push(loc.clone());
+                    }
+                }
+            }
+            self.reloaded.clear();
+            for loc in changed {
+                match std::fs::read(get_abs_path(&loc)) {
+                    Ok(fdata) => {
+                        info!("asset reload:{:?}", loc);
+                        self.set_data(&loc, &fdata[..]);
+                        self.reloaded.push(loc);
+                    }
+                    Err(e) => info!("asset reload error:{:?} {}", loc, e),
+                }
+            }
+            if !self.reloaded.is_empty() {
+                event_emit("Asset.Reload");
+            }
         }
     }
 
@@ -121,25 +323,38 @@ impl AssetManager {
             Some(_) => {}
             None => {
                 let mut ab = AssetBase::new(t, loc);
+                let packed = self.read_bundled(loc);
                 #[cfg(target_arch = "wasm32")]
-                {
+                if packed.is_none() {
                     js_load_asset(loc);
                 }
-                #[cfg(not(target_arch = "wasm32"))]
-                {}
                 let mut ast: Box<dyn Asset> = match t {
                     AssetType::ImgPix => Box::new(PixAsset::new(ab)),
                     AssetType::ImgEsc => Box::new(EscAsset::new(ab)),
                     AssetType::ImgSsf => Box::new(SeqFrameAsset::new(ab)),
+                    AssetType::ImgAse => Box::new(AseAsset::new(ab)),
+                    AssetType::ImgPng => Box::new(PngAsset::new(ab)),
+                    AssetType::ImgAns => Box::new(AnsAsset::new(ab)),
+                    AssetType::ImgXp => Box::new(XpAsset::new(ab)),
+                    AssetType::TiledMap => Box::new(TiledMapAsset::new(ab)),
                 };
                 self.assets.push(ast);
                 self.assets_index.insert(loc.to_string(), self.assets.len());
-                #[cfg(not(target_arch = "wasm32"))]
-                {
-                    let fpstr = get_abs_path(loc);
-                    info!("asset load:{:?}", fpstr);
-                    let fdata = std::fs::read(fpstr).expect("read file error");
-                    self.set_data(loc, &fdata[..]);
+                if let Some(data) = packed {
+                    self.set_data(loc, &data[..]);
+                } else {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        let fpstr = get_abs_path(loc);
+                        info!("asset load:{:?}", fpstr);
+                        match std::fs::read(fpstr) {
+                            Ok(fdata) => self.set_data(loc, &fdata[..]),
+                            Err(e) => {
+                                info!("asset load error:{:?} {}", loc, e);
+                                self.set_failed(loc);
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -154,20 +369,29 @@ impl AssetManager {
 
     // when in web mode, this method is called to get the resources ready after async load of js resources
     // when in other modes, this method is called after finishing reading files
-    // refer to rust-pixel/web-templates/index.js
+    // refer to rust-pixel/web-templates/host.js
     pub fn set_data(&mut self, loc: &str, data: &[u8]) {
         if let Some(idx) = self.assets_index.get(loc) {
             self.assets[*idx - 1].set_data(data);
             self.assets[*idx - 1].set_state(AssetState::Parsing);
             self.assets[*idx - 1].parse();
             self.assets[*idx - 1].set_state(AssetState::Ready);
+            self.notify_progress();
+        }
+    }
+
+    // in web mode, called when fetch fails
+    pub fn set_failed(&mut self, loc: &str) {
+        if let Some(idx) = self.assets_index.get(loc) {
+            self.assets[*idx - 1].set_state(AssetState::Failed);
+            self.notify_progress();
         }
     }
 }
 
-// refer to rust-pixel/web-templates/index.js
+// refer to rust-pixel/web-templates/host.js
 #[cfg(target_arch = "wasm32")]
-#[wasm_bindgen(raw_module = "/index.js")]
+#[wasm_bindgen(raw_module = "/host.js")]
 extern "C" {
     fn js_load_asset(url: &str);
 }
@@ -0,0 +1,164 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! pixel-pack bundles many asset files into one zstd compressed file.
+//!
+//! Layout: "PXPK", u32 (le) index length, bincode index, then the data
+//! of every file compressed separately, so any file can be read alone.
+//! Names are paths relative to the assets directory, using '/'.
+
+use ruzstd::{
+    decoding::StreamingDecoder,
+    encoding::{compress_to_vec, CompressionLevel},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, io::Read};
+
+const MAGIC: &[u8; 4] = b"PXPK";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PackEntry {
+    name: String,
+    offset: u64,
+    size: u32,
+    raw_size: u32,
+}
+
+/// a loaded bundle, files are decompressed on get
+pub struct Pack {
+    data: Vec<u8>,
+    // data offset of the first file
+    base: usize,
+    entries: HashMap<String, PackEntry>,
+}
+
+impl Pack {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 8 || &data[0..4] != MAGIC {
+            return Err("not a pixel-pack".to_string());
+        }
+        let ilen = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let base = 8 + ilen;
+        if data.len() < base {
+            return Err("pixel-pack index truncated".to_string());
+        }
+        let index: Vec<PackEntry> =
+            bincode::deserialize(&data[8..base]).map_err(|e| e.to_string())?;
+        Ok(Self {
+            data: data.to_vec(),
+            base,
+            entries: index.into_iter().map(|e| (e.name.clone(), e)).collect(),
+        })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.keys().map(|k| k.as_str()).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// decompressed content of a file
+    pub fn get(&self, name: &str) -> Option<Vec<u8>> {
+        let e = self.entries.get(name)?;
+        let start = self.base + e.offset as usize;
+        let src = self.data.get(start..start + e.size as usize)?;
+        let mut out = Vec::with_capacity(e.raw_size as usize);
+        StreamingDecoder::new(src)
+            .ok()?
+            .read_to_end(&mut out)
+            .ok()?;
+        Some(out)
+    }
+}
+
+#[derive(Default)]
+pub struct PackBuilder {
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl PackBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) {
+        self.files.push((name.replace('\\', "/"), data.to_vec()));
+    }
+
+    /// adds all files under dir, named by their path relative to dir
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_dir(&mut self, dir: &str) -> std::io::Result<()> {
+        let root = std::path::Path::new(dir);
+        let mut todo = vec![root.to_path_buf()];
+        while let Some(d) = todo.pop() {
+            for entry in std::fs::read_dir(&d)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    todo.push(path);
+                } else if let Ok(rel) = path.strip_prefix(root) {
+                    let data = std::fs::read(&path)?;
+                    self.add(&rel.to_string_lossy(), &data);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut index = vec![];
+        let mut blob = vec![];
+        for (name, data) in &self.files {
+            let c = compress_to_vec(&data[..], CompressionLevel::Fastest);
+            index.push(PackEntry {
+                name: name.clone(),
+                offset: blob.len() as u64,
+                size: c.len() as u32,
+                raw_size: data.len() as u32,
+            });
+            blob.extend(c);
+        }
+        let ib = bincode::serialize(&index).unwrap();
+        let mut out = MAGIC.to_vec();
+        out.extend((ib.len() as u32).to_le_bytes());
+        out.extend(ib);
+        out.extend(blob);
+        out
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.build())
+    }
+}
+
+/// pack name of an asset location, the part after the last "assets/"
+pub fn pack_key(loc: &str) -> String {
+    let l = loc.replace('\\', "/");
+    match l.rfind("assets/") {
+        Some(p) => l[p + 7..].to_string(),
+        None => l,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_roundtrip() {
+        let mut pb = PackBuilder::new();
+        let pix = "width=2,height=1,texture=255\n1,2,0,0 3,4,0,0\n".repeat(20);
+        pb.add("pix/a.pix", pix.as_bytes());
+        pb.add("b.txt", b"hello");
+        let data = pb.build();
+        assert!(data.len() < pix.len());
+        let p = Pack::from_bytes(&data).unwrap();
+        assert_eq!(p.get("pix/a.pix").unwrap(), pix.as_bytes());
+        assert_eq!(p.get("b.txt").unwrap(), b"hello");
+        assert!(p.get("c.txt").is_none());
+        assert_eq!(pack_key("/home/x/games/snake/assets/pix/a.pix"), "pix/a.pix");
+        assert!(Pack::from_bytes(b"nope").is_err());
+    }
+}
@@ -4,19 +4,256 @@
 
 //! audio provides playing music and sound effect, reference
 //! https://docs.rs/rodio
+//!
+//! context.audio plays one shot sound effects and a music track, streamed
+//! from file, looping and crossfading when a new track starts. Volumes are
+//! set per group (master, music, sfx) and fades advance in Audio::update,
+//! called by the game loop every frame.
+//! Sounds generated by Sound play in the sfx group.
+//! Desktop (terminal and sdl) uses rodio, wasm uses WebAudio through
+//! js_audio_* in web-templates/host.js.
 
-
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
 use crate::util::get_abs_path;
 #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
-use rodio::{source::Source, Decoder, OutputStream, OutputStreamHandle};
+use log::info;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+use rodio::{source::Source, Decoder, OutputStream, OutputStreamHandle, Sink};
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
 use std::fs::File;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
 use std::io::BufReader;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
 
-pub struct Audio {
+// WebAudio voices, refer to rust-pixel/web-templates/host.js
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(raw_module = "/host.js")]
+extern "C" {
+    fn js_audio_play(id: u32, url: &str, looping: bool, volume: f32);
+    fn js_audio_volume(id: u32, volume: f32);
+    fn js_audio_pause(id: u32, paused: bool);
+    fn js_audio_stop(id: u32);
+    fn js_audio_stream(id: u32, volume: f32);
+    fn js_audio_queue(id: u32, pcm: &[f32], rate: u32);
+}
+
+// output rate of tracker modules
+const TRACKER_RATE: u32 = 44100;
+
+// streams a ModPlayer to rodio
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+struct ModSource {
+    player: ModPlayer,
+    buf: Vec<f32>,
+    pos: usize,
+    len: usize,
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+impl ModSource {
+    fn new(player: ModPlayer) -> Self {
+        Self {
+            player,
+            buf: vec![0.0; 2048],
+            pos: 0,
+            len: 0,
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+impl Iterator for ModSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pos >= self.len {
+            self.len = self.player.render(&mut self.buf) * 2;
+            self.pos = 0;
+            if self.len == 0 {
+                return None;
+            }
+        }
+        self.pos += 1;
+        Some(self.buf[self.pos - 1])
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+impl Source for ModSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.player.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+// output rate of generated sounds
+const SYNTH_RATE: u32 = 22050;
+
+// plays mono samples of a generated sound
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+struct PcmSource {
+    pcm: std::vec::IntoIter<f32>,
+    rate: u32,
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+impl Iterator for PcmSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.pcm.next()
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+impl Source for PcmSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+/// Volume groups, the volume of a sound is its group volume by master
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioGroup {
+    Master,
+    Music,
+    Sfx,
+}
+
+// a playing sound of the backend
+struct Voice {
     #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
-    _out: OutputStream,
+    sink: Sink,
+    #[cfg(target_arch = "wasm32")]
+    id: u32,
+}
+
+impl Voice {
+    #[allow(unused_variables)]
+    fn set_volume(&self, v: f32) {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.sink.set_volume(v);
+        #[cfg(target_arch = "wasm32")]
+        js_audio_volume(self.id, v);
+    }
+
+    #[allow(unused_variables)]
+    fn set_paused(&self, paused: bool) {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        if paused {
+            self.sink.pause();
+        } else {
+            self.sink.play();
+        }
+        #[cfg(target_arch = "wasm32")]
+        js_audio_pause(self.id, paused);
+    }
+
+    fn stop(&self) {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.sink.stop();
+        #[cfg(target_arch = "wasm32")]
+        js_audio_stop(self.id);
+    }
+
+    // web voices stop by themselves, their end is not reported
+    fn is_done(&self) -> bool {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        {
+            self.sink.empty()
+        }
+        #[cfg(any(target_os = "android", target_os = "ios", target_arch = "wasm32"))]
+        {
+            true
+        }
+    }
+}
+
+// a music track with its fade level
+struct Track {
+    name: String,
+    voice: Voice,
+    level: f32,
+    target: f32,
+    // level change per second
+    rate: f32,
+    // tracker module streamed to js and seconds queued ahead
+    #[cfg(target_arch = "wasm32")]
+    stream: Option<(ModPlayer, f32)>,
+}
+
+impl Track {
+    // starts silent and fades in
+    fn new(name: &str, voice: Voice, fade: f32) -> Self {
+        let mut t = Self {
+            name: name.to_string(),
+            voice,
+            level: 0.0,
+            target: 0.0,
+            rate: 0.0,
+            #[cfg(target_arch = "wasm32")]
+            stream: None,
+        };
+        t.fade_to(1.0, fade);
+        t
+    }
+
+    fn fade_to(&mut self, target: f32, secs: f32) {
+        self.target = target;
+        if secs > 0.0 {
+            self.rate = 1.0 / secs;
+        } else {
+            self.level = target;
+        }
+    }
+
+    fn advance(&mut self, dt: f32) {
+        let step = self.rate * dt;
+        self.level = if self.level < self.target {
+            (self.level + step).min(self.target)
+        } else {
+            (self.level - step).max(self.target)
+        };
+    }
+}
+
+pub struct Audio {
     #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
-    handle: OutputStreamHandle,
+    out: Option<(OutputStream, OutputStreamHandle)>,
+    #[cfg(target_arch = "wasm32")]
+    next_id: u32,
+    master: f32,
+    music_volume: f32,
+    sfx_volume: f32,
+    muted: bool,
+    music: Option<Track>,
+    // old tracks fading out
+    fading: Vec<Track>,
+    sfx: Vec<Voice>,
 }
 
 impl Default for Audio {
@@ -26,30 +263,299 @@ impl Default for Audio {
 }
 
 impl Audio {
+    /// a machine without sound device gives a silent Audio
     pub fn new() -> Self {
-        #[cfg(any(target_os = "android", target_os = "ios", target_arch = "wasm32"))]
+        Self {
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            out: OutputStream::try_default()
+                .map_err(|e| info!("audio output not available:{}", e))
+                .ok(),
+            #[cfg(target_arch = "wasm32")]
+            next_id: 0,
+            master: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            muted: false,
+            music: None,
+            fading: vec![],
+            sfx: vec![],
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn open(&mut self, fpath: &str, looping: bool, volume: f32) -> Option<Voice> {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        {
+            let (_, handle) = self.out.as_ref()?;
+            let fpstr = get_abs_path(fpath);
+            let file = match File::open(&fpstr) {
+                Ok(f) => BufReader::new(f),
+                Err(e) => {
+                    info!("audio open {} failed:{}", fpstr, e);
+                    return None;
+                }
+            };
+            let sink = Sink::try_new(handle).ok()?;
+            sink.set_volume(volume);
+            let ok = if looping {
+                Decoder::new_looped(file).map(|s| sink.append(s.convert_samples::<f32>()))
+            } else {
+                Decoder::new(file).map(|s| sink.append(s.convert_samples::<f32>()))
+            };
+            if let Err(e) = ok {
+                info!("audio decode {} failed:{}", fpstr, e);
+                return None;
+            }
+            Some(Voice { sink })
+        }
+        #[cfg(target_arch = "wasm32")]
         {
-            Self {}
+            self.next_id += 1;
+            js_audio_play(self.next_id, fpath, looping, volume);
+            Some(Voice { id: self.next_id })
         }
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        {
+            None
+        }
+    }
+
+    /// plays a file once, or a looping music track without fade
+    pub fn play_file(&mut self, fpath: &str, is_loop: bool) {
+        if is_loop {
+            self.play_music(fpath, true, 0.0);
+        } else {
+            self.play_sfx(fpath);
+        }
+    }
+
+    /// plays a generated sound in the sfx group
+    pub fn play_sound(&mut self, sound: &Sound) {
+        let pcm = sound.render(SYNTH_RATE);
+        let v = self.group_volume(AudioGroup::Sfx);
         #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
         {
-            let (s, h) = OutputStream::try_default().unwrap();
-            Self { _out: s, handle: h }
+            let Some((_, handle)) = self.out.as_ref() else {
+                return;
+            };
+            let Ok(sink) = Sink::try_new(handle) else {
+                return;
+            };
+            sink.set_volume(v);
+            sink.append(PcmSource {
+                pcm: pcm.into_iter(),
+                rate: SYNTH_RATE,
+            });
+            self.sfx.push(Voice { sink });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.next_id += 1;
+            js_audio_stream(self.next_id, v);
+            let stereo: Vec<f32> = pcm.iter().flat_map(|s| [*s, *s]).collect();
+            js_audio_queue(self.next_id, &stereo, SYNTH_RATE);
         }
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        let _ = (pcm, v);
     }
-    #[allow(unused)]
-    pub fn play_file(&self, fpath: &str, is_loop: bool) {
-        let fpstr = get_abs_path(fpath);
-        let file = BufReader::new(File::open(fpstr).unwrap());
+
+    /// plays a sound effect once
+    pub fn play_sfx(&mut self, fpath: &str) {
+        let v = self.group_volume(AudioGroup::Sfx);
+        if let Some(voice) = self.open(fpath, false, v) {
+            self.sfx.push(voice);
+        }
+    }
+
+    /// streams a music track, the current one fades out while the new one
+    /// fades in during fade seconds
+    /// .mod files are played by ModPlayer on desktop, on wasm use
+    /// play_tracker_data
+    pub fn play_music(&mut self, fpath: &str, looping: bool, fade: f32) {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        if fpath.to_lowercase().ends_with(".mod") {
+            let fpstr = get_abs_path(fpath);
+            let res = std::fs::read(&fpstr)
+                .map_err(|e| e.to_string())
+                .and_then(|d| self.play_tracker_data(fpath, &d, looping, fade));
+            if let Err(e) = res {
+                info!("audio tracker {} failed:{}", fpstr, e);
+            }
+            return;
+        }
+        self.stop_music(fade);
+        if let Some(voice) = self.open(fpath, looping, 0.0) {
+            self.music = Some(Track::new(fpath, voice, fade));
+            self.apply_volumes();
+        }
+    }
+
+    /// plays a tracker module from memory as music, name is returned
+    /// by music()
+    #[allow(unused_variables)]
+    pub fn play_tracker_data(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        looping: bool,
+        fade: f32,
+    ) -> Result<(), String> {
+        let mut mp = ModPlayer::new(data, TRACKER_RATE)?;
+        mp.looping = looping;
+        self.stop_music(fade);
         #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
         {
-            if is_loop {
-                let source = Decoder::new(file).unwrap().repeat_infinite();
-                self.handle.play_raw(source.convert_samples()).unwrap();
-            } else {
-                let source = Decoder::new(file).unwrap();
-                self.handle.play_raw(source.convert_samples()).unwrap();
+            let Some((_, handle)) = self.out.as_ref() else {
+                return Ok(());
             };
+            let sink = Sink::try_new(handle).map_err(|e| e.to_string())?;
+            sink.set_volume(0.0);
+            sink.append(ModSource::new(mp));
+            self.music = Some(Track::new(name, Voice { sink }, fade));
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.next_id += 1;
+            js_audio_stream(self.next_id, 0.0);
+            let mut t = Track::new(name, Voice { id: self.next_id }, fade);
+            t.stream = Some((mp, 0.0));
+            self.music = Some(t);
+        }
+        self.apply_volumes();
+        Ok(())
+    }
+
+    pub fn stop_music(&mut self, fade: f32) {
+        if let Some(mut t) = self.music.take() {
+            t.fade_to(0.0, fade);
+            self.fading.push(t);
+            self.update(0.0);
+        }
+    }
+
+    pub fn set_music_paused(&mut self, paused: bool) {
+        if let Some(t) = &self.music {
+            t.voice.set_paused(paused);
+        }
+    }
+
+    /// file of the current music track
+    pub fn music(&self) -> Option<&str> {
+        self.music.as_ref().map(|t| t.name.as_str())
+    }
+
+    pub fn stop_all(&mut self) {
+        self.stop_music(0.0);
+        for v in self.sfx.drain(..) {
+            v.stop();
+        }
+    }
+
+    pub fn volume(&self, g: AudioGroup) -> f32 {
+        match g {
+            AudioGroup::Master => self.master,
+            AudioGroup::Music => self.music_volume,
+            AudioGroup::Sfx => self.sfx_volume,
         }
     }
+
+    pub fn set_volume(&mut self, g: AudioGroup, v: f32) {
+        let v = v.clamp(0.0, 1.0);
+        match g {
+            AudioGroup::Master => self.master = v,
+            AudioGroup::Music => self.music_volume = v,
+            AudioGroup::Sfx => self.sfx_volume = v,
+        }
+        self.apply_volumes();
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply_volumes();
+    }
+
+    // effective volume of a group
+    fn group_volume(&self, g: AudioGroup) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume(g) * self.master
+        }
+    }
+
+    fn apply_volumes(&self) {
+        let mv = self.group_volume(AudioGroup::Music);
+        for t in self.music.iter().chain(self.fading.iter()) {
+            t.voice.set_volume(t.level * mv);
+        }
+        let sv = self.group_volume(AudioGroup::Sfx);
+        for v in &self.sfx {
+            v.set_volume(sv);
+        }
+    }
+
+    /// advances fades and drops finished sounds, called every frame
+    pub fn update(&mut self, dt: f32) {
+        for t in self.music.iter_mut().chain(self.fading.iter_mut()) {
+            t.advance(dt);
+            #[cfg(target_arch = "wasm32")]
+            if let Some((mp, ahead)) = &mut t.stream {
+                // keep some audio queued in js, rendering 0.1s at a time
+                *ahead -= dt;
+                let mut buf = vec![0.0; TRACKER_RATE as usize / 10 * 2];
+                while *ahead < 0.3 && !mp.is_finished() {
+                    let n = mp.render(&mut buf);
+                    js_audio_queue(t.voice.id, &buf[..n * 2], TRACKER_RATE);
+                    *ahead += n as f32 / TRACKER_RATE as f32;
+                }
+            }
+        }
+        self.fading.retain(|t| {
+            let done = t.level <= 0.0;
+            if done {
+                t.voice.stop();
+            }
+            !done
+        });
+        self.sfx.retain(|v| !v.is_done());
+        self.apply_volumes();
+    }
+}
+
+mod tracker;
+pub use tracker::*;
+
+mod synth;
+pub use synth::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audio_track_fade() {
+        let voice = Voice {
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            sink: Sink::new_idle().0,
+        };
+        let mut t = Track::new("m", voice, 2.0);
+        t.advance(0.5);
+        assert!((t.level - 0.25).abs() < 1e-6);
+        t.advance(5.0);
+        assert_eq!(t.level, 1.0);
+        t.fade_to(0.0, 0.0);
+        t.advance(0.0);
+        assert_eq!(t.level, 0.0);
+
+        let mut a = Audio::new();
+        a.set_volume(AudioGroup::Music, 1.5);
+        a.set_volume(AudioGroup::Master, 0.5);
+        assert_eq!(a.group_volume(AudioGroup::Music), 0.5);
+        a.set_muted(true);
+        assert_eq!(a.group_volume(AudioGroup::Sfx), 0.0);
+    }
 }
@@ -0,0 +1,254 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Sound describes a generated sound effect, PICO-8 style: a square,
+//! triangle, saw, sine or noise oscillator shaped by an ADSR envelope,
+//! with pitch slide, vibrato and arpeggio. Games make blips, jumps and
+//! explosions from code, no audio asset is shipped.
+//! Audio::play_sound renders and plays it, natively and on the web.
+
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wave {
+    /// square with duty cycle 0.0 to 1.0
+    Square(f32),
+    Triangle,
+    Saw,
+    Sine,
+    /// 15 bit lfsr noise, freq sets the shift rate
+    Noise,
+}
+
+/// Envelope times in seconds, sustain is a level 0.0 to 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adsr {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self {
+            attack: 0.005,
+            decay: 0.05,
+            sustain: 0.7,
+            release: 0.05,
+        }
+    }
+}
+
+impl Adsr {
+    /// level at t seconds of a note held for held seconds
+    pub fn level(&self, t: f32, held: f32) -> f32 {
+        let on = |t: f32| {
+            if t < self.attack {
+                t / self.attack
+            } else if t < self.attack + self.decay {
+                1.0 - (1.0 - self.sustain) * (t - self.attack) / self.decay
+            } else {
+                self.sustain
+            }
+        };
+        if t < held {
+            on(t)
+        } else if self.release > 0.0 && t < held + self.release {
+            on(held) * (1.0 - (t - held) / self.release)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// frequency of a midi note, 69 is A4 440hz
+pub fn midi_freq(note: f32) -> f32 {
+    440.0 * 2f32.powf((note - 69.0) / 12.0)
+}
+
+/// frequency of a note name like "C4", "F#3" or "Bb5"
+pub fn note_freq(name: &str) -> Option<f32> {
+    let b = name.as_bytes();
+    let base = match b.first()?.to_ascii_uppercase() {
+        b'C' => 0,
+        b'D' => 2,
+        b'E' => 4,
+        b'F' => 5,
+        b'G' => 7,
+        b'A' => 9,
+        b'B' => 11,
+        _ => return None,
+    };
+    let (acc, rest) = match b.get(1) {
+        Some(b'#') => (1, &name[2..]),
+        Some(b'b') => (-1, &name[2..]),
+        _ => (0, &name[1..]),
+    };
+    let octave: i32 = rest.parse().ok()?;
+    Some(midi_freq(((octave + 1) * 12 + base + acc) as f32))
+}
+
+/// A generated sound effect
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sound {
+    pub wave: Wave,
+    pub freq: f32,
+    /// hz per second
+    pub slide: f32,
+    /// depth in semitones and rate in hz
+    pub vibrato: (f32, f32),
+    /// semitone offsets cycled every arp_step seconds
+    pub arp: Vec<i32>,
+    pub arp_step: f32,
+    pub adsr: Adsr,
+    /// seconds the note is held, release follows
+    pub duration: f32,
+    pub volume: f32,
+}
+
+impl Sound {
+    pub fn new(wave: Wave) -> Self {
+        Self {
+            wave,
+            freq: 440.0,
+            slide: 0.0,
+            vibrato: (0.0, 0.0),
+            arp: vec![],
+            arp_step: 0.05,
+            adsr: Adsr::default(),
+            duration: 0.2,
+            volume: 0.5,
+        }
+    }
+
+    pub fn freq(mut self, hz: f32) -> Self {
+        self.freq = hz;
+        self
+    }
+
+    /// sets freq by note name, unknown names are ignored
+    pub fn note(mut self, name: &str) -> Self {
+        if let Some(f) = note_freq(name) {
+            self.freq = f;
+        }
+        self
+    }
+
+    pub fn slide(mut self, hz_per_sec: f32) -> Self {
+        self.slide = hz_per_sec;
+        self
+    }
+
+    pub fn vibrato(mut self, semitones: f32, rate: f32) -> Self {
+        self.vibrato = (semitones, rate);
+        self
+    }
+
+    pub fn arp(mut self, semitones: &[i32], step: f32) -> Self {
+        self.arp = semitones.to_vec();
+        self.arp_step = step;
+        self
+    }
+
+    pub fn adsr(mut self, attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        self.adsr = Adsr {
+            attack,
+            decay,
+            sustain,
+            release,
+        };
+        self
+    }
+
+    pub fn duration(mut self, secs: f32) -> Self {
+        self.duration = secs;
+        self
+    }
+
+    pub fn volume(mut self, v: f32) -> Self {
+        self.volume = v.clamp(0.0, 1.0);
+        self
+    }
+
+    /// seconds including release
+    pub fn length(&self) -> f32 {
+        self.duration + self.adsr.release
+    }
+
+    fn freq_at(&self, t: f32) -> f32 {
+        let mut f = (self.freq + self.slide * t).max(1.0);
+        if !self.arp.is_empty() && self.arp_step > 0.0 {
+            let i = (t / self.arp_step) as usize % self.arp.len();
+            f *= 2f32.powf(self.arp[i] as f32 / 12.0);
+        }
+        let (depth, rate) = self.vibrato;
+        if depth != 0.0 {
+            f *= 2f32.powf(depth * (2.0 * PI * rate * t).sin() / 12.0);
+        }
+        f
+    }
+
+    /// renders mono samples at rate
+    pub fn render(&self, rate: u32) -> Vec<f32> {
+        let n = (self.length() * rate as f32) as usize;
+        let dt = 1.0 / rate as f32;
+        let mut out = Vec::with_capacity(n);
+        let mut phase = 0.0f32;
+        let mut lfsr: u16 = 1;
+        let mut noise = 1.0;
+        for i in 0..n {
+            let t = i as f32 * dt;
+            let v = match self.wave {
+                Wave::Square(duty) => {
+                    if phase < duty {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                Wave::Saw => 2.0 * phase - 1.0,
+                Wave::Sine => (2.0 * PI * phase).sin(),
+                Wave::Noise => noise,
+            };
+            out.push(v * self.adsr.level(t, self.duration) * self.volume);
+            phase += self.freq_at(t) * dt;
+            while phase >= 1.0 {
+                phase -= 1.0;
+                // nes style noise, shifted once per period
+                let bit = (lfsr ^ (lfsr >> 1)) & 1;
+                lfsr = (lfsr >> 1) | (bit << 14);
+                noise = if lfsr & 1 == 1 { 1.0 } else { -1.0 };
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sound_renders_envelope() {
+        assert_eq!(note_freq("A4"), Some(440.0));
+        assert!((note_freq("C4").unwrap() - 261.63).abs() < 0.01);
+        assert_eq!(note_freq("Bb3"), note_freq("A#3"));
+        assert_eq!(note_freq("H2"), None);
+
+        let s = Sound::new(Wave::Square(0.5))
+            .freq(1000.0)
+            .adsr(0.01, 0.0, 1.0, 0.1)
+            .duration(0.1)
+            .volume(0.5);
+        let pcm = s.render(8000);
+        assert_eq!(pcm.len(), 1600);
+        assert!(pcm.iter().all(|v| v.abs() <= 0.5));
+        assert_eq!(pcm[400].abs(), 0.5);
+        assert!(pcm[1590].abs() < 0.01);
+
+        let n = Sound::new(Wave::Noise).freq(4000.0).render(8000);
+        assert!(n.iter().any(|v| *v > 0.0) && n.iter().any(|v| *v < 0.0));
+    }
+}
@@ -0,0 +1,453 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! ModPlayer plays ProTracker MOD modules (M.K., FLT4, xCHN, xxCH),
+//! rendering interleaved stereo f32 samples. A few kilobytes of module
+//! give minutes of music, which suits the retro look and small wasm builds.
+//!
+//! Supported effects: arpeggio, portamento, tone portamento, vibrato,
+//! volume slide, sample offset, position jump, pattern break, set volume,
+//! set speed/tempo, fine slides and note cut. XM and S3M are not supported.
+//!
+//! Audio::play_music plays .mod files with it, Audio::play_tracker_data
+//! plays modules from memory, e.g. include_bytes! on wasm.
+
+// Amiga PAL clock / 2
+const PAULA_CLOCK: f64 = 3_546_894.6;
+const ROWS: usize = 64;
+
+const VIBRATO_SINE: [u8; 32] = [
+    0, 24, 49, 74, 97, 120, 141, 161, 180, 197, 212, 224, 235, 244, 250, 253, 255, 253, 250, 244,
+    235, 224, 212, 197, 180, 161, 141, 120, 97, 74, 49, 24,
+];
+
+#[derive(Debug, Clone, Default)]
+struct ModSample {
+    data: Vec<i8>,
+    finetune: i8,
+    volume: u8,
+    loop_start: usize,
+    loop_len: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Note {
+    sample: u8,
+    period: u16,
+    effect: u8,
+    param: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Channel {
+    sample: usize,
+    active: bool,
+    pos: f64,
+    period: f64,
+    // period after arpeggio and vibrato for this tick
+    out_period: f64,
+    volume: i32,
+    finetune: i8,
+    porta_target: f64,
+    porta_speed: u8,
+    vib_speed: u8,
+    vib_depth: u8,
+    vib_pos: u8,
+    offset: u8,
+    note: Note,
+}
+
+/// A MOD module player
+#[derive(Debug, Clone)]
+pub struct ModPlayer {
+    title: String,
+    samples: Vec<ModSample>,
+    orders: Vec<u8>,
+    restart: usize,
+    patterns: Vec<Note>,
+    nchan: usize,
+    rate: u32,
+    channels: Vec<Channel>,
+    order: usize,
+    row: usize,
+    tick: u32,
+    speed: u32,
+    tempo: u32,
+    tick_left: usize,
+    next: Option<(usize, usize)>,
+    finished: bool,
+    /// restart at the end of the song
+    pub looping: bool,
+}
+
+fn be16(d: &[u8], i: usize) -> usize {
+    (d[i] as usize) << 8 | d[i + 1] as usize
+}
+
+fn channels_of(sig: &[u8]) -> Option<usize> {
+    match sig {
+        b"M.K." | b"M!K!" | b"FLT4" | b"4CHN" => Some(4),
+        b"FLT8" => Some(8),
+        [n, b'C', b'H', b'N'] if n.is_ascii_digit() => Some((n - b'0') as usize),
+        [a, b, b'C', b'H'] if a.is_ascii_digit() && b.is_ascii_digit() => {
+            Some(((a - b'0') * 10 + b - b'0') as usize)
+        }
+        _ => None,
+    }
+}
+
+impl ModPlayer {
+    /// parses a module, rate is the output sample rate
+    pub fn new(data: &[u8], rate: u32) -> Result<Self, String> {
+        if data.len() < 1084 {
+            return Err("mod too short".to_string());
+        }
+        let nchan = channels_of(&data[1080..1084])
+            .filter(|n| *n > 0 && *n <= 32)
+            .ok_or("not a 31 sample mod")?;
+        let title = String::from_utf8_lossy(&data[0..20])
+            .trim_end_matches('\0')
+            .to_string();
+        let song_len = (data[950] as usize).clamp(1, 128);
+        let orders = data[952..952 + song_len].to_vec();
+        let npat = data[952..1080].iter().max().map_or(0, |m| *m as usize) + 1;
+        let pat_size = ROWS * nchan * 4;
+        let mut off = 1084;
+        if data.len() < off + npat * pat_size {
+            return Err("mod patterns truncated".to_string());
+        }
+        let mut patterns = Vec::with_capacity(npat * ROWS * nchan);
+        for n in data[off..off + npat * pat_size].chunks(4) {
+            patterns.push(Note {
+                sample: (n[0] & 0xf0) | (n[2] >> 4),
+                period: (((n[0] & 0x0f) as u16) << 8) | n[1] as u16,
+                effect: n[2] & 0x0f,
+                param: n[3],
+            });
+        }
+        off += npat * pat_size;
+        let mut samples = vec![];
+        for i in 0..31 {
+            let h = 20 + i * 30;
+            let len = be16(data, h + 22) * 2;
+            let ft = (data[h + 24] & 0x0f) as i8;
+            let mut s = ModSample {
+                finetune: if ft > 7 { ft - 16 } else { ft },
+                volume: data[h + 25].min(64),
+                loop_start: be16(data, h + 26) * 2,
+                loop_len: be16(data, h + 28) * 2,
+                data: vec![],
+            };
+            let end = (off + len).min(data.len());
+            s.data = data[off.min(end)..end].iter().map(|b| *b as i8).collect();
+            if s.loop_start + s.loop_len > s.data.len() {
+                s.loop_len = s.data.len().saturating_sub(s.loop_start);
+            }
+            off += len;
+            samples.push(s);
+        }
+        let restart = data[951] as usize;
+        let mut mp = Self {
+            title,
+            samples,
+            orders,
+            restart: if restart < song_len { restart } else { 0 },
+            patterns,
+            nchan,
+            rate: rate.max(1),
+            channels: vec![Channel::default(); nchan],
+            order: 0,
+            row: 0,
+            tick: 0,
+            speed: 6,
+            tempo: 125,
+            tick_left: 0,
+            next: None,
+            finished: false,
+            looping: true,
+        };
+        mp.process_row();
+        Ok(mp)
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn channels(&self) -> usize {
+        self.nchan
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// end of the song reached while not looping
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn frames_per_tick(&self) -> usize {
+        (self.rate as usize * 5 / (self.tempo as usize * 2)).max(1)
+    }
+
+    fn note_period(&self, period: u16, finetune: i8) -> f64 {
+        period as f64 * 2f64.powf(-(finetune as f64) / 96.0)
+    }
+
+    fn process_row(&mut self) {
+        self.tick = 0;
+        self.tick_left = self.frames_per_tick();
+        let pat = self.orders[self.order] as usize;
+        for c in 0..self.nchan {
+            let n = self.patterns[(pat * ROWS + self.row) * self.nchan + c];
+            let mut ch = std::mem::take(&mut self.channels[c]);
+            ch.note = n;
+            if n.sample > 0 && (n.sample as usize) <= self.samples.len() {
+                ch.sample = n.sample as usize - 1;
+                let s = &self.samples[ch.sample];
+                ch.volume = s.volume as i32;
+                ch.finetune = s.finetune;
+            }
+            let (x, y) = (n.param >> 4, n.param & 0x0f);
+            if n.period > 0 {
+                let p = self.note_period(n.period, ch.finetune);
+                if n.effect == 3 || n.effect == 5 {
+                    ch.porta_target = p;
+                } else {
+                    ch.period = p;
+                    ch.pos = 0.0;
+                    ch.active = true;
+                    ch.vib_pos = 0;
+                    if n.effect == 9 {
+                        if n.param > 0 {
+                            ch.offset = n.param;
+                        }
+                        ch.pos = ch.offset as f64 * 256.0;
+                    }
+                }
+            }
+            match n.effect {
+                0x3 if n.param > 0 => ch.porta_speed = n.param,
+                0x4 => {
+                    if x > 0 {
+                        ch.vib_speed = x;
+                    }
+                    if y > 0 {
+                        ch.vib_depth = y;
+                    }
+                }
+                0xb => self.next = Some((n.param as usize, 0)),
+                0xc => ch.volume = n.param.min(64) as i32,
+                0xd => {
+                    let order = self.next.map_or(self.order + 1, |(o, _)| o);
+                    self.next = Some((order, (x * 10 + y) as usize % ROWS));
+                }
+                0xf if n.param > 0 => {
+                    if n.param < 32 {
+                        self.speed = n.param as u32;
+                    } else {
+                        self.tempo = n.param as u32;
+                        self.tick_left = self.frames_per_tick();
+                    }
+                }
+                0xe => match x {
+                    0x1 => ch.period = (ch.period - y as f64).max(113.0),
+                    0x2 => ch.period = (ch.period + y as f64).min(856.0),
+                    0xa => ch.volume = (ch.volume + y as i32).min(64),
+                    0xb => ch.volume = (ch.volume - y as i32).max(0),
+                    0xc if y == 0 => ch.volume = 0,
+                    _ => {}
+                },
+                _ => {}
+            }
+            ch.out_period = ch.period;
+            self.channels[c] = ch;
+        }
+    }
+
+    fn vibrato(ch: &mut Channel) {
+        let d = VIBRATO_SINE[(ch.vib_pos & 31) as usize] as f64 * ch.vib_depth as f64 / 128.0;
+        ch.out_period = if ch.vib_pos & 32 == 0 {
+            ch.period + d
+        } else {
+            ch.period - d
+        };
+        ch.vib_pos = (ch.vib_pos + ch.vib_speed) & 63;
+    }
+
+    fn tone_porta(ch: &mut Channel) {
+        let s = ch.porta_speed as f64;
+        if ch.porta_target <= 0.0 {
+            return;
+        }
+        ch.period = if ch.period < ch.porta_target {
+            (ch.period + s).min(ch.porta_target)
+        } else {
+            (ch.period - s).max(ch.porta_target)
+        };
+    }
+
+    fn process_tick(&mut self) {
+        let tick = self.tick;
+        for ch in self.channels.iter_mut() {
+            let n = ch.note;
+            let (x, y) = (n.param >> 4, n.param & 0x0f);
+            ch.out_period = ch.period;
+            let vol_slide = |ch: &mut Channel| {
+                ch.volume = if x > 0 {
+                    (ch.volume + x as i32).min(64)
+                } else {
+                    (ch.volume - y as i32).max(0)
+                };
+            };
+            match n.effect {
+                0x0 if n.param > 0 => {
+                    let semi = [0, x, y][(tick % 3) as usize];
+                    ch.out_period = ch.period / 2f64.powf(semi as f64 / 12.0);
+                }
+                0x1 => ch.period = (ch.period - n.param as f64).max(113.0),
+                0x2 => ch.period = (ch.period + n.param as f64).min(856.0),
+                0x3 => Self::tone_porta(ch),
+                0x4 => Self::vibrato(ch),
+                0x5 => {
+                    Self::tone_porta(ch);
+                    vol_slide(ch);
+                }
+                0x6 => {
+                    Self::vibrato(ch);
+                    vol_slide(ch);
+                }
+                0xa => vol_slide(ch),
+                0xe if x == 0xc && y as u32 == tick => ch.volume = 0,
+                _ => {}
+            }
+            if matches!(n.effect, 0x1..=0x3 | 0x5) {
+                ch.out_period = ch.period;
+            }
+        }
+    }
+
+    fn next_tick(&mut self) {
+        self.tick += 1;
+        if self.tick < self.speed {
+            self.tick_left = self.frames_per_tick();
+            self.process_tick();
+            return;
+        }
+        let (order, row) = match self.next.take() {
+            Some(n) => n,
+            None if self.row + 1 < ROWS => (self.order, self.row + 1),
+            None => (self.order + 1, 0),
+        };
+        if order >= self.orders.len() {
+            if !self.looping {
+                self.finished = true;
+                return;
+            }
+            self.order = self.restart;
+        } else {
+            self.order = order;
+        }
+        self.row = row;
+        self.process_row();
+    }
+
+    fn mix_frame(&mut self) -> (f32, f32) {
+        let (mut l, mut r) = (0.0, 0.0);
+        for (i, ch) in self.channels.iter_mut().enumerate() {
+            if !ch.active || ch.out_period <= 0.0 {
+                continue;
+            }
+            let s = &self.samples[ch.sample];
+            let p = ch.pos as usize;
+            if s.loop_len > 2 {
+                let end = s.loop_start + s.loop_len;
+                if p >= end {
+                    ch.pos -= ((p - end) / s.loop_len + 1) as f64 * s.loop_len as f64;
+                }
+            } else if p >= s.data.len() {
+                ch.active = false;
+                continue;
+            }
+            let p = ch.pos as usize;
+            let Some(v) = s.data.get(p) else {
+                ch.active = false;
+                continue;
+            };
+            let v = *v as f32 / 128.0 * ch.volume as f32 / 64.0;
+            ch.pos += PAULA_CLOCK / ch.out_period / self.rate as f64;
+            // amiga panning L R R L, blended a little
+            if i % 4 == 0 || i % 4 == 3 {
+                l += v * 0.75;
+                r += v * 0.25;
+            } else {
+                l += v * 0.25;
+                r += v * 0.75;
+            }
+        }
+        let g = 2.0 / self.nchan as f32;
+        (l * g, r * g)
+    }
+
+    /// fills out with interleaved stereo samples, returns the number of
+    /// frames rendered, less than requested when the song finished
+    pub fn render(&mut self, out: &mut [f32]) -> usize {
+        let mut n = 0;
+        for f in out.chunks_mut(2) {
+            if self.finished {
+                break;
+            }
+            let (l, r) = self.mix_frame();
+            f[0] = l;
+            if f.len() > 1 {
+                f[1] = r;
+            }
+            n += 1;
+            self.tick_left -= 1;
+            if self.tick_left == 0 {
+                self.next_tick();
+            }
+        }
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one looping square sample, one note then speed 1
+    fn tiny_mod() -> Vec<u8> {
+        let mut d = vec![0u8; 1084];
+        d[..4].copy_from_slice(b"test");
+        let h = 20;
+        d[h + 23] = 16; // 32 bytes
+        d[h + 25] = 64;
+        d[h + 29] = 16; // loop whole sample
+        d[950] = 1;
+        d[1080..1084].copy_from_slice(b"M.K.");
+        let mut pat = vec![0u8; ROWS * 4 * 4];
+        // sample 1, period 428, effect F speed 1
+        pat[..4].copy_from_slice(&[0x01, 0xac, 0x1f, 0x01]);
+        d.extend(pat);
+        d.extend((0..32).map(|i| if i < 16 { 100u8 } else { 156 }));
+        d
+    }
+
+    #[test]
+    fn mod_player_renders_song() {
+        assert!(ModPlayer::new(&[0; 2000], 8000).is_err());
+        let mut mp = ModPlayer::new(&tiny_mod(), 8000).unwrap();
+        assert_eq!(mp.title(), "test");
+        assert_eq!(mp.channels(), 4);
+        mp.looping = false;
+        let mut buf = vec![0.0; 16000 * 2];
+        let n = mp.render(&mut buf);
+        // 64 rows of one tick, 160 frames per tick at 125 bpm
+        assert_eq!(n, 64 * 160);
+        assert!(mp.is_finished());
+        assert!(buf[..200].iter().any(|s| s.abs() > 0.1));
+        assert!(buf[..n * 2].iter().all(|s| s.abs() <= 1.0));
+    }
+}
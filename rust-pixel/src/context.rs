@@ -4,12 +4,38 @@
 //! Context encapsulates several public variables
 //! including stage，state，input events, etc.
 //! For simplicity, state is set to u8 type，you can create your own states using enums in your games.
-//! Context also integrates an RNG for user's convenience
+//! Context also integrates an RNG for user's convenience,
+//! a KeyTracker for key repeats, chords and double taps,
+//! players to route input devices to numbered players,
+//! the GameLoop policy for fixed timestep and frame limiting,
+//! SceneControl to switch scenes of a SceneStack,
+//! time_scale / paused for slow motion and pause menus,
+//! storage for save slots, replay to record and play input,
+//! timers for named, repeating timers,
+//! the world of the optional ecs, audio for sound effects and music,
+//! net for websocket connections, debug for the debug overlay,
+//! console for the drop-down developer console
+//! theme for the named colors of the current theme,
+//! config for the settings of the pixel_game! config block,
+//! host for the page hosting a wasm build
+//! and focus for the keyboard focus of sprites and other elements
 //! An render adapter is also provided
 //! to make it compatible with web, SDL, or terminal modes.
 //! Finally, an asset_manager is included as well.
 
-use crate::{asset::AssetManager, event::Event, render::adapter::Adapter, util::Rand};
+use crate::{
+    asset::AssetManager,
+    audio::Audio,
+    ecs::World,
+    event::{Event, KeyTracker, Players, Replay, ReplayControl, TimerSet},
+    game::{Console, DebugHud, GameConfig, GameLoop, Host, SceneControl},
+    network::Network,
+    render::{adapter::Adapter, style::Themes},
+    storage::{Storage, StorageError},
+    ui::FocusRing,
+    util::Rand,
+};
+use serde::{de::DeserializeOwned, Serialize};
 
 #[cfg(all(not(target_arch = "wasm32"), not(feature = "sdl")))]
 use crate::render::adapter::cross::CrosstermAdapter;
@@ -29,6 +55,26 @@ pub struct Context {
     pub rand: Rand,
     pub asset_manager: AssetManager,
     pub input_events: Vec<Event>,
+    pub keys: KeyTracker,
+    pub players: Players,
+    pub game_loop: GameLoop,
+    pub scene: SceneControl,
+    /// scales dt of model updates and timers, 0.5 is slow motion
+    pub time_scale: f32,
+    /// model dt and timers stop, input and render go on
+    pub paused: bool,
+    pub storage: Storage,
+    pub replay: ReplayControl,
+    pub timers: TimerSet,
+    pub world: World,
+    pub audio: Audio,
+    pub net: Network,
+    pub debug: DebugHud,
+    pub console: Console,
+    pub theme: Themes,
+    pub config: GameConfig,
+    pub host: Host,
+    pub focus: FocusRing,
     pub adapter: Box<dyn Adapter>,
 }
 
@@ -43,6 +89,24 @@ impl Context {
             rand: Rand::new(),
             asset_manager: AssetManager::new(),
             input_events: vec![],
+            keys: KeyTracker::new(),
+            players: Players::new(),
+            game_loop: GameLoop::new(),
+            scene: SceneControl::default(),
+            time_scale: 1.0,
+            paused: false,
+            storage: Storage::new(name, project_path),
+            replay: ReplayControl::default(),
+            timers: TimerSet::new(),
+            world: World::new(),
+            audio: Audio::new(),
+            net: Network::new(),
+            debug: DebugHud::new(),
+            console: Console::new(),
+            theme: Themes::new(),
+            config: GameConfig::default(),
+            host: Host::new(),
+            focus: FocusRing::new(),
             #[cfg(target_arch = "wasm32")]
             adapter: Box::new(WebAdapter::new(prefix, name, project_path)),
             #[cfg(all(not(target_arch = "wasm32"), feature = "sdl"))]
@@ -54,5 +118,54 @@ impl Context {
 
     pub fn set_asset_path(&mut self, project_path: &str) {
         self.project_path = project_path.to_string();
+        let version = self.storage.version;
+        self.storage = Storage::new(&self.game_name, project_path);
+        self.storage.version = version;
+    }
+
+    /// saves state to a named slot
+    pub fn save_slot<T: Serialize>(&self, slot: &str, state: &T) -> Result<(), StorageError> {
+        self.storage.save(slot, state)
+    }
+
+    /// loads state of a named slot, fails on missing, corrupt or newer saves
+    pub fn load_slot<T: DeserializeOwned>(&self, slot: &str) -> Result<T, StorageError> {
+        self.storage.load(slot)
+    }
+
+    /// time_scale honoring pause
+    pub fn effective_time_scale(&self) -> f32 {
+        if self.paused {
+            0.0
+        } else {
+            self.time_scale.max(0.0)
+        }
+    }
+
+    /// records input of following frames along with the rand state
+    pub fn replay_record(&mut self) {
+        self.replay.record(&self.rand);
+    }
+
+    /// plays a replay, live input goes to replay.live_events meanwhile
+    pub fn replay_play(&mut self, replay: Replay) {
+        self.replay.play(replay, &mut self.rand);
+    }
+
+    /// stops recording or playing, returns the replay being recorded
+    pub fn replay_stop(&mut self) -> Option<Replay> {
+        self.replay.stop()
+    }
+
+    /// text in clipboard, on web and terminal only text set or pasted
+    /// by this app is known, as reading the system clipboard is async or
+    /// not supported there
+    pub fn clipboard_get(&mut self) -> Option<String> {
+        self.adapter.clipboard_get()
+    }
+
+    /// copies text to the system clipboard, terminals use OSC 52
+    pub fn clipboard_set(&mut self, s: &str) {
+        self.adapter.clipboard_set(s);
     }
 }
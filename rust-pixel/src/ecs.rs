@@ -0,0 +1,446 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! A small Entity-Component-System for games outgrowing a single Model.
+//! World keeps entities and their components, it lives in context.world.
+//! Systems are closures on Context, grouped by Stage in a Schedule which
+//! is run from the Model hooks. EcsModel does so for a game made of
+//! systems only. On the render side SpriteSync keeps a sprite per entity
+//! with a given component, like panel.draw_objpool does for GameObjPool.
+//!
+//! Using it is optional, games may still keep all data in their Model.
+
+use crate::{
+    context::Context,
+    game::Model,
+    render::{panel::Panel, sprite::Sprite},
+};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+/// An entity id, gen tells reused ids apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    pub id: u32,
+    pub gen: u32,
+}
+
+trait AnyStore {
+    fn remove_id(&mut self, id: u32);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+// components of one type indexed by entity id
+struct Store<T> {
+    items: Vec<Option<T>>,
+}
+
+impl<T: 'static> AnyStore for Store<T> {
+    fn remove_id(&mut self, id: u32) {
+        if let Some(c) = self.items.get_mut(id as usize) {
+            *c = None;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Entities and their components
+#[derive(Default)]
+pub struct World {
+    gens: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    stores: HashMap<TypeId, Box<dyn AnyStore>>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        if let Some(id) = self.free.pop() {
+            self.alive[id as usize] = true;
+            Entity {
+                id,
+                gen: self.gens[id as usize],
+            }
+        } else {
+            self.gens.push(0);
+            self.alive.push(true);
+            Entity {
+                id: self.gens.len() as u32 - 1,
+                gen: 0,
+            }
+        }
+    }
+
+    /// removes an entity and all its components
+    pub fn despawn(&mut self, e: Entity) -> bool {
+        if !self.is_alive(e) {
+            return false;
+        }
+        for s in self.stores.values_mut() {
+            s.remove_id(e.id);
+        }
+        let i = e.id as usize;
+        self.alive[i] = false;
+        self.gens[i] = self.gens[i].wrapping_add(1);
+        self.free.push(e.id);
+        true
+    }
+
+    pub fn is_alive(&self, e: Entity) -> bool {
+        let i = e.id as usize;
+        i < self.alive.len() && self.alive[i] && self.gens[i] == e.gen
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// removes all entities
+    pub fn clear(&mut self) {
+        for e in self.entities().collect::<Vec<_>>() {
+            self.despawn(e);
+        }
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        (0..self.alive.len())
+            .filter(|i| self.alive[*i])
+            .map(|i| Entity {
+                id: i as u32,
+                gen: self.gens[i],
+            })
+    }
+
+    fn entity(&self, id: usize) -> Entity {
+        Entity {
+            id: id as u32,
+            gen: self.gens[id],
+        }
+    }
+
+    fn store<T: 'static>(&self) -> Option<&Store<T>> {
+        self.stores
+            .get(&TypeId::of::<T>())
+            .and_then(|s| s.as_any().downcast_ref())
+    }
+
+    fn store_mut<T: 'static>(&mut self) -> Option<&mut Store<T>> {
+        self.stores
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|s| s.as_any_mut().downcast_mut())
+    }
+
+    /// adds or replaces a component, ignored for dead entities
+    pub fn insert<T: 'static>(&mut self, e: Entity, c: T) {
+        if !self.is_alive(e) {
+            return;
+        }
+        let st = self
+            .stores
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Store::<T> { items: vec![] }));
+        let st: &mut Store<T> = st.as_any_mut().downcast_mut().unwrap();
+        let i = e.id as usize;
+        if st.items.len() <= i {
+            st.items.resize_with(i + 1, || None);
+        }
+        st.items[i] = Some(c);
+    }
+
+    pub fn remove<T: 'static>(&mut self, e: Entity) -> Option<T> {
+        if !self.is_alive(e) {
+            return None;
+        }
+        self.store_mut::<T>()?.items.get_mut(e.id as usize)?.take()
+    }
+
+    pub fn get<T: 'static>(&self, e: Entity) -> Option<&T> {
+        if !self.is_alive(e) {
+            return None;
+        }
+        self.store::<T>()?.items.get(e.id as usize)?.as_ref()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, e: Entity) -> Option<&mut T> {
+        if !self.is_alive(e) {
+            return None;
+        }
+        self.store_mut::<T>()?
+            .items
+            .get_mut(e.id as usize)?
+            .as_mut()
+    }
+
+    pub fn has<T: 'static>(&self, e: Entity) -> bool {
+        self.get::<T>(e).is_some()
+    }
+
+    /// entities with a T component
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> + '_ {
+        self.store::<T>()
+            .into_iter()
+            .flat_map(|s| s.items.iter().enumerate())
+            .filter_map(|(i, c)| c.as_ref().map(|c| (self.entity(i), c)))
+    }
+
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> + '_ {
+        let gens = &self.gens;
+        self.stores
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|s| s.as_any_mut().downcast_mut::<Store<T>>())
+            .into_iter()
+            .flat_map(|s| s.items.iter_mut().enumerate())
+            .filter_map(move |(i, c)| {
+                c.as_mut().map(|c| {
+                    let e = Entity {
+                        id: i as u32,
+                        gen: gens[i],
+                    };
+                    (e, c)
+                })
+            })
+    }
+
+    /// entities with both an A and a B component
+    pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> + '_ {
+        self.query::<A>()
+            .filter_map(|(e, a)| self.get::<B>(e).map(|b| (e, a, b)))
+    }
+
+    /// calls f for entities with both an A and a B component, A and B
+    /// must be different types
+    pub fn each2_mut<A: 'static, B: 'static>(&mut self, mut f: impl FnMut(Entity, &mut A, &mut B)) {
+        assert_ne!(TypeId::of::<A>(), TypeId::of::<B>());
+        // take store A out, so that B can be borrowed too
+        let Some(mut sa) = self.stores.remove(&TypeId::of::<A>()) else {
+            return;
+        };
+        if let Some(a) = sa.as_any_mut().downcast_mut::<Store<A>>() {
+            for (i, ca) in a.items.iter_mut().enumerate() {
+                let Some(ca) = ca else {
+                    continue;
+                };
+                let e = self.entity(i);
+                if let Some(cb) = self.get_mut::<B>(e) {
+                    f(e, ca, cb);
+                }
+            }
+        }
+        self.stores.insert(TypeId::of::<A>(), sa);
+    }
+}
+
+/// Model hook a system runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Event,
+    Timer,
+    Input,
+    Auto,
+}
+
+type System = Box<dyn FnMut(&mut Context, f32)>;
+type Setup = Box<dyn FnOnce(&mut Context)>;
+
+/// Systems by stage, run in the order added
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<(Stage, String, System)>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, stage: Stage, name: &str, f: impl FnMut(&mut Context, f32) + 'static) {
+        self.systems.push((stage, name.to_string(), Box::new(f)));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.systems.retain(|(_, n, _)| n != name);
+    }
+
+    /// runs systems of a stage, call from the matching Model hook
+    pub fn run(&mut self, stage: Stage, ctx: &mut Context, dt: f32) {
+        for (s, _, f) in self.systems.iter_mut() {
+            if *s == stage {
+                f(ctx, dt);
+            }
+        }
+    }
+}
+
+/// A Model made of systems, handle_event/timer/input/auto run the
+/// systems of the matching stage
+pub struct EcsModel {
+    pub schedule: Schedule,
+    setup: Option<Setup>,
+}
+
+impl EcsModel {
+    /// setup spawns the initial entities at init
+    pub fn new(schedule: Schedule, setup: impl FnOnce(&mut Context) + 'static) -> Self {
+        Self {
+            schedule,
+            setup: Some(Box::new(setup)),
+        }
+    }
+}
+
+impl Model for EcsModel {
+    fn init(&mut self, ctx: &mut Context) {
+        if let Some(f) = self.setup.take() {
+            f(ctx);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut Context, dt: f32) {
+        self.schedule.run(Stage::Event, ctx, dt);
+    }
+
+    fn handle_timer(&mut self, ctx: &mut Context, dt: f32) {
+        self.schedule.run(Stage::Timer, ctx, dt);
+    }
+
+    fn handle_input(&mut self, ctx: &mut Context, dt: f32) {
+        self.schedule.run(Stage::Input, ctx, dt);
+    }
+
+    fn handle_auto(&mut self, ctx: &mut Context, dt: f32) {
+        self.schedule.run(Stage::Auto, ctx, dt);
+    }
+}
+
+/// Keeps a sprite of a panel layer for each entity with a component,
+/// sprites are created up front and reused
+pub struct SpriteSync {
+    pub layer: String,
+    pub prefix: String,
+    pub max_count: usize,
+    map: HashMap<Entity, usize>,
+}
+
+impl SpriteSync {
+    pub fn new(layer: &str, prefix: &str, max_count: usize) -> Self {
+        Self {
+            layer: layer.to_string(),
+            prefix: prefix.to_string(),
+            max_count,
+            map: HashMap::new(),
+        }
+    }
+
+    fn tag(&self, i: usize) -> String {
+        format!("{}{}", self.prefix, i)
+    }
+
+    /// creates max_count hidden sprites and calls f to init them
+    pub fn create(&mut self, panel: &mut Panel, w: u16, h: u16, mut f: impl FnMut(&mut Sprite)) {
+        for i in 0..self.max_count {
+            let mut sp = Sprite::new(0, 0, w, h);
+            f(&mut sp);
+            sp.set_hidden(true);
+            panel.add_layer_sprite(sp, &self.layer, &self.tag(i));
+        }
+    }
+
+    /// shows a sprite for each entity with a T component and calls f to
+    /// draw it, sprites of other entities are hidden
+    pub fn sync<T: 'static>(
+        &mut self,
+        panel: &mut Panel,
+        world: &World,
+        mut f: impl FnMut(&mut Sprite, Entity, &T),
+    ) {
+        let gone: Vec<Entity> = self
+            .map
+            .keys()
+            .filter(|e| !world.has::<T>(**e))
+            .copied()
+            .collect();
+        for e in gone {
+            let i = self.map.remove(&e).unwrap();
+            panel
+                .get_layer_sprite(&self.layer, &self.tag(i))
+                .set_hidden(true);
+        }
+        for (e, c) in world.query::<T>() {
+            let i = match self.map.get(&e) {
+                Some(i) => *i,
+                None => {
+                    let used: Vec<usize> = self.map.values().copied().collect();
+                    let Some(i) = (0..self.max_count).find(|i| !used.contains(i)) else {
+                        continue;
+                    };
+                    self.map.insert(e, i);
+                    i
+                }
+            };
+            let sp = panel.get_layer_sprite(&self.layer, &self.tag(i));
+            sp.set_hidden(false);
+            f(sp, e, c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Pos(f32, f32);
+    struct Vel(f32, f32);
+
+    #[test]
+    fn world_components_and_schedule() {
+        let mut ctx = Context::new("games", "test", "");
+        let a = ctx.world.spawn();
+        let b = ctx.world.spawn();
+        ctx.world.insert(a, Pos(0.0, 0.0));
+        ctx.world.insert(a, Vel(2.0, 1.0));
+        ctx.world.insert(b, Pos(5.0, 5.0));
+
+        let mut sched = Schedule::new();
+        sched.add(Stage::Auto, "move", |ctx, dt| {
+            ctx.world.each2_mut::<Pos, Vel>(|_, p, v| {
+                p.0 += v.0 * dt;
+                p.1 += v.1 * dt;
+            });
+        });
+        sched.run(Stage::Input, &mut ctx, 1.0);
+        sched.run(Stage::Auto, &mut ctx, 0.5);
+        assert_eq!(ctx.world.get::<Pos>(a), Some(&Pos(1.0, 0.5)));
+        assert_eq!(ctx.world.get::<Pos>(b), Some(&Pos(5.0, 5.0)));
+        assert_eq!(ctx.world.query2::<Pos, Vel>().count(), 1);
+
+        assert!(ctx.world.despawn(a));
+        let c = ctx.world.spawn();
+        assert_eq!(c.id, a.id);
+        assert!(!ctx.world.is_alive(a));
+        assert!(ctx.world.get::<Pos>(c).is_none());
+        for (_, p) in ctx.world.query_mut::<Pos>() {
+            p.0 = 0.0;
+        }
+        assert_eq!(ctx.world.query::<Pos>().count(), 1);
+        assert_eq!(ctx.world.len(), 2);
+    }
+}
@@ -10,40 +10,67 @@
 //! one event in this class namely Event, describing I/Os from keyboard and mouse
 //! Input events triggered by renders such as web, sdl or cross are converted here to
 //! unified Event
+//!
+//! events may carry data, event_emit_data gives each subscriber a copy which
+//! it takes with event_check_data or event_drain. EventChannel binds an event
+//! name to its data type. Data not taken is dropped after the next frame.
+//! Subscribers with a higher priority can consume an event, so that lower
+//! ones do not see it
 
 
 use crate::GAME_FRAME;
 use lazy_static::lazy_static;
-use serde::Serialize;
-use std::{collections::HashMap, sync::Mutex};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
 
 // uses global Mutex variable
 lazy_static! {
     pub static ref GAME_TIMER: Mutex<Timers> = Mutex::new(Timers::new());
-    pub static ref EVENT_CENTER: Mutex<HashMap<String, HashMap<String, bool>>> =
+    pub static ref EVENT_CENTER: Mutex<HashMap<String, HashMap<String, Subscriber>>> =
         Mutex::new(HashMap::new());
 }
 
+// frame counter of event payloads, see event_new_frame
+static EVENT_FRAME: AtomicU32 = AtomicU32::new(0);
+
+/// A callback registered to an event, with its pending flag and payloads
+#[derive(Debug, Default)]
+pub struct Subscriber {
+    pub flag: bool,
+    /// higher priority subscribers may consume events before lower ones
+    pub priority: i32,
+    // frame of emit and bincode encoded data
+    payloads: VecDeque<(u32, Vec<u8>)>,
+}
+
 /// A global HashMap is used to save callbacks of events
 pub fn event_register(event: &str, func: &str) {
+    event_register_priority(event, func, 0);
+}
+
+/// registers a callback with priority, used by event_consume
+pub fn event_register_priority(event: &str, func: &str, priority: i32) {
     let mut ec = EVENT_CENTER.lock().unwrap();
-    match ec.get_mut(event) {
-        Some(ht) => {
-            ht.insert(func.to_string(), false);
-        }
-        None => {
-            let mut h: HashMap<String, bool> = HashMap::new();
-            h.insert(func.to_string(), false);
-            ec.insert(event.to_string(), h);
-        }
-    }
+    let sub = ec
+        .entry(event.to_string())
+        .or_default()
+        .entry(func.to_string())
+        .or_default();
+    sub.priority = priority;
 }
 
 pub fn event_check(event: &str, func: &str) -> bool {
     let mut ec = EVENT_CENTER.lock().unwrap();
-    if let Some(ht) = ec.get_mut(event) { if let Some(flag) = ht.get_mut(func) {
-        if *flag {
-            *flag = false;
+    if let Some(ht) = ec.get_mut(event) { if let Some(sub) = ht.get_mut(func) {
+        if sub.flag {
+            sub.flag = false;
             return true;
         }
     } }
@@ -52,14 +79,149 @@ pub fn event_check(event: &str, func: &str) -> bool {
 
 pub fn event_emit(event: &str) {
     if let Some(ht) = EVENT_CENTER.lock().unwrap().get_mut(event) {
-        for value in ht.values_mut() {
-            if !(*value) {
-                *value = true;
+        for sub in ht.values_mut() {
+            if !sub.flag {
+                sub.flag = true;
             }
         }
     }
 }
 
+/// emits an event carrying data, every subscriber gets its own copy
+pub fn event_emit_data<T: Serialize>(event: &str, data: &T) {
+    let bytes = bincode::serialize(data).unwrap();
+    let frame = EVENT_FRAME.load(Ordering::Relaxed);
+    if let Some(ht) = EVENT_CENTER.lock().unwrap().get_mut(event) {
+        for sub in ht.values_mut() {
+            sub.flag = true;
+            sub.payloads.push_back((frame, bytes.clone()));
+        }
+    }
+}
+
+/// takes the oldest payload of an event for func, also clears the flag
+/// when no payloads are left
+pub fn event_check_data<T: DeserializeOwned>(event: &str, func: &str) -> Option<T> {
+    let mut ec = EVENT_CENTER.lock().unwrap();
+    let sub = ec.get_mut(event)?.get_mut(func)?;
+    let (_, bytes) = sub.payloads.pop_front()?;
+    if sub.payloads.is_empty() {
+        sub.flag = false;
+    }
+    bincode::deserialize(&bytes).ok()
+}
+
+/// takes all payloads of an event for func, oldest first
+pub fn event_drain<T: DeserializeOwned>(event: &str, func: &str) -> Vec<T> {
+    let mut ec = EVENT_CENTER.lock().unwrap();
+    let Some(sub) = ec.get_mut(event).and_then(|ht| ht.get_mut(func)) else {
+        return vec![];
+    };
+    sub.flag = false;
+    sub.payloads
+        .drain(..)
+        .filter_map(|(_, b)| bincode::deserialize(&b).ok())
+        .collect()
+}
+
+/// marks an event as handled by func, pending flags and payloads of
+/// subscribers with lower priority are dropped
+pub fn event_consume(event: &str, func: &str) {
+    let mut ec = EVENT_CENTER.lock().unwrap();
+    let Some(ht) = ec.get_mut(event) else {
+        return;
+    };
+    let Some(p) = ht.get(func).map(|s| s.priority) else {
+        return;
+    };
+    for sub in ht.values_mut().filter(|s| s.priority < p) {
+        sub.flag = false;
+        sub.payloads.clear();
+    }
+}
+
+/// callbacks of an event, highest priority first
+pub fn event_subscribers(event: &str) -> Vec<String> {
+    let ec = EVENT_CENTER.lock().unwrap();
+    let mut subs: Vec<(&String, i32)> = ec
+        .get(event)
+        .map(|ht| ht.iter().map(|(f, s)| (f, s.priority)).collect())
+        .unwrap_or_default();
+    subs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    subs.into_iter().map(|(f, _)| f.clone()).collect()
+}
+
+/// called by the game loop every frame. Payloads live through the frame
+/// they are emitted and the next one, so Renders see data emitted by
+/// Models in the same frame and Models see data emitted by Renders,
+/// older payloads nobody took are dropped
+pub fn event_new_frame() {
+    let frame = EVENT_FRAME.fetch_add(1, Ordering::Relaxed) + 1;
+    for ht in EVENT_CENTER.lock().unwrap().values_mut() {
+        for sub in ht.values_mut() {
+            sub.payloads.retain(|(f, _)| frame.wrapping_sub(*f) <= 1);
+        }
+    }
+}
+
+/// An event name bound to its payload type
+///
+/// ```
+/// use rust_pixel::event::EventChannel;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// pub struct DealInfo {
+///     seat: u8,
+///     card: u8,
+/// }
+///
+/// pub const DEAL: EventChannel<DealInfo> = EventChannel::new("Poker.Deal");
+///
+/// DEAL.register("draw_deal");
+/// DEAL.emit(&DealInfo { seat: 1, card: 12 });
+/// for d in DEAL.drain("draw_deal") {
+///     assert_eq!(d.card, 12);
+/// }
+/// ```
+pub struct EventChannel<T> {
+    pub name: &'static str,
+    _t: PhantomData<fn(T)>,
+}
+
+impl<T: Serialize + DeserializeOwned> EventChannel<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _t: PhantomData,
+        }
+    }
+
+    pub fn register(&self, func: &str) {
+        event_register(self.name, func);
+    }
+
+    pub fn register_priority(&self, func: &str, priority: i32) {
+        event_register_priority(self.name, func, priority);
+    }
+
+    pub fn emit(&self, data: &T) {
+        event_emit_data(self.name, data);
+    }
+
+    pub fn check(&self, func: &str) -> Option<T> {
+        event_check_data(self.name, func)
+    }
+
+    pub fn drain(&self, func: &str) -> Vec<T> {
+        event_drain(self.name, func)
+    }
+
+    pub fn consume(&self, func: &str) {
+        event_consume(self.name, func);
+    }
+}
+
 pub fn timer_register(name: &str, time: f32, func: &str) {
     GAME_TIMER.lock().unwrap().register(name, time, func);
 }
@@ -99,6 +261,12 @@ pub fn timer_update() {
     GAME_TIMER.lock().unwrap().update()
 }
 
+/// advances timers by dt seconds, called by Model::update with the
+/// scaled dt, so timers stop in pause and slow down with time_scale
+pub fn timer_advance(dt: f32) {
+    GAME_TIMER.lock().unwrap().advance(dt)
+}
+
 pub struct Timer {
     time: u32,
     count: u32,
@@ -108,6 +276,8 @@ pub struct Timer {
 #[derive(Default)]
 pub struct Timers {
     pub timers: HashMap<String, Timer>,
+    // fraction of a frame not yet advanced
+    frame_acc: f32,
 }
 
 impl Timers {
@@ -204,6 +374,17 @@ impl Timers {
         }
     }
 
+    /// runs update once per GAME_FRAME frame in dt seconds
+    pub fn advance(&mut self, dt: f32) {
+        self.frame_acc += dt.max(0.0) * GAME_FRAME as f32;
+        // tolerate float error, 1/60 * 60 may be 0.99999
+        let n = (self.frame_acc + 0.001) as u32;
+        self.frame_acc = (self.frame_acc - n as f32).max(0.0);
+        for _ in 0..n {
+            self.update();
+        }
+    }
+
     pub fn update(&mut self) {
         for (name, timer) in &mut self.timers {
             if timer.time > 0 {
@@ -216,5 +397,84 @@ impl Timers {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_payloads_priority_and_draining() {
+        const CH: EventChannel<(u8, String)> = EventChannel::new("T.Payload");
+        CH.register("render");
+        CH.register_priority("ui", 10);
+        assert_eq!(event_subscribers(CH.name), vec!["ui", "render"]);
+
+        CH.emit(&(1, "a".to_string()));
+        CH.emit(&(2, "b".to_string()));
+        assert_eq!(CH.check("render"), Some((1, "a".to_string())));
+        assert!(event_check(CH.name, "render"));
+        assert_eq!(CH.drain("render"), vec![(2, "b".to_string())]);
+
+        // ui takes the event, render never sees it
+        assert_eq!(CH.drain("ui").len(), 2);
+        CH.emit(&(3, "c".to_string()));
+        assert_eq!(CH.check("ui").unwrap().0, 3);
+        CH.consume("ui");
+        assert!(!event_check(CH.name, "render"));
+        assert!(CH.drain("render").is_empty());
+
+        // unread payloads live for two frames
+        CH.emit(&(4, "d".to_string()));
+        event_new_frame();
+        assert_eq!(CH.check("render").unwrap().0, 4);
+        event_new_frame();
+        event_new_frame();
+        assert!(CH.drain("ui").is_empty());
+    }
+
+    #[test]
+    fn timers_advance_with_scaled_dt() {
+        let mut ts = Timers::new();
+        ts.register("T.Scaled", 0.5, "t");
+        ts.fire("T.Scaled", 0u8);
+        let frame = 1.0 / GAME_FRAME as f32;
+        for _ in 0..10 {
+            ts.advance(frame * 0.5);
+        }
+        assert_eq!(ts.rstage("T.Scaled"), 5);
+        // paused
+        ts.advance(0.0);
+        assert_eq!(ts.rstage("T.Scaled"), 5);
+        ts.advance(1.0);
+        assert_eq!(ts.stage("T.Scaled"), 0);
+    }
+}
+
 mod input;
 pub use input::*;
+
+mod gamepad;
+pub use gamepad::*;
+
+mod mouse;
+pub use mouse::*;
+
+mod touch;
+pub use touch::*;
+
+mod input_map;
+pub use input_map::*;
+
+mod text_input;
+pub use text_input::*;
+
+mod keys;
+pub use keys::*;
+
+mod replay;
+pub use replay::*;
+
+mod timer_set;
+pub use timer_set::*;
+
+mod players;
+pub use players::*;
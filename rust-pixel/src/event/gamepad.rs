@@ -0,0 +1,265 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! GamepadMap maps gamepad buttons, stick directions and keyboard keys
+//! to logical buttons, so a game reads "jump" rather than GamepadButton::A
+//! and works the same with a keyboard.
+//!
+//! Terminal mode has no gamepads and no key release events, so keyboard
+//! bindings only show up in just_pressed there.
+
+use crate::event::{
+    Event, GamepadAxis, GamepadButton, GamepadEventKind, KeyCode, KeyEventKind,
+};
+use std::collections::{HashMap, HashSet};
+
+pub struct GamepadMap {
+    buttons: HashMap<GamepadButton, String>,
+    // (axis, positive direction)
+    axes: HashMap<(GamepadAxis, bool), String>,
+    keys: HashMap<KeyCode, String>,
+    /// axis values below this count as 0
+    pub dead_zone: i16,
+    pads: Vec<u32>,
+    // logical buttons held, with the source holding it
+    down: HashSet<(String, u32, u8)>,
+    just_down: HashSet<String>,
+    just_up: HashSet<String>,
+    axis_values: HashMap<(u32, GamepadAxis), i16>,
+}
+
+// sources of a held logical button
+const SRC_BUTTON: u8 = 0;
+const SRC_AXIS: u8 = 1;
+const SRC_KEY: u8 = 2;
+
+impl Default for GamepadMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GamepadMap {
+    pub fn new() -> Self {
+        Self {
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+            keys: HashMap::new(),
+            dead_zone: 8000,
+            pads: vec![],
+            down: HashSet::new(),
+            just_down: HashSet::new(),
+            just_up: HashSet::new(),
+            axis_values: HashMap::new(),
+        }
+    }
+
+    /// up/down/left/right from dpad, left stick, arrows and wasd,
+    /// a/b/x/y/start/back from buttons, z/x/c/v, enter and esc
+    pub fn standard() -> Self {
+        use GamepadButton::*;
+        let mut m = Self::new();
+        for (b, n) in [
+            (A, "a"),
+            (B, "b"),
+            (X, "x"),
+            (Y, "y"),
+            (Start, "start"),
+            (Back, "back"),
+            (DPadUp, "up"),
+            (DPadDown, "down"),
+            (DPadLeft, "left"),
+            (DPadRight, "right"),
+        ] {
+            m.bind_button(b, n);
+        }
+        m.bind_axis(GamepadAxis::LeftX, false, "left");
+        m.bind_axis(GamepadAxis::LeftX, true, "right");
+        m.bind_axis(GamepadAxis::LeftY, false, "up");
+        m.bind_axis(GamepadAxis::LeftY, true, "down");
+        for (k, n) in [
+            (KeyCode::Up, "up"),
+            (KeyCode::Down, "down"),
+            (KeyCode::Left, "left"),
+            (KeyCode::Right, "right"),
+            (KeyCode::Char('w'), "up"),
+            (KeyCode::Char('s'), "down"),
+            (KeyCode::Char('a'), "left"),
+            (KeyCode::Char('d'), "right"),
+            (KeyCode::Char('z'), "a"),
+            (KeyCode::Char('x'), "b"),
+            (KeyCode::Char('c'), "x"),
+            (KeyCode::Char('v'), "y"),
+            (KeyCode::Enter, "start"),
+            (KeyCode::Esc, "back"),
+        ] {
+            m.bind_key(k, n);
+        }
+        m
+    }
+
+    pub fn bind_button(&mut self, b: GamepadButton, name: &str) {
+        self.buttons.insert(b, name.to_string());
+    }
+
+    /// binds one direction of an axis, positive is right or down
+    pub fn bind_axis(&mut self, a: GamepadAxis, positive: bool, name: &str) {
+        self.axes.insert((a, positive), name.to_string());
+    }
+
+    pub fn bind_key(&mut self, k: KeyCode, name: &str) {
+        self.keys.insert(k, name.to_string());
+    }
+
+    fn press(&mut self, name: &str, id: u32, src: u8) {
+        if !self.is_down(name) {
+            self.just_down.insert(name.to_string());
+        }
+        self.down.insert((name.to_string(), id, src));
+    }
+
+    fn release(&mut self, name: &str, id: u32, src: u8) {
+        if self.down.remove(&(name.to_string(), id, src)) && !self.is_down(name) {
+            self.just_up.insert(name.to_string());
+        }
+    }
+
+    /// processes input events of this frame, call once per frame
+    pub fn update(&mut self, events: &[Event]) {
+        self.just_down.clear();
+        self.just_up.clear();
+        for e in events {
+            match e {
+                Event::Gamepad(ge) => match ge.kind {
+                    GamepadEventKind::Connected => {
+                        if !self.pads.contains(&ge.id) {
+                            self.pads.push(ge.id);
+                        }
+                    }
+                    GamepadEventKind::Disconnected => {
+                        self.pads.retain(|p| *p != ge.id);
+                        let held: Vec<_> =
+                            self.down.iter().filter(|d| d.1 == ge.id).cloned().collect();
+                        for d in held {
+                            self.release(&d.0, d.1, d.2);
+                        }
+                        self.axis_values.retain(|k, _| k.0 != ge.id);
+                    }
+                    GamepadEventKind::ButtonDown(b) => {
+                        if let Some(n) = self.buttons.get(&b).cloned() {
+                            self.press(&n, ge.id, SRC_BUTTON);
+                        }
+                    }
+                    GamepadEventKind::ButtonUp(b) => {
+                        if let Some(n) = self.buttons.get(&b).cloned() {
+                            self.release(&n, ge.id, SRC_BUTTON);
+                        }
+                    }
+                    GamepadEventKind::Axis(a, v) => {
+                        self.axis_values.insert((ge.id, a), v);
+                        for positive in [false, true] {
+                            if let Some(n) = self.axes.get(&(a, positive)).cloned() {
+                                let on = if positive {
+                                    v > self.dead_zone
+                                } else {
+                                    v < -self.dead_zone
+                                };
+                                if on {
+                                    self.press(&n, ge.id, SRC_AXIS);
+                                } else {
+                                    self.release(&n, ge.id, SRC_AXIS);
+                                }
+                            }
+                        }
+                    }
+                },
+                Event::Key(k) => {
+                    if let Some(n) = self.keys.get(&k.code).cloned() {
+                        match k.kind {
+                            KeyEventKind::Release => self.release(&n, 0, SRC_KEY),
+                            // no release in terminal mode, only report the press
+                            _ => {
+                                if !self.is_down(&n) {
+                                    self.just_down.insert(n);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// held on any gamepad
+    pub fn is_down(&self, name: &str) -> bool {
+        self.down.iter().any(|d| d.0 == name)
+    }
+
+    pub fn just_pressed(&self, name: &str) -> bool {
+        self.just_down.contains(name)
+    }
+
+    pub fn just_released(&self, name: &str) -> bool {
+        self.just_up.contains(name)
+    }
+
+    /// axis in -1.0..=1.0 with dead zone applied, largest of all gamepads
+    pub fn axis(&self, a: GamepadAxis) -> f32 {
+        let v = self
+            .axis_values
+            .iter()
+            .filter(|(k, _)| k.1 == a)
+            .map(|(_, v)| *v)
+            .max_by_key(|v| v.unsigned_abs())
+            .unwrap_or(0);
+        if v.unsigned_abs() <= self.dead_zone.unsigned_abs() {
+            0.0
+        } else {
+            (v as f32 / 32767.0).clamp(-1.0, 1.0)
+        }
+    }
+
+    /// ids of connected gamepads
+    pub fn connected(&self) -> &[u32] {
+        &self.pads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{GamepadEvent, KeyEvent, KeyModifiers};
+
+    fn pad(kind: GamepadEventKind) -> Event {
+        Event::Gamepad(GamepadEvent { id: 1, kind })
+    }
+
+    #[test]
+    fn gamepad_map_logical_buttons() {
+        let mut gm = GamepadMap::standard();
+        gm.update(&[
+            pad(GamepadEventKind::Connected),
+            pad(GamepadEventKind::ButtonDown(GamepadButton::A)),
+            pad(GamepadEventKind::Axis(GamepadAxis::LeftX, -20000)),
+        ]);
+        assert_eq!(gm.connected(), &[1]);
+        assert!(gm.just_pressed("a") && gm.is_down("a"));
+        assert!(gm.is_down("left"));
+        assert!(gm.axis(GamepadAxis::LeftX) < -0.5);
+
+        gm.update(&[pad(GamepadEventKind::Axis(GamepadAxis::LeftX, 100))]);
+        assert!(!gm.just_pressed("a") && gm.is_down("a"));
+        assert!(gm.just_released("left"));
+        assert_eq!(gm.axis(GamepadAxis::LeftX), 0.0);
+
+        gm.update(&[Event::Key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE))]);
+        assert!(!gm.just_pressed("a"));
+        gm.update(&[
+            pad(GamepadEventKind::Disconnected),
+            Event::Key(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)),
+        ]);
+        assert!(gm.just_released("a") && !gm.is_down("a"));
+        assert!(gm.just_pressed("up"));
+    }
+}
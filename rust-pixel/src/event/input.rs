@@ -1,22 +1,102 @@
 // RustPixel
 // copyright zipxing@hotmail.com 2022~2024
 
-//! This module provides a unified Input Event, describing events from keyboard and mouse
+//! This module provides a unified Input Event, describing events from keyboard, mouse and gamepad
 //! Input events triggered by renders adapter such as web, sdl or cross are converted here to
 //! unified Event
 
 use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 
-#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub enum Event {
     /// A single key event with additional pressed modifiers.
     Key(KeyEvent),
     /// A single mouse event with additional pressed modifiers.
     Mouse(MouseEvent),
+    /// A gamepad button, axis or connection event.
+    Gamepad(GamepadEvent),
+    /// A single touch point event.
+    Touch(TouchEvent),
+    /// Text typed or composed by an IME, only sent in text input mode.
+    Text(String),
+    /// IME composition in progress, empty when composition ends.
+    TextEditing(String),
+    /// Text pasted from the clipboard.
+    Paste(String),
 }
 
-#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub struct TouchEvent {
+    /// Id of the finger, stable from Start to End.
+    pub id: u64,
+    /// The kind of touch event.
+    pub kind: TouchEventKind,
+    /// The column of the touch point.
+    pub column: u16,
+    /// The row of the touch point.
+    pub row: u16,
+}
+
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum TouchEventKind {
+    Start,
+    Move,
+    End,
+    /// Touch interrupted by the system, no gesture should fire.
+    Cancel,
+}
+
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub struct GamepadEvent {
+    /// Id of the gamepad, stable while it stays connected.
+    pub id: u32,
+    /// The kind of gamepad event.
+    pub kind: GamepadEventKind,
+}
+
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum GamepadEventKind {
+    Connected,
+    Disconnected,
+    ButtonDown(GamepadButton),
+    ButtonUp(GamepadButton),
+    /// Axis moved, value in -32768..=32767, triggers are 0..=32767.
+    Axis(GamepadAxis, i16),
+}
+
+/// Buttons of the standard (xbox like) layout.
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    TriggerLeft,
+    TriggerRight,
+}
+
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub struct MouseEvent {
     /// The kind of mouse event that was caused.
     pub kind: MouseEventKind,
@@ -28,7 +108,7 @@ pub struct MouseEvent {
     pub modifiers: KeyModifiers,
 }
 
-#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum MouseEventKind {
     /// Pressed mouse button. Contains the button that was pressed.
     Down(MouseButton),
@@ -38,9 +118,13 @@ pub enum MouseEventKind {
     Drag(MouseButton),
     /// Moved the mouse cursor while not pressing a mouse button.
     Moved,
+    /// Scrolled mouse wheel downwards (towards the user).
+    ScrollDown,
+    /// Scrolled mouse wheel upwards (away from the user).
+    ScrollUp,
 }
 
-#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum MouseButton {
     /// Left mouse button.
     Left,
@@ -53,7 +137,7 @@ pub enum MouseButton {
 bitflags! {
     /// Represents key modifiers (shift, control, alt, etc.).
     ///
-    #[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+    #[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
     pub struct KeyModifiers: u8 {
         const SHIFT = 0b0000_0001;
         const CONTROL = 0b0000_0010;
@@ -65,7 +149,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum KeyEventKind {
     Press,
     Repeat,
@@ -74,7 +158,7 @@ pub enum KeyEventKind {
 
 bitflags! {
     /// Represents extra state about the key event.
-    #[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+    #[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
     pub struct KeyEventState: u8 {
         /// The key event origins from the keypad.
         const KEYPAD = 0b0000_0001;
@@ -87,7 +171,7 @@ bitflags! {
 }
 
 /// Represents a key event.
-#[derive(Debug, PartialOrd, Clone, Copy)]
+#[derive(Debug, PartialOrd, Clone, Copy, Serialize, Deserialize)]
 pub struct KeyEvent {
     /// The key itself.
     pub code: KeyCode,
@@ -204,7 +288,7 @@ impl Hash for KeyEvent {
 }
 
 /// Represents a modifier key (as part of [`KeyCode::Modifier`]).
-#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum ModifierKeyCode {
     /// Left Shift key.
     LeftShift,
@@ -233,7 +317,7 @@ pub enum ModifierKeyCode {
 }
 
 /// Represents a key.
-#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum KeyCode {
     /// Backspace key.
     Backspace,
@@ -0,0 +1,219 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! InputMap binds logical actions ("MoveLeft", "Confirm") to keys, mouse
+//! buttons and gamepad buttons or stick directions. Models query actions
+//! instead of matching raw KeyCodes, and bindings are saved as json so
+//! players can rebind controls.
+//!
+//! Adapters sending no key release events (terminal) make key bindings
+//! pressed for one frame only.
+
+use crate::event::{
+    Event, GamepadAxis, GamepadButton, GamepadEventKind, KeyCode, KeyEventKind, MouseButton,
+    MouseEventKind,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    /// stick or trigger direction, true is positive (right, down)
+    Axis(GamepadAxis, bool),
+}
+
+fn default_dead_zone() -> i16 {
+    8000
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: BTreeMap<String, Vec<InputBinding>>,
+    #[serde(default = "default_dead_zone")]
+    pub dead_zone: i16,
+    #[serde(skip)]
+    held: HashSet<InputBinding>,
+    // keys pressed without release support, released on next update
+    #[serde(skip)]
+    pulse: HashSet<InputBinding>,
+    #[serde(skip)]
+    key_release_seen: bool,
+    #[serde(skip)]
+    prev: HashSet<String>,
+    #[serde(skip)]
+    cur: HashSet<String>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self {
+            dead_zone: default_dead_zone(),
+            ..Default::default()
+        }
+    }
+
+    /// adds a binding to action, an action can have many bindings
+    pub fn bind(&mut self, action: &str, b: InputBinding) {
+        let v = self.bindings.entry(action.to_string()).or_default();
+        if !v.contains(&b) {
+            v.push(b);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, b: InputBinding) {
+        if let Some(v) = self.bindings.get_mut(action) {
+            v.retain(|x| *x != b);
+        }
+    }
+
+    /// replaces all bindings of action
+    pub fn rebind(&mut self, action: &str, b: InputBinding) {
+        self.bindings.insert(action.to_string(), vec![b]);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[InputBinding] {
+        self.bindings.get(action).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    pub fn actions(&self) -> Vec<&str> {
+        self.bindings.keys().map(|k| k.as_str()).collect()
+    }
+
+    /// action already using a binding, to warn about conflicts when rebinding
+    pub fn action_of(&self, b: InputBinding) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|(_, v)| v.contains(&b))
+            .map(|(k, _)| k.as_str())
+    }
+
+    /// processes input events of this frame, call once per frame
+    pub fn update(&mut self, events: &[Event]) {
+        for p in self.pulse.drain() {
+            self.held.remove(&p);
+        }
+        for e in events {
+            match e {
+                Event::Key(k) => {
+                    let b = InputBinding::Key(k.code);
+                    if k.kind == KeyEventKind::Release {
+                        self.key_release_seen = true;
+                        self.held.remove(&b);
+                    } else {
+                        self.held.insert(b);
+                        if !self.key_release_seen {
+                            self.pulse.insert(b);
+                        }
+                    }
+                }
+                Event::Mouse(m) => match m.kind {
+                    MouseEventKind::Down(mb) => {
+                        self.held.insert(InputBinding::Mouse(mb));
+                    }
+                    MouseEventKind::Up(mb) => {
+                        self.held.remove(&InputBinding::Mouse(mb));
+                    }
+                    _ => {}
+                },
+                Event::Gamepad(g) => match g.kind {
+                    GamepadEventKind::ButtonDown(gb) => {
+                        self.held.insert(InputBinding::Gamepad(gb));
+                    }
+                    GamepadEventKind::ButtonUp(gb) => {
+                        self.held.remove(&InputBinding::Gamepad(gb));
+                    }
+                    GamepadEventKind::Axis(a, v) => {
+                        for (positive, on) in
+                            [(true, v > self.dead_zone), (false, v < -self.dead_zone)]
+                        {
+                            if on {
+                                self.held.insert(InputBinding::Axis(a, positive));
+                            } else {
+                                self.held.remove(&InputBinding::Axis(a, positive));
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        self.prev = std::mem::take(&mut self.cur);
+        for (action, bs) in &self.bindings {
+            if bs.iter().any(|b| self.held.contains(b)) {
+                self.cur.insert(action.clone());
+            }
+        }
+    }
+
+    /// action is held down
+    pub fn pressed(&self, action: &str) -> bool {
+        self.cur.contains(action)
+    }
+
+    /// action went down in this frame
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.cur.contains(action) && !self.prev.contains(action)
+    }
+
+    /// action went up in this frame
+    pub fn released(&self, action: &str) -> bool {
+        !self.cur.contains(action) && self.prev.contains(action)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// loads bindings saved by to_json
+    pub fn from_json(s: &str) -> Result<Self, String> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyEvent, KeyModifiers, MouseEvent};
+
+    fn key(c: KeyCode, kind: KeyEventKind) -> Event {
+        Event::Key(KeyEvent::new_with_kind(c, KeyModifiers::NONE, kind))
+    }
+
+    #[test]
+    fn input_map_actions_and_rebind() {
+        let mut im = InputMap::new();
+        im.bind("Left", InputBinding::Key(KeyCode::Left));
+        im.bind("Fire", InputBinding::Mouse(MouseButton::Left));
+
+        // no release events yet: key is pressed for one frame
+        im.update(&[key(KeyCode::Left, KeyEventKind::Press)]);
+        assert!(im.just_pressed("Left") && im.pressed("Left"));
+        im.update(&[]);
+        assert!(im.released("Left") && !im.pressed("Left"));
+
+        let md = Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        });
+        im.update(&[md]);
+        im.update(&[]);
+        assert!(im.pressed("Fire") && !im.just_pressed("Fire"));
+
+        im.rebind("Left", InputBinding::Key(KeyCode::Char('a')));
+        let im2 = InputMap::from_json(&im.to_json()).unwrap();
+        assert_eq!(
+            im2.bindings("Left"),
+            &[InputBinding::Key(KeyCode::Char('a'))]
+        );
+        assert_eq!(
+            im2.action_of(InputBinding::Mouse(MouseButton::Left)),
+            Some("Fire")
+        );
+    }
+}
@@ -0,0 +1,274 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! KeyTracker lives in context.keys and is updated before Model::update.
+//! It tags key repeats, synthesizes repeats at a configurable rate and
+//! detects chords (Ctrl+Shift+X) and double taps.
+//!
+//! SDL and web mark auto repeated keys, terminals do not. In terminal mode
+//! a press following another press of the same key within REPEAT_GAP is
+//! taken as a repeat. As the system repeat delay passes before the first
+//! repeat arrives, a key held in a terminal is seen as a press, another
+//! press and then repeats.
+
+use crate::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::HashMap;
+
+/// max seconds between presses of a terminal key auto repeat
+pub const REPEAT_GAP: f32 = 0.08;
+
+// a key is released if no repeat arrives for this long
+const HOLD_TIMEOUT: f32 = 0.15;
+
+/// a key combination like Ctrl+Shift+X
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub modifiers: KeyModifiers,
+    pub code: KeyCode,
+}
+
+// upper case chars are lower case with shift
+fn normalize(mut m: KeyModifiers, c: KeyCode) -> (KeyModifiers, KeyCode) {
+    let c = match c {
+        KeyCode::Char(ch) => {
+            if ch.is_ascii_uppercase() {
+                m.insert(KeyModifiers::SHIFT);
+            }
+            KeyCode::Char(ch.to_ascii_lowercase())
+        }
+        c => c,
+    };
+    (m, c)
+}
+
+impl Chord {
+    pub fn new(modifiers: KeyModifiers, code: KeyCode) -> Self {
+        Self { modifiers, code }
+    }
+
+    /// parses "Ctrl+Shift+X", "Alt+Enter" or "F5", names are case insensitive
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let parts: Vec<&str> = s.split('+').map(|p| p.trim()).collect();
+        let (key, mods) = parts.split_last()?;
+        for m in mods {
+            modifiers.insert(match m.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "shift" => KeyModifiers::SHIFT,
+                "alt" | "option" => KeyModifiers::ALT,
+                "super" | "cmd" | "win" => KeyModifiers::SUPER,
+                "meta" => KeyModifiers::META,
+                _ => return None,
+            });
+        }
+        let lk = key.to_lowercase();
+        let code = match lk.as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            _ if lk.chars().count() == 1 => KeyCode::Char(lk.chars().next()?),
+            _ => KeyCode::F(lk.strip_prefix('f')?.parse().ok()?),
+        };
+        Some(Self { modifiers, code })
+    }
+
+    pub fn matches(&self, k: &KeyEvent) -> bool {
+        normalize(self.modifiers, self.code) == normalize(k.modifiers, k.code)
+    }
+}
+
+/// synthesized key repeat, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyRepeat {
+    /// from press to first repeat, not shorter than the system delay
+    pub delay: f32,
+    pub interval: f32,
+}
+
+struct Held {
+    start: f32,
+    last_raw: f32,
+    next: f32,
+    event: KeyEvent,
+}
+
+pub struct KeyTracker {
+    /// repeats at this rate replace the system ones, None keeps them
+    pub repeat: Option<KeyRepeat>,
+    /// max seconds between the presses of a double tap
+    pub double_tap_time: f32,
+    time: f32,
+    last_press: HashMap<KeyCode, f32>,
+    last_tap: HashMap<KeyCode, f32>,
+    held: HashMap<KeyCode, Held>,
+    pressed: Vec<KeyEvent>,
+    double_taps: Vec<KeyCode>,
+}
+
+impl Default for KeyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyTracker {
+    pub fn new() -> Self {
+        Self {
+            repeat: None,
+            double_tap_time: 0.3,
+            time: 0.0,
+            last_press: HashMap::new(),
+            last_tap: HashMap::new(),
+            held: HashMap::new(),
+            pressed: vec![],
+            double_taps: vec![],
+        }
+    }
+
+    /// processes key events of this frame, tags and synthesizes repeats
+    /// in events, which the model clears after handle_input as usual
+    pub fn update(&mut self, events: &mut Vec<Event>, dt: f32) {
+        self.time += dt;
+        self.pressed.clear();
+        self.double_taps.clear();
+        let now = self.time;
+        let mut i = 0;
+        while i < events.len() {
+            let Event::Key(k) = &mut events[i] else {
+                i += 1;
+                continue;
+            };
+            let (_, code) = normalize(k.modifiers, k.code);
+            if k.kind == KeyEventKind::Release {
+                self.held.remove(&code);
+                i += 1;
+                continue;
+            }
+            let last = self.last_press.insert(code, now);
+            if k.kind == KeyEventKind::Press && last.is_some_and(|t| now - t <= REPEAT_GAP) {
+                k.kind = KeyEventKind::Repeat;
+            }
+            if k.kind == KeyEventKind::Repeat {
+                let ke = *k;
+                if let Some(h) = self.held.get_mut(&code) {
+                    h.last_raw = now;
+                } else {
+                    self.held.insert(
+                        code,
+                        Held {
+                            start: last.unwrap_or(now),
+                            last_raw: now,
+                            next: 0.0,
+                            event: ke,
+                        },
+                    );
+                }
+                if self.repeat.is_some() {
+                    events.remove(i);
+                    continue;
+                }
+            } else {
+                match self.last_tap.remove(&code) {
+                    Some(t) if now - t <= self.double_tap_time => self.double_taps.push(code),
+                    _ => {
+                        self.last_tap.insert(code, now);
+                    }
+                }
+                self.pressed.push(*k);
+            }
+            i += 1;
+        }
+        self.held.retain(|_, h| now - h.last_raw <= HOLD_TIMEOUT);
+        if let Some(r) = self.repeat {
+            for h in self.held.values_mut() {
+                if h.next == 0.0 {
+                    h.next = h.start + r.delay;
+                }
+                if now >= h.next {
+                    h.next = (h.next + r.interval.max(0.001)).max(now);
+                    events.push(Event::Key(h.event));
+                }
+            }
+        }
+    }
+
+    /// key pressed in this frame, repeats excluded
+    pub fn just_pressed(&self, code: KeyCode) -> bool {
+        let (_, c) = normalize(KeyModifiers::NONE, code);
+        self.pressed
+            .iter()
+            .any(|k| normalize(k.modifiers, k.code).1 == c)
+    }
+
+    /// chord pressed in this frame
+    pub fn chord(&self, ch: &Chord) -> bool {
+        self.pressed.iter().any(|k| ch.matches(k))
+    }
+
+    /// second press of key within double_tap_time in this frame
+    pub fn double_tap(&self, code: KeyCode) -> bool {
+        let (_, c) = normalize(KeyModifiers::NONE, code);
+        self.double_taps.contains(&c)
+    }
+
+    /// key is auto repeating
+    pub fn repeating(&self, code: KeyCode) -> bool {
+        let (_, c) = normalize(KeyModifiers::NONE, code);
+        self.held.contains_key(&c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: KeyCode, m: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(c, m))
+    }
+
+    #[test]
+    fn key_tracker_repeat_chord_double_tap() {
+        let ch = Chord::parse("Ctrl+Shift+X").unwrap();
+        assert_eq!(ch.code, KeyCode::Char('x'));
+        assert_eq!(Chord::parse("f5").unwrap().code, KeyCode::F(5));
+        assert!(Chord::parse("Hyper+Q").is_none());
+
+        let mut kt = KeyTracker::new();
+        let mut es = vec![key(KeyCode::Char('X'), KeyModifiers::CONTROL)];
+        kt.update(&mut es, 0.1);
+        assert!(kt.chord(&ch) && !kt.double_tap(KeyCode::Char('x')));
+        es = vec![key(KeyCode::Char('x'), KeyModifiers::NONE)];
+        kt.update(&mut es, 0.2);
+        assert!(kt.double_tap(KeyCode::Char('x')) && !kt.chord(&ch));
+
+        // terminal auto repeat, replaced by a slower synthesized one
+        kt.repeat = Some(KeyRepeat {
+            delay: 0.5,
+            interval: 0.2,
+        });
+        es.clear();
+        es.push(key(KeyCode::Left, KeyModifiers::NONE));
+        kt.update(&mut es, 1.0);
+        let mut repeats = 0;
+        for _ in 0..20 {
+            es.clear();
+            es.push(key(KeyCode::Left, KeyModifiers::NONE));
+            kt.update(&mut es, 0.05);
+            repeats += es.len();
+            assert!(kt.repeating(KeyCode::Left) && !kt.just_pressed(KeyCode::Left));
+        }
+        assert_eq!(repeats, 3);
+        es.clear();
+        kt.update(&mut es, 0.2);
+        assert!(!kt.repeating(KeyCode::Left));
+    }
+}
@@ -0,0 +1,179 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! MouseTracker turns the mouse events of any adapter into hover, wheel,
+//! click and drag gestures in cell coordinates, so UI code needs no
+//! per adapter handling.
+
+use crate::event::{Event, MouseButton, MouseEventKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DragState {
+    /// first frame of the drag
+    Started,
+    Moved,
+    /// button released, last frame of the drag
+    Ended,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DragGesture {
+    pub button: MouseButton,
+    pub start: (u16, u16),
+    pub pos: (u16, u16),
+    pub state: DragState,
+}
+
+impl DragGesture {
+    /// cells moved since the drag started
+    pub fn delta(&self) -> (i32, i32) {
+        (
+            self.pos.0 as i32 - self.start.0 as i32,
+            self.pos.1 as i32 - self.start.1 as i32,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MouseTracker {
+    hover: Option<(u16, u16)>,
+    pressed: Option<(MouseButton, (u16, u16))>,
+    drag: Option<DragGesture>,
+    clicks: Vec<(MouseButton, (u16, u16))>,
+    wheel: i32,
+}
+
+impl MouseTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// processes input events of this frame, call once per frame
+    pub fn update(&mut self, events: &[Event]) {
+        self.clicks.clear();
+        self.wheel = 0;
+        match self.drag.map(|d| d.state) {
+            Some(DragState::Ended) => self.drag = None,
+            Some(DragState::Started) => {
+                if let Some(d) = &mut self.drag {
+                    d.state = DragState::Moved;
+                }
+            }
+            _ => {}
+        }
+        for e in events {
+            let Event::Mouse(me) = e else {
+                continue;
+            };
+            let pos = (me.column, me.row);
+            self.hover = Some(pos);
+            match me.kind {
+                MouseEventKind::Down(b) => self.pressed = Some((b, pos)),
+                MouseEventKind::Drag(b) => match &mut self.drag {
+                    Some(d) => d.pos = pos,
+                    None => {
+                        // some adapters send no down before drag
+                        let start = match self.pressed {
+                            Some((pb, p)) if pb == b => p,
+                            _ => pos,
+                        };
+                        if start != pos {
+                            self.drag = Some(DragGesture {
+                                button: b,
+                                start,
+                                pos,
+                                state: DragState::Started,
+                            });
+                        } else {
+                            self.pressed = Some((b, pos));
+                        }
+                    }
+                },
+                MouseEventKind::Up(b) => {
+                    match &mut self.drag {
+                        Some(d) if d.state != DragState::Ended => {
+                            d.pos = pos;
+                            d.state = DragState::Ended;
+                        }
+                        _ => {
+                            if matches!(self.pressed, Some((pb, _)) if pb == b) {
+                                self.clicks.push((b, pos));
+                            }
+                        }
+                    }
+                    self.pressed = None;
+                }
+                MouseEventKind::ScrollUp => self.wheel += 1,
+                MouseEventKind::ScrollDown => self.wheel -= 1,
+                MouseEventKind::Moved => {}
+            }
+        }
+    }
+
+    /// cell under the mouse
+    pub fn hover(&self) -> Option<(u16, u16)> {
+        self.hover
+    }
+
+    /// drag in progress, or just ended in this frame
+    pub fn drag(&self) -> Option<&DragGesture> {
+        self.drag.as_ref()
+    }
+
+    /// cell of a press and release without drag in this frame
+    pub fn clicked(&self, b: MouseButton) -> Option<(u16, u16)> {
+        self.clicks.iter().find(|c| c.0 == b).map(|c| c.1)
+    }
+
+    /// wheel steps of this frame, positive is up
+    pub fn wheel(&self) -> i32 {
+        self.wheel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyModifiers, MouseEvent};
+
+    fn m(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn mouse_tracker_gestures() {
+        use MouseButton::*;
+        let mut mt = MouseTracker::new();
+        mt.update(&[
+            m(MouseEventKind::Down(Left), 2, 2),
+            m(MouseEventKind::Up(Left), 2, 2),
+            m(MouseEventKind::ScrollUp, 2, 2),
+        ]);
+        assert_eq!(mt.clicked(Left), Some((2, 2)));
+        assert_eq!(mt.wheel(), 1);
+
+        mt.update(&[
+            m(MouseEventKind::Down(Left), 1, 1),
+            m(MouseEventKind::Drag(Left), 3, 4),
+        ]);
+        assert_eq!(mt.clicked(Left), None);
+        let d = *mt.drag().unwrap();
+        assert_eq!((d.state, d.delta()), (DragState::Started, (2, 3)));
+
+        mt.update(&[m(MouseEventKind::Moved, 5, 5)]);
+        assert_eq!(mt.drag().unwrap().state, DragState::Moved);
+        assert_eq!(mt.hover(), Some((5, 5)));
+
+        mt.update(&[m(MouseEventKind::Up(Left), 6, 4)]);
+        let d = *mt.drag().unwrap();
+        assert_eq!((d.state, d.delta()), (DragState::Ended, (5, 3)));
+        assert_eq!(mt.clicked(Left), None);
+        mt.update(&[]);
+        assert!(mt.drag().is_none());
+    }
+}
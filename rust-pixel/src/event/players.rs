@@ -0,0 +1,202 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Players lives in context.players and routes input devices to numbered
+//! players for hot-seat and couch co-op games. Devices are whole
+//! keyboards, a set of keys (one half of a shared keyboard), gamepads,
+//! mouse or touch. Every frame the input events are tagged with the
+//! player of their device, events of unassigned devices are left out.
+//!
+//! Feed events(n) to an InputMap or GamepadMap per player to read actions
+//! of each player separately.
+
+use crate::event::{Event, GamepadEventKind, KeyCode};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Device {
+    /// all keys not taken by a Keys device
+    Keyboard,
+    /// a part of the keyboard, chars match case insensitive
+    Keys(Vec<KeyCode>),
+    /// gamepad by its event id
+    Gamepad(u32),
+    Mouse,
+    Touch,
+}
+
+impl Device {
+    /// wasd, f g for buttons, q e, tab and left side space
+    pub fn keys_left() -> Self {
+        let mut v: Vec<KeyCode> = "wasdfgqe".chars().map(KeyCode::Char).collect();
+        v.extend([KeyCode::Tab, KeyCode::Char(' ')]);
+        Device::Keys(v)
+    }
+
+    /// arrows, k l for buttons, o p, enter and backspace
+    pub fn keys_right() -> Self {
+        let mut v = vec![KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right];
+        v.extend("klop".chars().map(KeyCode::Char));
+        v.extend([KeyCode::Enter, KeyCode::Backspace]);
+        Device::Keys(v)
+    }
+
+    fn matches(&self, e: &Event) -> bool {
+        let lower = |k: KeyCode| match k {
+            KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+            k => k,
+        };
+        match (self, e) {
+            (Device::Keyboard, Event::Key(_)) => true,
+            (Device::Keys(ks), Event::Key(k)) => ks.iter().any(|c| lower(*c) == lower(k.code)),
+            (Device::Gamepad(id), Event::Gamepad(g)) => *id == g.id,
+            (Device::Mouse, Event::Mouse(_)) => true,
+            (Device::Touch, Event::Touch(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Device to player routing, in context.players
+#[derive(Debug, Clone, Default)]
+pub struct Players {
+    devices: Vec<(u8, Device)>,
+    tagged: Vec<(u8, Event)>,
+    /// connecting gamepads are assigned to the lowest player below this
+    /// without a gamepad, 0 turns it off
+    pub auto_gamepads: u8,
+}
+
+impl Players {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// gives a device to player, taking it from any other player
+    pub fn assign(&mut self, player: u8, d: Device) {
+        self.devices.retain(|(_, x)| *x != d);
+        // key sets win over the whole keyboard
+        if matches!(d, Device::Keys(_)) {
+            self.devices.insert(0, (player, d));
+        } else {
+            self.devices.push((player, d));
+        }
+    }
+
+    pub fn unassign(&mut self, d: &Device) {
+        self.devices.retain(|(_, x)| x != d);
+    }
+
+    /// removes all devices of player
+    pub fn remove_player(&mut self, player: u8) {
+        self.devices.retain(|(p, _)| *p != player);
+    }
+
+    pub fn devices(&self, player: u8) -> Vec<&Device> {
+        self.devices
+            .iter()
+            .filter(|(p, _)| *p == player)
+            .map(|(_, d)| d)
+            .collect()
+    }
+
+    /// players having a device, sorted
+    pub fn players(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = self.devices.iter().map(|(p, _)| *p).collect();
+        v.sort();
+        v.dedup();
+        v
+    }
+
+    /// player owning the device of an event
+    pub fn player_of(&self, e: &Event) -> Option<u8> {
+        self.devices
+            .iter()
+            .find(|(_, d)| d.matches(e))
+            .map(|(p, _)| *p)
+    }
+
+    /// tags events of this frame, called by the game loop
+    pub fn update(&mut self, events: &[Event]) {
+        self.tagged.clear();
+        for e in events {
+            if let Event::Gamepad(g) = e {
+                let d = Device::Gamepad(g.id);
+                if g.kind == GamepadEventKind::Connected && !self.devices.iter().any(|x| x.1 == d) {
+                    let free = (0..self.auto_gamepads).find(|p| {
+                        !self
+                            .devices(*p)
+                            .iter()
+                            .any(|x| matches!(x, Device::Gamepad(_)))
+                    });
+                    if let Some(p) = free {
+                        self.assign(p, d);
+                    }
+                }
+            }
+            if let Some(p) = self.player_of(e) {
+                self.tagged.push((p, e.clone()));
+            }
+        }
+    }
+
+    /// events of this frame with their player
+    pub fn tagged(&self) -> &[(u8, Event)] {
+        &self.tagged
+    }
+
+    /// events of this frame of one player
+    pub fn events(&self, player: u8) -> Vec<Event> {
+        self.tagged
+            .iter()
+            .filter(|(p, _)| *p == player)
+            .map(|(_, e)| e.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{GamepadEvent, KeyEvent, KeyModifiers};
+
+    fn key(c: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(c, KeyModifiers::NONE))
+    }
+
+    fn pad(id: u32, kind: GamepadEventKind) -> Event {
+        Event::Gamepad(GamepadEvent { id, kind })
+    }
+
+    #[test]
+    fn players_route_devices() {
+        let mut ps = Players::new();
+        ps.assign(0, Device::keys_left());
+        ps.assign(1, Device::keys_right());
+        ps.assign(2, Device::Keyboard);
+        ps.auto_gamepads = 4;
+        ps.update(&[
+            key(KeyCode::Char('W')),
+            key(KeyCode::Left),
+            key(KeyCode::Char('m')),
+            pad(7, GamepadEventKind::Connected),
+            pad(8, GamepadEventKind::Connected),
+            Event::Text("x".to_string()),
+        ]);
+        assert_eq!(ps.events(0).len(), 2);
+        assert_eq!(
+            ps.events(1),
+            vec![key(KeyCode::Left), pad(8, GamepadEventKind::Connected)]
+        );
+        assert_eq!(ps.events(2), vec![key(KeyCode::Char('m'))]);
+        assert_eq!(ps.tagged().len(), 5);
+        assert_eq!(ps.players(), vec![0, 1, 2]);
+
+        ps.assign(3, Device::Gamepad(7));
+        assert_eq!(
+            ps.player_of(&pad(7, GamepadEventKind::Disconnected)),
+            Some(3)
+        );
+        ps.remove_player(1);
+        assert_eq!(ps.player_of(&key(KeyCode::Left)), Some(2));
+    }
+}
@@ -0,0 +1,181 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Replay records the input events and dt of every frame together with the
+//! RNG state, and plays them back through handle_input. A game driven only
+//! by input, dt and context.rand runs the same way again, which helps to
+//! reproduce bugs and makes attract mode demos cheap.
+//!
+//! The game loop passes each frame through context.replay. While playing,
+//! live input is moved to replay.live_events, so a demo can be stopped on
+//! any key.
+
+use crate::{event::Event, util::Rand};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// Input and dt of one frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub dt: f32,
+    pub events: Vec<Event>,
+}
+
+/// A recorded input stream
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    /// state of context.rand when recording started
+    pub rand_state: Vec<u8>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        bincode::deserialize(data).map_err(|e| format!("bad replay:{}", e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::from_bytes(&data)
+    }
+
+    /// total seconds of the replay
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|f| f.dt).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Recording,
+    Playing,
+}
+
+/// Replay recorder and player, in context.replay
+#[derive(Debug, Default)]
+pub struct ReplayControl {
+    mode: ReplayMode,
+    replay: Replay,
+    cursor: usize,
+    /// live input while playing
+    pub live_events: Vec<Event>,
+    /// set when a playback reached its end
+    pub finished: bool,
+}
+
+impl ReplayControl {
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.mode == ReplayMode::Recording
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.mode == ReplayMode::Playing
+    }
+
+    /// starts recording from the current rand state
+    pub fn record(&mut self, rand: &Rand) {
+        self.replay = Replay {
+            rand_state: rand.state(),
+            frames: vec![],
+        };
+        self.mode = ReplayMode::Recording;
+        self.finished = false;
+    }
+
+    /// restores the recorded rand state and starts playing
+    pub fn play(&mut self, replay: Replay, rand: &mut Rand) {
+        if let Err(e) = rand.set_state(&replay.rand_state) {
+            info!("replay rand state:{}", e);
+        }
+        self.replay = replay;
+        self.cursor = 0;
+        self.mode = ReplayMode::Playing;
+        self.finished = false;
+    }
+
+    /// stops recording or playing, returns the recorded replay
+    pub fn stop(&mut self) -> Option<Replay> {
+        let mode = std::mem::take(&mut self.mode);
+        let replay = std::mem::take(&mut self.replay);
+        self.live_events.clear();
+        (mode == ReplayMode::Recording).then_some(replay)
+    }
+
+    /// records or replaces the input of a frame, returns dt to use,
+    /// called by the game loop before models update
+    pub fn process(&mut self, events: &mut Vec<Event>, dt: f32) -> f32 {
+        match self.mode {
+            ReplayMode::Off => dt,
+            ReplayMode::Recording => {
+                self.replay.frames.push(ReplayFrame {
+                    dt,
+                    events: events.clone(),
+                });
+                dt
+            }
+            ReplayMode::Playing => {
+                self.live_events = std::mem::take(events);
+                match self.replay.frames.get(self.cursor) {
+                    Some(f) => {
+                        self.cursor += 1;
+                        events.extend(f.events.iter().cloned());
+                        f.dt
+                    }
+                    None => {
+                        self.stop();
+                        self.finished = true;
+                        dt
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn replay_record_and_play() {
+        let mut rand = Rand::with_seed(7);
+        let mut rc = ReplayControl::default();
+        rc.record(&rand);
+        let first = rand.rand();
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
+        let mut es = vec![key.clone()];
+        assert_eq!(rc.process(&mut es, 0.02), 0.02);
+        let mut es = vec![];
+        rc.process(&mut es, 0.03);
+        let r = Replay::from_bytes(&rc.stop().unwrap().to_bytes()).unwrap();
+        assert!((r.duration() - 0.05).abs() < 1e-6);
+
+        rand.srand(99);
+        rc.play(r, &mut rand);
+        assert_eq!(rand.rand(), first);
+        let live = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        let mut es = vec![live.clone()];
+        assert_eq!(rc.process(&mut es, 0.1), 0.02);
+        assert_eq!(es, vec![key]);
+        assert_eq!(rc.live_events, vec![live]);
+        let mut es = vec![];
+        assert_eq!(rc.process(&mut es, 0.1), 0.03);
+        rc.process(&mut es, 0.1);
+        assert!(rc.finished && !rc.is_playing());
+    }
+}
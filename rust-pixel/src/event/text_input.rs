@@ -0,0 +1,211 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! TextInput is the state of a one line text field, it edits a string with
+//! Event::Text, Event::TextEditing, Event::Paste and editing keys (arrows,
+//! Home, End, Backspace, Delete, Enter, Esc).
+//!
+//! Text events are only sent in text input mode, turn it on with
+//! adapter.set_text_input(true) while the field has focus.
+
+use crate::event::{Event, KeyCode};
+#[cfg(not(feature = "base"))]
+use crate::render::{
+    buffer::Buffer,
+    style::{Modifier, Style},
+};
+#[cfg(not(feature = "base"))]
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextInputAction {
+    Changed,
+    /// Enter pressed
+    Submit,
+    /// Esc pressed
+    Cancel,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TextInput {
+    text: String,
+    // cursor position in chars
+    cursor: usize,
+    composing: String,
+    /// max chars of text, 0 is unlimited
+    pub max_len: usize,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// sets text and moves cursor to the end
+    pub fn set_text(&mut self, s: &str) {
+        self.text = s.to_string();
+        self.cursor = self.text.chars().count();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// IME composition in progress, not part of text yet
+    pub fn composing(&self) -> &str {
+        &self.composing
+    }
+
+    fn byte_pos(&self, ci: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(ci)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+
+    fn insert(&mut self, s: &str) -> bool {
+        let mut changed = false;
+        for c in s.chars().filter(|c| !c.is_control()) {
+            if self.max_len != 0 && self.text.chars().count() >= self.max_len {
+                break;
+            }
+            let p = self.byte_pos(self.cursor);
+            self.text.insert(p, c);
+            self.cursor += 1;
+            changed = true;
+        }
+        changed
+    }
+
+    /// processes input events of this frame, call once per frame
+    pub fn update(&mut self, events: &[Event]) -> Option<TextInputAction> {
+        let mut action = None;
+        for e in events {
+            match e {
+                Event::Text(s) | Event::Paste(s) => {
+                    self.composing.clear();
+                    if self.insert(s) {
+                        action = Some(TextInputAction::Changed);
+                    }
+                }
+                Event::TextEditing(s) => self.composing = s.clone(),
+                // keys are part of the composition while composing
+                Event::Key(k) if self.composing.is_empty() => {
+                    let len = self.text.chars().count();
+                    match k.code {
+                        KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+                        KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+                        KeyCode::Home => self.cursor = 0,
+                        KeyCode::End => self.cursor = len,
+                        KeyCode::Backspace if self.cursor > 0 => {
+                            self.cursor -= 1;
+                            self.text.remove(self.byte_pos(self.cursor));
+                            action = Some(TextInputAction::Changed);
+                        }
+                        KeyCode::Delete if self.cursor < len => {
+                            self.text.remove(self.byte_pos(self.cursor));
+                            action = Some(TextInputAction::Changed);
+                        }
+                        KeyCode::Enter => return Some(TextInputAction::Submit),
+                        KeyCode::Esc => return Some(TextInputAction::Cancel),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        action
+    }
+
+    /// draws the field in width cells at (x, y), scrolled to keep the
+    /// cursor visible, cursor is reversed and composition underlined
+    #[cfg(not(feature = "base"))]
+    pub fn draw(&self, buf: &mut Buffer, x: u16, y: u16, width: u16, style: Style) {
+        let mut cs: Vec<(char, bool)> = self.text.chars().map(|c| (c, false)).collect();
+        let cur = self.cursor + self.composing.chars().count();
+        cs.splice(
+            self.cursor..self.cursor,
+            self.composing.chars().map(|c| (c, true)),
+        );
+        // first visible char, so that chars up to cursor fit in width - 1
+        let mut start = cur;
+        let mut w = 1;
+        while start > 0 {
+            let cw = cs[start - 1].0.width().unwrap_or(0) as u16;
+            if w + cw > width {
+                break;
+            }
+            w += cw;
+            start -= 1;
+        }
+        buf.set_string(x, y, " ".repeat(width as usize), style);
+        let mut cx = x;
+        for (i, (c, composing)) in cs.iter().enumerate().skip(start) {
+            let cw = c.width().unwrap_or(0) as u16;
+            if cx + cw > x + width {
+                break;
+            }
+            let mut st = style;
+            if *composing {
+                st = st.add_modifier(Modifier::UNDERLINED);
+            }
+            if i == cur {
+                st = st.add_modifier(Modifier::REVERSED);
+            }
+            buf.set_string(cx, y, c.to_string(), st);
+            cx += cw;
+        }
+        if cur == cs.len() && cx < x + width {
+            buf.set_string(cx, y, " ", style.add_modifier(Modifier::REVERSED));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyEvent, KeyModifiers};
+
+    fn key(c: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(c, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn text_input_editing() {
+        let mut ti = TextInput::new();
+        ti.max_len = 5;
+        let a = ti.update(&[Event::Text("héllo!".to_string())]);
+        assert_eq!((a, ti.text()), (Some(TextInputAction::Changed), "héllo"));
+
+        ti.update(&[
+            key(KeyCode::Left),
+            key(KeyCode::Left),
+            key(KeyCode::Backspace),
+        ]);
+        assert_eq!((ti.text(), ti.cursor()), ("hélo", 2));
+        ti.update(&[key(KeyCode::Home), key(KeyCode::Delete)]);
+        assert_eq!(ti.text(), "élo");
+
+        // keys go to the ime while composing
+        ti.update(&[
+            Event::TextEditing("ni".to_string()),
+            key(KeyCode::Backspace),
+        ]);
+        assert_eq!((ti.text(), ti.composing()), ("élo", "ni"));
+        ti.update(&[Event::Text("你".to_string())]);
+        assert_eq!((ti.text(), ti.composing()), ("你élo", ""));
+        assert_eq!(
+            ti.update(&[key(KeyCode::Enter)]),
+            Some(TextInputAction::Submit)
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! TimerSet in context.timers holds named timers updated with the scaled
+//! dt right before Model::handle_timer, so models no longer need to sum
+//! up dt by hand. Unlike the global GAME_TIMER they count seconds, repeat
+//! and can be queried for how often they fired in this frame.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    Times(u32),
+    Forever,
+}
+
+#[derive(Debug, Clone)]
+pub struct NamedTimer {
+    pub interval: f32,
+    pub repeat: Repeat,
+    /// seconds to the next fire
+    pub remaining: f32,
+    pub paused: bool,
+    // fires in the last update
+    fired: u32,
+    // fires left for Repeat::Times
+    left: u32,
+}
+
+impl NamedTimer {
+    /// 0.0 after a fire up to 1.0 at the next one
+    pub fn percent(&self) -> f32 {
+        if self.interval <= 0.0 {
+            1.0
+        } else {
+            1.0 - (self.remaining / self.interval).clamp(0.0, 1.0)
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.repeat != Repeat::Forever && self.left == 0
+    }
+}
+
+/// Named timers of a game, in context.timers
+#[derive(Debug, Default)]
+pub struct TimerSet {
+    timers: HashMap<String, NamedTimer>,
+}
+
+impl TimerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// starts or restarts a timer firing every secs seconds
+    pub fn set(&mut self, name: &str, secs: f32, repeat: Repeat) {
+        let left = match repeat {
+            Repeat::Once => 1,
+            Repeat::Times(n) => n,
+            Repeat::Forever => 0,
+        };
+        self.timers.insert(
+            name.to_string(),
+            NamedTimer {
+                interval: secs,
+                repeat,
+                remaining: secs,
+                paused: false,
+                fired: 0,
+                left,
+            },
+        );
+    }
+
+    /// removes a timer, returns false if it did not exist
+    pub fn cancel(&mut self, name: &str) -> bool {
+        self.timers.remove(name).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+
+    pub fn pause(&mut self, name: &str, paused: bool) {
+        if let Some(t) = self.timers.get_mut(name) {
+            t.paused = paused;
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NamedTimer> {
+        self.timers.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.timers.keys().map(|k| k.as_str())
+    }
+
+    /// timer exists and will fire again
+    pub fn is_active(&self, name: &str) -> bool {
+        self.timers.get(name).is_some_and(|t| !t.done())
+    }
+
+    pub fn remaining(&self, name: &str) -> Option<f32> {
+        self.timers.get(name).map(|t| t.remaining)
+    }
+
+    pub fn percent(&self, name: &str) -> f32 {
+        self.timers.get(name).map_or(0.0, |t| t.percent())
+    }
+
+    /// times the timer fired in the last update, more than 1 if dt is
+    /// longer than the interval
+    pub fn fired(&self, name: &str) -> u32 {
+        self.timers.get(name).map_or(0, |t| t.fired)
+    }
+
+    pub fn just_fired(&self, name: &str) -> bool {
+        self.fired(name) > 0
+    }
+
+    /// advances all timers by dt, called by Model::update
+    pub fn update(&mut self, dt: f32) {
+        // finished timers were kept since the last update for fired queries
+        self.timers.retain(|_, t| !t.done());
+        for t in self.timers.values_mut() {
+            t.fired = 0;
+            if t.paused {
+                continue;
+            }
+            t.remaining -= dt;
+            while t.remaining <= 0.0 && !t.done() {
+                t.fired += 1;
+                if t.repeat != Repeat::Forever {
+                    t.left -= 1;
+                }
+                if t.interval <= 0.0 {
+                    break;
+                }
+                t.remaining += t.interval;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_set_repeat_and_cancel() {
+        let mut ts = TimerSet::new();
+        ts.set("spawn", 1.0, Repeat::Forever);
+        ts.set("boss", 0.5, Repeat::Once);
+        ts.set("blink", 0.2, Repeat::Times(2));
+        ts.update(0.4);
+        assert!(!ts.just_fired("spawn"));
+        assert_eq!(ts.fired("blink"), 2);
+        assert!((ts.percent("spawn") - 0.4).abs() < 1e-5);
+        ts.update(0.7);
+        assert_eq!(ts.fired("spawn"), 1);
+        assert!(ts.just_fired("boss"));
+        assert!(!ts.is_active("boss"));
+        assert_eq!(ts.fired("blink"), 0);
+        ts.update(2.0);
+        assert_eq!(ts.fired("spawn"), 2);
+        assert!(ts.get("boss").is_none());
+        assert!(ts.cancel("spawn"));
+        ts.update(1.0);
+        assert!(!ts.just_fired("spawn"));
+    }
+}
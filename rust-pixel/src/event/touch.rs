@@ -0,0 +1,193 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! TouchGestures synthesizes taps, long presses and swipes from touch
+//! events, positions are in cells like mouse events.
+//!
+//! A touch is a tap if released quickly without moving, a long press if
+//! held still for long_press seconds, and a swipe if it moved at least
+//! swipe_cells before release.
+
+use crate::event::{Event, TouchEventKind};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    Tap(u16, u16),
+    LongPress(u16, u16),
+    Swipe {
+        dir: SwipeDir,
+        from: (u16, u16),
+        to: (u16, u16),
+    },
+}
+
+// a finger on screen
+struct Touch {
+    start: (u16, u16),
+    pos: (u16, u16),
+    time: f32,
+    // moved away from start, can not be tap or long press
+    moved: bool,
+    long_pressed: bool,
+}
+
+pub struct TouchGestures {
+    /// max seconds of a tap
+    pub tap_time: f32,
+    /// seconds to hold for a long press
+    pub long_press: f32,
+    /// min distance in cells of a swipe
+    pub swipe_cells: u16,
+    touches: HashMap<u64, Touch>,
+}
+
+impl Default for TouchGestures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TouchGestures {
+    pub fn new() -> Self {
+        Self {
+            tap_time: 0.3,
+            long_press: 0.5,
+            swipe_cells: 3,
+            touches: HashMap::new(),
+        }
+    }
+
+    /// processes touch events of this frame, returns recognized gestures
+    pub fn update(&mut self, events: &[Event], dt: f32) -> Vec<Gesture> {
+        let mut gs = vec![];
+        for t in self.touches.values_mut() {
+            t.time += dt;
+            if !t.moved && !t.long_pressed && t.time >= self.long_press {
+                t.long_pressed = true;
+                gs.push(Gesture::LongPress(t.pos.0, t.pos.1));
+            }
+        }
+        for e in events {
+            let Event::Touch(te) = e else {
+                continue;
+            };
+            let pos = (te.column, te.row);
+            match te.kind {
+                TouchEventKind::Start => {
+                    self.touches.insert(
+                        te.id,
+                        Touch {
+                            start: pos,
+                            pos,
+                            time: 0.0,
+                            moved: false,
+                            long_pressed: false,
+                        },
+                    );
+                }
+                TouchEventKind::Move => {
+                    if let Some(t) = self.touches.get_mut(&te.id) {
+                        t.pos = pos;
+                        if pos != t.start {
+                            t.moved = true;
+                        }
+                    }
+                }
+                TouchEventKind::End => {
+                    if let Some(mut t) = self.touches.remove(&te.id) {
+                        t.pos = pos;
+                        if let Some(g) = self.finish(&t) {
+                            gs.push(g);
+                        }
+                    }
+                }
+                TouchEventKind::Cancel => {
+                    self.touches.remove(&te.id);
+                }
+            }
+        }
+        gs
+    }
+
+    fn finish(&self, t: &Touch) -> Option<Gesture> {
+        let dx = t.pos.0 as i32 - t.start.0 as i32;
+        let dy = t.pos.1 as i32 - t.start.1 as i32;
+        let min = self.swipe_cells as i32;
+        if dx.abs() >= min || dy.abs() >= min {
+            let dir = if dx.abs() >= dy.abs() {
+                if dx > 0 {
+                    SwipeDir::Right
+                } else {
+                    SwipeDir::Left
+                }
+            } else if dy > 0 {
+                SwipeDir::Down
+            } else {
+                SwipeDir::Up
+            };
+            return Some(Gesture::Swipe {
+                dir,
+                from: t.start,
+                to: t.pos,
+            });
+        }
+        if !t.moved && !t.long_pressed && t.time <= self.tap_time {
+            return Some(Gesture::Tap(t.pos.0, t.pos.1));
+        }
+        None
+    }
+
+    /// number of fingers on screen
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TouchEvent;
+
+    fn t(id: u64, kind: TouchEventKind, column: u16, row: u16) -> Event {
+        Event::Touch(TouchEvent {
+            id,
+            kind,
+            column,
+            row,
+        })
+    }
+
+    #[test]
+    fn touch_tap_long_press_swipe() {
+        let mut tg = TouchGestures::new();
+        tg.update(&[t(1, TouchEventKind::Start, 5, 5)], 0.0);
+        let g = tg.update(&[t(1, TouchEventKind::End, 5, 5)], 0.1);
+        assert_eq!(g, vec![Gesture::Tap(5, 5)]);
+
+        tg.update(&[t(2, TouchEventKind::Start, 1, 1)], 0.0);
+        assert_eq!(tg.update(&[], 0.6), vec![Gesture::LongPress(1, 1)]);
+        assert!(tg.update(&[t(2, TouchEventKind::End, 1, 1)], 0.1).is_empty());
+
+        tg.update(&[t(3, TouchEventKind::Start, 10, 10)], 0.0);
+        tg.update(&[t(3, TouchEventKind::Move, 8, 10)], 0.05);
+        let g = tg.update(&[t(3, TouchEventKind::End, 4, 11)], 0.05);
+        assert_eq!(
+            g,
+            vec![Gesture::Swipe {
+                dir: SwipeDir::Left,
+                from: (10, 10),
+                to: (4, 11)
+            }]
+        );
+        assert_eq!(tg.touch_count(), 0);
+    }
+}
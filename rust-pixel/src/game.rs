@@ -9,18 +9,23 @@
 //! fn main() -> Result<(), Box<dyn Error>> {
 //!    init_log(log::LevelFilter::Info, "log/snake.log");
 //!    info!("Snake(rust_pixel) start...");
-//!    let ad = Audio::new();
-//!    ad.play_file("assets/snake/back.mp3", true);
 //!    let m = SnakeModel::new();
 //!    let r = SnakeRender::new();
 //!    let mut g = Game::new(m, r);
+//!    g.context.audio.play_music("assets/snake/back.mp3", true, 0.0);
 //!    g.init();
 //!    g.run()?;
 //!    g.render.term.reset(&mut g.context);
 //!    Ok(())
 //! }
 
-use crate::{context::Context, event::timer_update, log::init_log, GAME_FRAME, LOGO_FRAME};
+use crate::{
+    context::Context,
+    event::{event_new_frame, timer_advance},
+    log::init_log,
+    util::{profile_clock, profile_scope, profiler_frame},
+    GAME_FRAME, LOGO_FRAME,
+};
 use log::info;
 use std::{
     io,
@@ -35,7 +40,8 @@ pub trait Model {
         if ctx.stage <= LOGO_FRAME {
             return;
         }
-        timer_update();
+        timer_advance(dt);
+        ctx.timers.update(dt);
         self.handle_event(ctx, dt);
         self.handle_timer(ctx, dt);
         self.handle_input(ctx, dt);
@@ -62,6 +68,95 @@ pub trait Render {
     fn draw(&mut self, ctx: &mut Context, model: &mut Self::Model, dt: f32);
 }
 
+/// How Game runs Model updates, see GameLoop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimestepMode {
+    /// one model update per frame with the measured dt
+    Variable,
+    /// model updates with a constant dt of step seconds, as many as needed
+    /// to catch up with real time but at most max_steps per frame
+    Fixed { step: f32, max_steps: u32 },
+}
+
+/// Game loop policy, in context.game_loop so Model::init can set it.
+/// With TimestepMode::Fixed the simulation runs at the same speed on every
+/// adapter, and render interpolates with alpha().
+#[derive(Debug, Clone)]
+pub struct GameLoop {
+    pub mode: TimestepMode,
+    /// frames per second, 0 is unlimited (web ticks at browser rate)
+    pub target_fps: u32,
+    accumulator: f32,
+    alpha: f32,
+    // time since last frame passed the limiter
+    pending: f32,
+}
+
+impl Default for GameLoop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameLoop {
+    pub fn new() -> Self {
+        Self {
+            mode: TimestepMode::Variable,
+            target_fps: GAME_FRAME,
+            accumulator: 0.0,
+            alpha: 1.0,
+            pending: 0.0,
+        }
+    }
+
+    /// seconds per frame of target_fps
+    pub fn frame_time(&self) -> Option<f32> {
+        (self.target_fps > 0).then(|| 1.0 / self.target_fps as f32)
+    }
+
+    /// frame limiter, returns the dt of a frame to run or None to skip
+    pub fn limit(&mut self, dt: f32) -> Option<f32> {
+        self.pending += dt;
+        match self.frame_time() {
+            // 1ms slack for timer jitter
+            Some(ft) if self.pending + 0.001 < ft => None,
+            _ => Some(std::mem::take(&mut self.pending)),
+        }
+    }
+
+    /// number of fixed model updates for a frame of dt seconds, the rest
+    /// is kept for the next frame, Variable mode always runs one update
+    pub fn steps(&mut self, dt: f32) -> u32 {
+        let TimestepMode::Fixed { step, max_steps } = self.mode else {
+            self.alpha = 1.0;
+            return 1;
+        };
+        self.accumulator += dt;
+        let n = ((self.accumulator / step) as u32).min(max_steps);
+        self.accumulator -= n as f32 * step;
+        // too far behind, drop the backlog instead of slowing down more
+        if n == max_steps {
+            self.accumulator = self.accumulator.min(step);
+        }
+        self.alpha = (self.accumulator / step).clamp(0.0, 1.0);
+        n
+    }
+
+    /// dt passed to Model::update
+    pub fn step_dt(&self, dt: f32) -> f32 {
+        match self.mode {
+            TimestepMode::Fixed { step, .. } => step,
+            TimestepMode::Variable => dt,
+        }
+    }
+
+    /// progress between the last and the next fixed update in 0.0..=1.0,
+    /// always 1.0 in Variable mode
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+}
+
 /// Game encapsulates a Model，a Render and a Context structure
 pub struct Game<M, R>
 where
@@ -71,6 +166,8 @@ where
     pub context: Context,
     pub model: M,
     pub render: R,
+    // time since keys last saw input events
+    input_dt: f32,
 }
 
 impl<M, R> Game<M, R>
@@ -82,6 +179,14 @@ where
         Self::new_with_project_path(m, r, name, None)
     }
 
+    /// game set up by a GameConfig, as made by the config block of pixel_game!
+    pub fn new_with_config(m: M, r: R, name: &str, cfg: GameConfig) -> Self {
+        let mut g = Self::new_with_project_path(m, r, name, cfg.asset_path.as_deref());
+        cfg.apply(&mut g.context);
+        g.context.config = cfg;
+        g
+    }
+
     pub fn new_with_project_path(m: M, r: R, name: &str, project_path: Option<&str>) -> Self {
         let res: Vec<String> = name.split("/").map(|s| s.to_string()).collect();
         let path_name;
@@ -120,6 +225,7 @@ where
             context: ctx,
             model: m,
             render: r,
+            input_dt: 0.0,
         }
     }
 
@@ -130,9 +236,10 @@ where
         info!("Begin run...");
 
         let mut last_tick = Instant::now();
-        let tick_rate = Duration::from_nanos(1_000_000_000 / GAME_FRAME as u64);
 
         loop {
+            let fps = self.context.game_loop.target_fps.max(1);
+            let tick_rate = Duration::from_nanos(1_000_000_000 / fps as u64);
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_nanos(100));
@@ -142,6 +249,9 @@ where
                 .adapter
                 .poll_event(timeout, &mut self.context.input_events)
             {
+                if let Some(f) = self.context.config.on_exit {
+                    f(&mut self.context);
+                }
                 return Ok(());
             }
 
@@ -156,14 +266,75 @@ where
 
     /// calls every frame, update timer, model logic and does rendering
     pub fn on_tick(&mut self, dt: f32) {
+        if self.context.host.paused {
+            return;
+        }
+        let Some(dt) = self.context.game_loop.limit(dt) else {
+            return;
+        };
+        profiler_frame();
+        let _f = profile_scope("frame");
+        // replays feed recorded input and dt back
+        let dt = self
+            .context
+            .replay
+            .process(&mut self.context.input_events, dt);
+        event_new_frame();
+        self.context.net.poll();
+        // fades go on in pause and slow motion
+        self.context.audio.update(dt);
         self.context.stage += 1;
-        self.model.update(&mut self.context, dt);
-        self.render.update(&mut self.context, &mut self.model, dt);
+        self.context.asset_manager.check_hot_reload();
+        // paused models still get a zero dt update to handle input
+        let scale = self.context.effective_time_scale();
+        let (steps, step_dt) = if scale > 0.0 {
+            let sdt = dt * scale;
+            let gl = &mut self.context.game_loop;
+            (gl.steps(sdt), gl.step_dt(sdt))
+        } else {
+            (1, 0.0)
+        };
+        let console = self.context.console.visible;
+        self.context.console.handle_input(&mut self.context.input_events);
+        if console != self.context.console.visible {
+            let on = self.context.console.visible;
+            self.context.adapter.set_text_input(on);
+        }
+        self.context.debug.handle_input(&self.context.input_events);
+        let t0 = profile_clock();
+        self.input_dt += dt;
+        {
+            let _p = profile_scope("update");
+            for _ in 0..steps {
+                // input is consumed by the first update, so keys see it once
+                if self.input_dt > 0.0 && self.context.stage > LOGO_FRAME {
+                    let idt = std::mem::take(&mut self.input_dt);
+                    self.context.keys.update(&mut self.context.input_events, idt);
+                    self.context.players.update(&self.context.input_events);
+                }
+                self.model.update(&mut self.context, step_dt);
+            }
+        }
+        let t1 = profile_clock();
+        {
+            let _p = profile_scope("render");
+            self.render.update(&mut self.context, &mut self.model, dt);
+        }
+        let t2 = profile_clock();
+        self.context
+            .debug
+            .frame(dt, (t1 - t0) as f32, (t2 - t1) as f32);
     }
 
-    /// init render and model
+    /// init render and model, before them the adapter if the config has
+    /// a size and the on_start hook
     pub fn init(&mut self) {
         info!("Init game...");
+        let cfg = self.context.config.clone();
+        cfg.init_adapter(&mut self.context);
+        if let Some(f) = cfg.on_start {
+            f(&mut self.context);
+        }
         self.model.init(&mut self.context);
         self.render.init(&mut self.context, &mut self.model);
     }
@@ -190,3 +361,89 @@ macro_rules! only_graphics_mode {
         }
     };
 }
+
+mod scene;
+pub use scene::*;
+
+mod sequence;
+pub use sequence::*;
+
+mod headless;
+pub use headless::*;
+
+mod debug;
+pub use debug::*;
+
+mod console;
+pub use console::*;
+
+mod config;
+pub use config::*;
+
+mod host;
+pub use host::*;
+
+#[cfg(all(feature = "hotreload", not(target_arch = "wasm32")))]
+mod hot;
+#[cfg(all(feature = "hotreload", not(target_arch = "wasm32")))]
+pub use hot::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_loop_fixed_steps_and_limiter() {
+        let mut gl = GameLoop::new();
+        gl.target_fps = 50;
+        assert_eq!(gl.limit(0.005), None);
+        assert_eq!(gl.limit(0.015), Some(0.02));
+
+        gl.mode = TimestepMode::Fixed {
+            step: 0.1,
+            max_steps: 3,
+        };
+        assert_eq!(gl.steps(0.25), 2);
+        assert!((gl.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(gl.steps(0.06), 1);
+        // far behind, backlog is dropped
+        assert_eq!(gl.steps(2.0), 3);
+        assert!(gl.alpha() <= 1.0);
+        assert_eq!(gl.step_dt(0.5), 0.1);
+    }
+
+    #[test]
+    fn game_config_applies_to_context() {
+        let cfg = GameConfig::new()
+            .size(40, 20)
+            .fps(30)
+            .feature("fixed")
+            .feature("debug")
+            .feature("no_console")
+            .feature("hard");
+        let mut ctx = Context::new("games", "test", "");
+        cfg.apply(&mut ctx);
+        assert_eq!(ctx.game_loop.target_fps, 30);
+        assert!(
+            matches!(ctx.game_loop.mode, TimestepMode::Fixed { step, .. } if (step - 1.0 / 30.0).abs() < 1e-6)
+        );
+        assert!(ctx.debug.visible);
+        assert_eq!(ctx.console.toggle_key, None);
+        assert!(cfg.has_feature("hard"));
+        assert_eq!(GameConfig::new().feature("a").feature("a").features.len(), 1);
+    }
+
+    #[test]
+    fn game_config_hooks() {
+        fn start(ctx: &mut Context) {
+            ctx.debug.visible = true;
+        }
+        let cfg = GameConfig::new().on_start(start);
+        assert!(cfg.on_exit.is_none());
+        let mut ctx = Context::new("games", "test", "");
+        if let Some(f) = cfg.clone().on_start {
+            f(&mut ctx);
+        }
+        assert!(ctx.debug.visible);
+    }
+}
@@ -0,0 +1,132 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! GameConfig is the config block of pixel_game!, applied by Game so
+//! Render::init does not have to hard-code the adapter size and title.
+//!
+//! With size set Game::init inits the adapter before Model::init, the
+//! render then only inits its panel. Feature names switch built-in
+//! helpers on at start: "debug" shows the debug hud, "fixed" runs the
+//! model at a fixed timestep of 1 / fps, "no_console" disables the
+//! developer console. Other names are kept for the game to query with
+//! has_feature.
+//!
+//! on_start runs after the adapter init and before Model::init, e.g. to
+//! register embedded assets or add themes. on_exit runs when the native
+//! main loop ends, e.g. to save settings, the web has no exit.
+//!
+//! A scenes list makes the game a SceneStack of Model/Render pairs, the
+//! first scene is entered at start, see SceneStack.
+
+use crate::{context::Context, game::TimestepMode};
+
+#[derive(Debug, Clone)]
+pub struct GameConfig {
+    /// adapter size in cells, None leaves adapter.init to Render::init
+    pub size: Option<(u16, u16)>,
+    /// cell scale of graphics mode
+    pub ratio: (f32, f32),
+    /// window title, the game name if None
+    pub title: Option<String>,
+    pub fps: Option<u32>,
+    /// project path assets and saves are relative to
+    pub asset_path: Option<String>,
+    pub features: Vec<String>,
+    pub on_start: Option<fn(&mut Context)>,
+    pub on_exit: Option<fn(&mut Context)>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            size: None,
+            ratio: (1.0, 1.0),
+            title: None,
+            fps: None,
+            asset_path: None,
+            features: vec![],
+            on_start: None,
+            on_exit: None,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn size(mut self, w: u16, h: u16) -> Self {
+        self.size = Some((w, h));
+        self
+    }
+
+    pub fn ratio(mut self, rx: f32, ry: f32) -> Self {
+        self.ratio = (rx, ry);
+        self
+    }
+
+    pub fn title(mut self, t: &str) -> Self {
+        self.title = Some(t.to_string());
+        self
+    }
+
+    pub fn fps(mut self, fps: u32) -> Self {
+        self.fps = Some(fps);
+        self
+    }
+
+    pub fn asset_path(mut self, p: &str) -> Self {
+        self.asset_path = Some(p.to_string());
+        self
+    }
+
+    pub fn feature(mut self, f: &str) -> Self {
+        if !self.has_feature(f) {
+            self.features.push(f.to_string());
+        }
+        self
+    }
+
+    pub fn on_start(mut self, f: fn(&mut Context)) -> Self {
+        self.on_start = Some(f);
+        self
+    }
+
+    pub fn on_exit(mut self, f: fn(&mut Context)) -> Self {
+        self.on_exit = Some(f);
+        self
+    }
+
+    pub fn has_feature(&self, f: &str) -> bool {
+        self.features.iter().any(|s| s == f)
+    }
+
+    /// sets up the context before Model::init, called by Game::new_with_config
+    pub fn apply(&self, ctx: &mut Context) {
+        if let Some(fps) = self.fps {
+            ctx.game_loop.target_fps = fps;
+        }
+        if self.has_feature("fixed") {
+            let fps = ctx.game_loop.target_fps.max(1);
+            ctx.game_loop.mode = TimestepMode::Fixed {
+                step: 1.0 / fps as f32,
+                max_steps: 5,
+            };
+        }
+        if self.has_feature("debug") {
+            ctx.debug.visible = true;
+        }
+        if self.has_feature("no_console") {
+            ctx.console.toggle_key = None;
+        }
+    }
+
+    /// inits the adapter if size is set, called by Game::init
+    pub fn init_adapter(&self, ctx: &mut Context) {
+        if let Some((w, h)) = self.size {
+            let title = self.title.clone().unwrap_or_else(|| ctx.game_name.clone());
+            ctx.adapter.init(w, h, self.ratio.0, self.ratio.1, title);
+        }
+    }
+}
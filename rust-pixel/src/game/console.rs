@@ -0,0 +1,314 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Console lives in context.console, a drop-down command line for
+//! development. toggle_key (`) opens it at the top of the screen, while
+//! open it takes all input events of the frame. Games register commands
+//! with a closure, its output goes to the scrollback. Every command run
+//! is also emitted as CONSOLE_EVENT with a ConsoleCommand as data, so a
+//! model can handle commands that need the context, and is logged.
+//! Up and Down walk the history, Tab completes command names, PageUp and
+//! PageDown scroll back. help and clear are built in.
+
+use crate::event::{event_emit_data, Event, KeyCode, KeyEventKind, TextInput, TextInputAction};
+use crate::render::{buffer::Buffer, style::Theme};
+use crate::ui::{LogPanel, Rect, UiInput, UiWidget};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// emitted with a ConsoleCommand as data for every command run
+pub const CONSOLE_EVENT: &str = "Console.Command";
+
+// history entries kept
+const HISTORY: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+type CommandFn = Box<dyn FnMut(&[&str]) -> Result<String, String>>;
+
+pub struct Console {
+    pub visible: bool,
+    pub toggle_key: Option<KeyCode>,
+    /// rows of the drop-down, with the input line
+    pub height: u16,
+    pub input: TextInput,
+    /// scrollback of commands and their output
+    pub log: LogPanel,
+    // commands by name, None for event only commands
+    commands: BTreeMap<String, Option<CommandFn>>,
+    history: Vec<String>,
+    // position while walking the history, history.len() is the new line
+    history_pos: usize,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        let mut c = Self {
+            visible: false,
+            toggle_key: Some(KeyCode::Char('`')),
+            height: 12,
+            input: TextInput::new(),
+            log: LogPanel::new(Rect::default(), 500),
+            commands: BTreeMap::new(),
+            history: vec![],
+            history_pos: 0,
+        };
+        c.register_event("help");
+        c.register_event("clear");
+        c
+    }
+
+    /// adds a command, its output or error is printed when it runs
+    pub fn register<F>(&mut self, name: &str, f: F)
+    where
+        F: FnMut(&[&str]) -> Result<String, String> + 'static,
+    {
+        self.commands.insert(name.to_string(), Some(Box::new(f)));
+    }
+
+    /// adds a command only emitted as CONSOLE_EVENT
+    pub fn register_event(&mut self, name: &str) {
+        self.commands.insert(name.to_string(), None);
+    }
+
+    pub fn unregister(&mut self, name: &str) {
+        self.commands.remove(name);
+    }
+
+    /// adds lines to the scrollback, style markup allowed
+    pub fn print(&mut self, text: &str) {
+        for l in text.lines() {
+            self.log.push(l);
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// runs a command line as if typed
+    pub fn execute(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        info!("console: {}", line);
+        self.print(&format!("> {}", line));
+        if self.history.last().map(|h| h.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+            if self.history.len() > HISTORY {
+                self.history.remove(0);
+            }
+        }
+        self.history_pos = self.history.len();
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (name, args) = (words[0], &words[1..]);
+        let out = match self.commands.get_mut(name) {
+            None => Err(format!("unknown command: {}", name)),
+            Some(Some(f)) => f(args),
+            Some(None) => match name {
+                "help" => Ok(self.commands.keys().cloned().collect::<Vec<_>>().join(" ")),
+                "clear" => {
+                    self.log.clear();
+                    Ok(String::new())
+                }
+                _ => Ok(String::new()),
+            },
+        };
+        match out {
+            Ok(s) => self.print(&s),
+            Err(e) => {
+                info!("console: {}", e);
+                self.print(&format!("[red]{}[/red]", e));
+            }
+        }
+        if self.commands.contains_key(name) {
+            let c = ConsoleCommand {
+                name: name.to_string(),
+                args: args.iter().map(|a| a.to_string()).collect(),
+            };
+            event_emit_data(CONSOLE_EVENT, &c);
+        }
+    }
+
+    /// completes the command name being typed, lists the candidates if
+    /// there are several
+    pub fn complete(&mut self) {
+        let text = self.input.text().to_string();
+        if text.contains(' ') {
+            return;
+        }
+        let found: Vec<&String> = self
+            .commands
+            .keys()
+            .filter(|k| k.starts_with(&text))
+            .collect();
+        match found.len() {
+            0 => {}
+            1 => self.input.set_text(&format!("{} ", found[0])),
+            _ => {
+                // extends to the common prefix
+                let mut p = found[0].clone();
+                for f in &found[1..] {
+                    while !f.starts_with(&p) {
+                        p.pop();
+                    }
+                }
+                let list = found.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                self.print(&list.join(" "));
+                self.input.set_text(&p);
+            }
+        }
+    }
+
+    fn walk_history(&mut self, up: bool) {
+        if up && self.history_pos > 0 {
+            self.history_pos -= 1;
+        } else if !up && self.history_pos < self.history.len() {
+            self.history_pos += 1;
+        } else {
+            return;
+        }
+        let s = self
+            .history
+            .get(self.history_pos)
+            .cloned()
+            .unwrap_or_default();
+        self.input.set_text(&s);
+    }
+
+    /// toggles on key press and takes all events while open, called by
+    /// the game loop before the model sees them
+    pub fn handle_input(&mut self, events: &mut Vec<Event>) {
+        let toggle = match self.toggle_key {
+            Some(KeyCode::Char(c)) => Some(c.to_string()),
+            _ => None,
+        };
+        let was = self.visible;
+        for e in events.iter() {
+            match e {
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press && Some(k.code) == self.toggle_key =>
+                {
+                    self.visible = !self.visible;
+                }
+                // typed along with the toggle key in text input mode
+                Event::Text(s) if Some(s) == toggle.as_ref() => {}
+                _ if !self.visible => {}
+                Event::Key(k) if k.kind != KeyEventKind::Release && k.code == KeyCode::Up => {
+                    self.walk_history(true)
+                }
+                Event::Key(k) if k.kind != KeyEventKind::Release && k.code == KeyCode::Down => {
+                    self.walk_history(false)
+                }
+                Event::Key(k) if k.kind != KeyEventKind::Release && k.code == KeyCode::Tab => {
+                    self.complete()
+                }
+                Event::Key(k) if matches!(k.code, KeyCode::PageUp | KeyCode::PageDown) => {
+                    self.log.handle(UiInput::Key(*k));
+                }
+                _ => match self.input.update(std::slice::from_ref(e)) {
+                    Some(TextInputAction::Submit) => {
+                        let line = self.input.text().to_string();
+                        self.input.clear();
+                        self.execute(&line);
+                    }
+                    Some(TextInputAction::Cancel) => self.visible = false,
+                    _ => {}
+                },
+            }
+        }
+        if was || self.visible {
+            events.clear();
+        }
+    }
+
+    /// draws the scrollback and the input line into buf, full width
+    pub fn draw(&mut self, buf: &mut Buffer, theme: &Theme) {
+        let a = buf.area;
+        let h = self.height.min(a.height).max(1);
+        self.log.set_area(Rect::new(0, 0, a.width, h - 1));
+        self.log.draw(buf, theme, false);
+        let st = theme.style("ui.fg", "ui.bg");
+        buf.set_string(a.x, a.y + h - 1, "> ", st);
+        self.input
+            .draw(buf, a.x + 2, a.y + h - 1, a.width.saturating_sub(2), st);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{event_check_data, event_register, KeyEvent, KeyModifiers};
+    use std::{cell::Cell, rc::Rc};
+
+    fn key(c: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(c, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn console_commands() {
+        let given = Rc::new(Cell::new(0));
+        let g = given.clone();
+        let mut c = Console::new();
+        c.register("give", move |args| match args {
+            [_, n] => {
+                g.set(n.parse().map_err(|_| "not a number".to_string())?);
+                Ok(format!("gave {}", n))
+            }
+            _ => Err("usage: give <item> <n>".to_string()),
+        });
+        c.register_event("god");
+        event_register(CONSOLE_EVENT, "console_test");
+
+        // closed, events pass
+        let mut ev = vec![key(KeyCode::Char('a'))];
+        c.handle_input(&mut ev);
+        assert_eq!(ev.len(), 1);
+
+        let mut ev = vec![
+            key(KeyCode::Char('`')),
+            Event::Text("`".to_string()),
+            Event::Text("gi".to_string()),
+            key(KeyCode::Tab),
+            Event::Text("gold 5".to_string()),
+            key(KeyCode::Enter),
+        ];
+        c.handle_input(&mut ev);
+        assert!(ev.is_empty());
+        assert!(c.visible);
+        assert_eq!(given.get(), 5);
+        assert_eq!(
+            c.log.lines().collect::<Vec<_>>(),
+            vec!["> give gold 5", "gave 5"]
+        );
+        let cmd: ConsoleCommand = event_check_data(CONSOLE_EVENT, "console_test").unwrap();
+        assert_eq!(cmd.args, vec!["gold", "5"]);
+
+        c.execute("give x");
+        c.execute("nope");
+        assert_eq!(
+            c.log.lines().last(),
+            Some("[red]unknown command: nope[/red]")
+        );
+        c.input.set_text("g");
+        c.complete();
+        assert_eq!(c.input.text(), "g");
+        c.handle_input(&mut vec![key(KeyCode::Up), key(KeyCode::Up)]);
+        assert_eq!(c.input.text(), "give x");
+        c.execute("clear");
+        assert!(c.log.is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! DebugHud lives in context.debug and collects fps, frame, update and
+//! draw times, GL draw calls and instances and user gauges. When visible
+//! Panel::draw shows them in a top layer. toggle_key (F3) switches it.
+//! While the profiler is enabled its scope times are listed as well.
+//! colorblind_key (F4) cycles colorblind, which renders the cells of
+//! every frame as seen with that color vision deficiency.
+
+use crate::{
+    event::{Event, KeyCode, KeyEventKind},
+    render::style::ColorBlindness,
+    util::{profiler_enabled, profiler_stats},
+};
+use std::collections::BTreeMap;
+
+// weight of a new frame in the smoothed values
+const SMOOTH: f32 = 0.1;
+
+#[derive(Debug, Clone)]
+pub struct DebugHud {
+    pub visible: bool,
+    pub toggle_key: Option<KeyCode>,
+    /// frame simulated as seen with a color vision deficiency
+    pub colorblind: Option<ColorBlindness>,
+    pub colorblind_key: Option<KeyCode>,
+    /// smoothed frame seconds
+    pub frame_time: f32,
+    /// smoothed seconds of model updates and render of a frame
+    pub update_time: f32,
+    pub draw_time: f32,
+    /// GL draw calls and instances of the last frame, graphics mode only
+    pub draw_calls: u32,
+    pub instances: u32,
+    gauges: BTreeMap<String, f64>,
+}
+
+impl Default for DebugHud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn smooth(old: f32, v: f32) -> f32 {
+    if old == 0.0 {
+        v
+    } else {
+        old + (v - old) * SMOOTH
+    }
+}
+
+impl DebugHud {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            toggle_key: Some(KeyCode::F(3)),
+            colorblind: None,
+            colorblind_key: Some(KeyCode::F(4)),
+            frame_time: 0.0,
+            update_time: 0.0,
+            draw_time: 0.0,
+            draw_calls: 0,
+            instances: 0,
+            gauges: BTreeMap::new(),
+        }
+    }
+
+    /// sets a named value shown until changed or removed
+    pub fn gauge(&mut self, name: &str, v: f64) {
+        self.gauges.insert(name.to_string(), v);
+    }
+
+    pub fn remove_gauge(&mut self, name: &str) {
+        self.gauges.remove(name);
+    }
+
+    pub fn get_gauge(&self, name: &str) -> Option<f64> {
+        self.gauges.get(name).copied()
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.frame_time > 0.0 {
+            1.0 / self.frame_time
+        } else {
+            0.0
+        }
+    }
+
+    /// off, then each of ColorBlindness::ALL
+    pub fn cycle_colorblind(&mut self) {
+        let all = ColorBlindness::ALL;
+        self.colorblind = match self.colorblind {
+            None => Some(all[0]),
+            Some(c) => all
+                .iter()
+                .position(|a| *a == c)
+                .and_then(|i| all.get(i + 1).copied()),
+        };
+    }
+
+    /// toggles on key press, called by the game loop
+    pub fn handle_input(&mut self, events: &[Event]) {
+        for e in events {
+            if let Event::Key(ke) = e {
+                if ke.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if Some(ke.code) == self.toggle_key {
+                    self.visible = !self.visible;
+                }
+                if Some(ke.code) == self.colorblind_key {
+                    self.cycle_colorblind();
+                }
+            }
+        }
+    }
+
+    /// timings of a frame, called by the game loop
+    pub fn frame(&mut self, dt: f32, update: f32, draw: f32) {
+        self.frame_time = smooth(self.frame_time, dt);
+        self.update_time = smooth(self.update_time, update);
+        self.draw_time = smooth(self.draw_time, draw);
+    }
+
+    /// text lines of the hud
+    pub fn lines(&self) -> Vec<String> {
+        let mut v = vec![
+            format!("fps {:.1} {:.2}ms", self.fps(), self.frame_time * 1000.0),
+            format!(
+                "update {:.2}ms draw {:.2}ms",
+                self.update_time * 1000.0,
+                self.draw_time * 1000.0
+            ),
+        ];
+        if let Some(cb) = self.colorblind {
+            v.push(format!("{:?}", cb).to_lowercase());
+        }
+        if self.draw_calls > 0 {
+            v.push(format!("gl {} calls {} inst", self.draw_calls, self.instances));
+        }
+        for (k, g) in &self.gauges {
+            v.push(format!("{} {}", k, g));
+        }
+        if profiler_enabled() {
+            for (k, s) in profiler_stats() {
+                v.push(format!("{} {:.2}ms max {:.2}", k, s.avg, s.max));
+            }
+        }
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyEvent, KeyModifiers};
+
+    #[test]
+    fn debug_hud_collects_stats() {
+        let mut d = DebugHud::new();
+        d.frame(0.02, 0.004, 0.002);
+        d.frame(0.02, 0.004, 0.002);
+        assert!((d.fps() - 50.0).abs() < 0.01);
+        d.gauge("bullets", 12.0);
+        d.gauge("speed", 1.5);
+        assert_eq!(d.get_gauge("bullets"), Some(12.0));
+        let ls = d.lines();
+        assert_eq!(ls[0], "fps 50.0 20.00ms");
+        assert_eq!(ls[2], "bullets 12");
+        assert_eq!(ls[3], "speed 1.5");
+
+        let f3 = Event::Key(KeyEvent::new(KeyCode::F(3), KeyModifiers::NONE));
+        d.handle_input(std::slice::from_ref(&f3));
+        assert!(d.visible);
+        d.handle_input(&[f3]);
+        assert!(!d.visible);
+
+        let f4 = Event::Key(KeyEvent::new(KeyCode::F(4), KeyModifiers::NONE));
+        d.handle_input(std::slice::from_ref(&f4));
+        assert_eq!(d.colorblind, Some(ColorBlindness::Protanopia));
+        for _ in 0..4 {
+            d.handle_input(std::slice::from_ref(&f4));
+        }
+        assert_eq!(d.colorblind, None);
+    }
+}
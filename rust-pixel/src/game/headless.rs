@@ -0,0 +1,127 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Headless games run a Model without Render, window or terminal, e.g.
+//! as authoritative server of a networked game or to fast-forward a
+//! simulation in tests. The context gets a NullAdapter and the game skips
+//! the logo. run() ticks target_fps times a second until stop_headless is
+//! called, step() runs ticks at once without waiting.
+
+use crate::{
+    context::Context,
+    event::Event,
+    game::{Game, Model, Render},
+    render::adapter::null::NullAdapter,
+    GAME_FRAME, LOGO_FRAME,
+};
+use std::marker::PhantomData;
+
+/// Render doing nothing, used by Game::new_headless
+pub struct NullRender<M>(PhantomData<M>);
+
+impl<M> Default for NullRender<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Model> Render for NullRender<M> {
+    type Model = M;
+
+    fn init(&mut self, _ctx: &mut Context, _m: &mut M) {}
+
+    fn handle_event(&mut self, _ctx: &mut Context, _m: &mut M, _dt: f32) {}
+
+    fn handle_timer(&mut self, _ctx: &mut Context, _m: &mut M, _dt: f32) {}
+
+    fn draw(&mut self, _ctx: &mut Context, _m: &mut M, _dt: f32) {}
+}
+
+/// ends run() of a headless game, for models running as server
+pub fn stop_headless(ctx: &mut Context) {
+    if let Some(a) = ctx.adapter.as_any().downcast_mut::<NullAdapter>() {
+        a.quit = true;
+    }
+}
+
+impl<M: Model> Game<M, NullRender<M>> {
+    /// game without render and devices ticking tps times a second
+    pub fn new_headless(m: M, name: &str, tps: u32) -> Self {
+        let mut g = Self::new(m, NullRender::default(), name);
+        let c = &mut g.context;
+        c.adapter = Box::new(NullAdapter::new(
+            &c.prefix_path,
+            &c.game_name,
+            &c.project_path,
+        ));
+        c.game_loop.target_fps = tps;
+        c.stage = LOGO_FRAME;
+        g
+    }
+
+    /// input handled by the next tick
+    pub fn push_input(&mut self, e: Event) {
+        self.context.input_events.push(e);
+    }
+
+    /// runs ticks frames at once, each with the dt of a frame
+    pub fn step(&mut self, ticks: u32) {
+        let dt = self
+            .context
+            .game_loop
+            .frame_time()
+            .unwrap_or(1.0 / GAME_FRAME as f32);
+        for _ in 0..ticks {
+            self.on_tick(dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[derive(Default)]
+    struct Counter {
+        ticks: u32,
+        time: f32,
+        keys: usize,
+    }
+
+    impl Model for Counter {
+        fn init(&mut self, _ctx: &mut Context) {}
+        fn handle_timer(&mut self, _ctx: &mut Context, _dt: f32) {}
+        fn handle_event(&mut self, _ctx: &mut Context, _dt: f32) {}
+        fn handle_input(&mut self, ctx: &mut Context, _dt: f32) {
+            self.keys += ctx.input_events.len();
+            ctx.input_events.clear();
+        }
+        fn handle_auto(&mut self, ctx: &mut Context, dt: f32) {
+            self.ticks += 1;
+            self.time += dt;
+            if self.ticks == 100 {
+                stop_headless(ctx);
+            }
+        }
+    }
+
+    #[test]
+    fn headless_game_steps_and_stops() {
+        let mut g = Game::new_headless(Counter::default(), "test", 50);
+        g.init();
+        g.push_input(Event::Key(KeyEvent::new(
+            KeyCode::Enter,
+            KeyModifiers::NONE,
+        )));
+        g.step(50);
+        assert_eq!(g.model.ticks, 50);
+        assert!((g.model.time - 1.0).abs() < 1e-3);
+        assert_eq!(g.model.keys, 1);
+
+        // real time loop ends when the model stops it
+        g.context.game_loop.target_fps = 1000;
+        g.run().unwrap();
+        assert_eq!(g.model.ticks, 100);
+    }
+}
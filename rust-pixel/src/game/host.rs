@@ -0,0 +1,82 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Host lives in context.host, the link to the web page running a wasm
+//! build. The pixel_game! wrapper exports pause, resume, send_event,
+//! resize and set_frame_callback / set_message_callback to js, so the page
+//! controls the game without patching the generated code.
+//!
+//! send_event(name, json) from the page is emitted as HOST_EVENT with a
+//! HostMessage as data. The game talks back with post, the messages go
+//! to the message callback of the page after the frame. While paused
+//! Game::on_tick does nothing, the canvas keeps the last frame.
+
+use crate::event::event_emit_data;
+use serde::{Deserialize, Serialize};
+
+/// emitted with a HostMessage as data for every send_event of the page
+pub const HOST_EVENT: &str = "Host.Event";
+
+/// a named message between the game and its page, data is json text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostMessage {
+    pub name: String,
+    pub data: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Host {
+    /// set by the page, no ticks run while paused
+    pub paused: bool,
+    // posted by the game, taken by the wrapper after each frame
+    posts: Vec<HostMessage>,
+}
+
+impl Host {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a message from the page, emitted as HOST_EVENT
+    pub fn receive(&self, name: &str, data: &str) {
+        let m = HostMessage {
+            name: name.to_string(),
+            data: data.to_string(),
+        };
+        event_emit_data(HOST_EVENT, &m);
+    }
+
+    /// sends a message to the page
+    pub fn post(&mut self, name: &str, data: &str) {
+        self.posts.push(HostMessage {
+            name: name.to_string(),
+            data: data.to_string(),
+        });
+    }
+
+    /// messages posted since the last call, oldest first
+    pub fn take_posts(&mut self) -> Vec<HostMessage> {
+        std::mem::take(&mut self.posts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{event_check_data, event_register};
+
+    #[test]
+    fn host_messages_both_ways() {
+        let mut h = Host::new();
+        event_register(HOST_EVENT, "host_test");
+        h.receive("level", "{\"n\":3}");
+        let m = event_check_data::<HostMessage>(HOST_EVENT, "host_test").unwrap();
+        assert_eq!((m.name.as_str(), m.data.as_str()), ("level", "{\"n\":3}"));
+
+        h.post("score", "10");
+        h.post("over", "{}");
+        let names: Vec<String> = h.take_posts().into_iter().map(|m| m.name).collect();
+        assert_eq!(names, vec!["score", "over"]);
+        assert!(h.take_posts().is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Hot reload of game logic, a dev mode enabled by the hotreload feature.
+//! The game's Model and Render are built as a dylib exporting a HotPair
+//! with pixel_hot_export!. A small host binary runs HotModel and
+//! HotRender, which load the library and reload it whenever it is
+//! rebuilt. The model is kept across reloads by serializing it, so it
+//! must derive Serialize and Deserialize.
+//!
+//! After a reload Render::init of the new render runs against a
+//! NullAdapter, so windows and terminal are not set up again.
+//! The library has its own copy of rust_pixel globals (event center,
+//! timers), they work inside the logic but are not shared with the host.
+//! Both must be built by the same compiler with the same features.
+
+use crate::{
+    context::Context,
+    event::event_new_frame,
+    game::{Model, Render},
+    render::adapter::{null::NullAdapter, Adapter},
+};
+use libloading::{Library, Symbol};
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::SystemTime;
+
+// seconds between checks of the library file
+const RELOAD_CHECK: f32 = 0.5;
+
+/// symbol exported by pixel_hot_export!
+pub const HOT_CREATE: &[u8] = b"pixel_hot_create";
+
+/// Game logic living in a hot reloaded library
+pub trait HotLogic {
+    fn init(&mut self, ctx: &mut Context);
+    /// Render::init after a reload
+    fn reinit(&mut self, ctx: &mut Context);
+    fn update(&mut self, ctx: &mut Context, dt: f32);
+    fn draw(&mut self, ctx: &mut Context, dt: f32);
+    fn save_state(&self) -> Result<Vec<u8>, String>;
+    fn load_state(&mut self, data: &[u8]) -> Result<(), String>;
+}
+
+/// A Model and its Render as HotLogic
+pub struct HotPair<M, R> {
+    pub model: M,
+    pub render: R,
+}
+
+impl<M, R> HotPair<M, R> {
+    pub fn new(model: M, render: R) -> Self {
+        Self { model, render }
+    }
+}
+
+impl<M, R> HotLogic for HotPair<M, R>
+where
+    M: Model + Serialize + DeserializeOwned,
+    R: Render<Model = M>,
+{
+    fn init(&mut self, ctx: &mut Context) {
+        self.model.init(ctx);
+        self.render.init(ctx, &mut self.model);
+    }
+
+    fn reinit(&mut self, ctx: &mut Context) {
+        self.render.init(ctx, &mut self.model);
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: f32) {
+        self.model.update(ctx, dt);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, dt: f32) {
+        self.render.update(ctx, &mut self.model, dt);
+        // ends the frame of the library's event center
+        event_new_frame();
+    }
+
+    fn save_state(&self) -> Result<Vec<u8>, String> {
+        bincode::serialize(&self.model).map_err(|e| e.to_string())
+    }
+
+    fn load_state(&mut self, data: &[u8]) -> Result<(), String> {
+        self.model = bincode::deserialize(data).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// exports the constructor of a HotPair from the game library
+#[macro_export]
+macro_rules! pixel_hot_export {
+    ($new:expr) => {
+        #[no_mangle]
+        pub fn pixel_hot_create() -> Box<dyn $crate::game::HotLogic> {
+            Box::new($new)
+        }
+    };
+}
+
+/// path of a library built by cargo, e.g. target/debug/libsnake.so
+pub fn hot_lib_path(dir: &str, name: &str) -> String {
+    use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+    format!(
+        "{}{}{}{}{}",
+        dir,
+        std::path::MAIN_SEPARATOR,
+        DLL_PREFIX,
+        name,
+        DLL_SUFFIX
+    )
+}
+
+/// Model forwarding to the logic of a hot reloaded library
+pub struct HotModel {
+    path: String,
+    // logic must drop before its library
+    logic: Option<Box<dyn HotLogic>>,
+    lib: Option<Library>,
+    modified: Option<SystemTime>,
+    // changed mtime waiting to settle while cargo writes the file
+    pending: Option<SystemTime>,
+    check_left: f32,
+    loads: u32,
+    /// last load error, the old logic keeps running
+    pub error: Option<String>,
+}
+
+impl HotModel {
+    pub fn new(lib_path: &str) -> Self {
+        Self {
+            path: lib_path.to_string(),
+            logic: None,
+            lib: None,
+            modified: None,
+            pending: None,
+            check_left: RELOAD_CHECK,
+            loads: 0,
+            error: None,
+        }
+    }
+
+    /// number of successful loads, 1 after init
+    pub fn loads(&self) -> u32 {
+        self.loads
+    }
+
+    fn mtime(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok()
+    }
+
+    fn open(&mut self) -> Result<(Library, Box<dyn HotLogic>), String> {
+        // a copy per load, the loader caches libraries by path
+        let copy = format!("{}.hot{}", self.path, self.loads);
+        std::fs::copy(&self.path, &copy).map_err(|e| format!("{}: {}", self.path, e))?;
+        unsafe {
+            let lib = Library::new(&copy).map_err(|e| e.to_string())?;
+            let _ = std::fs::remove_file(&copy);
+            let logic = {
+                let create: Symbol<fn() -> Box<dyn HotLogic>> =
+                    lib.get(HOT_CREATE).map_err(|e| e.to_string())?;
+                create()
+            };
+            Ok((lib, logic))
+        }
+    }
+
+    fn load(&mut self, ctx: &mut Context) {
+        self.modified = self.mtime();
+        let (lib, mut logic) = match self.open() {
+            Ok(r) => r,
+            Err(e) => {
+                info!("hot load error:{}", e);
+                self.error = Some(e);
+                return;
+            }
+        };
+        match self.logic.take() {
+            Some(old) => {
+                let state = old.save_state().and_then(|s| logic.load_state(&s));
+                if let Err(e) = state {
+                    info!("hot reload state lost:{}", e);
+                    self.error = Some(e);
+                }
+                drop(old);
+                // render init must not set up window or terminal again
+                let size = ctx.adapter.size();
+                let mut null =
+                    NullAdapter::new(&ctx.prefix_path, &ctx.game_name, &ctx.project_path);
+                null.set_size(size.width, size.height);
+                let real = std::mem::replace(&mut ctx.adapter, Box::new(null));
+                logic.reinit(ctx);
+                ctx.adapter = real;
+                info!("hot reloaded {}", self.path);
+            }
+            None => logic.init(ctx),
+        }
+        self.logic = Some(logic);
+        self.lib = Some(lib);
+        self.loads += 1;
+    }
+
+    fn check_reload(&mut self, ctx: &mut Context, dt: f32) {
+        self.check_left -= dt;
+        if self.check_left > 0.0 {
+            return;
+        }
+        self.check_left = RELOAD_CHECK;
+        let m = self.mtime();
+        if m.is_none() || m == self.modified {
+            self.pending = None;
+        } else if m == self.pending {
+            self.pending = None;
+            self.error = None;
+            self.load(ctx);
+        } else {
+            self.pending = m;
+        }
+    }
+}
+
+impl Drop for HotModel {
+    fn drop(&mut self) {
+        self.logic.take();
+        self.lib.take();
+    }
+}
+
+impl Model for HotModel {
+    fn init(&mut self, ctx: &mut Context) {
+        self.load(ctx);
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: f32) {
+        self.check_reload(ctx, dt);
+        if let Some(l) = &mut self.logic {
+            l.update(ctx, dt);
+        }
+    }
+
+    fn handle_timer(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_event(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_input(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_auto(&mut self, _ctx: &mut Context, _dt: f32) {}
+}
+
+/// Render drawing the logic of a HotModel
+pub struct HotRender;
+
+impl Render for HotRender {
+    type Model = HotModel;
+
+    fn init(&mut self, _ctx: &mut Context, _m: &mut HotModel) {}
+    fn handle_event(&mut self, _ctx: &mut Context, _m: &mut HotModel, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _m: &mut HotModel, _dt: f32) {}
+
+    fn draw(&mut self, ctx: &mut Context, m: &mut HotModel, dt: f32) {
+        if let Some(l) = &mut m.logic {
+            l.draw(ctx, dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::NullRender;
+    use serde::Deserialize;
+
+    #[derive(Default, Serialize, Deserialize)]
+    struct Score {
+        points: u32,
+    }
+
+    impl Model for Score {
+        fn init(&mut self, _ctx: &mut Context) {}
+        fn handle_timer(&mut self, _ctx: &mut Context, _dt: f32) {}
+        fn handle_event(&mut self, _ctx: &mut Context, _dt: f32) {}
+        fn handle_input(&mut self, _ctx: &mut Context, _dt: f32) {}
+        fn handle_auto(&mut self, _ctx: &mut Context, _dt: f32) {
+            self.points += 1;
+        }
+    }
+
+    #[test]
+    fn hot_pair_keeps_state_and_missing_lib_fails() {
+        let mut ctx = Context::new("games", "test", "");
+        ctx.stage = crate::LOGO_FRAME + 1;
+        let mut a = HotPair::new(Score::default(), NullRender::default());
+        a.init(&mut ctx);
+        a.update(&mut ctx, 0.1);
+        a.update(&mut ctx, 0.1);
+        let mut b = HotPair::new(Score::default(), NullRender::default());
+        b.load_state(&a.save_state().unwrap()).unwrap();
+        assert_eq!(b.model.points, 2);
+
+        let p = hot_lib_path("target", "no_such_game");
+        assert!(p.contains("no_such_game"));
+        let mut m = HotModel::new(&p);
+        m.init(&mut ctx);
+        m.update(&mut ctx, 1.0);
+        assert!(m.error.is_some());
+        assert_eq!(m.loads(), 0);
+    }
+}
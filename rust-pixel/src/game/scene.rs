@@ -0,0 +1,402 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! SceneStack composes a game of several scenes (menu, gameplay, pause),
+//! each can be a Model/Render pair wrapped in SceneOf. Only the top scene
+//! is updated and drawn, scenes below are paused until popped back.
+//!
+//! Scenes are registered by name and switched from any model with
+//! ctx.scene.push/pop/replace, which take effect on the next frame.
+//! Switches with a transition time run an out phase with the old scene,
+//! switch at midway and run an in phase with the new one. Renders read
+//! ctx.scene.transition to draw fades or wipes, or set ctx.scene.effect
+//! to have the panel blend the last frame of the old scene into the new
+//! one during the in phase.
+
+use crate::{
+    context::Context,
+    game::{Model, Render},
+    render::{buffer::Buffer, transition::TransitionKind},
+    LOGO_FRAME,
+};
+use log::info;
+use std::collections::{HashMap, VecDeque};
+
+/// A scene driven by SceneStack
+pub trait Scene {
+    /// called once before the first enter
+    fn init(&mut self, _ctx: &mut Context) {}
+    /// becomes the top scene by push or replace
+    fn enter(&mut self, _ctx: &mut Context) {}
+    /// removed by pop or replace
+    fn exit(&mut self, _ctx: &mut Context) {}
+    /// another scene pushed on top
+    fn pause(&mut self, _ctx: &mut Context) {}
+    /// top again after the scene above popped
+    fn resume(&mut self, _ctx: &mut Context) {}
+    fn update(&mut self, ctx: &mut Context, dt: f32);
+    fn draw(&mut self, ctx: &mut Context, dt: f32);
+}
+
+/// Wraps a Model/Render pair as a Scene
+pub struct SceneOf<M, R>
+where
+    M: Model,
+    R: Render<Model = M>,
+{
+    pub model: M,
+    pub render: R,
+}
+
+impl<M, R> SceneOf<M, R>
+where
+    M: Model,
+    R: Render<Model = M>,
+{
+    pub fn new(model: M, render: R) -> Self {
+        Self { model, render }
+    }
+}
+
+impl<M, R> Scene for SceneOf<M, R>
+where
+    M: Model,
+    R: Render<Model = M>,
+{
+    fn init(&mut self, ctx: &mut Context) {
+        self.model.init(ctx);
+        self.render.init(ctx, &mut self.model);
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: f32) {
+        self.model.update(ctx, dt);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, dt: f32) {
+        self.render.update(ctx, &mut self.model, dt);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneOp {
+    Push(String),
+    Pop,
+    Replace(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionPhase {
+    /// old scene leaving
+    Out,
+    /// new scene coming
+    In,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneTransition {
+    pub phase: TransitionPhase,
+    /// 0.0 at start of phase to 1.0 at end
+    pub progress: f32,
+}
+
+/// Scene switch requests and transition state, in context.scene
+#[derive(Debug, Default)]
+pub struct SceneControl {
+    ops: VecDeque<(SceneOp, f32)>,
+    /// transition in progress, set by SceneStack
+    pub transition: Option<SceneTransition>,
+    /// name of the top scene
+    pub current: String,
+    /// drawn by the panel during switches with a transition time
+    pub effect: Option<TransitionKind>,
+    // last frame of the out phase, the from side of effect
+    pub(crate) from_frame: Option<Buffer>,
+}
+
+impl SceneControl {
+    pub fn push(&mut self, name: &str) {
+        self.push_with(name, 0.0);
+    }
+
+    /// push with a transition of secs seconds
+    pub fn push_with(&mut self, name: &str, secs: f32) {
+        self.ops.push_back((SceneOp::Push(name.to_string()), secs));
+    }
+
+    pub fn pop(&mut self) {
+        self.pop_with(0.0);
+    }
+
+    pub fn pop_with(&mut self, secs: f32) {
+        self.ops.push_back((SceneOp::Pop, secs));
+    }
+
+    pub fn replace(&mut self, name: &str) {
+        self.replace_with(name, 0.0);
+    }
+
+    pub fn replace_with(&mut self, name: &str, secs: f32) {
+        self.ops
+            .push_back((SceneOp::Replace(name.to_string()), secs));
+    }
+}
+
+struct Switch {
+    op: SceneOp,
+    duration: f32,
+    time: f32,
+}
+
+/// Stack of named scenes, used as the Model of Game with SceneRender
+pub struct SceneStack {
+    scenes: HashMap<String, Box<dyn Scene>>,
+    inited: Vec<String>,
+    stack: Vec<String>,
+    initial: String,
+    switch: Option<Switch>,
+}
+
+impl SceneStack {
+    /// initial is the scene entered at init
+    pub fn new(initial: &str) -> Self {
+        Self {
+            scenes: HashMap::new(),
+            inited: vec![],
+            stack: vec![],
+            initial: initial.to_string(),
+            switch: None,
+        }
+    }
+
+    pub fn add<S: Scene + 'static>(&mut self, name: &str, scene: S) {
+        self.scenes.insert(name.to_string(), Box::new(scene));
+    }
+
+    /// scene names from bottom to top
+    pub fn stack(&self) -> &[String] {
+        &self.stack
+    }
+
+    pub fn top(&self) -> Option<&str> {
+        self.stack.last().map(|s| s.as_str())
+    }
+
+    fn with_scene(
+        &mut self,
+        name: &str,
+        ctx: &mut Context,
+        f: impl FnOnce(&mut dyn Scene, &mut Context),
+    ) {
+        match self.scenes.get_mut(name) {
+            Some(s) => f(s.as_mut(), ctx),
+            None => info!("scene not found:{}", name),
+        }
+    }
+
+    fn enter(&mut self, name: &str, ctx: &mut Context) {
+        if !self.scenes.contains_key(name) {
+            info!("scene not found:{}", name);
+            return;
+        }
+        if !self.inited.iter().any(|n| n == name) {
+            self.inited.push(name.to_string());
+            self.with_scene(name, ctx, |s, c| s.init(c));
+        }
+        self.stack.push(name.to_string());
+        self.with_scene(name, ctx, |s, c| s.enter(c));
+    }
+
+    fn apply(&mut self, op: SceneOp, ctx: &mut Context) {
+        match op {
+            SceneOp::Push(name) => {
+                if let Some(top) = self.stack.last().cloned() {
+                    self.with_scene(&top, ctx, |s, c| s.pause(c));
+                }
+                self.enter(&name, ctx);
+            }
+            SceneOp::Pop => {
+                if let Some(top) = self.stack.pop() {
+                    self.with_scene(&top, ctx, |s, c| s.exit(c));
+                }
+                if let Some(top) = self.stack.last().cloned() {
+                    self.with_scene(&top, ctx, |s, c| s.resume(c));
+                }
+            }
+            SceneOp::Replace(name) => {
+                if let Some(top) = self.stack.pop() {
+                    self.with_scene(&top, ctx, |s, c| s.exit(c));
+                }
+                self.enter(&name, ctx);
+            }
+        }
+        ctx.scene.current = self.top().unwrap_or("").to_string();
+    }
+
+    // advances a switch in progress, returns true while it runs
+    fn run_switch(&mut self, ctx: &mut Context, dt: f32) -> bool {
+        if self.switch.is_none() {
+            if let Some((op, secs)) = ctx.scene.ops.pop_front() {
+                if secs <= 0.0 {
+                    self.apply(op, ctx);
+                } else {
+                    self.switch = Some(Switch {
+                        op,
+                        duration: secs,
+                        time: 0.0,
+                    });
+                }
+            }
+        }
+        let Some(sw) = &mut self.switch else {
+            ctx.scene.transition = None;
+            return false;
+        };
+        let half = sw.duration / 2.0;
+        let before = sw.time;
+        sw.time += dt;
+        if before < half && sw.time >= half {
+            let op = sw.op.clone();
+            self.apply(op, ctx);
+        }
+        let Some(sw) = &self.switch else {
+            return false;
+        };
+        ctx.scene.transition = if sw.time >= sw.duration {
+            self.switch = None;
+            None
+        } else if sw.time < half {
+            Some(SceneTransition {
+                phase: TransitionPhase::Out,
+                progress: sw.time / half,
+            })
+        } else {
+            Some(SceneTransition {
+                phase: TransitionPhase::In,
+                progress: (sw.time - half) / half,
+            })
+        };
+        ctx.scene.transition.is_some()
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, dt: f32) {
+        if let Some(top) = self.stack.last().cloned() {
+            self.with_scene(&top, ctx, |s, c| s.draw(c, dt));
+        }
+    }
+}
+
+impl Model for SceneStack {
+    fn init(&mut self, ctx: &mut Context) {
+        let name = self.initial.clone();
+        self.apply(SceneOp::Push(name), ctx);
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: f32) {
+        if ctx.stage <= LOGO_FRAME {
+            return;
+        }
+        if self.run_switch(ctx, dt) {
+            // scenes are frozen during transitions
+            ctx.input_events.clear();
+            return;
+        }
+        if let Some(top) = self.stack.last().cloned() {
+            self.with_scene(&top, ctx, |s, c| s.update(c, dt));
+        }
+    }
+
+    fn handle_event(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_input(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_auto(&mut self, _ctx: &mut Context, _dt: f32) {}
+}
+
+/// Render of a SceneStack, draws the top scene
+pub struct SceneRender;
+
+impl Render for SceneRender {
+    type Model = SceneStack;
+
+    fn init(&mut self, _ctx: &mut Context, _m: &mut SceneStack) {}
+    fn handle_event(&mut self, _ctx: &mut Context, _m: &mut SceneStack, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _m: &mut SceneStack, _dt: f32) {}
+
+    fn draw(&mut self, ctx: &mut Context, m: &mut SceneStack, dt: f32) {
+        m.draw(ctx, dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Probe {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Probe {
+        fn log(&self, what: &str) {
+            self.log
+                .borrow_mut()
+                .push(format!("{}.{}", self.name, what));
+        }
+    }
+
+    impl Scene for Probe {
+        fn init(&mut self, _ctx: &mut Context) {
+            self.log("init");
+        }
+        fn enter(&mut self, _ctx: &mut Context) {
+            self.log("enter");
+        }
+        fn exit(&mut self, _ctx: &mut Context) {
+            self.log("exit");
+        }
+        fn pause(&mut self, _ctx: &mut Context) {
+            self.log("pause");
+        }
+        fn resume(&mut self, _ctx: &mut Context) {
+            self.log("resume");
+        }
+        fn update(&mut self, _ctx: &mut Context, _dt: f32) {}
+        fn draw(&mut self, _ctx: &mut Context, _dt: f32) {}
+    }
+
+    #[test]
+    fn scene_stack_push_pop_transition() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut ss = SceneStack::new("menu");
+        for name in ["menu", "game", "pause"] {
+            let log = log.clone();
+            ss.add(name, Probe { name, log });
+        }
+        let mut ctx = Context::new("games", "test", "");
+        ctx.stage = LOGO_FRAME + 1;
+        ss.init(&mut ctx);
+
+        ctx.scene.replace("game");
+        ss.update(&mut ctx, 0.1);
+        ctx.scene.push("pause");
+        ss.update(&mut ctx, 0.1);
+        assert_eq!(ss.stack(), &["game", "pause"]);
+
+        ctx.scene.pop_with(1.0);
+        ss.update(&mut ctx, 0.2);
+        let tr = ctx.scene.transition.unwrap();
+        assert_eq!(tr.phase, TransitionPhase::Out);
+        assert_eq!(ss.top(), Some("pause"));
+        ss.update(&mut ctx, 0.4);
+        assert_eq!(ctx.scene.transition.unwrap().phase, TransitionPhase::In);
+        assert_eq!(ctx.scene.current, "game");
+        ss.update(&mut ctx, 0.5);
+        assert!(ctx.scene.transition.is_none());
+
+        assert_eq!(
+            log.borrow().join(" "),
+            "menu.init menu.enter menu.exit game.init game.enter \
+             game.pause pause.init pause.enter pause.exit game.resume"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Seq describes a script of steps such as waits, calls and event emits,
+//! Sequencer runs several seqs from Model::handle_timer. Cutscenes and
+//! multi step animations then read as a script instead of a hand written
+//! state machine in every Model.
+//!
+//! Steps run in order, a seq advances by dt so it follows time_scale and
+//! pause. Steps without duration run in the same frame.
+
+use crate::{context::Context, event::event_emit};
+
+type Call<M> = Box<dyn FnMut(&mut M, &mut Context)>;
+type Cond<M> = Box<dyn FnMut(&M) -> bool>;
+type Tween<M> = Box<dyn FnMut(&mut M, f32)>;
+
+enum Step<M> {
+    Wait(f32),
+    Call(Call<M>),
+    Emit(String),
+    Until(Cond<M>),
+    During {
+        secs: f32,
+        time: f32,
+        f: Tween<M>,
+    },
+}
+
+/// A script of steps, built with chained calls
+pub struct Seq<M> {
+    steps: Vec<Step<M>>,
+    pos: usize,
+}
+
+impl<M> Default for Seq<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Seq<M> {
+    pub fn new() -> Self {
+        Self {
+            steps: vec![],
+            pos: 0,
+        }
+    }
+
+    /// waits secs seconds
+    pub fn wait(mut self, secs: f32) -> Self {
+        self.steps.push(Step::Wait(secs));
+        self
+    }
+
+    /// calls f with the model
+    pub fn then(self, mut f: impl FnMut(&mut M) + 'static) -> Self {
+        self.call(move |m, _ctx| f(m))
+    }
+
+    /// calls f with the model and context
+    pub fn call(mut self, f: impl FnMut(&mut M, &mut Context) + 'static) -> Self {
+        self.steps.push(Step::Call(Box::new(f)));
+        self
+    }
+
+    /// emits an event registered with event_register
+    pub fn emit(mut self, event: &str) -> Self {
+        self.steps.push(Step::Emit(event.to_string()));
+        self
+    }
+
+    /// waits until f returns true, checked once per frame
+    pub fn wait_until(mut self, f: impl FnMut(&M) -> bool + 'static) -> Self {
+        self.steps.push(Step::Until(Box::new(f)));
+        self
+    }
+
+    /// calls f every frame for secs seconds with progress 0.0 to 1.0,
+    /// the last call always gets 1.0
+    pub fn during(mut self, secs: f32, f: impl FnMut(&mut M, f32) + 'static) -> Self {
+        self.steps.push(Step::During {
+            secs,
+            time: 0.0,
+            f: Box::new(f),
+        });
+        self
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pos >= self.steps.len()
+    }
+
+    /// runs steps for dt seconds, returns true when finished
+    pub fn update(&mut self, m: &mut M, ctx: &mut Context, dt: f32) -> bool {
+        let mut left = dt;
+        while let Some(step) = self.steps.get_mut(self.pos) {
+            match step {
+                Step::Wait(t) => {
+                    if left < *t {
+                        *t -= left;
+                        return false;
+                    }
+                    left -= *t;
+                }
+                Step::Call(f) => f(m, ctx),
+                Step::Emit(e) => event_emit(e),
+                Step::Until(f) => {
+                    if !f(m) {
+                        return false;
+                    }
+                }
+                Step::During { secs, time, f } => {
+                    let used = left.min(*secs - *time);
+                    *time += used;
+                    left -= used;
+                    if *time < *secs {
+                        f(m, *time / *secs);
+                        return false;
+                    }
+                    f(m, 1.0);
+                }
+            }
+            self.pos += 1;
+        }
+        true
+    }
+}
+
+/// Runs named seqs of a model
+pub struct Sequencer<M> {
+    seqs: Vec<(String, Seq<M>)>,
+}
+
+impl<M> Default for Sequencer<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Sequencer<M> {
+    pub fn new() -> Self {
+        Self { seqs: vec![] }
+    }
+
+    /// starts a seq, replacing a running seq of the same name
+    pub fn start(&mut self, name: &str, seq: Seq<M>) {
+        self.stop(name);
+        self.seqs.push((name.to_string(), seq));
+    }
+
+    pub fn stop(&mut self, name: &str) {
+        self.seqs.retain(|(n, _)| n != name);
+    }
+
+    pub fn clear(&mut self) {
+        self.seqs.clear();
+    }
+
+    pub fn is_running(&self, name: &str) -> bool {
+        self.seqs.iter().any(|(n, _)| n == name)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.seqs.is_empty()
+    }
+
+    /// advances all seqs and drops finished ones
+    pub fn update(&mut self, m: &mut M, ctx: &mut Context, dt: f32) {
+        self.seqs.retain_mut(|(_, s)| !s.update(m, ctx, dt));
+    }
+
+    /// updates the sequencer kept in the model itself, get returns it,
+    /// seqs started by steps meanwhile are kept
+    pub fn update_in(m: &mut M, ctx: &mut Context, dt: f32, get: fn(&mut M) -> &mut Self) {
+        let mut sq = std::mem::take(get(m));
+        sq.update(m, ctx, dt);
+        let added = std::mem::take(get(m));
+        for (name, seq) in added.seqs {
+            sq.start(&name, seq);
+        }
+        *get(m) = sq;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct M {
+        log: Vec<String>,
+        x: f32,
+        ready: bool,
+        seq: Sequencer<M>,
+    }
+
+    #[test]
+    fn sequencer_runs_steps_in_time() {
+        let mut ctx = Context::new("games", "test", "");
+        let mut m = M::default();
+        m.seq.start(
+            "intro",
+            Seq::new()
+                .then(|m: &mut M| m.log.push("a".into()))
+                .wait(0.5)
+                .then(|m: &mut M| m.log.push("b".into()))
+                .during(0.2, |m: &mut M, t| m.x = t)
+                .wait_until(|m: &M| m.ready)
+                .then(|m: &mut M| {
+                    m.seq.start("next", Seq::new().wait(1.0));
+                }),
+        );
+        Sequencer::update_in(&mut m, &mut ctx, 0.3, |m| &mut m.seq);
+        assert_eq!(m.log, vec!["a"]);
+        Sequencer::update_in(&mut m, &mut ctx, 0.3, |m| &mut m.seq);
+        assert_eq!(m.log, vec!["a", "b"]);
+        assert!((m.x - 0.5).abs() < 1e-4);
+        Sequencer::update_in(&mut m, &mut ctx, 0.3, |m| &mut m.seq);
+        assert_eq!(m.x, 1.0);
+        assert!(m.seq.is_running("intro"));
+        m.ready = true;
+        Sequencer::update_in(&mut m, &mut ctx, 0.0, |m| &mut m.seq);
+        assert!(!m.seq.is_running("intro"));
+        assert!(m.seq.is_running("next"));
+    }
+}
@@ -50,10 +50,26 @@ pub mod audio;
 #[cfg(not(feature = "base"))]
 pub mod context;
 
+/// optional entity-component-system: World, Schedule and SpriteSync
+#[cfg(not(feature = "base"))]
+pub mod ecs;
+
 /// integrates model and render, encapsulates the main loop
 #[cfg(not(feature = "base"))]
 pub mod game;
 
+/// versioned, checksummed save slots for game states
+#[cfg(not(feature = "base"))]
+pub mod storage;
+
+/// websocket client for networked games, native with the net feature
+#[cfg(not(feature = "base"))]
+pub mod network;
+
+/// lua scripted models with hot reload, native with the script feature
+#[cfg(all(feature = "script", not(target_arch = "wasm32")))]
+pub mod script;
+
 /// log
 pub mod log;
 
@@ -74,3 +90,7 @@ pub mod log;
 /// in graphics mode.
 #[cfg(not(feature = "base"))]
 pub mod render;
+/// retained widgets drawn into sprites: Button, Checkbox, Slider, List, ScrollView,
+/// LogPanel, Table, ProgressBar, Gauge, Menu, FocusRing for keyboard focus and modal Dialog
+#[cfg(not(feature = "base"))]
+pub mod ui;
@@ -0,0 +1,332 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! network provides a WebSocket client in context.net, working the same
+//! way in native and wasm builds.
+//! Native builds need the net feature, a thread per connection runs a
+//! tungstenite socket. Wasm builds use the browser WebSocket by web_sys.
+//!
+//! Received messages and state changes are emitted every frame as
+//! NET_EVENT payloads, so models read them in handle_event like other
+//! events.
+
+use crate::event::EventChannel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(target_arch = "wasm32")]
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+use std::{
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+    thread,
+    time::Duration,
+};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{closure::Closure, JsCast};
+
+/// events of all connections, emitted by Network::poll
+pub const NET_EVENT: EventChannel<NetEvent> = EventChannel::new("Net.Event");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetEventKind {
+    Open,
+    Text(String),
+    Binary(Vec<u8>),
+    /// closed by either side, with the reason if any
+    Close(String),
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetEvent {
+    /// connection id returned by Network::connect
+    pub conn: u32,
+    pub kind: NetEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetState {
+    Connecting,
+    Open,
+    Closed,
+}
+
+// messages from the game to a connection thread
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+enum Cmd {
+    Text(String),
+    Binary(Vec<u8>),
+    Close,
+}
+
+struct Conn {
+    state: NetState,
+    #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+    tx: Sender<Cmd>,
+    #[cfg(target_arch = "wasm32")]
+    ws: web_sys::WebSocket,
+}
+
+/// WebSocket connections of a game, in context.net
+#[derive(Default)]
+pub struct Network {
+    conns: HashMap<u32, Conn>,
+    next_id: u32,
+    #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+    rx: Option<(Sender<NetEvent>, Receiver<NetEvent>)>,
+    #[cfg(target_arch = "wasm32")]
+    queue: Rc<RefCell<VecDeque<NetEvent>>>,
+    // events of failed connects, sent at the next poll
+    pending: Vec<NetEvent>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self, conn: u32) -> NetState {
+        self.conns.get(&conn).map_or(NetState::Closed, |c| c.state)
+    }
+
+    pub fn is_open(&self, conn: u32) -> bool {
+        self.state(conn) == NetState::Open
+    }
+
+    fn fail(&mut self, conn: u32, e: String) {
+        self.pending.push(NetEvent {
+            conn,
+            kind: NetEventKind::Error(e),
+        });
+    }
+
+    /// opens a connection to a ws:// url, returns its id at once,
+    /// Open or Error events follow
+    pub fn connect(&mut self, url: &str) -> u32 {
+        self.next_id += 1;
+        let id = self.next_id;
+        match self.open(id, url) {
+            Ok(c) => {
+                self.conns.insert(id, c);
+            }
+            Err(e) => self.fail(id, e),
+        }
+        id
+    }
+
+    #[cfg(not(any(feature = "net", target_arch = "wasm32")))]
+    fn open(&mut self, _id: u32, _url: &str) -> Result<Conn, String> {
+        Err("built without net feature".to_string())
+    }
+
+    #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+    fn open(&mut self, id: u32, url: &str) -> Result<Conn, String> {
+        use tungstenite::{stream::MaybeTlsStream, Message};
+        let etx = self.rx.get_or_insert_with(channel).0.clone();
+        let (tx, crx) = channel::<Cmd>();
+        let url = url.to_string();
+        let send = move |kind| {
+            let _ = etx.send(NetEvent { conn: id, kind });
+        };
+        thread::spawn(move || {
+            let mut ws = match tungstenite::connect(url.as_str()) {
+                Ok((ws, _)) => ws,
+                Err(e) => {
+                    send(NetEventKind::Error(e.to_string()));
+                    return;
+                }
+            };
+            // short reads so that sends are not blocked
+            if let MaybeTlsStream::Plain(s) = ws.get_mut() {
+                let _ = s.set_read_timeout(Some(Duration::from_millis(10)));
+            }
+            send(NetEventKind::Open);
+            loop {
+                loop {
+                    let msg = match crx.try_recv() {
+                        Ok(Cmd::Text(t)) => Message::Text(t),
+                        Ok(Cmd::Binary(b)) => Message::Binary(b),
+                        Ok(Cmd::Close) | Err(TryRecvError::Disconnected) => {
+                            let _ = ws.close(None);
+                            let _ = ws.flush();
+                            send(NetEventKind::Close(String::new()));
+                            return;
+                        }
+                        Err(TryRecvError::Empty) => break,
+                    };
+                    if let Err(e) = ws.send(msg) {
+                        send(NetEventKind::Error(e.to_string()));
+                    }
+                }
+                match ws.read() {
+                    Ok(Message::Text(t)) => send(NetEventKind::Text(t)),
+                    Ok(Message::Binary(b)) => send(NetEventKind::Binary(b)),
+                    Ok(Message::Close(f)) => {
+                        let reason = f.map(|f| f.reason.to_string()).unwrap_or_default();
+                        send(NetEventKind::Close(reason));
+                        return;
+                    }
+                    Ok(_) => {}
+                    Err(tungstenite::Error::Io(e))
+                        if matches!(
+                            e.kind(),
+                            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                        ) => {}
+                    Err(e) => {
+                        send(NetEventKind::Close(e.to_string()));
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Conn {
+            state: NetState::Connecting,
+            tx,
+        })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn open(&mut self, id: u32, url: &str) -> Result<Conn, String> {
+        let ws = web_sys::WebSocket::new(url).map_err(|e| format!("{:?}", e))?;
+        ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
+        let q = self.queue.clone();
+        let push = move |kind| q.borrow_mut().push_back(NetEvent { conn: id, kind });
+        let p = push.clone();
+        let onopen = Closure::<dyn FnMut()>::new(move || p(NetEventKind::Open));
+        let p = push.clone();
+        let onmessage = Closure::<dyn FnMut(_)>::new(move |e: web_sys::MessageEvent| {
+            let data = e.data();
+            if let Some(t) = data.as_string() {
+                p(NetEventKind::Text(t));
+            } else if let Ok(b) = data.dyn_into::<js_sys::ArrayBuffer>() {
+                p(NetEventKind::Binary(js_sys::Uint8Array::new(&b).to_vec()));
+            }
+        });
+        let p = push.clone();
+        let onclose = Closure::<dyn FnMut(_)>::new(move |e: web_sys::CloseEvent| {
+            p(NetEventKind::Close(e.reason()))
+        });
+        let onerror = Closure::<dyn FnMut(_)>::new(move |_e: web_sys::Event| {
+            push(NetEventKind::Error("websocket error".to_string()))
+        });
+        ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+        ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        ws.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+        ws.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+        onopen.forget();
+        onmessage.forget();
+        onclose.forget();
+        onerror.forget();
+        Ok(Conn {
+            state: NetState::Connecting,
+            ws,
+        })
+    }
+
+    #[allow(unused_variables)]
+    fn send(&mut self, conn: u32, text: Option<&str>, bin: &[u8]) {
+        let Some(c) = self.conns.get(&conn) else {
+            self.fail(conn, "no such connection".to_string());
+            return;
+        };
+        #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+        let _ = c.tx.send(match text {
+            Some(t) => Cmd::Text(t.to_string()),
+            None => Cmd::Binary(bin.to_vec()),
+        });
+        #[cfg(target_arch = "wasm32")]
+        let r = match text {
+            Some(t) => c.ws.send_with_str(t),
+            None => c.ws.send_with_u8_array(bin),
+        };
+        #[cfg(target_arch = "wasm32")]
+        if let Err(e) = r {
+            self.fail(conn, format!("{:?}", e));
+        }
+    }
+
+    pub fn send_text(&mut self, conn: u32, text: &str) {
+        self.send(conn, Some(text), &[]);
+    }
+
+    pub fn send_binary(&mut self, conn: u32, data: &[u8]) {
+        self.send(conn, None, data);
+    }
+
+    /// sends a serde value as json text
+    pub fn send_json<T: Serialize>(&mut self, conn: u32, v: &T) {
+        match serde_json::to_string(v) {
+            Ok(s) => self.send_text(conn, &s),
+            Err(e) => self.fail(conn, e.to_string()),
+        }
+    }
+
+    /// closes a connection, a Close event follows
+    pub fn close(&mut self, conn: u32) {
+        if let Some(_c) = self.conns.get(&conn) {
+            #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+            let _ = _c.tx.send(Cmd::Close);
+            #[cfg(target_arch = "wasm32")]
+            let _ = _c.ws.close();
+        }
+    }
+
+    /// emits received events as NET_EVENT, called by the game loop
+    pub fn poll(&mut self) {
+        #[allow(unused_mut)]
+        let mut es = std::mem::take(&mut self.pending);
+        #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+        if let Some((_, rx)) = &self.rx {
+            es.extend(rx.try_iter());
+        }
+        #[cfg(target_arch = "wasm32")]
+        es.extend(self.queue.borrow_mut().drain(..));
+        for e in es {
+            match e.kind {
+                NetEventKind::Open => {
+                    if let Some(c) = self.conns.get_mut(&e.conn) {
+                        c.state = NetState::Open;
+                    }
+                }
+                NetEventKind::Close(_) => {
+                    self.conns.remove(&e.conn);
+                }
+                NetEventKind::Error(_) if !self.is_open(e.conn) => {
+                    self.conns.remove(&e.conn);
+                }
+                _ => {}
+            }
+            NET_EVENT.emit(&e);
+        }
+    }
+}
+
+mod lockstep;
+pub use lockstep::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_reports_bad_connection() {
+        NET_EVENT.register("t_net");
+        let mut net = Network::new();
+        let c = net.connect("not a url");
+        net.send_text(c + 1, "hi");
+        let mut es = vec![];
+        for _ in 0..200 {
+            net.poll();
+            es.extend(NET_EVENT.drain("t_net"));
+            if es.iter().filter(|e| e.conn == c).count() > 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(es
+            .iter()
+            .any(|e| e.conn == c && matches!(e.kind, NetEventKind::Error(_))));
+        assert!(es.iter().any(|e| e.conn == c + 1));
+        assert_eq!(net.state(c), NetState::Closed);
+    }
+}
@@ -0,0 +1,241 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! LockstepSession runs a deterministic game on several machines by
+//! exchanging inputs only. Every peer advances a frame once inputs of all
+//! players for that frame arrived. Local input is scheduled delay frames
+//! ahead, so small latencies do not stall the game.
+//!
+//! The game must be deterministic: fixed timestep (TimestepMode::Fixed), the
+//! same rand seed on all peers and no other source of input. Peers send
+//! checksums of their state, a mismatch is reported by desync().
+//!
+//! The session only produces and consumes bytes, pump sends them over a
+//! Network connection, any other transport works as well.
+
+use crate::network::Network;
+use flate2::Crc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+
+// frames of checksums kept for comparison
+const CHECKSUM_KEEP: u32 = 240;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum LockstepMsg<I> {
+    Input { player: u8, frame: u32, input: I },
+    Checksum { player: u8, frame: u32, sum: u32 },
+}
+
+/// crc32 of the bincode of a value, for add_checksum
+pub fn checksum_of<T: Serialize>(v: &T) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(&bincode::serialize(v).unwrap_or_default());
+    crc.sum()
+}
+
+/// A lockstep session of players peers, I is the input of one frame
+pub struct LockstepSession<I> {
+    players: u8,
+    local: u8,
+    delay: u32,
+    frame: u32,
+    // next frame of local input
+    local_next: u32,
+    inputs: HashMap<u32, Vec<Option<I>>>,
+    outbox: Vec<Vec<u8>>,
+    local_sums: HashMap<u32, u32>,
+    remote_sums: HashMap<u32, Vec<(u8, u32)>>,
+    desync: Option<u32>,
+    /// calls of advance that had to wait for remote input
+    pub stalls: u32,
+}
+
+impl<I> LockstepSession<I>
+where
+    I: Serialize + DeserializeOwned + Clone + Default,
+{
+    /// local is the index of this peer, delay the input delay in frames
+    pub fn new(players: u8, local: u8, delay: u32) -> Self {
+        let mut inputs = HashMap::new();
+        // nobody has input for the first delay frames
+        for f in 0..delay {
+            inputs.insert(f, vec![Some(I::default()); players as usize]);
+        }
+        Self {
+            players,
+            local,
+            delay,
+            frame: 0,
+            local_next: delay,
+            inputs,
+            outbox: vec![],
+            local_sums: HashMap::new(),
+            remote_sums: HashMap::new(),
+            desync: None,
+            stalls: 0,
+        }
+    }
+
+    /// next frame to run
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    /// first frame found with a different checksum on some peer
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    fn set_input(&mut self, player: u8, frame: u32, input: I) {
+        if player >= self.players || frame < self.frame {
+            return;
+        }
+        let n = self.players as usize;
+        self.inputs.entry(frame).or_insert_with(|| vec![None; n])[player as usize] = Some(input);
+    }
+
+    fn send(&mut self, msg: &LockstepMsg<I>) {
+        if let Ok(b) = bincode::serialize(msg) {
+            self.outbox.push(b);
+        }
+    }
+
+    /// local input for the frame delay frames ahead, ignored while the
+    /// session waits for remote input, so call it once per fixed step
+    pub fn add_local_input(&mut self, input: I) {
+        if self.local_next > self.frame + self.delay {
+            return;
+        }
+        let frame = self.local_next;
+        self.local_next += 1;
+        self.set_input(self.local, frame, input.clone());
+        self.send(&LockstepMsg::Input {
+            player: self.local,
+            frame,
+            input,
+        });
+    }
+
+    /// inputs of all players for the next frame, None while some are
+    /// missing
+    pub fn advance(&mut self) -> Option<Vec<I>> {
+        let ready = self
+            .inputs
+            .get(&self.frame)
+            .is_some_and(|v| v.iter().all(|i| i.is_some()));
+        if !ready {
+            self.stalls += 1;
+            return None;
+        }
+        let v = self.inputs.remove(&self.frame)?;
+        self.frame += 1;
+        Some(v.into_iter().map(|i| i.unwrap()).collect())
+    }
+
+    /// checksum of the state after the last advanced frame
+    pub fn add_checksum(&mut self, sum: u32) {
+        let Some(frame) = self.frame.checked_sub(1) else {
+            return;
+        };
+        self.local_sums.insert(frame, sum);
+        self.send(&LockstepMsg::Checksum {
+            player: self.local,
+            frame,
+            sum,
+        });
+        self.compare(frame);
+        let old = self.frame.saturating_sub(CHECKSUM_KEEP);
+        self.local_sums.retain(|f, _| *f >= old);
+        self.remote_sums.retain(|f, _| *f >= old);
+    }
+
+    fn compare(&mut self, frame: u32) {
+        let (Some(l), Some(rs)) = (self.local_sums.get(&frame), self.remote_sums.get(&frame))
+        else {
+            return;
+        };
+        if rs.iter().any(|(_, s)| s != l) && self.desync.is_none_or(|d| frame < d) {
+            self.desync = Some(frame);
+        }
+    }
+
+    /// handles a message of a peer
+    pub fn receive(&mut self, data: &[u8]) {
+        match bincode::deserialize::<LockstepMsg<I>>(data) {
+            Ok(LockstepMsg::Input {
+                player,
+                frame,
+                input,
+            }) => self.set_input(player, frame, input),
+            Ok(LockstepMsg::Checksum { player, frame, sum }) => {
+                self.remote_sums
+                    .entry(frame)
+                    .or_default()
+                    .push((player, sum));
+                self.compare(frame);
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// messages to send to all peers
+    pub fn take_outgoing(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// sends outgoing messages as binary over a connection
+    pub fn pump(&mut self, net: &mut Network, conn: u32) {
+        for m in self.take_outgoing() {
+            net.send_binary(conn, &m);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(a: &mut LockstepSession<u8>, b: &mut LockstepSession<u8>) {
+        for m in a.take_outgoing() {
+            b.receive(&m);
+        }
+        for m in b.take_outgoing() {
+            a.receive(&m);
+        }
+    }
+
+    #[test]
+    fn lockstep_sessions_agree() {
+        let mut a = LockstepSession::<u8>::new(2, 0, 2);
+        let mut b = LockstepSession::<u8>::new(2, 1, 2);
+        let (mut sa, mut sb) = (0u32, 0u32);
+        for f in 0..10u8 {
+            a.add_local_input(f);
+            b.add_local_input(f * 2);
+            if f == 5 {
+                // b is late, a waits
+                assert!(a.advance().is_none());
+            } else {
+                exchange(&mut a, &mut b);
+            }
+            while let Some(i) = a.advance() {
+                sa += i[0] as u32 * 3 + i[1] as u32;
+                a.add_checksum(sa);
+            }
+            while let Some(i) = b.advance() {
+                sb += i[0] as u32 * 3 + i[1] as u32;
+                b.add_checksum(sb + (b.frame() == 7) as u32);
+            }
+        }
+        exchange(&mut a, &mut b);
+        assert_eq!(a.frame(), b.frame());
+        assert!(a.stalls > 0);
+        assert_eq!(a.desync(), Some(6));
+        assert_eq!(b.desync(), Some(6));
+    }
+}
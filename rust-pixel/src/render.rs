@@ -17,11 +17,35 @@ pub mod buffer;
 /// image, to read or write image files in pix or esc format
 pub mod image;
 
+/// text layout, word wrap & alignment
+pub mod textflow;
+
 /// sprite, basic drawing unit
 pub mod sprite;
 
 /// defines attributes like fore- or back-ground colors
 pub mod style;
 
+/// tween, interpolates sprite properties over time
+pub mod tween;
+
+/// camera, viewport into a world larger than the screen
+pub mod camera;
+
+/// tilemap, large tile grids drawn through the camera
+pub mod tilemap;
+
+/// board, grids of cells with a cursor and selection for board games
+pub mod board;
+
+/// transition, crossfade, wipe, pixelate and dissolve between buffers or GL textures
+pub mod transition;
+
+/// layout, splits the screen into regions by constraints and places sprites
+pub mod layout;
+
+/// lighting and field of view of grids, applied by panel
+pub mod light;
+
 /// draw panel, compatible with both text mode (crossterm) and graphics mode (SDL&wasm)
 pub mod panel;
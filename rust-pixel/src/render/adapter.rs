@@ -3,17 +3,18 @@
 
 #![allow(unused_variables)]
 use crate::{
-    event::Event,
-    render::{buffer::Buffer, sprite::Sprites},
+    event::{event_emit_data, Event},
+    render::{adapter::gl::transform::GlTransform, buffer::Buffer, sprite::Sprites},
     util::{Rand, Rect},
 };
 #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
 use crate::{
-    render::adapter::gl::{color::GlColor, pixel::GlPixel, transform::GlTransform},
+    render::adapter::gl::{color::GlColor, pixel::GlPixel},
     render::style::Color,
     util::{ARect, PointF32, PointI32, PointU16},
     LOGO_FRAME,
 };
+use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::time::Duration;
 // use log::info;
@@ -28,6 +29,90 @@ pub mod gl;
 #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
 pub const PIXEL_TEXTURE_FILES: [&str; 1] = ["assets/pix/c64.png"];
 
+/// emitted with a WindowSize as data after the panel took a new size
+pub const WINDOW_RESIZED: &str = "Window.Resized";
+
+/// emitted with a bool as data when the window or page enters (true)
+/// or leaves fullscreen
+pub const FULLSCREEN_CHANGED: &str = "Window.Fullscreen";
+
+/// emitted with a bool as data when the pointer gets locked or free
+pub const POINTER_LOCK_CHANGED: &str = "Window.PointerLock";
+
+/// terminal cursor shapes, see Adapter::set_cursor_shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CursorShape {
+    /// as configured by the user
+    #[default]
+    Default,
+    Block,
+    Underline,
+    Bar,
+}
+
+impl CursorShape {
+    /// parameter of the DECSCUSR sequence ESC [ n SP q
+    pub fn decscusr(self, blink: bool) -> u8 {
+        let n = match self {
+            CursorShape::Default => return 0,
+            CursorShape::Block => 1,
+            CursorShape::Underline => 3,
+            CursorShape::Bar => 5,
+        };
+        if blink {
+            n
+        } else {
+            n + 1
+        }
+    }
+}
+
+/// size of a resized terminal or window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct WindowSize {
+    /// cells, the size of the panel buffers
+    pub width: u16,
+    pub height: u16,
+    /// window pixels in graphics mode, 0 in terminal mode
+    pub pixel_w: u32,
+    pub pixel_h: u32,
+}
+
+/// how graphics mode draws its fixed canvas into a resized window,
+/// the cell grid the game works with never changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScaleMode {
+    /// unscaled at the top left
+    #[default]
+    None,
+    /// as large as fits, keeping the aspect
+    Fit,
+    /// by the largest integer factor that fits, so pixel art stays crisp,
+    /// falls back to Fit for windows smaller than the canvas
+    Integer,
+}
+
+/// x, y, w, h of a canvas scaled into a window and centered between
+/// letterbox bars, None for ScaleMode::None
+pub fn scale_viewport(mode: ScaleMode, canvas: (u32, u32), window: (u32, u32)) -> Option<[i32; 4]> {
+    let (cw, ch) = (canvas.0.max(1) as f32, canvas.1.max(1) as f32);
+    let (w, h) = (window.0 as f32, window.1 as f32);
+    let fit = (w / cw).min(h / ch);
+    let s = match mode {
+        ScaleMode::None => return None,
+        ScaleMode::Fit => fit,
+        ScaleMode::Integer if fit >= 1.0 => fit.floor(),
+        ScaleMode::Integer => fit,
+    };
+    let (vw, vh) = ((cw * s) as i32, (ch * s) as i32);
+    Some([
+        (window.0 as i32 - vw) / 2,
+        (window.1 as i32 - vh) / 2,
+        vw,
+        vh,
+    ])
+}
+
 pub const PIXEL_SYM_WIDTH: f32 = 16.0;
 pub const PIXEL_SYM_HEIGHT: f32 = 16.0;
 
@@ -91,6 +176,32 @@ pub struct RenderCell {
     pub angle: f32,
     pub cx: f32,
     pub cy: f32,
+    pub sx: f32,
+    pub sy: f32,
+    // camera zoom and sub cell scroll of its layer...
+    pub view: GlTransform,
+}
+
+// rotation and scale of a cell around its anchor point...
+#[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellTransform {
+    pub angle: f64,
+    pub scale: (f32, f32),
+    pub anchor: PointI32,
+    pub view: GlTransform,
+}
+
+#[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+impl Default for CellTransform {
+    fn default() -> Self {
+        Self {
+            angle: 0.0,
+            scale: (1.0, 1.0),
+            anchor: PointI32 { x: 0, y: 0 },
+            view: GlTransform::new(),
+        }
+    }
 }
 
 pub struct AdapterBase {
@@ -105,6 +216,23 @@ pub struct AdapterBase {
     pub ratio_x: f32,
     pub ratio_y: f32,
     pub rd: Rand,
+    pub text_input: bool,
+    pub clipboard: String,
+    // set by poll_event on a resize, taken by Panel::draw
+    pub resized: Option<WindowSize>,
+    // how graphics mode draws the canvas into a resized window
+    pub scale_mode: ScaleMode,
+    // linear filtering when scaled, nearest by default
+    pub smooth_scale: bool,
+    // window pixels the canvas is drawn into, None if unscaled
+    pub viewport: Option<[i32; 4]>,
+    // device pixels per window pixel, devicePixelRatio of high-dpi
+    // displays on web, the page sizes the canvas by it
+    pub dpr: f32,
+    pub fullscreen: bool,
+    // the mouse is hidden and only reports motion, see take_pointer_delta
+    pub pointer_locked: bool,
+    pointer_delta: (i32, i32),
     #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
     pub gl: Option<glow::Context>,
     #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
@@ -112,6 +240,79 @@ pub struct AdapterBase {
 }
 
 impl AdapterBase {
+    /// maps a window pixel position to the canvas, see ScaleMode
+    pub fn window_to_canvas(&self, x: i32, y: i32) -> (i32, i32) {
+        match self.viewport {
+            Some([vx, vy, vw, vh]) => (
+                (x - vx) * self.pixel_w as i32 / vw.max(1),
+                (y - vy) * self.pixel_h as i32 / vh.max(1),
+            ),
+            None => (x, y),
+        }
+    }
+
+    /// viewport in device pixels for drawing to the screen, the canvas is
+    /// dpr times larger than its window size on high-dpi displays and
+    /// nearest filtering keeps symbols crisp
+    pub fn screen_viewport(&self) -> Option<[i32; 4]> {
+        if (self.dpr - 1.0).abs() < f32::EPSILON {
+            return self.viewport;
+        }
+        let s = |v: i32| (v as f32 * self.dpr).round() as i32;
+        let [x, y, w, h] = self
+            .viewport
+            .unwrap_or([0, 0, self.pixel_w as i32, self.pixel_h as i32]);
+        Some([s(x), s(y), s(w), s(h)])
+    }
+
+    /// fullscreen state reported by the window or page, emits
+    /// FULLSCREEN_CHANGED on a change
+    pub fn fullscreen_changed(&mut self, on: bool) {
+        if self.fullscreen != on {
+            self.fullscreen = on;
+            event_emit_data(FULLSCREEN_CHANGED, &on);
+        }
+    }
+
+    /// pointer lock state reported by the window or page, emits
+    /// POINTER_LOCK_CHANGED on a change
+    pub fn pointer_lock_changed(&mut self, on: bool) {
+        if self.pointer_locked != on {
+            self.pointer_locked = on;
+            self.pointer_delta = (0, 0);
+            event_emit_data(POINTER_LOCK_CHANGED, &on);
+        }
+    }
+
+    /// adds mouse motion in window pixels while the pointer is locked
+    pub fn pointer_moved(&mut self, dx: i32, dy: i32) {
+        if self.pointer_locked {
+            self.pointer_delta.0 += dx;
+            self.pointer_delta.1 += dy;
+        }
+    }
+
+    /// mouse motion of a locked pointer since the last call
+    pub fn take_pointer_delta(&mut self) -> (i32, i32) {
+        std::mem::take(&mut self.pointer_delta)
+    }
+
+    /// a new window size in pixels: updates the viewport and filtering
+    /// by scale_mode and reports the size to the panel
+    #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+    pub fn window_resized(&mut self, w: u32, h: u32) {
+        self.viewport = scale_viewport(self.scale_mode, (self.pixel_w, self.pixel_h), (w, h));
+        if let (Some(pix), Some(gl)) = (self.gl_pixel.as_mut(), self.gl.as_ref()) {
+            pix.set_smooth(gl, self.smooth_scale && self.viewport.is_some());
+        }
+        self.resized = Some(WindowSize {
+            width: self.cell_w,
+            height: self.cell_h,
+            pixel_w: w,
+            pixel_h: h,
+        });
+    }
+
     pub fn new(pre: &str, gn: &str, project_path: &str) -> Self {
         Self {
             game_name: gn.to_string(),
@@ -125,6 +326,16 @@ impl AdapterBase {
             ratio_x: 1.0,
             ratio_y: 1.0,
             rd: Rand::new(),
+            text_input: false,
+            clipboard: String::new(),
+            resized: None,
+            scale_mode: ScaleMode::None,
+            smooth_scale: false,
+            viewport: None,
+            dpr: 1.0,
+            fullscreen: false,
+            pointer_locked: false,
+            pointer_delta: (0, 0),
             #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
             gl: None,
             #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
@@ -206,6 +417,60 @@ pub trait Adapter {
     fn set_cursor(&mut self, x: u16, y: u16) -> Result<(), String>;
     fn get_cursor(&mut self) -> Result<(u16, u16), String>;
 
+    /// cursor look of the terminal, reset restores the default, graphics
+    /// modes draw no cursor
+    fn set_cursor_shape(&mut self, _shape: CursorShape, _blink: bool) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// leaves the alternate screen init entered for the normal screen
+    /// with the shell history and back, e.g. to print a score table that
+    /// stays after exit. Only the terminal has two screens
+    fn set_alternate_screen(&mut self, _on: bool) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// title of the window, terminal tab or page, the terminal title is
+    /// restored by reset
+    fn set_window_title(&mut self, t: &str) -> Result<(), String> {
+        self.get_base().title = t.to_string();
+        Ok(())
+    }
+
+    /// enters or leaves fullscreen, FULLSCREEN_CHANGED follows when the
+    /// state changes. Browsers allow it only shortly after a key press or
+    /// click, the terminal ignores it
+    fn set_fullscreen(&mut self, on: bool) {}
+
+    fn request_fullscreen(&mut self) {
+        self.set_fullscreen(true);
+    }
+
+    fn exit_fullscreen(&mut self) {
+        self.set_fullscreen(false);
+    }
+
+    /// hides the mouse and keeps it in the window, motion is read with
+    /// take_pointer_delta of the base, POINTER_LOCK_CHANGED follows
+    fn set_pointer_lock(&mut self, on: bool) {}
+
+    /// text input mode, typed and IME composed text is sent as Event::Text,
+    /// turn it on while a text field has focus
+    fn set_text_input(&mut self, on: bool) {
+        self.get_base().text_input = on;
+    }
+
+    /// clipboard text, adapters unable to read the system clipboard
+    /// return the text last set or pasted
+    fn clipboard_get(&mut self) -> Option<String> {
+        let cb = &self.get_base().clipboard;
+        (!cb.is_empty()).then(|| cb.clone())
+    }
+
+    fn clipboard_set(&mut self, s: &str) {
+        self.get_base().clipboard = s.to_string();
+    }
+
     // sdl & web main render pass...
     #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
     fn draw_all_graph(
@@ -229,9 +494,10 @@ pub trait Adapter {
     #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
     fn draw_render_textures_to_screen(&mut self) {
         let bs = self.get_base();
+        let viewport = bs.screen_viewport();
 
         if let (Some(pix), Some(gl)) = (&mut bs.gl_pixel, &mut bs.gl) {
-            pix.bind_screen(gl);
+            pix.bind_screen(gl, viewport);
             let c = GlColor::new(1.0, 1.0, 1.0, 1.0);
 
             // draw render_texture 2 ( main buffer )
@@ -294,7 +560,7 @@ pub trait Adapter {
         let mut rbuf = vec![];
         let rx = self.get_base().ratio_x;
         let ry = self.get_base().ratio_y;
-        let pz = PointI32 { x: 0, y: 0 };
+        let ct = CellTransform::default();
         let mut rfunc = |fc: &(u8, u8, u8, u8),
                          bc: &Option<(u8, u8, u8, u8)>,
                          _s0: ARect,
@@ -302,7 +568,7 @@ pub trait Adapter {
                          s2: ARect,
                          texidx: usize,
                          symidx: usize| {
-            push_render_buffer(&mut rbuf, fc, bc, texidx, symidx, s2, 0.0, &pz);
+            push_render_buffer(&mut rbuf, fc, bc, texidx, symidx, s2, &ct);
         };
         render_main_buffer(cb, cb.area.width, rx, ry, true, &mut rfunc);
         rbuf
@@ -319,7 +585,7 @@ pub trait Adapter {
     ) -> Vec<RenderCell> {
         let mut rbuf = vec![];
         let width = cb.area.width;
-        let pz = PointI32 { x: 0, y: 0 };
+        let ct = CellTransform::default();
 
         // render logo...
         if stage <= LOGO_FRAME {
@@ -331,7 +597,7 @@ pub trait Adapter {
                 &mut self.get_base().rd,
                 stage,
                 |fc, _s1, s2, texidx, symidx| {
-                    push_render_buffer(&mut rbuf, fc, &None, texidx, symidx, s2, 0.0, &pz);
+                    push_render_buffer(&mut rbuf, fc, &None, texidx, symidx, s2, &ct);
                 },
             );
             return rbuf;
@@ -348,7 +614,7 @@ pub trait Adapter {
                          s2: ARect,
                          texidx: usize,
                          symidx: usize| {
-            push_render_buffer(&mut rbuf, fc, bc, texidx, symidx, s2, 0.0, &pz);
+            push_render_buffer(&mut rbuf, fc, bc, texidx, symidx, s2, &ct);
         };
 
         // render windows border, only at sdl mode
@@ -362,14 +628,18 @@ pub trait Adapter {
 
         // render pixel_sprites...
         if stage > LOGO_FRAME {
-            for item in ps {
+            // pixel layers ordered by render_weight, bigger is upper
+            let mut order: Vec<usize> = (0..ps.len()).collect();
+            order.sort_by_key(|i| ps[*i].render_weight);
+            for i in order {
+                let item = &mut ps[i];
                 if item.is_pixel && !item.is_hidden {
                     render_pixel_sprites(
                         item,
                         rx,
                         ry,
-                        |fc, bc, _s0, _s1, s2, texidx, symidx, angle, ccp| {
-                            push_render_buffer(&mut rbuf, fc, bc, texidx, symidx, s2, angle, &ccp);
+                        |fc, bc, _s0, _s1, s2, texidx, symidx, ct| {
+                            push_render_buffer(&mut rbuf, fc, bc, texidx, symidx, s2, &ct);
                         },
                     );
                 }
@@ -389,8 +659,7 @@ fn push_render_buffer(
     texidx: usize,
     symidx: usize,
     s: ARect,
-    angle: f64,
-    ccp: &PointI32,
+    ct: &CellTransform,
 ) {
     let mut wc = RenderCell {
         fcolor: (
@@ -418,10 +687,10 @@ fn push_render_buffer(
     wc.y = s.y as f32 + PIXEL_SYM_HEIGHT;
     wc.w = s.w;
     wc.h = s.h;
-    if angle == 0.0 {
-        wc.angle = angle as f32;
+    if ct.angle == 0.0 {
+        wc.angle = 0.0;
     } else {
-        let mut aa = (1.0 - angle / 180.0) * std::f64::consts::PI;
+        let mut aa = (1.0 - ct.angle / 180.0) * std::f64::consts::PI;
         let pi2 = std::f64::consts::PI * 2.0;
         while aa < 0.0 {
             aa += pi2;
@@ -431,8 +700,11 @@ fn push_render_buffer(
         }
         wc.angle = aa as f32;
     }
-    wc.cx = ccp.x as f32;
-    wc.cy = ccp.y as f32;
+    wc.cx = ct.anchor.x as f32;
+    wc.cy = ct.anchor.y as f32;
+    wc.sx = ct.scale.0;
+    wc.sy = ct.scale.1;
+    wc.view = ct.view;
     rbuf.push(wc);
 }
 
@@ -488,7 +760,7 @@ fn render_helper(
 #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
 pub fn render_pixel_sprites<F>(pixel_spt: &mut Sprites, rx: f32, ry: f32, mut f: F)
 where
-    // rgba, back rgba, back rect, sym rect, dst rect, tex, sym, angle, center point
+    // rgba, back rgba, back rect, sym rect, dst rect, tex, sym, angle & scale & anchor point
     F: FnMut(
         &(u8, u8, u8, u8),
         &Option<(u8, u8, u8, u8)>,
@@ -497,10 +769,14 @@ where
         ARect,
         usize,
         usize,
-        f64,
-        PointI32,
+        CellTransform,
     ),
 {
+    // camera offset in pixels...
+    let ox = (pixel_spt.camera_offset.0 as f32 * PIXEL_SYM_WIDTH / rx) as i32;
+    let oy = (pixel_spt.camera_offset.1 as f32 * PIXEL_SYM_HEIGHT / ry) as i32;
+    // attached sprites follow parents...
+    pixel_spt.update_hierarchy(PIXEL_SYM_WIDTH / rx, PIXEL_SYM_HEIGHT / ry);
     // sort by render_weight...
     pixel_spt.update_render_index();
     for si in &pixel_spt.render_index {
@@ -515,7 +791,7 @@ where
 
         for (i, cell) in s.content.content.iter().enumerate() {
             let sh = &cell.get_cell_info();
-            let (s0, s1, s2, texidx, symidx) = render_helper(
+            let (s0, s1, mut s2, texidx, symidx) = render_helper(
                 pw,
                 PointF32 { x: rx, y: ry },
                 i,
@@ -523,14 +799,21 @@ where
                 PointU16 { x: px, y: py },
                 false,
             );
+            s2.x -= ox;
+            s2.y -= oy;
             let x = i % pw as usize;
             let y = i / pw as usize;
-            // center point ...
-            let ccp = PointI32 {
-                x: ((pw as f32 / 2.0 - x as f32) * PIXEL_SYM_WIDTH / rx) as i32,
-                y: ((ph as f32 / 2.0 - y as f32) * PIXEL_SYM_HEIGHT / ry) as i32,
+            // anchor point, rotate and scale around it...
+            let ct = CellTransform {
+                angle: s.angle,
+                scale: (s.scale_x, s.scale_y),
+                anchor: PointI32 {
+                    x: ((pw as f32 * s.anchor.x - x as f32) * PIXEL_SYM_WIDTH / rx) as i32,
+                    y: ((ph as f32 * s.anchor.y - y as f32) * PIXEL_SYM_HEIGHT / ry) as i32,
+                },
+                view: pixel_spt.camera_view,
             };
-            let mut fc = sh.2.get_rgba();
+            let mut fc = sh.2.tint(s.tint).get_rgba();
             fc.3 = s.alpha;
             let bc;
             if sh.3 != Color::Reset {
@@ -540,7 +823,7 @@ where
             } else {
                 bc = None;
             }
-            f(&fc, &bc, s0, s1, s2, texidx, symidx, s.angle, ccp);
+            f(&fc, &bc, s0, s1, s2, texidx, symidx, ct);
         }
     }
 }
@@ -678,6 +961,10 @@ pub mod sdl;
 #[cfg(target_arch = "wasm32")]
 pub mod web;
 
+/// webgpu render path of the web driver...
+#[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+pub mod wgpu;
+
 /// crossterm driver...
 #[cfg(not(any(
     feature = "sdl",
@@ -686,3 +973,70 @@ pub mod web;
     target_arch = "wasm32"
 )))]
 pub mod cross;
+
+/// null driver for headless servers and simulations...
+pub mod null;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_viewport_letterbox() {
+        let c = (320, 200);
+        assert_eq!(scale_viewport(ScaleMode::None, c, (1000, 700)), None);
+        assert_eq!(
+            scale_viewport(ScaleMode::Integer, c, (1000, 700)),
+            Some([20, 50, 960, 600])
+        );
+        assert_eq!(
+            scale_viewport(ScaleMode::Fit, c, (1000, 700)),
+            Some([0, 37, 1000, 625])
+        );
+        // smaller than the canvas
+        assert_eq!(
+            scale_viewport(ScaleMode::Integer, c, (160, 200)),
+            Some([0, 50, 160, 100])
+        );
+        let mut b = AdapterBase::new("", "", "");
+        (b.pixel_w, b.pixel_h) = c;
+        b.viewport = scale_viewport(ScaleMode::Integer, c, (1000, 700));
+        assert_eq!(b.window_to_canvas(20 + 90, 50 + 30), (30, 10));
+
+        // high-dpi draws into a larger canvas, input stays in window pixels
+        assert_eq!(b.screen_viewport(), b.viewport);
+        b.dpr = 2.0;
+        assert_eq!(b.screen_viewport(), Some([40, 100, 1920, 1200]));
+        assert_eq!(b.window_to_canvas(20 + 90, 50 + 30), (30, 10));
+        b.viewport = None;
+        assert_eq!(b.screen_viewport(), Some([0, 0, 640, 400]));
+    }
+
+    #[test]
+    fn fullscreen_and_pointer_lock_state() {
+        use crate::event::{event_check_data, event_register};
+        event_register(FULLSCREEN_CHANGED, "adapter_test");
+        let mut b = AdapterBase::new("", "", "");
+        b.fullscreen_changed(true);
+        b.fullscreen_changed(true);
+        let check = || event_check_data::<bool>(FULLSCREEN_CHANGED, "adapter_test");
+        assert_eq!(check(), Some(true));
+        assert_eq!(check(), None);
+
+        // motion only counts while locked
+        b.pointer_moved(5, 5);
+        b.pointer_lock_changed(true);
+        b.pointer_moved(3, -2);
+        b.pointer_moved(1, 1);
+        assert_eq!(b.take_pointer_delta(), (4, -1));
+        assert_eq!(b.take_pointer_delta(), (0, 0));
+    }
+
+    #[test]
+    fn cursor_shape_decscusr() {
+        assert_eq!(CursorShape::Default.decscusr(false), 0);
+        assert_eq!(CursorShape::Block.decscusr(true), 1);
+        assert_eq!(CursorShape::Underline.decscusr(false), 4);
+        assert_eq!(CursorShape::Bar.decscusr(false), 6);
+    }
+}
@@ -1,34 +1,57 @@
 // RustPixel
 // copyright zipxing@hotmail.com 2022~2024
 
+//! Implements an Adapter trait for terminal, based on crossterm.
+//! Only cells changed since last frame are emitted, by diffing the current
+//! buffer with the previous one. A frame is queued into a buffered writer
+//! and written to the terminal at once, which matters a lot over SSH.
+//! Colors are reduced to what the terminal can show, see ColorDepth.
+//!
+//! Mouse reporting is negotiated by MouseMode: xterm 1000/1002/1003
+//! tracking with SGR 1006 coordinates, rxvt 1015 as fallback for old
+//! terminals. Inside tmux or screen the modes the multiplexer does not
+//! forward are left out, see Multiplexer. tmux passes the mouse only with
+//! `set -g mouse on`. RUST_PIXEL_MOUSE=off|click|drag|motion overrides.
+//!
+//! init saves the terminal title and installs a panic hook, so a crash
+//! leaves the alternate screen, shows the cursor and turns raw mode off
+//! before the panic message is printed, the same as reset does.
+
 use crate::{
     event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
     render::{
-        adapter::{Adapter, AdapterBase},
+        adapter::{Adapter, AdapterBase, CursorShape, WindowSize},
         buffer::Buffer,
         image::to_error,
         sprite::Sprites,
-        style::{Color, Modifier, ModifierDiff},
+        style::{Color, ColorDepth, Modifier, ModifierDiff},
     },
-    util::Rand,
+    util::{base64_encode, Rand},
     LOGO_FRAME,
 };
 #[cfg(not(feature = "sdl"))]
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
-    event::{DisableMouseCapture, EnableMouseCapture},
-    event::{Event as CEvent, KeyCode as CKeyCode, MouseButton as CMouseButton},
+    event::{
+        Event as CEvent, KeyCode as CKeyCode, KeyModifiers as CKeyModifiers,
+        MouseButton as CMouseButton,
+    },
     execute, queue,
     style::{
         Attribute as CAttribute, Color as CColor, Print, SetAttribute, SetBackgroundColor,
         SetForegroundColor,
     },
     terminal::{
-        self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+        self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen, SetTitle,
     },
 };
 use std::any::Any;
-use std::io::{self, Write};
+use std::io::{self, BufWriter, Write};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Once,
+};
 use std::time::Duration;
 // use log::info;
 
@@ -37,6 +60,13 @@ pub struct CrosstermAdapter {
     pub writer: Box<dyn Write>,
     pub base: AdapterBase,
     pub rd: Rand,
+    // screen content unknown, redraw all cells next frame
+    pub force_redraw: bool,
+    // truecolor, 256 or 16 colors, detected at init
+    pub color_depth: ColorDepth,
+    // tmux or screen, detected at init
+    pub multiplexer: Multiplexer,
+    pub mouse_mode: MouseMode,
 }
 
 #[cfg(not(feature = "sdl"))]
@@ -44,17 +74,36 @@ impl CrosstermAdapter {
     pub fn new(pre: &str, gn: &str, project_path: &str) -> Self {
         let stdout = io::stdout();
         Self {
-            writer: Box::new(stdout),
+            writer: Box::new(BufWriter::with_capacity(1 << 16, stdout)),
             base: AdapterBase::new(pre, gn, project_path),
             rd: Rand::new(),
+            force_redraw: true,
+            color_depth: ColorDepth::TrueColor,
+            multiplexer: Multiplexer::None,
+            mouse_mode: MouseMode::Motion,
         }
     }
+
+    /// switches mouse reporting, e.g. Click to save bandwidth over ssh
+    pub fn set_mouse_mode(&mut self, m: MouseMode) -> Result<(), String> {
+        self.mouse_mode = m.supported(self.multiplexer);
+        to_error(write!(
+            self.writer,
+            "{}{}",
+            MouseMode::DISABLE,
+            self.mouse_mode.enable_seq(self.multiplexer)
+        ))?;
+        to_error(self.writer.flush())
+    }
 }
 
 #[cfg(not(feature = "sdl"))]
 impl Adapter for CrosstermAdapter {
     fn init(&mut self, w: u16, h: u16, _rx: f32, _ry: f32, _s: String) {
         self.set_size(w, h);
+        self.color_depth = ColorDepth::detect();
+        self.multiplexer = Multiplexer::detect();
+        self.mouse_mode = MouseMode::detect().supported(self.multiplexer);
         // check terminal size, warns and exits if the size is smaller than the required size
         let (width, height) = terminal::size().unwrap();
         if w > width || h > height {
@@ -68,7 +117,13 @@ impl Adapter for CrosstermAdapter {
         }
         enable_raw_mode().unwrap();
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture).unwrap();
+        hook_panic();
+        execute!(stdout, EnterAlternateScreen).unwrap();
+        ALT_SCREEN.store(true, Ordering::Relaxed);
+        // pushes the title on the xterm title stack, restore_seq pops it
+        let _ = write!(stdout, "\x1b[22;0t");
+        let _ = write!(stdout, "{}", self.mouse_mode.enable_seq(self.multiplexer));
+        let _ = stdout.flush();
     }
 
     fn get_base(&mut self) -> &mut AdapterBase {
@@ -77,8 +132,9 @@ impl Adapter for CrosstermAdapter {
 
     fn reset(&mut self) {
         disable_raw_mode().unwrap();
-        execute!(self.writer, LeaveAlternateScreen, DisableMouseCapture).unwrap();
-        self.show_cursor().unwrap();
+        let alt = ALT_SCREEN.swap(false, Ordering::Relaxed);
+        let _ = write!(self.writer, "{}", restore_seq(alt));
+        let _ = self.writer.flush();
     }
 
     fn cell_width(&self) -> f32 {
@@ -103,6 +159,37 @@ impl Adapter for CrosstermAdapter {
         crossterm::cursor::position().map_err(|e| e.to_string())
     }
 
+    fn set_cursor_shape(&mut self, shape: CursorShape, blink: bool) -> Result<(), String> {
+        to_error(write!(self.writer, "\x1b[{} q", shape.decscusr(blink)))?;
+        to_error(self.writer.flush())
+    }
+
+    fn set_alternate_screen(&mut self, on: bool) -> Result<(), String> {
+        if on {
+            to_error(execute!(self.writer, EnterAlternateScreen))?;
+        } else {
+            to_error(execute!(self.writer, LeaveAlternateScreen))?;
+        }
+        ALT_SCREEN.store(on, Ordering::Relaxed);
+        // the other screen has other content
+        self.force_redraw = true;
+        Ok(())
+    }
+
+    fn set_window_title(&mut self, t: &str) -> Result<(), String> {
+        self.base.title = t.to_string();
+        to_error(execute!(self.writer, SetTitle(t)))
+    }
+
+    // terminal pastes arrive as typed keys, crossterm 0.22 has no
+    // bracketed paste, so only setting the clipboard is supported
+    fn clipboard_set(&mut self, s: &str) {
+        self.base.clipboard = s.to_string();
+        // OSC 52, ignored by terminals not supporting it
+        let _ = write!(self.writer, "\x1b]52;c;{}\x07", base64_encode(s.as_bytes()));
+        let _ = self.writer.flush();
+    }
+
     fn set_cursor(&mut self, x: u16, y: u16) -> Result<(), String> {
         to_error(execute!(self.writer, MoveTo(x, y)))
     }
@@ -111,13 +198,38 @@ impl Adapter for CrosstermAdapter {
         if crossterm::event::poll(timeout).unwrap() {
             let e = crossterm::event::read().unwrap();
             if let Some(et) = input_events_from_cross(&e) {
-                es.push(et);
+                // any-motion tracking floods events over ssh, only the
+                // latest position of a run of moves matters
+                let moved =
+                    |e: &Event| matches!(e, Event::Mouse(m) if m.kind == MouseEventKind::Moved);
+                match es.last_mut() {
+                    Some(last) if moved(last) && moved(&et) => *last = et,
+                    _ => es.push(et),
+                }
             }
             if let CEvent::Key(key) = e {
-                if let CKeyCode::Char('q') = key.code {
+                if self.base.text_input {
+                    // terminal does the IME composing, chars arrive complete
+                    if let CKeyCode::Char(c) = key.code {
+                        if !key.modifiers.intersects(CKeyModifiers::CONTROL | CKeyModifiers::ALT) {
+                            es.push(Event::Text(c.to_string()));
+                        }
+                    }
+                } else if let CKeyCode::Char('q') = key.code {
                     return true;
                 }
             }
+            if let CEvent::Resize(w, h) = e {
+                // panel takes the new size before the next draw
+                self.base.cell_w = w;
+                self.base.cell_h = h;
+                self.base.resized = Some(WindowSize {
+                    width: w,
+                    height: h,
+                    ..Default::default()
+                });
+                self.force_redraw = true;
+            }
         }
         false
     }
@@ -133,17 +245,19 @@ impl Adapter for CrosstermAdapter {
             let w = current_buffer.area().width;
             let x = w - self.rd.rand() as u16 % w;
             let y = current_buffer.area().height / 2;
-            let cc = CColor::from((
+            let cc = CColor::from(self.color_depth.reduce(Color::Rgba(
                 self.rd.rand() as u8,
                 self.rd.rand() as u8,
                 self.rd.rand() as u8,
-            ));
+                255,
+            )));
             to_error(queue!(self.writer, MoveTo(0, y)))?;
             to_error(queue!(self.writer, Print("                                                                                                                     ")))?;
             to_error(queue!(self.writer, MoveTo(x, y)))?;
             to_error(queue!(self.writer, SetForegroundColor(cc)))?;
             to_error(queue!(self.writer, Print("...RustPixel...")))?;
             if stage == LOGO_FRAME {
+                self.force_redraw = true;
                 // clear screen
                 to_error(queue!(self.writer, MoveTo(x, y)))?;
                 to_error(queue!(
@@ -156,10 +270,23 @@ impl Adapter for CrosstermAdapter {
                     SetForegroundColor(CColor::from((128, 128, 128)))
                 ))?;
             }
-            return Ok(());
+            return to_error(self.writer.flush());
         }
-        let updates = previous_buffer.diff(current_buffer);
+        let blank;
+        let updates = if self.force_redraw {
+            // clear screen, then draw every non blank cell
+            self.force_redraw = false;
+            to_error(queue!(self.writer, Clear(ClearType::All)))?;
+            blank = Buffer::empty(*current_buffer.area());
+            blank.diff(current_buffer)
+        } else {
+            previous_buffer.diff(current_buffer)
+        };
         // info!("diff_len.....{:?}", updates.len());
+        if updates.is_empty() {
+            // a queued clear still has to reach the terminal
+            return to_error(self.writer.flush());
+        }
 
         let mut fg = Color::Reset;
         let mut bg = Color::Reset;
@@ -180,12 +307,12 @@ impl Adapter for CrosstermAdapter {
                 modifier = cell.modifier;
             }
             if cell.fg != fg {
-                let color = CColor::from(cell.fg);
+                let color = CColor::from(self.color_depth.reduce(cell.fg));
                 to_error(queue!(self.writer, SetForegroundColor(color)))?;
                 fg = cell.fg;
             }
             if cell.bg != bg {
-                let color = CColor::from(cell.bg);
+                let color = CColor::from(self.color_depth.reduce(cell.bg));
                 to_error(queue!(self.writer, SetBackgroundColor(color)))?;
                 bg = cell.bg;
             }
@@ -197,7 +324,8 @@ impl Adapter for CrosstermAdapter {
             SetForegroundColor(CColor::Reset),
             SetBackgroundColor(CColor::Reset),
             SetAttribute(CAttribute::Reset)
-        ))
+        ))?;
+        to_error(self.writer.flush())
     }
 
     fn as_any(&mut self) -> &mut dyn Any {
@@ -219,11 +347,17 @@ pub fn input_events_from_cross(e: &CEvent) -> Option<Event> {
                 CKeyCode::Left => KeyCode::Left,
                 CKeyCode::Right => KeyCode::Right,
                 CKeyCode::Tab => KeyCode::Tab,
+                CKeyCode::Enter => KeyCode::Enter,
+                CKeyCode::Esc => KeyCode::Esc,
+                CKeyCode::Backspace => KeyCode::Backspace,
+                CKeyCode::Delete => KeyCode::Delete,
+                CKeyCode::Home => KeyCode::Home,
+                CKeyCode::End => KeyCode::End,
                 _ => {
                     return None;
                 }
             };
-            let cte = KeyEvent::new(kc, KeyModifiers::NONE);
+            let cte = KeyEvent::new(kc, KeyModifiers::from_bits_truncate(key.modifiers.bits()));
             return Some(Event::Key(cte));
         }
         CEvent::Mouse(mouse) => {
@@ -253,13 +387,14 @@ pub fn input_events_from_cross(e: &CEvent) -> Option<Event> {
                     MouseEventKind::Drag(eb)
                 }
                 crossterm::event::MouseEventKind::Moved => MouseEventKind::Moved,
-                _ => MouseEventKind::Moved,
+                crossterm::event::MouseEventKind::ScrollDown => MouseEventKind::ScrollDown,
+                crossterm::event::MouseEventKind::ScrollUp => MouseEventKind::ScrollUp,
             };
             let cte = MouseEvent {
                 kind: mk,
                 column: mouse.column,
                 row: mouse.row,
-                modifiers: KeyModifiers::NONE,
+                modifiers: KeyModifiers::from_bits_truncate(mouse.modifiers.bits()),
             };
             mcte = Some(cte);
         }
@@ -270,3 +405,169 @@ pub fn input_events_from_cross(e: &CEvent) -> Option<Event> {
     }
     None
 }
+
+// the game is on the alternate screen, shared with the panic hook
+static ALT_SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// undoes init and what games changed by the adapter: mouse modes,
+/// cursor shape and visibility and title. The alternate screen is only
+/// left when alt is set, leaving it again restores the saved cursor and
+/// may overwrite what a game printed on the normal screen
+pub fn restore_seq(alt: bool) -> String {
+    format!(
+        "{}\x1b[0 q\x1b[?25h\x1b[23;0t{}",
+        MouseMode::DISABLE,
+        if alt { "\x1b[?1049l" } else { "" }
+    )
+}
+
+// restores the terminal before the panic message is printed, else the
+// message is lost with the alternate screen and the shell stays raw
+#[cfg(not(feature = "sdl"))]
+fn hook_panic() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let prev = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = disable_raw_mode();
+            let mut out = io::stdout();
+            let alt = ALT_SCREEN.swap(false, Ordering::Relaxed);
+            let _ = write!(out, "{}", restore_seq(alt));
+            let _ = out.flush();
+            prev(info);
+        }));
+    });
+}
+
+/// terminal multiplexer the game runs in, forwards only part of the
+/// mouse modes of the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    None,
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// by environment
+    pub fn detect() -> Self {
+        let var = |k: &str| std::env::var(k).ok();
+        Self::from_env(
+            var("TMUX").as_deref(),
+            var("STY").as_deref(),
+            var("TERM").as_deref(),
+        )
+    }
+
+    /// by the values of TMUX, STY and TERM, TERM is kept over ssh
+    /// where TMUX and STY are not
+    pub fn from_env(tmux: Option<&str>, sty: Option<&str>, term: Option<&str>) -> Self {
+        if tmux.is_some_and(|t| !t.is_empty()) {
+            return Multiplexer::Tmux;
+        }
+        if sty.is_some_and(|s| !s.is_empty()) {
+            return Multiplexer::Screen;
+        }
+        match term {
+            Some(t) if t.starts_with("tmux") => Multiplexer::Tmux,
+            Some(t) if t.starts_with("screen") => Multiplexer::Screen,
+            _ => Multiplexer::None,
+        }
+    }
+}
+
+/// mouse events asked from the terminal, each mode adds to the previous
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseMode {
+    Off,
+    /// press, release and wheel, xterm 1000
+    Click,
+    /// and moves while a button is down, xterm 1002
+    Drag,
+    /// and all moves, for hover, xterm 1003
+    Motion,
+}
+
+impl MouseMode {
+    /// turns off every mode enable_seq may have set
+    pub const DISABLE: &'static str = "\x1b[?1006l\x1b[?1015l\x1b[?1003l\x1b[?1002l\x1b[?1000l";
+
+    /// Motion, RUST_PIXEL_MOUSE=off|click|drag|motion overrides
+    pub fn detect() -> Self {
+        match std::env::var("RUST_PIXEL_MOUSE").ok().as_deref() {
+            Some("off") | Some("0") => MouseMode::Off,
+            Some("click") => MouseMode::Click,
+            Some("drag") => MouseMode::Drag,
+            _ => MouseMode::Motion,
+        }
+    }
+
+    /// the mode the multiplexer forwards, screen before 5.0 has no any
+    /// motion tracking and falls back to drag
+    pub fn supported(self, mux: Multiplexer) -> Self {
+        match (self, mux) {
+            (MouseMode::Motion, Multiplexer::Screen) => MouseMode::Drag,
+            _ => self,
+        }
+    }
+
+    /// escape sequences enabling the mode. SGR 1006 is asked first for
+    /// coordinates past 223, terminals without it ignore it and send
+    /// rxvt 1015 or plain X10 reports, crossterm reads all three. tmux
+    /// and screen translate the reports themselves and do not know 1015
+    pub fn enable_seq(self, mux: Multiplexer) -> String {
+        let tracking = match self {
+            MouseMode::Off => return String::new(),
+            MouseMode::Click => "\x1b[?1000h",
+            MouseMode::Drag => "\x1b[?1000h\x1b[?1002h",
+            MouseMode::Motion => "\x1b[?1000h\x1b[?1002h\x1b[?1003h",
+        };
+        let encoding = match mux {
+            Multiplexer::None => "\x1b[?1015h\x1b[?1006h",
+            _ => "\x1b[?1006h",
+        };
+        format!("{}{}", tracking, encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mouse_mode_by_multiplexer() {
+        let mux = Multiplexer::from_env;
+        assert_eq!(
+            mux(Some("/tmp/tmux-0/default,1,0"), None, Some("screen")),
+            Multiplexer::Tmux
+        );
+        assert_eq!(
+            mux(None, Some("123.pts-0"), Some("xterm")),
+            Multiplexer::Screen
+        );
+        assert_eq!(mux(None, None, Some("tmux-256color")), Multiplexer::Tmux);
+        assert_eq!(
+            mux(Some(""), None, Some("xterm-256color")),
+            Multiplexer::None
+        );
+
+        assert_eq!(
+            MouseMode::Motion.supported(Multiplexer::Screen),
+            MouseMode::Drag
+        );
+        assert_eq!(
+            MouseMode::Motion.supported(Multiplexer::Tmux),
+            MouseMode::Motion
+        );
+
+        let plain = MouseMode::Motion.enable_seq(Multiplexer::None);
+        assert!(plain.contains("?1003h") && plain.contains("?1015h"));
+        assert!(plain.ends_with("\x1b[?1006h"));
+        let tmux = MouseMode::Click.enable_seq(Multiplexer::Tmux);
+        assert_eq!(tmux, "\x1b[?1000h\x1b[?1006h");
+        assert!(MouseMode::Off.enable_seq(Multiplexer::None).is_empty());
+        assert!(restore_seq(true).starts_with(MouseMode::DISABLE));
+        assert!(restore_seq(true).ends_with("\x1b[?1049l"));
+        assert!(!restore_seq(false).contains("1049"));
+    }
+}
@@ -7,9 +7,24 @@ pub mod shader_source;
 pub mod render_symbols;
 pub mod render_transition;
 pub mod render_general2d;
+#[cfg(feature = "ttf")]
+pub mod font;
 
 use shader::GlShader;
 
+/// How the symbol render turns atlas texels into colors.
+/// Normal: texel color * cell color.
+/// Indexed: texel red channel is a palette index (0..255), looked up in the
+/// 256 entries palette texture, then multiplied by cell color.
+/// Sprites stored as palette indices can be recolored at draw time by
+/// changing the palette, without duplicating atlas textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlRenderMode {
+    #[default]
+    Normal = 0,
+    Indexed = 1,
+}
+
 pub trait GlRender {
     fn new(canvas_width: u32, canvas_height: u32) -> Self
     where
@@ -0,0 +1,117 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! TrueType font support for graphics mode, enabled by feature "ttf".
+//! Glyphs are rasterized by fontdue on demand and packed into a dynamic
+//! atlas texture, then drawn by the symbols render like normal cells.
+//! Terminal mode keeps using plain characters.
+
+use crate::render::adapter::gl::texture::{GlCell, GlTexture};
+use fontdue::{Font, FontSettings};
+use glow::HasContext;
+use std::collections::HashMap;
+
+const ATLAS_SIZE: i32 = 1024;
+
+#[derive(Clone, Copy)]
+pub struct GlGlyph {
+    pub cell: GlCell,
+    pub xmin: f32,
+    pub ymin: f32,
+    pub advance: f32,
+}
+
+pub struct GlFontAtlas {
+    font: Font,
+    pub px: f32,
+    pub texture: GlTexture,
+    glyphs: HashMap<char, GlGlyph>,
+    // shelf packing cursor
+    cur_x: i32,
+    cur_y: i32,
+    row_h: i32,
+}
+
+impl GlFontAtlas {
+    pub fn new(gl: &glow::Context, data: &[u8], px: f32) -> Result<Self, String> {
+        let font = Font::from_bytes(data, FontSettings::default()).map_err(|e| e.to_string())?;
+        let empty = vec![0u8; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize];
+        let texture = GlTexture::new(gl, ATLAS_SIZE, ATLAS_SIZE, &empty)?;
+        Ok(Self {
+            font,
+            px,
+            texture,
+            glyphs: HashMap::new(),
+            cur_x: 0,
+            cur_y: 0,
+            row_h: 0,
+        })
+    }
+
+    /// line height in pixels
+    pub fn line_height(&self) -> f32 {
+        match self.font.horizontal_line_metrics(self.px) {
+            Some(m) => m.new_line_size,
+            None => self.px,
+        }
+    }
+
+    /// rasterizes and caches a glyph, None if the atlas is full
+    pub fn glyph(&mut self, gl: &glow::Context, ch: char) -> Option<GlGlyph> {
+        if let Some(g) = self.glyphs.get(&ch) {
+            return Some(*g);
+        }
+        let (m, coverage) = self.font.rasterize(ch, self.px);
+        let (w, h) = (m.width as i32, m.height as i32);
+        if self.cur_x + w + 1 > ATLAS_SIZE {
+            self.cur_x = 0;
+            self.cur_y += self.row_h + 1;
+            self.row_h = 0;
+        }
+        if self.cur_y + h + 1 > ATLAS_SIZE {
+            return None;
+        }
+        if w > 0 && h > 0 {
+            // white rgba, alpha from coverage, tinted by cell color in shader
+            let mut rgba = Vec::with_capacity(coverage.len() * 4);
+            for c in &coverage {
+                rgba.extend_from_slice(&[255, 255, 255, *c]);
+            }
+            unsafe {
+                gl.bind_texture(glow::TEXTURE_2D, Some(self.texture.texture));
+                gl.tex_sub_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    self.cur_x,
+                    self.cur_y,
+                    w,
+                    h,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    glow::PixelUnpackData::Slice(&rgba),
+                );
+            }
+        }
+        let fs = ATLAS_SIZE as f32;
+        let g = GlGlyph {
+            cell: GlCell {
+                texture: self.texture.texture,
+                width: w as f32,
+                height: h as f32,
+                origin_x: 0.5,
+                origin_y: 0.5,
+                uv_left: self.cur_x as f32 / fs,
+                uv_top: self.cur_y as f32 / fs,
+                uv_width: w as f32 / fs,
+                uv_height: h as f32 / fs,
+            },
+            xmin: m.xmin as f32,
+            ymin: m.ymin as f32,
+            advance: m.advance_width,
+        };
+        self.cur_x += w + 1;
+        self.row_h = self.row_h.max(h);
+        self.glyphs.insert(ch, g);
+        Some(g)
+    }
+}
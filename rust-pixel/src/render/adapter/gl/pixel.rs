@@ -5,7 +5,7 @@ use crate::render::adapter::{
     gl::{
         color::GlColor, render_general2d::GlRenderGeneral2d, render_symbols::GlRenderSymbols,
         render_transition::GlRenderTransition, texture::GlRenderTexture, transform::GlTransform,
-        GlRender, 
+        GlRender, GlRenderMode,
     },
     RenderCell,
 };
@@ -24,6 +24,12 @@ pub struct GlPixel {
     pub canvas_height: u32,
 
     clear_color: GlColor,
+
+    #[cfg(feature = "ttf")]
+    font: Option<crate::render::adapter::gl::font::GlFontAtlas>,
+    // text, x, y, color queued for this frame
+    #[cfg(feature = "ttf")]
+    text_queue: Vec<(String, f32, f32, GlColor)>,
 }
 
 impl GlPixel {
@@ -79,14 +85,27 @@ impl GlPixel {
             r_trans,
             render_textures,
             clear_color: GlColor::new(0.0, 0.0, 0.0, 1.0),
+            #[cfg(feature = "ttf")]
+            font: None,
+            #[cfg(feature = "ttf")]
+            text_queue: vec![],
         }
     }
 
-    // bind none for render to screen...
-    pub fn bind_screen(&mut self, gl: &glow::Context) {
+    // bind none for render to screen, viewport is x, y, w, h of
+    // the canvas scaled into the window...
+    pub fn bind_screen(&mut self, gl: &glow::Context, viewport: Option<[i32; 4]>) {
         unsafe {
             gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-            gl.viewport(0, 0, self.canvas_width as i32, self.canvas_height as i32);
+            match viewport {
+                Some([x, y, w, h]) => {
+                    // clear ignores the viewport, so the bars get black too
+                    gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                    gl.clear(glow::COLOR_BUFFER_BIT);
+                    gl.viewport(x, y, w, h);
+                }
+                None => gl.viewport(0, 0, self.canvas_width as i32, self.canvas_height as i32),
+            }
         }
     }
 
@@ -119,6 +138,14 @@ impl GlPixel {
         }
     }
 
+    /// linear filtering of the main and transition textures when
+    /// scaled, nearest keeps pixel art crisp
+    pub fn set_smooth(&mut self, gl: &glow::Context, smooth: bool) {
+        for rt in &self.render_textures[2..] {
+            rt.set_filter(gl, smooth);
+        }
+    }
+
     pub fn get_render_texture_hidden(&mut self, rtidx: usize) -> bool {
         self.render_textures[rtidx].is_hidden
     }
@@ -144,6 +171,33 @@ impl GlPixel {
         self.r_g2d.draw(gl);
     }
 
+    // transform whole main buffer & pixel sprites, used by Panel transform stack
+    /// draw calls and instances since the last call, for DebugHud
+    pub fn take_stats(&mut self) -> (u32, u32) {
+        let r = (self.r_sym.draw_calls, self.r_sym.instances);
+        self.r_sym.draw_calls = 0;
+        self.r_sym.instances = 0;
+        r
+    }
+
+    pub fn set_view_transform(&mut self, t: &GlTransform) {
+        self.r_sym.set_view_transform(t);
+    }
+
+    // switch symbol render between normal and palette indexed mode
+    pub fn set_render_mode(&mut self, gl: &glow::Context, mode: GlRenderMode) {
+        self.r_sym.set_render_mode(gl, mode);
+    }
+
+    pub fn get_render_mode(&self) -> GlRenderMode {
+        self.r_sym.get_render_mode()
+    }
+
+    // upload 256 rgba palette entries for GlRenderMode::Indexed
+    pub fn set_palette(&mut self, gl: &glow::Context, colors: &[u8]) {
+        self.r_sym.set_palette(gl, colors);
+    }
+
     pub fn render_rbuf(
         &mut self,
         gl: &glow::Context,
@@ -152,6 +206,25 @@ impl GlPixel {
         ratio_y: f32,
     ) {
         self.r_sym.render_rbuf(gl, rbuf, ratio_x, ratio_y);
+        #[cfg(feature = "ttf")]
+        if let Some(font) = &mut self.font {
+            for (text, x, y, c) in self.text_queue.drain(..) {
+                self.r_sym.render_text(gl, font, &text, x, y, &c);
+            }
+        }
+    }
+
+    // load a ttf font, px is the font size in pixels
+    #[cfg(feature = "ttf")]
+    pub fn load_font(&mut self, gl: &glow::Context, data: &[u8], px: f32) -> Result<(), String> {
+        self.font = Some(crate::render::adapter::gl::font::GlFontAtlas::new(gl, data, px)?);
+        Ok(())
+    }
+
+    // queue ttf text for next render_rbuf, (x, y) in pixels, y is the baseline
+    #[cfg(feature = "ttf")]
+    pub fn queue_text(&mut self, text: &str, x: f32, y: f32, color: GlColor) {
+        self.text_queue.push((text.to_string(), x, y, color));
     }
 
     pub fn render_trans_frame(
@@ -3,13 +3,15 @@
 
 use crate::render::adapter::gl::{
     color::GlColor,
-    shader::GlShader,
-    shader_source::{FRAGMENT_SRC_SYMBOLS, VERTEX_SRC_SYMBOLS},
+    shader::{GlShader, GlslProfile},
+    shader_source::{FRAGMENT_SRC_SYMBOLS, FRAGMENT_SRC_SYMBOLS_INDEXED, VERTEX_SRC_SYMBOLS},
     texture::{GlCell, GlTexture},
     transform::GlTransform,
-    GlRender, GlRenderBase,
+    GlRender, GlRenderBase, GlRenderMode,
 };
 use crate::render::adapter::{RenderCell, PIXEL_SYM_HEIGHT, PIXEL_SYM_WIDTH};
+use crate::render::style::ANSI_COLOR_RGB;
+use crate::util::profile_scope;
 use glow::HasContext;
 // use log::info;
 
@@ -19,10 +21,18 @@ pub struct GlRenderSymbols {
     instance_buffer_capacity: usize,
     instance_buffer_at: isize,
     instance_count: usize,
+    // draw calls and instances since the last take_stats
+    pub draw_calls: u32,
+    pub instances: u32,
     ubo_contents: [f32; 12],
+    profile: GlslProfile,
+    render_mode: GlRenderMode,
+    palette: Option<GlTexture>,
     pub symbols: Vec<GlCell>,
     pub transform_stack: GlTransform,
     pub transform_dirty: bool,
+    // flip y to canvas coordinate
+    base_transform: GlTransform,
 }
 
 impl GlRender for GlRenderSymbols {
@@ -44,23 +54,25 @@ impl GlRender for GlRenderSymbols {
         ubo_contents[10] = 1.0;
         ubo_contents[11] = 1.0;
 
+        let base_transform =
+            GlTransform::new_with_values(1.0, 0.0, 0.0, 0.0, -1.0, canvas_height as f32);
+
         Self {
             base,
             instance_buffer: vec![0.0; 1024],
             instance_buffer_capacity: 1024,
             instance_buffer_at: -1,
             instance_count: 0,
+            draw_calls: 0,
+            instances: 0,
             ubo_contents,
+            profile: GlslProfile::Core330,
+            render_mode: GlRenderMode::Normal,
+            palette: None,
             symbols: vec![],
-            transform_stack: GlTransform::new_with_values(
-                1.0,
-                0.0,
-                0.0,
-                0.0,
-                -1.0,
-                canvas_height as f32,
-            ),
+            transform_stack: base_transform,
             transform_dirty: true,
+            base_transform,
         }
     }
 
@@ -69,6 +81,7 @@ impl GlRender for GlRenderSymbols {
     }
 
     fn create_shader(&mut self, gl: &glow::Context, ver: &str) {
+        self.profile = GlslProfile::from_version(ver);
         let rbs = self.get_base();
         rbs.shader.push(GlShader::new(
             gl,
@@ -76,6 +89,14 @@ impl GlRender for GlRenderSymbols {
             VERTEX_SRC_SYMBOLS,
             FRAGMENT_SRC_SYMBOLS,
         ));
+        // shader[1] for GlRenderMode::Indexed, palette texture on unit 1
+        let indexed = GlShader::new(gl, ver, VERTEX_SRC_SYMBOLS, FRAGMENT_SRC_SYMBOLS_INDEXED);
+        indexed.bind(gl);
+        unsafe {
+            let loc = gl.get_uniform_location(indexed.get_program(), "palette");
+            gl.uniform_1_i32(loc.as_ref(), 1);
+        }
+        rbs.shader.push(indexed);
     }
 
     fn create_buffer(&mut self, gl: &glow::Context) {
@@ -101,10 +122,16 @@ impl GlRender for GlRenderSymbols {
                 glow::STATIC_DRAW,
             );
 
-            let ubo = gl.create_buffer().unwrap();
-            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(ubo));
-            gl.buffer_data_size(glow::UNIFORM_BUFFER, 48, glow::DYNAMIC_DRAW);
-            gl.bind_buffer_base(glow::UNIFORM_BUFFER, 0, Some(ubo));
+            // GLES 2.0 has no uniform buffer, see send_uniform_buffer
+            let ubo = if self.profile.has_ubo() {
+                let ubo = gl.create_buffer().unwrap();
+                gl.bind_buffer(glow::UNIFORM_BUFFER, Some(ubo));
+                gl.buffer_data_size(glow::UNIFORM_BUFFER, 48, glow::DYNAMIC_DRAW);
+                gl.bind_buffer_base(glow::UNIFORM_BUFFER, 0, Some(ubo));
+                Some(ubo)
+            } else {
+                None
+            };
 
             gl.bind_buffer(glow::ARRAY_BUFFER, Some(quad_vbo));
             gl.enable_vertex_attrib_array(0);
@@ -138,7 +165,10 @@ impl GlRender for GlRenderSymbols {
 
             self.base.vao = Some(vao_symbolss);
             self.base.gl_buffers.clear();
-            self.base.gl_buffers = vec![instances_vbo, quad_vbo, ubo];
+            self.base.gl_buffers = vec![instances_vbo, quad_vbo];
+            if let Some(ubo) = ubo {
+                self.base.gl_buffers.push(ubo);
+            }
         }
     }
 
@@ -149,6 +179,11 @@ impl GlRender for GlRenderSymbols {
             unsafe {
                 gl.active_texture(glow::TEXTURE0);
                 gl.bind_texture(glow::TEXTURE_2D, Some(self.base.textures[0]));
+                if let Some(pal) = &self.palette {
+                    gl.active_texture(glow::TEXTURE1);
+                    gl.bind_texture(glow::TEXTURE_2D, Some(pal.texture));
+                    gl.active_texture(glow::TEXTURE0);
+                }
             }
             self.base.textures_binded = true;
         }
@@ -160,7 +195,7 @@ impl GlRender for GlRenderSymbols {
 
         if !self.base.shader_binded {
             self.draw(gl);
-            self.base.shader[0].bind(gl);
+            self.base.shader[self.render_mode as usize].bind(gl);
             self.base.shader_binded = true;
         }
 
@@ -186,6 +221,7 @@ impl GlRender for GlRenderSymbols {
         if self.instance_count == 0 {
             return;
         }
+        let _p = profile_scope("gl_flush");
 
         unsafe {
             // instances_vbo
@@ -200,6 +236,8 @@ impl GlRender for GlRenderSymbols {
 
             gl.bind_vertex_array(self.base.vao);
             gl.draw_arrays_instanced(glow::TRIANGLE_FAN, 0, 4, self.instance_count as i32);
+            self.draw_calls += 1;
+            self.instances += self.instance_count as u32;
 
             self.instance_buffer_at = -1;
             self.instance_count = 0;
@@ -230,6 +268,65 @@ impl GlRenderSymbols {
         self.base.textures_binded = false;
     }
 
+    // view transform applied to all symbols, in screen pixels with y down
+    pub fn set_view_transform(&mut self, t: &GlTransform) {
+        let mut ts = self.base_transform;
+        ts.multiply(t);
+        if ts != self.transform_stack {
+            self.transform_stack = ts;
+            self.transform_dirty = true;
+        }
+    }
+
+    pub fn get_render_mode(&self) -> GlRenderMode {
+        self.render_mode
+    }
+
+    // flush pending instances before switching shader
+    pub fn set_render_mode(&mut self, gl: &glow::Context, mode: GlRenderMode) {
+        if mode == self.render_mode {
+            return;
+        }
+        self.draw(gl);
+        self.render_mode = mode;
+        self.base.shader_binded = false;
+        // the shader samples the palette unit, unbound it gives driver
+        // dependent colors, start with the xterm 256 colors
+        if mode == GlRenderMode::Indexed && self.palette.is_none() {
+            let colors: Vec<u8> = ANSI_COLOR_RGB
+                .iter()
+                .flat_map(|c| [c[0], c[1], c[2], 255])
+                .collect();
+            self.set_palette(gl, &colors);
+        }
+    }
+
+    // colors: 256 rgba entries, 1024 bytes
+    pub fn set_palette(&mut self, gl: &glow::Context, colors: &[u8]) {
+        assert_eq!(colors.len(), 256 * 4, "palette needs 256 rgba entries");
+        self.draw(gl);
+        match &self.palette {
+            Some(pal) => unsafe {
+                gl.bind_texture(glow::TEXTURE_2D, Some(pal.texture));
+                gl.tex_sub_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    0,
+                    0,
+                    256,
+                    1,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    glow::PixelUnpackData::Slice(colors),
+                );
+            },
+            None => {
+                self.palette = Some(GlTexture::new(gl, 256, 1, colors).unwrap());
+            }
+        }
+        self.base.textures_binded = false;
+    }
+
     fn send_uniform_buffer(&mut self, gl: &glow::Context) {
         let transform = self.transform_stack;
         self.ubo_contents[0] = transform.m00;
@@ -242,13 +339,25 @@ impl GlRenderSymbols {
         self.ubo_contents[7] = self.base.canvas_height as f32;
 
         unsafe {
-            // ubo
-            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(self.base.gl_buffers[2]));
-            gl.buffer_sub_data_u8_slice(
-                glow::UNIFORM_BUFFER,
-                0,
-                self.ubo_contents.align_to::<u8>().1,
-            );
+            if self.profile.has_ubo() {
+                // ubo
+                gl.bind_buffer(glow::UNIFORM_BUFFER, Some(self.base.gl_buffers[2]));
+                gl.buffer_sub_data_u8_slice(
+                    glow::UNIFORM_BUFFER,
+                    0,
+                    self.ubo_contents.align_to::<u8>().1,
+                );
+            } else {
+                // the uniform block is flattened into plain uniforms
+                for shader in &self.base.shader {
+                    shader.bind(gl);
+                    for (i, name) in ["tw", "th", "colorFilter"].iter().enumerate() {
+                        let loc = gl.get_uniform_location(shader.get_program(), name);
+                        gl.uniform_4_f32_slice(loc.as_ref(), &self.ubo_contents[i * 4..i * 4 + 4]);
+                    }
+                }
+                self.base.shader_binded = false;
+            }
         }
 
         self.transform_dirty = false;
@@ -261,9 +370,19 @@ impl GlRenderSymbols {
         sym: usize,
         transform: &GlTransform,
         color: &GlColor,
+    ) {
+        let frame = self.symbols[sym];
+        self.draw_cell(gl, &frame, transform, color);
+    }
+
+    fn draw_cell(
+        &mut self,
+        gl: &glow::Context,
+        frame: &GlCell,
+        transform: &GlTransform,
+        color: &GlColor,
     ) {
         self.prepare_draw(gl);
-        let frame = &self.symbols[sym];
         let instance_buffer = &mut self.instance_buffer;
 
         self.instance_buffer_at += 1;
@@ -315,11 +434,14 @@ impl GlRenderSymbols {
     ) {
         // info!("ratiox....{} ratioy....{}", ratio_x, ratio_y);
         for r in rbuf {
-            let mut transform = GlTransform::new();
+            let mut transform = r.view;
             transform.translate(r.x + r.cx - PIXEL_SYM_WIDTH, r.y + r.cy - PIXEL_SYM_HEIGHT);
             if r.angle != 0.0 {
                 transform.rotate(r.angle);
             }
+            if r.sx != 1.0 || r.sy != 1.0 {
+                transform.scale(r.sx, r.sy);
+            }
             transform.translate(
                 -r.cx + PIXEL_SYM_WIDTH / 2.0,
                 -r.cy + PIXEL_SYM_HEIGHT / 2.0,
@@ -339,6 +461,44 @@ impl GlRenderSymbols {
         self.draw(gl);
     }
 
+    // draw ttf text at pixel (x, y), y is the baseline
+    // glyphs live in the font atlas texture, so they are drawn in a separate batch
+    #[cfg(feature = "ttf")]
+    pub fn render_text(
+        &mut self,
+        gl: &glow::Context,
+        font: &mut crate::render::adapter::gl::font::GlFontAtlas,
+        text: &str,
+        x: f32,
+        y: f32,
+        color: &GlColor,
+    ) {
+        // flush symbols batch
+        self.draw(gl);
+        unsafe {
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(font.texture.texture));
+        }
+        self.base.textures_binded = true;
+        let mut pen = x;
+        for ch in text.chars() {
+            if let Some(g) = font.glyph(gl, ch) {
+                if g.cell.width > 0.0 {
+                    let mut transform = GlTransform::new();
+                    transform.translate(
+                        pen + g.xmin + g.cell.width / 2.0,
+                        y - g.ymin - g.cell.height / 2.0,
+                    );
+                    self.draw_cell(gl, &g.cell, &transform, color);
+                }
+                pen += g.advance;
+            }
+        }
+        // draw resets textures_binded, symbol texture is bound again next batch
+        self.draw(gl);
+        self.base.textures_binded = false;
+    }
+
     fn make_symbols_frame(&mut self, sheet: &mut GlTexture, x: f32, y: f32) -> GlCell {
         let origin_x = 0.5;
         let origin_y = 0.5;
@@ -116,6 +116,8 @@ impl GlRender for GlRenderTransition {
             );
             let lb = gl.get_uniform_location(self.base.shader[self.shader_idx].program, "progress");
             gl.uniform_1_f32(lb.as_ref(), self.progress);
+            let ls = gl.get_uniform_location(self.base.shader[self.shader_idx].program, "texSize");
+            gl.uniform_2_f32(ls.as_ref(), self.width as f32, self.height as f32);
         }
     }
 
@@ -1,8 +1,139 @@
 // RustPixel
 // copyright zipxing@hotmail.com 2022~2024
 
+//! Shader sources in shader_source.rs are written in GLSL 3.30 / GLSL ES 3.00 style.
+//! GlShader translates them at runtime for the selected GlslProfile, so the same
+//! sources also run on GLES 2.0 / WebGL1 devices (Raspberry Pi, older Android GPUs).
+//!
+//! GLES 2.0 profile needs the instanced arrays and vertex array object extensions
+//! (EXT_instanced_arrays / ANGLE_instanced_arrays, OES_vertex_array_object).
+
 use glow::HasContext;
+use lazy_static::lazy_static;
 use log::info;
+use regex::Regex;
+
+lazy_static! {
+    static ref RE_ATTRIB: Regex =
+        Regex::new(r"layout\s*\(\s*location\s*=\s*(\d+)\s*\)\s*in\s+(\w+)\s+(\w+)\s*;").unwrap();
+    static ref RE_UBO: Regex =
+        Regex::new(r"layout\s*\(\s*std140\s*\)\s*uniform\s+\w+\s*\{([^}]*)\}\s*;").unwrap();
+    static ref RE_FRAG_OUT: Regex =
+        Regex::new(r"(?:layout\s*\(\s*location\s*=\s*\d+\s*\)\s*)?out\s+vec4\s+(\w+)\s*;").unwrap();
+    // global declarations only, a whole `in vec2 uv;` line
+    static ref RE_IN: Regex = Regex::new(r"^(\s*)in\s+([^;]*;)").unwrap();
+    static ref RE_OUT: Regex = Regex::new(r"^(\s*)out\s+([^;]*;)").unwrap();
+    static ref RE_TEXTURE: Regex = Regex::new(r"\btexture\s*\(").unwrap();
+}
+
+/// GLSL dialects supported by the gl renders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslProfile {
+    /// desktop OpenGL 3.3 core
+    Core330,
+    /// OpenGL ES 3.0 / WebGL2
+    Es300,
+    /// OpenGL ES 2.0 / WebGL1
+    Es100,
+}
+
+impl GlslProfile {
+    /// select profile by the version header string passed to GlPixel
+    pub fn from_version(ver: &str) -> Self {
+        if ver.contains("330") {
+            GlslProfile::Core330
+        } else if ver.contains("300") {
+            GlslProfile::Es300
+        } else {
+            GlslProfile::Es100
+        }
+    }
+
+    /// select profile by the version of a created gl context
+    pub fn detect(gl: &glow::Context) -> Self {
+        let v = gl.version();
+        info!("gl version...{:?}", v);
+        if !v.is_embedded {
+            GlslProfile::Core330
+        } else if v.major >= 3 {
+            GlslProfile::Es300
+        } else {
+            GlslProfile::Es100
+        }
+    }
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            GlslProfile::Core330 => "#version 330 core",
+            GlslProfile::Es300 => "#version 300 es",
+            GlslProfile::Es100 => "#version 100",
+        }
+    }
+
+    /// uniform buffer objects are not available in GLES 2.0
+    pub fn has_ubo(&self) -> bool {
+        *self != GlslProfile::Es100
+    }
+}
+
+/// rewrite a GLSL 3.x vertex shader to GLSL ES 1.00,
+/// returns the new source and the attribute locations to bind before linking
+fn vertex_to_es100(src: &str) -> (String, Vec<(u32, String)>) {
+    let mut locations = vec![];
+    for cap in RE_ATTRIB.captures_iter(src) {
+        locations.push((cap[1].parse::<u32>().unwrap(), cap[3].to_string()));
+    }
+    let s = RE_ATTRIB.replace_all(src, "attribute $2 $3;");
+    let s = flatten_ubo(&s);
+    let s = globals_to_es100(&s, &RE_IN, "attribute");
+    let s = globals_to_es100(&s, &RE_OUT, "varying");
+    let s = RE_TEXTURE.replace_all(&s, "texture2D(");
+    (s.to_string(), locations)
+}
+
+/// rewrite a GLSL 3.x fragment shader to GLSL ES 1.00
+fn fragment_to_es100(src: &str) -> String {
+    let s = flatten_ubo(src);
+    let s = RE_FRAG_OUT.replace_all(&s, "\n#define $1 gl_FragColor\n");
+    let s = globals_to_es100(&s, &RE_IN, "varying");
+    let s = RE_TEXTURE.replace_all(&s, "texture2D(");
+    s.to_string()
+}
+
+/// renames the in / out qualifier of global declarations, lines inside
+/// function bodies and parameters like `void f(in vec2 p, out float d)`
+/// keep theirs
+fn globals_to_es100(src: &str, re: &Regex, qualifier: &str) -> String {
+    let mut depth = 0i32;
+    src.lines()
+        .map(|l| {
+            let r = if depth == 0 {
+                re.replace(l, format!("${{1}}{} $2", qualifier).as_str())
+                    .to_string()
+            } else {
+                l.to_string()
+            };
+            depth += l.matches('{').count() as i32 - l.matches('}').count() as i32;
+            r
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// uniform block members become plain uniforms
+fn flatten_ubo(src: &str) -> String {
+    RE_UBO
+        .replace_all(src, |cap: &regex::Captures| {
+            cap[1]
+                .split(';')
+                .map(|m| m.trim())
+                .filter(|m| !m.is_empty())
+                .map(|m| format!("uniform {};", m))
+                .collect::<Vec<String>>()
+                .join("\n")
+        })
+        .to_string()
+}
 
 #[derive(Clone)]
 pub struct GlShader {
@@ -11,9 +142,16 @@ pub struct GlShader {
 
 impl GlShader {
     pub fn new(gl: &glow::Context, ver: &str, vertex_source: &str, fragment_source: &str) -> Self {
+        let profile = GlslProfile::from_version(ver);
+        let (vsrc, fsrc, locations) = if profile == GlslProfile::Es100 {
+            let (v, l) = vertex_to_es100(vertex_source);
+            (v, fragment_to_es100(fragment_source), l)
+        } else {
+            (vertex_source.to_string(), fragment_source.to_string(), vec![])
+        };
         unsafe {
             let vertex_shader = gl.create_shader(glow::VERTEX_SHADER).unwrap();
-            gl.shader_source(vertex_shader, &format!("{}\n{}", ver, vertex_source));
+            gl.shader_source(vertex_shader, &format!("{}\n{}", ver, vsrc));
             gl.compile_shader(vertex_shader);
             if !gl.get_shader_compile_status(vertex_shader) {
                 info!(
@@ -23,7 +161,7 @@ impl GlShader {
             }
 
             let fragment_shader = gl.create_shader(glow::FRAGMENT_SHADER).unwrap();
-            gl.shader_source(fragment_shader, &format!("{}\n{}", ver, fragment_source));
+            gl.shader_source(fragment_shader, &format!("{}\n{}", ver, fsrc));
             gl.compile_shader(fragment_shader);
             if !gl.get_shader_compile_status(fragment_shader) {
                 info!(
@@ -35,6 +173,10 @@ impl GlShader {
             let program = gl.create_program().unwrap();
             gl.attach_shader(program, vertex_shader);
             gl.attach_shader(program, fragment_shader);
+            // GLSL ES 1.00 has no layout qualifier, keep the attribute locations
+            for (idx, name) in &locations {
+                gl.bind_attrib_location(program, *idx, name);
+            }
             gl.link_program(program);
             if !gl.get_program_link_status(program) {
                 panic!(
@@ -61,3 +203,23 @@ impl GlShader {
         self.program
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn es100_keeps_parameter_qualifiers() {
+        let fs =
+            "in vec2 uv;\nvoid f(in vec2 p, out float d) {\n    d = p.x;\n}\nout vec4 color;\n";
+        let s = fragment_to_es100(fs);
+        assert!(s.starts_with("varying vec2 uv;"));
+        assert!(s.contains("void f(in vec2 p, out float d)"));
+        assert!(s.contains("#define color gl_FragColor"));
+
+        let vs = "  in vec2 a;\n  out vec2 uv;\nvoid g(inout vec2 q) {}\n";
+        let (s, _) = vertex_to_es100(vs);
+        assert!(s.contains("  attribute vec2 a;\n  varying vec2 uv;"));
+        assert!(s.contains("void g(inout vec2 q)"));
+    }
+}
@@ -36,6 +36,26 @@ pub const FRAGMENT_SRC_SYMBOLS: &str = r#"
             }
         "#;
 
+// palette index is stored in the red channel of atlas texel
+pub const FRAGMENT_SRC_SYMBOLS_INDEXED: &str = r#"
+            precision mediump float;
+            uniform sampler2D source;
+            uniform sampler2D palette;
+            layout(std140) uniform transform {
+                vec4 tw;
+                vec4 th;
+                vec4 colorFilter;
+            };
+            in vec2 uv;
+            in vec4 colorj;
+            layout(location=0) out vec4 color;
+            void main() {
+                vec4 texel = texture(source, uv);
+                vec4 pal = texture(palette, vec2((texel.r * 255.0 + 0.5) / 256.0, 0.5));
+                color = vec4(pal.rgb, pal.a * texel.a) * colorj;
+            }
+        "#;
+
 // trans shader ...
 pub const VERTEX_SRC_TRANS: &str = r#"
             precision mediump float;
@@ -48,7 +68,7 @@ pub const VERTEX_SRC_TRANS: &str = r#"
             }
         "#;
 
-pub const TRANS_FS: [&str; 7] = [
+pub const TRANS_FS: [&str; 13] = [
     r#"
           const ivec2 squaresMin = ivec2(20);
           const int steps = 50;
@@ -158,6 +178,40 @@ pub const TRANS_FS: [&str; 7] = [
               }
             }
     "#,
+    r#"
+            vec4 transition(vec2 uv) {
+              return mix(getFromColor(uv), getToColor(uv), progress);
+            }
+    "#,
+    r#"
+            float rand(vec2 co) {
+              return fract(sin(dot(co, vec2(12.9898, 78.233))) * 43758.5453);
+            }
+            vec4 transition(vec2 uv) {
+              vec2 cell = floor(uv * texSize / 8.0);
+              return rand(cell) < progress ? getToColor(uv) : getFromColor(uv);
+            }
+    "#,
+    r#"
+            vec4 transition(vec2 uv) {
+              return uv.x < progress ? getToColor(uv) : getFromColor(uv);
+            }
+    "#,
+    r#"
+            vec4 transition(vec2 uv) {
+              return 1.0 - uv.x < progress ? getToColor(uv) : getFromColor(uv);
+            }
+    "#,
+    r#"
+            vec4 transition(vec2 uv) {
+              return 1.0 - uv.y < progress ? getToColor(uv) : getFromColor(uv);
+            }
+    "#,
+    r#"
+            vec4 transition(vec2 uv) {
+              return uv.y < progress ? getToColor(uv) : getFromColor(uv);
+            }
+    "#,
 ];
 
 pub fn get_trans_fragment_src() -> Vec<String> {
@@ -171,6 +225,8 @@ pub fn get_trans_fragment_src() -> Vec<String> {
             uniform sampler2D texture1;
             uniform sampler2D texture2;
             uniform float progress;
+            // pixel size of the textures, GLSL ES 1.00 has no textureSize
+            uniform vec2 texSize;
             vec4 getFromColor(vec2 uv) {{ return texture(texture1, uv); }}
             vec4 getToColor(vec2 uv) {{ return texture(texture2, uv); }}
             {}
@@ -88,6 +88,16 @@ impl GlRenderTexture {
         self.texture
     }
 
+    pub fn set_filter(&self, gl: &glow::Context, linear: bool) {
+        let f = if linear { glow::LINEAR } else { glow::NEAREST } as i32;
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, f);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, f);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+    }
+
     pub fn free(&self, gl: &glow::Context) {
         unsafe {
             gl.delete_framebuffer(self.framebuffer);
@@ -104,7 +114,7 @@ pub struct GlTexture {
     framebuffer: glow::Framebuffer,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct GlCell {
     pub texture: glow::Texture,
     pub width: f32,
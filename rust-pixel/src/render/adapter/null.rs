@@ -0,0 +1,94 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! NullAdapter draws nothing and reads no device, for headless servers
+//! and fast-forward simulations. Input is injected by pushing events,
+//! quit ends Game::run_headless.
+
+use crate::{
+    event::Event,
+    render::{
+        adapter::{Adapter, AdapterBase},
+        buffer::Buffer,
+        sprite::Sprites,
+    },
+};
+use std::any::Any;
+use std::time::Duration;
+
+pub struct NullAdapter {
+    pub base: AdapterBase,
+    /// events returned by the next poll_event
+    pub events: Vec<Event>,
+    pub quit: bool,
+}
+
+impl NullAdapter {
+    pub fn new(pre: &str, gn: &str, project_path: &str) -> Self {
+        Self {
+            base: AdapterBase::new(pre, gn, project_path),
+            events: vec![],
+            quit: false,
+        }
+    }
+}
+
+impl Adapter for NullAdapter {
+    fn init(&mut self, w: u16, h: u16, rx: f32, ry: f32, s: String) {
+        self.set_size(w, h)
+            .set_ratiox(rx)
+            .set_ratioy(ry)
+            .set_pixel_size()
+            .set_title(s);
+    }
+
+    fn reset(&mut self) {}
+
+    fn get_base(&mut self) -> &mut AdapterBase {
+        &mut self.base
+    }
+
+    fn poll_event(&mut self, timeout: Duration, ev: &mut Vec<Event>) -> bool {
+        std::thread::sleep(timeout);
+        ev.append(&mut self.events);
+        self.quit
+    }
+
+    fn draw_all_to_screen(
+        &mut self,
+        _current_buffer: &Buffer,
+        _previous_buffer: &Buffer,
+        _pixel_sprites: &mut Vec<Sprites>,
+        _stage: u32,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn cell_width(&self) -> f32 {
+        0.0
+    }
+
+    fn cell_height(&self) -> f32 {
+        0.0
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn set_cursor(&mut self, _x: u16, _y: u16) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn get_cursor(&mut self) -> Result<(u16, u16), String> {
+        Ok((0, 0))
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
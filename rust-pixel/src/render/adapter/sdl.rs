@@ -5,11 +5,13 @@
 //! Includes resizing of height and width, init settings.
 //! Use opengl and glow mod for rendering.
 use crate::event::{
-    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton::*, MouseEvent, MouseEventKind::*,
+    Event, GamepadAxis, GamepadButton, GamepadEvent, GamepadEventKind, KeyCode, KeyEvent,
+    KeyEventKind, KeyModifiers, MouseButton::*, MouseEvent, MouseEventKind::*, TouchEvent, TouchEventKind,
 };
 use crate::render::{
     adapter::{
-        gl::pixel::GlPixel, Adapter, AdapterBase, PIXEL_SYM_HEIGHT, PIXEL_SYM_WIDTH,
+        gl::{pixel::GlPixel, shader::GlslProfile},
+        Adapter, AdapterBase, PIXEL_SYM_HEIGHT, PIXEL_SYM_WIDTH,
         PIXEL_TEXTURE_FILES,
     },
     buffer::Buffer,
@@ -17,13 +19,15 @@ use crate::render::{
 };
 use log::info;
 use sdl2::{
+    controller::{Axis as SAxis, Button as SButton, GameController},
     event::Event as SEvent,
     image::{InitFlag, LoadSurface},
-    keyboard::Keycode as SKeycode,
+    keyboard::{Keycode as SKeycode, Mod},
     mouse::*,
     surface::Surface,
-    video::{Window, WindowPos::Positioned},
-    EventPump, Sdl,
+    video::{FullscreenType, Window, WindowPos::Positioned},
+    event::WindowEvent,
+    EventPump, GameControllerSubsystem, Sdl,
 };
 use std::any::Any;
 use std::time::Duration;
@@ -47,6 +51,10 @@ pub struct SdlAdapter {
     pub sdl_window: Option<Window>,
     pub event_pump: Option<EventPump>,
 
+    // opened game controllers
+    pub controller_subsystem: Option<GameControllerSubsystem>,
+    pub controllers: Vec<GameController>,
+
     // gl object
     pub gl_context: Option<sdl2::video::GLContext>,
 
@@ -55,6 +63,9 @@ pub struct SdlAdapter {
 
     // data for dragging the window
     drag: Drag,
+
+    // last mouse position in pixels, wheel events have none
+    mouse_pos: (i32, i32),
 }
 
 pub enum SdlBorderArea {
@@ -70,10 +81,13 @@ impl SdlAdapter {
             base: AdapterBase::new(pre, gn, project_path),
             sdl_context: sdl2::init().unwrap(),
             event_pump: None,
+            controller_subsystem: None,
+            controllers: vec![],
             cursor: None,
             sdl_window: None,
             gl_context: None,
             drag: Default::default(),
+            mouse_pos: (0, 0),
         }
     }
 
@@ -89,6 +103,44 @@ impl SdlAdapter {
         self.sdl_context.mouse().show_cursor(true);
     }
 
+    // opens or closes controllers, converts controller events to Event::Gamepad
+    fn controller_event(&mut self, e: &SEvent) -> Option<Event> {
+        let (id, kind) = match *e {
+            SEvent::ControllerDeviceAdded { which, .. } => {
+                let gc = self.controller_subsystem.as_ref()?.open(which).ok()?;
+                info!("controller connected:{}", gc.name());
+                let id = gc.instance_id();
+                self.controllers.push(gc);
+                (id, GamepadEventKind::Connected)
+            }
+            SEvent::ControllerDeviceRemoved { which, .. } => {
+                self.controllers.retain(|c| c.instance_id() != which);
+                (which, GamepadEventKind::Disconnected)
+            }
+            SEvent::ControllerButtonDown { which, button, .. } => {
+                (which, GamepadEventKind::ButtonDown(gamepad_button(button)?))
+            }
+            SEvent::ControllerButtonUp { which, button, .. } => {
+                (which, GamepadEventKind::ButtonUp(gamepad_button(button)?))
+            }
+            SEvent::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                let a = match axis {
+                    SAxis::LeftX => GamepadAxis::LeftX,
+                    SAxis::LeftY => GamepadAxis::LeftY,
+                    SAxis::RightX => GamepadAxis::RightX,
+                    SAxis::RightY => GamepadAxis::RightY,
+                    SAxis::TriggerLeft => GamepadAxis::TriggerLeft,
+                    SAxis::TriggerRight => GamepadAxis::TriggerRight,
+                };
+                (which, GamepadEventKind::Axis(a, value))
+            }
+            _ => return None,
+        };
+        Some(Event::Gamepad(GamepadEvent { id, kind }))
+    }
+
     fn in_border(&self, x: i32, y: i32) -> SdlBorderArea {
         let w = self.cell_width();
         let h = self.cell_height();
@@ -108,11 +160,12 @@ impl SdlAdapter {
 
     fn drag_window(&mut self, event: &SEvent) -> bool {
         match *event {
-            SEvent::Quit { .. }
-            | SEvent::KeyDown {
+            SEvent::Quit { .. } => return true,
+            // q is typed text in text input mode
+            SEvent::KeyDown {
                 keycode: Some(SKeycode::Q),
                 ..
-            } => return true,
+            } if !self.base.text_input => return true,
             SEvent::MouseButtonDown {
                 mouse_btn: sdl2::mouse::MouseButton::Left,
                 x,
@@ -152,6 +205,22 @@ impl SdlAdapter {
     }
 }
 
+impl SdlAdapter {
+    // maps mouse positions of a scaled window back to canvas pixels
+    fn unscale_mouse(&self, e: SEvent) -> SEvent {
+        let mut e = e;
+        match &mut e {
+            SEvent::MouseMotion { x, y, .. }
+            | SEvent::MouseButtonDown { x, y, .. }
+            | SEvent::MouseButtonUp { x, y, .. } => {
+                (*x, *y) = self.base.window_to_canvas(*x, *y);
+            }
+            _ => {}
+        }
+        e
+    }
+}
+
 impl Adapter for SdlAdapter {
     fn init(&mut self, w: u16, h: u16, rx: f32, ry: f32, s: String) {
         self.set_size(w, h)
@@ -163,24 +232,38 @@ impl Adapter for SdlAdapter {
         let video_subsystem = self.sdl_context.video().unwrap();
         let _image_context = sdl2::image::init(InitFlag::PNG | InitFlag::JPG).unwrap();
 
-        // Set OpenGL attributes
-        {
-            let gl_attr = video_subsystem.gl_attr();
-            gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
-            gl_attr.set_context_version(3, 3);
-        }
-
         let window = video_subsystem
             .window(&self.base.title, self.base.pixel_w, self.base.pixel_h)
             .opengl()
             .position_centered()
             .borderless()
+            .resizable()
             // .fullscreen()
             .build()
             .map_err(|e| e.to_string())
             .unwrap();
 
-        let gl_context = window.gl_create_context().unwrap();
+        // Try OpenGL 3.3 core first, then fall back to GLES 3.0 and GLES 2.0
+        // for embedded linux devices like Raspberry Pi
+        let gl_context = [
+            (sdl2::video::GLProfile::Core, 3, 3),
+            (sdl2::video::GLProfile::GLES, 3, 0),
+            (sdl2::video::GLProfile::GLES, 2, 0),
+        ]
+        .iter()
+        .find_map(|(profile, major, minor)| {
+            let gl_attr = video_subsystem.gl_attr();
+            gl_attr.set_context_profile(*profile);
+            gl_attr.set_context_version(*major, *minor);
+            match window.gl_create_context() {
+                Ok(ctx) => Some(ctx),
+                Err(e) => {
+                    info!("gl context {:?} {}.{} failed...{}", profile, major, minor, e);
+                    None
+                }
+            }
+        })
+        .expect("no usable OpenGL or OpenGL ES context");
         self.gl_context = Some(gl_context);
         video_subsystem.gl_set_swap_interval(1).unwrap(); // Enable vsync
 
@@ -191,6 +274,8 @@ impl Adapter for SdlAdapter {
             })
         };
 
+        let glsl_profile = GlslProfile::detect(&gl);
+
         // Store the OpenGL context
         self.base.gl = Some(gl);
         self.sdl_window = Some(window);
@@ -211,7 +296,7 @@ impl Adapter for SdlAdapter {
             let height = img.height();
             self.base.gl_pixel = Some(GlPixel::new(
                 self.base.gl.as_ref().unwrap(),
-                "#version 330 core",
+                glsl_profile.header(),
                 self.base.pixel_w as i32,
                 self.base.pixel_h as i32,
                 width as i32,
@@ -235,6 +320,12 @@ impl Adapter for SdlAdapter {
 
         // init event_pump
         self.event_pump = Some(self.sdl_context.event_pump().unwrap());
+
+        // controllers plugged in are reported by ControllerDeviceAdded
+        self.controller_subsystem = self.sdl_context.game_controller().ok();
+
+        // sdl starts with text input on, only enable it for text fields
+        video_subsystem.text_input().stop();
     }
 
     fn get_base(&mut self) -> &mut AdapterBase {
@@ -253,27 +344,109 @@ impl Adapter for SdlAdapter {
 
     fn poll_event(&mut self, timeout: Duration, es: &mut Vec<Event>) -> bool {
         let mut ses: Vec<SEvent> = vec![];
-        if let Some(ref mut ep) = self.event_pump {
-            for event in ep.poll_iter() {
-                ses.push(event.clone());
-                // convert sdl events to pixel events, providing a unified processing interfaces
-                if let Some(et) =
-                    input_events_from_sdl(&event, self.base.ratio_x, self.base.ratio_y)
+        let events: Vec<SEvent> = match self.event_pump {
+            Some(ref mut ep) => ep.poll_iter().collect(),
+            None => return false,
+        };
+        for event in events {
+            // drag_window moves the window, it needs window coordinates
+            ses.push(event.clone());
+            let event = self.unscale_mouse(event);
+            match event {
+                SEvent::Window {
+                    win_event: WindowEvent::SizeChanged(w, h),
+                    ..
+                } => self.base.window_resized(w.max(1) as u32, h.max(1) as u32),
+                SEvent::MouseMotion {
+                    x, y, xrel, yrel, ..
+                } => {
+                    self.mouse_pos = (x, y);
+                    self.base.pointer_moved(xrel, yrel);
+                }
+                SEvent::MouseButtonDown { x, y, .. } | SEvent::MouseButtonUp { x, y, .. } => {
+                    self.mouse_pos = (x, y)
+                }
+                SEvent::MouseWheel { y, .. } if y != 0 && !self.drag.draging => {
+                    let mc = MouseEvent {
+                        kind: if y > 0 { ScrollUp } else { ScrollDown },
+                        column: self.mouse_pos.0.max(0) as u16,
+                        row: self.mouse_pos.1.max(0) as u16,
+                        modifiers: KeyModifiers::NONE,
+                    };
+                    es.push(sdl_mouse_to_cell(mc, self.base.ratio_x, self.base.ratio_y));
+                }
+                SEvent::KeyDown {
+                    keycode: Some(SKeycode::V),
+                    keymod,
+                    ..
+                } if keymod.intersects(
+                    Mod::LCTRLMOD | Mod::RCTRLMOD | Mod::LGUIMOD | Mod::RGUIMOD,
+                ) =>
                 {
-                    if !self.drag.draging {
-                        es.push(et);
+                    let cb = self.sdl_context.video().map(|v| v.clipboard());
+                    if let Ok(text) = cb.and_then(|c| c.clipboard_text()) {
+                        es.push(Event::Paste(text));
                     }
                 }
+                SEvent::TextInput { ref text, .. } if self.base.text_input => {
+                    es.push(Event::Text(text.clone()));
+                }
+                SEvent::TextEditing { ref text, .. } if self.base.text_input => {
+                    es.push(Event::TextEditing(text.clone()));
+                }
+                SEvent::FingerDown {
+                    finger_id, x, y, ..
+                }
+                | SEvent::FingerMotion {
+                    finger_id, x, y, ..
+                }
+                | SEvent::FingerUp {
+                    finger_id, x, y, ..
+                } => {
+                    let kind = match event {
+                        SEvent::FingerDown { .. } => TouchEventKind::Start,
+                        SEvent::FingerMotion { .. } => TouchEventKind::Move,
+                        _ => TouchEventKind::End,
+                    };
+                    // finger position is normalized to the window size
+                    let (w, h) = self.sdl_window.as_ref().map(|w| w.size()).unwrap_or((0, 0));
+                    let (column, row) = sdl_pos_to_cell(
+                        (x * w as f32) as u16,
+                        (y * h as f32) as u16,
+                        self.base.ratio_x,
+                        self.base.ratio_y,
+                    );
+                    es.push(Event::Touch(TouchEvent {
+                        id: finger_id as u64,
+                        kind,
+                        column,
+                        row,
+                    }));
+                }
+                _ => {}
             }
-            for event in ses {
-                // sdl window is borderless, we draw the title and border ourselves
-                // processing mouse events such as dragging of borders, close, etc.
-                if self.drag_window(&event) {
-                    return true;
+            // convert sdl events to pixel events, providing a unified processing interfaces
+            if let Some(et) =
+                input_events_from_sdl(&event, self.base.ratio_x, self.base.ratio_y)
+            {
+                if !self.drag.draging {
+                    es.push(et);
                 }
             }
-            ::std::thread::sleep(timeout);
         }
+        for event in &ses {
+            if let Some(et) = self.controller_event(event) {
+                es.push(et);
+            }
+        }
+        for event in ses {
+            // sdl window is borderless, we draw the title and border ourselves
+            // processing mouse events such as dragging of borders, close, etc.
+            if self.drag_window(&event) {
+                return true;
+            }
+        }
+        ::std::thread::sleep(timeout);
         false
     }
 
@@ -315,6 +488,58 @@ impl Adapter for SdlAdapter {
         Ok((0, 0))
     }
 
+    fn set_fullscreen(&mut self, on: bool) {
+        let ft = if on {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        };
+        if let Some(w) = self.sdl_window.as_mut() {
+            match w.set_fullscreen(ft) {
+                // the new window size comes as SizeChanged
+                Ok(_) => self.base.fullscreen_changed(on),
+                Err(e) => info!("fullscreen failed:{}", e),
+            }
+        }
+    }
+
+    fn set_pointer_lock(&mut self, on: bool) {
+        self.sdl_context.mouse().set_relative_mouse_mode(on);
+        self.base.pointer_lock_changed(on);
+    }
+
+    fn set_window_title(&mut self, t: &str) -> Result<(), String> {
+        self.base.title = t.to_string();
+        // shown by the task bar, the borderless window draws no title
+        if let Some(w) = self.sdl_window.as_mut() {
+            w.set_title(t).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn set_text_input(&mut self, on: bool) {
+        self.base.text_input = on;
+        if let Ok(v) = self.sdl_context.video() {
+            if on {
+                v.text_input().start();
+            } else {
+                v.text_input().stop();
+            }
+        }
+    }
+
+    fn clipboard_get(&mut self) -> Option<String> {
+        let cb = self.sdl_context.video().ok()?.clipboard();
+        cb.clipboard_text().ok().filter(|s| !s.is_empty())
+    }
+
+    fn clipboard_set(&mut self, s: &str) {
+        self.base.clipboard = s.to_string();
+        if let Ok(v) = self.sdl_context.video() {
+            let _ = v.clipboard().set_clipboard_text(s);
+        }
+    }
+
     fn as_any(&mut self) -> &mut dyn Any {
         self
     }
@@ -341,14 +566,78 @@ macro_rules! sdl_event {
     };
 }
 
+fn gamepad_button(b: SButton) -> Option<GamepadButton> {
+    Some(match b {
+        SButton::A => GamepadButton::A,
+        SButton::B => GamepadButton::B,
+        SButton::X => GamepadButton::X,
+        SButton::Y => GamepadButton::Y,
+        SButton::Back => GamepadButton::Back,
+        SButton::Guide => GamepadButton::Guide,
+        SButton::Start => GamepadButton::Start,
+        SButton::LeftStick => GamepadButton::LeftStick,
+        SButton::RightStick => GamepadButton::RightStick,
+        SButton::LeftShoulder => GamepadButton::LeftShoulder,
+        SButton::RightShoulder => GamepadButton::RightShoulder,
+        SButton::DPadUp => GamepadButton::DPadUp,
+        SButton::DPadDown => GamepadButton::DPadDown,
+        SButton::DPadLeft => GamepadButton::DPadLeft,
+        SButton::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
 /// Convert sdl input events to RustPixel event, for the sake of unified event processing
 /// For keyboard and mouse event, please refer to the handle_input method in game/unblock/model.rs
+fn sdl_key_modifiers(m: Mod) -> KeyModifiers {
+    let mut km = KeyModifiers::NONE;
+    if m.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+        km.insert(KeyModifiers::SHIFT);
+    }
+    if m.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+        km.insert(KeyModifiers::CONTROL);
+    }
+    if m.intersects(Mod::LALTMOD | Mod::RALTMOD) {
+        km.insert(KeyModifiers::ALT);
+    }
+    if m.intersects(Mod::LGUIMOD | Mod::RGUIMOD) {
+        km.insert(KeyModifiers::SUPER);
+    }
+    km
+}
+
 pub fn input_events_from_sdl(e: &SEvent, adjx: f32, adjy: f32) -> Option<Event> {
-    let sym_width = PIXEL_SYM_WIDTH;
-    let sym_height = PIXEL_SYM_HEIGHT;
     let mut mcte: Option<MouseEvent> = None;
     match e {
-        SEvent::KeyDown { keycode, .. } => {
+        SEvent::KeyDown {
+            keycode,
+            keymod,
+            repeat,
+            ..
+        } => {
+            let mods = sdl_key_modifiers(*keymod);
+            let kind = if *repeat {
+                KeyEventKind::Repeat
+            } else {
+                KeyEventKind::Press
+            };
+            let edit_key = match keycode {
+                Some(SKeycode::Up) => Some(KeyCode::Up),
+                Some(SKeycode::Down) => Some(KeyCode::Down),
+                Some(SKeycode::Left) => Some(KeyCode::Left),
+                Some(SKeycode::Right) => Some(KeyCode::Right),
+                Some(SKeycode::Return) => Some(KeyCode::Enter),
+                Some(SKeycode::Escape) => Some(KeyCode::Esc),
+                Some(SKeycode::Backspace) => Some(KeyCode::Backspace),
+                Some(SKeycode::Delete) => Some(KeyCode::Delete),
+                Some(SKeycode::Home) => Some(KeyCode::Home),
+                Some(SKeycode::End) => Some(KeyCode::End),
+                Some(SKeycode::Tab) => Some(KeyCode::Tab),
+                _ => None,
+            };
+            if let Some(k) = edit_key {
+                return Some(Event::Key(KeyEvent::new_with_kind(k, mods, kind)));
+            }
             let kc = match keycode {
                 Some(SKeycode::Space) => ' ',
                 Some(SKeycode::A) => 'a',
@@ -381,36 +670,54 @@ pub fn input_events_from_sdl(e: &SEvent, adjx: f32, adjy: f32) -> Option<Event>
                     return None;
                 }
             };
-            let cte = KeyEvent::new(KeyCode::Char(kc), KeyModifiers::NONE);
+            let cte = KeyEvent::new_with_kind(KeyCode::Char(kc), mods, kind);
             return Some(Event::Key(cte));
         }
-        SEvent::MouseButtonUp { x, y, .. } => {
-            mcte = sdl_event!(Up, *x, *y, Left);
+        SEvent::MouseButtonUp {
+            x, y, mouse_btn, ..
+        } => {
+            mcte = sdl_event!(Up, *x, *y, mouse_button(*mouse_btn)?);
         }
-        SEvent::MouseButtonDown { x, y, .. } => {
-            mcte = sdl_event!(Down, *x, *y, Left);
+        SEvent::MouseButtonDown {
+            x, y, mouse_btn, ..
+        } => {
+            mcte = sdl_event!(Down, *x, *y, mouse_button(*mouse_btn)?);
         }
         SEvent::MouseMotion {
             x, y, mousestate, ..
         } => {
             if mousestate.left() {
                 mcte = sdl_event!(Drag, *x, *y, Left);
+            } else if mousestate.right() {
+                mcte = sdl_event!(Drag, *x, *y, Right);
+            } else if mousestate.middle() {
+                mcte = sdl_event!(Drag, *x, *y, Middle);
             } else {
                 mcte = sdl_event!(Moved, *x, *y,);
             }
         }
         _ => {}
     }
-    if let Some(mut mc) = mcte {
-        mc.column /= (sym_width / adjx) as u16;
-        mc.row /= (sym_height / adjy) as u16;
-        if mc.column >= 1 {
-            mc.column -= 1;
-        }
-        if mc.row >= 1 {
-            mc.row -= 1;
-        }
-        return Some(Event::Mouse(mc));
+    mcte.map(|mc| sdl_mouse_to_cell(mc, adjx, adjy))
+}
+
+fn mouse_button(b: sdl2::mouse::MouseButton) -> Option<crate::event::MouseButton> {
+    match b {
+        sdl2::mouse::MouseButton::Left => Some(Left),
+        sdl2::mouse::MouseButton::Right => Some(Right),
+        sdl2::mouse::MouseButton::Middle => Some(Middle),
+        _ => None,
     }
-    None
+}
+
+// pixel position to cell position, border excluded
+fn sdl_pos_to_cell(x: u16, y: u16, adjx: f32, adjy: f32) -> (u16, u16) {
+    let column = x / (PIXEL_SYM_WIDTH / adjx) as u16;
+    let row = y / (PIXEL_SYM_HEIGHT / adjy) as u16;
+    (column.saturating_sub(1), row.saturating_sub(1))
+}
+
+fn sdl_mouse_to_cell(mut mc: MouseEvent, adjx: f32, adjy: f32) -> Event {
+    (mc.column, mc.row) = sdl_pos_to_cell(mc.column, mc.row, adjx, adjy);
+    Event::Mouse(mc)
 }
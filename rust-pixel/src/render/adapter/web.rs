@@ -4,36 +4,132 @@
 //! Implements an Adapter trait. Moreover, all SDL related processing is handled here.
 //! Includes resizing of height and width, init settings.
 //! Use opengl and glow mod for rendering.
+//!
+//! In worker mode (see web-templates/worker.js) the game loop runs in a
+//! web worker drawing to an OffscreenCanvas passed by set_offscreen_canvas,
+//! the page posts input to it as plain objects read by WebInput. Audio,
+//! text input and clipboard calls are forwarded to the page by host.js,
+//! sync saves need localStorage and fail in a worker, the async ones
+//! use IndexedDB and work.
+//!
+//! With the webgpu feature the page can call webgpu_init first, init_glpix
+//! then draws with WgpuPixel (see wgpu.rs) if it got an adapter, and makes
+//! the gl context if WgpuPixel fails on the canvas.
 use crate::event::{
-    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton::*, MouseEvent, MouseEventKind::*,
+    Event, GamepadAxis, GamepadButton, GamepadEvent, GamepadEventKind, KeyCode, KeyEvent,
+    KeyEventKind, KeyModifiers, MouseButton::*, MouseEvent, MouseEventKind::*, TouchEvent,
+    TouchEventKind,
 };
+#[cfg(feature = "webgpu")]
+use crate::render::adapter::wgpu::{take_wgpu_context, WgpuContext, WgpuPixel};
 use crate::render::{
     adapter::{
-        gl::pixel::GlPixel, 
-        Adapter, AdapterBase, PIXEL_SYM_HEIGHT, PIXEL_SYM_WIDTH,
+        gl::{pixel::GlPixel, shader::GlslProfile},
+        Adapter, AdapterBase, WindowSize, PIXEL_SYM_HEIGHT, PIXEL_SYM_WIDTH,
     },
     buffer::Buffer,
     sprite::Sprites,
 };
 use log::info;
 use std::any::Any;
+use std::cell::RefCell;
 use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+// refer to rust-pixel/web-templates/host.js
+#[wasm_bindgen(raw_module = "/host.js")]
+extern "C" {
+    fn js_text_input(on: bool);
+    fn js_clipboard_set(s: &str);
+    fn js_fullscreen(on: bool);
+    fn js_pointer_lock(on: bool);
+}
+
+thread_local! {
+    // canvas of worker mode, taken by WebAdapter::init
+    static OFFSCREEN: RefCell<Option<web_sys::OffscreenCanvas>> = const { RefCell::new(None) };
+}
+
+/// makes the next WebAdapter::init draw to an OffscreenCanvas instead of
+/// the canvas element of the page, for games running in a web worker
+pub fn set_offscreen_canvas(c: web_sys::OffscreenCanvas) {
+    OFFSCREEN.with(|o| *o.borrow_mut() = Some(c));
+}
 
 pub struct WebAdapter {
     pub base: AdapterBase,
+    // symbol texture kept to rebuild gl_pixel on resize_cells
+    tex: Option<(i32, i32, Vec<u8>)>,
+    // set by init if webgpu_init got an adapter, used until init_glpix
+    #[cfg(feature = "webgpu")]
+    wgpu_ctx: Option<WgpuContext>,
+    #[cfg(feature = "webgpu")]
+    wgpu_pixel: Option<WgpuPixel>,
+    // canvas of worker mode, for webgpu or the webgl fallback
+    #[cfg(feature = "webgpu")]
+    offscreen: Option<web_sys::OffscreenCanvas>,
 }
 
 impl WebAdapter {
     pub fn new(pre: &str, gn: &str, project_path: &str) -> Self {
         Self {
             base: AdapterBase::new(pre, gn, project_path),
+            tex: None,
+            #[cfg(feature = "webgpu")]
+            wgpu_ctx: None,
+            #[cfg(feature = "webgpu")]
+            wgpu_pixel: None,
+            #[cfg(feature = "webgpu")]
+            offscreen: None,
         }
     }
 
+    /// a new size in cells asked by the page, rebuilds gl_pixel for the
+    /// new canvas size and reports the size to the panel, returns the
+    /// canvas size in pixels
+    pub fn resize_cells(&mut self, w: u16, h: u16) -> (u32, u32) {
+        self.set_size(w, h).set_pixel_size();
+        if let Some((tw, th, tex)) = self.tex.take() {
+            self.init_glpix(tw, th, &tex);
+        }
+        let bs = &mut self.base;
+        bs.resized = Some(WindowSize {
+            width: w,
+            height: h,
+            pixel_w: bs.pixel_w,
+            pixel_h: bs.pixel_h,
+        });
+        (bs.pixel_w, bs.pixel_h)
+    }
+
     pub fn init_glpix(&mut self, w: i32, h: i32, tex: &[u8]) {
+        self.tex = Some((w, h, tex.to_vec()));
+        #[cfg(feature = "webgpu")]
+        {
+            let ctx = self
+                .wgpu_ctx
+                .take()
+                .or_else(|| self.wgpu_pixel.take().map(|p| p.into_context()));
+            if let Some(ctx) = ctx {
+                let bs = &self.base;
+                let oc = self.offscreen.clone();
+                match WgpuPixel::new(ctx, oc, bs.pixel_w, bs.pixel_h, w as u32, h as u32, tex) {
+                    Ok(p) => {
+                        self.wgpu_pixel = Some(p);
+                        return;
+                    }
+                    Err(e) => {
+                        info!("webgpu init failed, use webgl...{}", e);
+                        self.init_gl(self.offscreen.clone());
+                    }
+                }
+            }
+        }
+        let glsl_profile = GlslProfile::detect(self.base.gl.as_ref().unwrap());
         self.base.gl_pixel = Some(GlPixel::new(
             self.base.gl.as_ref().unwrap(),
-            "#version 300 es",
+            glsl_profile.header(),
             self.base.pixel_w as i32,
             self.base.pixel_h as i32,
             w as i32,
@@ -41,6 +137,47 @@ impl WebAdapter {
             tex,
         ));
     }
+
+    // webgl2 or webgl1 context on the canvas, the OffscreenCanvas in worker mode
+    fn init_gl(&mut self, offscreen: Option<web_sys::OffscreenCanvas>) {
+        let canvas = if offscreen.is_some() {
+            None
+        } else {
+            Some(
+                web_sys::window()
+                    .unwrap()
+                    .document()
+                    .unwrap()
+                    .get_element_by_id("canvas")
+                    .unwrap()
+                    .dyn_into::<web_sys::HtmlCanvasElement>()
+                    .unwrap(),
+            )
+        };
+        let get_context = |kind: &str| match (&offscreen, &canvas) {
+            (Some(oc), _) => oc.get_context(kind).ok().flatten(),
+            (None, Some(c)) => c.get_context(kind).ok().flatten(),
+            _ => None,
+        };
+        // fall back to webgl1 on browsers without webgl2
+        let gl = match get_context("webgl2") {
+            Some(ctx) => glow::Context::from_webgl2_context(
+                ctx.dyn_into::<web_sys::WebGl2RenderingContext>().unwrap(),
+            ),
+            None => {
+                info!("webgl2 not available, use webgl1...");
+                let ctx = get_context("webgl")
+                    .unwrap()
+                    .dyn_into::<web_sys::WebGlRenderingContext>()
+                    .unwrap();
+                glow::Context::from_webgl1_context(ctx)
+            }
+        };
+
+        // Store the OpenGL context
+        self.base.gl = Some(gl);
+        info!("Window & gl init ok...");
+    }
 }
 
 impl Adapter for WebAdapter {
@@ -51,26 +188,16 @@ impl Adapter for WebAdapter {
             .set_pixel_size()
             .set_title(s);
 
-        use wasm_bindgen::JsCast;
-        let canvas = web_sys::window()
-            .unwrap()
-            .document()
-            .unwrap()
-            .get_element_by_id("canvas")
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap();
-        let webgl2_context = canvas
-            .get_context("webgl2")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<web_sys::WebGl2RenderingContext>()
-            .unwrap();
-        let gl = glow::Context::from_webgl2_context(webgl2_context);
-
-        // Store the OpenGL context
-        self.base.gl = Some(gl);
-        info!("Window & gl init ok...");
+        let offscreen = OFFSCREEN.with(|o| o.borrow_mut().take());
+        // webgpu gets the canvas in init_glpix, gl only if that fails
+        #[cfg(feature = "webgpu")]
+        if let Some(ctx) = take_wgpu_context() {
+            self.wgpu_ctx = Some(ctx);
+            self.offscreen = offscreen;
+            info!("Window & webgpu init ok...");
+            return;
+        }
+        self.init_gl(offscreen);
     }
 
     fn get_base(&mut self) -> &mut AdapterBase {
@@ -98,6 +225,16 @@ impl Adapter for WebAdapter {
         pixel_sprites: &mut Vec<Sprites>,
         stage: u32,
     ) -> Result<(), String> {
+        #[cfg(feature = "webgpu")]
+        if self.wgpu_pixel.is_some() {
+            let rbuf = self.draw_all_to_render_buffer(current_buffer, _p, pixel_sprites, stage);
+            let bs = &self.base;
+            let (rx, ry, viewport) = (bs.ratio_x, bs.ratio_y, bs.screen_viewport());
+            if let Some(p) = &mut self.wgpu_pixel {
+                p.render_rbuf(&rbuf, rx, ry, viewport)?;
+            }
+            return Ok(());
+        }
         self.draw_all_graph(current_buffer, _p, pixel_sprites, stage);
         Ok(())
     }
@@ -118,6 +255,38 @@ impl Adapter for WebAdapter {
         Ok((0, 0))
     }
 
+    // the page reports the change with fullscreen_event
+    fn set_fullscreen(&mut self, on: bool) {
+        js_fullscreen(on);
+    }
+
+    // the page reports the change with pointer_lock_event
+    fn set_pointer_lock(&mut self, on: bool) {
+        js_pointer_lock(on);
+    }
+
+    // a worker has no document, the page keeps its title
+    fn set_window_title(&mut self, t: &str) -> Result<(), String> {
+        self.base.title = t.to_string();
+        if let Some(d) = web_sys::window().and_then(|w| w.document()) {
+            d.set_title(t);
+        }
+        Ok(())
+    }
+
+    fn set_text_input(&mut self, on: bool) {
+        self.base.text_input = on;
+        // focuses a hidden textarea in host.js, which receives ime composition
+        js_text_input(on);
+    }
+
+    // reading the clipboard is async on web, pasted text comes with
+    // the paste event, see paste_event in pixel_macro
+    fn clipboard_set(&mut self, s: &str) {
+        self.base.clipboard = s.to_string();
+        js_clipboard_set(s);
+    }
+
     fn as_any(&mut self) -> &mut dyn Any {
         self
     }
@@ -127,65 +296,223 @@ macro_rules! web_event {
     ($ek:expr, $ei:expr, $($btn:expr)* ) => {
         Some(MouseEvent {
             kind: $ek$(($btn))*,
-            column: $ei.5 as u16,
-            row: $ei.6 as u16,
+            column: $ei.x as u16,
+            row: $ei.y as u16,
             modifiers: KeyModifiers::NONE,
         })
     };
 }
 
+/// Convert gamepad changes polled by index.js to RustPixel event,
+/// t: 0 connected, 1 disconnected, 2 button down, 3 button up, 4 axis
+/// code is the button or axis index of the web standard gamepad layout
+pub fn gamepad_event_from_web(id: u32, t: u8, code: u8, value: f32) -> Option<Event> {
+    use GamepadButton::*;
+    let button = || {
+        Some(match code {
+            0 => A,
+            1 => B,
+            2 => X,
+            3 => Y,
+            4 => LeftShoulder,
+            5 => RightShoulder,
+            8 => Back,
+            9 => Start,
+            10 => LeftStick,
+            11 => RightStick,
+            12 => DPadUp,
+            13 => DPadDown,
+            14 => DPadLeft,
+            15 => DPadRight,
+            16 => Guide,
+            _ => return None,
+        })
+    };
+    let kind = match t {
+        0 => GamepadEventKind::Connected,
+        1 => GamepadEventKind::Disconnected,
+        2 | 3 => {
+            // triggers are buttons 6 and 7 on web, report them as axes
+            if code == 6 || code == 7 {
+                let a = if code == 6 { GamepadAxis::TriggerLeft } else { GamepadAxis::TriggerRight };
+                let v = if t == 2 { value.max(0.0) } else { 0.0 };
+                GamepadEventKind::Axis(a, (v * 32767.0) as i16)
+            } else if t == 2 {
+                GamepadEventKind::ButtonDown(button()?)
+            } else {
+                GamepadEventKind::ButtonUp(button()?)
+            }
+        }
+        4 => {
+            let a = match code {
+                0 => GamepadAxis::LeftX,
+                1 => GamepadAxis::LeftY,
+                2 => GamepadAxis::RightX,
+                3 => GamepadAxis::RightY,
+                _ => return None,
+            };
+            GamepadEventKind::Axis(a, (value.clamp(-1.0, 1.0) * 32767.0) as i16)
+        }
+        _ => return None,
+    };
+    Some(Event::Gamepad(GamepadEvent { id, kind }))
+}
+
+/// Convert text sent by index.js to RustPixel event,
+/// t: 0 committed text, 1 ime composition in progress
+pub fn text_event_from_web(t: u8, s: &str) -> Option<Event> {
+    match t {
+        0 if !s.is_empty() => Some(Event::Text(s.to_string())),
+        1 => Some(Event::TextEditing(s.to_string())),
+        _ => None,
+    }
+}
+
+/// Convert touch points sent by index.js to RustPixel event,
+/// t: 0 start, 1 move, 2 end, 3 cancel, (x, y) is the client position
+pub fn touch_event_from_web(t: u8, id: u32, x: f32, y: f32, ratiox: f32, ratioy: f32) -> Option<Event> {
+    let kind = match t {
+        0 => TouchEventKind::Start,
+        1 => TouchEventKind::Move,
+        2 => TouchEventKind::End,
+        3 => TouchEventKind::Cancel,
+        _ => return None,
+    };
+    let column = (x / (PIXEL_SYM_WIDTH / ratiox)) as u16;
+    let row = (y / (PIXEL_SYM_HEIGHT / ratioy)) as u16;
+    Some(Event::Touch(TouchEvent {
+        id: id as u64,
+        kind,
+        column: column.saturating_sub(1),
+        row: row.saturating_sub(1),
+    }))
+}
+
+/// Key and mouse fields of a web event. Read by name, so it is built
+/// from a KeyboardEvent, MouseEvent or WheelEvent as well as from the
+/// plain object of the same fields worker.js gets from the page
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WebInput {
+    pub key_code: u32,
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+    pub repeat: bool,
+    pub button: i16,
+    pub buttons: u16,
+    pub x: i32,
+    pub y: i32,
+    /// motion since the last move, the position stays put while locked
+    pub movement_x: i32,
+    pub movement_y: i32,
+    pub delta_y: f64,
+}
+
+impl WebInput {
+    pub fn from_js(o: &JsValue) -> Self {
+        let get =
+            |k: &str| js_sys::Reflect::get(o, &JsValue::from_str(k)).unwrap_or(JsValue::UNDEFINED);
+        let num = |k: &str| get(k).as_f64().unwrap_or(0.0);
+        let flag = |k: &str| get(k).as_bool().unwrap_or(false);
+        Self {
+            key_code: num("keyCode") as u32,
+            shift: flag("shiftKey"),
+            ctrl: flag("ctrlKey"),
+            alt: flag("altKey"),
+            meta: flag("metaKey"),
+            repeat: flag("repeat"),
+            button: num("button") as i16,
+            buttons: num("buttons") as u16,
+            x: num("x") as i32,
+            y: num("y") as i32,
+            movement_x: num("movementX") as i32,
+            movement_y: num("movementY") as i32,
+            delta_y: num("deltaY"),
+        }
+    }
+}
+
 /// Convert web I/O events to RustPixel event, for the sake of unified event processing
 /// For keyboard and mouse event, please refer to the handle_input method in game/unblock/model.rs
-pub fn input_events_from_web(t: u8, e: web_sys::Event, ratiox: f32, ratioy: f32) -> Option<Event> {
+/// Mouse positions of a scaled canvas are mapped back by base.window_to_canvas
+pub fn input_events_from_web(t: u8, e: web_sys::Event, base: &mut AdapterBase) -> Option<Event> {
+    input_from_web(t, &WebInput::from_js(e.as_ref()), base)
+}
+
+/// Convert WebInput to RustPixel event, t: 0 keypress, 5 keydown,
+/// 1 mouse up, 2 mouse down, 3 mouse move, 4 wheel. Motion of a locked
+/// pointer goes to base.take_pointer_delta
+pub fn input_from_web(t: u8, wi: &WebInput, base: &mut AdapterBase) -> Option<Event> {
+    if t == 3 {
+        base.pointer_moved(wi.movement_x, wi.movement_y);
+    }
+    let (ratiox, ratioy) = (base.ratio_x, base.ratio_y);
     let sym_width = PIXEL_SYM_WIDTH as f32;
     let sym_height = PIXEL_SYM_HEIGHT as f32;
-    let mut mcte: Option<MouseEvent> = None;
-
-    if let Some(key_e) = wasm_bindgen::JsCast::dyn_ref::<web_sys::KeyboardEvent>(&e) {
-        assert!(t == 0);
-        let kcc = (key_e.key_code(), key_e.char_code());
-        match kcc.0 {
-            32 | 48..=57 | 97..=122 => {
-                let cte = KeyEvent::new(
-                    KeyCode::Char(char::from_u32(kcc.0).unwrap()),
-                    KeyModifiers::NONE,
-                );
-                return Some(Event::Key(cte));
-            }
-            _ => {
-                return None;
-            }
-        }
-    }
 
-    if let Some(mouse_e) = wasm_bindgen::JsCast::dyn_ref::<web_sys::MouseEvent>(&e) {
-        let medat = (
-            mouse_e.buttons(),
-            mouse_e.screen_x(),
-            mouse_e.screen_y(),
-            mouse_e.client_x(),
-            mouse_e.client_y(),
-            mouse_e.x(),
-            mouse_e.y(),
-        );
-        match t {
-            1 => {
-                mcte = web_event!(Up, medat, Left);
-            }
-            2 => {
-                mcte = web_event!(Down, medat, Left);
-            }
-            3 => {
-                if medat.0 == 1 {
-                    mcte = web_event!(Drag, medat, Left);
-                } else {
-                    mcte = web_event!(Moved, medat,);
-                }
-            }
-            _ => {}
+    if t == 0 || t == 5 {
+        let mut mods = KeyModifiers::NONE;
+        mods.set(KeyModifiers::SHIFT, wi.shift);
+        mods.set(KeyModifiers::CONTROL, wi.ctrl);
+        mods.set(KeyModifiers::ALT, wi.alt);
+        mods.set(KeyModifiers::SUPER, wi.meta);
+        let kind = if wi.repeat {
+            KeyEventKind::Repeat
+        } else {
+            KeyEventKind::Press
+        };
+        // t 5 is keydown, only used for editing keys and ctrl/alt chords
+        // which are not sent by keypress
+        if t == 5 {
+            let kc = match wi.key_code {
+                8 => KeyCode::Backspace,
+                9 => KeyCode::Tab,
+                13 => KeyCode::Enter,
+                27 => KeyCode::Esc,
+                35 => KeyCode::End,
+                36 => KeyCode::Home,
+                37 => KeyCode::Left,
+                38 => KeyCode::Up,
+                39 => KeyCode::Right,
+                40 => KeyCode::Down,
+                46 => KeyCode::Delete,
+                c @ 65..=90 if wi.ctrl || wi.alt => KeyCode::Char(char::from_u32(c + 32).unwrap()),
+                _ => return None,
+            };
+            return Some(Event::Key(KeyEvent::new_with_kind(kc, mods, kind)));
         }
+        return match wi.key_code {
+            c @ (32 | 48..=57 | 97..=122) => Some(Event::Key(KeyEvent::new_with_kind(
+                KeyCode::Char(char::from_u32(c).unwrap()),
+                mods,
+                kind,
+            ))),
+            _ => None,
+        };
     }
+
+    // button of up/down: 0 left, 1 middle, 2 right
+    let btn = match wi.button {
+        1 => Middle,
+        2 => Right,
+        _ => Left,
+    };
+    let mcte = match t {
+        1 => web_event!(Up, wi, btn),
+        2 => web_event!(Down, wi, btn),
+        // buttons bits: 1 left, 2 right, 4 middle
+        3 if wi.buttons & 1 != 0 => web_event!(Drag, wi, Left),
+        3 if wi.buttons & 2 != 0 => web_event!(Drag, wi, Right),
+        3 if wi.buttons & 4 != 0 => web_event!(Drag, wi, Middle),
+        3 => web_event!(Moved, wi,),
+        4 if wi.delta_y < 0.0 => web_event!(ScrollUp, wi,),
+        4 if wi.delta_y > 0.0 => web_event!(ScrollDown, wi,),
+        _ => None,
+    };
     if let Some(mut mc) = mcte {
+        let (x, y) = base.window_to_canvas(mc.column as i32, mc.row as i32);
+        (mc.column, mc.row) = (x.max(0) as u16, y.max(0) as u16);
         mc.column /= (sym_width / ratiox) as u16;
         mc.row /= (sym_height / ratioy) as u16;
         if mc.column >= 1 {
@@ -0,0 +1,517 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! WebGPU render path of the web adapter, built with the webgpu feature.
+//! webgpu_init picks WebGPU when the browser offers an adapter, the page
+//! calls it before PixelGame.new, otherwise WebAdapter keeps using WebGL2
+//! or WebGL1 through glow.
+//!
+//! WgpuPixel draws the render buffer straight to the canvas with the same
+//! instanced symbols as GlRenderSymbols, it binds the canvas only after
+//! the adapter and device are there, a failure falls back to WebGL.
+//! Transitions, the indexed palette, ttf text and the panel view transform
+//! are still glow only.
+use crate::render::adapter::{
+    gl::transform::GlTransform, RenderCell, PIXEL_SYM_HEIGHT, PIXEL_SYM_WIDTH,
+};
+use log::info;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use wgpu::util::DeviceExt;
+
+const SYMBOLS_WGSL: &str = r#"
+struct Transform {
+    tw: vec4<f32>,
+    th: vec4<f32>,
+    color_filter: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> tf: Transform;
+@group(0) @binding(1) var source: texture_2d<f32>;
+@group(0) @binding(2) var smp: sampler;
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    @location(0) vertex: vec2<f32>,
+    @location(1) a1: vec4<f32>,
+    @location(2) a2: vec4<f32>,
+    @location(3) a3: vec4<f32>,
+    @location(4) color: vec4<f32>,
+) -> VsOut {
+    var o: VsOut;
+    o.uv = a1.zw + vertex * a2.xy;
+    let p = ((vertex - a1.xy) * mat2x2<f32>(a2.zw, a3.xy) + a3.zw)
+        * mat2x2<f32>(tf.tw.xy, tf.th.xy) + vec2<f32>(tf.tw.z, tf.th.z);
+    let t = p / vec2<f32>(tf.tw.w, tf.th.w) * 2.0;
+    o.pos = vec4<f32>(t - vec2<f32>(1.0, 1.0), 0.0, 1.0);
+    o.color = color * tf.color_filter;
+    return o;
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    return textureSample(source, smp, in.uv) * in.color;
+}
+"#;
+
+// origin, uv, transform and color, 16 floats per instance like GlRenderSymbols
+const INSTANCE_ATTRS: [wgpu::VertexAttribute; 4] =
+    wgpu::vertex_attr_array![1 => Float32x4, 2 => Float32x4, 3 => Float32x4, 4 => Float32x4];
+const INSTANCE_SIZE: u64 = 64;
+
+/// adapter, device and queue got by webgpu_init, the surface is made on
+/// the canvas by WgpuPixel::new, so webgl can still take the canvas if
+/// webgpu fails before that
+pub struct WgpuContext {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub surface: Option<wgpu::Surface<'static>>,
+}
+
+thread_local! {
+    // taken by WebAdapter::init, which then leaves the gl context to init_glpix
+    static WGPU: RefCell<Option<WgpuContext>> = const { RefCell::new(None) };
+}
+
+pub fn take_wgpu_context() -> Option<WgpuContext> {
+    WGPU.with(|w| w.borrow_mut().take())
+}
+
+/// asks the browser for a WebGPU adapter and device, the canvas is not
+/// touched yet, resolves to true if the next WebAdapter::init tries
+/// WebGPU, false to stay on WebGL
+#[wasm_bindgen]
+pub fn webgpu_init() -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        let ctx = request_context().await;
+        if let Err(e) = &ctx {
+            info!("webgpu not available, use webgl...{}", e);
+        }
+        let ok = ctx.is_ok();
+        WGPU.with(|w| *w.borrow_mut() = ctx.ok());
+        Ok(JsValue::from_bool(ok))
+    })
+}
+
+async fn request_context() -> Result<WgpuContext, String> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::BROWSER_WEBGPU,
+        ..Default::default()
+    });
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        })
+        .await
+        .ok_or("no webgpu adapter")?;
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                    .using_resolution(adapter.limits()),
+                memory_hints: wgpu::MemoryHints::default(),
+            },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("webgpu adapter...{:?}", adapter.get_info());
+    Ok(WgpuContext {
+        instance,
+        adapter,
+        device,
+        queue,
+        surface: None,
+    })
+}
+
+// getContext("webgpu") on the OffscreenCanvas of worker mode or on the
+// canvas element of the page
+fn create_surface(
+    instance: &wgpu::Instance,
+    offscreen: Option<web_sys::OffscreenCanvas>,
+) -> Result<wgpu::Surface<'static>, String> {
+    let target = match offscreen {
+        Some(oc) => wgpu::SurfaceTarget::OffscreenCanvas(oc),
+        None => {
+            let canvas = web_sys::window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.get_element_by_id("canvas"))
+                .ok_or("no canvas element")?
+                .dyn_into::<web_sys::HtmlCanvasElement>()
+                .map_err(|_| "canvas is not a HtmlCanvasElement")?;
+            wgpu::SurfaceTarget::Canvas(canvas)
+        }
+    };
+    instance.create_surface(target).map_err(|e| e.to_string())
+}
+
+pub struct WgpuPixel {
+    ctx: WgpuContext,
+    surface: wgpu::Surface<'static>,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    quad: wgpu::Buffer,
+    instances: wgpu::Buffer,
+    instance_capacity: u64,
+    instance_data: Vec<f32>,
+    // uv left, top, width, height of the 32 x 32 symbols of the atlas
+    symbols: Vec<[f32; 4]>,
+
+    pub canvas_width: u32,
+    pub canvas_height: u32,
+}
+
+impl WgpuPixel {
+    pub fn new(
+        mut ctx: WgpuContext,
+        offscreen: Option<web_sys::OffscreenCanvas>,
+        canvas_width: u32,
+        canvas_height: u32,
+        texw: u32,
+        texh: u32,
+        texdata: &[u8],
+    ) -> Result<Self, String> {
+        let surface = match ctx.surface.take() {
+            Some(s) => s,
+            None => create_surface(&ctx.instance, offscreen)?,
+        };
+        let device = &ctx.device;
+        let config = surface
+            .get_default_config(&ctx.adapter, canvas_width, canvas_height)
+            .ok_or("surface not supported by the webgpu adapter")?;
+        surface.configure(device, &config);
+
+        // symbols atlas
+        let size = wgpu::Extent3d {
+            width: texw,
+            height: texh,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("symbols"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        ctx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            texdata,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * texw),
+                rows_per_image: Some(texh),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // nearest keeps pixel art crisp
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // same layout as the transform uniform block of the gl shaders,
+        // y flipped so symbols are placed in pixels with y down
+        let t = GlTransform::new_with_values(1.0, 0.0, 0.0, 0.0, -1.0, canvas_height as f32);
+        let ubo: [f32; 12] = [
+            t.m00,
+            t.m10,
+            t.m20,
+            canvas_width as f32,
+            t.m01,
+            t.m11,
+            t.m21,
+            canvas_height as f32,
+            1.0,
+            1.0,
+            1.0,
+            1.0,
+        ];
+        let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("transform"),
+            contents: unsafe { ubo.align_to::<u8>().1 },
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("symbols"),
+            source: wgpu::ShaderSource::Wgsl(SYMBOLS_WGSL.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("symbols"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: 8,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: INSTANCE_SIZE,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &INSTANCE_ATTRS,
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        // the gl quad is a triangle fan, reordered for a strip
+        let quad_vertices: [f32; 8] = [0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0];
+        let quad = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("quad"),
+            contents: unsafe { quad_vertices.align_to::<u8>().1 },
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let instance_capacity = 1024;
+        let instances = Self::instance_buffer(device, instance_capacity);
+
+        let mut symbols = vec![];
+        for i in 0..32 {
+            for j in 0..32 {
+                symbols.push([
+                    j as f32 * (PIXEL_SYM_WIDTH + 1.0) / texw as f32,
+                    i as f32 * (PIXEL_SYM_HEIGHT + 1.0) / texh as f32,
+                    PIXEL_SYM_WIDTH / texw as f32,
+                    PIXEL_SYM_HEIGHT / texh as f32,
+                ]);
+            }
+        }
+
+        Ok(Self {
+            ctx,
+            surface,
+            pipeline,
+            bind_group,
+            quad,
+            instances,
+            instance_capacity,
+            instance_data: vec![],
+            symbols,
+            canvas_width,
+            canvas_height,
+        })
+    }
+
+    fn instance_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("instances"),
+            size: capacity * INSTANCE_SIZE,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// gives the surface, adapter and device back, to build a new
+    /// WgpuPixel for another canvas size
+    pub fn into_context(self) -> WgpuContext {
+        WgpuContext {
+            surface: Some(self.surface),
+            ..self.ctx
+        }
+    }
+
+    fn push_symbol(&mut self, sym: usize, t: &GlTransform, c: (f32, f32, f32, f32)) {
+        let [u, v, uw, vh] = self.symbols[sym];
+        self.instance_data.extend_from_slice(&[
+            0.5,
+            0.5,
+            u,
+            v,
+            uw,
+            vh,
+            t.m00 * PIXEL_SYM_WIDTH,
+            t.m10 * PIXEL_SYM_HEIGHT,
+            t.m01 * PIXEL_SYM_WIDTH,
+            t.m11 * PIXEL_SYM_HEIGHT,
+            t.m20,
+            t.m21,
+            c.0,
+            c.1,
+            c.2,
+            c.3,
+        ]);
+    }
+
+    /// draws rbuf to the canvas in one instanced draw, viewport is x, y,
+    /// w, h of the canvas scaled into the window
+    pub fn render_rbuf(
+        &mut self,
+        rbuf: &[RenderCell],
+        ratio_x: f32,
+        ratio_y: f32,
+        viewport: Option<[i32; 4]>,
+    ) -> Result<(), String> {
+        self.instance_data.clear();
+        for r in rbuf {
+            let mut transform = r.view;
+            transform.translate(r.x + r.cx - PIXEL_SYM_WIDTH, r.y + r.cy - PIXEL_SYM_HEIGHT);
+            if r.angle != 0.0 {
+                transform.rotate(r.angle);
+            }
+            if r.sx != 1.0 || r.sy != 1.0 {
+                transform.scale(r.sx, r.sy);
+            }
+            transform.translate(
+                -r.cx + PIXEL_SYM_WIDTH / 2.0,
+                -r.cy + PIXEL_SYM_HEIGHT / 2.0,
+            );
+            transform.scale(1.0 / ratio_x, 1.0 / ratio_y);
+            if let Some(b) = r.bcolor {
+                self.push_symbol(320, &transform, b);
+            }
+            self.push_symbol(r.texsym, &transform, r.fcolor);
+        }
+
+        let count = (self.instance_data.len() / 16) as u64;
+        if count > self.instance_capacity {
+            self.instance_capacity = count.next_power_of_two();
+            self.instances = Self::instance_buffer(&self.ctx.device, self.instance_capacity);
+        }
+        if count > 0 {
+            self.ctx.queue.write_buffer(&self.instances, 0, unsafe {
+                self.instance_data.align_to::<u8>().1
+            });
+        }
+
+        let frame = self
+            .surface
+            .get_current_texture()
+            .map_err(|e| e.to_string())?;
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if let Some([x, y, w, h]) = viewport {
+                pass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0.0, 1.0);
+            }
+            if count > 0 {
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.set_vertex_buffer(0, self.quad.slice(..));
+                pass.set_vertex_buffer(1, self.instances.slice(..count * INSTANCE_SIZE));
+                pass.draw(0..4, 0..count as u32);
+            }
+        }
+        self.ctx.queue.submit(Some(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! GridBoard keeps a width x height board of cells for chess, 2048 or
+//! minesweeper like games, with a cursor, a selected cell and highlights.
+//!
+//! Each board cell is drawn as cell_w x cell_h cells of a panel sprite
+//! from origin x, y. update takes the input events of the frame: arrows
+//! move the cursor, Enter or Space activate the cell under it, the mouse
+//! moves the cursor over the cells, a left click activates and a right
+//! click marks a cell, e.g. flags a mine. The events come back from update
+//! and are also emitted as BOARD_EVENT with the BoardEvent as data.
+//! Selecting a cell is up to the game, e.g. pick a piece on the first
+//! activation and move it on the second.
+
+use crate::event::{event_emit_data, Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
+use crate::render::{buffer::Buffer, sprite::Sprite, style::Theme};
+use crate::util::Rect;
+use serde::{Deserialize, Serialize};
+
+/// emitted with a BoardEvent as data
+pub const BOARD_EVENT: &str = "Board.Event";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoardEventKind {
+    /// the cursor moved to the cell
+    Moved,
+    /// Enter, Space or a left click
+    Activated,
+    /// a right click
+    Marked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardEvent {
+    pub kind: BoardEventKind,
+    pub x: u16,
+    pub y: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct GridBoard<T> {
+    pub width: u16,
+    pub height: u16,
+    /// size of a board cell in sprite cells
+    pub cell_w: u16,
+    pub cell_h: u16,
+    /// position of the board in the sprite
+    pub x: u16,
+    pub y: u16,
+    cells: Vec<T>,
+    highlights: Vec<bool>,
+    pub cursor: (u16, u16),
+    pub show_cursor: bool,
+    pub selected: Option<(u16, u16)>,
+    /// the cursor wraps around the edges
+    pub wrap: bool,
+    // sprite position of the last draw, to match mouse positions
+    origin: (u16, u16),
+}
+
+impl<T: Clone + Default> GridBoard<T> {
+    pub fn new(width: u16, height: u16, cell_w: u16, cell_h: u16) -> Self {
+        let n = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            cell_w: cell_w.max(1),
+            cell_h: cell_h.max(1),
+            x: 0,
+            y: 0,
+            cells: vec![T::default(); n],
+            highlights: vec![false; n],
+            cursor: (0, 0),
+            show_cursor: true,
+            selected: None,
+            wrap: false,
+            origin: (0, 0),
+        }
+    }
+
+    /// sets every cell to the default value and clears the marks
+    pub fn reset(&mut self) {
+        self.cells.fill(T::default());
+        self.clear_highlights();
+        self.selected = None;
+    }
+}
+
+impl<T> GridBoard<T> {
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        self.in_bounds(x as i32, y as i32)
+            .then(|| y as usize * self.width as usize + x as usize)
+    }
+
+    pub fn get(&self, x: u16, y: u16) -> Option<&T> {
+        self.index(x, y).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, x: u16, y: u16) -> Option<&mut T> {
+        self.index(x, y).map(move |i| &mut self.cells[i])
+    }
+
+    pub fn set(&mut self, x: u16, y: u16, v: T) {
+        if let Some(i) = self.index(x, y) {
+            self.cells[i] = v;
+        }
+    }
+
+    /// cells row by row with their positions
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u16, &T)> {
+        let w = self.width as usize;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, c)| ((i % w) as u16, (i / w) as u16, c))
+    }
+
+    /// positions of the up to 8 cells around x, y, diagonals if diag
+    pub fn neighbors(&self, x: u16, y: u16, diag: bool) -> Vec<(u16, u16)> {
+        let mut v = vec![];
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx == 0 && dy == 0) || (!diag && dx != 0 && dy != 0) {
+                    continue;
+                }
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if self.in_bounds(nx, ny) {
+                    v.push((nx as u16, ny as u16));
+                }
+            }
+        }
+        v
+    }
+
+    pub fn highlight(&mut self, x: u16, y: u16, on: bool) {
+        if let Some(i) = self.index(x, y) {
+            self.highlights[i] = on;
+        }
+    }
+
+    pub fn is_highlighted(&self, x: u16, y: u16) -> bool {
+        self.index(x, y).is_some_and(|i| self.highlights[i])
+    }
+
+    pub fn clear_highlights(&mut self) {
+        self.highlights.fill(false);
+    }
+
+    pub fn select(&mut self, x: u16, y: u16) {
+        if self.index(x, y).is_some() {
+            self.selected = Some((x, y));
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selected = None;
+    }
+
+    pub fn set_cursor(&mut self, x: u16, y: u16) {
+        if self.index(x, y).is_some() {
+            self.cursor = (x, y);
+        }
+    }
+
+    /// moves the cursor by dx, dy, stops at the edges unless wrap,
+    /// returns if it moved
+    pub fn move_cursor(&mut self, dx: i32, dy: i32) -> bool {
+        let (w, h) = (self.width as i32, self.height as i32);
+        if w == 0 || h == 0 {
+            return false;
+        }
+        let (mut x, mut y) = (self.cursor.0 as i32 + dx, self.cursor.1 as i32 + dy);
+        if self.wrap {
+            x = x.rem_euclid(w);
+            y = y.rem_euclid(h);
+        } else {
+            x = x.clamp(0, w - 1);
+            y = y.clamp(0, h - 1);
+        }
+        let c = (x as u16, y as u16);
+        let moved = c != self.cursor;
+        self.cursor = c;
+        moved
+    }
+
+    /// area of board cell x, y in the sprite
+    pub fn cell_area(&self, x: u16, y: u16) -> Rect {
+        Rect::new(
+            self.x + x * self.cell_w,
+            self.y + y * self.cell_h,
+            self.cell_w,
+            self.cell_h,
+        )
+    }
+
+    /// board cell under a screen cell, as matched against the last draw
+    pub fn cell_at(&self, col: u16, row: u16) -> Option<(u16, u16)> {
+        let x = col.checked_sub(self.origin.0 + self.x)? / self.cell_w;
+        let y = row.checked_sub(self.origin.1 + self.y)? / self.cell_h;
+        self.index(x, y).map(|_| (x, y))
+    }
+
+    fn push(&self, out: &mut Vec<BoardEvent>, kind: BoardEventKind, (x, y): (u16, u16)) {
+        let ev = BoardEvent { kind, x, y };
+        event_emit_data(BOARD_EVENT, &ev);
+        out.push(ev);
+    }
+
+    /// processes input events of this frame, returns the board events
+    pub fn update(&mut self, events: &[Event]) -> Vec<BoardEvent> {
+        let mut out = vec![];
+        for e in events {
+            match e {
+                Event::Key(k) if k.kind != KeyEventKind::Release => {
+                    let d = match k.code {
+                        KeyCode::Left => (-1, 0),
+                        KeyCode::Right => (1, 0),
+                        KeyCode::Up => (0, -1),
+                        KeyCode::Down => (0, 1),
+                        KeyCode::Enter | KeyCode::Char(' ') => {
+                            self.push(&mut out, BoardEventKind::Activated, self.cursor);
+                            continue;
+                        }
+                        _ => continue,
+                    };
+                    if self.move_cursor(d.0, d.1) {
+                        self.push(&mut out, BoardEventKind::Moved, self.cursor);
+                    }
+                }
+                Event::Mouse(m) => {
+                    let c = match self.cell_at(m.column, m.row) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    let kind = match m.kind {
+                        MouseEventKind::Down(MouseButton::Left) => BoardEventKind::Activated,
+                        MouseEventKind::Down(MouseButton::Right) => BoardEventKind::Marked,
+                        MouseEventKind::Moved | MouseEventKind::Drag(_) => BoardEventKind::Moved,
+                        _ => continue,
+                    };
+                    if c != self.cursor {
+                        self.cursor = c;
+                        self.push(&mut out, BoardEventKind::Moved, c);
+                    }
+                    if kind != BoardEventKind::Moved {
+                        self.push(&mut out, kind, c);
+                    }
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+
+    /// draws every cell with f(buf, area, cell), then marks the
+    /// highlights with ui.accent, the selected cell with ui.title and
+    /// the cursor with ui.selected backgrounds
+    pub fn draw<F>(&mut self, sp: &mut Sprite, theme: &Theme, mut f: F)
+    where
+        F: FnMut(&mut Buffer, Rect, &T),
+    {
+        let a = sp.content.area;
+        self.origin = (a.x, a.y);
+        let bg = |key: &str| theme.style("ui.fg", key);
+        for (x, y, c) in self.iter() {
+            let r = self.cell_area(x, y);
+            let r = Rect::new(a.x + r.x, a.y + r.y, r.width, r.height).intersection(a);
+            if r.area() == 0 {
+                continue;
+            }
+            f(&mut sp.content, r, c);
+            if self.show_cursor && (x, y) == self.cursor {
+                sp.content.set_style(r, bg("ui.selected"));
+            } else if self.selected == Some((x, y)) {
+                sp.content.set_style(r, bg("ui.title"));
+            } else if self.is_highlighted(x, y) {
+                sp.content.set_style(r, bg("ui.accent"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyEvent, KeyModifiers, MouseEvent};
+
+    fn key(c: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(c, KeyModifiers::NONE))
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn board_cursor_and_events() {
+        let mut b: GridBoard<u8> = GridBoard::new(4, 3, 3, 2);
+        b.set(3, 2, 9);
+        assert_eq!(b.get(3, 2), Some(&9));
+        assert_eq!(b.get(4, 0), None);
+        assert_eq!(b.neighbors(0, 0, true), vec![(1, 0), (0, 1), (1, 1)]);
+        assert_eq!(b.neighbors(1, 1, false).len(), 4);
+
+        let ev = b.update(&[key(KeyCode::Left), key(KeyCode::Right), key(KeyCode::Enter)]);
+        assert_eq!(ev.len(), 2);
+        assert_eq!(ev[1].kind, BoardEventKind::Activated);
+        assert_eq!((ev[1].x, ev[1].y), (1, 0));
+        b.wrap = true;
+        b.move_cursor(0, -1);
+        assert_eq!(b.cursor, (1, 2));
+
+        // the board is drawn at 2, 1 of a sprite at 10, 5
+        b.x = 2;
+        b.y = 1;
+        b.highlight(0, 0, true);
+        let mut sp = Sprite::new(10, 5, 20, 10);
+        let theme = Theme::dark();
+        b.draw(&mut sp, &theme, |buf, a, v| {
+            buf.set_string(a.x, a.y, v.to_string(), Default::default());
+        });
+        assert_eq!(sp.content.get(21, 10).symbol, "9");
+        assert_eq!(sp.content.get(12, 6).bg, theme.get("ui.accent"));
+        assert_eq!(sp.content.get(15, 10).bg, theme.get("ui.selected"));
+
+        assert_eq!(b.cell_at(11, 6), None);
+        assert_eq!(b.cell_at(23, 11), Some((3, 2)));
+        let ev = b.update(&[
+            mouse(MouseEventKind::Moved, 17, 8),
+            mouse(MouseEventKind::Down(MouseButton::Right), 17, 8),
+        ]);
+        assert_eq!(ev[0].kind, BoardEventKind::Moved);
+        assert_eq!(
+            (ev[1].kind, ev[1].x, ev[1].y),
+            (BoardEventKind::Marked, 1, 1)
+        );
+        assert_eq!(b.cursor, (1, 1));
+    }
+}
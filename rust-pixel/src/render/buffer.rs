@@ -16,6 +16,10 @@
 //! Display width is a bit tricky, very much relying on the terminal apps(currently the development
 //! work uses iterm2 in macOS). Moreover, bold and italics fonts are also supported in text mode.
 //!
+//! A wide char (CJK...) occupies two cells, the second one is a shadow cell
+//! with empty symbol, which is skipped by the renderer. Overwriting either half
+//! of a wide char clears the other half.
+//!
 //! In graphics mode,
 //! 256 unicode chars mark the index of a symbol in a SDL texture
 //! unicode: 0x2200 ~ 0x22FF
@@ -46,10 +50,27 @@
 //! Warning！tex here must be set to 0，because the offset in SDL_SYM_MAP is preset based on
 //! texture0(assets/c64l.png). May have display issues if set to another texture.
 //!
+//! With a blend mode set, writes composite their colors over the colors already in
+//! the cells instead of replacing them, e.g. a shadow or light overlay:
+//! ```
+//! # use rust_pixel::render::{buffer::Buffer, style::{BlendMode, Color, Style}};
+//! # use rust_pixel::util::Rect;
+//! # let area = Rect::new(0, 0, 10, 4);
+//! # let mut my_buffer = Buffer::empty(area);
+//! # let shade = Color::Rgba(40, 40, 60, 255);
+//! my_buffer.set_blend(Some(BlendMode::Multiply));
+//! my_buffer.set_style(area, Style::default().fg(shade).bg(shade));
+//! my_buffer.set_blend(None);
+//! ```
+//!
+//! draw_line, draw_circle, flood_fill and raycast work in cell coordinates and
+//! return the cells they touch, see geometry.rs.
+//!
 #[allow(unused_imports)]
 use crate::{
     render::cell::{cellsym, Cell},
-    render::style::{Color, Style},
+    render::style::{parse_markup, BlendMode, Color, Style},
+    render::textflow::TextFlow,
     util::Rect,
 };
 use log::info;
@@ -58,10 +79,17 @@ use std::cmp::min;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+/// lines, circles, flood fill and raycasts
+mod geometry;
+pub use geometry::RayHit;
+
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Buffer {
     pub area: Rect,
     pub content: Vec<Cell>,
+    /// composite writes over the current colors, None overwrites
+    #[serde(skip)]
+    pub blend: Option<BlendMode>,
 }
 
 impl Buffer {
@@ -76,7 +104,11 @@ impl Buffer {
         for _ in 0..size {
             content.push(cell.clone());
         }
-        Buffer { area, content }
+        Buffer {
+            area,
+            content,
+            blend: None,
+        }
     }
 
     pub fn with_lines<S>(lines: Vec<S>) -> Buffer
@@ -101,6 +133,17 @@ impl Buffer {
         buffer
     }
 
+    pub fn set_blend(&mut self, mode: Option<BlendMode>) {
+        self.blend = mode;
+    }
+
+    fn write_style(&mut self, i: usize, style: Style) {
+        match self.blend {
+            Some(m) => self.content[i].blend_style(style, m),
+            None => self.content[i].set_style(style),
+        };
+    }
+
     pub fn content(&self) -> &[Cell] {
         &self.content
     }
@@ -216,6 +259,43 @@ impl Buffer {
         );
     }
 
+    //relative pos in game sprite, string with style markup like "[red]HP[/red] 100"
+    //refers to render/style/markup.rs
+    pub fn set_rich_str<S>(&mut self, x: u16, y: u16, string: S, style: Style) -> (u16, u16)
+    where
+        S: AsRef<str>,
+    {
+        let mut px = x + self.area.x;
+        let py = y + self.area.y;
+        for (text, st) in parse_markup(string.as_ref(), style) {
+            px = self.set_stringn(px, py, text, usize::MAX, st, 0).0;
+        }
+        (px - self.area.x, y)
+    }
+
+    //relative pos in game sprite, lays out text by TextFlow, returns lines count
+    pub fn set_text_flow<S>(&mut self, x: u16, y: u16, string: S, style: Style, tf: &TextFlow) -> u16
+    where
+        S: AsRef<str>,
+    {
+        let lines = tf.layout(string.as_ref());
+        for (i, (lx, l)) in lines.iter().enumerate() {
+            let py = y + i as u16;
+            if py >= self.area.height {
+                break;
+            }
+            self.set_stringn(
+                x + lx + self.area.x,
+                py + self.area.y,
+                l,
+                tf.width as usize,
+                style,
+                0,
+            );
+        }
+        lines.len() as u16
+    }
+
     //absolute pos
     pub fn set_string_tex<S>(&mut self, x: u16, y: u16, string: S, style: Style, tex: u8)
     where
@@ -259,13 +339,15 @@ impl Buffer {
                 break;
             }
 
+            self.clear_wide_at(index, false);
             self.content[index].set_symbol(s);
-            self.content[index].set_style(style);
+            self.write_style(index, style);
             self.content[index].set_texture(tex);
 
-            // Reset following cells if multi-width (they would be hidden by the grapheme),
+            // Mark following cells as shadow if multi-width (they would be hidden by the grapheme),
             for i in index + 1..index + width {
-                self.content[i].reset();
+                self.clear_wide_at(i, true);
+                self.content[i].set_shadow();
             }
             index += width;
             x_offset += width;
@@ -276,7 +358,8 @@ impl Buffer {
     pub fn set_style(&mut self, area: Rect, style: Style) {
         for y in area.top()..area.bottom() {
             for x in area.left()..area.right() {
-                self.get_mut(x, y).set_style(style);
+                let i = self.index_of(x, y);
+                self.write_style(i, style);
             }
         }
     }
@@ -297,8 +380,32 @@ impl Buffer {
         }
     }
 
+    /// called before overwriting cell i, keeps wide chars consistent:
+    /// a wide char being overwritten loses its shadow cells,
+    /// a shadow cell being overwritten by a normal cell clears its wide char
+    fn clear_wide_at(&mut self, i: usize, incoming_shadow: bool) {
+        let w = self.area.width as usize;
+        let row_start = i - i % w;
+        let row_end = row_start + w;
+        if self.content[i].symbol.width() > 1 {
+            let mut j = i + 1;
+            while j < row_end && self.content[j].is_shadow() {
+                self.content[j].reset();
+                j += 1;
+            }
+        } else if self.content[i].is_shadow() && !incoming_shadow {
+            let mut j = i;
+            while j > row_start && self.content[j].is_shadow() {
+                self.content[j].reset();
+                j -= 1;
+            }
+            self.content[j].reset();
+        }
+    }
+
     #[allow(unused_variables)]
     pub fn copy_cell(&mut self, pos_self: usize, other: &Buffer, alpha: u8, pos_other: usize) {
+        self.clear_wide_at(pos_self, other.content[pos_other].is_shadow());
         // self.content[pos_self].symbol = other.content[pos_other].symbol.clone();
         // self.content[pos_self].bg = other.content[pos_other].bg;
         self.content[pos_self] = other.content[pos_other].clone();
@@ -380,6 +487,25 @@ impl Buffer {
         self.area = area;
     }
 
+    /// merge other buffer moved by (-ox, -oy), cells outside self are clipped,
+    /// area of self is not changed. Used by camera
+    pub fn merge_offset(&mut self, other: &Buffer, alpha: u8, ox: i32, oy: i32) {
+        let size = other.area.area() as usize;
+        for i in 0..size {
+            if other.content[i].is_blank() {
+                continue;
+            }
+            let (x, y) = other.pos_of(i);
+            let dx = x as i32 - ox - self.area.x as i32;
+            let dy = y as i32 - oy - self.area.y as i32;
+            if dx < 0 || dy < 0 || dx >= self.area.width as i32 || dy >= self.area.height as i32 {
+                continue;
+            }
+            let k = (dy * self.area.width as i32 + dx) as usize;
+            self.copy_cell(k, other, alpha, i);
+        }
+    }
+
     /// Builds a minimal sequence of coordinates and Cells necessary to update the UI from
     /// self to other.
     pub fn diff<'a>(&self, other: &'a Buffer) -> Vec<(u16, u16, &'a Cell)> {
@@ -432,4 +558,69 @@ mod tests {
         assert_eq!(buf.pos_of(buf.content.len() - 1), (249, 179));
         assert_eq!(buf.index_of(249, 179), buf.content.len() - 1);
     }
+
+    #[test]
+    fn wide_char_occupies_two_cells() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 6, 1));
+        buf.set_str(0, 0, "中a", Style::default());
+        assert_eq!(buf.get(0, 0).symbol, "中");
+        assert!(buf.get(1, 0).is_shadow());
+        assert_eq!(buf.get(2, 0).symbol, "a");
+
+        // overwrite the shadow half clears the wide char
+        buf.set_str(1, 0, "b", Style::default());
+        assert_eq!(buf.get(0, 0).symbol, " ");
+        assert_eq!(buf.get(1, 0).symbol, "b");
+
+        // merged wide char hides the cell under its shadow
+        let mut spr = Buffer::empty(Rect::new(2, 0, 2, 1));
+        spr.set_str(0, 0, "文", Style::default());
+        let mut dst = Buffer::empty(Rect::new(0, 0, 6, 1));
+        dst.set_str(0, 0, "abcdef", Style::default());
+        dst.merge(&spr, 255, true);
+        assert_eq!(dst.get(2, 0).symbol, "文");
+        assert!(dst.get(3, 0).is_shadow());
+        let updates = Buffer::empty(dst.area).diff(&dst);
+        assert!(!updates.iter().any(|u| u.0 == 3));
+    }
+
+    #[test]
+    fn rich_str_markup() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 12, 1));
+        buf.set_rich_str(0, 0, "[red]HP[/red] [bold]1[[0[/]", Style::default());
+        assert_eq!(buf.get(0, 0).fg, Color::Red);
+        assert_eq!(buf.get(1, 0).symbol, "P");
+        assert_eq!(buf.get(2, 0).fg, Color::Reset);
+        assert_eq!(buf.get(4, 0).symbol, "[");
+        assert!(buf.get(4, 0).modifier.contains(crate::render::style::Modifier::BOLD));
+        assert!(!buf.get(6, 0).modifier.contains(crate::render::style::Modifier::BOLD));
+    }
+
+    #[test]
+    fn blend_composites_colors() {
+        let gray = Color::Rgba(128, 128, 128, 255);
+        let white = Color::Rgba(255, 255, 255, 255);
+        let black = Color::Rgba(0, 0, 0, 255);
+        assert_eq!(gray.blend(white, BlendMode::Multiply, 1.0), gray);
+        assert_eq!(gray.blend(black, BlendMode::Screen, 1.0), gray);
+        assert_eq!(gray.blend(white, BlendMode::Screen, 1.0), white);
+        assert_eq!(white.blend(black, BlendMode::Normal, 0.0), white);
+        assert_eq!(black.blend(white, BlendMode::Overlay, 1.0), black);
+        // half opacity is mixed in linear rgb, brighter than 128
+        let half = Color::Rgba(255, 255, 255, 128);
+        let (r, _, _, a) = black.blend(half, BlendMode::Normal, 1.0).get_rgba();
+        assert!(r > 180 && a == 255);
+        assert_eq!(Color::Reset.blend(gray, BlendMode::Multiply, 1.0), gray);
+        assert_eq!(gray.blend(Color::Reset, BlendMode::Multiply, 1.0), gray);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 4, 1));
+        buf.set_str(0, 0, "ab", Style::default().fg(white).bg(gray));
+        buf.set_blend(Some(BlendMode::Multiply));
+        buf.set_style(Rect::new(0, 0, 1, 1), Style::default().bg(black));
+        buf.set_blend(None);
+        assert_eq!(buf.get(0, 0).bg, black);
+        assert_eq!(buf.get(0, 0).fg, white);
+        assert_eq!(buf.get(1, 0).bg, gray);
+        assert_eq!(buf.get(0, 0).symbol, "a");
+    }
 }
@@ -0,0 +1,191 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! lines, circles, flood fill and raycasts in cell coordinates
+//!
+//! Coordinates are the same as Buffer::get, shapes may reach out of the
+//! buffer and are clipped. Drawing returns the cells written, so the same
+//! calls serve gameplay queries, e.g. the cells of an explosion radius.
+//! raycast only reads the buffer, it walks a line until a cell blocks.
+
+use crate::render::{buffer::Buffer, cell::Cell, style::Style};
+use crate::util::shape::line;
+
+/// result of Buffer::raycast
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RayHit {
+    /// cells passed from start, without the blocking cell
+    pub cells: Vec<(u16, u16)>,
+    /// first blocking cell
+    pub hit: Option<(u16, u16)>,
+}
+
+impl Buffer {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.area.left() as i32
+            && x < self.area.right() as i32
+            && y >= self.area.top() as i32
+            && y < self.area.bottom() as i32
+    }
+
+    // writes one symbol, false if out of the buffer
+    fn put(&mut self, x: i32, y: i32, sym: &str, style: Style) -> bool {
+        if !self.contains(x, y) {
+            return false;
+        }
+        let i = self.index_of(x as u16, y as u16);
+        self.clear_wide_at(i, false);
+        self.content[i].set_symbol(sym);
+        self.write_style(i, style);
+        true
+    }
+
+    fn put_all(&mut self, cells: Vec<(i32, i32)>, sym: &str, style: Style) -> Vec<(u16, u16)> {
+        cells
+            .into_iter()
+            .filter(|(x, y)| self.put(*x, *y, sym, style))
+            .map(|(x, y)| (x as u16, y as u16))
+            .collect()
+    }
+
+    /// bresenham line from (x0, y0) to (x1, y1), returns the cells written
+    pub fn draw_line(
+        &mut self,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        sym: &str,
+        style: Style,
+    ) -> Vec<(u16, u16)> {
+        let cells = line(x0 as i16, y0 as i16, x1 as i16, y1 as i16)
+            .into_iter()
+            .map(|(x, y, _)| (x as i32, y as i32))
+            .collect();
+        self.put_all(cells, sym, style)
+    }
+
+    /// circle of radius r around (cx, cy), filled or just the outline,
+    /// returns the cells written
+    pub fn draw_circle(
+        &mut self,
+        cx: i32,
+        cy: i32,
+        r: u16,
+        filled: bool,
+        sym: &str,
+        style: Style,
+    ) -> Vec<(u16, u16)> {
+        let r = r as i32;
+        // r * r + r rounds the edge nicer than r * r
+        let inside = |dx: i32, dy: i32| dx * dx + dy * dy <= r * r + r;
+        let mut cells = vec![];
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if !inside(dx, dy) {
+                    continue;
+                }
+                let edge = !inside(dx + 1, dy)
+                    || !inside(dx - 1, dy)
+                    || !inside(dx, dy + 1)
+                    || !inside(dx, dy - 1);
+                if filled || edge {
+                    cells.push((cx + dx, cy + dy));
+                }
+            }
+        }
+        self.put_all(cells, sym, style)
+    }
+
+    /// fills the 4-connected area of cells equal to the cell at (x, y),
+    /// returns the cells written
+    pub fn flood_fill(&mut self, x: i32, y: i32, sym: &str, style: Style) -> Vec<(u16, u16)> {
+        if !self.contains(x, y) {
+            return vec![];
+        }
+        let target = self.get(x as u16, y as u16).clone();
+        let mut seen = vec![false; self.content.len()];
+        let mut cells = vec![];
+        let mut stack = vec![(x, y)];
+        seen[self.index_of(x as u16, y as u16)] = true;
+        while let Some((x, y)) = stack.pop() {
+            cells.push((x, y));
+            for (nx, ny) in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if !self.contains(nx, ny) {
+                    continue;
+                }
+                let i = self.index_of(nx as u16, ny as u16);
+                if !seen[i] && self.content[i] == target {
+                    seen[i] = true;
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        self.put_all(cells, sym, style)
+    }
+
+    /// walks the line from (x0, y0) to (x1, y1) until blocks is true for
+    /// a cell, the start cell never blocks, leaving the buffer stops too
+    pub fn raycast<F>(&self, x0: i32, y0: i32, x1: i32, y1: i32, blocks: F) -> RayHit
+    where
+        F: Fn(&Cell) -> bool,
+    {
+        let mut ray = RayHit {
+            cells: vec![],
+            hit: None,
+        };
+        for (i, (x, y, _)) in line(x0 as i16, y0 as i16, x1 as i16, y1 as i16)
+            .into_iter()
+            .enumerate()
+        {
+            let (x, y) = (x as i32, y as i32);
+            if !self.contains(x, y) {
+                break;
+            }
+            let p = (x as u16, y as u16);
+            if i > 0 && blocks(self.get(p.0, p.1)) {
+                ray.hit = Some(p);
+                break;
+            }
+            ray.cells.push(p);
+        }
+        ray
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Rect;
+
+    #[test]
+    fn shapes_and_rays() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 20, 10));
+        let st = Style::default();
+        let l = buf.draw_line(0, 0, 5, 5, "\\", st);
+        assert_eq!(l.len(), 6);
+        assert_eq!(buf.get(3, 3).symbol, "\\");
+        // clipped
+        assert_eq!(buf.draw_line(15, 2, 25, 2, "-", st).len(), 5);
+
+        let c = buf.draw_circle(10, 5, 3, false, "o", st);
+        assert!(c.contains(&(13, 5)) && c.contains(&(10, 2)));
+        assert!(!c.contains(&(10, 5)));
+        let f = buf.draw_circle(10, 5, 3, true, "o", st);
+        assert!(f.len() > c.len() && f.contains(&(10, 5)));
+
+        // wall splits the buffer
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 4));
+        buf.draw_line(4, 0, 4, 3, "#", st);
+        let filled = buf.flood_fill(0, 0, ".", st);
+        assert_eq!(filled.len(), 16);
+        assert_eq!(buf.get(9, 3).symbol, " ");
+
+        let ray = buf.raycast(1, 1, 8, 1, |c| c.symbol == "#");
+        assert_eq!(ray.hit, Some((4, 1)));
+        assert_eq!(ray.cells.len(), 3);
+        let ray = buf.raycast(6, 0, 9, 3, |c| c.symbol == "#");
+        assert_eq!(ray.hit, None);
+        assert_eq!(ray.cells.last(), Some(&(9, 3)));
+    }
+}
@@ -0,0 +1,124 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Camera provides a viewport into a world larger than the screen.
+//! Sprites are placed in world coordinates (cells), the panel applies
+//! the camera when composing buffers.
+//!
+//! In text mode the camera offset is applied by whole cells during buffer
+//! composition, zoom is ignored.
+//! In graphics mode the whole cells offset is applied the same way, and
+//! pixel layers following the camera also get the fractional part and
+//! zoom as a per layer view transform, so scrolling is smooth.
+
+use crate::render::adapter::gl::transform::GlTransform;
+use crate::util::{PointF32, Rect};
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    /// top left corner of the view in world cells
+    pub pos: PointF32,
+    pub zoom: f32,
+    /// view size in cells
+    pub view_w: u16,
+    pub view_h: u16,
+    /// world area the view is clamped into
+    pub bounds: Option<Rect>,
+    /// follow smoothing factor per update, 1.0 means snap to target
+    pub smooth: f32,
+    target: Option<PointF32>,
+}
+
+impl Camera {
+    pub fn new(view_w: u16, view_h: u16) -> Self {
+        Self {
+            pos: PointF32 { x: 0.0, y: 0.0 },
+            zoom: 1.0,
+            view_w,
+            view_h,
+            bounds: None,
+            smooth: 1.0,
+            target: None,
+        }
+    }
+
+    pub fn set_pos(&mut self, x: f32, y: f32) {
+        self.pos = PointF32 { x, y };
+        self.clamp();
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(0.01);
+    }
+
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = Some(bounds);
+        self.clamp();
+    }
+
+    pub fn set_smooth(&mut self, smooth: f32) {
+        self.smooth = smooth.clamp(0.0, 1.0);
+    }
+
+    /// center the view on a world point, reached gradually by update
+    pub fn follow(&mut self, x: f32, y: f32) {
+        self.target = Some(PointF32 {
+            x: x - self.view_w as f32 / 2.0,
+            y: y - self.view_h as f32 / 2.0,
+        });
+    }
+
+    /// call once per frame, moves towards follow target
+    pub fn update(&mut self) {
+        if let Some(t) = self.target {
+            self.pos.x += (t.x - self.pos.x) * self.smooth;
+            self.pos.y += (t.y - self.pos.y) * self.smooth;
+            if (t.x - self.pos.x).abs() < 0.01 && (t.y - self.pos.y).abs() < 0.01 {
+                self.pos = t;
+                self.target = None;
+            }
+            self.clamp();
+        }
+    }
+
+    fn clamp(&mut self) {
+        if let Some(b) = self.bounds {
+            let maxx = (b.x as f32 + b.width as f32 - self.view_w as f32).max(b.x as f32);
+            let maxy = (b.y as f32 + b.height as f32 - self.view_h as f32).max(b.y as f32);
+            self.pos.x = self.pos.x.clamp(b.x as f32, maxx);
+            self.pos.y = self.pos.y.clamp(b.y as f32, maxy);
+        }
+    }
+
+    /// whole cells offset, applied during buffer composition
+    pub fn cell_offset(&self) -> (i32, i32) {
+        (self.pos.x.floor() as i32, self.pos.y.floor() as i32)
+    }
+
+    /// sub cell offset, in 0.0..1.0
+    pub fn fraction(&self) -> (f32, f32) {
+        (self.pos.x - self.pos.x.floor(), self.pos.y - self.pos.y.floor())
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (x - self.pos.x, y - self.pos.y)
+    }
+
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        (x + self.pos.x, y + self.pos.y)
+    }
+
+    /// transform for graphics mode, cell_w & cell_h are cell size in pixels
+    pub fn gl_transform(&self, cell_w: f32, cell_h: f32) -> GlTransform {
+        let (fx, fy) = self.fraction();
+        let cx = self.view_w as f32 * cell_w / 2.0;
+        let cy = self.view_h as f32 * cell_h / 2.0;
+        let mut t = GlTransform::new();
+        // zoom around view center
+        t.translate(cx, cy);
+        t.scale(self.zoom, self.zoom);
+        t.translate(-cx, -cy);
+        t.translate(-fx * cell_w, -fy * cell_h);
+        t
+    }
+}
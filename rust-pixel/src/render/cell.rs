@@ -11,7 +11,7 @@
 //! how to use cell.
 //!
 
-use crate::render::style::{Color, Modifier, Style};
+use crate::render::style::{BlendMode, Color, Modifier, Style};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use lazy_static::lazy_static;
@@ -79,6 +79,10 @@ pub fn cellsym(idx: u8) -> &'static str {
 /// return idx, if it is a unicode char
 /// otherwise get index from CELL_SYM_MAP
 fn symidx(symbol: &String) -> u8 {
+    // shadow cell of a wide char, draw as space
+    if symbol.is_empty() {
+        return *CELL_SYM_MAP.get(" ").unwrap_or(&0);
+    }
     let sbts = symbol.as_bytes();
     // unicode graphics symbol
     if sbts.len() == 3 && sbts[0] == 0xe2 && (sbts[1] >> 2 == 0x22) {
@@ -123,6 +127,18 @@ impl Cell {
         (symidx(&self.symbol), self.tex, self.fg, self.bg)
     }
 
+    /// the cell covered by the right half of a wide char (CJK...),
+    /// it has an empty symbol and is skipped when rendering
+    pub fn set_shadow(&mut self) -> &mut Cell {
+        self.reset();
+        self.symbol.clear();
+        self
+    }
+
+    pub fn is_shadow(&self) -> bool {
+        self.symbol.is_empty()
+    }
+
     pub fn set_char(&mut self, ch: char) -> &mut Cell {
         self.symbol.clear();
         self.symbol.push(ch);
@@ -156,6 +172,19 @@ impl Cell {
         self
     }
 
+    /// like set_style, but fg and bg are composited over the current colors
+    pub fn blend_style(&mut self, style: Style, mode: BlendMode) -> &mut Cell {
+        if let Some(c) = style.fg {
+            self.fg = self.fg.blend(c, mode, 1.0);
+        }
+        if let Some(c) = style.bg {
+            self.bg = self.bg.blend(c, mode, 1.0);
+        }
+        self.modifier.insert(style.add_modifier);
+        self.modifier.remove(style.sub_modifier);
+        self
+    }
+
     pub fn style(&self) -> Style {
         Style::default()
             .fg(self.fg)
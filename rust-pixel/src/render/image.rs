@@ -77,3 +77,17 @@ pub use esc::EscAsset;
 
 pub mod seq_frame;
 pub use seq_frame::SeqFrameAsset;
+
+pub mod ase;
+pub use ase::AseAsset;
+
+pub mod ans;
+pub use ans::{ans_to_buffer, cp437_byte, cp437_char, parse_sauce, AnsAsset, Sauce};
+
+pub mod xp;
+pub use xp::{buffer_to_xp, xp_to_buffer, XpAsset};
+
+pub mod png;
+pub use png::{pix_text, quantize, rgba_to_pix, PaletteMode, PngAsset};
+#[cfg(feature = "image")]
+pub use png::png_to_pix;
@@ -0,0 +1,363 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Implements load of classic ANSI art files (.ans)
+//!
+//! Bytes are CP437 characters mixed with ANSI escape sequences
+//! (SGR colors and cursor moves). An optional SAUCE record at the end
+//! gives title, author, width and the iCE colors flag: with iCE colors,
+//! blink selects a bright background instead of blinking.
+
+use crate::{
+    asset::{Asset, AssetBase},
+    render::buffer::Buffer,
+    render::style::{Color, Style},
+    util::Rect,
+};
+use log::info;
+
+// CP437 0x80..0xFF
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■ ";
+
+// CP437 0x01..0x1F glyphs, 0 is blank
+const CP437_LOW: &str = " ☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼";
+
+/// maps a CP437 byte to its unicode symbol
+pub fn cp437_char(b: u8) -> char {
+    match b {
+        0x00..=0x1f => CP437_LOW.chars().nth(b as usize).unwrap_or(' '),
+        0x7f => '⌂',
+        0x80..=0xff => CP437_HIGH.chars().nth(b as usize - 0x80).unwrap_or(' '),
+        _ => b as char,
+    }
+}
+
+/// CP437 byte of a unicode symbol, if it has one
+pub fn cp437_byte(c: char) -> Option<u8> {
+    if c.is_ascii() && !c.is_ascii_control() {
+        return Some(c as u8);
+    }
+    (0..=255u8).find(|b| cp437_char(*b) == c)
+}
+
+/// SAUCE metadata record
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    pub date: String,
+    pub width: u16,
+    pub height: u16,
+    pub ice_colors: bool,
+    pub comments: Vec<String>,
+}
+
+fn sauce_str(b: &[u8]) -> String {
+    b.iter()
+        .map(|c| cp437_char(*c))
+        .collect::<String>()
+        .trim_end_matches([' ', '\0'])
+        .to_string()
+}
+
+/// reads the SAUCE record, returns it and the length of the art data
+pub fn parse_sauce(data: &[u8]) -> (Option<Sauce>, usize) {
+    let n = data.len();
+    let mut end = n;
+    let mut sauce = None;
+    if n >= 128 && &data[n - 128..n - 121] == b"SAUCE00" {
+        let s = &data[n - 128..];
+        let word = |i: usize| u16::from_le_bytes([s[i], s[i + 1]]);
+        let mut sc = Sauce {
+            title: sauce_str(&s[7..42]),
+            author: sauce_str(&s[42..62]),
+            group: sauce_str(&s[62..82]),
+            date: sauce_str(&s[82..90]),
+            width: word(96),
+            height: word(98),
+            ice_colors: s[105] & 1 != 0,
+            comments: vec![],
+        };
+        end = n - 128;
+        // comment block: "COMNT" followed by 64 bytes lines
+        let cn = s[104] as usize;
+        let cb = 5 + cn * 64;
+        if cn > 0 && end >= cb && &data[end - cb..end - cb + 5] == b"COMNT" {
+            let lines = &data[end - cb + 5..end];
+            sc.comments = lines.chunks(64).map(sauce_str).collect();
+            end -= cb;
+        }
+        sauce = Some(sc);
+    }
+    // art ends at the first EOF (^Z) char
+    if let Some(p) = data[..end].iter().position(|b| *b == 0x1a) {
+        end = p;
+    }
+    (sauce, end)
+}
+
+// terminal state while drawing the art
+struct AnsPen {
+    x: u16,
+    y: u16,
+    saved: (u16, u16),
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    blink: bool,
+    fg256: Option<Color>,
+    bg256: Option<Color>,
+}
+
+impl AnsPen {
+    fn reset(&mut self) {
+        self.fg = 7;
+        self.bg = 0;
+        self.bold = false;
+        self.blink = false;
+        self.fg256 = None;
+        self.bg256 = None;
+    }
+
+    fn style(&self, ice: bool) -> Style {
+        let fg = self
+            .fg256
+            .unwrap_or(Color::Indexed(self.fg + if self.bold { 8 } else { 0 }));
+        let bg = self
+            .bg256
+            .unwrap_or(Color::Indexed(self.bg + if ice && self.blink { 8 } else { 0 }));
+        Style::default().fg(fg).bg(bg)
+    }
+
+    fn sgr(&mut self, ps: &[u16]) {
+        if ps.is_empty() {
+            self.reset();
+        }
+        let mut i = 0;
+        while i < ps.len() {
+            match ps[i] {
+                0 => self.reset(),
+                1 => self.bold = true,
+                5 | 6 => self.blink = true,
+                22 => self.bold = false,
+                25 => self.blink = false,
+                30..=37 => {
+                    self.fg = (ps[i] - 30) as u8;
+                    self.fg256 = None;
+                }
+                39 => {
+                    self.fg = 7;
+                    self.fg256 = None;
+                }
+                40..=47 => {
+                    self.bg = (ps[i] - 40) as u8;
+                    self.bg256 = None;
+                }
+                49 => {
+                    self.bg = 0;
+                    self.bg256 = None;
+                }
+                90..=97 => self.fg256 = Some(Color::Indexed((ps[i] - 90 + 8) as u8)),
+                100..=107 => self.bg256 = Some(Color::Indexed((ps[i] - 100 + 8) as u8)),
+                38 | 48 => {
+                    let is_fg = ps[i] == 38;
+                    let c = match ps.get(i + 1) {
+                        Some(5) => {
+                            i += 2;
+                            ps.get(i).map(|v| Color::Indexed(*v as u8))
+                        }
+                        Some(2) => {
+                            i += 4;
+                            let g = |k: usize| ps.get(k).copied().unwrap_or(0) as u8;
+                            Some(Color::Rgba(g(i - 2), g(i - 1), g(i), 255))
+                        }
+                        _ => None,
+                    };
+                    if c.is_some() {
+                        if is_fg {
+                            self.fg256 = c;
+                        } else {
+                            self.bg256 = c;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// draws ANSI art bytes into a buffer, width is the wrapping column,
+/// the buffer height fits the drawn lines
+pub fn ans_to_buffer(data: &[u8], width: u16, ice: bool) -> Buffer {
+    let width = width.max(1);
+    let mut cells: Vec<Vec<(char, Style)>> = vec![];
+    let mut pen = AnsPen {
+        x: 0,
+        y: 0,
+        saved: (0, 0),
+        fg: 7,
+        bg: 0,
+        bold: false,
+        blink: false,
+        fg256: None,
+        bg256: None,
+    };
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        i += 1;
+        match b {
+            b'\r' => pen.x = 0,
+            b'\n' => {
+                pen.x = 0;
+                pen.y += 1;
+            }
+            0x1b if data.get(i) == Some(&b'[') => {
+                i += 1;
+                let start = i;
+                while i < data.len() && !(0x40..=0x7e).contains(&data[i]) {
+                    i += 1;
+                }
+                let Some(&cmd) = data.get(i) else {
+                    break;
+                };
+                let raw = String::from_utf8_lossy(&data[start..i]).to_string();
+                i += 1;
+                // private modes like ?7h are ignored
+                if raw.starts_with('?') {
+                    continue;
+                }
+                let ps: Vec<u16> = if raw.is_empty() {
+                    vec![]
+                } else {
+                    raw.split(';').map(|p| p.parse().unwrap_or(0)).collect()
+                };
+                let n = ps.first().copied().unwrap_or(1).max(1);
+                match cmd {
+                    b'm' => pen.sgr(&ps),
+                    b'A' => pen.y = pen.y.saturating_sub(n),
+                    b'B' => pen.y += n,
+                    b'C' => pen.x = (pen.x + n).min(width - 1),
+                    b'D' => pen.x = pen.x.saturating_sub(n),
+                    b'H' | b'f' => {
+                        pen.y = ps.first().copied().unwrap_or(1).max(1) - 1;
+                        pen.x = (ps.get(1).copied().unwrap_or(1).max(1) - 1).min(width - 1);
+                    }
+                    b's' => pen.saved = (pen.x, pen.y),
+                    b'u' => (pen.x, pen.y) = pen.saved,
+                    b'J' if ps.first() == Some(&2) => {
+                        cells.clear();
+                        pen.x = 0;
+                        pen.y = 0;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {
+                let y = pen.y as usize;
+                if cells.len() <= y {
+                    cells.resize(y + 1, vec![]);
+                }
+                let row = &mut cells[y];
+                if row.len() <= pen.x as usize {
+                    row.resize(pen.x as usize + 1, (' ', Style::default()));
+                }
+                row[pen.x as usize] = (cp437_char(b), pen.style(ice));
+                pen.x += 1;
+                if pen.x >= width {
+                    pen.x = 0;
+                    pen.y += 1;
+                }
+            }
+        }
+    }
+    let w = cells.iter().map(|r| r.len()).max().unwrap_or(0) as u16;
+    let mut buf = Buffer::empty(Rect::new(0, 0, w, cells.len() as u16));
+    for (y, row) in cells.iter().enumerate() {
+        for (x, (c, st)) in row.iter().enumerate() {
+            buf.get_mut(x as u16, y as u16)
+                .set_symbol(&c.to_string())
+                .set_style(*st);
+        }
+    }
+    buf
+}
+
+pub struct AnsAsset {
+    base: AssetBase,
+    pub sauce: Option<Sauce>,
+}
+
+impl Asset for AnsAsset {
+    fn new(ab: AssetBase) -> Self {
+        Self {
+            base: ab,
+            sauce: None,
+        }
+    }
+
+    fn get_base(&mut self) -> &mut AssetBase {
+        &mut self.base
+    }
+
+    fn parse(&mut self) {
+        self.base.parsed_buffers.clear();
+        let (sauce, len) = parse_sauce(&self.base.raw_data);
+        let (width, ice) = match &sauce {
+            Some(s) if s.width > 0 => (s.width, s.ice_colors),
+            Some(s) => (80, s.ice_colors),
+            None => (80, false),
+        };
+        if let Some(s) = &sauce {
+            info!("ans sauce:{:?} by {:?} {}x{}", s.title, s.author, s.width, s.height);
+        }
+        let buf = ans_to_buffer(&self.base.raw_data[..len], width, ice);
+        self.base.parsed_buffers.push(buf);
+        self.sauce = sauce;
+    }
+
+    fn save(&mut self, _content: &Buffer) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ans_with_sauce() {
+        let mut data = b"\x1b[1;31mA\x1b[0;5;44m\xdb\r\n\x1b[2C\xb0".to_vec();
+        data.push(0x1a);
+        let mut s = b"SAUCE00".to_vec();
+        s.extend(format!("{:35}", "Test").as_bytes());
+        s.extend(format!("{:20}", "zip").as_bytes());
+        s.extend([b' '; 20]);
+        s.extend(b"20240101");
+        s.extend([0u8; 4]); // file size
+        s.extend([1, 1]); // character, ansi
+        s.extend(4u16.to_le_bytes());
+        s.extend(2u16.to_le_bytes());
+        s.extend([0u8; 4]);
+        s.push(0); // comments
+        s.push(1); // iCE colors
+        s.extend([0u8; 22]);
+        assert_eq!(s.len(), 128);
+        data.extend(s);
+
+        let (sauce, len) = parse_sauce(&data);
+        let sauce = sauce.unwrap();
+        assert_eq!((sauce.title.as_str(), sauce.author.as_str()), ("Test", "zip"));
+        assert_eq!((sauce.width, sauce.height, sauce.ice_colors), (4, 2, true));
+        let buf = ans_to_buffer(&data[..len], sauce.width, sauce.ice_colors);
+        assert_eq!((buf.area.width, buf.area.height), (3, 2));
+        assert_eq!(buf.get(0, 0).symbol, "A");
+        assert_eq!(buf.get(0, 0).fg, Color::Indexed(9));
+        assert_eq!(buf.get(1, 0).symbol, "█");
+        assert_eq!(buf.get(1, 0).bg, Color::Indexed(12));
+        assert_eq!(buf.get(2, 1).symbol, "░");
+    }
+}
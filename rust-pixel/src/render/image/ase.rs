@@ -0,0 +1,429 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Implements load of Aseprite .ase / .aseprite files
+//!
+//! Every pixel becomes a cell drawn with a solid block symbol,
+//! its color is mapped to the nearest of the 256 ansi colors,
+//! transparent pixels are left as blank cells.
+//! Visible layers are composed, each frame becomes a parsed buffer,
+//! so frame_idx of asset2sprite! selects an Aseprite frame.
+//! Tags are exposed as FrameTag, use AnimatedSprite::from_tag to play them.
+//! A parsed buffer can be saved as .pix by PixAsset::save.
+//!
+//! Refer: https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+
+use crate::{
+    asset::{Asset, AssetBase, FrameTag},
+    render::buffer::Buffer,
+    render::sprite::LoopMode,
+    render::style::{nearest_ansi, Color, Style},
+    util::Rect,
+};
+use flate2::read::ZlibDecoder;
+use log::info;
+use std::io::Read;
+
+#[cfg(any(feature = "sdl", target_os = "android", target_os = "ios", target_arch = "wasm32"))]
+const PIXEL_SYM: &str = "⊠"; // cellsym(160), solid block in c64 texture
+#[cfg(not(any(feature = "sdl", target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+const PIXEL_SYM: &str = "█";
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> u8 {
+        let v = self.data.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        v
+    }
+
+    fn word(&mut self) -> u16 {
+        self.byte() as u16 | (self.byte() as u16) << 8
+    }
+
+    fn short(&mut self) -> i16 {
+        self.word() as i16
+    }
+
+    fn dword(&mut self) -> u32 {
+        self.word() as u32 | (self.word() as u32) << 16
+    }
+
+    fn string(&mut self) -> String {
+        let n = self.word() as usize;
+        let end = (self.pos + n).min(self.data.len());
+        let s = String::from_utf8_lossy(&self.data[self.pos.min(end)..end]).to_string();
+        self.pos += n;
+        s
+    }
+
+    fn skip(&mut self, n: usize) {
+        self.pos += n;
+    }
+
+    fn rest(&self, end: usize) -> &'a [u8] {
+        let end = end.min(self.data.len());
+        &self.data[self.pos.min(end)..end]
+    }
+}
+
+struct AseLayer {
+    visible: bool,
+    is_image: bool,
+}
+
+#[derive(Clone)]
+struct AseCel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    w: usize,
+    h: usize,
+    // rgba
+    pixels: Vec<[u8; 4]>,
+}
+
+pub struct AseAsset {
+    pub base: AssetBase,
+    pub width: u16,
+    pub height: u16,
+    /// duration of each frame in seconds
+    pub durations: Vec<f32>,
+    pub tags: Vec<FrameTag>,
+}
+
+impl AseAsset {
+    fn decode(&mut self, data: &[u8]) -> Result<(), String> {
+        let mut r = Reader { data, pos: 0 };
+        r.dword();
+        if r.word() != 0xA5E0 {
+            return Err("bad aseprite magic".to_string());
+        }
+        let frames = r.word() as usize;
+        let (w, h) = (r.word() as usize, r.word() as usize);
+        let depth = r.word();
+        r.skip(14);
+        let transparent = r.byte();
+        r.pos = 128;
+
+        let mut palette = vec![[0u8, 0, 0, 255]; 256];
+        let mut layers: Vec<AseLayer> = vec![];
+        let mut frame_cels: Vec<Vec<AseCel>> = vec![];
+        for _f in 0..frames {
+            let fstart = r.pos;
+            let fsize = r.dword() as usize;
+            if r.word() != 0xF1FA {
+                break;
+            }
+            let old_chunks = r.word() as usize;
+            let duration = r.word();
+            r.skip(2);
+            let new_chunks = r.dword() as usize;
+            let chunks = if new_chunks == 0 { old_chunks } else { new_chunks };
+            self.durations.push(duration as f32 / 1000.0);
+            let mut cels: Vec<AseCel> = vec![];
+            for _c in 0..chunks {
+                let cstart = r.pos;
+                let csize = r.dword() as usize;
+                let ctype = r.word();
+                let cend = cstart + csize;
+                match ctype {
+                    CHUNK_LAYER => {
+                        let flags = r.word();
+                        let ltype = r.word();
+                        layers.push(AseLayer {
+                            visible: flags & 1 != 0,
+                            is_image: ltype == 0,
+                        });
+                    }
+                    CHUNK_CEL => {
+                        let layer = r.word() as usize;
+                        let (x, y) = (r.short() as i32, r.short() as i32);
+                        r.byte();
+                        let ctype = r.word();
+                        r.short();
+                        r.skip(5);
+                        match ctype {
+                            0 | 2 => {
+                                let (cw, ch) = (r.word() as usize, r.word() as usize);
+                                let raw = if ctype == 0 {
+                                    r.rest(cend).to_vec()
+                                } else {
+                                    let mut out = vec![];
+                                    let mut d = ZlibDecoder::new(r.rest(cend));
+                                    d.read_to_end(&mut out).map_err(|e| e.to_string())?;
+                                    out
+                                };
+                                let pixels = to_rgba(&raw, depth, &palette, transparent);
+                                cels.push(AseCel {
+                                    layer,
+                                    x,
+                                    y,
+                                    w: cw,
+                                    h: ch,
+                                    pixels,
+                                });
+                            }
+                            1 => {
+                                // linked cel
+                                let fp = r.word() as usize;
+                                if let Some(c) = frame_cels
+                                    .get(fp)
+                                    .and_then(|fc| fc.iter().find(|c| c.layer == layer))
+                                {
+                                    cels.push(c.clone());
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    CHUNK_PALETTE => {
+                        r.dword();
+                        let (first, last) = (r.dword() as usize, r.dword() as usize);
+                        r.skip(8);
+                        if first > last {
+                            return Err("bad aseprite palette range".to_string());
+                        }
+                        for i in first..=last.min(255) {
+                            let flags = r.word();
+                            let c = [r.byte(), r.byte(), r.byte(), r.byte()];
+                            if let Some(p) = palette.get_mut(i) {
+                                *p = c;
+                            }
+                            if flags & 1 != 0 {
+                                r.string();
+                            }
+                        }
+                    }
+                    CHUNK_OLD_PALETTE => {
+                        let packets = r.word();
+                        let mut idx = 0usize;
+                        for _ in 0..packets {
+                            idx += r.byte() as usize;
+                            let n = match r.byte() {
+                                0 => 256,
+                                n => n as usize,
+                            };
+                            for _ in 0..n {
+                                let c = [r.byte(), r.byte(), r.byte(), 255];
+                                if idx < 256 {
+                                    palette[idx] = c;
+                                }
+                                idx += 1;
+                            }
+                        }
+                    }
+                    CHUNK_TAGS => {
+                        let n = r.word();
+                        r.skip(8);
+                        for _ in 0..n {
+                            let (from, to) = (r.word() as usize, r.word() as usize);
+                            let dir = r.byte();
+                            let repeat = r.word();
+                            r.skip(10);
+                            let name = r.string();
+                            self.tags.push(FrameTag {
+                                name,
+                                frames: (from..=to).map(|i| (i, 0.0)).collect(),
+                                mode: match (dir, repeat) {
+                                    (2 | 3, 0) => LoopMode::PingPongLoop,
+                                    (2 | 3, _) => LoopMode::PingPong,
+                                    (_, 0) => LoopMode::Loop,
+                                    _ => LoopMode::Once,
+                                },
+                            });
+                            // reverse and pingpong reverse
+                            if dir == 1 || dir == 3 {
+                                self.tags.last_mut().unwrap().frames.reverse();
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                r.pos = cend;
+            }
+            frame_cels.push(cels);
+            r.pos = fstart + fsize;
+        }
+
+        for tag in self.tags.iter_mut() {
+            for f in tag.frames.iter_mut() {
+                f.1 = self.durations.get(f.0).copied().unwrap_or(0.1);
+            }
+        }
+        for cels in &frame_cels {
+            self.base
+                .parsed_buffers
+                .push(compose(cels, &layers, w, h));
+        }
+        self.width = w as u16;
+        self.height = h as u16;
+        self.base.frame_count = self.base.parsed_buffers.len().max(1);
+        Ok(())
+    }
+}
+
+fn to_rgba(raw: &[u8], depth: u16, palette: &[[u8; 4]], transparent: u8) -> Vec<[u8; 4]> {
+    match depth {
+        32 => raw.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        16 => raw.chunks_exact(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        _ => raw
+            .iter()
+            .map(|i| {
+                if *i == transparent {
+                    [0, 0, 0, 0]
+                } else {
+                    palette[*i as usize]
+                }
+            })
+            .collect(),
+    }
+}
+
+fn compose(cels: &[AseCel], layers: &[AseLayer], w: usize, h: usize) -> Buffer {
+    let mut px = vec![[0u8; 4]; w * h];
+    let mut sorted: Vec<&AseCel> = cels.iter().collect();
+    sorted.sort_by_key(|c| c.layer);
+    for c in sorted {
+        match layers.get(c.layer) {
+            Some(l) if l.visible && l.is_image => {}
+            _ => continue,
+        }
+        for cy in 0..c.h {
+            for cx in 0..c.w {
+                let (x, y) = (c.x + cx as i32, c.y + cy as i32);
+                if x < 0 || y < 0 || x >= w as i32 || y >= h as i32 {
+                    continue;
+                }
+                if let Some(p) = c.pixels.get(cy * c.w + cx) {
+                    if p[3] >= 128 {
+                        px[y as usize * w + x as usize] = *p;
+                    }
+                }
+            }
+        }
+    }
+    let mut buf = Buffer::empty(Rect::new(0, 0, w as u16, h as u16));
+    for (i, p) in px.iter().enumerate() {
+        if p[3] == 0 {
+            continue;
+        }
+        let fg = Color::Indexed(nearest_ansi(p[0], p[1], p[2]));
+        buf.set_str((i % w) as u16, (i / w) as u16, PIXEL_SYM, Style::default().fg(fg));
+    }
+    buf
+}
+
+impl Asset for AseAsset {
+    fn new(ab: AssetBase) -> Self {
+        Self {
+            base: ab,
+            width: 0,
+            height: 0,
+            durations: vec![],
+            tags: vec![],
+        }
+    }
+
+    fn get_base(&mut self) -> &mut AssetBase {
+        &mut self.base
+    }
+
+    fn get_frame_tags(&mut self) -> Vec<FrameTag> {
+        self.tags.clone()
+    }
+
+    fn parse(&mut self) {
+        self.base.parsed_buffers.clear();
+        self.durations.clear();
+        self.tags.clear();
+        let data = self.base.raw_data.clone();
+        if let Err(e) = self.decode(&data) {
+            info!("aseprite parse error:{} {}", self.base.location, e);
+        }
+        if self.base.parsed_buffers.is_empty() {
+            self.base
+                .parsed_buffers
+                .push(Buffer::empty(Rect::new(0, 0, 0, 0)));
+        }
+    }
+
+    fn save(&mut self, _content: &Buffer) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+
+    fn chunk(t: u16, body: &[u8]) -> Vec<u8> {
+        let mut c = ((body.len() + 6) as u32).to_le_bytes().to_vec();
+        c.extend(t.to_le_bytes());
+        c.extend(body);
+        c
+    }
+
+    #[test]
+    fn ase_frames_and_tags() {
+        // 2x1 rgba, one layer, 2 frames, tag "walk" pingpong
+        let mut layer = vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0];
+        layer.extend([1, 0, b'L']);
+        let cel = |rgba: [u8; 8]| {
+            let mut c = vec![0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            c.extend([2, 0, 1, 0]);
+            c.extend(rgba);
+            c
+        };
+        let mut tags = vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        tags.extend([0, 0, 1, 0, 2, 0, 0]);
+        tags.extend([0; 10]);
+        tags.extend([4, 0]);
+        tags.extend(b"walk");
+        let frame = |chunks: Vec<Vec<u8>>| {
+            let body: Vec<u8> = chunks.concat();
+            let mut f = ((body.len() + 16) as u32).to_le_bytes().to_vec();
+            f.extend([0xFA, 0xF1, chunks.len() as u8, 0, 100, 0, 0, 0]);
+            f.extend((chunks.len() as u32).to_le_bytes());
+            f.extend(body);
+            f
+        };
+        let f0 = frame(vec![
+            chunk(CHUNK_LAYER, &layer),
+            chunk(CHUNK_TAGS, &tags),
+            chunk(CHUNK_CEL, &cel([255, 0, 0, 255, 0, 0, 0, 0])),
+        ]);
+        let f1 = frame(vec![chunk(CHUNK_CEL, &cel([0, 0, 0, 0, 0, 0, 255, 255]))]);
+        let mut data = vec![0u8; 128];
+        data[4..6].copy_from_slice(&0xA5E0u16.to_le_bytes());
+        data[6] = 2;
+        data[8] = 2;
+        data[10] = 1;
+        data[12] = 32;
+        data.extend(f0);
+        data.extend(f1);
+
+        let mut a = AseAsset::new(AssetBase::new(AssetType::ImgAse, "t.ase"));
+        a.set_data(&data);
+        a.parse();
+        assert_eq!(a.base.frame_count, 2);
+        let b0 = &a.base.parsed_buffers[0];
+        assert_eq!(b0.get(0, 0).symbol, PIXEL_SYM);
+        assert_eq!(b0.get(0, 0).fg, Color::Indexed(9));
+        assert_eq!(b0.get(1, 0).symbol, " ");
+        assert_eq!(a.base.parsed_buffers[1].get(1, 0).fg, Color::Indexed(12));
+        let t = a.get_frame_tags();
+        assert_eq!(t[0].name, "walk");
+        assert_eq!(t[0].mode, LoopMode::PingPongLoop);
+        assert_eq!(t[0].frames, vec![(0, 0.1), (1, 0.1)]);
+    }
+}
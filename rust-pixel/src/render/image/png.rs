@@ -0,0 +1,210 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Converts PNG (and other rgba) images to cells in .pix layout
+//!
+//! Each cell shows 2 vertical pixels with a lower half block symbol:
+//! fg is the lower pixel, bg is the upper pixel.
+//! Colors are quantized to the 256 ansi colors, optionally dithered.
+//! Pixels with alpha < 128 are transparent.
+//!
+//! Decoding png needs the "image" crate (enabled by feature sdl),
+//! rgba_to_pix works without it.
+
+use crate::{
+    asset::{Asset, AssetBase},
+    render::buffer::Buffer,
+    render::style::{nearest_ansi, Color, Style, ANSI_COLOR_RGB},
+    util::Rect,
+};
+use log::info;
+
+#[cfg(any(feature = "sdl", target_os = "android", target_os = "ios", target_arch = "wasm32"))]
+const HALF_SYM: &str = "≢"; // cellsym(98), lower half block in c64 texture
+#[cfg(not(any(feature = "sdl", target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+const HALF_SYM: &str = "▄";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaletteMode {
+    /// nearest color, no dithering
+    #[default]
+    Nearest,
+    /// 4x4 bayer matrix
+    Ordered,
+    /// error diffusion
+    FloydSteinberg,
+}
+
+const BAYER4: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+/// quantizes rgba pixels to ansi indices, None for transparent pixels
+pub fn quantize(w: usize, h: usize, rgba: &[u8], mode: PaletteMode) -> Vec<Option<u8>> {
+    let mut out = vec![None; w * h];
+    // working copy for error diffusion
+    let mut px: Vec<[f32; 3]> = rgba
+        .chunks_exact(4)
+        .take(w * h)
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+    px.resize(w * h, [0.0; 3]);
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            if rgba.get(i * 4 + 3).copied().unwrap_or(0) < 128 {
+                continue;
+            }
+            let mut c = px[i];
+            if mode == PaletteMode::Ordered {
+                let t = (BAYER4[y % 4][x % 4] / 16.0 - 0.5) * 48.0;
+                for v in c.iter_mut() {
+                    *v += t;
+                }
+            }
+            let u = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+            let idx = nearest_ansi(u(c[0]), u(c[1]), u(c[2]));
+            out[i] = Some(idx);
+            if mode == PaletteMode::FloydSteinberg {
+                let q = ANSI_COLOR_RGB[idx as usize];
+                let err = [c[0] - q[0] as f32, c[1] - q[1] as f32, c[2] - q[2] as f32];
+                let mut spread = |dx: isize, dy: usize, f: f32| {
+                    let nx = x as isize + dx;
+                    if nx < 0 || nx >= w as isize || y + dy >= h {
+                        return;
+                    }
+                    let p = &mut px[(y + dy) * w + nx as usize];
+                    for k in 0..3 {
+                        p[k] += err[k] * f;
+                    }
+                };
+                spread(1, 0, 7.0 / 16.0);
+                spread(-1, 1, 3.0 / 16.0);
+                spread(0, 1, 5.0 / 16.0);
+                spread(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+    out
+}
+
+/// converts rgba pixels (row by row, 4 bytes each) to a buffer
+/// of w x ceil(h / 2) cells
+pub fn rgba_to_pix(w: u16, h: u16, rgba: &[u8], mode: PaletteMode) -> Buffer {
+    let (wu, hu) = (w as usize, h as usize);
+    let q = quantize(wu, hu, rgba, mode);
+    let ch = h.div_ceil(2);
+    let mut buf = Buffer::empty(Rect::new(0, 0, w, ch));
+    for cy in 0..ch as usize {
+        for x in 0..wu {
+            let top = q[cy * 2 * wu + x];
+            let bottom = if cy * 2 + 1 < hu {
+                q[(cy * 2 + 1) * wu + x]
+            } else {
+                None
+            };
+            if top.is_none() && bottom.is_none() {
+                continue;
+            }
+            let bg = top.map(Color::Indexed).unwrap_or(Color::Reset);
+            let (sym, fg) = match bottom {
+                Some(b) => (HALF_SYM, Color::Indexed(b)),
+                // only upper pixel, fill cell with bg
+                None => (" ", Color::Reset),
+            };
+            buf.set_str(x as u16, cy as u16, sym, Style::default().fg(fg).bg(bg));
+        }
+    }
+    buf
+}
+
+#[cfg(feature = "image")]
+pub fn png_to_pix(img: &image::DynamicImage, mode: PaletteMode) -> Buffer {
+    let rgba = img.to_rgba8();
+    rgba_to_pix(rgba.width() as u16, rgba.height() as u16, rgba.as_raw(), mode)
+}
+
+/// .pix text of buffer, each cell as symbol index, fg, texture, bg
+pub fn pix_text(buf: &Buffer) -> String {
+    let mut s = format!(
+        "width={},height={},texture=255\n",
+        buf.area.width, buf.area.height
+    );
+    for row in 0..buf.area.height {
+        for col in 0..buf.area.width {
+            let cell = &buf.content[(row * buf.area.width + col) as usize];
+            let (idx, tex, fg, bg) = cell.get_cell_info();
+            s.push_str(&format!("{},{},{},{} ", idx, u8::from(fg), tex, u8::from(bg)));
+        }
+        s.push('\n');
+    }
+    s
+}
+
+/// loads a png as a sprite image, dithered by Floyd-Steinberg
+pub struct PngAsset {
+    base: AssetBase,
+}
+
+impl Asset for PngAsset {
+    fn new(ab: AssetBase) -> Self {
+        Self { base: ab }
+    }
+
+    fn get_base(&mut self) -> &mut AssetBase {
+        &mut self.base
+    }
+
+    fn parse(&mut self) {
+        self.base.parsed_buffers.clear();
+        #[cfg(feature = "image")]
+        match image::load_from_memory(&self.base.raw_data) {
+            Ok(img) => {
+                let buf = png_to_pix(&img, PaletteMode::FloydSteinberg);
+                self.base.parsed_buffers.push(buf);
+            }
+            Err(e) => info!("png decode error:{} {}", self.base.location, e),
+        }
+        #[cfg(not(feature = "image"))]
+        info!("png asset needs feature image:{}", self.base.location);
+        if self.base.parsed_buffers.is_empty() {
+            self.base
+                .parsed_buffers
+                .push(Buffer::empty(Rect::new(0, 0, 0, 0)));
+        }
+    }
+
+    fn save(&mut self, content: &Buffer) {
+        self.base.raw_data = pix_text(content).into_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgba_to_half_blocks() {
+        // 2x3: red over blue, transparent over green, white over nothing
+        let px = [
+            255, 0, 0, 255, 0, 0, 0, 0, //
+            0, 0, 255, 255, 0, 255, 0, 255, //
+            255, 255, 255, 255, 0, 0, 0, 0,
+        ];
+        let buf = rgba_to_pix(2, 3, &px, PaletteMode::Nearest);
+        assert_eq!((buf.area.width, buf.area.height), (2, 2));
+        let c = buf.get(0, 0);
+        assert_eq!((c.symbol.as_str(), c.fg, c.bg), (HALF_SYM, Color::Indexed(12), Color::Indexed(9)));
+        assert_eq!(buf.get(1, 0).bg, Color::Reset);
+        assert_eq!(buf.get(1, 0).fg, Color::Indexed(10));
+        assert_eq!(buf.get(0, 1).bg, Color::Indexed(15));
+        assert!(buf.get(1, 1).is_blank());
+        // dithered gray stays opaque
+        let gray = [128u8, 128, 128, 255].repeat(16);
+        let q = quantize(4, 4, &gray, PaletteMode::FloydSteinberg);
+        assert!(q.iter().all(|c| c.is_some()));
+    }
+}
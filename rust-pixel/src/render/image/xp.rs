@@ -0,0 +1,159 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Implements load/save of REXPaint images (.xp)
+//!
+//! xp is a gzipped file: i32 version, i32 layer count, then for each layer
+//! i32 width, i32 height and cells in column-major order, every cell is
+//! u32 CP437 code, fg rgb, bg rgb. A bg of (255, 0, 255) is transparent,
+//! upper layers are drawn over lower ones except at transparent cells.
+
+use crate::{
+    asset::{Asset, AssetBase},
+    render::buffer::Buffer,
+    render::image::ans::{cp437_byte, cp437_char},
+    render::style::{Color, Style},
+    util::Rect,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::info;
+use std::io::{Read, Write};
+
+const XP_TRANSPARENT: (u8, u8, u8) = (255, 0, 255);
+
+fn read_i32(d: &[u8], pos: &mut usize) -> Result<i32, String> {
+    let b = d.get(*pos..*pos + 4).ok_or("xp data truncated")?;
+    *pos += 4;
+    Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// decodes xp file data, all layers are merged
+pub fn xp_to_buffer(data: &[u8]) -> Result<Buffer, String> {
+    let mut d = vec![];
+    GzDecoder::new(data)
+        .read_to_end(&mut d)
+        .map_err(|e| e.to_string())?;
+    let mut pos = 0;
+    let _version = read_i32(&d, &mut pos)?;
+    let layers = read_i32(&d, &mut pos)?;
+    let mut buf = Buffer::empty(Rect::new(0, 0, 0, 0));
+    for l in 0..layers {
+        let w = read_i32(&d, &mut pos)?.max(0) as u16;
+        let h = read_i32(&d, &mut pos)?.max(0) as u16;
+        if l == 0 {
+            buf = Buffer::empty(Rect::new(0, 0, w, h));
+        }
+        let cells = d
+            .get(pos..pos + w as usize * h as usize * 10)
+            .ok_or("xp layer truncated")?;
+        pos += cells.len();
+        for (i, c) in cells.chunks_exact(10).enumerate() {
+            let (x, y) = ((i / h as usize) as u16, (i % h as usize) as u16);
+            if (c[7], c[8], c[9]) == XP_TRANSPARENT || x >= buf.area.width || y >= buf.area.height {
+                continue;
+            }
+            let code = u32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+            let sym = cp437_char(code.min(255) as u8);
+            buf.set_str(
+                x,
+                y,
+                sym.to_string(),
+                Style::default()
+                    .fg(Color::Rgba(c[4], c[5], c[6], 255))
+                    .bg(Color::Rgba(c[7], c[8], c[9], 255)),
+            );
+        }
+    }
+    Ok(buf)
+}
+
+/// encodes buffer as a one layer xp file, symbols out of CP437 become '?'
+/// and blank cells with Reset bg are transparent
+pub fn buffer_to_xp(buf: &Buffer) -> Vec<u8> {
+    let (w, h) = (buf.area.width, buf.area.height);
+    let mut d = vec![];
+    d.extend((-1i32).to_le_bytes());
+    d.extend(1i32.to_le_bytes());
+    d.extend((w as i32).to_le_bytes());
+    d.extend((h as i32).to_le_bytes());
+    for x in 0..w {
+        for y in 0..h {
+            let cell = buf.get(x, y);
+            let code = cell
+                .symbol
+                .chars()
+                .next()
+                .and_then(cp437_byte)
+                .unwrap_or(b'?');
+            d.extend((code as u32).to_le_bytes());
+            let (r, g, b, _) = match cell.fg {
+                Color::Reset => (255, 255, 255, 255),
+                c => c.get_rgba(),
+            };
+            d.extend([r, g, b]);
+            let (r, g, b) = match cell.bg {
+                Color::Reset if cell.is_blank() => XP_TRANSPARENT,
+                Color::Reset => (0, 0, 0),
+                c => {
+                    let (r, g, b, _) = c.get_rgba();
+                    (r, g, b)
+                }
+            };
+            d.extend([r, g, b]);
+        }
+    }
+    let mut e = GzEncoder::new(vec![], Compression::default());
+    let _ = e.write_all(&d);
+    e.finish().unwrap_or_default()
+}
+
+pub struct XpAsset {
+    base: AssetBase,
+}
+
+impl Asset for XpAsset {
+    fn new(ab: AssetBase) -> Self {
+        Self { base: ab }
+    }
+
+    fn get_base(&mut self) -> &mut AssetBase {
+        &mut self.base
+    }
+
+    fn parse(&mut self) {
+        self.base.parsed_buffers.clear();
+        let buf = match xp_to_buffer(&self.base.raw_data) {
+            Ok(b) => b,
+            Err(e) => {
+                info!("xp parse error:{} {}", self.base.location, e);
+                Buffer::empty(Rect::new(0, 0, 0, 0))
+            }
+        };
+        self.base.parsed_buffers.push(buf);
+    }
+
+    fn save(&mut self, content: &Buffer) {
+        self.base.raw_data = buffer_to_xp(content);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xp_roundtrip() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 3, 2));
+        let st = Style::default()
+            .fg(Color::Rgba(10, 20, 30, 255))
+            .bg(Color::Rgba(1, 2, 3, 255));
+        buf.set_str(0, 0, "@", st);
+        buf.set_str(2, 1, "▒", st);
+        let xp = buffer_to_xp(&buf);
+        let b2 = xp_to_buffer(&xp).unwrap();
+        assert_eq!(b2.area, buf.area);
+        assert_eq!(b2.get(0, 0), buf.get(0, 0));
+        assert_eq!(b2.get(2, 1), buf.get(2, 1));
+        assert!(b2.get(1, 0).is_blank());
+    }
+}
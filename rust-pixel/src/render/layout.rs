@@ -0,0 +1,295 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Layout splits an area into rows or columns by constraints
+//!
+//! Like the Layout of ratatui: Length takes a fixed size, Percentage and
+//! Ratio a part of the area, Min, Max and Fill share what is left, Fill
+//! by its weight. A LayoutNode tree names the regions with sprite tags,
+//! Panel::set_layout places the sprites in their regions again whenever
+//! the screen size changes, so no sprite needs a hard coded position.
+
+use crate::util::Rect;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// exactly n cells
+    Length(u16),
+    /// percent of the area
+    Percentage(u16),
+    /// a / b of the area
+    Ratio(u32, u32),
+    /// at least n cells, grows like Fill(1)
+    Min(u16),
+    /// at most n cells, grows like Fill(1)
+    Max(u16),
+    /// shares the space left by weight
+    Fill(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    Horizontal,
+    #[default]
+    Vertical,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Layout {
+    pub direction: Direction,
+    pub constraints: Vec<Constraint>,
+    /// cells left free around the area
+    pub margin: u16,
+    /// cells between regions
+    pub spacing: u16,
+}
+
+impl Layout {
+    pub fn new(direction: Direction, constraints: Vec<Constraint>) -> Self {
+        Self {
+            direction,
+            constraints,
+            margin: 0,
+            spacing: 0,
+        }
+    }
+
+    /// regions side by side
+    pub fn horizontal(constraints: Vec<Constraint>) -> Self {
+        Self::new(Direction::Horizontal, constraints)
+    }
+
+    /// regions one below the other
+    pub fn vertical(constraints: Vec<Constraint>) -> Self {
+        Self::new(Direction::Vertical, constraints)
+    }
+
+    pub fn margin(mut self, m: u16) -> Self {
+        self.margin = m;
+        self
+    }
+
+    pub fn spacing(mut self, s: u16) -> Self {
+        self.spacing = s;
+        self
+    }
+
+    /// a node with children for the regions, in order
+    pub fn nodes(self, children: Vec<LayoutNode>) -> LayoutNode {
+        LayoutNode::Split(self, children)
+    }
+
+    /// one region per constraint, regions that do not fit are shrunk from
+    /// the end, down to zero size
+    pub fn split(&self, area: Rect) -> Vec<Rect> {
+        let m = self.margin;
+        let inner = Rect::new(
+            area.x + m.min(area.width / 2),
+            area.y + m.min(area.height / 2),
+            area.width.saturating_sub(m * 2),
+            area.height.saturating_sub(m * 2),
+        );
+        let (start, len) = match self.direction {
+            Direction::Horizontal => (inner.x, inner.width),
+            Direction::Vertical => (inner.y, inner.height),
+        };
+        let n = self.constraints.len();
+        let gaps = (self.spacing as u32 * n.saturating_sub(1) as u32).min(len as u32) as u16;
+        let sizes = sizes(&self.constraints, len - gaps);
+
+        let mut pos = start;
+        let end = start + len;
+        sizes
+            .iter()
+            .map(|s| {
+                let s = (*s).min(end - pos);
+                let r = match self.direction {
+                    Direction::Horizontal => Rect::new(pos, inner.y, s, inner.height),
+                    Direction::Vertical => Rect::new(inner.x, pos, inner.width, s),
+                };
+                pos = (pos + s).saturating_add(self.spacing).min(end);
+                r
+            })
+            .collect()
+    }
+
+    /// cells of a grid, row by row
+    pub fn grid(area: Rect, cols: &[Constraint], rows: &[Constraint]) -> Vec<Rect> {
+        Layout::vertical(rows.to_vec())
+            .split(area)
+            .into_iter()
+            .flat_map(|r| Layout::horizontal(cols.to_vec()).split(r))
+            .collect()
+    }
+}
+
+// sizes along the direction, in total cells
+fn sizes(cs: &[Constraint], total: u16) -> Vec<u16> {
+    let t = total as u32;
+    let mut sizes: Vec<u32> = cs
+        .iter()
+        .map(|c| match *c {
+            Constraint::Length(n) | Constraint::Min(n) => n as u32,
+            Constraint::Percentage(p) => t * p.min(100) as u32 / 100,
+            Constraint::Ratio(a, b) => (t * a).checked_div(b).unwrap_or(0).min(t),
+            Constraint::Max(_) | Constraint::Fill(_) => 0,
+        })
+        .collect();
+    let fills = cs.iter().any(|c| matches!(c, Constraint::Fill(_)));
+    let weight = |c: &Constraint| match *c {
+        Constraint::Fill(w) => w as u32,
+        // with no Fill, Min grows instead
+        Constraint::Min(_) if !fills => 1,
+        Constraint::Max(_) => 1,
+        _ => 0,
+    };
+    let cap = |c: &Constraint| match *c {
+        Constraint::Max(n) => n as u32,
+        _ => u32::MAX,
+    };
+
+    // share what is left by weight, again when a Max is full
+    loop {
+        let used: u32 = sizes.iter().sum();
+        let left = t.saturating_sub(used);
+        let growers: Vec<usize> = (0..cs.len())
+            .filter(|&i| weight(&cs[i]) > 0 && sizes[i] < cap(&cs[i]))
+            .collect();
+        let wsum: u32 = growers.iter().map(|&i| weight(&cs[i])).sum();
+        if left == 0 || wsum == 0 {
+            break;
+        }
+        let mut given = 0;
+        for &i in &growers {
+            let add = (left * weight(&cs[i]) / wsum).min(cap(&cs[i]) - sizes[i]);
+            sizes[i] += add;
+            given += add;
+        }
+        // rounding leftovers, one cell each
+        for &i in &growers {
+            if given < left && sizes[i] < cap(&cs[i]) {
+                sizes[i] += 1;
+                given += 1;
+            }
+        }
+        if given == 0 {
+            break;
+        }
+    }
+    sizes.iter().map(|s| (*s).min(t) as u16).collect()
+}
+
+/// a tree of regions, leaves are sprite tags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutNode {
+    /// sprite moved to the top left of the region
+    Sprite(String),
+    /// sprite moved to the center of the region
+    Center(String),
+    /// sprite resized to the region, its content is cleared on resize
+    Fill(String),
+    Split(Layout, Vec<LayoutNode>),
+    /// region left free
+    Empty,
+}
+
+impl LayoutNode {
+    pub fn sprite(tag: &str) -> Self {
+        Self::Sprite(tag.to_string())
+    }
+
+    pub fn center(tag: &str) -> Self {
+        Self::Center(tag.to_string())
+    }
+
+    pub fn fill(tag: &str) -> Self {
+        Self::Fill(tag.to_string())
+    }
+
+    /// regions of all leaves, by tag
+    pub fn resolve(&self, area: Rect) -> HashMap<String, Rect> {
+        let mut m = HashMap::new();
+        self.walk(area, &mut |n, r| {
+            if let Some(t) = n.tag() {
+                m.insert(t.to_string(), r);
+            }
+        });
+        m
+    }
+
+    /// calls f with every leaf and its region
+    pub fn walk<F: FnMut(&LayoutNode, Rect)>(&self, area: Rect, f: &mut F) {
+        match self {
+            LayoutNode::Split(l, children) => {
+                for (c, r) in children.iter().zip(l.split(area)) {
+                    c.walk(r, f);
+                }
+            }
+            n => f(n, area),
+        }
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        match self {
+            LayoutNode::Sprite(t) | LayoutNode::Center(t) | LayoutNode::Fill(t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Constraint::*;
+
+    #[test]
+    fn layout_split_and_resolve() {
+        let a = Rect::new(0, 0, 100, 30);
+        let r = Layout::horizontal(vec![Length(10), Percentage(50), Fill(1), Fill(3)]).split(a);
+        let w: Vec<u16> = r.iter().map(|r| r.width).collect();
+        assert_eq!(w, vec![10, 50, 10, 30]);
+        assert_eq!(r[3].x, 70);
+
+        let r = Layout::vertical(vec![Length(3), Max(5), Min(2)])
+            .margin(1)
+            .spacing(1)
+            .split(a);
+        assert_eq!(
+            r,
+            vec![
+                Rect::new(1, 1, 98, 3),
+                Rect::new(1, 5, 98, 5),
+                Rect::new(1, 11, 98, 18)
+            ]
+        );
+        // too small, shrunk from the end
+        let r = Layout::vertical(vec![Length(4), Length(4)]).split(Rect::new(0, 0, 10, 6));
+        assert_eq!((r[0].height, r[1].height), (4, 2));
+        let g = Layout::grid(a, &[Fill(1); 4], &[Ratio(1, 3), Fill(1)]);
+        assert_eq!(g[5], Rect::new(25, 10, 25, 20));
+
+        let tree = Layout::vertical(vec![Length(3), Fill(1)]).nodes(vec![
+            LayoutNode::center("TITLE"),
+            Layout::horizontal(vec![Fill(1), Fill(1)])
+                .nodes(vec![LayoutNode::Empty, LayoutNode::fill("INFO")]),
+        ]);
+        let m = tree.resolve(a);
+        assert_eq!(m.len(), 2);
+        assert_eq!(m["INFO"], Rect::new(50, 3, 50, 27));
+
+        // 180x80 panel
+        let mut panel = crate::render::panel::Panel::new();
+        panel.add_sprite(crate::render::sprite::Sprite::new(0, 0, 10, 1), "TITLE");
+        panel.add_sprite(crate::render::sprite::Sprite::new(0, 0, 4, 4), "INFO");
+        panel.set_layout(tree);
+        assert_eq!(
+            panel.get_sprite("TITLE").content.area,
+            Rect::new(85, 1, 10, 1)
+        );
+        assert_eq!(
+            panel.get_sprite("INFO").content.area,
+            Rect::new(90, 3, 90, 77)
+        );
+    }
+}
@@ -0,0 +1,422 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Light computes per cell illumination of a grid from point and cone
+//! lights, walls stop the light by symmetric shadowcasting.
+//! The same shadowcasting gives the field of view of a roguelike player:
+//! with a viewer set, cells out of sight are dark, cells seen before are
+//! kept dim.
+//!
+//! Set a LightMap to the panel and it modulates the fg and bg colors of
+//! the composed buffer each frame, before the debug overlay is drawn.
+//! Positions are world cells, the panel camera is applied. Reset colors
+//! are kept, as the terminal default color can not be darkened.
+//! Refer:
+//!   https://www.albertford.com/shadowcasting/
+
+use crate::render::{buffer::Buffer, style::Color};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightShape {
+    Point,
+    /// dir and half angle of the cone in radians, 0.0 points to +x
+    Cone {
+        dir: f32,
+        spread: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub color: Color,
+    pub intensity: f32,
+    pub shape: LightShape,
+    /// stopped by opaque cells
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    pub fn point(x: f32, y: f32, radius: f32, color: Color) -> Self {
+        Self {
+            x,
+            y,
+            radius,
+            color,
+            intensity: 1.0,
+            shape: LightShape::Point,
+            cast_shadows: true,
+        }
+    }
+
+    pub fn cone(x: f32, y: f32, radius: f32, color: Color, dir: f32, spread: f32) -> Self {
+        Self {
+            shape: LightShape::Cone { dir, spread },
+            ..Self::point(x, y, radius, color)
+        }
+    }
+
+    /// light reaching a cell, quadratic falloff to 0.0 at radius
+    pub fn falloff(&self, cx: i32, cy: i32) -> f32 {
+        let (dx, dy) = (cx as f32 - self.x, cy as f32 - self.y);
+        let d = (dx * dx + dy * dy).sqrt();
+        if d > self.radius {
+            return 0.0;
+        }
+        if let LightShape::Cone { dir, spread } = self.shape {
+            if d >= 0.5 {
+                let a = dy.atan2(dx) - dir;
+                // angle difference in -PI..PI
+                let a = (a + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+                    - std::f32::consts::PI;
+                if a.abs() > spread {
+                    return 0.0;
+                }
+            }
+        }
+        let f = 1.0 - d / self.radius.max(0.001);
+        f * f * self.intensity
+    }
+}
+
+// slope as a fraction num / den, den > 0
+#[derive(Clone, Copy)]
+struct Slope(i32, i32);
+
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    fn cols(&self) -> std::ops::RangeInclusive<i32> {
+        // round half up of depth * start, round half down of depth * end
+        let (s, e) = (self.start, self.end);
+        let min = (2 * self.depth * s.0 + s.1).div_euclid(2 * s.1);
+        let max = -(e.1 - 2 * self.depth * e.0).div_euclid(2 * e.1);
+        min..=max
+    }
+
+    // col lies in the row, both ends included
+    fn is_symmetric(&self, col: i32) -> bool {
+        col * self.start.1 >= self.depth * self.start.0
+            && col * self.end.1 <= self.depth * self.end.0
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+/// symmetric shadowcasting from (ox, oy), calls visible for every cell in
+/// sight within radius, opaque cells in sight included. a sees b if and
+/// only if b sees a
+pub fn shadowcast<O, V>(ox: i32, oy: i32, radius: i32, is_opaque: O, mut visible: V)
+where
+    O: Fn(i32, i32) -> bool,
+    V: FnMut(i32, i32),
+{
+    visible(ox, oy);
+    for quadrant in 0..4 {
+        let transform = |depth: i32, col: i32| match quadrant {
+            0 => (ox + col, oy - depth),
+            1 => (ox + depth, oy + col),
+            2 => (ox + col, oy + depth),
+            _ => (ox - depth, oy + col),
+        };
+        let mut rows = vec![Row {
+            depth: 1,
+            start: Slope(-1, 1),
+            end: Slope(1, 1),
+        }];
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+            let mut prev: Option<bool> = None;
+            for col in row.cols() {
+                let (x, y) = transform(row.depth, col);
+                let wall = is_opaque(x, y);
+                let in_range = row.depth * row.depth + col * col <= radius * radius;
+                if in_range && (wall || row.is_symmetric(col)) {
+                    visible(x, y);
+                }
+                let slope = Slope(2 * col - 1, 2 * row.depth);
+                if prev == Some(true) && !wall {
+                    row.start = slope;
+                }
+                if prev == Some(false) && wall {
+                    let mut next = row.next();
+                    next.end = slope;
+                    rows.push(next);
+                }
+                prev = Some(wall);
+            }
+            if prev == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+}
+
+/// cells in sight from (ox, oy) of a width * height grid, row by row
+pub fn field_of_view(
+    width: u16,
+    height: u16,
+    opaque: &[bool],
+    ox: i32,
+    oy: i32,
+    radius: i32,
+) -> Vec<bool> {
+    let (w, h) = (width as i32, height as i32);
+    let mut seen = vec![false; opaque.len()];
+    let inside = |x: i32, y: i32| x >= 0 && y >= 0 && x < w && y < h;
+    shadowcast(
+        ox,
+        oy,
+        radius,
+        // outside of the grid blocks the view
+        |x, y| !inside(x, y) || opaque[(y * w + x) as usize],
+        |x, y| {
+            if inside(x, y) {
+                seen[(y * w + x) as usize] = true;
+            }
+        },
+    );
+    seen
+}
+
+/// light levels of a grid, see the module doc
+#[derive(Debug, Clone)]
+pub struct LightMap {
+    pub width: u16,
+    pub height: u16,
+    /// light of every cell in sight
+    pub ambient: Color,
+    pub lights: Vec<Light>,
+    /// factor of cells seen before but out of sight now
+    pub memory: f32,
+    opaque: Vec<bool>,
+    // viewer x, y and sight radius
+    viewer: Option<(i32, i32, i32)>,
+    explored: Vec<bool>,
+    visible: Vec<bool>,
+    // rgb light per cell, 1.0 keeps the color
+    levels: Vec<[f32; 3]>,
+}
+
+impl LightMap {
+    pub fn new(width: u16, height: u16) -> Self {
+        let size = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            ambient: Color::Rgba(0, 0, 0, 255),
+            lights: vec![],
+            memory: 0.3,
+            opaque: vec![false; size],
+            viewer: None,
+            explored: vec![false; size],
+            visible: vec![true; size],
+            levels: vec![[1.0; 3]; size],
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        Some((y * self.width as i32 + x) as usize)
+    }
+
+    pub fn set_opaque(&mut self, x: u16, y: u16, opaque: bool) {
+        if let Some(i) = self.index(x as i32, y as i32) {
+            self.opaque[i] = opaque;
+        }
+    }
+
+    pub fn is_opaque(&self, x: i32, y: i32) -> bool {
+        match self.index(x, y) {
+            Some(i) => self.opaque[i],
+            None => true,
+        }
+    }
+
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    /// viewer x, y and sight radius, None sees everything
+    pub fn set_viewer(&mut self, viewer: Option<(i32, i32, i32)>) {
+        self.viewer = viewer;
+    }
+
+    pub fn is_visible(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).is_some_and(|i| self.visible[i])
+    }
+
+    pub fn is_explored(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).is_some_and(|i| self.explored[i])
+    }
+
+    /// forgets the explored cells, e.g. on a new level
+    pub fn clear_explored(&mut self) {
+        self.explored.iter_mut().for_each(|e| *e = false);
+    }
+
+    /// rgb light of a cell
+    pub fn level(&self, x: i32, y: i32) -> [f32; 3] {
+        self.index(x, y).map_or([0.0; 3], |i| self.levels[i])
+    }
+
+    /// updates sight and light levels, call after moving lights or walls
+    pub fn compute(&mut self) {
+        match self.viewer {
+            Some((vx, vy, r)) => {
+                self.visible = field_of_view(self.width, self.height, &self.opaque, vx, vy, r);
+                for (e, v) in self.explored.iter_mut().zip(&self.visible) {
+                    *e |= *v;
+                }
+            }
+            None => self.visible.iter_mut().for_each(|v| *v = true),
+        }
+        let (ar, ag, ab, _) = self.ambient.get_rgba();
+        let ambient = [ar as f32 / 255.0, ag as f32 / 255.0, ab as f32 / 255.0];
+        self.levels.iter_mut().for_each(|l| *l = ambient);
+        let (w, h) = (self.width as i32, self.height as i32);
+        for light in &self.lights {
+            let (r, g, b, _) = light.color.get_rgba();
+            let color = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0];
+            let (lx, ly) = (light.x.round() as i32, light.y.round() as i32);
+            let radius = light.radius.ceil() as i32;
+            let mut lit = vec![];
+            if light.cast_shadows {
+                let opaque = &self.opaque;
+                shadowcast(
+                    lx,
+                    ly,
+                    radius,
+                    |x, y| x < 0 || y < 0 || x >= w || y >= h || opaque[(y * w + x) as usize],
+                    |x, y| lit.push((x, y)),
+                );
+            } else {
+                for y in ly - radius..=ly + radius {
+                    for x in lx - radius..=lx + radius {
+                        lit.push((x, y));
+                    }
+                }
+            }
+            for (x, y) in lit {
+                if x < 0 || y < 0 || x >= w || y >= h {
+                    continue;
+                }
+                let f = light.falloff(x, y);
+                for (l, c) in self.levels[(y * w + x) as usize].iter_mut().zip(color) {
+                    *l += c * f;
+                }
+            }
+        }
+        for i in 0..self.levels.len() {
+            if !self.visible[i] {
+                let m = if self.explored[i] { self.memory } else { 0.0 };
+                self.levels[i] = [m; 3];
+            }
+        }
+    }
+
+    /// modulates fg and bg of the buffer cells, world x is buffer x + ox
+    pub fn apply(&self, buf: &mut Buffer, ox: i32, oy: i32) {
+        let area = buf.area;
+        for (i, cell) in buf.content.iter_mut().enumerate() {
+            let x = (i % area.width as usize) as i32 + ox;
+            let y = (i / area.width as usize) as i32 + oy;
+            let l = self.level(x, y);
+            let (r, g, b) = (to_u8(l[0]), to_u8(l[1]), to_u8(l[2]));
+            if (r, g, b) == (255, 255, 255) {
+                continue;
+            }
+            let tint = Color::Rgba(r, g, b, 255);
+            for c in [&mut cell.fg, &mut cell.bg] {
+                if *c != Color::Reset {
+                    *c = c.tint(tint);
+                }
+            }
+        }
+    }
+}
+
+fn to_u8(l: f32) -> u8 {
+    (l.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render::style::Style, util::Rect};
+
+    #[test]
+    fn fov_and_light_levels() {
+        // a wall at x=5 from y=0 to y=6
+        let (w, h) = (12u16, 9u16);
+        let mut opaque = vec![false; (w * h) as usize];
+        for y in 0..7 {
+            opaque[(y * w + 5) as usize] = true;
+        }
+        let fov = field_of_view(w, h, &opaque, 2, 3, 20);
+        let seen = |x: u16, y: u16| fov[(y * w + x) as usize];
+        assert!(seen(2, 3) && seen(5, 3) && seen(0, 0));
+        assert!(!seen(8, 3) && !seen(11, 0));
+        assert!(seen(5, 7) && seen(2, 8));
+        // symmetric: each seen cell sees the origin back
+        for y in 0..h {
+            for x in 0..w {
+                if seen(x, y) && !opaque[(y * w + x) as usize] {
+                    let back = field_of_view(w, h, &opaque, x as i32, y as i32, 20);
+                    assert!(back[(3 * w + 2) as usize], "{} {}", x, y);
+                }
+            }
+        }
+        assert!(!field_of_view(w, h, &opaque, 2, 3, 2)[(8 * w + 2) as usize]);
+
+        let mut lm = LightMap::new(w, h);
+        for y in 0..7 {
+            lm.set_opaque(5, y, true);
+        }
+        lm.add_light(Light::point(2.0, 3.0, 6.0, Color::Rgba(255, 255, 255, 255)));
+        lm.compute();
+        assert!(lm.level(2, 3)[0] >= 1.0);
+        assert!(lm.level(3, 3)[0] > lm.level(4, 3)[0]);
+        assert_eq!(lm.level(7, 3), [0.0; 3]);
+
+        // a cone lights only its side
+        let mut cone = LightMap::new(w, h);
+        cone.add_light(Light::cone(2.0, 3.0, 6.0, Color::White, 0.0, 0.5));
+        cone.compute();
+        assert!(cone.level(4, 3)[0] > 0.0);
+        assert_eq!(cone.level(0, 3), [0.0; 3]);
+
+        lm.set_viewer(Some((2, 3, 20)));
+        lm.compute();
+        lm.set_viewer(Some((8, 8, 1)));
+        lm.compute();
+        assert!(lm.is_visible(8, 8) && !lm.is_visible(2, 3));
+        assert!(lm.is_explored(2, 3) && !lm.is_explored(8, 3));
+        assert_eq!(lm.level(2, 3), [lm.memory; 3]);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, w, h));
+        let gray = Color::Rgba(200, 200, 200, 255);
+        buf.set_str(0, 3, "ab", Style::default().fg(gray).bg(Color::Reset));
+        lm.apply(&mut buf, 0, 0);
+        let (r, _, _, _) = buf.get(0, 3).fg.get_rgba();
+        // 200 * 77 / 255
+        assert_eq!(r, 60);
+        assert_eq!(buf.get(0, 3).bg, Color::Reset);
+    }
+}
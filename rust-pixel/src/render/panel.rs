@@ -23,19 +23,37 @@
 
 use crate::{
     context::Context,
+    event::event_emit_data,
+    game::TransitionPhase,
     render::{
+        adapter::{gl::transform::GlTransform, WINDOW_RESIZED},
         buffer::Buffer,
-        sprite::{Sprite, Sprites},
+        camera::Camera,
+        layout::LayoutNode,
+        light::LightMap,
+        sprite::{Sprite, Sprites, Particles},
+        style::{simulate_color, BlendMode, Color, ColorBlindness, Modifier, Style, Theme},
+        tilemap::TileMap,
+        transition::apply_transition,
+        tween::{TweenBuilder, Tweens},
     },
     util::{
         objpool::{GObj, GameObjPool, GameObject},
-        Rect,
+        profile_scope, Rect,
     },
     LOGO_FRAME,
 };
+#[cfg(feature = "ttf")]
+use crate::render::adapter::gl::color::GlColor;
+#[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+use crate::render::sprite::particle_color;
 use log::info;
 use std::{collections::HashMap, io};
-use std::cmp::Reverse;
+
+// layer of the DebugHud overlay
+const DEBUG_LAYER: &str = "pixel_debug";
+// layer of the drop-down Console
+const CONSOLE_LAYER: &str = "pixel_console";
 
 pub struct Panel {
     pub buffers: [Buffer; 2],
@@ -45,6 +63,29 @@ pub struct Panel {
 
     // layer index, render weight...
     pub render_index: Vec<(usize, i32)>,
+
+    // transforms applied to all sprites in graphics mode
+    // camera effects, screen shake...
+    pub transform_stack: Vec<GlTransform>,
+
+    // world view, applied to layers following the camera...
+    pub camera: Option<Camera>,
+
+    // lighting and field of view, applied after composition
+    pub light: Option<LightMap>,
+
+    // running sprite tweens, updated by update_tweens
+    pub tweens: Tweens,
+
+    // draws a loading bar instead of layers until all assets are loaded
+    pub loading_screen: bool,
+
+    // places sprites by tag, again when the screen size changes
+    layout: Option<LayoutNode>,
+    layout_area: Rect,
+
+    // ctx.theme generation the sprites are styled with
+    theme_generation: u32,
 }
 
 #[allow(unused)]
@@ -78,6 +119,14 @@ impl Panel {
             layer_tag_index,
             layers,
             render_index: vec![],
+            transform_stack: vec![],
+            camera: None,
+            light: None,
+            tweens: Tweens::default(),
+            loading_screen: false,
+            layout: None,
+            layout_area: Rect::default(),
+            theme_generation: 0,
         }
     }
 
@@ -88,6 +137,15 @@ impl Panel {
         info!("panel init size...{:?}", size);
     }
 
+    /// resizes and clears both buffers, sprites in the layout are placed
+    /// again on the next draw
+    pub fn resize(&mut self, width: u16, height: u16) {
+        for b in self.buffers.iter_mut() {
+            b.resize(Rect::new(0, 0, width, height));
+            b.reset();
+        }
+    }
+
     pub fn current_buffer_mut(&mut self) -> &mut Buffer {
         &mut self.buffers[self.current]
     }
@@ -101,6 +159,7 @@ impl Panel {
         self.layers.push(sps);
         self.layer_tag_index
             .insert(name.to_string(), self.layers.len() - 1);
+        self.render_index.clear();
     }
 
     pub fn add_layer(&mut self, name: &str) {
@@ -116,6 +175,17 @@ impl Panel {
         self.layers[*idx].add_by_tag(sp, tag);
     }
 
+    /// add sprite to a named layer with z order, bigger z is upper, z >= 1
+    pub fn add_sprite_to_layer(&mut self, mut sp: Sprite, layer_name: &str, tag: &str, z: i32) {
+        sp.set_render_weight(z);
+        self.add_layer_sprite(sp, layer_name, tag);
+    }
+
+    pub fn set_sprite_z(&mut self, layer_name: &str, tag: &str, z: i32) {
+        let idx = self.layer_tag_index.get(layer_name).unwrap();
+        self.layers[*idx].set_weight_by_tag(tag, z);
+    }
+
     pub fn get_layer_sprite(&mut self, layer_name: &str, tag: &str) -> &mut Sprite {
         let idx = self.layer_tag_index.get(layer_name).unwrap();
         self.layers[*idx].get_by_tag(tag)
@@ -127,6 +197,31 @@ impl Panel {
         self.render_index.clear();
     }
 
+    pub fn show_layer(&mut self, layer_name: &str) {
+        self.active_layer(layer_name);
+    }
+
+    pub fn hide_layer(&mut self, layer_name: &str) {
+        self.deactive_layer(layer_name);
+    }
+
+    pub fn is_layer_hidden(&self, layer_name: &str) -> bool {
+        let idx = self.layer_tag_index.get(layer_name).unwrap();
+        self.layers[*idx].is_hidden
+    }
+
+    /// a modal layer dims all layers below it, e.g. a dialog
+    pub fn set_layer_modal(&mut self, layer_name: &str, modal: bool) {
+        let idx = self.layer_tag_index.get(layer_name).unwrap();
+        self.layers[*idx].is_modal = modal;
+    }
+
+    /// a fixed layer is not moved by the camera, e.g. a hud or menu
+    pub fn set_layer_fixed(&mut self, layer_name: &str, fixed: bool) {
+        let idx = self.layer_tag_index.get(layer_name).unwrap();
+        self.layers[*idx].follow_camera = !fixed;
+    }
+
     pub fn deactive_layer(&mut self, layer_name: &str) {
         let idx = self.layer_tag_index.get(layer_name).unwrap();
         self.layers[*idx].deactive();
@@ -145,6 +240,16 @@ impl Panel {
         self.layers[0].get_by_tag(tag)
     }
 
+    /// attach child sprite to parent sprite in main layer, (x, y) relative to parent
+    pub fn attach_sprite(&mut self, child: &str, parent: &str, x: i32, y: i32) {
+        self.layers[0].attach(child, parent, x, y);
+    }
+
+    /// attach child sprite to parent sprite in pixel layer, (x, y) in pixels
+    pub fn attach_pixel_sprite(&mut self, child: &str, parent: &str, x: i32, y: i32) {
+        self.layers[1].attach(child, parent, x, y);
+    }
+
     pub fn add_pixel_sprite(&mut self, sp: Sprite, tag: &str) {
         self.layers[1].add_by_tag(sp, tag);
     }
@@ -153,6 +258,146 @@ impl Panel {
         self.layers[1].get_by_tag(tag)
     }
 
+    /// push a transform, the composition of all pushed transforms is applied
+    /// to main buffer and pixel sprites when drawing in graphics mode.
+    /// Coordinates are screen pixels with y down.
+    /// Text mode ignores the transform stack.
+    pub fn push_transform(&mut self, t: GlTransform) {
+        self.transform_stack.push(t);
+    }
+
+    pub fn pop_transform(&mut self) -> Option<GlTransform> {
+        self.transform_stack.pop()
+    }
+
+    /// composition of the transform stack, first pushed is outermost
+    pub fn get_transform(&self) -> GlTransform {
+        let mut t = GlTransform::new();
+        for ts in &self.transform_stack {
+            t.multiply(ts);
+        }
+        t
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = Some(camera);
+    }
+
+    pub fn get_camera(&mut self) -> Option<&mut Camera> {
+        self.camera.as_mut()
+    }
+
+    pub fn set_light(&mut self, light: LightMap) {
+        self.light = Some(light);
+    }
+
+    pub fn get_light(&mut self) -> Option<&mut LightMap> {
+        self.light.as_mut()
+    }
+
+    /// load a TrueType font for graphics mode, px is the font size in pixels
+    #[cfg(feature = "ttf")]
+    pub fn load_ttf(&mut self, ctx: &mut Context, path: &str, px: f32) -> Result<(), String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let bs = ctx.adapter.get_base();
+        match (&mut bs.gl_pixel, &bs.gl) {
+            (Some(pix), Some(gl)) => pix.load_font(gl, &data, px),
+            _ => Err("gl not ready".to_string()),
+        }
+    }
+
+    /// draw ttf text this frame at pixel (x, y), y is the baseline
+    /// call it every frame like other immediate drawing
+    #[cfg(feature = "ttf")]
+    pub fn draw_ttf_text(&mut self, ctx: &mut Context, text: &str, x: f32, y: f32, color: Color) {
+        let (r, g, b, a) = color.get_rgba();
+        if let Some(pix) = &mut ctx.adapter.get_base().gl_pixel {
+            pix.queue_text(
+                text,
+                x,
+                y,
+                GlColor::new(
+                    r as f32 / 255.0,
+                    g as f32 / 255.0,
+                    b as f32 / 255.0,
+                    a as f32 / 255.0,
+                ),
+            );
+        }
+    }
+
+    /// tween a sprite in main layer, steps are chained
+    /// places the sprites named in layout now and whenever the screen
+    /// size changes, tags are looked up in all layers
+    pub fn set_layout(&mut self, layout: LayoutNode) {
+        self.layout = Some(layout);
+        self.layout_area = Rect::default();
+        self.apply_layout();
+    }
+
+    pub fn clear_layout(&mut self) {
+        self.layout = None;
+    }
+
+    /// region of a tag in the current layout
+    pub fn layout_region(&self, tag: &str) -> Option<Rect> {
+        self.layout
+            .as_ref()?
+            .resolve(self.buffers[self.current].area)
+            .remove(tag)
+    }
+
+    fn apply_layout(&mut self) {
+        let area = self.buffers[self.current].area;
+        if area == self.layout_area {
+            return;
+        }
+        self.layout_area = area;
+        let Some(layout) = self.layout.take() else {
+            return;
+        };
+        layout.walk(area, &mut |node, r| {
+            let Some(tag) = node.tag() else {
+                return;
+            };
+            let Some(l) = self.layers.iter_mut().find(|l| l.tag_index.contains_key(tag)) else {
+                return;
+            };
+            let sp = l.get_by_tag(tag);
+            let a = sp.content.area;
+            match node {
+                LayoutNode::Center(_) => sp.set_pos(
+                    r.x + r.width.saturating_sub(a.width) / 2,
+                    r.y + r.height.saturating_sub(a.height) / 2,
+                ),
+                LayoutNode::Fill(_) if a.width != r.width || a.height != r.height => {
+                    sp.content.resize(r);
+                    sp.content.reset();
+                }
+                _ => sp.set_pos(r.x, r.y),
+            }
+        });
+        self.layout = Some(layout);
+    }
+
+    pub fn tween(&mut self, tag: &str) -> TweenBuilder<'_> {
+        TweenBuilder::new(self.tweens.get(0, tag))
+    }
+
+    pub fn tween_layer(&mut self, layer_name: &str, tag: &str) -> TweenBuilder<'_> {
+        let idx = *self.layer_tag_index.get(layer_name).unwrap();
+        TweenBuilder::new(self.tweens.get(idx, tag))
+    }
+
+    pub fn stop_tween(&mut self, tag: &str) {
+        self.tweens.stop(0, tag);
+    }
+
+    /// call once per frame in Render::update
+    pub fn update_tweens(&mut self, dt: f32) {
+        self.tweens.update(&mut self.layers, dt);
+    }
+
     pub fn reset(&mut self, ctx: &mut Context) {
         ctx.adapter.reset();
     }
@@ -162,26 +407,115 @@ impl Panel {
             for (i, s) in self.layers.iter().enumerate() {
                 self.render_index.push((i, s.render_weight));
             }
-            self.render_index.sort_by_key(|d| Reverse(d.1));
+            // bigger weight is rendered later (upper level)
+            self.render_index.sort_by_key(|d| d.1);
+        }
+    }
+
+    /// replaces colors of from theme by the same names of to theme
+    /// in all sprites, done by draw when ctx.theme switches
+    pub fn restyle(&mut self, from: &Theme, to: &Theme) {
+        let pairs = from.remap(to);
+        if pairs.is_empty() {
+            return;
+        }
+        let map = |c: &mut Color| {
+            if let Some(p) = pairs.iter().find(|p| p.0 == *c) {
+                *c = p.1;
+            }
+        };
+        for l in self.layers.iter_mut() {
+            for sp in l.sprites.iter_mut() {
+                for cell in sp.content.content.iter_mut() {
+                    map(&mut cell.fg);
+                    map(&mut cell.bg);
+                }
+            }
         }
     }
 
     pub fn draw(&mut self, ctx: &mut Context) -> io::Result<()> {
-        if ctx.stage > LOGO_FRAME {
+        if let Some(ws) = ctx.adapter.get_base().resized.take() {
+            info!("panel resize...{:?}", ws);
+            // a graphics window keeps its cells, no need to clear
+            let a = self.buffers[self.current].area;
+            if (a.width, a.height) != (ws.width, ws.height) {
+                self.resize(ws.width, ws.height);
+            }
+            event_emit_data(WINDOW_RESIZED, &ws);
+        }
+        if self.theme_generation != ctx.theme.generation() {
+            self.theme_generation = ctx.theme.generation();
+            if let Some(prev) = ctx.theme.previous() {
+                self.restyle(prev, ctx.theme.current());
+            }
+        }
+        let offset = match &self.camera {
+            Some(c) => c.cell_offset(),
+            None => (0, 0),
+        };
+        let view = match &self.camera {
+            Some(c) => c.gl_transform(ctx.adapter.cell_width(), ctx.adapter.cell_height()),
+            None => GlTransform::new(),
+        };
+        for l in self.layers.iter_mut() {
+            (l.camera_offset, l.camera_view) = if l.follow_camera {
+                (offset, view)
+            } else {
+                ((0, 0), GlTransform::new())
+            };
+        }
+        self.apply_layout();
+        self.sync_focus(ctx, offset);
+        self.update_debug_layer(ctx);
+        self.update_console_layer(ctx);
+        if ctx.stage > LOGO_FRAME && self.loading_screen && !ctx.asset_manager.is_loaded() {
+            let (done, total) = ctx.asset_manager.progress();
+            draw_loading_bar(&mut self.buffers[self.current], done, total);
+        } else if ctx.stage > LOGO_FRAME {
+            let _p = profile_scope("compose");
             self.update_render_index();
-            for idx in &self.render_index {
-                if !self.layers[idx.0].is_hidden {
-                    self.layers[idx.0]
+            let mut lit = self.light.is_none();
+            for n in 0..self.render_index.len() {
+                let idx = self.render_index[n].0;
+                if !self.layers[idx].is_hidden {
+                    // lights the world, not the debug overlay
+                    if !lit && self.layers[idx].name == DEBUG_LAYER {
+                        self.apply_light(offset);
+                        lit = true;
+                    }
+                    if self.layers[idx].is_modal {
+                        dim_buffer(&mut self.buffers[self.current]);
+                    }
+                    self.layers[idx]
                         .render_all_to_buffer(&mut ctx.asset_manager, &mut self.buffers[self.current]);
                 }
             }
+            if !lit {
+                self.apply_light(offset);
+            }
+            self.draw_focus(ctx);
+            self.apply_scene_effect(ctx);
+        }
+        #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+        if let Some(pix) = &mut ctx.adapter.get_base().gl_pixel {
+            pix.set_view_transform(&self.get_transform());
+        }
+        if let Some(cvd) = ctx.debug.colorblind {
+            simulate_buffer(&mut self.buffers[self.current], cvd);
         }
         let cb = &self.buffers[self.current];
         let pb = &self.buffers[1 - self.current];
+        let p = profile_scope("present");
         ctx.adapter
             .draw_all_to_screen(cb, pb, &mut self.layers, ctx.stage)
             .unwrap();
+        drop(p);
         ctx.adapter.hide_cursor().unwrap();
+        #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+        if let Some(pix) = &mut ctx.adapter.get_base().gl_pixel {
+            (ctx.debug.draw_calls, ctx.debug.instances) = pix.take_stats();
+        }
 
         // Swap buffers
         if ctx.stage > LOGO_FRAME {
@@ -192,6 +526,190 @@ impl Panel {
         Ok(())
     }
 
+    // keeps frames of the old scene, blends the last into the new one
+    fn apply_scene_effect(&mut self, ctx: &mut Context) {
+        let sc = &mut ctx.scene;
+        let (Some(tr), Some(kind)) = (sc.transition, sc.effect) else {
+            sc.from_frame = None;
+            return;
+        };
+        let buf = &mut self.buffers[self.current];
+        match tr.phase {
+            TransitionPhase::Out => sc.from_frame = Some(buf.clone()),
+            TransitionPhase::In => {
+                if let Some(from) = &sc.from_frame {
+                    let to = buf.clone();
+                    apply_transition(kind, tr.progress, 0, from, &to, buf);
+                }
+            }
+        }
+    }
+
+    fn apply_light(&mut self, offset: (i32, i32)) {
+        if let Some(lm) = &self.light {
+            lm.apply(&mut self.buffers[self.current], offset.0, offset.1);
+        }
+    }
+
+    // focusable sprite tags of main layer get their screen areas,
+    // hidden ones an empty area so the focus skips them
+    fn sync_focus(&self, ctx: &mut Context, offset: (i32, i32)) {
+        let l = &self.layers[0];
+        let ids: Vec<String> = ctx
+            .focus
+            .ids()
+            .filter(|id| l.tag_index.contains_key(*id))
+            .map(|id| id.to_string())
+            .collect();
+        for id in ids {
+            let sp = &l.sprites[l.tag_index[&id]];
+            let a = sp.content.area;
+            let area = if sp.is_hidden() {
+                Rect::default()
+            } else {
+                Rect::new(
+                    (a.x as i32 - offset.0).max(0) as u16,
+                    (a.y as i32 - offset.1).max(0) as u16,
+                    a.width,
+                    a.height,
+                )
+            };
+            ctx.focus.add(&id, area);
+        }
+    }
+
+    // highlights the focused sprite
+    fn draw_focus(&mut self, ctx: &Context) {
+        let Some(id) = ctx.focus.focused() else {
+            return;
+        };
+        if !self.layers[0].tag_index.contains_key(id) {
+            return;
+        }
+        let buf = &mut self.buffers[self.current];
+        let a = ctx.focus.area(id).unwrap_or_default();
+        if a.area() == 0 || !a.intersects(buf.area) {
+            return;
+        }
+        let st = ctx
+            .focus
+            .style
+            .unwrap_or_else(|| Style::default().bg(ctx.theme.get("ui.selected")));
+        buf.set_style(a.intersection(buf.area), st);
+    }
+
+    // DebugHud text in a top layer, not moved by the camera
+    fn update_debug_layer(&mut self, ctx: &Context) {
+        let has = self.layer_tag_index.contains_key(DEBUG_LAYER);
+        if !ctx.debug.visible {
+            if has {
+                self.hide_layer(DEBUG_LAYER);
+            }
+            return;
+        }
+        let lines = ctx.debug.lines();
+        let w = lines.iter().map(|l| l.len()).max().unwrap_or(0) as u16;
+        let h = lines.len() as u16;
+        if !has {
+            self.add_layer(DEBUG_LAYER);
+            self.set_layer_weight(DEBUG_LAYER, i32::MAX);
+            self.set_layer_fixed(DEBUG_LAYER, true);
+            self.add_layer_sprite(Sprite::new(0, 0, w, h), DEBUG_LAYER, "hud");
+        }
+        self.show_layer(DEBUG_LAYER);
+        let idx = self.layer_tag_index[DEBUG_LAYER];
+        let sp = self.layers[idx].get_by_tag("hud");
+        if sp.content.area.width != w || sp.content.area.height != h {
+            *sp = Sprite::new(0, 0, w, h);
+        }
+        sp.content.reset();
+        let style = Style::default()
+            .fg(crate::render::style::Color::Yellow)
+            .bg(crate::render::style::Color::Black);
+        for (i, l) in lines.iter().enumerate() {
+            sp.content.set_str(0, i as u16, l, style);
+        }
+    }
+
+    // Console at the top of the screen, full width, not moved by the camera
+    fn update_console_layer(&mut self, ctx: &mut Context) {
+        let has = self.layer_tag_index.contains_key(CONSOLE_LAYER);
+        if !ctx.console.visible {
+            if has {
+                self.hide_layer(CONSOLE_LAYER);
+            }
+            return;
+        }
+        let a = self.buffers[self.current].area;
+        let (w, h) = (a.width, ctx.console.height.min(a.height));
+        if !has {
+            self.add_layer(CONSOLE_LAYER);
+            self.set_layer_weight(CONSOLE_LAYER, i32::MAX - 2);
+            self.set_layer_fixed(CONSOLE_LAYER, true);
+            self.add_layer_sprite(Sprite::new(0, 0, w, h), CONSOLE_LAYER, "console");
+        }
+        self.show_layer(CONSOLE_LAYER);
+        let idx = self.layer_tag_index[CONSOLE_LAYER];
+        let sp = self.layers[idx].get_by_tag("console");
+        if sp.content.area.width != w || sp.content.area.height != h {
+            *sp = Sprite::new(0, 0, w, h);
+        }
+        sp.content.reset();
+        ctx.console.draw(&mut sp.content, ctx.theme.current());
+    }
+
+    /// shows a loading bar while assets are loading, e.g. on web
+    pub fn set_loading_screen(&mut self, on: bool) {
+        self.loading_screen = on;
+    }
+
+    /// draws the visible part of a tilemap into sprite tag of main layer
+    pub fn draw_tilemap(&mut self, tm: &TileMap, tag: &str) {
+        let sp = self.layers[0].get_by_tag(tag);
+        tm.render_to_sprite(sp, self.camera.as_ref());
+    }
+
+    /// creates sprites to draw particles, a text sprite covering the panel
+    /// in text mode, or a pool of pixel sprites in graphics mode
+    pub fn add_particles(&mut self, ps: &Particles) {
+        #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
+        {
+            let a = self.buffers[0].area;
+            self.add_sprite(
+                Sprite::new(0, 0, a.width, a.height),
+                &ps.system.particles.prefix,
+            );
+        }
+        #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+        {
+            let (tex, sym) = ps.graph_sym;
+            self.creat_objpool_sprites(&ps.system.particles, 1, 1, |bl| {
+                bl.set_graph_sym(0, 0, tex, sym, Color::White);
+            });
+        }
+    }
+
+    /// draws alive particles, call after ps.update
+    pub fn draw_particles(&mut self, ps: &mut Particles) {
+        #[cfg(not(any(feature = "sdl", target_arch = "wasm32")))]
+        {
+            let sp = self.get_sprite(&ps.system.particles.prefix);
+            sp.content.reset();
+            ps.render_to(&mut sp.content);
+        }
+        #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+        {
+            let (tex, sym) = ps.graph_sym;
+            let colors = ps.colors.as_ref();
+            self.draw_objpool(&mut ps.system.particles, |pl, m| {
+                let (c, a) = particle_color(colors, &m.obj);
+                pl.set_graph_sym(0, 0, tex, sym, c);
+                pl.set_alpha(a);
+                pl.set_pos(m.obj.loc[0].max(0.0) as u16, m.obj.loc[1].max(0.0) as u16);
+            });
+        }
+    }
+
     /// create a max number of sprites
     /// and calls f closure to init
     pub fn creat_objpool_sprites<T, F>(
@@ -254,3 +772,57 @@ impl Panel {
         }
     }
 }
+
+// cells as seen with a color vision deficiency, pixel sprites of
+// graphics mode are not covered
+fn simulate_buffer(buf: &mut Buffer, cvd: ColorBlindness) {
+    let mut seen: Vec<(Color, Color)> = vec![];
+    let mut sim = |c: &mut Color| {
+        *c = match seen.iter().find(|s| s.0 == *c) {
+            Some(s) => s.1,
+            None => {
+                let n = simulate_color(*c, cvd);
+                seen.push((*c, n));
+                n
+            }
+        };
+    };
+    for cell in buf.content.iter_mut() {
+        sim(&mut cell.fg);
+        sim(&mut cell.bg);
+    }
+}
+
+// darkens the cells below a modal layer, default colors of terminal
+// mode can not be blended so they get the DIM modifier
+fn dim_buffer(buf: &mut Buffer) {
+    let shade = Color::Rgba(0, 0, 0, 255);
+    for cell in buf.content.iter_mut() {
+        if cell.fg == Color::Reset {
+            cell.modifier.insert(Modifier::DIM);
+        } else {
+            cell.fg = cell.fg.blend(shade, BlendMode::Normal, 0.5);
+        }
+        if cell.bg != Color::Reset {
+            cell.bg = cell.bg.blend(shade, BlendMode::Normal, 0.5);
+        }
+    }
+}
+
+// progress bar in the center of buffer
+fn draw_loading_bar(buf: &mut Buffer, done: usize, total: usize) {
+    let a = buf.area;
+    let w = a.width.saturating_sub(4).min(40);
+    let x = (a.width - w) / 2;
+    let y = a.height / 2;
+    let fill = (w as usize * done).checked_div(total).unwrap_or(w as usize) as u16;
+    let text = format!("Loading {}/{}", done, total);
+    buf.set_str(
+        (a.width.saturating_sub(text.len() as u16)) / 2,
+        y.saturating_sub(1),
+        &text,
+        Style::default(),
+    );
+    let bar = format!("{}{}", "#".repeat(fill as usize), "-".repeat((w - fill) as usize));
+    buf.set_str(x, y, &bar, Style::default());
+}
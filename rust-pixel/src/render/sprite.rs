@@ -11,7 +11,8 @@ use crate::{
     render::buffer::Buffer,
     render::cell::cellsym,
     // render::image::*,
-    render::style::{Color, Style},
+    render::style::{Color, Modifier, Style},
+    render::textflow::{TextAlign, TextFlow},
     util::shape::{circle, line, prepare_line},
     util::{PointU16, PointF32, Rect},
 };
@@ -22,6 +23,15 @@ use bitflags::bitflags;
 mod sprites;
 pub use sprites::Sprites;
 
+mod ninepatch;
+pub use ninepatch::NinePatchSprite;
+
+mod animated;
+pub use animated::{AnimFrame, AnimatedSprite, LoopMode};
+
+mod particles;
+pub use particles::{particle_color, Particles};
+
 /// Defines some common tabs symbol (in text mode)
 pub const SYMBOL_LINE: [&str; 37] = [
     "│", "║", "┃", "─", "═", "━", "┐", "╮", "╗", "┓", "┌", "╭", "╔", "┏", "┘", "╯", "╝", "┛", "└",
@@ -64,6 +74,18 @@ macro_rules! asset2sprite {
         if ll.ends_with(".ssf") {
             at = AssetType::ImgSsf;
         }
+        if ll.ends_with(".ase") || ll.ends_with(".aseprite") {
+            at = AssetType::ImgAse;
+        }
+        if ll.ends_with(".png") {
+            at = AssetType::ImgPng;
+        }
+        if ll.ends_with(".ans") {
+            at = AssetType::ImgAns;
+        }
+        if ll.ends_with(".xp") {
+            at = AssetType::ImgXp;
+        }
         // collect other args...
         let mut va = Vec::new();
         $( va.push($arg); )*
@@ -109,7 +131,12 @@ pub trait Widget {
 pub struct Sprite {
     pub content: Buffer,
     pub angle: f64,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub anchor: PointF32,
     pub alpha: u8,
+    /// multiplies fg color, Color::Reset means no tint
+    pub tint: Color,
     pub asset_request: Option<(AssetType, String, usize, u16, u16)>,
     render_weight: i32,
 }
@@ -118,8 +145,11 @@ impl Widget for Sprite {
     fn render(&mut self, is_pixel: bool, am: &mut AssetManager, buf: &mut Buffer) {
         if !self.is_hidden() {
             self.check_asset_request(am);
-            if !is_pixel {
-                buf.merge(&self.content, self.alpha, true);
+            if !is_pixel && self.alpha != 0 {
+                match self.styled_content() {
+                    Some(c) => buf.merge(&c, self.alpha, true),
+                    None => buf.merge(&self.content, self.alpha, true),
+                }
             }
         }
     }
@@ -132,7 +162,11 @@ impl Sprite {
         Self {
             content: buffer,
             angle: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            anchor: PointF32 { x: 0.5, y: 0.5 },
             alpha: 255,
+            tint: Color::Reset,
             asset_request: None,
             render_weight: 1,
         }
@@ -142,6 +176,43 @@ impl Sprite {
         self.alpha = a;
     }
 
+    /// alpha in 0.0 ~ 1.0
+    pub fn set_opacity(&mut self, a: f32) {
+        self.alpha = (a.clamp(0.0, 1.0) * 255.0) as u8;
+    }
+
+    pub fn set_tint(&mut self, c: Color) {
+        self.tint = c;
+    }
+
+    pub fn set_visible(&mut self, v: bool) {
+        self.set_hidden(!v);
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.is_hidden()
+    }
+
+    /// content with tint applied, and dimmed in text mode when alpha is low,
+    /// None if content can be used as is
+    fn styled_content(&self) -> Option<Buffer> {
+        #[cfg(not(any(feature = "sdl", target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        let dim = self.alpha < 128;
+        #[cfg(any(feature = "sdl", target_os = "android", target_os = "ios", target_arch = "wasm32"))]
+        let dim = false;
+        if self.tint == Color::Reset && !dim {
+            return None;
+        }
+        let mut c = self.content.clone();
+        for cell in c.content.iter_mut() {
+            cell.fg = cell.fg.tint(self.tint);
+            if dim {
+                cell.modifier.insert(Modifier::DIM);
+            }
+        }
+        Some(c)
+    }
+
     /// set string content at (x,y) with fg/bg color...
     pub fn set_color_str<S>(&mut self, x: u16, y: u16, string: S, f: Color, b: Color)
     where
@@ -151,6 +222,26 @@ impl Sprite {
             .set_str(x, y, string, Style::default().fg(f).bg(b));
     }
 
+    /// set string with style markup at (x,y), e.g. "[red]HP[/red] [bold]100[/bold]"
+    pub fn set_rich_str<S>(&mut self, x: u16, y: u16, string: S)
+    where
+        S: AsRef<str>,
+    {
+        self.content.set_rich_str(x, y, string, Style::default());
+    }
+
+    /// wrap text to fit the sprite from (x,y), truncated with '…' at the bottom
+    pub fn set_text_flow<S>(&mut self, x: u16, y: u16, string: S, style: Style, align: TextAlign)
+    where
+        S: AsRef<str>,
+    {
+        let area = self.content.area;
+        let tf = TextFlow::new(area.width.saturating_sub(x))
+            .align(align)
+            .max_lines(area.height.saturating_sub(y));
+        self.content.set_text_flow(x, y, string, style, &tf);
+    }
+
     /// set string content at (0,0) with default style...
     pub fn set_default_str<S>(&mut self, string: S)
     where
@@ -192,6 +283,10 @@ impl Sprite {
                     self.asset_request = None;
                     return true;
                 }
+                // nothing to wait for
+                if ast.get_state() == AssetState::Failed {
+                    self.asset_request = None;
+                }
             }
         } else {
             return true;
@@ -199,10 +294,41 @@ impl Sprite {
         false
     }
 
+    /// render with camera offset in cells, content out of buf is clipped
+    pub fn render_offset(
+        &mut self,
+        is_pixel: bool,
+        am: &mut AssetManager,
+        buf: &mut Buffer,
+        offset: (i32, i32),
+    ) {
+        if !self.is_hidden() {
+            self.check_asset_request(am);
+            if !is_pixel && self.alpha != 0 {
+                match self.styled_content() {
+                    Some(c) => buf.merge_offset(&c, self.alpha, offset.0, offset.1),
+                    None => buf.merge_offset(&self.content, self.alpha, offset.0, offset.1),
+                }
+            }
+        }
+    }
+
     pub fn set_angle(&mut self, a: f64) {
         self.angle = a;
     }
 
+    /// scale only works in graphics mode
+    pub fn set_scale(&mut self, sx: f32, sy: f32) {
+        self.scale_x = sx;
+        self.scale_y = sy;
+    }
+
+    /// anchor point for rotate and scale, relative to sprite size
+    /// (0.0, 0.0) is top left, (0.5, 0.5) is center (default)
+    pub fn set_anchor(&mut self, ax: f32, ay: f32) {
+        self.anchor = PointF32 { x: ax, y: ay };
+    }
+
     pub fn get_center_point(&self) -> PointF32 {
         PointF32 {
             x: self.content.area.x as f32 + self.content.area.width as f32 / 2.0,
@@ -210,6 +336,17 @@ impl Sprite {
         }
     }
 
+    /// bigger weight is rendered later (upper level), w must be positive,
+    /// as the sign of render_weight is used as hidden flag
+    pub fn set_render_weight(&mut self, w: i32) {
+        let w = w.max(1);
+        self.render_weight = if self.is_hidden() { -w } else { w };
+    }
+
+    pub fn get_render_weight(&self) -> i32 {
+        self.render_weight.abs()
+    }
+
     pub fn set_hidden(&mut self, flag: bool) {
         if flag {
             self.render_weight = -self.render_weight.abs();
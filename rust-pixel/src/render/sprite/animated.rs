@@ -0,0 +1,265 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! AnimatedSprite plays a sequence of frames on a sprite.
+//! A frame is either an asset frame (.pix, .txt, or one frame of .ssf)
+//! or a region of an atlas buffer, each with its own duration in seconds.
+//!
+//! When a Once or PingPong animation finishes, the completion event is
+//! emitted to the global event center, check it with event_check.
+
+use crate::{
+    asset::{AssetManager, AssetType, FrameTag},
+    context::Context,
+    event::event_emit,
+    render::{buffer::Buffer, sprite::Sprite},
+    util::Rect,
+};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    /// stops at the last frame
+    Once,
+    #[default]
+    Loop,
+    /// plays forward then backward, once
+    PingPong,
+    /// plays forward then backward forever
+    PingPongLoop,
+}
+
+#[derive(Clone)]
+pub enum AnimFrame {
+    /// asset type, location, frame index
+    Asset(AssetType, String, usize),
+    /// region in the atlas buffer
+    Region(Rect),
+}
+
+pub struct AnimatedSprite {
+    pub frames: Vec<(AnimFrame, f32)>,
+    pub atlas: Option<Buffer>,
+    pub mode: LoopMode,
+    pub speed: f32,
+    pub current: usize,
+    pub playing: bool,
+    complete_event: Option<String>,
+    elapsed: f32,
+    backward: bool,
+    // frame needs to be set to sprite
+    dirty: bool,
+}
+
+impl AnimatedSprite {
+    pub fn new(mode: LoopMode) -> Self {
+        Self {
+            frames: vec![],
+            atlas: None,
+            mode,
+            speed: 1.0,
+            current: 0,
+            playing: true,
+            complete_event: None,
+            elapsed: 0.0,
+            backward: false,
+            dirty: true,
+        }
+    }
+
+    /// location is relative to assets dir, same as asset2sprite!
+    pub fn add_asset_frame(&mut self, ctx: &Context, loc: &str, frame_idx: usize, duration: f32) {
+        let ll = loc.to_lowercase();
+        let at = if ll.ends_with(".txt") {
+            AssetType::ImgEsc
+        } else if ll.ends_with(".ssf") {
+            AssetType::ImgSsf
+        } else if ll.ends_with(".ase") || ll.ends_with(".aseprite") {
+            AssetType::ImgAse
+        } else if ll.ends_with(".png") {
+            AssetType::ImgPng
+        } else if ll.ends_with(".ans") {
+            AssetType::ImgAns
+        } else if ll.ends_with(".xp") {
+            AssetType::ImgXp
+        } else {
+            AssetType::ImgPix
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let nl = format!(
+            "{}{}assets{}{}",
+            ctx.project_path,
+            std::path::MAIN_SEPARATOR,
+            std::path::MAIN_SEPARATOR,
+            loc
+        );
+        #[cfg(target_arch = "wasm32")]
+        let nl = {
+            let _ = ctx;
+            format!("assets{}{}", std::path::MAIN_SEPARATOR, loc)
+        };
+        self.frames.push((AnimFrame::Asset(at, nl, frame_idx), duration));
+    }
+
+    /// adds frames of an .ssf file, all with the same duration
+    pub fn add_asset_frames(&mut self, ctx: &Context, loc: &str, range: Range<usize>, duration: f32) {
+        for i in range {
+            self.add_asset_frame(ctx, loc, i, duration);
+        }
+    }
+
+    /// plays a named frame tag of an asset, e.g. an Aseprite tag
+    pub fn from_tag(ctx: &Context, loc: &str, tag: &FrameTag) -> Self {
+        let mut a = Self::new(tag.mode);
+        for (idx, d) in &tag.frames {
+            a.add_asset_frame(ctx, loc, *idx, *d);
+        }
+        a
+    }
+
+    /// uses a buffer as atlas, frames are added by add_region_frame
+    pub fn set_atlas(&mut self, atlas: Buffer) {
+        self.atlas = Some(atlas);
+        self.dirty = true;
+    }
+
+    /// region is relative to the atlas top left corner
+    pub fn add_region_frame(&mut self, region: Rect, duration: f32) {
+        self.frames.push((AnimFrame::Region(region), duration));
+    }
+
+    /// adds cols x rows regions of w x h cells, row by row
+    pub fn add_grid_frames(&mut self, w: u16, h: u16, cols: u16, rows: u16, duration: f32) {
+        for r in 0..rows {
+            for c in 0..cols {
+                self.add_region_frame(Rect::new(c * w, r * h, w, h), duration);
+            }
+        }
+    }
+
+    /// event emitted when a Once or PingPong animation finishes
+    pub fn set_complete_event(&mut self, name: &str) {
+        self.complete_event = Some(name.to_string());
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// back to first frame and play again
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.elapsed = 0.0;
+        self.backward = false;
+        self.playing = true;
+        self.dirty = true;
+    }
+
+    pub fn set_frame(&mut self, idx: usize) {
+        if idx < self.frames.len() {
+            self.current = idx;
+            self.elapsed = 0.0;
+            self.dirty = true;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.playing && matches!(self.mode, LoopMode::Once | LoopMode::PingPong)
+    }
+
+    // moves to next frame, returns false when finished
+    fn advance(&mut self) -> bool {
+        let n = self.frames.len();
+        if n <= 1 {
+            return !matches!(self.mode, LoopMode::Once | LoopMode::PingPong);
+        }
+        match self.mode {
+            LoopMode::Once => {
+                if self.current + 1 >= n {
+                    return false;
+                }
+                self.current += 1;
+            }
+            LoopMode::Loop => self.current = (self.current + 1) % n,
+            LoopMode::PingPong | LoopMode::PingPongLoop => {
+                if !self.backward {
+                    if self.current + 1 >= n {
+                        self.backward = true;
+                        self.current -= 1;
+                    } else {
+                        self.current += 1;
+                    }
+                } else if self.current == 0 {
+                    if self.mode == LoopMode::PingPong {
+                        return false;
+                    }
+                    self.backward = false;
+                    self.current = 1;
+                } else {
+                    self.current -= 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// advances dt seconds and sets current frame to sprite
+    pub fn update(&mut self, am: &mut AssetManager, sp: &mut Sprite, dt: f32) {
+        if self.frames.is_empty() {
+            return;
+        }
+        if self.playing {
+            self.elapsed += dt * self.speed;
+            while self.elapsed >= self.frames[self.current].1 {
+                self.elapsed -= self.frames[self.current].1;
+                if !self.advance() {
+                    self.playing = false;
+                    self.elapsed = 0.0;
+                    if let Some(e) = &self.complete_event {
+                        event_emit(e);
+                    }
+                    break;
+                }
+                self.dirty = true;
+                // zero duration frames
+                if self.frames[self.current].1 <= 0.0 {
+                    break;
+                }
+            }
+        }
+        if self.dirty {
+            self.apply(am, sp);
+        }
+    }
+
+    fn apply(&mut self, am: &mut AssetManager, sp: &mut Sprite) {
+        match &self.frames[self.current].0 {
+            AnimFrame::Asset(at, loc, idx) => {
+                sp.set_content_by_asset(am, *at, loc, *idx, 0, 0);
+            }
+            AnimFrame::Region(r) => {
+                let atlas = match &self.atlas {
+                    Some(a) => a,
+                    None => return,
+                };
+                let (aw, ah) = (atlas.area.width, atlas.area.height);
+                let (sw, sh) = (sp.content.area.width, sp.content.area.height);
+                for y in 0..r.height.min(sh) {
+                    for x in 0..r.width.min(sw) {
+                        let (ax, ay) = (r.x + x, r.y + y);
+                        if ax < aw && ay < ah {
+                            let si = (ay * aw + ax) as usize;
+                            let di = (y * sw + x) as usize;
+                            sp.content.content[di] = atlas.content[si].clone();
+                        }
+                    }
+                }
+            }
+        }
+        self.dirty = false;
+    }
+}
@@ -0,0 +1,83 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! ninepatch.rs implements a 3x3 sliced sprite source.
+//! Corners are kept as is, edges and center are repeated to fill any size,
+//! so windows, buttons and dialog frames scale cleanly in text and graphics mode.
+
+use crate::render::{buffer::Buffer, sprite::Sprite};
+use crate::util::Rect;
+
+#[derive(Clone)]
+pub struct NinePatchSprite {
+    pub source: Buffer,
+    pub left: u16,
+    pub top: u16,
+    pub right: u16,
+    pub bottom: u16,
+}
+
+impl NinePatchSprite {
+    /// left, top, right, bottom are border sizes in cells
+    pub fn new(source: &Buffer, left: u16, top: u16, right: u16, bottom: u16) -> Self {
+        let mut src = source.clone();
+        src.area = Rect::new(0, 0, source.area.width, source.area.height);
+        Self {
+            source: src,
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn from_sprite(sp: &Sprite, left: u16, top: u16, right: u16, bottom: u16) -> Self {
+        Self::new(&sp.content, left, top, right, bottom)
+    }
+
+    // maps a dst coordinate to source coordinate
+    fn map(d: u16, dsize: u16, ssize: u16, head: u16, tail: u16) -> u16 {
+        if d < head {
+            return d.min(ssize.saturating_sub(1));
+        }
+        if d + tail >= dsize {
+            return (ssize + d).saturating_sub(dsize).min(ssize.saturating_sub(1));
+        }
+        let mid = ssize.saturating_sub(head + tail);
+        if mid == 0 {
+            return head.min(ssize.saturating_sub(1));
+        }
+        head + (d - head) % mid
+    }
+
+    /// fills the whole buffer area with the stretched source
+    pub fn render_to(&self, buf: &mut Buffer) {
+        let sw = self.source.area.width;
+        let sh = self.source.area.height;
+        if sw == 0 || sh == 0 {
+            return;
+        }
+        let dw = buf.area.width;
+        let dh = buf.area.height;
+        for y in 0..dh {
+            let sy = Self::map(y, dh, sh, self.top, self.bottom);
+            for x in 0..dw {
+                let sx = Self::map(x, dw, sw, self.left, self.right);
+                let si = (sy * sw + sx) as usize;
+                let di = (y * dw + x) as usize;
+                buf.content[di] = self.source.content[si].clone();
+            }
+        }
+    }
+
+    /// fills sprite content to its current size
+    pub fn apply(&self, sp: &mut Sprite) {
+        self.render_to(&mut sp.content);
+    }
+
+    pub fn new_sprite(&self, x: u16, y: u16, width: u16, height: u16) -> Sprite {
+        let mut sp = Sprite::new(x, y, width, height);
+        self.apply(&mut sp);
+        sp
+    }
+}
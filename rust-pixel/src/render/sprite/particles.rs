@@ -0,0 +1,95 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Particles draws a ParticleSystem (util/particle.rs) for explosions,
+//! sparkles, smoke...
+//!
+//! In text mode all particles are drawn as colored cells into one sprite,
+//! particle location is in cells.
+//! In graphics mode each particle is a 1x1 pixel sprite (a quad), taken from
+//! an object pool, particle location is in pixels.
+
+use crate::{
+    render::{
+        buffer::Buffer,
+        style::{Color, ColorGradient, ColorPro, ColorSpace, Fraction, Style},
+    },
+    util::{Particle, ParticleSystem, ParticleSystemInfo},
+};
+
+pub struct Particles {
+    pub system: ParticleSystem,
+    /// color over life, sampled by age / term_age
+    pub colors: Option<ColorGradient>,
+    /// symbol in text mode
+    pub symbol: String,
+    /// texture and symbol index in graphics mode
+    pub graph_sym: (u8, u8),
+}
+
+impl Particles {
+    /// name is used as sprite tag prefix, max_count caps alive particles
+    pub fn new(name: &str, info: ParticleSystemInfo, max_count: usize) -> Self {
+        Self {
+            system: ParticleSystem::with_pool(info, name, max_count),
+            colors: None,
+            symbol: "•".to_string(),
+            graph_sym: (2, 25),
+        }
+    }
+
+    pub fn set_color_over_life(&mut self, g: ColorGradient) {
+        self.colors = Some(g);
+    }
+
+    pub fn set_symbol(&mut self, s: &str) {
+        self.symbol = s.to_string();
+    }
+
+    pub fn set_graph_sym(&mut self, texture_id: u8, sym: u8) {
+        self.graph_sym = (texture_id, sym);
+    }
+
+    pub fn fire_at(&mut self, x: f64, y: f64) {
+        self.system.fire_at(x, y);
+    }
+
+    pub fn stop(&mut self) {
+        self.system.stop();
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.system.update(dt as f64);
+    }
+
+    /// draws alive particles into buf, location relative to buf top left
+    pub fn render_to(&self, buf: &mut Buffer) {
+        let (w, h) = (buf.area.width as f64, buf.area.height as f64);
+        for o in self.system.particles.pool.iter().filter(|o| o.active) {
+            let (x, y) = (o.obj.loc[0].floor(), o.obj.loc[1].floor());
+            if x < 0.0 || y < 0.0 || x >= w || y >= h {
+                continue;
+            }
+            let (fg, _a) = particle_color(self.colors.as_ref(), &o.obj);
+            let i = (y as usize) * buf.area.width as usize + x as usize;
+            buf.content[i].set_symbol(&self.symbol).set_style(Style::default().fg(fg));
+        }
+    }
+}
+
+/// color and alpha of a particle, from gradient if given,
+/// or else from its own rgba which changes over life
+pub fn particle_color(colors: Option<&ColorGradient>, p: &Particle) -> (Color, u8) {
+    if let Some(g) = colors {
+        let t = if p.term_age > 0.0 { p.age / p.term_age } else { 1.0 };
+        if let Some(c) = g.sample(Fraction::from(t), ColorSpace::OKLchA) {
+            let (r, g, b, a) = ColorPro::from_space(ColorSpace::OKLchA, c).get_srgba_u8();
+            return (Color::Rgba(r, g, b, 255), a);
+        }
+    }
+    let u = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    (
+        Color::Rgba(u(p.color[0]), u(p.color[1]), u(p.color[2]), 255),
+        u(p.color[3]),
+    )
+}
@@ -4,21 +4,37 @@
 //! sprites.rs implements a set of Sprites. Those Sprites are stored in a vector
 //! Sprite can be accessed via offset in the vector or by tag in the hashmap
 //! render_all method draws the sprites in a sorted order indicated by render_weight
+//!
+//! A sprite can be attached to a parent sprite in the same Sprites, its position is
+//! relative to the parent, in cells for text layers and in pixels for pixel layers.
+//! In graphics mode children also inherit rotation and scale, rotating around
+//! the anchor point of the parent.
 
 use crate::{
     asset::AssetManager,
+    render::adapter::gl::transform::GlTransform,
     render::sprite::Sprite,
     render::buffer::Buffer,
-    util::PointU16,
+    util::{PointF32, PointU16},
 };
 use crate::render::sprite::Widget;
 // use log::info;
 use std::{
-    cmp::Reverse,
     collections::HashMap,
     ops::{Index, IndexMut},
 };
 
+/// relative transform of a child sprite
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteLink {
+    pub parent: usize,
+    pub x: i32,
+    pub y: i32,
+    pub angle: f64,
+    pub scale_x: f32,
+    pub scale_y: f32,
+}
+
 /// Set of text sprite, stored in a vector
 /// Sprite can be accessed via offset in the vector or by tag in the hashmap
 pub struct Sprites {
@@ -33,6 +49,21 @@ pub struct Sprites {
 
     // render weight as layers in panel...
     pub render_weight: i32,
+
+    // camera offset in cells, set by panel...
+    pub camera_offset: (i32, i32),
+
+    // camera zoom and sub cell scroll of a pixel layer, set by panel...
+    pub camera_view: GlTransform,
+
+    // false keeps the layer fixed on screen, e.g. a hud...
+    pub follow_camera: bool,
+
+    // dims the layers below when drawn, e.g. behind a dialog...
+    pub is_modal: bool,
+
+    // child sprite index -> link to parent...
+    pub links: HashMap<usize, SpriteLink>,
 }
 
 /// 实现Index，IndexMut协议
@@ -60,6 +91,11 @@ impl Sprites {
             tag_index: HashMap::new(),
             render_index: vec![],
             render_weight: 1, 
+            camera_offset: (0, 0),
+            camera_view: GlTransform::new(),
+            follow_camera: true,
+            is_modal: false,
+            links: HashMap::new(),
         }
     }
 
@@ -72,6 +108,11 @@ impl Sprites {
             tag_index: HashMap::new(),
             render_index: vec![],
             render_weight: 1, 
+            camera_offset: (0, 0),
+            camera_view: GlTransform::new(),
+            follow_camera: true,
+            is_modal: false,
+            links: HashMap::new(),
         }
     }
 
@@ -124,7 +165,7 @@ impl Sprites {
 
     pub fn set_weight_by_tag(&mut self, name: &str, w: i32) {
         let idx = self.tag_index.get(name).unwrap();
-        self.sprites[*idx].render_weight = w;
+        self.sprites[*idx].set_render_weight(w);
         self.render_index.clear();
     }
 
@@ -133,6 +174,98 @@ impl Sprites {
         self.sprites[*idx].set_hidden(hidden);
     }
 
+    /// attach child to parent, (x, y) is the position relative to parent
+    pub fn attach(&mut self, child: &str, parent: &str, x: i32, y: i32) {
+        let c = *self.tag_index.get(child).unwrap();
+        let p = *self.tag_index.get(parent).unwrap();
+        if c == p {
+            return;
+        }
+        self.links.insert(
+            c,
+            SpriteLink {
+                parent: p,
+                x,
+                y,
+                angle: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+            },
+        );
+    }
+
+    pub fn detach(&mut self, child: &str) {
+        let c = self.tag_index.get(child).unwrap();
+        self.links.remove(c);
+    }
+
+    pub fn set_local_pos(&mut self, child: &str, x: i32, y: i32) {
+        let c = self.tag_index.get(child).unwrap();
+        if let Some(l) = self.links.get_mut(c) {
+            l.x = x;
+            l.y = y;
+        }
+    }
+
+    /// angle & scale relative to parent, graphics mode only
+    pub fn set_local_transform(&mut self, child: &str, angle: f64, sx: f32, sy: f32) {
+        let c = self.tag_index.get(child).unwrap();
+        if let Some(l) = self.links.get_mut(c) {
+            l.angle = angle;
+            l.scale_x = sx;
+            l.scale_y = sy;
+        }
+    }
+
+    fn link_depth(&self, mut idx: usize) -> usize {
+        let mut depth = 0;
+        while let Some(l) = self.links.get(&idx) {
+            depth += 1;
+            // guard against cycles
+            if depth > self.links.len() {
+                break;
+            }
+            idx = l.parent;
+        }
+        depth
+    }
+
+    /// computes position, angle, scale and anchor of attached sprites,
+    /// cell_w & cell_h are cell size in layer units (1.0 for text layers)
+    pub fn update_hierarchy(&mut self, cell_w: f32, cell_h: f32) {
+        if self.links.is_empty() {
+            return;
+        }
+        // parents first
+        let mut order: Vec<(usize, usize)> =
+            self.links.keys().map(|c| (self.link_depth(*c), *c)).collect();
+        order.sort();
+        for (_, c) in order {
+            let l = self.links[&c];
+            let p = &self.sprites[l.parent];
+            let pa = p.content.area;
+            let (pangle, psx, psy, panchor) = (p.angle, p.scale_x, p.scale_y, p.anchor);
+
+            let s = &mut self.sprites[c];
+            let x = (pa.x as i32 + l.x).max(0) as u16;
+            let y = (pa.y as i32 + l.y).max(0) as u16;
+            s.set_pos(x, y);
+            s.angle = pangle + l.angle;
+            s.scale_x = psx * l.scale_x;
+            s.scale_y = psy * l.scale_y;
+            // rotate & scale around parent's anchor point
+            let ca = s.content.area;
+            if ca.width > 0 && ca.height > 0 {
+                let pivot_x = pa.width as f32 * panchor.x * cell_w - l.x as f32;
+                let pivot_y = pa.height as f32 * panchor.y * cell_h - l.y as f32;
+                s.anchor = PointF32 {
+                    x: pivot_x / (ca.width as f32 * cell_w),
+                    y: pivot_y / (ca.height as f32 * cell_h),
+                };
+            }
+        }
+    }
+
     pub fn update_render_index(&mut self) {
         // renders in an order by render_weight
         // bigger render_weight is rendered later（upper level)
@@ -140,15 +273,24 @@ impl Sprites {
             for (i, s) in self.sprites.iter().enumerate() {
                 self.render_index.push((i, s.render_weight));
             }
-            self.render_index.sort_by_key(|d| Reverse(d.1));
+            // stable sort, same weight keeps insertion order
+            // weight sign is the hidden flag, so sort by abs
+            self.render_index.sort_by_key(|d| d.1.abs());
             // info!("render_index...{:?}", self.render_index);
         }
     }
 
     pub fn render_all_to_buffer(&mut self, am: &mut AssetManager, buffer: &mut Buffer) {
+        if !self.is_pixel {
+            self.update_hierarchy(1.0, 1.0);
+        }
         self.update_render_index();
         for v in &self.render_index {
-            self.sprites[v.0].render(self.is_pixel, am, buffer);
+            if self.camera_offset == (0, 0) {
+                self.sprites[v.0].render(self.is_pixel, am, buffer);
+            } else {
+                self.sprites[v.0].render_offset(self.is_pixel, am, buffer, self.camera_offset);
+            }
         }
     }
 }
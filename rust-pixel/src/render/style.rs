@@ -20,6 +20,15 @@ pub use color::*;
 mod color_pro;
 pub use color_pro::*;
 
+mod markup;
+pub use markup::*;
+
+mod nearest;
+pub use nearest::*;
+
+mod theme;
+pub use theme::*;
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
     pub struct Modifier: u16 {
@@ -3,11 +3,25 @@
 
 //! Defines styles color
 
-use crate::render::style::ColorPro;
+use crate::render::style::{ColorGradient, ColorPro};
 #[cfg(not(any(feature = "sdl", target_os = "android", target_os = "ios", target_arch = "wasm32")))]
 use crossterm::style::Color as CColor;
 use serde::{Deserialize, Serialize};
 
+/// how a color is composited over a backdrop color, see Color::blend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// alpha compositing, source over backdrop
+    #[default]
+    Normal,
+    /// darkens, e.g. shadows
+    Multiply,
+    /// lightens, e.g. light sources
+    Screen,
+    /// multiply on dark backdrop, screen on light one
+    Overlay,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Color {
     Reset,
@@ -32,6 +46,56 @@ pub enum Color {
 }
 
 impl Color {
+    /// multiplies rgb by tint color, Reset means no tint
+    pub fn tint(self, t: Color) -> Color {
+        if t == Color::Reset {
+            return self;
+        }
+        let (tr, tg, tb, _) = t.get_rgba();
+        if self == Color::Reset {
+            return Color::Rgba(tr, tg, tb, 255);
+        }
+        let (r, g, b, a) = self.get_rgba();
+        Color::Rgba(
+            (r as u16 * tr as u16 / 255) as u8,
+            (g as u16 * tg as u16 / 255) as u8,
+            (b as u16 * tb as u16 / 255) as u8,
+            a,
+        )
+    }
+
+    /// composites other over self in linear rgb, t from 0.0 to 1.0 is the
+    /// opacity of other and is scaled by its alpha. The alpha of self is kept,
+    /// a Reset backdrop gives other as is, a Reset other leaves self
+    pub fn blend(self, other: Color, mode: BlendMode, t: f32) -> Color {
+        if other == Color::Reset {
+            return self;
+        }
+        if self == Color::Reset {
+            return other;
+        }
+        let (r, g, b, a) = self.get_rgba();
+        let (sr, sg, sb, sa) = other.get_rgba();
+        let t = t.clamp(0.0, 1.0) * sa as f32 / 255.0;
+        let mix = |d: u8, s: u8| {
+            let (d, s) = (srgb_to_linear(d), srgb_to_linear(s));
+            let m = match mode {
+                BlendMode::Normal => s,
+                BlendMode::Multiply => d * s,
+                BlendMode::Screen => d + s - d * s,
+                BlendMode::Overlay => {
+                    if d <= 0.5 {
+                        2.0 * d * s
+                    } else {
+                        1.0 - 2.0 * (1.0 - d) * (1.0 - s)
+                    }
+                }
+            };
+            linear_to_srgb(d + (m - d) * t)
+        };
+        Color::Rgba(mix(r, sr), mix(g, sg), mix(b, sb), a)
+    }
+
     pub fn get_rgba(self) -> (u8, u8, u8, u8) {
         let cidx: usize = match self {
             Color::Reset => 8,
@@ -123,6 +187,101 @@ impl From<ColorPro> for Color {
     }
 }
 
+/// colors at positions of 0.0..=1.0, blended in between,
+/// e.g. the fill of a health bar shifting with its value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorScale {
+    stops: Vec<(f32, Color)>,
+}
+
+impl ColorScale {
+    pub fn new(stops: &[(f32, Color)]) -> Self {
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// green at 0.0, yellow at 0.5, red at 1.0
+    pub fn green_red() -> Self {
+        Self::new(&[
+            (0.0, Color::Rgba(0, 200, 0, 255)),
+            (0.5, Color::Rgba(230, 200, 0, 255)),
+            (1.0, Color::Rgba(220, 0, 0, 255)),
+        ])
+    }
+
+    /// n stops evenly spread over a gradient
+    pub fn from_gradient(g: &ColorGradient, n: usize) -> Self {
+        let cs = g.colors(n);
+        let last = cs.len().saturating_sub(1).max(1) as f32;
+        let stops: Vec<(f32, Color)> = cs
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| (i as f32 / last, Color::from(c)))
+            .collect();
+        Self { stops }
+    }
+
+    /// the same colors from 1.0 down to 0.0
+    pub fn reversed(&self) -> Self {
+        let stops: Vec<(f32, Color)> = self.stops.iter().map(|(p, c)| (1.0 - p, *c)).collect();
+        Self::new(&stops)
+    }
+
+    /// color at t, Reset without stops
+    pub fn at(&self, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let Some(first) = self.stops.first() else {
+            return Color::Reset;
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for w in self.stops.windows(2) {
+            let ((p0, c0), (p1, c1)) = (w[0], w[1]);
+            if t <= p1 {
+                let f = if p1 > p0 { (t - p0) / (p1 - p0) } else { 1.0 };
+                return c0.blend(c1, BlendMode::Normal, f);
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 255.0).round() as u8
+}
+
+/// maps rgb to the nearest ansi 256 color index
+pub fn nearest_ansi(r: u8, g: u8, b: u8) -> u8 {
+    let mut best = (u32::MAX, 0u8);
+    for (i, c) in ANSI_COLOR_RGB.iter().enumerate() {
+        let dr = c[0] as i32 - r as i32;
+        let dg = c[1] as i32 - g as i32;
+        let db = c[2] as i32 - b as i32;
+        let d = (dr * dr * 3 + dg * dg * 4 + db * db * 2) as u32;
+        if d < best.0 {
+            best = (d, i as u8);
+        }
+    }
+    best.1
+}
+
 fn get_u8_rgb(r: u8, g: u8, b: u8) -> u8 {
     let ret = 0;
     for (i, item) in ANSI_COLOR_RGB.iter().enumerate() {
@@ -19,6 +19,9 @@
 ///   Oklab l: 0.0 - 1.0 a: -0.5 - 0.5 (approximate range) b: -0.5 - 0.5 (approximate range)
 ///   Oklch l: 0.0 - 1.0 c: 0.0 - 1.0 (approximate range) h: 0.0 - 360.0 (degrees)
 ///   XYZ x: 0.0 - 1.0 (normalized range) y: 0.0 - 1.0 (normalized range) z: 0.0 - 1.0 (normalized range)
+///   HSLuv h: 0.0 - 360.0 (degrees) s: 0.0 - 100.0 l: 0.0 - 100.0
+///   CAM16-UCS j: 0.0 - 100.0 a: -50.0 - 50.0 (approximate range) b: -50.0 - 50.0 (approximate range)
+///   Display P3 r: 0.0 - 1.0 g: 0.0 - 1.0 b: 0.0 - 1.0
 ///
 /// Example:
 /// ```
@@ -80,12 +83,28 @@ pub use delta::*;
 mod gradient;
 pub use gradient::*;
 
+/// palette extraction, median cut and k-means
+mod extract;
+pub use extract::*;
+
+/// colorblind simulation, wcag contrast
+mod vision;
+pub use vision::*;
+
+/// hsluv color_space
+mod hsluv;
+use hsluv::*;
+
+/// css, x11 and xkcd color names
+mod names;
+pub use names::*;
+
 // 0.3127 / 0.3290  (1.0 - 0.3127 - 0.3290) / 0.3290
 pub const WHITE: [f64; 3] = [0.9504559270516716, 1.0, 1.0890577507598784];
 pub const EPSILON_LSTAR: f64 = 216.0 / 24389.0;
 pub const KAPPA: f64 = 24389.0 / 27.0;
 
-pub const COLOR_SPACE_COUNT: usize = 13;
+pub const COLOR_SPACE_COUNT: usize = 16;
 
 #[derive(Debug, Clone, Copy, FromPrimitive)]
 pub enum ColorSpace {
@@ -102,11 +121,14 @@ pub enum ColorSpace {
     CAM16A,
     HCTA,
     XYZA,
+    HSLuvA,
+    CAM16UCSA,
+    DisplayP3A,
 }
 
 impl fmt::Display for ColorSpace {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let css = ["rgb", "lrgb", "cmyk", "hsl", "hsv", "hwb", "lab", "lch", "oklab", "oklch", "cam16", "hct", "xyz"];
+        let css = ["rgb", "lrgb", "cmyk", "hsl", "hsv", "hwb", "lab", "lch", "oklab", "oklch", "cam16", "hct", "xyz", "hsluv", "cam16ucs", "p3"];
         write!(f, "{:5}", css[*self as usize])
     }
 }
@@ -269,6 +291,9 @@ impl ColorPro {
         self.set_data(OKLchA, oklaba_to_oklcha(self[OKLabA].unwrap()));
         self.set_data(CAM16A, xyz_to_cam16(xyza));
         self.set_data(HCTA, xyz_to_hct(xyza));
+        self.set_data(HSLuvA, xyz_to_hsluv(xyza));
+        self.set_data(CAM16UCSA, cam16_to_ucs(self[CAM16A].unwrap()));
+        self.set_data(DisplayP3A, xyz_to_p3(xyza));
         Ok(())
     }
 
@@ -379,6 +404,20 @@ impl ColorPro {
             self.set_data(HCTA, hct);
         }
 
+        if let Some(hsluv) = self[HSLuvA] {
+            self.set_data(XYZA, hsluv_to_xyz(hsluv));
+        }
+
+        if let Some(ucs) = self[CAM16UCSA] {
+            let cam16 = ucs_to_cam16(ucs);
+            self.set_data(CAM16A, cam16);
+            self.set_data(XYZA, cam16_to_xyz(cam16));
+        }
+
+        if let Some(p3) = self[DisplayP3A] {
+            self.set_data(XYZA, p3_to_xyz(p3));
+        }
+
         if self[XYZA].is_none() {
             return Err("No color data available for conversion".to_string());
         };
@@ -77,3 +77,11 @@ pub fn delta_e_ciede2000(lab1: ColorData, lab2: ColorData) -> f64 {
 }
 
 
+
+/// euclidean distance in CAM16-UCS
+pub fn delta_e_cam16ucs(ucs1: ColorData, ucs2: ColorData) -> f64 {
+    ((ucs1.v[0] - ucs2.v[0]).powi(2)
+        + (ucs1.v[1] - ucs2.v[1]).powi(2)
+        + (ucs1.v[2] - ucs2.v[2]).powi(2))
+    .sqrt()
+}
@@ -0,0 +1,268 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Dominant colors of an image by median cut or k-means quantization
+//!
+//! Pixels are counted in a 15bit histogram first, both methods work on
+//! the weighted bins, so the cost does not grow with the image size.
+//! k-means starts from the median cut boxes and clusters in OKLab.
+
+use crate::render::style::color_pro::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuantizeMethod {
+    #[default]
+    MedianCut,
+    KMeans,
+}
+
+// pixels falling into one 5bit per channel bin
+#[derive(Debug, Clone, Copy)]
+struct Bin {
+    sum: [f64; 3],
+    count: usize,
+}
+
+impl Bin {
+    fn mean(&self) -> [f64; 3] {
+        let n = self.count as f64;
+        [self.sum[0] / n, self.sum[1] / n, self.sum[2] / n]
+    }
+}
+
+fn histogram(rgba: &[u8]) -> Vec<Bin> {
+    let mut bins = vec![
+        Bin {
+            sum: [0.0; 3],
+            count: 0
+        };
+        1 << 15
+    ];
+    for p in rgba.chunks_exact(4) {
+        // transparent pixels are skipped
+        if p[3] < 128 {
+            continue;
+        }
+        let key = (p[0] as usize >> 3) << 10 | (p[1] as usize >> 3) << 5 | p[2] as usize >> 3;
+        let b = &mut bins[key];
+        for (s, v) in b.sum.iter_mut().zip(p) {
+            *s += *v as f64;
+        }
+        b.count += 1;
+    }
+    bins.into_iter().filter(|b| b.count > 0).collect()
+}
+
+// merges bins to one color and its pixel count
+fn merge(bins: &[Bin]) -> ([f64; 3], usize) {
+    let mut sum = [0.0; 3];
+    let mut count = 0;
+    for b in bins {
+        for (s, v) in sum.iter_mut().zip(b.sum) {
+            *s += v;
+        }
+        count += b.count;
+    }
+    let n = count.max(1) as f64;
+    ([sum[0] / n, sum[1] / n, sum[2] / n], count)
+}
+
+fn channel_range(bins: &[Bin], c: usize) -> f64 {
+    let (lo, hi) = bins.iter().fold((f64::MAX, f64::MIN), |(lo, hi), b| {
+        let v = b.mean()[c];
+        (lo.min(v), hi.max(v))
+    });
+    hi - lo
+}
+
+fn median_cut_bins(mut bins: Vec<Bin>, k: usize) -> Vec<Vec<Bin>> {
+    let mut boxes = vec![];
+    if !bins.is_empty() {
+        boxes.push(std::mem::take(&mut bins));
+    }
+    while boxes.len() < k {
+        // splits the box with the widest channel, weighted by its pixels
+        let mut best: Option<(usize, usize, f64)> = None;
+        for (i, bx) in boxes.iter().enumerate() {
+            if bx.len() < 2 {
+                continue;
+            }
+            let count = bx.iter().map(|b| b.count).sum::<usize>() as f64;
+            for c in 0..3 {
+                let score = channel_range(bx, c) * count.sqrt();
+                match best {
+                    Some((_, _, s)) if s >= score => {}
+                    _ => best = Some((i, c, score)),
+                }
+            }
+        }
+        let Some((i, c, _)) = best else {
+            break;
+        };
+        let mut bx = boxes.swap_remove(i);
+        bx.sort_by(|a, b| a.mean()[c].total_cmp(&b.mean()[c]));
+        let half = bx.iter().map(|b| b.count).sum::<usize>() / 2;
+        let mut acc = 0;
+        let mut at = 1;
+        for (j, b) in bx.iter().enumerate() {
+            acc += b.count;
+            if acc >= half {
+                at = (j + 1).clamp(1, bx.len() - 1);
+                break;
+            }
+        }
+        let rest = bx.split_off(at);
+        boxes.push(bx);
+        boxes.push(rest);
+    }
+    boxes
+}
+
+fn oklab_of(rgb: [f64; 3]) -> [f64; 3] {
+    let c = ColorPro::from_space_f64(SRGBA, rgb[0] / 255.0, rgb[1] / 255.0, rgb[2] / 255.0, 1.0);
+    let v = c[OKLabA].unwrap().v;
+    [v[0], v[1], v[2]]
+}
+
+fn dist2(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+fn kmeans_bins(bins: &[Bin], k: usize, iters: usize) -> Vec<([f64; 3], usize)> {
+    let labs: Vec<[f64; 3]> = bins.iter().map(|b| oklab_of(b.mean())).collect();
+    let mut centers: Vec<[f64; 3]> = median_cut_bins(bins.to_vec(), k)
+        .iter()
+        .map(|bx| oklab_of(merge(bx).0))
+        .collect();
+    let mut assign = vec![usize::MAX; bins.len()];
+    for _ in 0..iters {
+        let mut changed = false;
+        for (i, l) in labs.iter().enumerate() {
+            let mut near = 0;
+            for (j, c) in centers.iter().enumerate() {
+                if dist2(l, c) < dist2(l, &centers[near]) {
+                    near = j;
+                }
+            }
+            if assign[i] != near {
+                assign[i] = near;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        let mut sums = vec![([0.0; 3], 0usize); centers.len()];
+        for (i, l) in labs.iter().enumerate() {
+            let s = &mut sums[assign[i]];
+            for (sc, lc) in s.0.iter_mut().zip(l) {
+                *sc += lc * bins[i].count as f64;
+            }
+            s.1 += bins[i].count;
+        }
+        for (j, (s, n)) in sums.iter().enumerate() {
+            if *n > 0 {
+                let n = *n as f64;
+                centers[j] = [s[0] / n, s[1] / n, s[2] / n];
+            }
+        }
+    }
+    // colors are the rgb means of the clusters
+    let mut clusters = vec![vec![]; centers.len()];
+    for (i, b) in bins.iter().enumerate() {
+        clusters[assign[i]].push(*b);
+    }
+    clusters
+        .iter()
+        .filter(|c| !c.is_empty())
+        .map(|c| merge(c))
+        .collect()
+}
+
+/// up to k dominant colors of rgba pixels with their share of the pixels,
+/// most frequent first
+pub fn extract_palette(rgba: &[u8], k: usize, method: QuantizeMethod) -> Vec<(ColorPro, f64)> {
+    let bins = histogram(rgba);
+    if bins.is_empty() || k == 0 {
+        return vec![];
+    }
+    let mut cs: Vec<([f64; 3], usize)> = match method {
+        QuantizeMethod::MedianCut => median_cut_bins(bins, k)
+            .iter()
+            .map(|bx| merge(bx))
+            .collect(),
+        QuantizeMethod::KMeans => kmeans_bins(&bins, k, 16),
+    };
+    cs.sort_by_key(|c| std::cmp::Reverse(c.1));
+    let total = cs.iter().map(|c| c.1).sum::<usize>() as f64;
+    cs.iter()
+        .map(|(rgb, n)| {
+            (
+                ColorPro::from_space_f64(
+                    SRGBA,
+                    rgb[0] / 255.0,
+                    rgb[1] / 255.0,
+                    rgb[2] / 255.0,
+                    1.0,
+                ),
+                *n as f64 / total,
+            )
+        })
+        .collect()
+}
+
+/// palette order by OKLch: grays first from dark to light,
+/// then by hue in 30 degree steps and by lightness
+pub fn oklch_cmp(a: &ColorPro, b: &ColorPro) -> Ordering {
+    let key = |c: &ColorPro| {
+        let v = c[OKLchA].unwrap().v;
+        let hue = if v[1] < 0.03 {
+            -1
+        } else {
+            (v[2] / 30.0) as i32
+        };
+        (hue, v[0])
+    };
+    let (ka, kb) = (key(a), key(b));
+    ka.0.cmp(&kb.0).then(ka.1.total_cmp(&kb.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_dominant_colors() {
+        // 60% red, 30% blue, 10% gray, a transparent pixel
+        let mut rgba = vec![];
+        for i in 0..100 {
+            let p = match i {
+                0..=59 => [250, 10, 10, 255],
+                60..=89 => [10, 10, 240, 255],
+                _ => [128, 128, 128, 255],
+            };
+            rgba.extend(p);
+        }
+        rgba.extend([0, 255, 0, 0]);
+
+        for m in [QuantizeMethod::MedianCut, QuantizeMethod::KMeans] {
+            let cs = extract_palette(&rgba, 3, m);
+            assert_eq!(cs.len(), 3);
+            assert_eq!(cs[0].0.get_srgba_u8(), (250, 10, 10, 255));
+            assert!((cs[0].1 - 0.6).abs() < 1e-9);
+            assert_eq!(cs[1].0.get_srgba_u8(), (10, 10, 240, 255));
+            assert_eq!(cs[2].0.get_srgba_u8(), (128, 128, 128, 255));
+        }
+        assert_eq!(
+            extract_palette(&rgba, 8, QuantizeMethod::MedianCut).len(),
+            3
+        );
+
+        let mut cs: Vec<ColorPro> = extract_palette(&rgba, 3, QuantizeMethod::KMeans)
+            .iter()
+            .map(|c| c.0)
+            .collect();
+        cs.sort_by(oklch_cmp);
+        assert_eq!(cs[0].get_srgba_u8(), (128, 128, 128, 255));
+    }
+}
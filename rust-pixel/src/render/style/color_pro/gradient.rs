@@ -64,18 +64,58 @@ struct ColorStop {
     position: Fraction,
 }
 
+/// perceptual ramps built from a list of colors, like chroma.js scales
 #[derive(Debug, Clone)]
 pub struct ColorGradient {
     color_stops: Vec<ColorStop>,
+    // interpolate all stops as one bezier curve in OKLab
+    bezier: bool,
+    // lightness grows linearly with the position
+    correct_lightness: bool,
+    // 0 for a smooth ramp, n for n flat classes
+    classes: usize,
 }
 
 impl ColorGradient {
     pub fn empty() -> Self {
         Self {
             color_stops: Vec::new(),
+            bezier: false,
+            correct_lightness: false,
+            classes: 0,
         }
     }
 
+    /// stops evenly spaced from 0.0 to 1.0
+    pub fn new(colors: &[ColorPro]) -> Self {
+        let mut g = Self::empty();
+        let n = colors.len().max(2) - 1;
+        for (i, c) in colors.iter().enumerate() {
+            g.add_stop(*c, Fraction::from(i as f64 / n as f64));
+        }
+        g
+    }
+
+    /// smooth curve through all stops instead of linear pieces,
+    /// stop positions are ignored
+    pub fn bezier(mut self) -> Self {
+        self.bezier = true;
+        self
+    }
+
+    /// evens out the lightness steps, the lightness of the ramp
+    /// should grow or fall monotonically
+    pub fn correct_lightness(mut self) -> Self {
+        self.correct_lightness = true;
+        self
+    }
+
+    /// n flat classes instead of a smooth ramp, 0 or 1 for smooth
+    pub fn sharp(mut self, n: usize) -> Self {
+        self.classes = if n > 1 { n } else { 0 };
+        self
+    }
+
     pub fn add_stop(&mut self, color: ColorPro, position: Fraction) -> &mut Self {
         #![allow(clippy::float_cmp)]
         let same_position = self
@@ -135,4 +175,109 @@ impl ColorGradient {
             _ => None,
         }
     }
+
+    // de casteljau over the stops in OKLab
+    fn sample_bezier(&self, t: f64) -> ColorData {
+        let mut ps: Vec<ColorData> = self
+            .color_stops
+            .iter()
+            .map(|s| s.color[OKLabA].unwrap())
+            .collect();
+        while ps.len() > 1 {
+            for i in 0..ps.len() - 1 {
+                for j in 0..4 {
+                    ps[i].v[j] += (ps[i + 1].v[j] - ps[i].v[j]) * t;
+                }
+            }
+            ps.pop();
+        }
+        ps[0]
+    }
+
+    fn sample_raw(&self, t: f64) -> ColorPro {
+        if self.bezier {
+            ColorPro::from_space(OKLabA, self.sample_bezier(t))
+        } else {
+            let c = self.sample(Fraction::from(t), OKLchA).unwrap();
+            ColorPro::from_space(OKLchA, c)
+        }
+    }
+
+    // position where the lightness is the linear one of t, by bisection
+    fn lightness_position(&self, t: f64) -> f64 {
+        let l = |t: f64| self.sample_raw(t)[OKLabA].unwrap().v[0];
+        let (l0, l1) = (l(0.0), l(1.0));
+        let target = l0 + (l1 - l0) * t;
+        let (mut lo, mut hi, mut m) = (0.0, 1.0, t);
+        for _ in 0..20 {
+            let lm = l(m);
+            if (lm - target).abs() < 1e-4 {
+                break;
+            }
+            if (lm < target) == (l0 < l1) {
+                lo = m;
+            } else {
+                hi = m;
+            }
+            m = (lo + hi) / 2.0;
+        }
+        m
+    }
+
+    /// color at position t of 0.0..=1.0, None with less than 2 stops
+    pub fn at(&self, t: f64) -> Option<ColorPro> {
+        if self.color_stops.len() < 2 {
+            return None;
+        }
+        let mut t = t.clamp(0.0, 1.0);
+        if self.classes > 0 {
+            let n = self.classes as f64;
+            t = (t * n).floor().min(n - 1.0) / (n - 1.0);
+        }
+        if self.correct_lightness {
+            t = self.lightness_position(t);
+        }
+        Some(self.sample_raw(t))
+    }
+
+    /// n colors evenly spread over the gradient
+    pub fn colors(&self, n: usize) -> Vec<ColorPro> {
+        (0..n)
+            .filter_map(|i| self.at(i as f64 / (n.max(2) - 1) as f64))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gradient_builder() {
+        let black = ColorPro::from_space_u8(SRGBA, 0, 0, 0, 255);
+        let red = ColorPro::from_space_u8(SRGBA, 255, 0, 0, 255);
+        let yellow = ColorPro::from_space_u8(SRGBA, 255, 255, 0, 255);
+        let white = ColorPro::from_space_u8(SRGBA, 255, 255, 255, 255);
+        assert!(ColorGradient::new(&[red]).at(0.5).is_none());
+
+        let g = ColorGradient::new(&[black, red, yellow, white])
+            .bezier()
+            .correct_lightness();
+        let cs = g.colors(5);
+        assert_eq!(cs.len(), 5);
+        assert_eq!(cs[0].get_srgba_u8(), (0, 0, 0, 255));
+        assert_eq!(cs[4].get_srgba_u8(), (255, 255, 255, 255));
+        // even lightness steps
+        let ls: Vec<f64> = cs.iter().map(|c| c[OKLabA].unwrap().v[0]).collect();
+        for w in ls.windows(2) {
+            assert!((w[1] - w[0] - 0.25).abs() < 0.01, "{:?}", ls);
+        }
+
+        let g = ColorGradient::new(&[black, white]).sharp(3);
+        let l = |t: f64| g.at(t).unwrap().get_srgba_u8();
+        assert_eq!(l(0.0), l(0.3));
+        assert_eq!(l(0.9), (255, 255, 255, 255));
+        assert_ne!(l(0.5), l(0.3));
+        assert_ne!(l(0.5), l(0.9));
+    }
 }
@@ -520,3 +520,25 @@ pub fn xyz_to_hct(l: ColorData) -> ColorData {
 //     // let cam16_converted = to_cam16(xyz, &viewing_conditions);
 //     // println!("CAM16: {:?}", cam16_converted);
 // }
+
+// CAM16-UCS J' a' b' of CAM16 J M h
+#[inline(always)]
+pub fn cam16_to_ucs(l: ColorData) -> ColorData {
+    let (j, m, h) = (l.v[0], l.v[1], l.v[2].to_radians());
+    let jp = 1.7 * j / (1.0 + 0.007 * j);
+    let mp = (1.0 + 0.0228 * m).ln() / 0.0228;
+    ColorData {
+        v: [jp, mp * h.cos(), mp * h.sin(), l.v[3]],
+    }
+}
+
+#[inline(always)]
+pub fn ucs_to_cam16(l: ColorData) -> ColorData {
+    let (jp, a, b) = (l.v[0], l.v[1], l.v[2]);
+    let j = jp / (1.7 - 0.007 * jp);
+    let m = ((a.hypot(b) * 0.0228).exp() - 1.0) / 0.0228;
+    let h = b.atan2(a).to_degrees().rem_euclid(360.0);
+    ColorData {
+        v: [j, m, h, l.v[3]],
+    }
+}
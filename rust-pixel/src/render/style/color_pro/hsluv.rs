@@ -0,0 +1,151 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! HSLuv, a human friendly alternative to HSL
+//!
+//! Hue and lightness come from CIE LCh(uv), saturation is the chroma
+//! relative to the most the sRGB gamut has at that lightness and hue,
+//! so every h, s, l is a valid color and equal l looks equally light.
+//! Refer:
+//!   https://www.hsluv.org/math/
+
+use crate::render::style::color_pro::*;
+
+// xyz to linear srgb, as in the reference implementation
+const M: [[f64; 3]; 3] = [
+    [3.240969941904521, -1.537383177570093, -0.498610760293],
+    [-0.96924363628087, 1.87596750150772, 0.041555057407175],
+    [0.055630079696993, -0.20397695888897, 1.056971514242878],
+];
+const REF_U: f64 = 0.19783000664283;
+const REF_V: f64 = 0.46831999493879;
+
+// the six lines bounding the srgb gamut in the uv plane at lightness l,
+// as (slope, intercept)
+fn bounds(l: f64) -> Vec<(f64, f64)> {
+    let sub1 = (l + 16.0).powi(3) / 1560896.0;
+    let sub2 = if sub1 > EPSILON_LSTAR {
+        sub1
+    } else {
+        l / KAPPA
+    };
+    let mut lines = vec![];
+    for m in M.iter() {
+        for t in [0.0, 1.0] {
+            let top1 = (284517.0 * m[0] - 94839.0 * m[2]) * sub2;
+            let top2 =
+                (838422.0 * m[2] + 769860.0 * m[1] + 731718.0 * m[0]) * l * sub2 - 769860.0 * t * l;
+            let bottom = (632260.0 * m[2] - 126452.0 * m[1]) * sub2 + 126452.0 * t;
+            lines.push((top1 / bottom, top2 / bottom));
+        }
+    }
+    lines
+}
+
+fn max_chroma(l: f64, h: f64) -> f64 {
+    let hrad = h.to_radians();
+    bounds(l)
+        .iter()
+        .map(|(slope, intercept)| intercept / (hrad.sin() - slope * hrad.cos()))
+        .filter(|len| *len >= 0.0)
+        .fold(f64::MAX, f64::min)
+}
+
+pub fn xyz_to_hsluv(xyz: ColorData) -> ColorData {
+    let (x, y, z) = (xyz.v[0], xyz.v[1], xyz.v[2]);
+    // Luv
+    let l = if y <= EPSILON_LSTAR {
+        y * KAPPA
+    } else {
+        116.0 * y.cbrt() - 16.0
+    };
+    let divider = x + 15.0 * y + 3.0 * z;
+    let (u, v) = if l < 1e-8 || divider == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (
+            13.0 * l * (4.0 * x / divider - REF_U),
+            13.0 * l * (9.0 * y / divider - REF_V),
+        )
+    };
+    // LCh(uv)
+    let c = u.hypot(v);
+    let h = if c < 1e-8 {
+        0.0
+    } else {
+        v.atan2(u).to_degrees().rem_euclid(360.0)
+    };
+    // HSLuv
+    let (s, l) = if l > 99.9999999 {
+        (0.0, 100.0)
+    } else if l < 1e-8 {
+        (0.0, 0.0)
+    } else {
+        ((c / max_chroma(l, h) * 100.0).min(100.0), l)
+    };
+    ColorData {
+        v: [h, s, l, xyz.v[3]],
+    }
+}
+
+pub fn hsluv_to_xyz(hsluv: ColorData) -> ColorData {
+    let (h, s, l) = (hsluv.v[0], hsluv.v[1], hsluv.v[2]);
+    if l < 1e-8 {
+        return ColorData {
+            v: [0.0, 0.0, 0.0, hsluv.v[3]],
+        };
+    }
+    let l = l.min(100.0);
+    let c = if l > 99.9999999 {
+        0.0
+    } else {
+        max_chroma(l, h) / 100.0 * s
+    };
+    let hrad = h.to_radians();
+    let (u, v) = (c * hrad.cos(), c * hrad.sin());
+    let var_u = u / (13.0 * l) + REF_U;
+    let var_v = v / (13.0 * l) + REF_V;
+    let y = if l <= 8.0 {
+        l / KAPPA
+    } else {
+        ((l + 16.0) / 116.0).powi(3)
+    };
+    let x = -(9.0 * y * var_u) / ((var_u - 4.0) * var_v - var_u * var_v);
+    let z = (9.0 * y - 15.0 * var_v * y - var_v * x) / (3.0 * var_v);
+    ColorData {
+        v: [x, y, z, hsluv.v[3]],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_gamut_and_uniform_spaces() {
+        let red = ColorPro::from_space_f64(SRGBA, 1.0, 0.0, 0.0, 1.0);
+        let v = red[HSLuvA].unwrap().v;
+        assert!((v[0] - 12.177).abs() < 0.01);
+        assert!((v[1] - 100.0).abs() < 0.01);
+        assert!((v[2] - 53.237).abs() < 0.01);
+        let back = ColorPro::from_space_f64(HSLuvA, v[0], v[1], v[2], 1.0);
+        assert_eq!(back.get_srgba_u8(), (255, 0, 0, 255));
+        let white = ColorPro::from_space_f64(SRGBA, 1.0, 1.0, 1.0, 1.0);
+        assert!((white[HSLuvA].unwrap().v[2] - 100.0).abs() < 1e-6);
+
+        // srgb red lies inside p3
+        let p3 = red[DisplayP3A].unwrap().v;
+        assert!((p3[0] - 0.9175).abs() < 0.001);
+        assert!((p3[1] - 0.2003).abs() < 0.001);
+        assert!((p3[2] - 0.1386).abs() < 0.001);
+        let back = ColorPro::from_space_f64(DisplayP3A, p3[0], p3[1], p3[2], 1.0);
+        assert_eq!(back.get_srgba_u8(), (255, 0, 0, 255));
+
+        let ucs = red[CAM16UCSA].unwrap();
+        let back = ColorPro::from_space(CAM16UCSA, ucs);
+        assert_eq!(back.get_srgba_u8(), (255, 0, 0, 255));
+        let dark = ColorPro::from_space_f64(SRGBA, 0.9, 0.0, 0.0, 1.0);
+        let d = delta_e_cam16ucs(ucs, dark[CAM16UCSA].unwrap());
+        assert!(d > 1.0 && d < delta_e_cam16ucs(ucs, white[CAM16UCSA].unwrap()));
+    }
+}
@@ -0,0 +1,428 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Named colors of css, x11 and the xkcd color survey
+//!
+//! Names are matched ignoring case, spaces, '-' and '_', a "css:", "x11:"
+//! or "xkcd:" prefix picks the set, otherwise css is tried first, then
+//! x11 and xkcd. x11 shares most names with css, only the colors that
+//! differ are kept in its table.
+
+use crate::render::style::color_pro::*;
+use lazy_static::lazy_static;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorNameSet {
+    Css,
+    X11,
+    Xkcd,
+}
+
+impl ColorNameSet {
+    pub const ALL: [ColorNameSet; 3] = [ColorNameSet::Css, ColorNameSet::X11, ColorNameSet::Xkcd];
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            ColorNameSet::Css => "css",
+            ColorNameSet::X11 => "x11",
+            ColorNameSet::Xkcd => "xkcd",
+        }
+    }
+
+    pub fn table(self) -> &'static [(&'static str, u8, u8, u8)] {
+        match self {
+            ColorNameSet::Css => &CSS_COLOR_NAMES,
+            ColorNameSet::X11 => &X11_COLOR_NAMES,
+            ColorNameSet::Xkcd => &XKCD_COLOR_NAMES,
+        }
+    }
+
+    fn labs(self) -> &'static [ColorData] {
+        match self {
+            ColorNameSet::Css => &CSS_LABS,
+            ColorNameSet::X11 => &X11_LABS,
+            ColorNameSet::Xkcd => &XKCD_LABS,
+        }
+    }
+}
+
+fn labs_of(table: &[(&str, u8, u8, u8)]) -> Vec<ColorData> {
+    table
+        .iter()
+        .map(|c| ColorPro::from_space_u8(SRGBA, c.1, c.2, c.3, 255)[LabA].unwrap())
+        .collect()
+}
+
+lazy_static! {
+    static ref CSS_LABS: Vec<ColorData> = labs_of(&CSS_COLOR_NAMES);
+    static ref X11_LABS: Vec<ColorData> = labs_of(&X11_COLOR_NAMES);
+    static ref XKCD_LABS: Vec<ColorData> = labs_of(&XKCD_COLOR_NAMES);
+}
+
+// lowercase letters and digits only
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn lookup(set: ColorNameSet, key: &str) -> Option<ColorPro> {
+    set.table()
+        .iter()
+        .find(|c| normalize(c.0) == key)
+        .map(|c| ColorPro::from_space_u8(SRGBA, c.1, c.2, c.3, 255))
+}
+
+impl ColorPro {
+    /// color of a css, x11 or xkcd name, e.g. "rebeccapurple" or "xkcd:puke green"
+    pub fn from_name(name: &str) -> Option<ColorPro> {
+        let name = name.trim();
+        for set in ColorNameSet::ALL {
+            if let Some(n) = name
+                .strip_prefix(set.prefix())
+                .and_then(|n| n.strip_prefix(':'))
+            {
+                let key = normalize(n);
+                return lookup(set, &key).or_else(|| match set {
+                    // x11 shares the other names with css
+                    ColorNameSet::X11 => lookup(ColorNameSet::Css, &key),
+                    _ => None,
+                });
+            }
+        }
+        let key = normalize(name);
+        ColorNameSet::ALL.iter().find_map(|s| lookup(*s, &key))
+    }
+
+    /// nearest css color name by ciede2000
+    pub fn nearest_name(&self) -> &'static str {
+        self.nearest_name_in(ColorNameSet::Css).0
+    }
+
+    /// nearest name of a set and its delta_e_ciede2000
+    pub fn nearest_name_in(&self, set: ColorNameSet) -> (&'static str, f64) {
+        let lab = self[LabA].unwrap();
+        set.labs()
+            .iter()
+            .zip(set.table())
+            .map(|(l, c)| (c.0, delta_e_ciede2000(lab, *l)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or(("", f64::MAX))
+    }
+}
+
+/// names of all sets containing query, as "set:name" with their colors,
+/// an empty query matches nothing
+pub fn search_color_names(query: &str) -> Vec<(String, ColorPro)> {
+    let q = normalize(query);
+    let mut found = vec![];
+    if q.is_empty() {
+        return found;
+    }
+    for set in ColorNameSet::ALL {
+        for c in set.table() {
+            if normalize(c.0).contains(&q) {
+                found.push((
+                    format!("{}:{}", set.prefix(), c.0),
+                    ColorPro::from_space_u8(SRGBA, c.1, c.2, c.3, 255),
+                ));
+            }
+        }
+    }
+    found
+}
+
+/// css named colors
+pub static CSS_COLOR_NAMES: [(&str, u8, u8, u8); 148] = [
+    ("aliceblue", 240, 248, 255),
+    ("antiquewhite", 250, 235, 215),
+    ("aqua", 0, 255, 255),
+    ("aquamarine", 127, 255, 212),
+    ("azure", 240, 255, 255),
+    ("beige", 245, 245, 220),
+    ("bisque", 255, 228, 196),
+    ("black", 0, 0, 0),
+    ("blanchedalmond", 255, 235, 205),
+    ("blue", 0, 0, 255),
+    ("blueviolet", 138, 43, 226),
+    ("brown", 165, 42, 42),
+    ("burlywood", 222, 184, 135),
+    ("cadetblue", 95, 158, 160),
+    ("chartreuse", 127, 255, 0),
+    ("chocolate", 210, 105, 30),
+    ("coral", 255, 127, 80),
+    ("cornflowerblue", 100, 149, 237),
+    ("cornsilk", 255, 248, 220),
+    ("crimson", 220, 20, 60),
+    ("cyan", 0, 255, 255),
+    ("darkblue", 0, 0, 139),
+    ("darkcyan", 0, 139, 139),
+    ("darkgoldenrod", 184, 134, 11),
+    ("darkgray", 169, 169, 169),
+    ("darkgreen", 0, 100, 0),
+    ("darkgrey", 169, 169, 169),
+    ("darkkhaki", 189, 183, 107),
+    ("darkmagenta", 139, 0, 139),
+    ("darkolivegreen", 85, 107, 47),
+    ("darkorange", 255, 140, 0),
+    ("darkorchid", 153, 50, 204),
+    ("darkred", 139, 0, 0),
+    ("darksalmon", 233, 150, 122),
+    ("darkseagreen", 143, 188, 143),
+    ("darkslateblue", 72, 61, 139),
+    ("darkslategray", 47, 79, 79),
+    ("darkslategrey", 47, 79, 79),
+    ("darkturquoise", 0, 206, 209),
+    ("darkviolet", 148, 0, 211),
+    ("deeppink", 255, 20, 147),
+    ("deepskyblue", 0, 191, 255),
+    ("dimgray", 105, 105, 105),
+    ("dimgrey", 105, 105, 105),
+    ("dodgerblue", 30, 144, 255),
+    ("firebrick", 178, 34, 34),
+    ("floralwhite", 255, 250, 240),
+    ("forestgreen", 34, 139, 34),
+    ("fuchsia", 255, 0, 255),
+    ("gainsboro", 220, 220, 220),
+    ("ghostwhite", 248, 248, 255),
+    ("gold", 255, 215, 0),
+    ("goldenrod", 218, 165, 32),
+    ("gray", 128, 128, 128),
+    ("green", 0, 128, 0),
+    ("greenyellow", 173, 255, 47),
+    ("grey", 128, 128, 128),
+    ("honeydew", 240, 255, 240),
+    ("hotpink", 255, 105, 180),
+    ("indianred", 205, 92, 92),
+    ("indigo", 75, 0, 130),
+    ("ivory", 255, 255, 240),
+    ("khaki", 240, 230, 140),
+    ("lavender", 230, 230, 250),
+    ("lavenderblush", 255, 240, 245),
+    ("lawngreen", 124, 252, 0),
+    ("lemonchiffon", 255, 250, 205),
+    ("lightblue", 173, 216, 230),
+    ("lightcoral", 240, 128, 128),
+    ("lightcyan", 224, 255, 255),
+    ("lightgoldenrodyellow", 250, 250, 210),
+    ("lightgray", 211, 211, 211),
+    ("lightgreen", 144, 238, 144),
+    ("lightgrey", 211, 211, 211),
+    ("lightpink", 255, 182, 193),
+    ("lightsalmon", 255, 160, 122),
+    ("lightseagreen", 32, 178, 170),
+    ("lightskyblue", 135, 206, 250),
+    ("lightslategray", 119, 136, 153),
+    ("lightslategrey", 119, 136, 153),
+    ("lightsteelblue", 176, 196, 222),
+    ("lightyellow", 255, 255, 224),
+    ("lime", 0, 255, 0),
+    ("limegreen", 50, 205, 50),
+    ("linen", 250, 240, 230),
+    ("magenta", 255, 0, 255),
+    ("maroon", 128, 0, 0),
+    ("mediumaquamarine", 102, 205, 170),
+    ("mediumblue", 0, 0, 205),
+    ("mediumorchid", 186, 85, 211),
+    ("mediumpurple", 147, 112, 219),
+    ("mediumseagreen", 60, 179, 113),
+    ("mediumslateblue", 123, 104, 238),
+    ("mediumspringgreen", 0, 250, 154),
+    ("mediumturquoise", 72, 209, 204),
+    ("mediumvioletred", 199, 21, 133),
+    ("midnightblue", 25, 25, 112),
+    ("mintcream", 245, 255, 250),
+    ("mistyrose", 255, 228, 225),
+    ("moccasin", 255, 228, 181),
+    ("navajowhite", 255, 222, 173),
+    ("navy", 0, 0, 128),
+    ("oldlace", 253, 245, 230),
+    ("olive", 128, 128, 0),
+    ("olivedrab", 107, 142, 35),
+    ("orange", 255, 165, 0),
+    ("orangered", 255, 69, 0),
+    ("orchid", 218, 112, 214),
+    ("palegoldenrod", 238, 232, 170),
+    ("palegreen", 152, 251, 152),
+    ("paleturquoise", 175, 238, 238),
+    ("palevioletred", 219, 112, 147),
+    ("papayawhip", 255, 239, 213),
+    ("peachpuff", 255, 218, 185),
+    ("peru", 205, 133, 63),
+    ("pink", 255, 192, 203),
+    ("plum", 221, 160, 221),
+    ("powderblue", 176, 224, 230),
+    ("purple", 128, 0, 128),
+    ("rebeccapurple", 102, 51, 153),
+    ("red", 255, 0, 0),
+    ("rosybrown", 188, 143, 143),
+    ("royalblue", 65, 105, 225),
+    ("saddlebrown", 139, 69, 19),
+    ("salmon", 250, 128, 114),
+    ("sandybrown", 244, 164, 96),
+    ("seagreen", 46, 139, 87),
+    ("seashell", 255, 245, 238),
+    ("sienna", 160, 82, 45),
+    ("silver", 192, 192, 192),
+    ("skyblue", 135, 206, 235),
+    ("slateblue", 106, 90, 205),
+    ("slategray", 112, 128, 144),
+    ("slategrey", 112, 128, 144),
+    ("snow", 255, 250, 250),
+    ("springgreen", 0, 255, 127),
+    ("steelblue", 70, 130, 180),
+    ("tan", 210, 180, 140),
+    ("teal", 0, 128, 128),
+    ("thistle", 216, 191, 216),
+    ("tomato", 255, 99, 71),
+    ("turquoise", 64, 224, 208),
+    ("violet", 238, 130, 238),
+    ("wheat", 245, 222, 179),
+    ("white", 255, 255, 255),
+    ("whitesmoke", 245, 245, 245),
+    ("yellow", 255, 255, 0),
+    ("yellowgreen", 154, 205, 50),
+];
+
+/// x11 colors whose rgb differs from css or which css does not have
+pub static X11_COLOR_NAMES: [(&str, u8, u8, u8); 14] = [
+    ("gray", 190, 190, 190),
+    ("green", 0, 255, 0),
+    ("grey", 190, 190, 190),
+    ("lightgoldenrod", 238, 221, 130),
+    ("lightslateblue", 132, 112, 255),
+    ("maroon", 176, 48, 96),
+    ("navyblue", 0, 0, 128),
+    ("purple", 160, 32, 240),
+    ("violetred", 208, 32, 144),
+    ("webgray", 128, 128, 128),
+    ("webgreen", 0, 128, 0),
+    ("webgrey", 128, 128, 128),
+    ("webmaroon", 128, 0, 0),
+    ("webpurple", 128, 0, 128),
+];
+
+/// the most named colors of the xkcd color survey
+pub static XKCD_COLOR_NAMES: [(&str, u8, u8, u8); 66] = [
+    ("purple", 126, 30, 156),
+    ("green", 21, 176, 26),
+    ("blue", 3, 67, 223),
+    ("pink", 255, 129, 192),
+    ("brown", 101, 55, 0),
+    ("red", 229, 0, 0),
+    ("light blue", 149, 208, 252),
+    ("teal", 2, 147, 134),
+    ("orange", 249, 115, 6),
+    ("light green", 150, 249, 123),
+    ("magenta", 194, 0, 120),
+    ("yellow", 255, 255, 20),
+    ("sky blue", 117, 187, 253),
+    ("grey", 146, 149, 145),
+    ("lime green", 137, 254, 5),
+    ("light purple", 191, 119, 246),
+    ("violet", 154, 14, 234),
+    ("dark green", 3, 53, 0),
+    ("turquoise", 6, 194, 172),
+    ("lavender", 199, 159, 239),
+    ("dark blue", 0, 3, 91),
+    ("tan", 209, 178, 111),
+    ("cyan", 0, 255, 255),
+    ("aqua", 19, 234, 201),
+    ("forest green", 6, 71, 12),
+    ("mauve", 174, 113, 129),
+    ("dark purple", 53, 6, 62),
+    ("bright green", 1, 255, 7),
+    ("maroon", 101, 0, 33),
+    ("olive", 110, 117, 14),
+    ("salmon", 255, 121, 108),
+    ("beige", 230, 218, 166),
+    ("royal blue", 5, 4, 170),
+    ("navy blue", 0, 17, 70),
+    ("lilac", 206, 162, 253),
+    ("black", 0, 0, 0),
+    ("hot pink", 255, 2, 141),
+    ("light brown", 173, 129, 80),
+    ("pale green", 199, 253, 181),
+    ("peach", 255, 176, 124),
+    ("olive green", 103, 122, 4),
+    ("dark pink", 203, 65, 107),
+    ("periwinkle", 142, 130, 254),
+    ("sea green", 83, 252, 161),
+    ("lime", 170, 255, 50),
+    ("indigo", 56, 2, 130),
+    ("mustard", 206, 179, 1),
+    ("light pink", 255, 209, 223),
+    ("white", 255, 255, 255),
+    ("navy", 1, 21, 62),
+    ("puke green", 154, 174, 7),
+    ("baby blue", 162, 207, 254),
+    ("burnt orange", 192, 78, 1),
+    ("dusty rose", 192, 115, 122),
+    ("eggshell", 255, 255, 212),
+    ("kelly green", 2, 171, 46),
+    ("mint green", 143, 255, 159),
+    ("neon green", 12, 255, 12),
+    ("rust", 168, 60, 9),
+    ("gold", 219, 180, 12),
+    ("crimson", 140, 0, 15),
+    ("light grey", 216, 220, 214),
+    ("dark grey", 54, 55, 55),
+    ("cobalt", 30, 72, 143),
+    ("dark red", 132, 0, 0),
+    ("khaki", 170, 166, 98),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_names() {
+        let c = ColorPro::from_name("rebeccapurple").unwrap();
+        assert_eq!(c.get_srgba_u8(), (102, 51, 153, 255));
+        assert_eq!(
+            ColorPro::from_name("Rebecca Purple")
+                .unwrap()
+                .get_srgba_u8(),
+            (102, 51, 153, 255)
+        );
+        assert_eq!(
+            ColorPro::from_name("gray").unwrap().get_srgba_u8(),
+            (128, 128, 128, 255)
+        );
+        assert_eq!(
+            ColorPro::from_name("x11:gray").unwrap().get_srgba_u8(),
+            (190, 190, 190, 255)
+        );
+        // x11 falls back to css names
+        assert_eq!(
+            ColorPro::from_name("x11:red").unwrap().get_srgba_u8(),
+            (255, 0, 0, 255)
+        );
+        assert_eq!(
+            ColorPro::from_name("xkcd:puke green")
+                .unwrap()
+                .get_srgba_u8(),
+            (154, 174, 7, 255)
+        );
+        assert_eq!(
+            ColorPro::from_name("periwinkle").unwrap().get_srgba_u8(),
+            (142, 130, 254, 255)
+        );
+        assert!(ColorPro::from_name("xkcd:rebeccapurple").is_none());
+        assert!(ColorPro::from_name("nocolor").is_none());
+
+        assert_eq!(c.nearest_name(), "rebeccapurple");
+        let near = ColorPro::from_space_u8(SRGBA, 250, 130, 112, 255);
+        assert_eq!(near.nearest_name(), "salmon");
+        let (n, d) = near.nearest_name_in(ColorNameSet::Xkcd);
+        assert_eq!(n, "salmon");
+        assert!(d > 0.0);
+
+        let found = search_color_names("slate");
+        assert!(found.iter().any(|f| f.0 == "css:darkslateblue"));
+        assert!(found.iter().any(|f| f.0 == "x11:lightslateblue"));
+        assert!(search_color_names(" ").is_empty());
+    }
+}
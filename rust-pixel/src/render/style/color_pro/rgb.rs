@@ -78,3 +78,30 @@ pub fn xyz_to_srgba(xyz: ColorData) -> ColorData {
     linear_to_srgba(l)
 }
 
+
+// Display P3, D65 white and the srgb transfer function
+#[inline(always)]
+pub fn p3_to_xyz(p3: ColorData) -> ColorData {
+    let r = linearize(p3.v[0]);
+    let g = linearize(p3.v[1]);
+    let b = linearize(p3.v[2]);
+    let x = r * 0.4865709486482162 + g * 0.26566769316909306 + b * 0.1982172852343625;
+    let y = r * 0.2289745640697488 + g * 0.6917385218365064 + b * 0.079286914093745;
+    let z = g * 0.04511338185890264 + b * 1.043944368900976;
+
+    ColorData {
+        v: [x, y, z, p3.v[3]],
+    }
+}
+
+#[inline(always)]
+pub fn xyz_to_p3(xyz: ColorData) -> ColorData {
+    let (x, y, z) = (xyz.v[0], xyz.v[1], xyz.v[2]);
+    let r = x * 2.493496911941425 - y * 0.9313836179191239 - z * 0.40271078445071684;
+    let g = x * -0.8294889695615747 + y * 1.7626640603183463 + z * 0.023624685841943577;
+    let b = x * 0.03584583024378447 - y * 0.07617238926804182 + z * 0.9568845240076872;
+
+    ColorData {
+        v: [delinearize(r), delinearize(g), delinearize(b), xyz.v[3]],
+    }
+}
@@ -0,0 +1,127 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Color vision deficiency simulation and WCAG contrast
+//!
+//! Simulation uses the Machado 2009 matrices of full severity,
+//! applied in linear rgb.
+//! Refer:
+//!   https://www.inf.ufrgs.br/~oliveira/pubs_files/CVD_Simulation/CVD_Simulation.html
+//!   https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+
+use crate::render::style::color_pro::*;
+use crate::render::style::Color;
+
+/// minimum contrast of normal text, WCAG AA
+pub const WCAG_AA: f64 = 4.5;
+/// minimum contrast of large text, WCAG AA
+pub const WCAG_AA_LARGE: f64 = 3.0;
+/// minimum contrast of normal text, WCAG AAA
+pub const WCAG_AAA: f64 = 7.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorBlindness {
+    /// no red cones
+    Protanopia,
+    /// no green cones
+    Deuteranopia,
+    /// no blue cones
+    Tritanopia,
+    /// no color at all
+    Achromatopsia,
+}
+
+impl ColorBlindness {
+    pub const ALL: [ColorBlindness; 4] = [
+        ColorBlindness::Protanopia,
+        ColorBlindness::Deuteranopia,
+        ColorBlindness::Tritanopia,
+        ColorBlindness::Achromatopsia,
+    ];
+
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+            ColorBlindness::Achromatopsia => [[0.2126, 0.7152, 0.0722]; 3],
+        }
+    }
+}
+
+impl ColorPro {
+    /// the color as seen with a color vision deficiency
+    pub fn simulate(&self, cb: ColorBlindness) -> ColorPro {
+        let c = self[LinearRGBA].unwrap().v;
+        let m = cb.matrix();
+        let v: Vec<f64> = m
+            .iter()
+            .map(|r| (r[0] * c[0] + r[1] * c[1] + r[2] * c[2]).clamp(0.0, 1.0))
+            .collect();
+        ColorPro::from_space_f64(LinearRGBA, v[0], v[1], v[2], c[3])
+    }
+
+    /// WCAG contrast ratio, from 1.0 to 21.0
+    pub fn contrast(&self, other: &ColorPro) -> f64 {
+        contrast_ratio(self, other)
+    }
+}
+
+/// WCAG contrast ratio of two colors, from 1.0 to 21.0
+pub fn contrast_ratio(a: &ColorPro, b: &ColorPro) -> f64 {
+    let (la, lb) = (a.luminance(), b.luminance());
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// simulates a cell color, Reset is kept
+pub fn simulate_color(c: Color, cb: ColorBlindness) -> Color {
+    if c == Color::Reset {
+        return c;
+    }
+    let (r, g, b, a) = c.get_rgba();
+    let s = ColorPro::from_space_u8(SRGBA, r, g, b, a).simulate(cb);
+    let (r, g, b, _) = s.get_srgba_u8();
+    Color::Rgba(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulate_and_contrast() {
+        let rgb = |r, g, b| ColorPro::from_space_u8(SRGBA, r, g, b, 255);
+        let black = rgb(0, 0, 0);
+        let white = rgb(255, 255, 255);
+        assert!((contrast_ratio(&black, &white) - 21.0).abs() < 1e-6);
+        assert!((white.contrast(&white) - 1.0).abs() < 1e-6);
+        assert!(rgb(118, 118, 118).contrast(&white) >= WCAG_AA);
+
+        // grays are seen the same
+        for cb in ColorBlindness::ALL {
+            let (r, g, b, _) = rgb(128, 128, 128).simulate(cb).get_srgba_u8();
+            assert!(r.abs_diff(128) <= 1 && g.abs_diff(128) <= 1 && b.abs_diff(128) <= 1);
+        }
+        // red and green get close without green cones
+        let (red, green) = (rgb(200, 60, 40), rgb(80, 140, 40));
+        let d = |a: &ColorPro, b: &ColorPro| delta_e_ciede2000(a[LabA].unwrap(), b[LabA].unwrap());
+        let cb = ColorBlindness::Deuteranopia;
+        assert!(d(&red.simulate(cb), &green.simulate(cb)) < d(&red, &green) / 2.0);
+
+        let (r, g, b, _) = simulate_color(Color::Red, ColorBlindness::Achromatopsia).get_rgba();
+        assert!(r == g && g == b);
+        assert_eq!(simulate_color(Color::Reset, cb), Color::Reset);
+    }
+}
@@ -0,0 +1,177 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! A miniature markup for styled text, e.g.
+//! ```text
+//! [red]HP[/red] [bold]100[/bold] [bg=blue]slot [[1][/]
+//! ```
+//! Supported tags:
+//! - color names: `[red]` `[light_blue]` ... or `[#ff8000]`, `[fg=red]`
+//! - background: `[bg=blue]` `[bg=#202020]`
+//! - indexed color: `[fg=208]` `[bg=17]`
+//! - modifiers: `[bold]` `[dim]` `[italic]` `[underline]` `[blink]` `[reverse]` `[hidden]` `[crossed]`
+//!
+//! A tag is closed by `[/name]` or `[/]`, which closes the latest opened tag.
+//! Use `[[` for a literal `[`. Unknown tags are kept as plain text.
+
+use crate::render::style::{Color, Modifier, Style};
+
+/// parses a color name, #rrggbb or an ansi index
+pub fn color_from_name(name: &str) -> Option<Color> {
+    let n = name.trim().to_lowercase();
+    if let Some(hex) = n.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let v = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::Rgba(
+            (v >> 16) as u8,
+            (v >> 8) as u8,
+            v as u8,
+            255,
+        ));
+    }
+    if let Ok(i) = n.parse::<u8>() {
+        return Some(Color::Indexed(i));
+    }
+    let c = match n.as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark_gray" | "dark_grey" => Color::DarkGray,
+        "light_red" => Color::LightRed,
+        "light_green" => Color::LightGreen,
+        "light_yellow" => Color::LightYellow,
+        "light_blue" => Color::LightBlue,
+        "light_magenta" => Color::LightMagenta,
+        "light_cyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn modifier_from_name(name: &str) -> Option<Modifier> {
+    let m = match name {
+        "bold" | "b" => Modifier::BOLD,
+        "dim" => Modifier::DIM,
+        "italic" | "i" => Modifier::ITALIC,
+        "underline" | "u" => Modifier::UNDERLINED,
+        "blink" => Modifier::SLOW_BLINK,
+        "rapid_blink" => Modifier::RAPID_BLINK,
+        "reverse" => Modifier::REVERSED,
+        "hidden" => Modifier::HIDDEN,
+        "crossed" | "s" => Modifier::CROSSED_OUT,
+        _ => return None,
+    };
+    Some(m)
+}
+
+/// style patch of a tag, None for unknown tags
+fn tag_style(tag: &str) -> Option<Style> {
+    let tag = tag.trim().to_lowercase();
+    if let Some(c) = tag.strip_prefix("bg=") {
+        return color_from_name(c).map(|c| Style::default().bg(c));
+    }
+    if let Some(c) = tag.strip_prefix("fg=") {
+        return color_from_name(c).map(|c| Style::default().fg(c));
+    }
+    if let Some(m) = modifier_from_name(&tag) {
+        return Some(Style::default().add_modifier(m));
+    }
+    // a bare number like [3] is text, indexed colors need fg= or bg=
+    if tag.parse::<u8>().is_ok() {
+        return None;
+    }
+    color_from_name(&tag).map(|c| Style::default().fg(c))
+}
+
+/// splits markup text into (text, style) spans, base is the style of untagged text
+pub fn parse_markup(text: &str, base: Style) -> Vec<(String, Style)> {
+    let mut spans: Vec<(String, Style)> = vec![];
+    // opened tag name and the style before it
+    let mut stack: Vec<(String, Style)> = vec![];
+    let mut style = base;
+    let mut cur = String::new();
+    let mut rest = text;
+
+    while let Some(pos) = rest.find('[') {
+        cur.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if rest.starts_with("[[") {
+            cur.push('[');
+            rest = &rest[2..];
+            continue;
+        }
+        let end = match rest.find(']') {
+            Some(e) => e,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        let mut handled = true;
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_lowercase();
+            let idx = if name.is_empty() {
+                stack.len().checked_sub(1)
+            } else {
+                stack.iter().rposition(|t| t.0 == name)
+            };
+            match idx {
+                Some(i) => {
+                    flush_span(&mut spans, &mut cur, style);
+                    style = stack[i].1;
+                    stack.truncate(i);
+                }
+                None => handled = false,
+            }
+        } else if let Some(patch) = tag_style(tag) {
+            flush_span(&mut spans, &mut cur, style);
+            stack.push((tag_name(tag), style));
+            style = style.patch(patch);
+        } else {
+            handled = false;
+        }
+        if handled {
+            rest = &rest[end + 1..];
+        } else {
+            cur.push('[');
+            rest = &rest[1..];
+        }
+    }
+    cur.push_str(rest);
+    flush_span(&mut spans, &mut cur, style);
+    spans
+}
+
+// name used to match the closing tag, [bg=red] is closed by [/bg]
+fn tag_name(tag: &str) -> String {
+    let t = tag.trim().to_lowercase();
+    match t.find('=') {
+        Some(p) => t[..p].to_string(),
+        None => t,
+    }
+}
+
+fn flush_span(spans: &mut Vec<(String, Style)>, cur: &mut String, style: Style) {
+    if !cur.is_empty() {
+        spans.push((std::mem::take(cur), style));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bare_number_is_text() {
+        let spans = parse_markup("slot [3] [fg=3]x[/]", Style::default());
+        assert_eq!(spans[0], ("slot [3] ".to_string(), Style::default()));
+        assert_eq!(spans[1], ("x".to_string(), Style::default().fg(Color::Indexed(3))));
+    }
+}
@@ -0,0 +1,321 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Maps truecolor to the nearest terminal palette color
+//!
+//! The palette is kept in a k-d tree over CIE Lab, the few nearest
+//! candidates by euclidean Lab distance (delta_e_cie76) are ranked again
+//! by delta_e_ciede2000. Lookups are cached, so mapping a whole frame on a
+//! terminal without 24bit color stays cheap.
+//!
+//! # Example
+//! ```
+//! use rust_pixel::render::style::{Color, ColorDepth};
+//!
+//! let fg = Color::Rgba(250, 128, 10, 255);
+//! let c256 = fg.to_indexed_256();
+//! assert!(matches!(c256, Color::Indexed(_)));
+//!
+//! // what the terminal can show, by COLORTERM and TERM
+//! let depth = ColorDepth::from_env(None, Some("xterm-256color"), None);
+//! assert_eq!(depth.reduce(fg), c256);
+//! ```
+
+use crate::render::style::{
+    delta_e_ciede2000, Color, ColorData, ColorPro, ColorSpace::*, ANSI_COLOR_RGB,
+};
+use lazy_static::lazy_static;
+use std::{collections::HashMap, sync::Mutex};
+
+// candidates re-ranked by ciede2000
+const CANDIDATES: usize = 4;
+// cache is dropped when it grows past this
+const CACHE_MAX: usize = 1 << 16;
+
+lazy_static! {
+    static ref ANSI_TREE_256: ColorTree = ColorTree::new(&ANSI_COLOR_RGB);
+    static ref ANSI_TREE_16: ColorTree = ColorTree::new(&ANSI_COLOR_RGB[..16]);
+    static ref NEAREST_CACHE: Mutex<HashMap<(u8, u8, u8, bool), u8>> = Mutex::new(HashMap::new());
+}
+
+fn lab_of(r: u8, g: u8, b: u8) -> ColorData {
+    ColorPro::from_space_u8(SRGBA, r, g, b, 255)[LabA].unwrap()
+}
+
+fn dist2(a: &ColorData, b: &ColorData) -> f64 {
+    (0..3).map(|i| (a.v[i] - b.v[i]).powi(2)).sum()
+}
+
+#[derive(Debug, Clone)]
+struct KdNode {
+    lab: ColorData,
+    index: u8,
+    axis: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+/// k-d tree of palette colors in Lab space
+#[derive(Debug, Clone)]
+pub struct ColorTree {
+    nodes: Vec<KdNode>,
+    root: Option<usize>,
+}
+
+impl ColorTree {
+    /// palette index is the position in colors
+    pub fn new(colors: &[[u8; 3]]) -> Self {
+        let mut items: Vec<(ColorData, u8)> = colors
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (lab_of(c[0], c[1], c[2]), i as u8))
+            .collect();
+        let mut t = Self {
+            nodes: vec![],
+            root: None,
+        };
+        t.root = t.build(&mut items, 0);
+        t
+    }
+
+    fn build(&mut self, items: &mut [(ColorData, u8)], depth: usize) -> Option<usize> {
+        if items.is_empty() {
+            return None;
+        }
+        let axis = depth % 3;
+        items.sort_by(|a, b| a.0.v[axis].total_cmp(&b.0.v[axis]));
+        let mid = items.len() / 2;
+        let (lab, index) = items[mid];
+        let id = self.nodes.len();
+        self.nodes.push(KdNode {
+            lab,
+            index,
+            axis,
+            left: None,
+            right: None,
+        });
+        let (lo, hi) = items.split_at_mut(mid);
+        self.nodes[id].left = self.build(lo, depth + 1);
+        self.nodes[id].right = self.build(&mut hi[1..], depth + 1);
+        Some(id)
+    }
+
+    // keeps the k nearest (distance, node) in best, sorted
+    fn search(&self, node: Option<usize>, lab: &ColorData, best: &mut Vec<(f64, usize)>) {
+        let Some(id) = node else {
+            return;
+        };
+        let n = &self.nodes[id];
+        let d = dist2(lab, &n.lab);
+        if best.len() < CANDIDATES || d < best[best.len() - 1].0 {
+            let pos = best.partition_point(|b| b.0 <= d);
+            best.insert(pos, (d, id));
+            best.truncate(CANDIDATES);
+        }
+        let diff = lab.v[n.axis] - n.lab.v[n.axis];
+        let (near, far) = if diff < 0.0 {
+            (n.left, n.right)
+        } else {
+            (n.right, n.left)
+        };
+        self.search(near, lab, best);
+        if best.len() < CANDIDATES || diff * diff < best[best.len() - 1].0 {
+            self.search(far, lab, best);
+        }
+    }
+
+    /// palette index nearest to rgb by ciede2000
+    pub fn nearest(&self, r: u8, g: u8, b: u8) -> u8 {
+        let lab = lab_of(r, g, b);
+        let mut best = vec![];
+        self.search(self.root, &lab, &mut best);
+        let de = |n: &KdNode| delta_e_ciede2000(lab, n.lab);
+        best.iter()
+            .map(|(_, id)| &self.nodes[*id])
+            .min_by(|a, b| de(a).total_cmp(&de(b)))
+            .map_or(0, |n| n.index)
+    }
+}
+
+fn nearest_cached(r: u8, g: u8, b: u8, is16: bool) -> u8 {
+    let key = (r, g, b, is16);
+    if let Some(i) = NEAREST_CACHE.lock().unwrap().get(&key) {
+        return *i;
+    }
+    let i = if is16 {
+        ANSI_TREE_16.nearest(r, g, b)
+    } else {
+        ANSI_TREE_256.nearest(r, g, b)
+    };
+    let mut cache = NEAREST_CACHE.lock().unwrap();
+    if cache.len() >= CACHE_MAX {
+        cache.clear();
+    }
+    cache.insert(key, i);
+    i
+}
+
+/// colors a terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    /// by environment, RUST_PIXEL_COLORS=16|256|24 overrides the detection
+    pub fn detect() -> Self {
+        let var = |k: &str| std::env::var(k).ok();
+        if let Some(c) = var("RUST_PIXEL_COLORS") {
+            match c.as_str() {
+                "16" => return ColorDepth::Ansi16,
+                "256" => return ColorDepth::Ansi256,
+                "24" | "truecolor" => return ColorDepth::TrueColor,
+                _ => {}
+            }
+        }
+        Self::from_env(
+            var("COLORTERM").as_deref(),
+            var("TERM").as_deref(),
+            var("TERM_PROGRAM").as_deref(),
+        )
+    }
+
+    /// by the values of COLORTERM, TERM and TERM_PROGRAM
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>, program: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+            return ColorDepth::TrueColor;
+        }
+        // terminals known to have 24bit color without setting COLORTERM
+        if matches!(
+            program,
+            Some("iTerm.app") | Some("WezTerm") | Some("vscode") | Some("Hyper")
+        ) {
+            return ColorDepth::TrueColor;
+        }
+        match term {
+            Some(t) if t.ends_with("-direct") || t.contains("truecolor") => ColorDepth::TrueColor,
+            Some(t)
+                if t.contains("256color") || t.starts_with("alacritty") || t == "xterm-kitty" =>
+            {
+                ColorDepth::Ansi256
+            }
+            _ => ColorDepth::Ansi16,
+        }
+    }
+
+    /// nearest color the terminal can show
+    pub fn reduce(self, c: Color) -> Color {
+        match self {
+            ColorDepth::TrueColor => c,
+            ColorDepth::Ansi256 => c.to_indexed_256(),
+            ColorDepth::Ansi16 => c.to_indexed_16(),
+        }
+    }
+}
+
+impl Color {
+    /// nearest xterm 256 color, Reset and palette colors are kept
+    pub fn to_indexed_256(self) -> Color {
+        match self {
+            Color::Rgba(r, g, b, _) => Color::Indexed(nearest_cached(r, g, b, false)),
+            c => c,
+        }
+    }
+
+    /// nearest of the 16 basic colors as a named color, Reset is kept
+    pub fn to_indexed_16(self) -> Color {
+        let i = match self {
+            Color::Rgba(r, g, b, _) => nearest_cached(r, g, b, true),
+            Color::Indexed(i) if i >= 16 => {
+                let c = ANSI_COLOR_RGB[i as usize];
+                nearest_cached(c[0], c[1], c[2], true)
+            }
+            Color::Indexed(i) => i,
+            c => return c,
+        };
+        [
+            Color::Black,
+            Color::Red,
+            Color::Green,
+            Color::Yellow,
+            Color::Blue,
+            Color::Magenta,
+            Color::Cyan,
+            Color::Gray,
+            Color::DarkGray,
+            Color::LightRed,
+            Color::LightGreen,
+            Color::LightYellow,
+            Color::LightBlue,
+            Color::LightMagenta,
+            Color::LightCyan,
+            Color::White,
+        ][i as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_terminal_colors() {
+        // the tree agrees with a full scan by ciede2000
+        let tree = ColorTree::new(&ANSI_COLOR_RGB);
+        for (r, g, b) in [
+            (250, 128, 10),
+            (12, 200, 90),
+            (90, 90, 100),
+            (255, 255, 250),
+        ] {
+            let lab = lab_of(r, g, b);
+            let scan = ANSI_COLOR_RGB
+                .iter()
+                .map(|c| delta_e_ciede2000(lab, lab_of(c[0], c[1], c[2])))
+                .fold(f64::MAX, f64::min);
+            let c = ANSI_COLOR_RGB[tree.nearest(r, g, b) as usize];
+            assert!(delta_e_ciede2000(lab, lab_of(c[0], c[1], c[2])) - scan < 1e-9);
+        }
+
+        assert_eq!(
+            Color::Rgba(255, 135, 0, 255).to_indexed_256(),
+            Color::Indexed(208)
+        );
+        assert_eq!(Color::Rgba(120, 0, 0, 255).to_indexed_16(), Color::Red);
+        assert_eq!(
+            Color::Rgba(250, 250, 250, 255).to_indexed_16(),
+            Color::White
+        );
+        assert_eq!(Color::Indexed(196).to_indexed_16(), Color::LightRed);
+        assert_eq!(Color::Reset.to_indexed_256(), Color::Reset);
+        assert_eq!(Color::Blue.to_indexed_16(), Color::Blue);
+    }
+
+    #[test]
+    fn color_depth_from_env() {
+        use ColorDepth::*;
+        assert_eq!(
+            ColorDepth::from_env(Some("truecolor"), Some("xterm"), None),
+            TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env(None, Some("xterm-256color"), None),
+            Ansi256
+        );
+        assert_eq!(
+            ColorDepth::from_env(None, Some("screen-256color"), Some("iTerm.app")),
+            TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env(None, Some("xterm-direct"), None),
+            TrueColor
+        );
+        assert_eq!(ColorDepth::from_env(None, Some("linux"), None), Ansi16);
+        assert_eq!(ColorDepth::from_env(None, None, None), Ansi16);
+        let c = Color::Rgba(255, 135, 0, 255);
+        assert_eq!(TrueColor.reduce(c), c);
+        assert_eq!(Ansi256.reduce(c), Color::Indexed(208));
+    }
+}
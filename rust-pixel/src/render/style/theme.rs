@@ -0,0 +1,242 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Themes map semantic names like "ui.accent" to colors
+//!
+//! ctx.theme holds the dark and light presets and any added theme.
+//! Switching emits THEME_EVENT, and every Panel restyles its sprites
+//! on the next draw: cells using a color of the old theme get the color
+//! of the same name in the new theme. Code drawing every frame just asks
+//! ctx.theme for its colors.
+//! A name not found falls back to its parent, "ui.accent.hover" to
+//! "ui.accent" and "ui", then to Color::Reset.
+
+use crate::{
+    event::event_emit,
+    render::style::{Color, Style},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// emitted when the current theme changes
+pub const THEME_EVENT: &str = "Theme.Changed";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    colors: BTreeMap<String, Color>,
+}
+
+impl Theme {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            colors: BTreeMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, color: Color) -> &mut Self {
+        self.colors.insert(key.to_string(), color);
+        self
+    }
+
+    /// color of key or of its nearest parent, Reset if none
+    pub fn get(&self, key: &str) -> Color {
+        let mut k = key;
+        loop {
+            if let Some(c) = self.colors.get(k) {
+                return *c;
+            }
+            match k.rfind('.') {
+                Some(i) => k = &k[..i],
+                None => return Color::Reset,
+            }
+        }
+    }
+
+    pub fn style(&self, fg: &str, bg: &str) -> Style {
+        Style::default().fg(self.get(fg)).bg(self.get(bg))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.colors.keys()
+    }
+
+    /// (old, new) color pairs of names whose color differs in to,
+    /// an old color used by several names maps by the first of them
+    pub fn remap(&self, to: &Theme) -> Vec<(Color, Color)> {
+        let mut pairs: Vec<(Color, Color)> = vec![];
+        for (k, c) in &self.colors {
+            let n = to.get(k);
+            if n != *c && !pairs.iter().any(|p| p.0 == *c) {
+                pairs.push((*c, n));
+            }
+        }
+        pairs
+    }
+
+    fn preset(name: &str, colors: &[(&str, Color)]) -> Self {
+        let mut t = Self::new(name);
+        for (k, c) in colors {
+            t.set(k, *c);
+        }
+        t
+    }
+
+    pub fn dark() -> Self {
+        Self::preset(
+            "dark",
+            &[
+                ("ui.fg", Color::Indexed(252)),
+                ("ui.bg", Color::Indexed(235)),
+                ("ui.accent", Color::Indexed(222)),
+                ("ui.title", Color::Indexed(222)),
+                ("ui.border", Color::Indexed(244)),
+                ("ui.selected", Color::Indexed(39)),
+                ("ui.disabled", Color::Indexed(240)),
+                ("text.info", Color::Indexed(111)),
+                ("text.success", Color::Indexed(114)),
+                ("text.warn", Color::Indexed(215)),
+                ("text.error", Color::Indexed(203)),
+            ],
+        )
+    }
+
+    pub fn light() -> Self {
+        Self::preset(
+            "light",
+            &[
+                ("ui.fg", Color::Indexed(236)),
+                ("ui.bg", Color::Indexed(255)),
+                ("ui.accent", Color::Indexed(130)),
+                ("ui.title", Color::Indexed(130)),
+                ("ui.border", Color::Indexed(248)),
+                ("ui.selected", Color::Indexed(25)),
+                ("ui.disabled", Color::Indexed(250)),
+                ("text.info", Color::Indexed(25)),
+                ("text.success", Color::Indexed(28)),
+                ("text.warn", Color::Indexed(166)),
+                ("text.error", Color::Indexed(160)),
+            ],
+        )
+    }
+}
+
+/// registry of themes with the current one, kept in Context
+#[derive(Debug, Clone)]
+pub struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+    previous: Option<usize>,
+    // counts switches, panels compare it to restyle once
+    generation: u32,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Themes {
+    /// dark and light presets, dark is current
+    pub fn new() -> Self {
+        Self {
+            themes: vec![Theme::dark(), Theme::light()],
+            current: 0,
+            previous: None,
+            generation: 0,
+        }
+    }
+
+    /// adds or replaces a theme of the same name
+    pub fn add(&mut self, theme: Theme) {
+        match self.themes.iter().position(|t| t.name == theme.name) {
+            Some(i) => self.themes[i] = theme,
+            None => self.themes.push(theme),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.themes.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    /// theme before the last switch
+    pub fn previous(&self) -> Option<&Theme> {
+        self.previous.map(|i| &self.themes[i])
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// color of key in the current theme
+    pub fn get(&self, key: &str) -> Color {
+        self.current().get(key)
+    }
+
+    pub fn style(&self, fg: &str, bg: &str) -> Style {
+        self.current().style(fg, bg)
+    }
+
+    /// makes a theme current and emits THEME_EVENT,
+    /// false if there is no theme of that name
+    pub fn switch(&mut self, name: &str) -> bool {
+        let Some(i) = self.themes.iter().position(|t| t.name == name) else {
+            return false;
+        };
+        if i != self.current {
+            self.previous = Some(self.current);
+            self.current = i;
+            self.generation += 1;
+            event_emit(THEME_EVENT);
+        }
+        true
+    }
+
+    /// switches to the next theme, e.g. on a key press
+    pub fn cycle(&mut self) {
+        let n = self.themes[(self.current + 1) % self.themes.len()]
+            .name
+            .clone();
+        self.switch(&n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_lookup_and_switch() {
+        let mut ts = Themes::new();
+        assert_eq!(ts.get("ui.accent"), Color::Indexed(222));
+        assert_eq!(ts.get("ui.accent.hover"), Color::Indexed(222));
+        assert_eq!(ts.get("game.snake"), Color::Reset);
+
+        let mut t = Theme::new("mono");
+        t.set("ui", Color::White).set("ui.bg", Color::Black);
+        assert_eq!(t.get("ui.accent"), Color::White);
+        ts.add(t);
+        assert_eq!(ts.names(), vec!["dark", "light", "mono"]);
+
+        assert!(!ts.switch("none"));
+        assert!(ts.switch("light"));
+        assert_eq!(ts.generation(), 1);
+        assert_eq!(ts.previous().unwrap().name, "dark");
+        assert_eq!(ts.get("ui.accent"), Color::Indexed(130));
+        // accent and title share a color, mapped once
+        let pairs = Theme::dark().remap(ts.current());
+        assert!(pairs.contains(&(Color::Indexed(222), Color::Indexed(130))));
+        assert_eq!(
+            pairs.iter().filter(|p| p.0 == Color::Indexed(222)).count(),
+            1
+        );
+        ts.cycle();
+        assert_eq!(ts.current().name, "mono");
+    }
+}
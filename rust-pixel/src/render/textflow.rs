@@ -0,0 +1,169 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! TextFlow lays out long text into lines of a given width,
+//! with word wrap, left/center/right alignment, optional hyphenation
+//! and `…` truncation when the text exceeds max lines.
+//!
+//! Widths are display widths, wide chars (CJK...) count as 2.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextFlow {
+    pub width: u16,
+    pub align: TextAlign,
+    /// break long words with '-' instead of cutting them
+    pub hyphenate: bool,
+    /// end the last line with '…' when text is truncated
+    pub ellipsis: bool,
+    pub max_lines: Option<u16>,
+}
+
+impl TextFlow {
+    pub fn new(width: u16) -> Self {
+        Self {
+            width,
+            align: TextAlign::Left,
+            hyphenate: false,
+            ellipsis: true,
+            max_lines: None,
+        }
+    }
+
+    pub fn align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn hyphenate(mut self, h: bool) -> Self {
+        self.hyphenate = h;
+        self
+    }
+
+    pub fn ellipsis(mut self, e: bool) -> Self {
+        self.ellipsis = e;
+        self
+    }
+
+    pub fn max_lines(mut self, n: u16) -> Self {
+        self.max_lines = Some(n);
+        self
+    }
+
+    /// wraps text, returns lines without alignment
+    pub fn wrap(&self, text: &str) -> Vec<String> {
+        let w = self.width.max(1) as usize;
+        let mut lines = vec![];
+        for para in text.split('\n') {
+            let mut line = String::new();
+            for word in para.split_whitespace() {
+                let lw = line.width();
+                let ww = word.width();
+                if lw > 0 && lw + 1 + ww <= w {
+                    line.push(' ');
+                    line.push_str(word);
+                    continue;
+                }
+                if lw == 0 && ww <= w {
+                    line.push_str(word);
+                    continue;
+                }
+                if lw > 0 {
+                    lines.push(std::mem::take(&mut line));
+                }
+                // word longer than a line, break it
+                let mut rest = word;
+                while rest.width() > w {
+                    let (head, tail) = if self.hyphenate && w > 1 {
+                        let (h, t) = split_at_width(rest, w - 1);
+                        (format!("{}-", h), t)
+                    } else {
+                        let (h, t) = split_at_width(rest, w);
+                        (h.to_string(), t)
+                    };
+                    // not even one grapheme fits
+                    if tail.len() == rest.len() {
+                        break;
+                    }
+                    lines.push(head);
+                    rest = tail;
+                }
+                line.push_str(rest);
+            }
+            lines.push(line);
+        }
+        if let Some(m) = self.max_lines {
+            let m = m as usize;
+            if lines.len() > m {
+                lines.truncate(m);
+                if self.ellipsis && m > 0 {
+                    let last = lines.pop().unwrap();
+                    let (h, _) = split_at_width(&last, w.saturating_sub(1));
+                    lines.push(format!("{}…", h.trim_end()));
+                }
+            }
+        }
+        lines
+    }
+
+    /// wraps text, returns (x offset, line) after alignment
+    pub fn layout(&self, text: &str) -> Vec<(u16, String)> {
+        self.wrap(text)
+            .into_iter()
+            .map(|l| {
+                let space = (self.width as usize).saturating_sub(l.width()) as u16;
+                let x = match self.align {
+                    TextAlign::Left => 0,
+                    TextAlign::Center => space / 2,
+                    TextAlign::Right => space,
+                };
+                (x, l)
+            })
+            .collect()
+    }
+}
+
+/// splits s by graphemes, head display width <= w
+fn split_at_width(s: &str, w: usize) -> (&str, &str) {
+    let mut acc = 0;
+    for (idx, g) in s.grapheme_indices(true) {
+        let gw = g.width();
+        if acc + gw > w {
+            return (&s[..idx], &s[idx..]);
+        }
+        acc += gw;
+    }
+    (s, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_words_and_aligns() {
+        let tf = TextFlow::new(10).align(TextAlign::Right);
+        let ls = tf.layout("hello rust pixel engine");
+        assert_eq!(ls[0], (0, "hello rust".to_string()));
+        assert_eq!(ls[1], (5, "pixel".to_string()));
+        assert_eq!(ls[2], (4, "engine".to_string()));
+    }
+
+    #[test]
+    fn hyphenates_and_truncates() {
+        let tf = TextFlow::new(5).hyphenate(true);
+        assert_eq!(tf.wrap("abcdefghij"), vec!["abcd-", "efgh-", "ij"]);
+        let tf = TextFlow::new(6).max_lines(2);
+        assert_eq!(tf.wrap("aaa bbb ccc ddd"), vec!["aaa", "bbb…"]);
+    }
+}
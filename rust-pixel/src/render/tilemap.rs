@@ -0,0 +1,265 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! TileMap stores a large grid of tile indices in several layers,
+//! and draws only the part visible through the camera.
+//!
+//! Tiles are defined once in a tile set (a Cell plus flags), index 0 is
+//! always the empty tile. The map is split into chunks of CHUNK_SIZE x CHUNK_SIZE,
+//! chunks without any tile are skipped when drawing.
+
+use crate::render::{buffer::Buffer, camera::Camera, cell::Cell, sprite::Sprite};
+use crate::util::Rect;
+use bitflags::bitflags;
+
+/// Tiled editor map importer
+mod tiled;
+pub use tiled::{SpawnPoint, TiledMap, TiledMapAsset};
+
+pub const CHUNK_SIZE: u16 = 16;
+
+bitflags! {
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+    pub struct TileFlags: u8 {
+        const NONE = 0b0000_0000;
+        const SOLID = 0b0000_0001;
+        const ANIMATED = 0b0000_0010;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TileDef {
+    pub cell: Cell,
+    pub flags: TileFlags,
+    /// frames (tile indices) and seconds per frame, for animated tiles
+    pub frames: Vec<u16>,
+    pub frame_time: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub tiles: Vec<u16>,
+    // tile count of each chunk
+    chunk_count: Vec<u32>,
+}
+
+#[derive(Clone)]
+pub struct TileMap {
+    pub width: u16,
+    pub height: u16,
+    pub tileset: Vec<TileDef>,
+    pub layers: Vec<TileLayer>,
+    time: f32,
+}
+
+impl TileMap {
+    pub fn new(width: u16, height: u16) -> Self {
+        let empty = TileDef {
+            cell: Cell::default(),
+            flags: TileFlags::NONE,
+            frames: vec![],
+            frame_time: 0.0,
+        };
+        Self {
+            width,
+            height,
+            tileset: vec![empty],
+            layers: vec![],
+            time: 0.0,
+        }
+    }
+
+    fn chunks_x(&self) -> usize {
+        self.width.div_ceil(CHUNK_SIZE) as usize
+    }
+
+    fn chunk_index(&self, x: u16, y: u16) -> usize {
+        (y / CHUNK_SIZE) as usize * self.chunks_x() + (x / CHUNK_SIZE) as usize
+    }
+
+    /// adds a tile definition, returns its index
+    pub fn add_tile(&mut self, cell: Cell, flags: TileFlags) -> u16 {
+        self.tileset.push(TileDef {
+            cell,
+            flags,
+            frames: vec![],
+            frame_time: 0.0,
+        });
+        (self.tileset.len() - 1) as u16
+    }
+
+    /// adds an animated tile cycling through other tiles
+    pub fn add_animated_tile(&mut self, frames: &[u16], frame_time: f32, flags: TileFlags) -> u16 {
+        let first = frames.first().map(|f| self.tileset[*f as usize].cell.clone());
+        self.tileset.push(TileDef {
+            cell: first.unwrap_or_default(),
+            flags: flags | TileFlags::ANIMATED,
+            frames: frames.to_vec(),
+            frame_time,
+        });
+        (self.tileset.len() - 1) as u16
+    }
+
+    /// adds an empty layer, returns its index, later layers draw on top
+    pub fn add_layer(&mut self, name: &str) -> usize {
+        let cs = self.chunks_x() * self.height.div_ceil(CHUNK_SIZE) as usize;
+        self.layers.push(TileLayer {
+            name: name.to_string(),
+            visible: true,
+            tiles: vec![0; self.width as usize * self.height as usize],
+            chunk_count: vec![0; cs],
+        });
+        self.layers.len() - 1
+    }
+
+    pub fn get_layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|l| l.name == name)
+    }
+
+    pub fn set_layer_visible(&mut self, layer: usize, v: bool) {
+        self.layers[layer].visible = v;
+    }
+
+    pub fn get_tile(&self, layer: usize, x: u16, y: u16) -> u16 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.layers[layer].tiles[y as usize * self.width as usize + x as usize]
+    }
+
+    pub fn set_tile(&mut self, layer: usize, x: u16, y: u16, tile: u16) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let ci = self.chunk_index(x, y);
+        let i = y as usize * self.width as usize + x as usize;
+        let l = &mut self.layers[layer];
+        let old = l.tiles[i];
+        if old == 0 && tile != 0 {
+            l.chunk_count[ci] += 1;
+        } else if old != 0 && tile == 0 {
+            l.chunk_count[ci] -= 1;
+        }
+        l.tiles[i] = tile;
+    }
+
+    /// fills a rect with a tile
+    pub fn fill(&mut self, layer: usize, area: Rect, tile: u16) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                self.set_tile(layer, x, y, tile);
+            }
+        }
+    }
+
+    pub fn flags_at(&self, layer: usize, x: u16, y: u16) -> TileFlags {
+        self.tileset[self.get_tile(layer, x, y) as usize].flags
+    }
+
+    /// true if any layer has a solid tile at (x, y), out of map is solid
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return true;
+        }
+        (0..self.layers.len())
+            .any(|l| self.flags_at(l, x as u16, y as u16).contains(TileFlags::SOLID))
+    }
+
+    /// advances animated tiles
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    // cell of a tile at current time
+    fn tile_cell(&self, tile: u16) -> &Cell {
+        let td = &self.tileset[tile as usize];
+        if td.flags.contains(TileFlags::ANIMATED) && !td.frames.is_empty() && td.frame_time > 0.0 {
+            let f = (self.time / td.frame_time) as usize % td.frames.len();
+            return &self.tileset[td.frames[f] as usize].cell;
+        }
+        &td.cell
+    }
+
+    /// draws map area starting at world (wx, wy) into the whole buf,
+    /// only chunks intersecting the area and having tiles are visited
+    pub fn render_to(&self, buf: &mut Buffer, wx: i32, wy: i32) {
+        let (bw, bh) = (buf.area.width as i32, buf.area.height as i32);
+        let x0 = wx.max(0);
+        let y0 = wy.max(0);
+        let x1 = (wx + bw).min(self.width as i32);
+        let y1 = (wy + bh).min(self.height as i32);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let cs = CHUNK_SIZE as i32;
+        for l in self.layers.iter().filter(|l| l.visible) {
+            for cy in (y0 / cs)..=((y1 - 1) / cs) {
+                for cx in (x0 / cs)..=((x1 - 1) / cs) {
+                    let ci = cy as usize * self.chunks_x() + cx as usize;
+                    if l.chunk_count[ci] == 0 {
+                        continue;
+                    }
+                    for y in (cy * cs).max(y0)..((cy + 1) * cs).min(y1) {
+                        for x in (cx * cs).max(x0)..((cx + 1) * cs).min(x1) {
+                            let t = l.tiles[y as usize * self.width as usize + x as usize];
+                            if t == 0 {
+                                continue;
+                            }
+                            let di = ((y - wy) * bw + (x - wx)) as usize;
+                            buf.content[di] = self.tile_cell(t).clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// draws the view of camera into sprite, sprite is moved to the camera
+    /// position, so the panel camera offset puts it back on screen. Sprite
+    /// positions can't be negative, a view left of or above the map starts
+    /// at the map edge and the camera offset moves it into place
+    pub fn render_to_sprite(&self, sp: &mut Sprite, camera: Option<&Camera>) {
+        let (wx, wy) = camera.map(|c| c.cell_offset()).unwrap_or((0, 0));
+        let (sx, sy) = (wx.max(0), wy.max(0));
+        sp.set_pos(sx as u16, sy as u16);
+        sp.content.reset();
+        self.render_to(&mut sp.content, sx, sy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PointF32;
+
+    #[test]
+    fn tilemap_culls_and_flags() {
+        let mut tm = TileMap::new(100, 100);
+        let mut c = Cell::default();
+        c.set_symbol("#");
+        let wall = tm.add_tile(c, TileFlags::SOLID);
+        let l = tm.add_layer("ground");
+        tm.set_tile(l, 50, 50, wall);
+        tm.set_tile(l, 2, 3, wall);
+        assert!(tm.is_solid(50, 50));
+        assert!(!tm.is_solid(49, 50));
+        assert!(tm.is_solid(-1, 0));
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 10));
+        tm.render_to(&mut buf, 45, 45);
+        assert_eq!(buf.get(5, 5).symbol, "#");
+        assert_eq!(buf.get(0, 0).symbol, " ");
+        tm.set_tile(l, 50, 50, 0);
+        assert_eq!(tm.layers[l].chunk_count[tm.chunk_index(50, 50)], 0);
+
+        // view left of the map, the tile keeps its world position
+        let mut cam = Camera::new(10, 10);
+        cam.pos = PointF32 { x: -3.0, y: 0.0 };
+        let mut sp = Sprite::new(0, 0, 10, 10);
+        tm.render_to_sprite(&mut sp, Some(&cam));
+        assert_eq!(sp.content.area.x, 0);
+        assert_eq!(sp.content.get(2, 3).symbol, "#");
+    }
+}
@@ -0,0 +1,514 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Loads maps made by the Tiled editor (https://www.mapeditor.org)
+//! Both .tmx (xml) and .tmj/.json files are supported, tilesets can be
+//! embedded or external (.tsx/.tsj, native only).
+//!
+//! Tile layers are converted to TileMap layers, engine tile index equals
+//! Tiled gid. Object layers are converted to SpawnPoint list.
+//!
+//! A tile is converted to a cell by its custom properties:
+//! - symbol: text mode symbol, e.g. "#"
+//! - fg, bg: color name, e.g. "red", "#ff8000"
+//! - solid: bool, sets TileFlags::SOLID
+//!
+//! Tile without symbol property uses symbol local_id of the texture given
+//! by the tileset property "texture" (default is the tileset order),
+//! so a tileset made from the engine symbol image maps 1:1.
+//! Animated tiles use the duration of their first frame.
+//!
+//! Limitations: only csv (tmx) or plain array (json) layer data,
+//! finite maps, flip bits are ignored.
+
+use crate::{
+    asset::{Asset, AssetBase, AssetState},
+    render::{
+        buffer::Buffer,
+        cell::{cellsym, Cell},
+        sprite::Sprite,
+        style::{color_from_name, Color},
+        tilemap::{TileFlags, TileMap},
+    },
+};
+use log::info;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+const GID_MASK: u64 = 0x1FFF_FFFF;
+
+/// object of a Tiled object layer, position and size in cells
+#[derive(Debug, Clone, Default)]
+pub struct SpawnPoint {
+    pub layer: String,
+    pub name: String,
+    /// Tiled object type (class)
+    pub kind: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Clone)]
+pub struct TiledMap {
+    pub map: TileMap,
+    pub spawns: Vec<SpawnPoint>,
+}
+
+impl TiledMap {
+    /// spawn points of a kind
+    pub fn spawns_of(&self, kind: &str) -> Vec<&SpawnPoint> {
+        self.spawns.iter().filter(|s| s.kind == kind).collect()
+    }
+}
+
+pub struct TiledMapAsset {
+    pub base: AssetBase,
+    pub tiled: Option<TiledMap>,
+}
+
+impl Asset for TiledMapAsset {
+    fn new(base: AssetBase) -> Self {
+        Self { base, tiled: None }
+    }
+
+    fn get_base(&mut self) -> &mut AssetBase {
+        &mut self.base
+    }
+
+    fn get_tiled_map(&mut self) -> Option<&mut TiledMap> {
+        self.tiled.as_mut()
+    }
+
+    /// draws map starting at (off_x, off_y) into the sprite
+    fn set_sprite(&mut self, sp: &mut Sprite, _frame_idx: usize, off_x: u16, off_y: u16) {
+        if let Some(t) = &self.tiled {
+            t.map.render_to(&mut sp.content, off_x as i32, off_y as i32);
+        }
+    }
+
+    fn parse(&mut self) {
+        if self.get_state() != AssetState::Parsing {
+            return;
+        }
+        let text = String::from_utf8_lossy(&self.base.raw_data).to_string();
+        let doc = if text.trim_start().starts_with('<') {
+            Some(tmx_to_json(&text))
+        } else {
+            serde_json::from_str::<Value>(&text).ok()
+        };
+        match doc {
+            Some(v) => {
+                let dir = parent_dir(&self.base.location);
+                self.tiled = Some(load_tiled(&v, &dir));
+                self.base.frame_count = 1;
+            }
+            None => info!("tiled map parse error:{}", self.base.location),
+        }
+    }
+
+    fn save(&mut self, _buf: &Buffer) {}
+}
+
+fn parent_dir(loc: &str) -> String {
+    match loc.rfind(['/', '\\']) {
+        Some(p) => loc[..p + 1].to_string(),
+        None => String::new(),
+    }
+}
+
+fn num(v: &Value, key: &str) -> f64 {
+    match &v[key] {
+        Value::Number(n) => n.as_f64().unwrap_or(0.0),
+        Value::String(s) => s.parse().unwrap_or(0.0),
+        _ => 0.0,
+    }
+}
+
+fn string(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        o => o.to_string(),
+    }
+}
+
+fn properties(v: &Value) -> HashMap<String, String> {
+    let mut ps = HashMap::new();
+    if let Some(arr) = v["properties"].as_array() {
+        for p in arr {
+            ps.insert(string(&p["name"]), string(&p["value"]));
+        }
+    }
+    ps
+}
+
+// external tileset, relative to map file
+#[cfg(not(target_arch = "wasm32"))]
+fn load_external_tileset(dir: &str, source: &str) -> Option<Value> {
+    let path = crate::util::get_abs_path(&format!("{}{}", dir, source));
+    let text = std::fs::read_to_string(path).ok()?;
+    if text.trim_start().starts_with('<') {
+        Some(tmx_to_json(&text))
+    } else {
+        serde_json::from_str(&text).ok()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_external_tileset(_dir: &str, source: &str) -> Option<Value> {
+    info!("external tileset not supported in web:{}", source);
+    None
+}
+
+fn tile_cell(tp: &HashMap<String, String>, texture: u8, local_id: u32) -> Cell {
+    let mut c = Cell::default();
+    match tp.get("symbol") {
+        Some(s) => {
+            c.set_symbol(s);
+        }
+        None => {
+            c.set_symbol(cellsym((local_id % 256) as u8));
+            c.set_texture(texture.wrapping_add((local_id / 256) as u8));
+        }
+    }
+    c.set_fg(tp.get("fg").and_then(|s| color_from_name(s)).unwrap_or(Color::White));
+    if let Some(bg) = tp.get("bg").and_then(|s| color_from_name(s)) {
+        c.set_bg(bg);
+    }
+    c
+}
+
+fn load_tiled(v: &Value, dir: &str) -> TiledMap {
+    let (w, h) = (num(v, "width") as u16, num(v, "height") as u16);
+    let (tw, th) = (num(v, "tilewidth").max(1.0), num(v, "tileheight").max(1.0));
+    let mut map = TileMap::new(w, h);
+
+    // tileset, engine tile index == gid
+    let mut animations = vec![];
+    if let Some(tss) = v["tilesets"].as_array() {
+        for (order, ts) in tss.iter().enumerate() {
+            let firstgid = num(ts, "firstgid") as u32;
+            let ts = match ts["source"].as_str() {
+                Some(src) => match load_external_tileset(dir, src) {
+                    Some(e) => e,
+                    None => continue,
+                },
+                None => ts.clone(),
+            };
+            let tsp = properties(&ts);
+            let texture = tsp
+                .get("texture")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(order as u8);
+            let mut tiles: HashMap<u32, &Value> = HashMap::new();
+            if let Some(arr) = ts["tiles"].as_array() {
+                for t in arr {
+                    tiles.insert(num(t, "id") as u32, t);
+                }
+            }
+            let count = num(&ts, "tilecount") as u32;
+            while (map.tileset.len() as u32) < firstgid {
+                map.add_tile(Cell::default(), TileFlags::NONE);
+            }
+            for id in 0..count {
+                let (tp, anim) = match tiles.get(&id) {
+                    Some(t) => (properties(t), t["animation"].as_array().cloned()),
+                    None => (HashMap::new(), None),
+                };
+                let mut flags = TileFlags::NONE;
+                if tp.get("solid").map(|s| s == "true").unwrap_or(false) {
+                    flags |= TileFlags::SOLID;
+                }
+                let gid = map.add_tile(tile_cell(&tp, texture, id), flags);
+                if let Some(frames) = anim {
+                    // frames of other tilesets are dropped
+                    let fs: Vec<u32> = frames
+                        .iter()
+                        .map(|f| num(f, "tileid") as u32)
+                        .filter(|t| *t < count)
+                        .map(|t| firstgid + t)
+                        .collect();
+                    let ft = frames.first().map(|f| num(f, "duration")).unwrap_or(100.0);
+                    animations.push((gid, fs, ft as f32 / 1000.0));
+                }
+            }
+        }
+    }
+    let len = map.tileset.len();
+    for (gid, frames, ft) in animations {
+        let frames: Vec<u16> = frames
+            .into_iter()
+            .filter(|f| (*f as usize) < len)
+            .map(|f| f as u16)
+            .collect();
+        if frames.is_empty() {
+            continue;
+        }
+        let td = &mut map.tileset[gid as usize];
+        td.flags |= TileFlags::ANIMATED;
+        td.frames = frames;
+        td.frame_time = ft;
+    }
+
+    // layers, groups are flattened
+    let mut spawns = vec![];
+    let mut stack: Vec<Value> = v["layers"].as_array().cloned().unwrap_or_default();
+    stack.reverse();
+    while let Some(l) = stack.pop() {
+        match l["type"].as_str().unwrap_or("") {
+            "tilelayer" => {
+                let li = map.add_layer(l["name"].as_str().unwrap_or(""));
+                map.set_layer_visible(li, l["visible"].as_bool().unwrap_or(true));
+                let lw = num(&l, "width") as usize;
+                match l["data"].as_array() {
+                    Some(data) => {
+                        for (i, g) in data.iter().enumerate() {
+                            let gid = (g.as_u64().unwrap_or(0) & GID_MASK) as u16;
+                            // unknown gids of a broken map are left empty
+                            if gid == 0 || lw == 0 || gid as usize >= map.tileset.len() {
+                                continue;
+                            }
+                            // in usize, maps may have more than 65535 cells
+                            let (x, y) = (i % lw, i / lw);
+                            if let (Ok(x), Ok(y)) = (u16::try_from(x), u16::try_from(y)) {
+                                map.set_tile(li, x, y, gid);
+                            }
+                        }
+                    }
+                    None => info!("tiled layer data encoding not supported"),
+                }
+            }
+            "objectgroup" => {
+                let ln = string(&l["name"]);
+                for o in l["objects"].as_array().cloned().unwrap_or_default() {
+                    let kind = match o["type"].as_str() {
+                        Some(t) if !t.is_empty() => t.to_string(),
+                        _ => string(&o["class"]),
+                    };
+                    spawns.push(SpawnPoint {
+                        layer: ln.clone(),
+                        name: string(&o["name"]),
+                        kind,
+                        x: (num(&o, "x") / tw) as f32,
+                        y: (num(&o, "y") / th) as f32,
+                        width: (num(&o, "width") / tw) as f32,
+                        height: (num(&o, "height") / th) as f32,
+                        properties: properties(&o),
+                    });
+                }
+            }
+            "group" => {
+                let mut sub = l["layers"].as_array().cloned().unwrap_or_default();
+                sub.reverse();
+                stack.extend(sub);
+            }
+            _ => {}
+        }
+    }
+    TiledMap { map, spawns }
+}
+
+struct XmlNode {
+    name: String,
+    attrs: Map<String, Value>,
+    children: Vec<XmlNode>,
+    text: String,
+}
+
+// a tiny xml reader, enough for tmx & tsx files
+fn parse_xml(text: &str) -> XmlNode {
+    let tag_re = Regex::new(r#"<(/?)([\w:.-]+)((?:\s+[\w:.-]+\s*=\s*"[^"]*")*)\s*(/?)>"#).unwrap();
+    let attr_re = Regex::new(r#"([\w:.-]+)\s*=\s*"([^"]*)""#).unwrap();
+    let mut stack = vec![XmlNode {
+        name: String::new(),
+        attrs: Map::new(),
+        children: vec![],
+        text: String::new(),
+    }];
+    let mut last = 0;
+    for cap in tag_re.captures_iter(text) {
+        let m = cap.get(0).unwrap();
+        stack.last_mut().unwrap().text.push_str(&text[last..m.start()]);
+        last = m.end();
+        if &cap[1] == "/" {
+            if stack.len() > 1 {
+                let n = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(n);
+            }
+            continue;
+        }
+        let mut attrs = Map::new();
+        for a in attr_re.captures_iter(&cap[3]) {
+            attrs.insert(a[1].to_string(), Value::String(unescape(&a[2])));
+        }
+        let node = XmlNode {
+            name: cap[2].to_string(),
+            attrs,
+            children: vec![],
+            text: String::new(),
+        };
+        if &cap[4] == "/" {
+            stack.last_mut().unwrap().children.push(node);
+        } else {
+            stack.push(node);
+        }
+    }
+    while stack.len() > 1 {
+        let n = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(n);
+    }
+    stack.pop().unwrap()
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn xml_properties(n: &XmlNode) -> Value {
+    let mut ps = vec![];
+    for p in n.children.iter().filter(|c| c.name == "properties") {
+        for c in p.children.iter().filter(|c| c.name == "property") {
+            let value = match c.attrs.get("value") {
+                Some(v) => v.clone(),
+                None => Value::String(c.text.trim().to_string()),
+            };
+            ps.push(json!({"name": c.attrs.get("name"), "value": value}));
+        }
+    }
+    Value::Array(ps)
+}
+
+fn xml_tileset(n: &XmlNode) -> Value {
+    let mut ts = Value::Object(n.attrs.clone());
+    ts["properties"] = xml_properties(n);
+    let mut tiles = vec![];
+    for t in n.children.iter().filter(|c| c.name == "tile") {
+        let mut tv = Value::Object(t.attrs.clone());
+        tv["properties"] = xml_properties(t);
+        if let Some(a) = t.children.iter().find(|c| c.name == "animation") {
+            let frames: Vec<Value> = a
+                .children
+                .iter()
+                .map(|f| Value::Object(f.attrs.clone()))
+                .collect();
+            tv["animation"] = Value::Array(frames);
+        }
+        tiles.push(tv);
+    }
+    ts["tiles"] = Value::Array(tiles);
+    ts
+}
+
+fn xml_layers(n: &XmlNode) -> Vec<Value> {
+    let mut ls = vec![];
+    for c in &n.children {
+        let mut l = Value::Object(c.attrs.clone());
+        l["visible"] = Value::Bool(c.attrs.get("visible").map(|v| v != "0").unwrap_or(true));
+        match c.name.as_str() {
+            "layer" => {
+                l["type"] = json!("tilelayer");
+                if let Some(d) = c.children.iter().find(|d| d.name == "data") {
+                    let enc = d.attrs.get("encoding").and_then(|e| e.as_str());
+                    if enc == Some("csv") {
+                        let data: Vec<Value> = d
+                            .text
+                            .split(',')
+                            .filter_map(|s| s.trim().parse::<u64>().ok())
+                            .map(Value::from)
+                            .collect();
+                        l["data"] = Value::Array(data);
+                    }
+                }
+            }
+            "objectgroup" => {
+                l["type"] = json!("objectgroup");
+                let objs: Vec<Value> = c
+                    .children
+                    .iter()
+                    .filter(|o| o.name == "object")
+                    .map(|o| {
+                        let mut ov = Value::Object(o.attrs.clone());
+                        ov["properties"] = xml_properties(o);
+                        ov
+                    })
+                    .collect();
+                l["objects"] = Value::Array(objs);
+            }
+            "group" => {
+                l["type"] = json!("group");
+                l["layers"] = Value::Array(xml_layers(c));
+            }
+            _ => continue,
+        }
+        ls.push(l);
+    }
+    ls
+}
+
+/// converts tmx / tsx xml to the json layout of tmj / tsj
+fn tmx_to_json(text: &str) -> Value {
+    let root = parse_xml(text);
+    let top = match root.children.iter().find(|c| c.name == "map" || c.name == "tileset") {
+        Some(t) => t,
+        None => return Value::Null,
+    };
+    if top.name == "tileset" {
+        return xml_tileset(top);
+    }
+    let mut m = Value::Object(top.attrs.clone());
+    m["tilesets"] = Value::Array(
+        top.children
+            .iter()
+            .filter(|c| c.name == "tileset")
+            .map(xml_tileset)
+            .collect(),
+    );
+    m["layers"] = Value::Array(xml_layers(top));
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tmx_and_tmj_load_the_same() {
+        let tmx = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" width="3" height="2" tilewidth="8" tileheight="8">
+ <tileset firstgid="1" name="t" tilecount="2" columns="2">
+  <tile id="1"><properties><property name="solid" type="bool" value="true"/><property name="symbol" value="#"/></properties></tile>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">
+1,2,0,
+0,2,1
+</data>
+ </layer>
+ <objectgroup id="2" name="spawn">
+  <object id="1" name="p1" type="player" x="16" y="8"/>
+ </objectgroup>
+</map>"##;
+        let tmj = r##"{"width":3,"height":2,"tilewidth":8,"tileheight":8,
+ "tilesets":[{"firstgid":1,"tilecount":2,"tiles":[{"id":1,"properties":[
+   {"name":"solid","type":"bool","value":true},{"name":"symbol","type":"string","value":"#"}]}]}],
+ "layers":[{"type":"tilelayer","name":"ground","width":3,"height":2,"data":[1,2,0,0,2,1]},
+   {"type":"objectgroup","name":"spawn","objects":[{"name":"p1","type":"player","x":16,"y":8}]}]}"##;
+        for v in [tmx_to_json(tmx), serde_json::from_str(tmj).unwrap()] {
+            let t = load_tiled(&v, "");
+            assert_eq!(t.map.get_tile(0, 1, 0), 2);
+            assert!(t.map.is_solid(1, 1));
+            assert!(!t.map.is_solid(0, 0));
+            assert_eq!(t.map.tileset[2].cell.symbol, "#");
+            let p = t.spawns_of("player");
+            assert_eq!((p[0].x, p[0].y), (2.0, 1.0));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Transition blends from one picture to another over a duration
+//!
+//! Crossfade, Wipe, Pixelate and Dissolve work on two Buffers cell by
+//! cell, in terminal and graphics mode. In graphics mode they can also
+//! run as GL shaders on render textures 0 and 1, drawn to render texture
+//! 3, next to the other shaders of petview. Set SceneControl::effect to
+//! have the panel run one between the old and the new scene of every
+//! scene switch with a transition time.
+
+#[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+use crate::render::adapter::Adapter;
+use crate::render::{
+    buffer::Buffer,
+    cell::Cell,
+    style::{BlendMode, Color},
+};
+use serde::{Deserialize, Serialize};
+
+/// side the new picture comes in from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WipeDir {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransitionKind {
+    /// colors blend, symbols switch halfway
+    Crossfade,
+    Wipe(WipeDir),
+    /// blocks grow until halfway, then shrink on the new picture
+    Pixelate,
+    /// cells switch one by one in random order
+    Dissolve,
+}
+
+impl TransitionKind {
+    /// index of the shader in TRANS_FS
+    pub fn shader(self) -> usize {
+        match self {
+            TransitionKind::Pixelate => 0,
+            TransitionKind::Crossfade => 7,
+            TransitionKind::Dissolve => 8,
+            TransitionKind::Wipe(WipeDir::Left) => 9,
+            TransitionKind::Wipe(WipeDir::Right) => 10,
+            TransitionKind::Wipe(WipeDir::Up) => 11,
+            TransitionKind::Wipe(WipeDir::Down) => 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    /// seconds
+    pub duration: f32,
+    pub time: f32,
+    /// order of cells in Dissolve
+    pub seed: u32,
+}
+
+// largest Pixelate block at halfway, in cells
+const PIXELATE_MAX: f32 = 8.0;
+
+// 0.0..1.0 per cell and seed
+fn cell_noise(x: u16, y: u16, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(374761393)
+        ^ (y as u32).wrapping_mul(668265263)
+        ^ seed.wrapping_mul(2246822519);
+    h = (h ^ (h >> 13)).wrapping_mul(1274126177);
+    h ^= h >> 16;
+    (h & 0xffff) as f32 / 65536.0
+}
+
+// cell at (x, y) relative to buf, a blank one outside
+fn cell_at(buf: &Buffer, x: u16, y: u16) -> Cell {
+    let a = buf.area;
+    if x < a.width && y < a.height {
+        buf.get(a.x + x, a.y + y).clone()
+    } else {
+        Cell::default()
+    }
+}
+
+impl Transition {
+    pub fn new(kind: TransitionKind, duration: f32) -> Self {
+        Self {
+            kind,
+            duration,
+            time: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// advances by dt seconds, returns true when done
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.time = (self.time + dt).min(self.duration);
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.time >= self.duration
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    /// 0.0 at start to 1.0 when done
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.time / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// draws the blend of from and to into out at the current progress
+    pub fn apply(&self, from: &Buffer, to: &Buffer, out: &mut Buffer) {
+        apply_transition(self.kind, self.progress(), self.seed, from, to, out);
+    }
+
+    /// draws from and to into render textures 0 and 1
+    #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+    pub fn set_gl_textures(&self, adapter: &mut dyn Adapter, from: &Buffer, to: &Buffer) {
+        adapter.draw_buffer_to_texture(from, 0);
+        adapter.draw_buffer_to_texture(to, 1);
+    }
+
+    /// draws the shader of kind into render texture 3 and shows it
+    #[cfg(any(feature = "sdl", target_arch = "wasm32"))]
+    pub fn draw_gl(&self, adapter: &mut dyn Adapter) {
+        let sa = adapter.get_base();
+        if let (Some(pix), Some(gl)) = (&mut sa.gl_pixel, &mut sa.gl) {
+            pix.bind_target(gl, 3);
+            pix.set_render_texture_hidden(3, false);
+            pix.render_trans_frame(gl, self.kind.shader(), self.progress());
+        }
+    }
+}
+
+/// draws the blend of from and to at progress t into out, cell by cell
+pub fn apply_transition(
+    kind: TransitionKind,
+    t: f32,
+    seed: u32,
+    from: &Buffer,
+    to: &Buffer,
+    out: &mut Buffer,
+) {
+    let t = t.clamp(0.0, 1.0);
+    let a = out.area;
+    let (w, h) = (a.width.max(1) as f32, a.height.max(1) as f32);
+    for y in 0..a.height {
+        for x in 0..a.width {
+            let c = match kind {
+                TransitionKind::Crossfade => {
+                    let (f, n) = (cell_at(from, x, y), cell_at(to, x, y));
+                    let mut c = if t < 0.5 { f.clone() } else { n.clone() };
+                    c.fg = blend(f.fg, n.fg, t);
+                    c.bg = blend(f.bg, n.bg, t);
+                    c
+                }
+                TransitionKind::Wipe(d) => {
+                    // position along the wipe, 0.0 where it starts
+                    let p = match d {
+                        WipeDir::Left => x as f32 / w,
+                        WipeDir::Right => 1.0 - (x + 1) as f32 / w,
+                        WipeDir::Up => y as f32 / h,
+                        WipeDir::Down => 1.0 - (y + 1) as f32 / h,
+                    };
+                    if p < t {
+                        cell_at(to, x, y)
+                    } else {
+                        cell_at(from, x, y)
+                    }
+                }
+                TransitionKind::Pixelate => {
+                    let d = t.min(1.0 - t) * 2.0;
+                    let s = (1.0 + d * (PIXELATE_MAX - 1.0)).round() as u16;
+                    let (sx, sy) = ((x / s) * s + s / 2, (y / s) * s + s / 2);
+                    let (sx, sy) = (sx.min(a.width - 1), sy.min(a.height - 1));
+                    if t < 0.5 {
+                        cell_at(from, sx, sy)
+                    } else {
+                        cell_at(to, sx, sy)
+                    }
+                }
+                TransitionKind::Dissolve => {
+                    if cell_noise(x, y, seed) < t {
+                        cell_at(to, x, y)
+                    } else {
+                        cell_at(from, x, y)
+                    }
+                }
+            };
+            *out.get_mut(a.x + x, a.y + y) = c;
+        }
+    }
+}
+
+fn blend(a: Color, b: Color, t: f32) -> Color {
+    if t <= 0.0 {
+        a
+    } else if t >= 1.0 {
+        b
+    } else {
+        a.blend(b, BlendMode::Normal, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Rect;
+
+    #[test]
+    fn transition_between_buffers() {
+        let r = Rect::new(0, 0, 10, 4);
+        let mut from = Buffer::empty(r);
+        let mut to = Buffer::empty(r);
+        for y in 0..4 {
+            from.set_string(0, y, "aaaaaaaaaa", Default::default());
+            to.set_string(0, y, "bbbbbbbbbb", Default::default());
+        }
+        let mut out = Buffer::empty(r);
+
+        let mut tr = Transition::new(TransitionKind::Wipe(WipeDir::Left), 1.0);
+        assert!(!tr.update(0.3));
+        tr.apply(&from, &to, &mut out);
+        assert_eq!(out.get(2, 0).symbol, "b");
+        assert_eq!(out.get(3, 0).symbol, "a");
+        tr.kind = TransitionKind::Wipe(WipeDir::Up);
+        tr.apply(&from, &to, &mut out);
+        assert_eq!(
+            (out.get(5, 1).symbol.as_str(), out.get(5, 2).symbol.as_str()),
+            ("b", "a")
+        );
+
+        // about half the cells switched
+        tr.kind = TransitionKind::Dissolve;
+        tr.time = 0.5;
+        tr.apply(&from, &to, &mut out);
+        let n = out.content.iter().filter(|c| c.symbol == "b").count();
+        assert!(n > 10 && n < 30, "{}", n);
+
+        let mut f = Cell::default();
+        f.set_symbol("a").set_fg(Color::Rgba(0, 0, 0, 255));
+        let mut t = Cell::default();
+        t.set_symbol("b").set_fg(Color::Rgba(255, 255, 255, 255));
+        let from = Buffer::filled(r, &f);
+        let to = Buffer::filled(r, &t);
+        tr.kind = TransitionKind::Crossfade;
+        tr.time = 0.6;
+        tr.apply(&from, &to, &mut out);
+        assert_eq!(out.get(0, 0).symbol, "b");
+        assert!(matches!(out.get(0, 0).fg, Color::Rgba(r, _, _, _) if r > 100 && r < 255));
+
+        tr.kind = TransitionKind::Pixelate;
+        assert!(tr.update(1.0));
+        tr.apply(&from, &to, &mut out);
+        assert_eq!(out.get(9, 3), to.get(9, 3));
+    }
+}
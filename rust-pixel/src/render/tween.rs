@@ -0,0 +1,321 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Tween interpolates sprite properties over time, so games don't have to
+//! lerp positions, angles and alpha by hand every frame.
+//!
+//! Steps added to the same tween run one after another,
+//! start value of each step is taken from the sprite when the step begins.
+
+use crate::render::sprite::{Sprite, Sprites};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ease {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InSine,
+    OutSine,
+    InOutSine,
+    OutBack,
+    OutBounce,
+    OutElastic,
+}
+
+impl Ease {
+    /// maps t in 0.0..=1.0 to eased progress
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::InQuad => t * t,
+            Ease::OutQuad => t * (2.0 - t),
+            Ease::InOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    -1.0 + (4.0 - 2.0 * t) * t
+                }
+            }
+            Ease::InCubic => t * t * t,
+            Ease::OutCubic => {
+                let f = t - 1.0;
+                f * f * f + 1.0
+            }
+            Ease::InOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    let f = 2.0 * t - 2.0;
+                    0.5 * f * f * f + 1.0
+                }
+            }
+            Ease::InSine => 1.0 - (t * PI / 2.0).cos(),
+            Ease::OutSine => (t * PI / 2.0).sin(),
+            Ease::InOutSine => -0.5 * ((PI * t).cos() - 1.0),
+            Ease::OutBack => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                let f = t - 1.0;
+                1.0 + c3 * f * f * f + c1 * f * f
+            }
+            Ease::OutBounce => {
+                let n1 = 7.5625;
+                let d1 = 2.75;
+                if t < 1.0 / d1 {
+                    n1 * t * t
+                } else if t < 2.0 / d1 {
+                    let f = t - 1.5 / d1;
+                    n1 * f * f + 0.75
+                } else if t < 2.5 / d1 {
+                    let f = t - 2.25 / d1;
+                    n1 * f * f + 0.9375
+                } else {
+                    let f = t - 2.625 / d1;
+                    n1 * f * f + 0.984375
+                }
+            }
+            Ease::OutElastic => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2.0f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+                }
+            }
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+pub type TweenCallback = Box<dyn FnMut(&mut Sprite)>;
+
+#[derive(Debug, Clone, Copy)]
+enum TweenProp {
+    Pos(f32, f32),
+    Angle(f64),
+    Scale(f32, f32),
+    Alpha(u8),
+    Wait,
+}
+
+struct TweenStep {
+    prop: TweenProp,
+    duration: f32,
+    ease: Ease,
+    // start values, taken when the step begins
+    from: Option<(f32, f32)>,
+    callback: Option<TweenCallback>,
+}
+
+impl TweenStep {
+    fn start(&mut self, sp: &Sprite) {
+        self.from = Some(match self.prop {
+            TweenProp::Pos(..) => (sp.content.area.x as f32, sp.content.area.y as f32),
+            TweenProp::Angle(_) => (sp.angle as f32, 0.0),
+            TweenProp::Scale(..) => (sp.scale_x, sp.scale_y),
+            TweenProp::Alpha(_) => (sp.alpha as f32, 0.0),
+            TweenProp::Wait => (0.0, 0.0),
+        });
+    }
+
+    fn apply(&self, sp: &mut Sprite, t: f32) {
+        let (fa, fb) = self.from.unwrap_or_default();
+        let e = self.ease.apply(t);
+        match self.prop {
+            TweenProp::Pos(x, y) => sp.set_pos(
+                lerp(fa, x, e).round().max(0.0) as u16,
+                lerp(fb, y, e).round().max(0.0) as u16,
+            ),
+            TweenProp::Angle(a) => sp.set_angle(lerp(fa, a as f32, e) as f64),
+            TweenProp::Scale(x, y) => sp.set_scale(lerp(fa, x, e), lerp(fb, y, e)),
+            TweenProp::Alpha(a) => {
+                sp.set_alpha(lerp(fa, a as f32, e).round().clamp(0.0, 255.0) as u8)
+            }
+            TweenProp::Wait => {}
+        }
+    }
+}
+
+/// a queue of steps applied to one sprite
+pub struct Tween {
+    pub layer: usize,
+    pub tag: String,
+    steps: VecDeque<TweenStep>,
+    elapsed: f32,
+}
+
+impl Tween {
+    pub fn new(layer: usize, tag: &str) -> Self {
+        Self {
+            layer,
+            tag: tag.to_string(),
+            steps: VecDeque::new(),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    fn push(&mut self, prop: TweenProp, duration: f32, ease: Ease) {
+        self.steps.push_back(TweenStep {
+            prop,
+            duration: duration.max(0.0),
+            ease,
+            from: None,
+            callback: None,
+        });
+    }
+
+    /// advances dt seconds, returns false when all steps are done
+    pub fn update(&mut self, sp: &mut Sprite, dt: f32) -> bool {
+        let mut dt = dt;
+        while let Some(step) = self.steps.front_mut() {
+            if step.from.is_none() {
+                step.start(sp);
+            }
+            let remain = step.duration - self.elapsed;
+            if dt < remain {
+                self.elapsed += dt;
+                step.apply(sp, self.elapsed / step.duration);
+                return true;
+            }
+            // step done, carry the rest of dt to next step
+            dt -= remain.max(0.0);
+            step.apply(sp, 1.0);
+            if let Some(cb) = &mut step.callback {
+                cb(sp);
+            }
+            self.steps.pop_front();
+            self.elapsed = 0.0;
+        }
+        false
+    }
+}
+
+/// chains steps onto a tween, returned by Panel::tween
+pub struct TweenBuilder<'a> {
+    tween: &'a mut Tween,
+}
+
+impl<'a> TweenBuilder<'a> {
+    pub fn new(tween: &'a mut Tween) -> Self {
+        Self { tween }
+    }
+
+    /// moves to (x, y) in cells, or pixels for pixel sprites
+    pub fn move_to(self, x: f32, y: f32, duration: f32, ease: Ease) -> Self {
+        self.tween.push(TweenProp::Pos(x, y), duration, ease);
+        self
+    }
+
+    pub fn rotate_to(self, angle: f64, duration: f32, ease: Ease) -> Self {
+        self.tween.push(TweenProp::Angle(angle), duration, ease);
+        self
+    }
+
+    pub fn scale_to(self, sx: f32, sy: f32, duration: f32, ease: Ease) -> Self {
+        self.tween.push(TweenProp::Scale(sx, sy), duration, ease);
+        self
+    }
+
+    pub fn alpha_to(self, alpha: u8, duration: f32, ease: Ease) -> Self {
+        self.tween.push(TweenProp::Alpha(alpha), duration, ease);
+        self
+    }
+
+    pub fn wait(self, duration: f32) -> Self {
+        self.tween.push(TweenProp::Wait, duration, Ease::Linear);
+        self
+    }
+
+    /// called with the sprite when all previous steps are done
+    pub fn call<F>(self, f: F) -> Self
+    where
+        F: FnMut(&mut Sprite) + 'static,
+    {
+        self.tween.push(TweenProp::Wait, 0.0, Ease::Linear);
+        self.tween.steps.back_mut().unwrap().callback = Some(Box::new(f));
+        self
+    }
+}
+
+/// all running tweens of a panel
+#[derive(Default)]
+pub struct Tweens {
+    pub tweens: Vec<Tween>,
+}
+
+impl Tweens {
+    /// gets the tween of a sprite, new steps are queued after existing ones
+    pub fn get(&mut self, layer: usize, tag: &str) -> &mut Tween {
+        let idx = match self
+            .tweens
+            .iter()
+            .position(|t| t.layer == layer && t.tag == tag)
+        {
+            Some(i) => i,
+            None => {
+                self.tweens.push(Tween::new(layer, tag));
+                self.tweens.len() - 1
+            }
+        };
+        &mut self.tweens[idx]
+    }
+
+    pub fn stop(&mut self, layer: usize, tag: &str) {
+        self.tweens.retain(|t| !(t.layer == layer && t.tag == tag));
+    }
+
+    pub fn clear(&mut self) {
+        self.tweens.clear();
+    }
+
+    /// updates all tweens, finished ones and ones whose sprite is gone are removed
+    pub fn update(&mut self, layers: &mut [Sprites], dt: f32) {
+        self.tweens.retain_mut(|t| {
+            let l = match layers.get_mut(t.layer) {
+                Some(l) => l,
+                None => return false,
+            };
+            match l.tag_index.get(&t.tag) {
+                Some(&i) => t.update(&mut l.sprites[i], dt),
+                None => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tween_steps_in_sequence() {
+        let mut sp = Sprite::new(0, 0, 2, 2);
+        let mut tw = Tween::new(0, "t");
+        TweenBuilder::new(&mut tw)
+            .move_to(10.0, 4.0, 1.0, Ease::Linear)
+            .alpha_to(0, 1.0, Ease::Linear)
+            .call(|s| s.set_hidden(true));
+        assert!(tw.update(&mut sp, 0.5));
+        assert_eq!((sp.content.area.x, sp.content.area.y), (5, 2));
+        assert!(tw.update(&mut sp, 1.0));
+        assert_eq!(sp.content.area.x, 10);
+        assert_eq!(sp.alpha, 128);
+        assert!(!tw.update(&mut sp, 0.6));
+        assert_eq!(sp.alpha, 0);
+        assert!(sp.is_hidden());
+    }
+}
@@ -0,0 +1,436 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! script runs game logic written in Lua (5.4, by mlua), enabled by the
+//! script feature on native builds. ScriptModel forwards Model calls to
+//! global functions of a script file and reloads the file when it
+//! changes, globals are kept so the game state survives a reload.
+//! ScriptRender forwards Render::draw with the panel.
+//!
+//! Functions called if the script defines them:
+//! init(ctx), update(ctx, dt), on_input(ctx, ev), on_event(ctx, name),
+//! on_timer(ctx, name), on_reload(ctx), draw(ctx, panel, dt).
+//!
+//! ctx methods: stage(), state(), set_state(n), rand(lo, hi),
+//! emit(event), set_timer(name, secs, times), cancel_timer(name).
+//! panel methods take sprite tags of the main layer or "layer:tag":
+//! add_sprite(tag, x, y, w, h), set_pos(tag, x, y),
+//! set_str(tag, x, y, text, fg, bg), clear(tag), set_hidden(tag, b),
+//! set_alpha(tag, a), set_angle(tag, deg). Colors are indexed colors.
+//! Global pixel.subscribe(event) makes on_event receive an event,
+//! pixel.log(msg) writes to the log.
+//!
+//! Lua errors are logged and kept in ScriptModel::error, the game goes on.
+
+use crate::{
+    context::Context,
+    event::{event_check, event_emit, event_register, Event, KeyCode, MouseEventKind, Repeat},
+    game::{Model, Render},
+    render::{panel::Panel, sprite::Sprite, style::Color},
+};
+use log::info;
+use mlua::{
+    AnyUserData, Error as LuaError, Function, IntoLuaMulti, Lua, Table, UserData, UserDataMethods,
+    Value,
+};
+use std::{cell::RefCell, rc::Rc, time::SystemTime};
+
+// seconds between checks of the script file
+const RELOAD_CHECK: f32 = 0.5;
+
+// subscriber name of script events
+const SCRIPT_FUNC: &str = "script";
+
+fn sprite_mut<'a>(p: &'a mut Panel, name: &str) -> mlua::Result<&'a mut Sprite> {
+    let (layer, tag) = match name.split_once(':') {
+        Some((l, t)) => (p.layer_tag_index.get(l).copied(), t),
+        None => (Some(0), name),
+    };
+    let sps = layer.and_then(|l| p.layers.get_mut(l));
+    match sps {
+        Some(s) if s.tag_index.contains_key(tag) => Ok(s.get_by_tag(tag)),
+        _ => Err(LuaError::RuntimeError(format!("no sprite {}", name))),
+    }
+}
+
+fn color(c: Option<u8>) -> Color {
+    c.map_or(Color::Reset, Color::Indexed)
+}
+
+impl UserData for Panel {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method_mut(
+            "add_sprite",
+            |_, p, (tag, x, y, w, h): (String, u16, u16, u16, u16)| {
+                p.add_sprite(Sprite::new(x, y, w, h), &tag);
+                Ok(())
+            },
+        );
+        methods.add_method_mut("set_pos", |_, p, (tag, x, y): (String, u16, u16)| {
+            sprite_mut(p, &tag)?.set_pos(x, y);
+            Ok(())
+        });
+        methods.add_method_mut(
+            "set_str",
+            |_, p, (tag, x, y, s, f, b): (String, u16, u16, String, Option<u8>, Option<u8>)| {
+                sprite_mut(p, &tag)?.set_color_str(x, y, s, color(f), color(b));
+                Ok(())
+            },
+        );
+        methods.add_method_mut("clear", |_, p, tag: String| {
+            sprite_mut(p, &tag)?.content.reset();
+            Ok(())
+        });
+        methods.add_method_mut("set_hidden", |_, p, (tag, h): (String, bool)| {
+            sprite_mut(p, &tag)?.set_hidden(h);
+            Ok(())
+        });
+        methods.add_method_mut("set_alpha", |_, p, (tag, a): (String, u8)| {
+            sprite_mut(p, &tag)?.set_alpha(a);
+            Ok(())
+        });
+        methods.add_method_mut("set_angle", |_, p, (tag, a): (String, f64)| {
+            sprite_mut(p, &tag)?.set_angle(a);
+            Ok(())
+        });
+    }
+}
+
+impl UserData for Context {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("stage", |_, c, ()| Ok(c.stage));
+        methods.add_method("state", |_, c, ()| Ok(c.state));
+        methods.add_method_mut("set_state", |_, c, s: u8| {
+            c.state = s;
+            Ok(())
+        });
+        methods.add_method_mut("rand", |_, c, (lo, hi): (i32, i32)| {
+            Ok(c.rand.range(lo, hi))
+        });
+        methods.add_method("emit", |_, _, e: String| {
+            event_emit(&e);
+            Ok(())
+        });
+        methods.add_method_mut(
+            "set_timer",
+            |_, c, (name, secs, times): (String, f32, Option<u32>)| {
+                let r = match times.unwrap_or(1) {
+                    0 => Repeat::Forever,
+                    1 => Repeat::Once,
+                    n => Repeat::Times(n),
+                };
+                c.timers.set(&name, secs, r);
+                Ok(())
+            },
+        );
+        methods.add_method_mut("cancel_timer", |_, c, name: String| {
+            Ok(c.timers.cancel(&name))
+        });
+    }
+}
+
+fn key_name(k: KeyCode) -> String {
+    match k {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        k => format!("{:?}", k).to_lowercase(),
+    }
+}
+
+// input event as a lua table with a type field, nil for other events
+fn event_table<'lua>(lua: &'lua Lua, e: &Event) -> mlua::Result<Option<Table<'lua>>> {
+    let t = lua.create_table()?;
+    match e {
+        Event::Key(k) => {
+            t.set("type", "key")?;
+            t.set("key", key_name(k.code))?;
+            t.set("kind", format!("{:?}", k.kind).to_lowercase())?;
+        }
+        Event::Mouse(m) => {
+            t.set("type", "mouse")?;
+            t.set("x", m.column)?;
+            t.set("y", m.row)?;
+            let kind = match m.kind {
+                MouseEventKind::Down(_) => "down",
+                MouseEventKind::Up(_) => "up",
+                MouseEventKind::Drag(_) => "drag",
+                MouseEventKind::Moved => "moved",
+                MouseEventKind::ScrollDown => "scroll_down",
+                MouseEventKind::ScrollUp => "scroll_up",
+            };
+            t.set("kind", kind)?;
+        }
+        Event::Text(s) => {
+            t.set("type", "text")?;
+            t.set("text", s.as_str())?;
+        }
+        _ => return Ok(None),
+    }
+    Ok(Some(t))
+}
+
+/// Model running a Lua script
+pub struct ScriptModel {
+    lua: Lua,
+    path: Option<String>,
+    modified: Option<SystemTime>,
+    check_left: f32,
+    events: Rc<RefCell<Vec<String>>>,
+    /// last Lua error
+    pub error: Option<String>,
+}
+
+impl ScriptModel {
+    /// script loaded from a file in init, reloaded when it changes
+    pub fn new(path: &str) -> Self {
+        let mut m = Self::from_source("");
+        m.path = Some(path.to_string());
+        m
+    }
+
+    /// script from a string, no reload
+    pub fn from_source(src: &str) -> Self {
+        let lua = Lua::new();
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut m = Self {
+            lua,
+            path: None,
+            modified: None,
+            check_left: 0.0,
+            events,
+            error: None,
+        };
+        let r = m.register_globals();
+        m.check(r);
+        if !src.is_empty() {
+            let r = m.lua.load(src).set_name("script").exec();
+            m.check(r);
+        }
+        m
+    }
+
+    fn register_globals(&self) -> mlua::Result<()> {
+        let pixel = self.lua.create_table()?;
+        let evs = self.events.clone();
+        pixel.set(
+            "subscribe",
+            self.lua.create_function(move |_, e: String| {
+                if !evs.borrow().contains(&e) {
+                    event_register(&e, SCRIPT_FUNC);
+                    evs.borrow_mut().push(e);
+                }
+                Ok(())
+            })?,
+        )?;
+        pixel.set(
+            "log",
+            self.lua.create_function(|_, s: String| {
+                info!("lua: {}", s);
+                Ok(())
+            })?,
+        )?;
+        self.lua.globals().set("pixel", pixel)
+    }
+
+    fn check<T>(&mut self, r: mlua::Result<T>) {
+        if let Err(e) = r {
+            info!("script error:{}", e);
+            self.error = Some(e.to_string());
+        }
+    }
+
+    // loads the file if it changed since the last load
+    fn load_file(&mut self) -> bool {
+        let Some(path) = self.path.clone() else {
+            return false;
+        };
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if self.modified.is_some() && modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        match std::fs::read_to_string(&path) {
+            Ok(src) => {
+                let r = self.lua.load(&src).set_name(path.as_str()).exec();
+                self.error = None;
+                self.check(r);
+                true
+            }
+            Err(e) => {
+                self.check::<()>(Err(LuaError::RuntimeError(format!("{}: {}", path, e))));
+                false
+            }
+        }
+    }
+
+    /// calls a global function of the script with ctx and args
+    pub fn call<A>(&mut self, ctx: &mut Context, name: &str, args: A)
+    where
+        A: for<'lua> IntoLuaMulti<'lua>,
+    {
+        let r = self.call_with(name, |s, f| {
+            let c = s.create_userdata_ref_mut(ctx)?;
+            f.call::<_, ()>((c, args))
+        });
+        self.check(r);
+    }
+
+    fn call_with<'lua, F>(&'lua self, name: &str, f: F) -> mlua::Result<()>
+    where
+        F: for<'s> FnOnce(&mlua::Scope<'lua, 's>, Function<'lua>) -> mlua::Result<()>,
+    {
+        match self.lua.globals().get::<_, Value>(name)? {
+            Value::Function(func) => self.lua.scope(|s| f(s, func)),
+            _ => Ok(()),
+        }
+    }
+
+    fn call_draw(&mut self, ctx: &mut Context, panel: &mut Panel, dt: f32) {
+        let r = self.call_with("draw", |s, f| {
+            let c: AnyUserData = s.create_userdata_ref_mut(ctx)?;
+            let p = s.create_userdata_ref_mut(panel)?;
+            f.call::<_, ()>((c, p, dt))
+        });
+        self.check(r);
+    }
+}
+
+impl Model for ScriptModel {
+    fn init(&mut self, ctx: &mut Context) {
+        self.load_file();
+        self.call(ctx, "init", ());
+    }
+
+    fn handle_event(&mut self, ctx: &mut Context, _dt: f32) {
+        let evs = self.events.borrow().clone();
+        for e in evs {
+            if event_check(&e, SCRIPT_FUNC) {
+                self.call(ctx, "on_event", e);
+            }
+        }
+    }
+
+    fn handle_timer(&mut self, ctx: &mut Context, _dt: f32) {
+        let fired: Vec<String> = ctx
+            .timers
+            .names()
+            .filter(|n| ctx.timers.just_fired(n))
+            .map(|n| n.to_string())
+            .collect();
+        for n in fired {
+            self.call(ctx, "on_timer", n);
+        }
+    }
+
+    fn handle_input(&mut self, ctx: &mut Context, _dt: f32) {
+        let es = ctx.input_events.clone();
+        let lua = &self.lua;
+        let r = self.call_with("on_input", |s, f| {
+            let c = s.create_userdata_ref_mut(ctx)?;
+            for e in es.iter() {
+                if let Some(t) = event_table(lua, e)? {
+                    f.call::<_, ()>((c.clone(), t))?;
+                }
+            }
+            Ok(())
+        });
+        self.check(r);
+    }
+
+    fn handle_auto(&mut self, ctx: &mut Context, dt: f32) {
+        self.check_left -= dt;
+        if self.path.is_some() && self.check_left <= 0.0 {
+            self.check_left = RELOAD_CHECK;
+            if self.load_file() {
+                info!("script reloaded");
+                self.call(ctx, "on_reload", ());
+            }
+        }
+        self.call(ctx, "update", dt);
+    }
+}
+
+/// Render calling draw(ctx, panel, dt) of a ScriptModel
+pub struct ScriptRender {
+    pub panel: Panel,
+}
+
+impl Default for ScriptRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptRender {
+    pub fn new() -> Self {
+        Self {
+            panel: Panel::new(),
+        }
+    }
+}
+
+impl Render for ScriptRender {
+    type Model = ScriptModel;
+
+    fn init(&mut self, ctx: &mut Context, _m: &mut ScriptModel) {
+        self.panel.init(ctx);
+    }
+
+    fn handle_event(&mut self, _ctx: &mut Context, _m: &mut ScriptModel, _dt: f32) {}
+
+    fn handle_timer(&mut self, _ctx: &mut Context, _m: &mut ScriptModel, _dt: f32) {}
+
+    fn draw(&mut self, ctx: &mut Context, m: &mut ScriptModel, dt: f32) {
+        m.call_draw(ctx, &mut self.panel, dt);
+        self.panel.draw(ctx).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyEvent, KeyModifiers};
+
+    #[test]
+    fn script_model_forwards_calls() {
+        let mut ctx = Context::new("games", "test", "");
+        let mut m = ScriptModel::from_source(
+            r#"
+            function init(ctx)
+                x = 1
+                pixel.subscribe("Script.Test")
+                ctx:set_timer("t", 0.1)
+            end
+            function on_input(ctx, ev) if ev.key == "right" then x = x + 1 end end
+            function on_event(ctx, name) ctx:set_state(7) end
+            function on_timer(ctx, name) fired = name end
+            function draw(ctx, panel, dt) panel:set_str("s", 0, 0, "x" .. x, 1) end
+            function update(ctx, dt) if dt > 1 then error("boom") end end
+            "#,
+        );
+        assert!(m.error.is_none());
+        m.init(&mut ctx);
+        ctx.input_events.push(Event::Key(KeyEvent::new(
+            KeyCode::Right,
+            KeyModifiers::NONE,
+        )));
+        event_emit("Script.Test");
+        ctx.timers.update(0.2);
+        m.handle_input(&mut ctx, 0.0);
+        m.handle_event(&mut ctx, 0.0);
+        m.handle_timer(&mut ctx, 0.0);
+        assert_eq!(ctx.state, 7);
+        assert_eq!(m.lua.globals().get::<_, String>("fired").unwrap(), "t");
+
+        let mut p = Panel::new();
+        p.add_sprite(Sprite::new(0, 0, 4, 1), "s");
+        m.call_draw(&mut ctx, &mut p, 0.0);
+        assert!(m.error.is_none());
+        assert_eq!(p.get_sprite("s").content.get(0, 0).symbol, "x");
+        assert_eq!(p.get_sprite("s").content.get(1, 0).symbol, "2");
+
+        m.handle_auto(&mut ctx, 2.0);
+        assert!(m.error.as_ref().unwrap().contains("boom"));
+    }
+}
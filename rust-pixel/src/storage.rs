@@ -0,0 +1,408 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Storage saves game states in named slots.
+//! Any serde type is encoded with bincode and wrapped in a small header:
+//! magic "PXSV", a u32 version and a crc32 of the payload, so truncated or
+//! modified saves are detected instead of loading garbage.
+//!
+//! Desktop saves go to files in project_path/save, written to a temp file
+//! and renamed to avoid half written saves. Web saves go to localStorage
+//! as base64 text, keyed by game name and slot.
+//!
+//! localStorage holds a few MB only, the async api saves to IndexedDB on
+//! web instead, for big saves or cached data. save_async, load_async and
+//! delete_async return at once, STORAGE_EVENT is emitted with a
+//! StorageDone when the request finished, a load is then taken with
+//! take_loaded. A full browser storage fails with StorageError::Quota.
+//! On desktop the async api uses the save files and finishes right away.
+
+use crate::event::event_emit_data;
+#[cfg(target_arch = "wasm32")]
+use crate::util::{base64_decode, base64_encode};
+use flate2::Crc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+// IndexedDB requests, refer to rust-pixel/web-templates/host.js
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(raw_module = "/host.js")]
+extern "C" {
+    fn js_idb_put(key: &str, data: &[u8]);
+    fn js_idb_get(key: &str);
+    fn js_idb_delete(key: &str);
+}
+
+/// emitted with a StorageDone as data when an async request finished
+pub const STORAGE_EVENT: &str = "Storage.Done";
+
+const MAGIC: &[u8; 4] = b"PXSV";
+const HEADER_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageError {
+    NotFound,
+    /// bad magic, bad checksum or undecodable payload
+    Corrupt,
+    /// saved with a newer version than Storage.version
+    Version(u32),
+    /// the browser storage quota is used up
+    Quota,
+    Io(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageOp {
+    Save,
+    Load,
+    Delete,
+}
+
+/// an async request finished, error is None on success
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageDone {
+    pub op: StorageOp,
+    pub slot: String,
+    pub error: Option<StorageError>,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::NotFound => write!(f, "save not found"),
+            StorageError::Corrupt => write!(f, "save corrupt"),
+            StorageError::Version(v) => write!(f, "save version {} not supported", v),
+            StorageError::Quota => write!(f, "save storage quota exceeded"),
+            StorageError::Io(e) => write!(f, "save io error:{}", e),
+        }
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// wraps payload with magic, version and checksum
+pub fn encode_save(version: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&version.to_le_bytes());
+    out.extend_from_slice(&crc32(payload).to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// checks header and checksum, returns version and payload
+pub fn decode_save(data: &[u8]) -> Result<(u32, &[u8]), StorageError> {
+    if data.len() < HEADER_LEN || &data[0..4] != MAGIC {
+        return Err(StorageError::Corrupt);
+    }
+    let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    let crc = u32::from_le_bytes(data[8..12].try_into().unwrap());
+    let payload = &data[HEADER_LEN..];
+    if crc32(payload) != crc {
+        return Err(StorageError::Corrupt);
+    }
+    Ok((version, payload))
+}
+
+/// Save slots of a game, in context.storage
+pub struct Storage {
+    pub game_name: String,
+    /// directory of save files, not used on web
+    pub dir: String,
+    /// written to new saves, saves with a higher version are refused
+    pub version: u32,
+    // finished async loads, taken by take_loaded
+    loaded: HashMap<String, Result<Vec<u8>, StorageError>>,
+}
+
+impl Storage {
+    pub fn new(game_name: &str, project_path: &str) -> Self {
+        let dir = if project_path.is_empty() {
+            "save".to_string()
+        } else {
+            format!("{}{}save", project_path, std::path::MAIN_SEPARATOR)
+        };
+        Self {
+            game_name: game_name.to_string(),
+            dir,
+            version: 1,
+            loaded: HashMap::new(),
+        }
+    }
+
+    fn encode<T: Serialize>(&self, state: &T) -> Result<Vec<u8>, StorageError> {
+        let payload = bincode::serialize(state).map_err(|e| StorageError::Io(e.to_string()))?;
+        Ok(encode_save(self.version, &payload))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, StorageError> {
+        let (version, payload) = decode_save(data)?;
+        if version > self.version {
+            return Err(StorageError::Version(version));
+        }
+        bincode::deserialize(payload).map_err(|_| StorageError::Corrupt)
+    }
+
+    pub fn save<T: Serialize>(&self, slot: &str, state: &T) -> Result<(), StorageError> {
+        self.write_raw(slot, &self.encode(state)?)
+    }
+
+    pub fn load<T: DeserializeOwned>(&self, slot: &str) -> Result<T, StorageError> {
+        self.decode(&self.read_raw(slot)?)
+    }
+
+    /// saves in the background, IndexedDB on web, fails only if state
+    /// can not be encoded
+    pub fn save_async<T: Serialize>(&mut self, slot: &str, state: &T) -> Result<(), StorageError> {
+        let data = self.encode(state)?;
+        #[cfg(target_arch = "wasm32")]
+        js_idb_put(&self.key(slot), &data);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let r = self.write_raw(slot, &data);
+            self.finish(StorageOp::Save, slot, r.map(|_| vec![]));
+        }
+        Ok(())
+    }
+
+    /// loads in the background, take the state with take_loaded after
+    /// STORAGE_EVENT
+    pub fn load_async(&mut self, slot: &str) {
+        #[cfg(target_arch = "wasm32")]
+        js_idb_get(&self.key(slot));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let r = self.read_raw(slot);
+            self.finish(StorageOp::Load, slot, r);
+        }
+    }
+
+    pub fn delete_async(&mut self, slot: &str) {
+        #[cfg(target_arch = "wasm32")]
+        js_idb_delete(&self.key(slot));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let r = self.delete(slot);
+            self.finish(StorageOp::Delete, slot, r.map(|_| vec![]));
+        }
+    }
+
+    /// state of a finished load_async, NotFound if none is pending
+    pub fn take_loaded<T: DeserializeOwned>(&mut self, slot: &str) -> Result<T, StorageError> {
+        let data = self.loaded.remove(slot).ok_or(StorageError::NotFound)??;
+        self.decode(&data)
+    }
+
+    // keeps the data of a load and emits STORAGE_EVENT
+    fn finish(&mut self, op: StorageOp, slot: &str, r: Result<Vec<u8>, StorageError>) {
+        let error = r.as_ref().err().cloned();
+        if op == StorageOp::Load {
+            self.loaded.insert(slot.to_string(), r);
+        }
+        let done = StorageDone {
+            op,
+            slot: slot.to_string(),
+            error,
+        };
+        event_emit_data(STORAGE_EVENT, &done);
+    }
+
+    /// result of an IndexedDB request of host.js, op 0 save, 1 load,
+    /// 2 delete, err is empty on success or the name of the js error
+    pub fn web_done(&mut self, op: u8, key: &str, err: &str, data: &[u8]) {
+        let op = match op {
+            0 => StorageOp::Save,
+            1 => StorageOp::Load,
+            _ => StorageOp::Delete,
+        };
+        let r = match err {
+            "" => Ok(data.to_vec()),
+            "NotFound" => Err(StorageError::NotFound),
+            "QuotaExceededError" => Err(StorageError::Quota),
+            e => Err(StorageError::Io(e.to_string())),
+        };
+        let prefix = self.key("");
+        let slot = key.strip_prefix(&prefix).unwrap_or(key).to_string();
+        self.finish(op, &slot, r);
+    }
+
+    /// version of a saved slot, to migrate old saves by hand
+    pub fn slot_version(&self, slot: &str) -> Result<u32, StorageError> {
+        let data = self.read_raw(slot)?;
+        decode_save(&data).map(|(v, _)| v)
+    }
+
+    pub fn exists(&self, slot: &str) -> bool {
+        self.read_raw(slot).is_ok()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn path(&self, slot: &str) -> std::path::PathBuf {
+        std::path::Path::new(&self.dir).join(format!("{}.sav", slot))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_raw(&self, slot: &str, data: &[u8]) -> Result<(), StorageError> {
+        let io = |e: std::io::Error| StorageError::Io(e.to_string());
+        std::fs::create_dir_all(&self.dir).map_err(io)?;
+        let path = self.path(slot);
+        let tmp = path.with_extension("sav.tmp");
+        std::fs::write(&tmp, data).map_err(io)?;
+        std::fs::rename(&tmp, &path).map_err(io)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_raw(&self, slot: &str) -> Result<Vec<u8>, StorageError> {
+        std::fs::read(self.path(slot)).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => StorageError::NotFound,
+            _ => StorageError::Io(e.to_string()),
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn delete(&self, slot: &str) -> Result<(), StorageError> {
+        std::fs::remove_file(self.path(slot)).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => StorageError::NotFound,
+            _ => StorageError::Io(e.to_string()),
+        })
+    }
+
+    /// names of saved slots
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn slots(&self) -> Vec<String> {
+        let mut ss: Vec<String> = std::fs::read_dir(&self.dir)
+            .map(|rd| {
+                rd.filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        let name = e.file_name().to_string_lossy().to_string();
+                        name.strip_suffix(".sav").map(|s| s.to_string())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        ss.sort();
+        ss
+    }
+
+    // localStorage and IndexedDB key
+    fn key(&self, slot: &str) -> String {
+        format!("rust_pixel/{}/{}", self.game_name, slot)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn local_storage() -> Result<web_sys::Storage, StorageError> {
+        web_sys::window()
+            .and_then(|w| w.local_storage().ok().flatten())
+            .ok_or(StorageError::Io("localStorage not available".to_string()))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn write_raw(&self, slot: &str, data: &[u8]) -> Result<(), StorageError> {
+        Self::local_storage()?
+            .set_item(&self.key(slot), &base64_encode(data))
+            .map_err(|e| StorageError::Io(format!("{:?}", e)))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read_raw(&self, slot: &str) -> Result<Vec<u8>, StorageError> {
+        let s = Self::local_storage()?
+            .get_item(&self.key(slot))
+            .ok()
+            .flatten()
+            .ok_or(StorageError::NotFound)?;
+        base64_decode(&s).ok_or(StorageError::Corrupt)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn delete(&self, slot: &str) -> Result<(), StorageError> {
+        Self::local_storage()?
+            .remove_item(&self.key(slot))
+            .map_err(|e| StorageError::Io(format!("{:?}", e)))
+    }
+
+    /// names of saved slots
+    #[cfg(target_arch = "wasm32")]
+    pub fn slots(&self) -> Vec<String> {
+        let Ok(ls) = Self::local_storage() else {
+            return vec![];
+        };
+        let prefix = self.key("");
+        let n = ls.length().unwrap_or(0);
+        let mut ss: Vec<String> = (0..n)
+            .filter_map(|i| ls.key(i).ok().flatten())
+            .filter_map(|k| k.strip_prefix(&prefix).map(|s| s.to_string()))
+            .collect();
+        ss.sort();
+        ss
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{base64_decode, base64_encode};
+
+    #[test]
+    fn save_roundtrip_and_corruption() {
+        let data = encode_save(3, b"pixel");
+        assert_eq!(decode_save(&data), Ok((3, &b"pixel"[..])));
+        let mut bad = data.clone();
+        bad[13] ^= 1;
+        assert_eq!(decode_save(&bad), Err(StorageError::Corrupt));
+        assert_eq!(decode_save(&data[..8]), Err(StorageError::Corrupt));
+        let b64 = base64_encode(&data);
+        assert_eq!(base64_decode(&b64).unwrap(), data);
+
+        let dir = std::env::temp_dir().join(format!("pxsave{}", std::process::id()));
+        let mut st = Storage::new("test", "");
+        st.dir = dir.to_string_lossy().to_string();
+        st.save("slot1", &(7u32, "hero".to_string())).unwrap();
+        assert_eq!(st.slots(), vec!["slot1".to_string()]);
+        let s: (u32, String) = st.load("slot1").unwrap();
+        assert_eq!(s, (7, "hero".to_string()));
+        st.version = 0;
+        assert_eq!(
+            st.load::<(u32, String)>("slot1"),
+            Err(StorageError::Version(1))
+        );
+        st.delete("slot1").unwrap();
+        assert_eq!(st.load::<u32>("slot1"), Err(StorageError::NotFound));
+        assert_eq!(st.delete("slot1"), Err(StorageError::NotFound));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn async_saves_emit_done() {
+        use crate::event::{event_check_data, event_register};
+        event_register(STORAGE_EVENT, "storage_test");
+        let done = || event_check_data::<StorageDone>(STORAGE_EVENT, "storage_test").unwrap();
+        let dir = std::env::temp_dir().join(format!("pxasync{}", std::process::id()));
+        let mut st = Storage::new("test", "");
+        st.dir = dir.to_string_lossy().to_string();
+        st.save_async("big", &vec![1u8; 100]).unwrap();
+        let d = done();
+        assert_eq!((d.op, d.error), (StorageOp::Save, None));
+        st.load_async("big");
+        assert_eq!(done().op, StorageOp::Load);
+        assert_eq!(st.take_loaded::<Vec<u8>>("big").unwrap().len(), 100);
+        let again = st.take_loaded::<Vec<u8>>("big");
+        assert_eq!(again, Err(StorageError::NotFound));
+        let _ = std::fs::remove_dir_all(dir);
+
+        // answers of host.js
+        let key = st.key("big");
+        let data = encode_save(1, &bincode::serialize(&7u32).unwrap());
+        st.web_done(1, &key, "", &data);
+        assert_eq!(done().slot, "big");
+        assert_eq!(st.take_loaded::<u32>("big"), Ok(7));
+        st.web_done(0, &key, "QuotaExceededError", &[]);
+        assert_eq!(done().error, Some(StorageError::Quota));
+    }
+}
@@ -0,0 +1,387 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! ui holds retained widgets drawn into a panel sprite
+//!
+//! A Ui keeps widgets by id with their areas in cells of the sprite it is
+//! drawn into. update takes the input events of the frame: a click
+//! focuses the widget under the mouse, Tab and BackTab move the focus,
+//! other keys go to the focused widget first, arrows it ignores move the
+//! focus to the nearest widget that way, see FocusRing. Activations come back from update and are also emitted as
+//! UI_EVENT with the UiEvent as data.
+//! Colors are taken from the theme: ui.fg, ui.bg, ui.accent and ui.selected.
+
+use crate::event::{
+    event_emit_data, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEventKind,
+};
+use crate::render::{
+    buffer::Buffer,
+    sprite::Sprite,
+    style::{Style, Theme},
+};
+pub use crate::util::Rect;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use unicode_width::UnicodeWidthStr;
+
+mod widgets;
+pub use widgets::*;
+mod list;
+pub use list::*;
+mod log_panel;
+pub use log_panel::*;
+mod table;
+pub use table::*;
+mod progress;
+pub use progress::*;
+mod menu;
+pub use menu::*;
+mod focus;
+pub use focus::*;
+mod dialog;
+pub use dialog::*;
+
+/// emitted with a UiEvent as data
+pub const UI_EVENT: &str = "Ui.Event";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UiEventKind {
+    Clicked,
+    Toggled(bool),
+    Changed(f32),
+    Selected(usize),
+    Activated(usize),
+    Scrolled(usize),
+    /// column and ascending
+    Sorted(usize, bool),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UiEvent {
+    pub id: String,
+    pub kind: UiEventKind,
+}
+
+/// input passed to a widget, positions are relative to its area
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UiInput {
+    Key(KeyEvent),
+    Click(u16, u16),
+    /// mouse moved with the left button held, after a click on the widget
+    Drag(u16, u16),
+    /// wheel steps, negative is up
+    Wheel(i32),
+}
+
+/// what a widget did with an input
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiReply {
+    /// not used, e.g. an arrow key may move the focus then
+    Ignored,
+    Handled,
+    Event(UiEventKind),
+}
+
+pub trait UiWidget {
+    /// area in cells of the sprite
+    fn area(&self) -> Rect;
+    fn set_area(&mut self, area: Rect);
+    fn focusable(&self) -> bool {
+        true
+    }
+    fn handle(&mut self, input: UiInput) -> UiReply;
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// fg and bg of a widget by state
+pub fn widget_style(theme: &Theme, focused: bool) -> Style {
+    if focused {
+        theme.style("ui.bg", "ui.selected")
+    } else {
+        theme.style("ui.fg", "ui.bg")
+    }
+}
+
+/// writes s at (x, y) relative to buf, cut at width cells
+pub fn draw_text(buf: &mut Buffer, x: u16, y: u16, s: &str, width: u16, style: Style) {
+    let a = buf.area;
+    if x >= a.width || y >= a.height || width == 0 {
+        return;
+    }
+    let w = width.min(a.width - x) as usize;
+    buf.set_stringn(a.x + x, a.y + y, s, w, style, 0);
+}
+
+/// writes s padded with spaces to fill width cells
+pub fn draw_text_fill(buf: &mut Buffer, x: u16, y: u16, s: &str, width: u16, style: Style) {
+    let pad = (width as usize).saturating_sub(s.width());
+    draw_text(
+        buf,
+        x,
+        y,
+        &format!("{}{}", s, " ".repeat(pad)),
+        width,
+        style,
+    );
+}
+
+/// vertical scroll bar in column x from y, for offset of total in view rows
+pub fn draw_scrollbar(
+    buf: &mut Buffer,
+    x: u16,
+    y: u16,
+    view: usize,
+    offset: usize,
+    total: usize,
+    style: Style,
+) {
+    if total <= view || view == 0 {
+        return;
+    }
+    let thumb = (view * view / total).max(1);
+    let pos = (offset * (view - thumb) + (total - view) / 2) / (total - view);
+    for i in 0..view {
+        let s = if i >= pos && i < pos + thumb {
+            "#"
+        } else {
+            "|"
+        };
+        draw_text(buf, x, y + i as u16, s, 1, style);
+    }
+}
+
+/// retained widgets of one sprite
+#[derive(Default)]
+pub struct Ui {
+    widgets: Vec<(String, Box<dyn UiWidget>)>,
+    focus: FocusRing,
+    // widget pressed by the left button, gets drags
+    pressed: Option<usize>,
+    // sprite position of the last draw, to match mouse positions
+    origin: (u16, u16),
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a widget, replaces one of the same id
+    pub fn add<W: UiWidget + 'static>(&mut self, id: &str, w: W) {
+        match self.widgets.iter().position(|(i, _)| i == id) {
+            Some(i) => self.widgets[i].1 = Box::new(w),
+            None => self.widgets.push((id.to_string(), Box::new(w))),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if let Some(i) = self.widgets.iter().position(|(w, _)| w == id) {
+            self.widgets.remove(i);
+            self.focus.remove(id);
+            self.pressed = None;
+        }
+    }
+
+    pub fn get<T: 'static>(&self, id: &str) -> Option<&T> {
+        self.widgets
+            .iter()
+            .find(|(i, _)| i == id)
+            .and_then(|(_, w)| w.as_any().downcast_ref::<T>())
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, id: &str) -> Option<&mut T> {
+        self.widgets
+            .iter_mut()
+            .find(|(i, _)| i == id)
+            .and_then(|(_, w)| w.as_any_mut().downcast_mut::<T>())
+    }
+
+    // focus ring follows the focusable widgets and their areas
+    fn sync_focus(&mut self) {
+        for (id, w) in &self.widgets {
+            if w.focusable() {
+                self.focus.add(id, w.area());
+            } else {
+                self.focus.remove(id);
+            }
+        }
+    }
+
+    /// id of the focused widget
+    pub fn focused(&self) -> Option<&str> {
+        self.focus.focused()
+    }
+
+    pub fn set_focus(&mut self, id: &str) {
+        self.sync_focus();
+        self.focus.set_focus(id);
+    }
+
+    /// moves the focus to the next focusable widget, backwards if back
+    pub fn focus_next(&mut self, back: bool) {
+        self.sync_focus();
+        self.focus.next(back);
+    }
+
+    fn focused_index(&self) -> Option<usize> {
+        let f = self.focus.focused()?;
+        self.widgets.iter().position(|(i, _)| i == f)
+    }
+
+    // widget under a sprite cell, with the position inside it
+    fn hit(&self, col: u16, row: u16) -> Option<(usize, u16, u16)> {
+        let (x, y) = (
+            col.checked_sub(self.origin.0)?,
+            row.checked_sub(self.origin.1)?,
+        );
+        self.widgets
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, (_, w))| {
+                let a = w.area();
+                if x >= a.left() && x < a.right() && y >= a.top() && y < a.bottom() {
+                    Some((i, x - a.x, y - a.y))
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn send(&mut self, i: usize, input: UiInput, out: &mut Vec<UiEvent>) -> bool {
+        match self.widgets[i].1.handle(input) {
+            UiReply::Ignored => false,
+            UiReply::Handled => true,
+            UiReply::Event(kind) => {
+                let ev = UiEvent {
+                    id: self.widgets[i].0.clone(),
+                    kind,
+                };
+                event_emit_data(UI_EVENT, &ev);
+                out.push(ev);
+                true
+            }
+        }
+    }
+
+    /// processes input events of this frame, returns the widget events
+    pub fn update(&mut self, events: &[Event]) -> Vec<UiEvent> {
+        let mut out = vec![];
+        for e in events {
+            match e {
+                Event::Key(k) if k.kind != KeyEventKind::Release => {
+                    self.sync_focus();
+                    let nav = k.code == KeyCode::Tab || k.code == KeyCode::BackTab;
+                    let used = match self.focused_index() {
+                        Some(f) if !nav => self.send(f, UiInput::Key(*k), &mut out),
+                        _ => false,
+                    };
+                    if !used {
+                        self.focus.navigate(k);
+                    }
+                }
+                Event::Mouse(m) => match m.kind {
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        self.pressed = None;
+                        if let Some((i, x, y)) = self.hit(m.column, m.row) {
+                            if self.widgets[i].1.focusable() {
+                                self.sync_focus();
+                                self.focus.set_focus(&self.widgets[i].0);
+                            }
+                            self.pressed = Some(i);
+                            self.send(i, UiInput::Click(x, y), &mut out);
+                        }
+                    }
+                    MouseEventKind::Drag(MouseButton::Left) => {
+                        if let Some(i) = self.pressed {
+                            let a = self.widgets[i].1.area();
+                            let x = m.column.saturating_sub(self.origin.0 + a.x);
+                            let y = m.row.saturating_sub(self.origin.1 + a.y);
+                            let x = x.min(a.width.saturating_sub(1));
+                            let y = y.min(a.height.saturating_sub(1));
+                            self.send(i, UiInput::Drag(x, y), &mut out);
+                        }
+                    }
+                    MouseEventKind::Up(MouseButton::Left) => self.pressed = None,
+                    MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                        if let Some((i, _, _)) = self.hit(m.column, m.row) {
+                            let d = if m.kind == MouseEventKind::ScrollUp {
+                                -1
+                            } else {
+                                1
+                            };
+                            self.send(i, UiInput::Wheel(d), &mut out);
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        out
+    }
+
+    /// draws all widgets into the sprite, mouse positions of later
+    /// updates are matched against this sprite
+    pub fn draw(&mut self, sp: &mut Sprite, theme: &Theme) {
+        self.origin = (sp.content.area.x, sp.content.area.y);
+        for (id, w) in self.widgets.iter() {
+            w.draw(&mut sp.content, theme, self.focus.is_focused(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{KeyModifiers, MouseEvent};
+
+    pub(crate) fn key(c: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(c, KeyModifiers::NONE))
+    }
+
+    pub(crate) fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn ui_focus_and_widgets() {
+        let mut ui = Ui::new();
+        ui.add("ok", Button::new(Rect::new(0, 0, 6, 1), "OK"));
+        ui.add("snd", Checkbox::new(Rect::new(0, 1, 10, 1), "Sound", false));
+        ui.add("vol", Slider::new(Rect::new(0, 2, 10, 1), 0.0, 10.0, 5.0));
+        let mut sp = Sprite::new(10, 5, 20, 5);
+        ui.draw(&mut sp, &Theme::dark());
+        assert_eq!(sp.content.get(12, 5).symbol, "O");
+
+        assert!(ui.update(&[key(KeyCode::Enter)]).is_empty());
+        let ev = ui.update(&[key(KeyCode::Tab), key(KeyCode::Enter)]);
+        assert_eq!(ev[0].id, "ok");
+        assert_eq!(ev[0].kind, UiEventKind::Clicked);
+        // buttons ignore arrows, focus moves
+        let ev = ui.update(&[key(KeyCode::Down), key(KeyCode::Char(' '))]);
+        assert_eq!(ev[0].kind, UiEventKind::Toggled(true));
+        assert!(ui.get::<Checkbox>("snd").unwrap().checked);
+
+        // click on the slider track, then drag to the end
+        let ev = ui.update(&[
+            mouse(MouseEventKind::Down(MouseButton::Left), 10, 7),
+            mouse(MouseEventKind::Drag(MouseButton::Left), 40, 7),
+        ]);
+        assert_eq!(ui.focused(), Some("vol"));
+        assert_eq!(ev[0].kind, UiEventKind::Changed(0.0));
+        assert_eq!(ev[1].kind, UiEventKind::Changed(10.0));
+        let ev = ui.update(&[key(KeyCode::Left)]);
+        assert_eq!(ev[0].kind, UiEventKind::Changed(9.0));
+        assert_eq!(ui.focused(), Some("vol"));
+        ui.update(&[key(KeyCode::BackTab)]);
+        assert_eq!(ui.focused(), Some("snd"));
+    }
+}
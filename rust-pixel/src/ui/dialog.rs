@@ -0,0 +1,261 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Dialog is a modal message box with a row of buttons
+//!
+//! It is drawn centered on a layer of its own above all game layers, the
+//! panel dims everything below it. While open, update takes all input
+//! events of the frame, so the game sees none: Left, Right, Tab and
+//! BackTab move between buttons, Enter or Space chooses, Esc cancels and
+//! a click chooses the button under the mouse. The choice comes back from
+//! update, is emitted as DIALOG_EVENT and is passed to the on_close
+//! callback. It works the same in terminal and graphics mode.
+
+use crate::event::{event_emit_data, Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
+use crate::render::{
+    panel::Panel,
+    sprite::{BorderType, Borders, Sprite},
+    style::Theme,
+};
+use crate::ui::{draw_text, draw_text_fill, widget_style};
+use crate::util::Rect;
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+
+/// emitted with a DialogResult as data when a dialog closes
+pub const DIALOG_EVENT: &str = "Dialog.Closed";
+
+// layer drawn above all others but the debug overlay
+const DIALOG_LAYER: &str = "pixel_dialog";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogResult {
+    pub id: String,
+    /// index of the chosen button, None if cancelled with Esc
+    pub choice: Option<usize>,
+}
+
+pub struct Dialog {
+    /// id in DialogResult, the message by default
+    pub id: String,
+    pub message: String,
+    pub buttons: Vec<String>,
+    pub selected: usize,
+    pub open: bool,
+    on_close: Option<Box<dyn FnMut(Option<usize>)>>,
+    // screen area of the last draw, to match mouse positions
+    area: Rect,
+}
+
+impl Default for Dialog {
+    fn default() -> Self {
+        Self::confirm("", &[])
+    }
+}
+
+impl Dialog {
+    /// an open dialog, message may have several lines
+    pub fn confirm(message: &str, buttons: &[&str]) -> Self {
+        Self {
+            id: message.to_string(),
+            message: message.to_string(),
+            buttons: buttons.iter().map(|s| s.to_string()).collect(),
+            selected: 0,
+            open: !buttons.is_empty(),
+            on_close: None,
+            area: Rect::default(),
+        }
+    }
+
+    /// a message with an OK button
+    pub fn message(message: &str) -> Self {
+        Self::confirm(message, &["OK"])
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = id.to_string();
+        self
+    }
+
+    /// called with the choice when the dialog closes
+    pub fn on_close<F: FnMut(Option<usize>) + 'static>(mut self, f: F) -> Self {
+        self.on_close = Some(Box::new(f));
+        self
+    }
+
+    pub fn close(&mut self, choice: Option<usize>) -> DialogResult {
+        self.open = false;
+        let r = DialogResult {
+            id: self.id.clone(),
+            choice,
+        };
+        event_emit_data(DIALOG_EVENT, &r);
+        if let Some(f) = self.on_close.as_mut() {
+            f(choice);
+        }
+        r
+    }
+
+    fn lines(&self) -> Vec<&str> {
+        self.message.lines().collect()
+    }
+
+    fn buttons_width(&self) -> u16 {
+        let w: usize = self.buttons.iter().map(|b| b.width() + 4).sum();
+        (w + self.buttons.len().saturating_sub(1) * 2) as u16
+    }
+
+    // size with border, a blank row and the button row
+    fn size(&self) -> (u16, u16) {
+        let text = self.lines().iter().map(|l| l.width()).max().unwrap_or(0) as u16;
+        (
+            text.max(self.buttons_width()) + 4,
+            self.lines().len() as u16 + 4,
+        )
+    }
+
+    // button areas relative to the dialog
+    fn button_areas(&self, width: u16, height: u16) -> Vec<Rect> {
+        let mut x = width.saturating_sub(self.buttons_width()) / 2;
+        self.buttons
+            .iter()
+            .map(|b| {
+                let w = b.width() as u16 + 4;
+                let r = Rect::new(x, height.saturating_sub(2), w, 1);
+                x += w + 2;
+                r
+            })
+            .collect()
+    }
+
+    /// takes all events while open, returns the result when the dialog closes
+    pub fn update(&mut self, events: &mut Vec<Event>) -> Option<DialogResult> {
+        if !self.open {
+            return None;
+        }
+        let n = self.buttons.len();
+        let mut choice = None;
+        for e in events.drain(..) {
+            match e {
+                Event::Key(k) if k.kind != KeyEventKind::Release => match k.code {
+                    KeyCode::Left | KeyCode::BackTab => self.selected = (self.selected + n - 1) % n,
+                    KeyCode::Right | KeyCode::Tab => self.selected = (self.selected + 1) % n,
+                    KeyCode::Enter | KeyCode::Char(' ') => choice = Some(Some(self.selected)),
+                    KeyCode::Esc => choice = Some(None),
+                    _ => {}
+                },
+                Event::Mouse(m) if m.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    let a = self.area;
+                    let (x, y) = (m.column.wrapping_sub(a.x), m.row.wrapping_sub(a.y));
+                    let hit = self
+                        .button_areas(a.width, a.height)
+                        .iter()
+                        .position(|b| x >= b.left() && x < b.right() && y == b.y);
+                    if let Some(i) = hit {
+                        self.selected = i;
+                        choice = Some(Some(i));
+                    }
+                }
+                _ => {}
+            }
+            if choice.is_some() {
+                break;
+            }
+        }
+        events.clear();
+        choice.map(|c| self.close(c))
+    }
+
+    /// draws the dialog centered on its layer, or hides the layer if closed
+    pub fn draw(&mut self, panel: &mut Panel, theme: &Theme) {
+        if !panel.layer_tag_index.contains_key(DIALOG_LAYER) {
+            panel.add_layer(DIALOG_LAYER);
+            panel.set_layer_weight(DIALOG_LAYER, i32::MAX - 1);
+            panel.set_layer_modal(DIALOG_LAYER, true);
+            panel.add_layer_sprite(Sprite::new(0, 0, 1, 1), DIALOG_LAYER, "DIALOG");
+        }
+        if !self.open {
+            panel.hide_layer(DIALOG_LAYER);
+            return;
+        }
+        panel.show_layer(DIALOG_LAYER);
+
+        let screen = panel.buffers[panel.current].area;
+        let (w, h) = self.size();
+        let (w, h) = (w.min(screen.width), h.min(screen.height));
+        self.area = Rect::new(
+            screen.x + (screen.width - w) / 2,
+            screen.y + (screen.height - h) / 2,
+            w,
+            h,
+        );
+        let mut sp = Sprite::new(self.area.x, self.area.y, w, h);
+        let st = widget_style(theme, false);
+        for y in 0..h {
+            draw_text_fill(&mut sp.content, 0, y, "", w, st);
+        }
+        sp.set_border(
+            Borders::ALL,
+            BorderType::Rounded,
+            theme.style("ui.border", "ui.bg"),
+        );
+        for (i, l) in self.lines().iter().enumerate() {
+            let x = w.saturating_sub(l.width() as u16) / 2;
+            draw_text(
+                &mut sp.content,
+                x,
+                1 + i as u16,
+                l,
+                w.saturating_sub(x + 1),
+                st,
+            );
+        }
+        for (i, (b, r)) in self.buttons.iter().zip(self.button_areas(w, h)).enumerate() {
+            let s = format!("[ {} ]", b);
+            let bst = widget_style(theme, i == self.selected);
+            draw_text(&mut sp.content, r.x, r.y, &s, r.width, bst);
+        }
+        *panel.get_layer_sprite(DIALOG_LAYER, "DIALOG") = sp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::tests::{key, mouse};
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn dialog_confirm() {
+        let got = Rc::new(Cell::new(None));
+        let g = got.clone();
+        let mut d = Dialog::confirm("Quit?", &["Yes", "No"]).on_close(move |c| g.set(c));
+        let mut panel = Panel::new();
+        d.draw(&mut panel, &Theme::dark());
+        assert!(!panel.is_layer_hidden(DIALOG_LAYER));
+        // 180x80 screen, 19x5 dialog
+        let sp = panel.get_layer_sprite(DIALOG_LAYER, "DIALOG");
+        assert_eq!(sp.content.area, Rect::new(80, 37, 19, 5));
+        assert_eq!(sp.content.get(87, 38).symbol, "Q");
+
+        let mut ev = vec![key(KeyCode::Right), key(KeyCode::Char('q'))];
+        assert_eq!(d.update(&mut ev), None);
+        assert!(ev.is_empty());
+        assert_eq!(d.selected, 1);
+
+        // click on Yes
+        let mut ev = vec![mouse(MouseEventKind::Down(MouseButton::Left), 83, 40)];
+        let r = d.update(&mut ev).unwrap();
+        assert_eq!(r.choice, Some(0));
+        assert_eq!(got.get(), Some(0));
+        assert!(!d.open);
+        let mut ev = vec![key(KeyCode::Esc)];
+        assert_eq!(d.update(&mut ev), None);
+        assert_eq!(ev.len(), 1);
+        d.draw(&mut panel, &Theme::dark());
+        assert!(panel.is_layer_hidden(DIALOG_LAYER));
+
+        let mut m = Dialog::message("Saved");
+        assert_eq!(m.update(&mut vec![key(KeyCode::Esc)]).unwrap().choice, None);
+    }
+}
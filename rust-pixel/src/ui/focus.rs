@@ -0,0 +1,291 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! FocusRing tracks which element has the keyboard focus
+//!
+//! Elements are ids with an area in screen cells, sprites by their tag or
+//! anything else the game names. Tab and BackTab walk them in the order
+//! added, arrows move to the nearest element in that direction. Elements
+//! with an empty area, e.g. hidden sprites, are skipped.
+//!
+//! ctx.focus is the ring of the game: Panel::draw keeps the areas of
+//! sprite tags in it up to date and highlights the focused sprite.
+//! dispatch gives key events to the focused element first, keys it does
+//! not use move the focus, the rest is returned for the game.
+//! A Ui has a ring of its own for its widgets.
+
+use crate::event::{Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEventKind};
+use crate::render::style::Style;
+use crate::util::Rect;
+
+#[derive(Debug, Clone)]
+pub struct FocusRing {
+    items: Vec<(String, Rect)>,
+    current: Option<usize>,
+    /// highlight of the focused sprite, None uses ui.selected of the theme as bg
+    pub style: Option<Style>,
+    /// arrows not used by the focused element move the focus
+    pub arrows: bool,
+}
+
+impl Default for FocusRing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FocusRing {
+    pub fn new() -> Self {
+        Self {
+            items: vec![],
+            current: None,
+            style: None,
+            arrows: true,
+        }
+    }
+
+    /// adds an element or updates its area
+    pub fn add(&mut self, id: &str, area: Rect) {
+        match self.items.iter().position(|(i, _)| i == id) {
+            Some(i) => self.items[i].1 = area,
+            None => self.items.push((id.to_string(), area)),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if let Some(i) = self.items.iter().position(|(n, _)| n == id) {
+            self.items.remove(i);
+            self.current = match self.current {
+                Some(c) if c == i => None,
+                Some(c) if c > i => Some(c - 1),
+                c => c,
+            };
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.current = None;
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.items.iter().any(|(i, _)| i == id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().map(|(i, _)| i.as_str())
+    }
+
+    pub fn area(&self, id: &str) -> Option<Rect> {
+        self.items.iter().find(|(i, _)| i == id).map(|(_, a)| *a)
+    }
+
+    pub fn focused(&self) -> Option<&str> {
+        self.current.map(|i| self.items[i].0.as_str())
+    }
+
+    pub fn is_focused(&self, id: &str) -> bool {
+        self.focused() == Some(id)
+    }
+
+    /// false if there is no such element
+    pub fn set_focus(&mut self, id: &str) -> bool {
+        match self.items.iter().position(|(i, _)| i == id) {
+            Some(i) => {
+                self.current = Some(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear_focus(&mut self) {
+        self.current = None;
+    }
+
+    fn usable(&self, i: usize) -> bool {
+        let a = self.items[i].1;
+        a.width > 0 && a.height > 0
+    }
+
+    /// focuses the next element in order, backwards if back
+    pub fn next(&mut self, back: bool) {
+        let n = self.items.len();
+        if n == 0 {
+            return;
+        }
+        let start = match (self.current, back) {
+            (Some(c), _) => c,
+            (None, false) => n - 1,
+            (None, true) => 0,
+        };
+        for k in 1..=n {
+            let i = if back {
+                (start + n * 2 - k) % n
+            } else {
+                (start + k) % n
+            };
+            if self.usable(i) {
+                self.current = Some(i);
+                return;
+            }
+        }
+    }
+
+    /// focuses the nearest element in direction (dx, dy),
+    /// false if there is none
+    pub fn move_dir(&mut self, dx: i32, dy: i32) -> bool {
+        let Some(c) = self.current else {
+            self.next(false);
+            return self.current.is_some();
+        };
+        // centers doubled, to stay in integers
+        let center = |a: Rect| {
+            (
+                a.x as i32 * 2 + a.width as i32,
+                a.y as i32 * 2 + a.height as i32,
+            )
+        };
+        let (cx, cy) = center(self.items[c].1);
+        let mut best: Option<(i32, usize)> = None;
+        for i in 0..self.items.len() {
+            if i == c || !self.usable(i) {
+                continue;
+            }
+            let (ox, oy) = center(self.items[i].1);
+            let along = (ox - cx) * dx + (oy - cy) * dy;
+            if along <= 0 {
+                continue;
+            }
+            let across = (ox - cx).abs() * dy.abs() + (oy - cy).abs() * dx.abs();
+            let score = along + across * 2;
+            match best {
+                Some(b) if b.0 <= score => {}
+                _ => best = Some((score, i)),
+            }
+        }
+        match best {
+            Some((_, i)) => {
+                self.current = Some(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// moves the focus by a navigation key, true if the key was one
+    pub fn navigate(&mut self, k: &KeyEvent) -> bool {
+        match k.code {
+            KeyCode::Tab => self.next(false),
+            KeyCode::BackTab => self.next(true),
+            KeyCode::Up if self.arrows => return self.move_dir(0, -1),
+            KeyCode::Down if self.arrows => return self.move_dir(0, 1),
+            KeyCode::Left if self.arrows => return self.move_dir(-1, 0),
+            KeyCode::Right if self.arrows => return self.move_dir(1, 0),
+            _ => return false,
+        }
+        true
+    }
+
+    /// element under a screen cell, the last added wins
+    pub fn item_at(&self, x: u16, y: u16) -> Option<&str> {
+        self.items.iter().rev().find_map(|(id, a)| {
+            if x >= a.left() && x < a.right() && y >= a.top() && y < a.bottom() {
+                Some(id.as_str())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// routes the events of a frame: key presses go to on_key with the
+    /// focused id first, keys it returns false for may move the focus,
+    /// a left click focuses the element under it.
+    /// Returns the events left for the game
+    pub fn dispatch<F>(&mut self, events: &[Event], mut on_key: F) -> Vec<Event>
+    where
+        F: FnMut(&str, &KeyEvent) -> bool,
+    {
+        let mut rest = vec![];
+        for e in events {
+            match e {
+                Event::Key(k) if k.kind != KeyEventKind::Release => {
+                    let used = match self.current {
+                        Some(c) => on_key(&self.items[c].0.clone(), k),
+                        None => false,
+                    };
+                    if !used && !self.navigate(k) {
+                        rest.push(e.clone());
+                    }
+                }
+                Event::Mouse(m) if m.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    if let Some(id) = self.item_at(m.column, m.row).map(|s| s.to_string()) {
+                        self.set_focus(&id);
+                    }
+                    rest.push(e.clone());
+                }
+                _ => rest.push(e.clone()),
+            }
+        }
+        rest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::KeyModifiers;
+    use crate::ui::tests::{key, mouse};
+
+    #[test]
+    fn focus_ring_navigation() {
+        // a b
+        // c
+        let mut fr = FocusRing::new();
+        fr.add("a", Rect::new(0, 0, 4, 1));
+        fr.add("b", Rect::new(10, 0, 4, 1));
+        fr.add("c", Rect::new(0, 3, 4, 1));
+        fr.add("hidden", Rect::new(0, 0, 0, 0));
+
+        fr.next(false);
+        assert_eq!(fr.focused(), Some("a"));
+        assert!(fr.move_dir(0, 1));
+        assert_eq!(fr.focused(), Some("c"));
+        assert!(!fr.move_dir(0, 1));
+        assert!(fr.move_dir(0, -1));
+        assert_eq!(fr.focused(), Some("a"));
+        fr.set_focus("a");
+        fr.next(true);
+        assert_eq!(fr.focused(), Some("c"));
+
+        // b uses Enter, a leaves Right to move the focus
+        fr.set_focus("a");
+        let mut used = vec![];
+        let rest = fr.dispatch(
+            &[
+                key(KeyCode::Right),
+                key(KeyCode::Enter),
+                key(KeyCode::Char('q')),
+                mouse(MouseEventKind::Down(MouseButton::Left), 1, 3),
+            ],
+            |id, k| {
+                let u = id == "b" && k.code == KeyCode::Enter;
+                if u {
+                    used.push(id.to_string());
+                }
+                u
+            },
+        );
+        assert_eq!(used, vec!["b"]);
+        assert_eq!(rest.len(), 2);
+        assert_eq!(
+            rest[0],
+            Event::Key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE))
+        );
+        assert_eq!(fr.focused(), Some("c"));
+        fr.remove("a");
+        assert_eq!(fr.focused(), Some("c"));
+        fr.remove("c");
+        assert_eq!(fr.focused(), None);
+    }
+}
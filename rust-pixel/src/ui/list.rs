@@ -0,0 +1,294 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! List of selectable items and ScrollView of text lines
+//!
+//! Both scroll with the wheel and show a scroll bar in their last column
+//! when the content is higher than the area. A List moves the selection
+//! with arrows, Home, End, PageUp and PageDown and activates it with
+//! Enter or a click on the selected item. ScrollView lines may use style
+//! markup, e.g. "[red]HP[/red] 10".
+
+use crate::event::KeyCode;
+use crate::render::{
+    buffer::Buffer,
+    style::{parse_markup, Theme},
+};
+use crate::ui::{
+    draw_scrollbar, draw_text, draw_text_fill, widget_style, UiEventKind, UiInput, UiReply,
+    UiWidget,
+};
+use crate::util::Rect;
+use std::any::Any;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone)]
+pub struct List {
+    pub area: Rect,
+    pub items: Vec<String>,
+    pub selected: usize,
+    /// first visible item
+    pub offset: usize,
+}
+
+impl List {
+    pub fn new(area: Rect, items: &[&str]) -> Self {
+        Self {
+            area,
+            items: items.iter().map(|s| s.to_string()).collect(),
+            selected: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<&str> {
+        self.items.get(self.selected).map(|s| s.as_str())
+    }
+
+    fn view(&self) -> usize {
+        self.area.height as usize
+    }
+
+    fn select(&mut self, i: usize) -> UiReply {
+        if self.items.is_empty() {
+            return UiReply::Ignored;
+        }
+        let i = i.min(self.items.len() - 1);
+        if i < self.offset {
+            self.offset = i;
+        } else if i >= self.offset + self.view() {
+            self.offset = i + 1 - self.view();
+        }
+        if i == self.selected {
+            return UiReply::Handled;
+        }
+        self.selected = i;
+        UiReply::Event(UiEventKind::Selected(i))
+    }
+}
+
+impl UiWidget for List {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+        self.select(self.selected);
+    }
+
+    fn handle(&mut self, input: UiInput) -> UiReply {
+        let (s, page) = (self.selected, self.view().max(1));
+        match input {
+            UiInput::Key(k) => match k.code {
+                // arrows off the ends move the focus
+                KeyCode::Up if s > 0 => self.select(s - 1),
+                KeyCode::Down if s + 1 < self.items.len() => self.select(s + 1),
+                KeyCode::Home => self.select(0),
+                KeyCode::End => self.select(usize::MAX),
+                KeyCode::PageUp => self.select(s.saturating_sub(page)),
+                KeyCode::PageDown => self.select(s + page),
+                KeyCode::Enter if !self.items.is_empty() => {
+                    UiReply::Event(UiEventKind::Activated(s))
+                }
+                _ => UiReply::Ignored,
+            },
+            UiInput::Click(_, y) => {
+                let i = self.offset + y as usize;
+                if i >= self.items.len() {
+                    UiReply::Handled
+                } else if i == s {
+                    UiReply::Event(UiEventKind::Activated(i))
+                } else {
+                    self.select(i)
+                }
+            }
+            UiInput::Drag(..) => UiReply::Handled,
+            UiInput::Wheel(d) => {
+                let max = self.items.len().saturating_sub(self.view());
+                self.offset = (self.offset as i64 + d as i64).clamp(0, max as i64) as usize;
+                UiReply::Handled
+            }
+        }
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let a = self.area;
+        let bar = self.items.len() > self.view();
+        let w = if bar {
+            a.width.saturating_sub(1)
+        } else {
+            a.width
+        };
+        for row in 0..a.height {
+            let i = self.offset + row as usize;
+            let st = widget_style(theme, focused && i == self.selected);
+            let st = if !focused && i == self.selected {
+                st.fg(theme.get("ui.accent"))
+            } else {
+                st
+            };
+            let s = self.items.get(i).map(|s| s.as_str()).unwrap_or("");
+            draw_text_fill(buf, a.x, a.y + row, s, w, st);
+        }
+        if bar {
+            draw_scrollbar(
+                buf,
+                a.right() - 1,
+                a.y,
+                self.view(),
+                self.offset,
+                self.items.len(),
+                widget_style(theme, false),
+            );
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// scrollable text lines
+#[derive(Debug, Clone)]
+pub struct ScrollView {
+    pub area: Rect,
+    pub lines: Vec<String>,
+    /// first visible line
+    pub offset: usize,
+}
+
+impl ScrollView {
+    pub fn new(area: Rect) -> Self {
+        Self {
+            area,
+            lines: vec![],
+            offset: 0,
+        }
+    }
+
+    pub fn set_lines(&mut self, lines: &[&str]) {
+        self.lines = lines.iter().map(|s| s.to_string()).collect();
+        self.offset = self.offset.min(self.max_offset());
+    }
+
+    pub fn max_offset(&self) -> usize {
+        self.lines.len().saturating_sub(self.area.height as usize)
+    }
+
+    /// scrolls by d lines, negative is up
+    pub fn scroll(&mut self, d: i64) -> UiReply {
+        let o = (self.offset as i64 + d).clamp(0, self.max_offset() as i64) as usize;
+        if o == self.offset {
+            return UiReply::Handled;
+        }
+        self.offset = o;
+        UiReply::Event(UiEventKind::Scrolled(o))
+    }
+}
+
+impl UiWidget for ScrollView {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+        self.offset = self.offset.min(self.max_offset());
+    }
+
+    fn handle(&mut self, input: UiInput) -> UiReply {
+        let page = self.area.height.max(1) as i64;
+        match input {
+            UiInput::Key(k) => match k.code {
+                KeyCode::Up if self.offset > 0 => self.scroll(-1),
+                KeyCode::Down if self.offset < self.max_offset() => self.scroll(1),
+                KeyCode::PageUp => self.scroll(-page),
+                KeyCode::PageDown => self.scroll(page),
+                KeyCode::Home => self.scroll(-(self.offset as i64)),
+                KeyCode::End => self.scroll(self.max_offset() as i64),
+                _ => UiReply::Ignored,
+            },
+            UiInput::Wheel(d) => self.scroll(d as i64),
+            _ => UiReply::Handled,
+        }
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let a = self.area;
+        let st = widget_style(theme, false);
+        let bar = self.lines.len() > a.height as usize;
+        let w = if bar {
+            a.width.saturating_sub(1)
+        } else {
+            a.width
+        };
+        for row in 0..a.height {
+            draw_text_fill(buf, a.x, a.y + row, "", w, st);
+            if let Some(l) = self.lines.get(self.offset + row as usize) {
+                let mut x = 0;
+                for (text, ts) in parse_markup(l, st) {
+                    draw_text(buf, a.x + x, a.y + row, &text, w.saturating_sub(x), ts);
+                    x += text.width() as u16;
+                }
+            }
+        }
+        if bar {
+            draw_scrollbar(
+                buf,
+                a.right() - 1,
+                a.y,
+                a.height as usize,
+                self.offset,
+                self.lines.len(),
+                widget_style(theme, focused),
+            );
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{tests::key, Ui};
+
+    #[test]
+    fn list_and_scroll_view() {
+        let mut ui = Ui::new();
+        let items = ["a", "b", "c", "d", "e", "f"];
+        ui.add("list", List::new(Rect::new(0, 0, 8, 3), &items));
+        let mut sv = ScrollView::new(Rect::new(0, 4, 8, 2));
+        sv.set_lines(&["one", "[red]two[/red]", "three"]);
+        ui.add("log", sv);
+        ui.set_focus("list");
+
+        let ev = ui.update(&[key(KeyCode::End)]);
+        assert_eq!(ev[0].kind, UiEventKind::Selected(5));
+        let l = ui.get::<List>("list").unwrap();
+        assert_eq!((l.offset, l.selected_item()), (3, Some("f")));
+        let ev = ui.update(&[key(KeyCode::PageUp), key(KeyCode::Enter)]);
+        assert_eq!(ev[1].kind, UiEventKind::Activated(2));
+        // down at the last item moves on to the scroll view
+        ui.update(&[key(KeyCode::End), key(KeyCode::Down)]);
+        assert_eq!(ui.focused(), Some("log"));
+        let ev = ui.update(&[key(KeyCode::PageDown)]);
+        assert_eq!(ev[0].kind, UiEventKind::Scrolled(1));
+
+        let mut sp = crate::render::sprite::Sprite::new(0, 0, 8, 6);
+        ui.draw(&mut sp, &Theme::dark());
+        assert_eq!(sp.content.get(0, 4).symbol, "t");
+        assert_eq!(sp.content.get(7, 0).symbol, "|");
+    }
+}
@@ -0,0 +1,219 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! LogPanel shows the newest lines of a message log at its bottom
+//!
+//! Lines may use style markup, e.g. "[red]The orc hits you[/red]". It keeps
+//! at most capacity lines, older ones are dropped. PageUp, PageDown, Up,
+//! Down and the wheel scroll back, End follows new lines again. While
+//! scrolled back, new lines do not move the view. For roguelike message
+//! logs and debug consoles.
+
+use crate::event::KeyCode;
+use crate::render::{
+    buffer::Buffer,
+    style::{parse_markup, Theme},
+};
+use crate::ui::{
+    draw_scrollbar, draw_text, draw_text_fill, widget_style, UiEventKind, UiInput, UiReply,
+    UiWidget,
+};
+use crate::util::{profile_clock, Rect};
+use std::any::Any;
+use std::collections::VecDeque;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone)]
+pub struct LogPanel {
+    pub area: Rect,
+    /// lines kept at most
+    pub capacity: usize,
+    /// prefixes lines with the time since the log was made, as mm:ss
+    pub timestamps: bool,
+    // seconds of profile_clock and text
+    lines: VecDeque<(f64, String)>,
+    // lines scrolled back from the newest
+    scroll: usize,
+    start: f64,
+}
+
+impl LogPanel {
+    pub fn new(area: Rect, capacity: usize) -> Self {
+        Self {
+            area,
+            capacity: capacity.max(1),
+            timestamps: false,
+            lines: VecDeque::new(),
+            scroll: 0,
+            start: profile_clock(),
+        }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        } else if self.scroll > 0 {
+            // keeps the view on the same lines
+            self.scroll += 1;
+        }
+        self.lines.push_back((profile_clock(), line.to_string()));
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// lines from the oldest, without timestamps
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(|(_, l)| l.as_str())
+    }
+
+    /// lines scrolled back from the newest, 0 follows new lines
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.area.height as usize)
+    }
+
+    // index of the first visible line
+    fn top(&self) -> usize {
+        self.max_scroll() - self.scroll
+    }
+
+    /// scrolls back by d lines, negative is towards the newest
+    pub fn scroll_by(&mut self, d: i64) -> UiReply {
+        let s = (self.scroll as i64 + d).clamp(0, self.max_scroll() as i64) as usize;
+        if s == self.scroll {
+            return UiReply::Handled;
+        }
+        self.scroll = s;
+        UiReply::Event(UiEventKind::Scrolled(self.top()))
+    }
+
+    fn stamp(&self, t: f64) -> String {
+        let s = (t - self.start).max(0.0) as u64;
+        format!("[{:02}:{:02}] ", s / 60, s % 60)
+    }
+}
+
+impl UiWidget for LogPanel {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    fn handle(&mut self, input: UiInput) -> UiReply {
+        let page = self.area.height.max(1) as i64;
+        match input {
+            UiInput::Key(k) => match k.code {
+                KeyCode::Up if self.scroll < self.max_scroll() => self.scroll_by(1),
+                KeyCode::Down if self.scroll > 0 => self.scroll_by(-1),
+                KeyCode::PageUp => self.scroll_by(page),
+                KeyCode::PageDown => self.scroll_by(-page),
+                KeyCode::Home => self.scroll_by(self.max_scroll() as i64),
+                KeyCode::End => self.scroll_by(-(self.scroll as i64)),
+                _ => UiReply::Ignored,
+            },
+            UiInput::Wheel(d) => self.scroll_by(-d as i64),
+            _ => UiReply::Handled,
+        }
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let a = self.area;
+        let st = widget_style(theme, false);
+        let bar = self.lines.len() > a.height as usize;
+        let w = if bar {
+            a.width.saturating_sub(1)
+        } else {
+            a.width
+        };
+        let top = self.top();
+        for row in 0..a.height {
+            draw_text_fill(buf, a.x, a.y + row, "", w, st);
+            let Some((t, l)) = self.lines.get(top + row as usize) else {
+                continue;
+            };
+            let mut x = 0;
+            if self.timestamps {
+                let s = self.stamp(*t);
+                draw_text(buf, a.x, a.y + row, &s, w, st.fg(theme.get("ui.disabled")));
+                x = s.width() as u16;
+            }
+            for (text, ts) in parse_markup(l, st) {
+                draw_text(buf, a.x + x, a.y + row, &text, w.saturating_sub(x), ts);
+                x += text.width() as u16;
+            }
+        }
+        if bar {
+            draw_scrollbar(
+                buf,
+                a.right() - 1,
+                a.y,
+                a.height as usize,
+                top,
+                self.lines.len(),
+                widget_style(theme, focused),
+            );
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{tests::key, Ui};
+
+    #[test]
+    fn log_panel_scrollback() {
+        let mut log = LogPanel::new(Rect::new(0, 0, 20, 3), 5);
+        for i in 0..4 {
+            log.push(&format!("line {}", i));
+        }
+        let mut ui = Ui::new();
+        ui.add("log", log);
+        ui.set_focus("log");
+
+        let ev = ui.update(&[key(KeyCode::PageUp)]);
+        assert_eq!(ev[0].kind, UiEventKind::Scrolled(0));
+        let log = ui.get_mut::<LogPanel>("log").unwrap();
+        // scrolled back, the view stays on line 0 while lines come in
+        log.push("line 4");
+        assert_eq!(log.scroll(), 2);
+        log.push("[red]line 5[/red]");
+        assert_eq!(log.len(), 5);
+        assert_eq!(log.lines().next(), Some("line 1"));
+        assert_eq!(log.scroll(), 2);
+        ui.update(&[key(KeyCode::End)]);
+        assert_eq!(ui.get::<LogPanel>("log").unwrap().scroll(), 0);
+
+        let mut sp = crate::render::sprite::Sprite::new(0, 0, 20, 3);
+        ui.draw(&mut sp, &Theme::dark());
+        assert_eq!(sp.content.get(5, 2).symbol, "5");
+        assert_eq!(sp.content.get(19, 2).symbol, "#");
+    }
+}
@@ -0,0 +1,359 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Menu is a declarative tree of items, separators and submenus
+//!
+//! Only the open level is drawn, one item per row with the title of the
+//! submenu path above it. Up and Down, the dpad or the left stick move the
+//! cursor past separators and disabled items. Enter, Space, Right or the
+//! A button open a submenu or choose an item, Left, Esc, Backspace or the
+//! B button go back a level. A click chooses the item under the mouse.
+//! The id of a chosen item comes back from update and is emitted as
+//! MENU_EVENT, so title screens need no cursor logic of their own.
+
+use crate::event::{
+    event_emit_data, Event, GamepadAxis, GamepadButton, GamepadEventKind, KeyCode, KeyEventKind,
+    MouseButton, MouseEventKind,
+};
+use crate::render::{sprite::Sprite, style::Theme};
+use crate::ui::{draw_text_fill, widget_style};
+
+/// emitted with the id of the chosen item as data
+pub const MENU_EVENT: &str = "Menu.Selected";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuItem {
+    Item {
+        id: String,
+        label: String,
+        enabled: bool,
+    },
+    Separator,
+    Submenu(Menu),
+}
+
+impl MenuItem {
+    fn selectable(&self) -> bool {
+        match self {
+            MenuItem::Item { enabled, .. } => *enabled,
+            MenuItem::Submenu(m) => m.enabled,
+            MenuItem::Separator => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Menu {
+    pub title: String,
+    pub items: Vec<MenuItem>,
+    /// a disabled submenu can not be opened
+    pub enabled: bool,
+    /// stick values below this count as centered
+    pub dead_zone: i16,
+    // cursor of each open level, the first is this menu
+    path: Vec<usize>,
+    // direction the stick is held in, moves once per push
+    stick: i8,
+    // sprite position of the last draw, to match mouse positions
+    origin: (u16, u16),
+}
+
+impl Menu {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            items: vec![],
+            enabled: true,
+            dead_zone: 8000,
+            path: vec![0],
+            stick: 0,
+            origin: (0, 0),
+        }
+    }
+
+    pub fn item(mut self, id: &str, label: &str) -> Self {
+        self.items.push(MenuItem::Item {
+            id: id.to_string(),
+            label: label.to_string(),
+            enabled: true,
+        });
+        self.path[0] = self.first();
+        self
+    }
+
+    pub fn separator(mut self) -> Self {
+        self.items.push(MenuItem::Separator);
+        self
+    }
+
+    /// adds a submenu, shown with its title
+    pub fn submenu(mut self, m: Menu) -> Self {
+        self.items.push(MenuItem::Submenu(m));
+        self.path[0] = self.first();
+        self
+    }
+
+    /// enables or disables the item or submenu of id, in all levels
+    pub fn set_enabled(&mut self, id: &str, on: bool) {
+        for it in self.items.iter_mut() {
+            match it {
+                MenuItem::Item { id: i, enabled, .. } if i == id => *enabled = on,
+                MenuItem::Submenu(m) => {
+                    if m.title == id {
+                        m.enabled = on;
+                    }
+                    m.set_enabled(id, on);
+                }
+                _ => {}
+            }
+        }
+        self.fix_cursor();
+    }
+
+    fn first(&self) -> usize {
+        self.items.iter().position(|i| i.selectable()).unwrap_or(0)
+    }
+
+    // items of level d of the open path
+    fn level(&self, d: usize) -> &Menu {
+        let mut m = self;
+        for &i in &self.path[..d] {
+            if let Some(MenuItem::Submenu(s)) = m.items.get(i) {
+                m = s;
+            }
+        }
+        m
+    }
+
+    /// the open level
+    pub fn current(&self) -> &Menu {
+        self.level(self.path.len() - 1)
+    }
+
+    /// open levels, 1 at the top
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// index of the cursor in the open level
+    pub fn cursor(&self) -> usize {
+        self.path[self.path.len() - 1]
+    }
+
+    // moves off a disabled item
+    fn fix_cursor(&mut self) {
+        let items = &self.current().items;
+        if !items.get(self.cursor()).is_some_and(|i| i.selectable()) {
+            let c = self.current().first();
+            *self.path.last_mut().unwrap() = c;
+        }
+    }
+
+    /// moves the cursor by d selectable items, wrapping around
+    pub fn move_cursor(&mut self, d: i32) {
+        let items = &self.current().items;
+        let n = items.len() as i32;
+        let mut c = self.cursor() as i32;
+        for _ in 0..n {
+            c = (c + d.signum()).rem_euclid(n.max(1));
+            if items[c as usize].selectable() {
+                *self.path.last_mut().unwrap() = c as usize;
+                return;
+            }
+        }
+    }
+
+    /// opens the submenu under the cursor, or returns the id of the item
+    pub fn activate(&mut self) -> Option<String> {
+        match self.current().items.get(self.cursor()) {
+            Some(MenuItem::Item {
+                id, enabled: true, ..
+            }) => {
+                let id = id.clone();
+                event_emit_data(MENU_EVENT, &id);
+                Some(id)
+            }
+            Some(MenuItem::Submenu(m)) if m.enabled => {
+                let c = m.first();
+                self.path.push(c);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// closes the open submenu, false at the top level
+    pub fn back(&mut self) -> bool {
+        if self.path.len() > 1 {
+            self.path.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// back to the top level and its first item
+    pub fn reset(&mut self) {
+        self.path = vec![self.first()];
+    }
+
+    /// processes input events of this frame, returns the id of a chosen item
+    pub fn update(&mut self, events: &[Event]) -> Option<String> {
+        let mut chosen = None;
+        for e in events {
+            match e {
+                Event::Key(k) if k.kind != KeyEventKind::Release => match k.code {
+                    KeyCode::Up => self.move_cursor(-1),
+                    KeyCode::Down => self.move_cursor(1),
+                    KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Right => {
+                        chosen = self.activate()
+                    }
+                    KeyCode::Left | KeyCode::Esc | KeyCode::Backspace => {
+                        self.back();
+                    }
+                    _ => {}
+                },
+                Event::Gamepad(g) => match g.kind {
+                    GamepadEventKind::ButtonDown(b) => match b {
+                        GamepadButton::DPadUp => self.move_cursor(-1),
+                        GamepadButton::DPadDown => self.move_cursor(1),
+                        GamepadButton::A | GamepadButton::Start | GamepadButton::DPadRight => {
+                            chosen = self.activate()
+                        }
+                        GamepadButton::B | GamepadButton::DPadLeft => {
+                            self.back();
+                        }
+                        _ => {}
+                    },
+                    GamepadEventKind::Axis(GamepadAxis::LeftY, v) => {
+                        let dir = if v > self.dead_zone {
+                            1
+                        } else if v < -self.dead_zone {
+                            -1
+                        } else {
+                            0
+                        };
+                        if dir != 0 && dir != self.stick {
+                            self.move_cursor(dir as i32);
+                        }
+                        self.stick = dir;
+                    }
+                    _ => {}
+                },
+                Event::Mouse(m) if m.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    // the title takes the first row
+                    let row = m.row.wrapping_sub(self.origin.1) as usize;
+                    let n = self.current().items.len();
+                    if m.column >= self.origin.0 && row >= 1 && row <= n {
+                        *self.path.last_mut().unwrap() = row - 1;
+                        chosen = self.activate();
+                    }
+                }
+                _ => {}
+            }
+            if chosen.is_some() {
+                break;
+            }
+        }
+        chosen
+    }
+
+    /// draws the title path and the open level into the sprite
+    pub fn draw(&mut self, sp: &mut Sprite, theme: &Theme) {
+        self.origin = (sp.content.area.x, sp.content.area.y);
+        let w = sp.content.area.width;
+        let buf = &mut sp.content;
+        let title: Vec<&str> = (0..self.path.len())
+            .map(|d| self.level(d).title.as_str())
+            .collect();
+        draw_text_fill(
+            buf,
+            0,
+            0,
+            &title.join(" > "),
+            w,
+            theme.style("ui.title", "ui.bg"),
+        );
+        let cur = self.cursor();
+        for (i, it) in self.current().items.iter().enumerate() {
+            let y = 1 + i as u16;
+            let st = widget_style(theme, i == cur);
+            match it {
+                MenuItem::Separator => {
+                    draw_text_fill(buf, 0, y, &"-".repeat(w as usize), w, st);
+                }
+                _ => {
+                    let (label, enabled, more) = match it {
+                        MenuItem::Item { label, enabled, .. } => (label, *enabled, ""),
+                        MenuItem::Submenu(m) => (&m.title, m.enabled, " >"),
+                        MenuItem::Separator => unreachable!(),
+                    };
+                    let mark = if i == cur { "> " } else { "  " };
+                    let st = if enabled {
+                        st
+                    } else {
+                        st.fg(theme.get("ui.disabled"))
+                    };
+                    draw_text_fill(buf, 0, y, &format!("{}{}{}", mark, label, more), w, st);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::GamepadEvent;
+    use crate::ui::tests::{key, mouse};
+
+    fn pad(kind: GamepadEventKind) -> Event {
+        Event::Gamepad(GamepadEvent { id: 0, kind })
+    }
+
+    #[test]
+    fn menu_navigation() {
+        let mut m = Menu::new("Main")
+            .item("start", "Start")
+            .item("load", "Continue")
+            .submenu(Menu::new("Options").separator().item("sound", "Sound"))
+            .separator()
+            .item("quit", "Quit");
+        m.set_enabled("load", false);
+
+        // skips the disabled item, wraps past the separator
+        m.update(&[key(KeyCode::Down)]);
+        assert_eq!(m.cursor(), 2);
+        m.update(&[key(KeyCode::Up), key(KeyCode::Up)]);
+        assert_eq!(m.cursor(), 4);
+
+        // stick moves once per push
+        m.update(&[
+            pad(GamepadEventKind::Axis(GamepadAxis::LeftY, 20000)),
+            pad(GamepadEventKind::Axis(GamepadAxis::LeftY, 30000)),
+        ]);
+        assert_eq!(m.cursor(), 0);
+        m.update(&[
+            pad(GamepadEventKind::Axis(GamepadAxis::LeftY, 0)),
+            pad(GamepadEventKind::Axis(GamepadAxis::LeftY, 20000)),
+        ]);
+        assert_eq!(m.cursor(), 2);
+        assert_eq!(
+            m.update(&[pad(GamepadEventKind::ButtonDown(GamepadButton::A))]),
+            None
+        );
+        assert_eq!((m.depth(), m.cursor()), (2, 1));
+
+        let mut sp = Sprite::new(0, 0, 20, 6);
+        m.draw(&mut sp, &Theme::dark());
+        assert_eq!(sp.content.get(5, 0).symbol, ">");
+        assert_eq!(sp.content.get(2, 2).symbol, "S");
+        let got = m.update(&[mouse(MouseEventKind::Down(MouseButton::Left), 3, 2)]);
+        assert_eq!(got.as_deref(), Some("sound"));
+
+        m.update(&[key(KeyCode::Esc), key(KeyCode::Esc)]);
+        assert_eq!(m.depth(), 1);
+        assert_eq!(m.update(&[key(KeyCode::Enter)]), None);
+        assert_eq!(m.depth(), 2);
+    }
+}
@@ -0,0 +1,232 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! ProgressBar fills its area from the left, Gauge from the bottom
+//!
+//! Values are 0.0..=1.0. Terminal mode draws the last cell with a partial
+//! block, eighth by eighth. Graphics mode draws solid quads and blends
+//! the color of the last one by its part, so the fill grows smoothly.
+//! The fill is ui.accent, or shifts with the value along a ColorScale.
+//! Neither takes the focus.
+
+use crate::render::{
+    buffer::Buffer,
+    cell::cellsym,
+    style::{BlendMode, Color, ColorScale, Style, Theme},
+};
+use crate::ui::{draw_text, UiInput, UiReply, UiWidget};
+use crate::util::Rect;
+use std::any::Any;
+use unicode_width::UnicodeWidthStr;
+
+// partial cells by eighths, left to right and bottom to top
+const LEFT_EIGHTHS: [&str; 8] = [" ", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+const LOWER_EIGHTHS: [&str; 8] = [" ", "▁", "▂", "▃", "▄", "▅", "▆", "▇"];
+const GRAPHICS: bool = cfg!(any(feature = "sdl", target_arch = "wasm32"));
+
+fn fill_color(theme: &Theme, scale: &Option<ColorScale>, value: f32) -> Color {
+    match scale {
+        Some(s) => s.at(value),
+        None => theme.get("ui.accent"),
+    }
+}
+
+// symbol and style of cell i of len cells filled to value
+fn fill_cell(
+    i: u16,
+    len: u16,
+    value: f32,
+    fill: Color,
+    bg: Color,
+    eighths: &[&'static str; 8],
+) -> (&'static str, Style) {
+    let filled = value.clamp(0.0, 1.0) * len as f32;
+    let part = (filled - i as f32).clamp(0.0, 1.0);
+    let full = if GRAPHICS { cellsym(160) } else { "█" };
+    if part >= 1.0 {
+        (full, Style::default().fg(fill).bg(bg))
+    } else if GRAPHICS {
+        let c = bg.blend(fill, BlendMode::Normal, part);
+        (full, Style::default().fg(c).bg(bg))
+    } else {
+        (
+            eighths[(part * 8.0) as usize],
+            Style::default().fg(fill).bg(bg),
+        )
+    }
+}
+
+/// a horizontal bar with an optional label in its middle
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    pub area: Rect,
+    pub value: f32,
+    pub label: Option<String>,
+    /// fill color by value, ui.accent if None
+    pub scale: Option<ColorScale>,
+}
+
+impl ProgressBar {
+    pub fn new(area: Rect) -> Self {
+        Self {
+            area,
+            value: 0.0,
+            label: None,
+            scale: None,
+        }
+    }
+}
+
+impl UiWidget for ProgressBar {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    fn handle(&mut self, _input: UiInput) -> UiReply {
+        UiReply::Ignored
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, _focused: bool) {
+        let a = self.area;
+        let fill = fill_color(theme, &self.scale, self.value);
+        let bg = theme.get("ui.bg");
+        for x in 0..a.width {
+            let (s, st) = fill_cell(x, a.width, self.value, fill, bg, &LEFT_EIGHTHS);
+            for y in 0..a.height {
+                draw_text(buf, a.x + x, a.y + y, s, 1, st);
+            }
+        }
+        if let Some(l) = &self.label {
+            let x0 = a.width.saturating_sub(l.width() as u16) / 2;
+            let filled = (self.value.clamp(0.0, 1.0) * a.width as f32) as u16;
+            for (i, c) in l.chars().enumerate() {
+                let x = x0 + i as u16;
+                // dark on the fill, light on the track
+                let st = if x < filled {
+                    Style::default().fg(bg).bg(fill)
+                } else {
+                    theme.style("ui.fg", "ui.bg")
+                };
+                draw_text(
+                    buf,
+                    a.x + x,
+                    a.y + a.height / 2,
+                    &c.to_string(),
+                    a.width.saturating_sub(x),
+                    st,
+                );
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// a vertical bar filled from the bottom
+#[derive(Debug, Clone)]
+pub struct Gauge {
+    pub area: Rect,
+    pub value: f32,
+    /// fill color by value, ui.accent if None
+    pub scale: Option<ColorScale>,
+}
+
+impl Gauge {
+    pub fn new(area: Rect) -> Self {
+        Self {
+            area,
+            value: 0.0,
+            scale: None,
+        }
+    }
+}
+
+impl UiWidget for Gauge {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    fn handle(&mut self, _input: UiInput) -> UiReply {
+        UiReply::Ignored
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, _focused: bool) {
+        let a = self.area;
+        let fill = fill_color(theme, &self.scale, self.value);
+        let bg = theme.get("ui.bg");
+        for i in 0..a.height {
+            let (s, st) = fill_cell(i, a.height, self.value, fill, bg, &LOWER_EIGHTHS);
+            for x in 0..a.width {
+                draw_text(buf, a.x + x, a.y + a.height - 1 - i, s, 1, st);
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::Ui;
+
+    #[test]
+    fn progress_fill_and_scale() {
+        let s = ColorScale::green_red();
+        assert_eq!(s.at(0.0), Color::Rgba(0, 200, 0, 255));
+        assert_eq!(s.at(2.0), Color::Rgba(220, 0, 0, 255));
+        assert_eq!(s.reversed().at(1.0), Color::Rgba(0, 200, 0, 255));
+
+        let mut bar = ProgressBar::new(Rect::new(0, 0, 4, 1));
+        bar.value = 0.5 + 1.0 / 32.0;
+        bar.scale = Some(s);
+        let mut g = Gauge::new(Rect::new(5, 0, 1, 2));
+        g.value = 0.25;
+        let mut ui = Ui::new();
+        ui.add("bar", bar);
+        ui.add("g", g);
+        let mut sp = crate::render::sprite::Sprite::new(0, 0, 8, 2);
+        ui.draw(&mut sp, &Theme::dark());
+        // two full cells and one eighth
+        if !GRAPHICS {
+            assert_eq!(sp.content.get(1, 0).symbol, "█");
+            assert_eq!(sp.content.get(2, 0).symbol, "▏");
+            assert_eq!(sp.content.get(3, 0).symbol, " ");
+            assert_eq!(sp.content.get(5, 1).symbol, "▄");
+            assert_eq!(sp.content.get(5, 0).symbol, " ");
+        }
+
+        ui.get_mut::<ProgressBar>("bar").unwrap().label = Some("ok".to_string());
+        ui.draw(&mut sp, &Theme::dark());
+        assert_eq!(sp.content.get(1, 0).symbol, "o");
+        assert_eq!(sp.content.get(1, 0).bg, ColorScale::green_red().at(0.53125));
+    }
+}
@@ -0,0 +1,318 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Table of rows under a header of columns
+//!
+//! Column widths are layout constraints, columns are one cell apart.
+//! Rows are selected and scrolled like a List. A click on a header, or
+//! the digit key of a column, sorts the rows by it, again reverses the
+//! order. Cells that are numbers on both sides compare as numbers.
+
+use crate::event::KeyCode;
+use crate::render::{
+    buffer::Buffer,
+    layout::{Constraint, Layout},
+    style::{Modifier, Theme},
+};
+use crate::ui::{
+    draw_scrollbar, draw_text_fill, widget_style, UiEventKind, UiInput, UiReply, UiWidget,
+};
+use crate::util::Rect;
+use std::any::Any;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub title: String,
+    pub width: Constraint,
+}
+
+impl Column {
+    pub fn new(title: &str, width: Constraint) -> Self {
+        Self {
+            title: title.to_string(),
+            width,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub area: Rect,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<String>>,
+    pub selected: usize,
+    /// first visible row
+    pub offset: usize,
+    // column and ascending
+    sort: Option<(usize, bool)>,
+}
+
+// numbers compare as numbers, the rest as text
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+impl Table {
+    pub fn new(area: Rect, columns: Vec<Column>) -> Self {
+        Self {
+            area,
+            columns,
+            rows: vec![],
+            selected: 0,
+            offset: 0,
+            sort: None,
+        }
+    }
+
+    /// adds a row, kept in place until the next sort
+    pub fn push(&mut self, cells: &[&str]) {
+        self.rows
+            .push(cells.iter().map(|s| s.to_string()).collect());
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+        self.selected = 0;
+        self.offset = 0;
+    }
+
+    pub fn selected_row(&self) -> Option<&[String]> {
+        self.rows.get(self.selected).map(|r| r.as_slice())
+    }
+
+    /// column and ascending of the last sort
+    pub fn sort(&self) -> Option<(usize, bool)> {
+        self.sort
+    }
+
+    /// sorts rows by a column, the selected row stays selected
+    pub fn sort_by(&mut self, col: usize, ascending: bool) {
+        let sel = self.rows.get(self.selected).cloned();
+        let cell = |r: &Vec<String>| r.get(col).cloned().unwrap_or_default();
+        self.rows.sort_by(|a, b| {
+            let o = compare(&cell(a), &cell(b));
+            if ascending {
+                o
+            } else {
+                o.reverse()
+            }
+        });
+        self.sort = Some((col, ascending));
+        if let Some(i) = sel.and_then(|s| self.rows.iter().position(|r| *r == s)) {
+            self.selected = i;
+        }
+        self.select(self.selected);
+    }
+
+    // sorts by col, reverses the order if already sorted by it
+    fn toggle_sort(&mut self, col: usize) -> UiReply {
+        if col >= self.columns.len() {
+            return UiReply::Handled;
+        }
+        let asc = !matches!(self.sort, Some((c, true)) if c == col);
+        self.sort_by(col, asc);
+        UiReply::Event(UiEventKind::Sorted(col, asc))
+    }
+
+    /// x and width of each column
+    pub fn column_areas(&self) -> Vec<Rect> {
+        let w = if self.rows.len() > self.view() {
+            self.area.width.saturating_sub(1)
+        } else {
+            self.area.width
+        };
+        Layout::horizontal(self.columns.iter().map(|c| c.width).collect())
+            .spacing(1)
+            .split(Rect::new(self.area.x, self.area.y, w, 1))
+    }
+
+    // rows visible below the header
+    fn view(&self) -> usize {
+        self.area.height.saturating_sub(1) as usize
+    }
+
+    fn select(&mut self, i: usize) -> UiReply {
+        if self.rows.is_empty() {
+            return UiReply::Ignored;
+        }
+        let i = i.min(self.rows.len() - 1);
+        if i < self.offset {
+            self.offset = i;
+        } else if i >= self.offset + self.view() {
+            self.offset = i + 1 - self.view().max(1);
+        }
+        if i == self.selected {
+            return UiReply::Handled;
+        }
+        self.selected = i;
+        UiReply::Event(UiEventKind::Selected(i))
+    }
+}
+
+impl UiWidget for Table {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+        self.select(self.selected);
+    }
+
+    fn handle(&mut self, input: UiInput) -> UiReply {
+        let (s, page) = (self.selected, self.view().max(1));
+        match input {
+            UiInput::Key(k) => match k.code {
+                KeyCode::Up if s > 0 => self.select(s - 1),
+                KeyCode::Down if s + 1 < self.rows.len() => self.select(s + 1),
+                KeyCode::Home => self.select(0),
+                KeyCode::End => self.select(usize::MAX),
+                KeyCode::PageUp => self.select(s.saturating_sub(page)),
+                KeyCode::PageDown => self.select(s + page),
+                KeyCode::Enter if !self.rows.is_empty() => {
+                    UiReply::Event(UiEventKind::Activated(s))
+                }
+                KeyCode::Char(c @ '1'..='9') => self.toggle_sort(c as usize - '1' as usize),
+                _ => UiReply::Ignored,
+            },
+            UiInput::Click(x, 0) => {
+                let x = self.area.x + x;
+                match self
+                    .column_areas()
+                    .iter()
+                    .position(|r| x >= r.left() && x < r.right())
+                {
+                    Some(c) => self.toggle_sort(c),
+                    None => UiReply::Handled,
+                }
+            }
+            UiInput::Click(_, y) => {
+                let i = self.offset + y as usize - 1;
+                if i >= self.rows.len() {
+                    UiReply::Handled
+                } else if i == s {
+                    UiReply::Event(UiEventKind::Activated(i))
+                } else {
+                    self.select(i)
+                }
+            }
+            UiInput::Drag(..) => UiReply::Handled,
+            UiInput::Wheel(d) => {
+                let max = self.rows.len().saturating_sub(self.view());
+                self.offset = (self.offset as i64 + d as i64).clamp(0, max as i64) as usize;
+                UiReply::Handled
+            }
+        }
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let a = self.area;
+        if a.height == 0 {
+            return;
+        }
+        let cols = self.column_areas();
+        let head = theme
+            .style("ui.title", "ui.bg")
+            .add_modifier(Modifier::BOLD);
+        draw_text_fill(buf, a.x, a.y, "", a.width, head);
+        for (i, (c, r)) in self.columns.iter().zip(&cols).enumerate() {
+            let mark = match self.sort {
+                Some((s, true)) if s == i => " ^",
+                Some((s, false)) if s == i => " v",
+                _ => "",
+            };
+            let t = format!("{}{}", c.title, mark);
+            draw_text_fill(buf, r.x, a.y, &t, r.width, head);
+        }
+        for row in 1..a.height {
+            let i = self.offset + row as usize - 1;
+            let st = widget_style(theme, focused && i == self.selected);
+            let st = if !focused && i == self.selected {
+                st.fg(theme.get("ui.accent"))
+            } else {
+                st
+            };
+            draw_text_fill(
+                buf,
+                a.x,
+                a.y + row,
+                "",
+                cols.last().map_or(0, |r| r.right() - a.x),
+                st,
+            );
+            if let Some(cells) = self.rows.get(i) {
+                for (cell, r) in cells.iter().zip(&cols) {
+                    draw_text_fill(buf, r.x, a.y + row, cell, r.width, st);
+                }
+            }
+        }
+        if self.rows.len() > self.view() {
+            draw_scrollbar(
+                buf,
+                a.right() - 1,
+                a.y + 1,
+                self.view(),
+                self.offset,
+                self.rows.len(),
+                widget_style(theme, false),
+            );
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{MouseButton, MouseEventKind};
+    use crate::ui::{
+        tests::{key, mouse},
+        Ui,
+    };
+    use Constraint::*;
+
+    #[test]
+    fn table_sort_and_select() {
+        let mut t = Table::new(
+            Rect::new(0, 0, 20, 3),
+            vec![Column::new("Name", Fill(1)), Column::new("N", Length(4))],
+        );
+        t.push(&["bow", "10"]);
+        t.push(&["axe", "9"]);
+        t.push(&["club", "100"]);
+        assert_eq!(t.column_areas()[1], Rect::new(15, 0, 4, 1));
+
+        let mut ui = Ui::new();
+        ui.add("t", t);
+        ui.set_focus("t");
+        let ev = ui.update(&[key(KeyCode::Down), key(KeyCode::Char('2'))]);
+        assert_eq!(ev[0].kind, UiEventKind::Selected(1));
+        assert_eq!(ev[1].kind, UiEventKind::Sorted(1, true));
+        let t = ui.get::<Table>("t").unwrap();
+        // numeric order, axe still selected
+        assert_eq!(t.rows[2][1], "100");
+        assert_eq!(t.selected_row().unwrap()[0], "axe");
+        assert_eq!((t.selected, t.offset), (0, 0));
+
+        // a click on the header reverses it
+        let ev = ui.update(&[mouse(MouseEventKind::Down(MouseButton::Left), 16, 0)]);
+        assert_eq!(ev[0].kind, UiEventKind::Sorted(1, false));
+        let mut sp = crate::render::sprite::Sprite::new(0, 0, 20, 3);
+        ui.draw(&mut sp, &Theme::dark());
+        // axe scrolled into view at the bottom
+        assert_eq!(ui.get::<Table>("t").unwrap().offset, 1);
+        assert_eq!(sp.content.get(0, 1).symbol, "b");
+        assert_eq!(sp.content.get(17, 0).symbol, "v");
+    }
+}
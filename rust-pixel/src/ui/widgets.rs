@@ -0,0 +1,227 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Button, Checkbox and Slider
+//!
+//! Enter or Space presses a button and toggles a checkbox, a slider
+//! moves by step with Left and Right, Home and End, or follows the mouse.
+
+use crate::event::KeyCode;
+use crate::render::{buffer::Buffer, style::Theme};
+use crate::ui::{draw_text, draw_text_fill, widget_style, UiEventKind, UiInput, UiReply, UiWidget};
+use crate::util::Rect;
+use std::any::Any;
+use unicode_width::UnicodeWidthStr;
+
+fn is_press(input: &UiInput) -> bool {
+    match input {
+        UiInput::Key(k) => matches!(k.code, KeyCode::Enter | KeyCode::Char(' ')),
+        UiInput::Click(..) => true,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Button {
+    pub area: Rect,
+    pub label: String,
+}
+
+impl Button {
+    pub fn new(area: Rect, label: &str) -> Self {
+        Self {
+            area,
+            label: label.to_string(),
+        }
+    }
+}
+
+impl UiWidget for Button {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    fn handle(&mut self, input: UiInput) -> UiReply {
+        if is_press(&input) {
+            UiReply::Event(UiEventKind::Clicked)
+        } else {
+            UiReply::Ignored
+        }
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let st = widget_style(theme, focused);
+        let a = self.area;
+        let pad = (a.width as usize).saturating_sub(self.label.width()) / 2;
+        for y in 0..a.height {
+            let s = if y == a.height / 2 {
+                format!("{}{}", " ".repeat(pad), self.label)
+            } else {
+                String::new()
+            };
+            draw_text_fill(buf, a.x, a.y + y, &s, a.width, st);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkbox {
+    pub area: Rect,
+    pub label: String,
+    pub checked: bool,
+}
+
+impl Checkbox {
+    pub fn new(area: Rect, label: &str, checked: bool) -> Self {
+        Self {
+            area,
+            label: label.to_string(),
+            checked,
+        }
+    }
+}
+
+impl UiWidget for Checkbox {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    fn handle(&mut self, input: UiInput) -> UiReply {
+        if is_press(&input) {
+            self.checked = !self.checked;
+            UiReply::Event(UiEventKind::Toggled(self.checked))
+        } else {
+            UiReply::Ignored
+        }
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let mark = if self.checked { "[x] " } else { "[ ] " };
+        let s = format!("{}{}", mark, self.label);
+        draw_text_fill(
+            buf,
+            self.area.x,
+            self.area.y,
+            &s,
+            self.area.width,
+            widget_style(theme, focused),
+        );
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// a value in min..=max on a one row track
+#[derive(Debug, Clone)]
+pub struct Slider {
+    pub area: Rect,
+    pub min: f32,
+    pub max: f32,
+    pub value: f32,
+    /// change of one key press, a tenth of the range by default
+    pub step: f32,
+}
+
+impl Slider {
+    pub fn new(area: Rect, min: f32, max: f32, value: f32) -> Self {
+        Self {
+            area,
+            min,
+            max,
+            value: value.clamp(min, max),
+            step: (max - min) / 10.0,
+        }
+    }
+
+    fn set(&mut self, v: f32) -> UiReply {
+        let v = v.clamp(self.min, self.max);
+        if v == self.value {
+            return UiReply::Handled;
+        }
+        self.value = v;
+        UiReply::Event(UiEventKind::Changed(v))
+    }
+
+    // track cell of the knob
+    fn knob(&self) -> u16 {
+        let span = self.max - self.min;
+        if span <= 0.0 || self.area.width < 2 {
+            return 0;
+        }
+        ((self.value - self.min) / span * (self.area.width - 1) as f32).round() as u16
+    }
+
+    fn value_at(&self, x: u16) -> f32 {
+        if self.area.width < 2 {
+            return self.min;
+        }
+        self.min + (self.max - self.min) * x as f32 / (self.area.width - 1) as f32
+    }
+}
+
+impl UiWidget for Slider {
+    fn area(&self) -> Rect {
+        self.area
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    fn handle(&mut self, input: UiInput) -> UiReply {
+        match input {
+            UiInput::Key(k) => match k.code {
+                KeyCode::Left => self.set(self.value - self.step),
+                KeyCode::Right => self.set(self.value + self.step),
+                KeyCode::Home => self.set(self.min),
+                KeyCode::End => self.set(self.max),
+                _ => UiReply::Ignored,
+            },
+            UiInput::Click(x, _) | UiInput::Drag(x, _) => self.set(self.value_at(x)),
+            UiInput::Wheel(d) => self.set(self.value - d as f32 * self.step),
+        }
+    }
+
+    fn draw(&self, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let st = widget_style(theme, focused);
+        let k = self.knob();
+        for x in 0..self.area.width {
+            let (s, st) = match x.cmp(&k) {
+                std::cmp::Ordering::Less => ("=", st.fg(theme.get("ui.accent"))),
+                std::cmp::Ordering::Equal => ("O", st),
+                std::cmp::Ordering::Greater => ("-", st),
+            };
+            draw_text(buf, self.area.x + x, self.area.y, s, 1, st);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
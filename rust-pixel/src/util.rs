@@ -4,6 +4,8 @@
 //! Utils of random rect PointU16...
 //! and a simple object pool: objpool.rs
 //! some primitive algorithm: shape.rs
+//! scoped timers and trace export: profiler.rs
+//! aabb, grid movement and spatial hash: collision.rs
 
 use serde::{Deserialize, Serialize};
 use std::{
@@ -18,10 +20,13 @@ use keyframe_derive::CanTween;
 
 pub mod objpool;
 pub mod shape;
+pub mod collision;
 mod particle;
 pub use particle::*;
 mod rand;
 pub use rand::*;
+mod profiler;
+pub use profiler::*;
 
 /// get flag_file path...
 pub fn get_project_root(flag_file: &str) -> io::Result<PathBuf> {
@@ -76,6 +81,43 @@ pub fn get_file_name(fpath: &str) -> String {
         .to_string()
 }
 
+const BASE64_TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// standard base64 with padding
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for c in data.chunks(3) {
+        let n = (c[0] as u32) << 16
+            | (*c.get(1).unwrap_or(&0) as u32) << 8
+            | *c.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= c.len() {
+                out.push(BASE64_TABLE[(n >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// decodes standard base64, None on invalid input
+pub fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for b in s.bytes() {
+        let v = BASE64_TABLE.iter().position(|t| *t == b)? as u32;
+        acc = acc << 6 | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum Dir {
     Up,
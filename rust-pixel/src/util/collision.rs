@@ -0,0 +1,304 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! Basic collision for grid games, in cell units
+//!
+//! Aabb is an axis aligned box with float position, so entities can move
+//! smoothly between cells. move_and_collide moves a box against a solid
+//! grid, x first then y, and stops it flush with the wall, so sliding
+//! along walls just works. A TileMap is a grid by its SOLID tiles, any
+//! Fn(i32, i32) -> bool is one too.
+//!
+//! SpatialHash buckets boxes by cell, so checking many entities against
+//! each other only looks at near ones.
+//!
+//! # Example
+//! ```
+//! use rust_pixel::util::collision::*;
+//!
+//! // walls right of x = 6
+//! let wall = |x: i32, _y: i32| x >= 6;
+//! let player = Aabb::new(3.0, 4.0, 1.0, 1.0);
+//! let r = move_and_collide(&wall, player, 5.0, 0.0);
+//! assert!(r.hit_x);
+//! assert_eq!(r.aabb.right(), 6.0);
+//!
+//! let mut sh = SpatialHash::new(4.0);
+//! sh.insert(1, &Aabb::new(5.0, 4.0, 1.0, 1.0));
+//! sh.insert(2, &Aabb::new(30.0, 30.0, 1.0, 1.0));
+//! assert!(sh.query(&r.aabb).contains(&1));
+//! ```
+
+#[cfg(not(feature = "base"))]
+use crate::render::tilemap::TileMap;
+use std::collections::HashMap;
+
+/// axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Aabb {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Aabb {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.w
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.h
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.x + self.w / 2.0, self.y + self.h / 2.0)
+    }
+
+    /// touching edges do not overlap
+    pub fn overlaps(&self, o: &Aabb) -> bool {
+        self.x < o.right() && o.x < self.right() && self.y < o.bottom() && o.y < self.bottom()
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// shortest move of self out of o, None if they do not overlap
+    pub fn penetration(&self, o: &Aabb) -> Option<(f32, f32)> {
+        if !self.overlaps(o) {
+            return None;
+        }
+        let dx = if self.center().0 < o.center().0 {
+            o.x - self.right()
+        } else {
+            o.right() - self.x
+        };
+        let dy = if self.center().1 < o.center().1 {
+            o.y - self.bottom()
+        } else {
+            o.bottom() - self.y
+        };
+        if dx.abs() < dy.abs() {
+            Some((dx, 0.0))
+        } else {
+            Some((0.0, dy))
+        }
+    }
+
+    /// cells covered, as (x0, y0, x1, y1) inclusive
+    pub fn cells(&self) -> (i32, i32, i32, i32) {
+        (
+            self.x.floor() as i32,
+            self.y.floor() as i32,
+            self.right().ceil() as i32 - 1,
+            self.bottom().ceil() as i32 - 1,
+        )
+    }
+}
+
+/// a grid of solid cells
+pub trait SolidGrid {
+    fn is_solid(&self, x: i32, y: i32) -> bool;
+}
+
+#[cfg(not(feature = "base"))]
+impl SolidGrid for TileMap {
+    fn is_solid(&self, x: i32, y: i32) -> bool {
+        TileMap::is_solid(self, x, y)
+    }
+}
+
+impl<F: Fn(i32, i32) -> bool> SolidGrid for F {
+    fn is_solid(&self, x: i32, y: i32) -> bool {
+        self(x, y)
+    }
+}
+
+/// true if any cell under the box is solid
+pub fn hits_solid<G: SolidGrid + ?Sized>(grid: &G, b: &Aabb) -> bool {
+    let (x0, y0, x1, y1) = b.cells();
+    (y0..=y1).any(|y| (x0..=x1).any(|x| grid.is_solid(x, y)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveResult {
+    pub aabb: Aabb,
+    pub hit_x: bool,
+    pub hit_y: bool,
+}
+
+/// moves b by (dx, dy), stopping at solid cells of grid,
+/// b should not overlap a solid cell before moving
+pub fn move_and_collide<G: SolidGrid + ?Sized>(grid: &G, b: Aabb, dx: f32, dy: f32) -> MoveResult {
+    let mut aabb = b;
+    let hit_x = sweep(grid, &mut aabb, dx, true);
+    let hit_y = sweep(grid, &mut aabb, dy, false);
+    MoveResult { aabb, hit_x, hit_y }
+}
+
+// moves along one axis in steps of at most one cell, so no wall is skipped
+fn sweep<G: SolidGrid + ?Sized>(grid: &G, b: &mut Aabb, d: f32, horizontal: bool) -> bool {
+    let mut left = d;
+    while left != 0.0 {
+        let step = left.clamp(-1.0, 1.0);
+        left -= step;
+        let mut nb = *b;
+        if horizontal {
+            nb.x += step;
+        } else {
+            nb.y += step;
+        }
+        if hits_solid(grid, &nb) {
+            // flush with the cell entered
+            match (horizontal, step > 0.0) {
+                (true, true) => nb.x = nb.right().ceil() - 1.0 - nb.w,
+                (true, false) => nb.x = nb.x.floor() + 1.0,
+                (false, true) => nb.y = nb.bottom().ceil() - 1.0 - nb.h,
+                (false, false) => nb.y = nb.y.floor() + 1.0,
+            }
+            *b = nb;
+            return true;
+        }
+        *b = nb;
+    }
+    false
+}
+
+/// buckets of ids by cell, for broad phase checks of many boxes
+#[derive(Debug, Clone)]
+pub struct SpatialHash {
+    pub cell_size: f32,
+    buckets: HashMap<(i32, i32), Vec<u32>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(f32::EPSILON),
+            buckets: HashMap::new(),
+        }
+    }
+
+    fn keys(&self, b: &Aabb) -> impl Iterator<Item = (i32, i32)> {
+        let s = self.cell_size;
+        let (x0, y0) = ((b.x / s).floor() as i32, (b.y / s).floor() as i32);
+        let (x1, y1) = (
+            (b.right() / s).floor() as i32,
+            (b.bottom() / s).floor() as i32,
+        );
+        (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    pub fn insert(&mut self, id: u32, b: &Aabb) {
+        for k in self.keys(b).collect::<Vec<_>>() {
+            self.buckets.entry(k).or_default().push(id);
+        }
+    }
+
+    /// removes id, b must be the box it was inserted with
+    pub fn remove(&mut self, id: u32, b: &Aabb) {
+        for k in self.keys(b).collect::<Vec<_>>() {
+            if let Some(v) = self.buckets.get_mut(&k) {
+                v.retain(|i| *i != id);
+                if v.is_empty() {
+                    self.buckets.remove(&k);
+                }
+            }
+        }
+    }
+
+    /// ids sharing a bucket with b, sorted, may not really overlap it
+    pub fn query(&self, b: &Aabb) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .keys(b)
+            .filter_map(|k| self.buckets.get(&k))
+            .flatten()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// index pairs (i < j) of overlapping boxes
+pub fn overlapping_pairs(boxes: &[Aabb], cell_size: f32) -> Vec<(usize, usize)> {
+    let mut sh = SpatialHash::new(cell_size);
+    for (i, b) in boxes.iter().enumerate() {
+        sh.insert(i as u32, b);
+    }
+    let mut pairs = vec![];
+    for (i, b) in boxes.iter().enumerate() {
+        for j in sh.query(b) {
+            let j = j as usize;
+            if j > i && b.overlaps(&boxes[j]) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aabb_grid_and_hash() {
+        let a = Aabb::new(0.0, 0.0, 2.0, 2.0);
+        assert!(a.overlaps(&Aabb::new(1.5, 1.0, 1.0, 1.0)));
+        assert!(!a.overlaps(&Aabb::new(2.0, 0.0, 1.0, 1.0)));
+        assert_eq!(
+            a.penetration(&Aabb::new(1.5, 0.0, 2.0, 2.0)),
+            Some((-0.5, 0.0))
+        );
+
+        // wall at x = 5, floor at y = 6
+        let grid = |x: i32, y: i32| x == 5 || y >= 6 || x < 0;
+        let p = Aabb::new(1.0, 1.0, 1.0, 1.0);
+        let r = move_and_collide(&grid, p, 10.0, 0.5);
+        assert!(r.hit_x && !r.hit_y);
+        assert_eq!(r.aabb.x, 4.0);
+        assert_eq!(r.aabb.y, 1.5);
+        // slides down the wall and lands
+        let r = move_and_collide(&grid, r.aabb, 1.0, 7.25);
+        assert_eq!((r.aabb.x, r.aabb.y), (4.0, 5.0));
+        assert!(r.hit_x && r.hit_y);
+        let r = move_and_collide(&grid, r.aabb, -2.5, 0.0);
+        assert_eq!(r.aabb.x, 1.5);
+        assert!(!r.hit_x);
+
+        #[cfg(not(feature = "base"))]
+        {
+            let mut tm = TileMap::new(10, 10);
+            let wall = tm.add_tile(Default::default(), crate::render::tilemap::TileFlags::SOLID);
+            let l = tm.add_layer("ground");
+            tm.set_tile(l, 3, 0, wall);
+            let r = move_and_collide(&tm, Aabb::new(0.5, 0.0, 1.0, 1.0), 5.0, 0.0);
+            assert_eq!(r.aabb.x, 2.0);
+        }
+
+        let boxes = vec![
+            Aabb::new(0.0, 0.0, 1.0, 1.0),
+            Aabb::new(0.5, 0.5, 1.0, 1.0),
+            Aabb::new(20.0, 20.0, 1.0, 1.0),
+            Aabb::new(20.5, 20.0, 1.0, 1.0),
+            Aabb::new(10.0, 0.0, 1.0, 1.0),
+        ];
+        assert_eq!(overlapping_pairs(&boxes, 4.0), vec![(0, 1), (2, 3)]);
+        let mut sh = SpatialHash::new(4.0);
+        sh.insert(7, &boxes[2]);
+        assert_eq!(sh.query(&Aabb::new(21.0, 21.0, 1.0, 1.0)), vec![7]);
+        sh.remove(7, &boxes[2]);
+        assert!(sh.query(&boxes[2]).is_empty());
+    }
+}
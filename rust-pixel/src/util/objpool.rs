@@ -58,32 +58,48 @@ where
 
     /// search available object or creat new object...
     /// then call a custom closure init this obj
-    pub fn create_with_func<F>(&mut self, otype: u8, mut f: F)
+    pub fn create_with_func<F>(&mut self, otype: u8, f: F)
+    where
+        F: FnMut(u8, &mut GameObject<T>),
+    {
+        self.create_in(otype, usize::MAX, f);
+    }
+
+    /// same as create_with_func, but the pool never grows beyond
+    /// max_count, returns the object id or None when the pool is full
+    pub fn try_create_with_func<F>(&mut self, otype: u8, f: F) -> Option<usize>
+    where
+        F: FnMut(u8, &mut GameObject<T>),
+    {
+        self.create_in(otype, self.max_count, f)
+    }
+
+    fn create_in<F>(&mut self, otype: u8, cap: usize, mut f: F) -> Option<usize>
     where
         F: FnMut(u8, &mut GameObject<T>),
     {
-        let mut find = false;
         // search for an available object
         for o in &mut self.pool {
             if !o.active {
                 f(otype, o);
                 o.active = true;
-                find = true;
-                break;
+                return Some(o.id);
             }
         }
         // if not found, create a new one and add to the pool
-        if !find {
-            let l = self.pool.len();
-            let bo: T = T::new();
-            let mut o = GameObject {
-                id: l,
-                obj: bo,
-                active: true,
-            };
-            f(otype, &mut o);
-            self.pool.push(o);
+        if self.pool.len() >= cap {
+            return None;
         }
+        let l = self.pool.len();
+        let bo: T = T::new();
+        let mut o = GameObject {
+            id: l,
+            obj: bo,
+            active: true,
+        };
+        f(otype, &mut o);
+        self.pool.push(o);
+        Some(l)
     }
 
     /// create obj by vector...
@@ -81,12 +81,17 @@ pub struct ParticleSystem {
 
 impl ParticleSystem {
     pub fn new(info: ParticleSystemInfo) -> ParticleSystem {
+        Self::with_pool(info, "PARTICLE", MAX_PARTICLES)
+    }
+
+    /// prefix names the pool sprites, max_count caps alive particles
+    pub fn with_pool(info: ParticleSystemInfo, prefix: &str, max_count: usize) -> ParticleSystem {
         let mut rnd = Rand::new();
         rnd.srand_now();
         ParticleSystem {
             info,
             rnd,
-            particles: GameObjPool::<Particle>::new("PARTICLE", MAX_PARTICLES),
+            particles: GameObjPool::<Particle>::new(prefix, max_count),
             emission_residue: 0.0,
             age: -2.0,
             loc: [0.0, 0.0],
@@ -147,7 +152,8 @@ impl ParticleSystem {
             self.emission_residue = particles_needed - particles_to_create as f64;
 
             for _ in 0..particles_to_create {
-                self.particles.create_with_func(0, |ot, po| {
+                // a full pool drops the particle
+                let _ = self.particles.try_create_with_func(0, |ot, po| {
                     let p = &mut po.obj;
                     p.ptype = ot;
                     p.age = 0.0;
@@ -0,0 +1,261 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+//! A small built-in profiler. profile_scope times a scope until the guard
+//! drops, scopes nest. The game loop, panel composition and GL flushes are
+//! instrumented, games add their own scopes. Per frame totals of each
+//! scope are shown by the debug overlay while profiling is on.
+//!
+//! Recorded spans are exported as chrome tracing json (chrome://tracing,
+//! Perfetto, speedscope) or as folded stacks for flamegraph tools.
+//!
+//! The profiler is per thread, the one running the game loop, so scopes
+//! of other threads do not mix up the nesting.
+
+use serde::Serialize;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+};
+
+// weight of a new frame in the averages
+const SMOOTH: f64 = 0.1;
+
+/// recorded spans kept at most, older ones are dropped
+pub const MAX_TRACE_SPANS: usize = 200_000;
+
+/// seconds since some fixed point
+pub fn profile_clock() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now() / 1000.0
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::{sync::OnceLock, time::Instant};
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_secs_f64()
+    }
+}
+
+/// Time of a scope in ms, averaged over frames
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProfileStat {
+    pub last: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    ph: &'static str,
+    // microseconds
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: u32,
+}
+
+struct Open {
+    name: &'static str,
+    start: f64,
+    child: f64,
+}
+
+#[derive(Default)]
+struct Profiler {
+    stack: Vec<Open>,
+    frame: HashMap<&'static str, f64>,
+    stats: BTreeMap<&'static str, ProfileStat>,
+    recording: bool,
+    trace: Vec<TraceEvent>,
+    // self time in seconds by stack path
+    folded: HashMap<String, f64>,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default());
+}
+
+/// Guard of profile_scope
+pub struct ProfileScope {
+    active: bool,
+}
+
+/// times the enclosing scope while the guard lives
+pub fn profile_scope(name: &'static str) -> ProfileScope {
+    let active = ENABLED.with(|e| e.get());
+    if active {
+        let start = profile_clock();
+        PROFILER.with(|p| {
+            p.borrow_mut().stack.push(Open {
+                name,
+                start,
+                child: 0.0,
+            })
+        });
+    }
+    ProfileScope { active }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        let end = profile_clock();
+        PROFILER.with(|p| {
+            let mut p = p.borrow_mut();
+            let Some(o) = p.stack.last() else {
+                return;
+            };
+            let dur = end - o.start;
+            let (name, start, child) = (o.name, o.start, o.child);
+            if p.recording {
+                let path: Vec<&str> = p.stack.iter().map(|o| o.name).collect();
+                *p.folded.entry(path.join(";")).or_default() += (dur - child).max(0.0);
+                if p.trace.len() >= MAX_TRACE_SPANS {
+                    p.trace.remove(0);
+                }
+                p.trace.push(TraceEvent {
+                    name,
+                    ph: "X",
+                    ts: start * 1e6,
+                    dur: dur * 1e6,
+                    pid: 1,
+                    tid: 1,
+                });
+            }
+            p.stack.pop();
+            if let Some(parent) = p.stack.last_mut() {
+                parent.child += dur;
+            }
+            *p.frame.entry(name).or_default() += dur;
+        });
+    }
+}
+
+pub fn profiler_enable(on: bool) {
+    ENABLED.with(|e| e.set(on));
+    if !on {
+        PROFILER.with(|p| {
+            let mut p = p.borrow_mut();
+            p.stack.clear();
+            p.frame.clear();
+        });
+    }
+}
+
+pub fn profiler_enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// starts or stops keeping spans for export, starting clears old ones
+pub fn profiler_record(on: bool) {
+    PROFILER.with(|p| {
+        let mut p = p.borrow_mut();
+        if on && !p.recording {
+            p.trace.clear();
+            p.folded.clear();
+        }
+        p.recording = on;
+    });
+}
+
+/// ends a frame, adding its totals to the stats, called by the game loop
+pub fn profiler_frame() {
+    if !profiler_enabled() {
+        return;
+    }
+    PROFILER.with(|p| {
+        let mut p = p.borrow_mut();
+        let frame = std::mem::take(&mut p.frame);
+        for (name, secs) in frame {
+            let ms = secs * 1000.0;
+            let s = p.stats.entry(name).or_insert(ProfileStat {
+                avg: ms,
+                ..Default::default()
+            });
+            s.last = ms;
+            s.avg += (ms - s.avg) * SMOOTH;
+            s.max = s.max.max(ms);
+        }
+    });
+}
+
+/// stats of all scopes seen, by name
+pub fn profiler_stats() -> Vec<(&'static str, ProfileStat)> {
+    PROFILER.with(|p| p.borrow().stats.iter().map(|(k, v)| (*k, *v)).collect())
+}
+
+pub fn profiler_reset_stats() {
+    PROFILER.with(|p| p.borrow_mut().stats.clear());
+}
+
+/// recorded spans as chrome tracing json
+pub fn profiler_chrome_json() -> String {
+    PROFILER.with(|p| {
+        let p = p.borrow();
+        serde_json::json!({ "traceEvents": p.trace }).to_string()
+    })
+}
+
+pub fn profiler_save_trace(path: &str) -> Result<(), String> {
+    std::fs::write(path, profiler_chrome_json()).map_err(|e| e.to_string())
+}
+
+/// recorded self times as folded stacks "a;b;c micros" per line,
+/// the input of inferno or flamegraph.pl
+pub fn profiler_folded() -> String {
+    PROFILER.with(|p| {
+        let p = p.borrow();
+        let mut v: Vec<_> = p.folded.iter().collect();
+        v.sort_by(|a, b| a.0.cmp(b.0));
+        v.iter()
+            .map(|(k, s)| format!("{} {}\n", k, (**s * 1e6).round() as u64))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn busy(secs: f64) {
+        let t = profile_clock();
+        while profile_clock() - t < secs {}
+    }
+
+    #[test]
+    fn profiler_nests_and_exports() {
+        {
+            let _p = profile_scope("off");
+        }
+        profiler_enable(true);
+        profiler_record(true);
+        for _ in 0..2 {
+            let _f = profile_scope("frame");
+            busy(0.001);
+            for _ in 0..2 {
+                let _u = profile_scope("update");
+                busy(0.001);
+            }
+        }
+        profiler_frame();
+        let st: HashMap<_, _> = profiler_stats().into_iter().collect();
+        assert!(!st.contains_key("off"));
+        assert!(st["update"].last >= 4.0);
+        assert!(st["frame"].last >= st["update"].last + 2.0);
+
+        let js: serde_json::Value = serde_json::from_str(&profiler_chrome_json()).unwrap();
+        assert_eq!(js["traceEvents"].as_array().unwrap().len(), 6);
+        let folded = profiler_folded();
+        let lines: Vec<&str> = folded.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("frame "));
+        assert!(lines[1].starts_with("frame;update "));
+        profiler_enable(false);
+    }
+}
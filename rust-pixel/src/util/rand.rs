@@ -1,15 +1,36 @@
 // RustPixel
 // copyright zipxing@hotmail.com 2022~2024
 
+//! Rand is the seedable PRNG in context.rand, seeded with 0 by default.
+//! Gameplay using only context.rand is deterministic for a seed, and the
+//! state can be saved and restored for replays, save games and tests.
+//!
+//! # Example
+//! ```
+//! use rust_pixel::util::Rand;
+//!
+//! let mut rand = Rand::new();
+//! rand.srand(42);
+//! let saved = rand.state();
+//! let dmg = rand.range(3, 7);
+//! let loot = rand.pick_weighted(&[("gold", 8.0), ("gem", 1.5), ("sword", 0.5)]);
+//!
+//! // the same rolls again
+//! rand.set_state(&saved).unwrap();
+//! assert_eq!(rand.range(3, 7), dmg);
+//! ```
+
 use rand::seq::SliceRandom;
 use rand_xoshiro::{
     rand_core::{RngCore, SeedableRng},
     Xoshiro256StarStar,
 };
+use serde::{Deserialize, Serialize};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// RCG
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Rand {
     rng: Xoshiro256StarStar,
 }
@@ -65,9 +86,80 @@ impl Rand {
         (u1 + (self.rng.next_u64() % (u2 - u1 + 1))) as f64 / 1000.0
     }
 
-    pub fn shuffle<T: Copy>(&mut self, v: &mut Vec<T>) {
+    pub fn shuffle<T>(&mut self, v: &mut [T]) {
         v.shuffle(&mut self.rng);
     }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: Xoshiro256StarStar::seed_from_u64(seed),
+        }
+    }
+
+    /// integer in lo..hi, lo if the range is empty
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        if hi <= lo {
+            return lo;
+        }
+        let span = (hi as i64 - lo as i64) as u64;
+        (lo as i64 + (self.rng.next_u64() % span) as i64) as i32
+    }
+
+    /// float in lo..hi
+    pub fn range_f32(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.unit()
+    }
+
+    /// float in 0.0..1.0
+    pub fn unit(&mut self) -> f32 {
+        (self.rng.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// true with probability p
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.unit() < p
+    }
+
+    pub fn pick<'a, T>(&mut self, v: &'a [T]) -> Option<&'a T> {
+        if v.is_empty() {
+            return None;
+        }
+        Some(&v[self.rng.next_u64() as usize % v.len()])
+    }
+
+    /// index picked with probability proportional to its weight,
+    /// None if no weight is positive
+    pub fn weighted_index(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().filter(|w| **w > 0.0).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut r = self.unit() * total;
+        let mut last = None;
+        for (i, w) in weights.iter().enumerate().filter(|(_, w)| **w > 0.0) {
+            if r < *w {
+                return Some(i);
+            }
+            r -= w;
+            last = Some(i);
+        }
+        last
+    }
+
+    pub fn pick_weighted<'a, T>(&mut self, items: &'a [(T, f32)]) -> Option<&'a T> {
+        let ws: Vec<f32> = items.iter().map(|i| i.1).collect();
+        self.weighted_index(&ws).map(|i| &items[i].0)
+    }
+
+    /// serialized generator state, restore it with set_state
+    pub fn state(&self) -> Vec<u8> {
+        bincode::serialize(&self.rng).unwrap_or_default()
+    }
+
+    pub fn set_state(&mut self, data: &[u8]) -> Result<(), String> {
+        self.rng = bincode::deserialize(data).map_err(|e| e.to_string())?;
+        Ok(())
+    }
 }
 
 /// 封装LCG随机数生成器, 随机效果不好
@@ -140,3 +232,30 @@ impl RandLCG {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rand_seeded_and_restorable() {
+        let mut a = Rand::with_seed(7);
+        let saved = a.state();
+        let seq: Vec<i32> = (0..10).map(|_| a.range(-3, 3)).collect();
+        assert!(seq.iter().all(|v| (-3..3).contains(v)));
+
+        let mut b = Rand::new();
+        b.set_state(&saved).unwrap();
+        let seq2: Vec<i32> = (0..10).map(|_| b.range(-3, 3)).collect();
+        assert_eq!(seq, seq2);
+
+        assert_eq!(a.weighted_index(&[0.0, 0.0]), None);
+        for _ in 0..20 {
+            assert_eq!(a.pick_weighted(&[("no", 0.0), ("yes", 1.0)]), Some(&"yes"));
+        }
+        let mut v = vec![1, 2, 3, 4];
+        a.shuffle(&mut v);
+        v.sort();
+        assert_eq!(v, [1, 2, 3, 4]);
+    }
+}
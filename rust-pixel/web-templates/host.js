@@ -0,0 +1,238 @@
+// functions rust calls through wasm_bindgen(raw_module = "/host.js"),
+// index.js and worker.js give it the game to call back with setGame.
+// In a worker the calls that need the page (text input, clipboard,
+// audio) are posted to index.js as { t: "host", f, args }, which runs
+// them with runHostCall
+let sg = null;
+export const setGame = (g) => { sg = g; };
+
+const inWorker = typeof document === "undefined";
+const page = {};
+
+// rust call this function...
+export const js_load_asset = (url) => {
+    fetch(url)
+        .then(data=>{
+            if (!data.ok) {
+                throw new Error(data.status);
+            }
+            return data.arrayBuffer();
+        })
+        .then(res=>{
+            sg.on_asset_loaded(url, new Uint8Array(res));
+        })
+        .catch(err=>{
+            console.log("load asset failed:", url, err);
+            sg.on_asset_failed(url);
+        })
+    ;
+};
+
+// rust call these functions for async saves in IndexedDB, which also
+// works in a worker, on_storage_done(op, key, err, data) answers with
+// op 0 put, 1 get, 2 delete and err "" or the name of the error, like
+// QuotaExceededError
+let idb = null;
+const openDb = () => {
+    if (!idb) {
+        idb = new Promise((ok, fail) => {
+            const r = indexedDB.open("rust_pixel", 1);
+            r.onupgradeneeded = () => r.result.createObjectStore("saves");
+            r.onsuccess = () => ok(r.result);
+            r.onerror = () => fail(r.error);
+        });
+    }
+    return idb;
+};
+const idbRequest = (op, key, mode, f) => {
+    const none = new Uint8Array();
+    openDb()
+        .then(db => new Promise((ok, fail) => {
+            const tx = db.transaction("saves", mode);
+            const r = f(tx.objectStore("saves"));
+            tx.oncomplete = () => ok(r.result);
+            tx.onerror = tx.onabort = () => fail(tx.error || r.error);
+        }))
+        .then(res => {
+            if (op === 1 && res === undefined) sg.on_storage_done(op, key, "NotFound", none);
+            else sg.on_storage_done(op, key, "", op === 1 ? res : none);
+        })
+        .catch(err => {
+            console.log("indexeddb failed:", key, err);
+            sg.on_storage_done(op, key, (err && err.name) || String(err), none);
+        });
+};
+export const js_idb_put = (key, data) => {
+    // data is a view of the wasm memory, copy it before going async
+    const d = data.slice();
+    idbRequest(0, key, "readwrite", s => s.put(d, key));
+};
+export const js_idb_get = (key) => idbRequest(1, key, "readonly", s => s.get(key));
+export const js_idb_delete = (key) => idbRequest(2, key, "readwrite", s => s.delete(key));
+
+// rust call this function to turn text input mode on or off,
+// a hidden textarea takes focus so ime composition works
+let textArea = null;
+page.js_text_input = (on) => {
+    if (!textArea) {
+        textArea = document.createElement("textarea");
+        textArea.style.cssText = "position:absolute;left:-1000px;top:0;opacity:0;";
+        document.body.appendChild(textArea);
+        // text, t: 0 committed, 1 composition in progress
+        textArea.addEventListener("compositionupdate", (e) => { sg.text_event(1, e.data); });
+        textArea.addEventListener("compositionend", (e) => {
+            sg.text_event(1, "");
+            sg.text_event(0, e.data);
+            textArea.value = "";
+        });
+        textArea.addEventListener("input", (e) => {
+            if (!e.isComposing && e.inputType === "insertText") {
+                sg.text_event(0, e.data);
+                textArea.value = "";
+            }
+        });
+    }
+    if (on) {
+        textArea.focus();
+    } else {
+        textArea.blur();
+    }
+};
+
+// rust call these functions to enter or leave fullscreen and to lock
+// the pointer, index.js reports the changes back
+page.js_fullscreen = (on) => {
+    const p = on
+        ? document.getElementById("canvas").requestFullscreen()
+        : document.fullscreenElement && document.exitFullscreen();
+    Promise.resolve(p).catch(err => { console.log("fullscreen failed:", err); });
+};
+page.js_pointer_lock = (on) => {
+    if (on) document.getElementById("canvas").requestPointerLock();
+    else document.exitPointerLock();
+};
+
+// rust call this function to copy text...
+page.js_clipboard_set = (s) => {
+    navigator.clipboard.writeText(s).catch(err => {
+        console.log("clipboard write failed:", err);
+    });
+};
+
+// rust call these functions to play sounds with WebAudio,
+// each voice is a buffer source through its own gain node
+let audioCtx = null;
+const audioBuffers = {};
+const audioVoices = {};
+const loadAudio = (url) => {
+    if (!audioBuffers[url]) {
+        audioBuffers[url] = fetch(url)
+            .then(r => r.arrayBuffer())
+            .then(b => audioCtx.decodeAudioData(b));
+    }
+    return audioBuffers[url];
+};
+page.js_audio_play = (id, url, looping, volume) => {
+    if (!audioCtx) audioCtx = new AudioContext();
+    // browsers start the context suspended until a user gesture
+    audioCtx.resume();
+    const gain = audioCtx.createGain();
+    gain.gain.value = volume;
+    gain.connect(audioCtx.destination);
+    const voice = { gain, volume, paused: false, src: null, stopped: false };
+    audioVoices[id] = voice;
+    loadAudio(url).then(buf => {
+        if (voice.stopped) return;
+        const src = audioCtx.createBufferSource();
+        src.buffer = buf;
+        src.loop = looping;
+        src.connect(gain);
+        src.onended = () => { delete audioVoices[id]; };
+        src.start();
+        voice.src = src;
+    }).catch(err => {
+        console.log("audio load failed:", url, err);
+        delete audioVoices[id];
+    });
+};
+// rust renders tracker music and queues pcm chunks to a stream voice
+page.js_audio_stream = (id, volume) => {
+    if (!audioCtx) audioCtx = new AudioContext();
+    audioCtx.resume();
+    const gain = audioCtx.createGain();
+    gain.gain.value = volume;
+    gain.connect(audioCtx.destination);
+    audioVoices[id] = { gain, volume, paused: false, src: null, stopped: false, queued: [], next: 0 };
+};
+page.js_audio_queue = (id, pcm, rate) => {
+    const v = audioVoices[id];
+    if (!v || pcm.length < 2) return;
+    // interleaved stereo to planar
+    const frames = pcm.length / 2;
+    const buf = audioCtx.createBuffer(2, frames, rate);
+    const l = buf.getChannelData(0), r = buf.getChannelData(1);
+    for (let i = 0; i < frames; i++) {
+        l[i] = pcm[i * 2];
+        r[i] = pcm[i * 2 + 1];
+    }
+    const src = audioCtx.createBufferSource();
+    src.buffer = buf;
+    src.connect(v.gain);
+    v.next = Math.max(v.next, audioCtx.currentTime);
+    src.start(v.next);
+    v.next += buf.duration;
+    v.queued.push(src);
+    src.onended = () => { v.queued = v.queued.filter(s => s !== src); };
+};
+
+// a paused voice is muted, buffer sources can not be resumed
+const setGain = (v) => { v.gain.gain.value = v.paused ? 0 : v.volume; };
+page.js_audio_volume = (id, volume) => {
+    const v = audioVoices[id];
+    if (!v) return;
+    v.volume = volume;
+    setGain(v);
+};
+page.js_audio_pause = (id, paused) => {
+    const v = audioVoices[id];
+    if (!v) return;
+    v.paused = paused;
+    setGain(v);
+};
+page.js_audio_stop = (id) => {
+    const v = audioVoices[id];
+    if (!v) return;
+    v.stopped = true;
+    if (v.src) v.src.stop();
+    if (v.queued) v.queued.forEach(s => s.stop());
+    delete audioVoices[id];
+};
+
+// index.js runs calls posted by a worker
+export const runHostCall = (m) => { page[m.f](...m.args); };
+
+const call = (f) => (...args) => {
+    if (inWorker) {
+        self.postMessage({ t: "host", f, args });
+    } else {
+        page[f](...args);
+    }
+};
+export const js_text_input = call("js_text_input");
+export const js_clipboard_set = call("js_clipboard_set");
+export const js_fullscreen = call("js_fullscreen");
+export const js_pointer_lock = call("js_pointer_lock");
+export const js_audio_play = call("js_audio_play");
+export const js_audio_stream = call("js_audio_stream");
+export const js_audio_volume = call("js_audio_volume");
+export const js_audio_pause = call("js_audio_pause");
+export const js_audio_stop = call("js_audio_stop");
+export const js_audio_queue = (id, pcm, rate) => {
+    if (inWorker) {
+        // pcm is a view of the wasm memory, post a copy of the chunk only
+        const c = pcm.slice();
+        self.postMessage({ t: "host", f: "js_audio_queue", args: [id, c, rate] }, [c.buffer]);
+    } else {
+        page.js_audio_queue(id, pcm, rate);
+    }
+};
@@ -1,15 +1,3 @@
-// rust call this function...
-export const js_load_asset = (url) => {
-    fetch(url)
-        .then(data=>{
-            return data.arrayBuffer();
-        })
-        .then(res=>{
-            sg.on_asset_loaded(url, new Uint8Array(res));
-        })
-    ;
-};
-
 const utils = {};
 
 utils.loop = update => {
@@ -28,7 +16,15 @@ utils.loop = update => {
 };
 
 import init, {PixelGame} from "./pkg/pixel.js";
-const wasm = await init();
+// webgpu_init is only exported by builds with the webgpu feature
+import * as pkg from "./pkg/pixel.js";
+import { setGame, runHostCall } from "./host.js";
+
+// worker mode runs the game loop in worker.js drawing to an
+// OffscreenCanvas, so heavy models do not block the page, try it with
+// index.html?worker
+const workerMode = new URLSearchParams(location.search).has("worker")
+    && "transferControlToOffscreen" in HTMLCanvasElement.prototype;
 
 const timg = new Image();
 timg.src = "assets/pix/c64.png";
@@ -40,17 +36,151 @@ const ctx = canvas.getContext("2d");
 ctx.drawImage(timg, 0, 0);
 const imgdata = ctx.getImageData(0,0,timg.width,timg.height).data;
 
-const sg = PixelGame.new();
-sg.upload_imgdata(timg.width, timg.height, imgdata);
+let sg;
+let worker = null;
+if (workerMode) {
+    // sg forwards calls to the worker, callbacks set with set_*_callback
+    // stay on the page and are called with the arguments the worker posts
+    const callbacks = {};
+    worker = new Worker("./worker.js", { type: "module" });
+    const offscreen = document.getElementById("canvas").transferControlToOffscreen();
+    worker.postMessage({ t: "init", canvas: offscreen, w: timg.width, h: timg.height, img: imgdata },
+        [offscreen, imgdata.buffer]);
+    worker.onmessage = (e) => {
+        const m = e.data;
+        if (m.t === "host") runHostCall(m);
+        else if (m.t === "callback") callbacks[m.method]?.(...m.args);
+        else if (m.t === "scaled") scaleToWindow();
+    };
+    sg = new Proxy({}, {
+        get: (_, method) => (...args) => {
+            if (method.startsWith("set_") && method.endsWith("_callback")) {
+                callbacks[method] = args[0];
+                args = [!!args[0]];
+            }
+            worker.postMessage({ t: "call", method, args });
+        },
+    });
+} else {
+    await init();
+    // WebGPU when the browser has an adapter, WebGL2 or WebGL1 otherwise
+    if (navigator.gpu) await pkg.webgpu_init?.();
+    sg = PixelGame.new();
+    sg.upload_imgdata(timg.width, timg.height, imgdata);
+}
+setGame(sg);
+
+// the page controls the game with window.pixelGame: pause(), resume(),
+// send_event(name, json), resize(cols, rows), set_frame_callback(f)
+// and set_message_callback(f) for messages posted by the game
+window.pixelGame = sg;
 
-// send event to rust...
-window.onkeypress = (e) => { sg.key_event(0, e); };
-window.onmouseup = (e) => { sg.key_event(1, e); };
-window.onmousedown = (e) => { sg.key_event(2, e); };
-window.onmousemove = (e) => { sg.key_event(3, e); };
+// send event to rust, a worker gets the fields WebInput reads
+const pick = (e) => ({
+    keyCode: e.keyCode, shiftKey: e.shiftKey, ctrlKey: e.ctrlKey, altKey: e.altKey,
+    metaKey: e.metaKey, repeat: e.repeat, button: e.button, buttons: e.buttons,
+    x: e.x, y: e.y, movementX: e.movementX, movementY: e.movementY, deltaY: e.deltaY,
+});
+const input = (t) => (e) => { sg.input_event(t, workerMode ? pick(e) : e); };
+window.onkeypress = input(0);
+window.onkeydown = input(5);
+window.onmouseup = input(1);
+window.onmousedown = input(2);
+window.onmousemove = input(3);
+window.onwheel = input(4);
+
+// touch points, t: 0 start, 1 move, 2 end, 3 cancel
+const touchHandler = (t) => (e) => {
+    e.preventDefault();
+    for (const tc of e.changedTouches) {
+        sg.touch_event(t, tc.identifier, tc.clientX, tc.clientY);
+    }
+};
+window.addEventListener("touchstart", touchHandler(0), { passive: false });
+window.addEventListener("touchmove", touchHandler(1), { passive: false });
+window.addEventListener("touchend", touchHandler(2), { passive: false });
+window.addEventListener("touchcancel", touchHandler(3), { passive: false });
+window.addEventListener("paste", (e) => {
+    const text = e.clipboardData.getData("text");
+    if (text) sg.paste_event(text);
+});
+window.oncontextmenu = (e) => { e.preventDefault(); };
+document.addEventListener("fullscreenchange", () => {
+    sg.fullscreen_event(!!document.fullscreenElement);
+});
+document.addEventListener("pointerlockchange", () => {
+    sg.pointer_lock_event(!!document.pointerLockElement);
+});
+// css size of the canvas, the whole window when the game scales
+const cv = document.getElementById("canvas");
+let cssSize = [cv.width, cv.height];
+// the canvas gets devicePixelRatio pixels per css pixel, so symbols are
+// crisp on high-dpi displays, a worker sizes its canvas by the args
+const fitCanvas = () => {
+    const dpr = window.devicePixelRatio || 1;
+    const [w, h] = cssSize;
+    cv.style.width = w + "px";
+    cv.style.height = h + "px";
+    sg.dpr_event(dpr, w, h);
+    if (!workerMode) {
+        cv.width = Math.round(w * dpr);
+        cv.height = Math.round(h * dpr);
+    }
+};
+const scaleToWindow = () => {
+    // the game letterboxes its canvas in the whole window
+    cssSize = [window.innerWidth, window.innerHeight];
+    cv.style.top = "0px";
+    cv.style.left = "0px";
+    fitCanvas();
+};
+window.addEventListener("resize", () => {
+    const scaled = sg.resize_event(window.innerWidth, window.innerHeight);
+    // a worker posts scaled back
+    if (scaled) scaleToWindow();
+    else fitCanvas();
+});
+window.dispatchEvent(new Event("resize"));
+// browser zoom and moving to another display change devicePixelRatio
+const watchPixelRatio = () => {
+    matchMedia(`(resolution: ${window.devicePixelRatio}dppx)`)
+        .addEventListener("change", () => { fitCanvas(); watchPixelRatio(); }, { once: true });
+};
+watchPixelRatio();
+
+// gamepad api has no button events, poll and send changes to rust...
+const padState = {};
+window.addEventListener("gamepadconnected", (e) => {
+    padState[e.gamepad.index] = { buttons: [], axes: [] };
+    sg.gamepad_event(e.gamepad.index, 0, 0, 0);
+});
+window.addEventListener("gamepaddisconnected", (e) => {
+    delete padState[e.gamepad.index];
+    sg.gamepad_event(e.gamepad.index, 1, 0, 0);
+});
+const pollGamepads = () => {
+    for (const pad of navigator.getGamepads()) {
+        if (!pad || !padState[pad.index]) continue;
+        const st = padState[pad.index];
+        pad.buttons.forEach((b, i) => {
+            if (b.pressed !== !!st.buttons[i]) {
+                sg.gamepad_event(pad.index, b.pressed ? 2 : 3, i, b.value);
+                st.buttons[i] = b.pressed;
+            }
+        });
+        pad.axes.forEach((v, i) => {
+            if (Math.abs(v - (st.axes[i] || 0)) > 0.01) {
+                sg.gamepad_event(pad.index, 4, i, v);
+                st.axes[i] = v;
+            }
+        });
+    }
+};
 
 utils.loop(function(timeStep) {
-    sg.tick(timeStep);
+    pollGamepads();
+    // worker.js runs its own loop
+    if (!workerMode) sg.tick(timeStep);
     return true;
 });
 
@@ -0,0 +1,69 @@
+// worker mode of index.js: the game loop runs here and draws to the
+// OffscreenCanvas of the page, which posts input and api calls as
+// { t: "call", method, args }
+import init, {PixelGame} from "./pkg/pixel.js";
+// webgpu_init is only exported by builds with the webgpu feature
+import * as pkg from "./pkg/pixel.js";
+import { setGame } from "./host.js";
+
+let sg = null;
+let canvas = null;
+// devicePixelRatio of the page, see fitCanvas of index.js
+let dpr = 1;
+// calls posted before the game is ready
+const pending = [];
+
+const run = (m) => {
+    if (m.method === "resize_event") {
+        if (sg.resize_event(...m.args)) self.postMessage({ t: "scaled" });
+    } else if (m.method === "dpr_event") {
+        // css size of the canvas on the page
+        const [r, w, h] = m.args;
+        dpr = r;
+        sg.dpr_event(dpr);
+        canvas.width = Math.round(w * dpr);
+        canvas.height = Math.round(h * dpr);
+    } else if (m.method.startsWith("set_") && m.method.endsWith("_callback")) {
+        // callbacks live on the page, post their arguments to it
+        const method = m.method;
+        sg[method](m.args[0] ? (...args) => self.postMessage({ t: "callback", method, args }) : null);
+    } else if (m.method === "resize") {
+        // a new cell size, the canvas follows
+        const [w, h] = sg.resize(...m.args);
+        canvas.width = Math.round(w * dpr);
+        canvas.height = Math.round(h * dpr);
+    } else {
+        sg[m.method](...m.args);
+    }
+};
+
+self.onmessage = async (e) => {
+    const m = e.data;
+    if (m.t === "init") {
+        await init();
+        canvas = m.canvas;
+        if (navigator.gpu) await pkg.webgpu_init?.();
+        sg = PixelGame.new_offscreen(canvas);
+        setGame(sg);
+        sg.upload_imgdata(m.w, m.h, m.img);
+        pending.forEach(run);
+        pending.length = 0;
+        let last = performance.now();
+        const frame = (now) => {
+            const dt = (now - last) * 0.001;
+            last = now;
+            if (dt > 0) sg.tick(dt);
+            next(frame);
+        };
+        next(frame);
+    } else if (m.t === "call") {
+        if (sg) run(m);
+        else pending.push(m);
+    }
+};
+
+// requestAnimationFrame of workers is missing in older browsers
+const next = (f) => {
+    if (self.requestAnimationFrame) self.requestAnimationFrame(f);
+    else setTimeout(() => f(performance.now()), 16);
+};
@@ -0,0 +1,197 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+/// cargo pixel asset convert/pack/validate
+///
+/// convert: every png of a directory to .pix by tpetii
+/// pack:    assets directory of a game to <game>/assets.pxpk by tpack
+/// validate: assets named in asset2sprite! calls must exist under
+///           <game>/assets, format! names are matched as patterns
+///
+use crate::{crate_path, exec_cmd, PixelContext};
+use clap::ArgMatches;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ASSET_EXTS: &str = "pix|txt|ssf|ase|aseprite|png|ans|xp";
+
+// a referenced asset name, a literal or a format! string
+#[derive(Debug, PartialEq)]
+enum AssetRef {
+    Exact(String),
+    Pattern(String),
+}
+
+fn files_under(dir: &Path, ext: Option<&str>, out: &mut Vec<PathBuf>) {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in rd.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files_under(&path, ext, out);
+        } else if ext.is_none() || path.extension().and_then(|e| e.to_str()) == ext {
+            out.push(path);
+        }
+    }
+}
+
+// asset names used by asset2sprite! in a source file
+fn asset_refs(src: &str) -> Vec<AssetRef> {
+    let mut refs = vec![];
+    if !src.contains("asset2sprite!") {
+        return refs;
+    }
+    let lit = Regex::new(r#"asset2sprite!\s*\(\s*[^,]+,\s*[^,]+,\s*&?"([^"]+)""#).unwrap();
+    for c in lit.captures_iter(src) {
+        refs.push(AssetRef::Exact(c[1].to_string()));
+    }
+    // names built by format!, e.g. format!("poker/{}.{}", n, ext)
+    let fmt = Regex::new(&format!(
+        r#"format!\(\s*"([^"]*(\.({})|\.\{{\}}))""#,
+        ASSET_EXTS
+    ))
+    .unwrap();
+    for c in fmt.captures_iter(src) {
+        if c[1].contains("{}") {
+            refs.push(AssetRef::Pattern(c[1].to_string()));
+        }
+    }
+    refs
+}
+
+// regex matching names of a format! string
+fn pattern_regex(p: &str) -> Regex {
+    let parts: Vec<String> = p.split("{}").map(regex::escape).collect();
+    Regex::new(&format!("^{}$", parts.join("[^/]+"))).unwrap()
+}
+
+fn validate(cpath: &str) -> usize {
+    let adir = Path::new(cpath).join("assets");
+    let mut assets = vec![];
+    files_under(&adir, None, &mut assets);
+    let names: Vec<String> = assets
+        .iter()
+        .filter_map(|p| p.strip_prefix(&adir).ok())
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .collect();
+
+    let mut srcs = vec![];
+    files_under(&Path::new(cpath).join("src"), Some("rs"), &mut srcs);
+    let mut checked = 0;
+    let mut missing = 0;
+    for s in srcs {
+        let content = fs::read_to_string(&s).unwrap_or_default();
+        for r in asset_refs(&content) {
+            checked += 1;
+            let ok = match &r {
+                AssetRef::Exact(n) => names.contains(n),
+                AssetRef::Pattern(p) => {
+                    let re = pattern_regex(p);
+                    names.iter().any(|n| re.is_match(n))
+                }
+            };
+            if !ok {
+                missing += 1;
+                println!(
+                    "❌ {}: {:?} not found in {}",
+                    s.display(),
+                    r,
+                    adir.display()
+                );
+            }
+        }
+    }
+
+    // .pix files need a header line, lines before it are skipped as PixAsset does
+    let header = Regex::new(r"(?m)^width=(\d+),height=(\d+),texture=(\d+)").unwrap();
+    for (p, n) in assets.iter().zip(names.iter()) {
+        if n.ends_with(".pix") {
+            let content = String::from_utf8_lossy(&fs::read(p).unwrap_or_default()).to_string();
+            if !header.is_match(&content) {
+                missing += 1;
+                println!("❌ {}: not a pix file", p.display());
+            }
+        }
+    }
+    println!(
+        "🍀 {} asset references checked, {} assets, {} problems",
+        checked,
+        names.len(),
+        missing
+    );
+    missing
+}
+
+pub fn pixel_asset(ctx: &PixelContext, args: &ArgMatches) {
+    match args.subcommand() {
+        Some(("convert", sub_m)) => {
+            let dir = sub_m.value_of("png_dir").unwrap();
+            let out = sub_m.value_of("out_dir").unwrap_or(dir);
+            let width = sub_m.value_of("width").unwrap();
+            let height = sub_m.value_of("height").unwrap();
+            let _ = fs::create_dir_all(out);
+            let _ = fs::create_dir_all("tmp");
+            let mut pngs = vec![];
+            files_under(Path::new(dir), Some("png"), &mut pngs);
+            println!("🍀 tpetii convert {} png to pix...", pngs.len());
+            for p in pngs {
+                let stem = p.file_stem().unwrap().to_string_lossy();
+                let pix = Path::new(out).join(format!("{}.pix", stem));
+                println!("   {} -> {}", p.display(), pix.display());
+                exec_cmd(&format!(
+                    "cargo r --bin tpetii --release {} {} {} > {} 2>/dev/null",
+                    p.display(),
+                    width,
+                    height,
+                    pix.display()
+                ));
+            }
+        }
+        Some(("pack", sub_m)) => {
+            let cpath = crate_path(ctx, sub_m.value_of("mod_name").unwrap());
+            let out = format!("{}/assets.pxpk", cpath);
+            println!("🍀 pack {}/assets -> {}", cpath, out);
+            exec_cmd(&format!(
+                "cargo r --bin tpack --release {}/assets {}",
+                cpath, out
+            ));
+        }
+        Some(("validate", sub_m)) => {
+            let cpath = crate_path(ctx, sub_m.value_of("mod_name").unwrap());
+            if validate(&cpath) > 0 {
+                std::process::exit(1);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asset_refs_of_source() {
+        let src = r#"
+            asset2sprite!(l, ctx, "back.txt");
+            asset2sprite!(ss, ctx, "1.ssf", (ctx.stage / 3) as usize, 40, 1);
+            let cn = format!("poker/{}.{}", d.card, ext);
+            asset2sprite!(l, ctx, &cn);
+            let msg = format!("score {}", s);
+        "#;
+        let refs = asset_refs(src);
+        assert_eq!(
+            refs,
+            vec![
+                AssetRef::Exact("back.txt".to_string()),
+                AssetRef::Exact("1.ssf".to_string()),
+                AssetRef::Pattern("poker/{}.{}".to_string()),
+            ]
+        );
+        let re = pattern_regex("poker/{}.{}");
+        assert!(re.is_match("poker/12.pix"));
+        assert!(!re.is_match("pix/poker/12.pix"));
+    }
+}
@@ -7,6 +7,12 @@
 /// cargo pixel run snake term
 /// cargo pixel run snake sdl
 /// cargo pixel creat games mygame
+/// cargo pixel new mygame
+/// cargo pixel new mytool apps
+/// cargo pixel new mygame --full
+/// cargo pixel asset convert games/mygame/pngs 40 25 games/mygame/assets/pix
+/// cargo pixel asset pack mygame
+/// cargo pixel asset validate mygame
 /// cargo pixel build snake web
 ///
 /// shortcut:
@@ -15,10 +21,15 @@
 /// cargo pixel r snake w
 /// ...
 ///
+mod asset;
+mod scaffold;
+
+use asset::pixel_asset;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use regex::Regex;
+use scaffold::Scaffold;
 use std::ffi::OsStr;
 use std::fs;
 use std::io::{self, Write};
@@ -90,6 +101,31 @@ fn make_parser() -> ArgMatches {
                 .arg(Arg::with_name("dir_name").required(true))
                 .arg(Arg::with_name("mod_name").required(true)),
         ))
+        .subcommand(common_arg(
+            SubCommand::with_name("new")
+                .alias("n")
+                .arg(Arg::with_name("mod_name").required(true))
+                .arg(Arg::with_name("dir_name").default_value("games"))
+                .arg(Arg::with_name("full").long("full").takes_value(false)),
+        ))
+        .subcommand(
+            SubCommand::with_name("asset")
+                .alias("a")
+                .subcommand(
+                    SubCommand::with_name("convert")
+                        .arg(Arg::with_name("png_dir").required(true))
+                        .arg(Arg::with_name("width").required(true))
+                        .arg(Arg::with_name("height").required(true))
+                        .arg(Arg::with_name("out_dir")),
+                )
+                .subcommand(
+                    SubCommand::with_name("pack").arg(Arg::with_name("mod_name").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("validate")
+                        .arg(Arg::with_name("mod_name").required(true)),
+                ),
+        )
         .subcommand(common_arg(
             SubCommand::with_name("convert_gif")
                 .alias("cg")
@@ -138,19 +174,7 @@ fn get_cmds(ctx: &PixelContext, args: &ArgMatches, subcmd: &str) -> Vec<String>
                 .join(" ")
         )),
         "web" | "w" => {
-            let mut crate_path = "".to_string();
-            if ctx.standalone {
-                crate_path = ".".to_string();
-            } else {
-                let cpath = format!("games/{}", mod_name);
-                if Path::new(&cpath).exists() {
-                    crate_path = cpath;
-                }
-                let cpath = format!("apps/{}", mod_name);
-                if Path::new(&cpath).exists() {
-                    crate_path = cpath;
-                }
-            }
+            let crate_path = crate_path(ctx, mod_name);
             cmds.push(format!(
                 "wasm-pack build --target web {} {} {}",
                 crate_path,
@@ -168,14 +192,14 @@ fn get_cmds(ctx: &PixelContext, args: &ArgMatches, subcmd: &str) -> Vec<String>
                 "cp {}/rust-pixel/web-templates/* {}",
                 ctx.rust_pixel_path, tmpwd
             ));
-            cmds.push(format!(
-                "sed -i '' \"s/Pixel/{}/g\" {}/index.js",
-                capname, tmpwd
-            ));
-            cmds.push(format!(
-                "sed -i '' \"s/pixel/{}/g\" {}/index.js",
-                loname, tmpwd
-            ));
+            // only the wasm-pack module and its class, words like
+            // devicePixelRatio must stay
+            for js in ["index.js", "worker.js"] {
+                cmds.push(format!(
+                    "sed -i '' \"s#pkg/pixel.js#pkg/{}.js#; s/PixelGame/{}Game/g\" {}/{}",
+                    loname, capname, tmpwd, js
+                ));
+            }
             cmds.push(format!("cp -r {}/pkg {}", crate_path, tmpwd));
             if subcmd == "run" {
                 cmds.push(format!("python3 -m http.server -d {} {}", tmpwd, webport));
@@ -187,6 +211,24 @@ fn get_cmds(ctx: &PixelContext, args: &ArgMatches, subcmd: &str) -> Vec<String>
     cmds
 }
 
+// directory of a game or app crate
+fn crate_path(ctx: &PixelContext, mod_name: &str) -> String {
+    let mut crate_path = "".to_string();
+    if ctx.standalone {
+        crate_path = ".".to_string();
+    } else {
+        let cpath = format!("games/{}", mod_name);
+        if Path::new(&cpath).exists() {
+            crate_path = cpath;
+        }
+        let cpath = format!("apps/{}", mod_name);
+        if Path::new(&cpath).exists() {
+            crate_path = cpath;
+        }
+    }
+    crate_path
+}
+
 fn capitalize(s: &str) -> String {
     let mut c = s.chars();
     match c.next() {
@@ -366,6 +408,71 @@ fn pixel_creat(ctx: &PixelContext, args: &ArgMatches) {
     }
 }
 
+// adds dir/* to the workspace members of Cargo.toml
+fn add_workspace_member(dir_name: &str) {
+    let ct = fs::read_to_string("Cargo.toml").unwrap();
+    let mut doc = ct.parse::<toml::Value>().unwrap();
+    if let Some(members) = doc
+        .get_mut("workspace")
+        .and_then(|w| w.get_mut("members"))
+        .and_then(|m| m.as_array_mut())
+    {
+        let ds: toml::Value = format!("{}/*", dir_name).into();
+        if members.contains(&ds) {
+            return;
+        }
+        members.push(ds);
+    }
+    println!("🍀 update Cargo.toml...");
+    fs::write("Cargo.toml", toml::to_string_pretty(&doc).unwrap()).unwrap();
+}
+
+fn pixel_new(ctx: &PixelContext, args: &ArgMatches) {
+    if ctx.standalone {
+        println!("Cargo pixel new must run in rust_pixel root directory.");
+        return;
+    }
+    let dir_name = args.value_of("dir_name").unwrap();
+    let mod_name = args.value_of("mod_name").unwrap();
+    let is_standalone = args.is_present("standalone");
+    let root = if is_standalone {
+        ctx.rust_pixel_path.clone()
+    } else {
+        "../..".to_string()
+    };
+    let sc = Scaffold::new(dir_name, mod_name, &root, is_standalone);
+    let pdir = format!("{}/{}", dir_name, sc.loname);
+    if Path::new(&pdir).exists() {
+        println!("{} already exists.", pdir);
+        return;
+    }
+    if !is_standalone {
+        add_workspace_member(dir_name);
+    }
+
+    println!("🍀 new {}...({}/)", sc.capname, pdir);
+    let files = if args.is_present("full") {
+        sc.files_from(&Path::new(&ctx.rust_pixel_path).join("games/starter"))
+    } else {
+        sc.files()
+    };
+    for (f, content) in files {
+        let path = Path::new(&pdir).join(f);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+    }
+
+    let cd = if is_standalone {
+        format!("cd {}\n   ", pdir)
+    } else {
+        "".to_string()
+    };
+    println!(
+        "🍀 compile & run: \n   {}cargo pixel r {} term\n   cargo pixel r {} sdl\n   cargo pixel r {} web",
+        cd, sc.loname, sc.loname, sc.loname
+    );
+}
+
 fn pixel_convert_gif(_ctx: &PixelContext, args: &ArgMatches) {
     let gif = args.value_of("gif").unwrap();
     let ssf = args.value_of("ssf").unwrap();
@@ -485,6 +592,8 @@ fn main() {
         Some(("run", sub_m)) => pixel_run(&ctx, sub_m),
         Some(("build", sub_m)) => pixel_build(&ctx, sub_m),
         Some(("creat", sub_m)) => pixel_creat(&ctx, sub_m),
+        Some(("new", sub_m)) => pixel_new(&ctx, sub_m),
+        Some(("asset", sub_m)) => pixel_asset(&ctx, sub_m),
         Some(("convert_gif", sub_m)) => pixel_convert_gif(&ctx, sub_m),
         _ => {}
     }
@@ -0,0 +1,259 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+/// files of a minimal game made by cargo pixel new
+///
+/// Unlike creat, which copies games/template with its poker assets and
+/// lib/ffi/wasm crates, new writes a small single crate: model.rs,
+/// render.rs, lib.rs with pixel_game!, main.rs, empty asset folders and
+/// a Cargo.toml with the cdylib and wasm dependencies wasm-pack needs
+/// for cargo pixel build <name> web.
+/// With --full the files are copied from games/starter instead, a
+/// complete snake game using the fixed timestep, InputMap, sound and
+/// save slots.
+///
+pub struct Scaffold {
+    /// lower case crate name, e.g. my_game
+    pub loname: String,
+    /// type prefix, e.g. MyGame
+    pub capname: String,
+    /// pixel_game! arguments after the name
+    pub game_args: String,
+    /// path of rust_pixel root as seen from the new crate
+    pub rust_pixel_root: String,
+    pub standalone: bool,
+}
+
+impl Scaffold {
+    pub fn new(dir_name: &str, mod_name: &str, rust_pixel_root: &str, standalone: bool) -> Self {
+        let loname = mod_name.to_lowercase().replace('-', "_");
+        let capname = loname
+            .split('_')
+            .map(|w| {
+                let mut c = w.chars();
+                match c.next() {
+                    None => String::new(),
+                    Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
+                }
+            })
+            .collect::<String>();
+        let game_args = if standalone {
+            ", \"app\", \".\"".to_string()
+        } else if dir_name == "games" {
+            "".to_string()
+        } else {
+            format!(", \"{}\"", dir_name)
+        };
+        Self {
+            loname,
+            capname,
+            game_args,
+            rust_pixel_root: rust_pixel_root.to_string(),
+            standalone,
+        }
+    }
+
+    /// (relative path, content) of all files
+    pub fn files(&self) -> Vec<(String, String)> {
+        let fs = vec![
+            ("Cargo.toml".to_string(), self.fill(CARGO_TOML)),
+            ("src/main.rs".to_string(), self.fill(MAIN_RS)),
+            ("src/lib.rs".to_string(), self.fill(LIB_RS)),
+            ("src/model.rs".to_string(), self.fill(MODEL_RS)),
+            ("src/render.rs".to_string(), self.fill(RENDER_RS)),
+            ("assets/pix/.gitkeep".to_string(), "".to_string()),
+            ("assets/sdq/.gitkeep".to_string(), "".to_string()),
+        ];
+        self.with_standalone(fs)
+    }
+
+    /// files of the game in dir, games/starter, renamed to this game
+    pub fn files_from(&self, dir: &Path) -> Vec<(String, String)> {
+        let mut fs = vec![];
+        collect_files(dir, dir, &mut fs);
+        for (path, content) in fs.iter_mut() {
+            let c = content
+                .replace("../../", &format!("{}/", self.rust_pixel_root))
+                .replace("pixel_game!(Starter,", "pixel_game!(Starter$GAME_ARGS,")
+                .replace("Starter", "$CAPNAME")
+                .replace("STARTER", "$UPNAME")
+                .replace("starter", "$LONAME");
+            *content = self.fill(&c);
+            *path = path.replace("starter", &self.loname);
+        }
+        self.with_standalone(fs)
+    }
+
+    fn with_standalone(&self, mut fs: Vec<(String, String)>) -> Vec<(String, String)> {
+        if self.standalone {
+            fs.push(("pixel.toml".to_string(), self.fill(PIXEL_TOML)));
+            fs.push(("tmp/.gitkeep".to_string(), "".to_string()));
+        }
+        fs
+    }
+
+    fn fill(&self, t: &str) -> String {
+        t.replace("$RUST_PIXEL_ROOT", &self.rust_pixel_root)
+            .replace("$GAME_ARGS", &self.game_args)
+            .replace("$CAPNAME", &self.capname)
+            .replace("$UPNAME", &self.loname.to_uppercase())
+            .replace("$LONAME", &self.loname)
+    }
+}
+
+// text files under dir with paths relative to root, build output skipped
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<(String, String)>) {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in rd.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if path.file_name() != Some(OsStr::new("target")) {
+                collect_files(root, &path, out);
+            }
+        } else if let Ok(content) = fs::read_to_string(&path) {
+            let rel = path.strip_prefix(root).unwrap().to_string_lossy();
+            out.push((rel.to_string(), content));
+        }
+    }
+}
+
+const CARGO_TOML: &str = r#"[package]
+name = "$LONAME"
+version = "0.1.0"
+edition = "2021"
+
+# cdylib is built by wasm-pack for the web, rlib by the native binary
+[lib]
+crate-type = ["cdylib", "rlib"]
+path = "src/lib.rs"
+
+[dependencies]
+log = "0.4.16"
+wasm-bindgen = "0.2.92"
+wasm-bindgen-futures = "0.4.42"
+web-sys = "0.3.4"
+rust_pixel = { path = "$RUST_PIXEL_ROOT/rust-pixel", default-features = false }
+pixel_macro = { path = "$RUST_PIXEL_ROOT/pixel_macro" }
+
+[features]
+sdl = ["rust_pixel/sdl"]
+term = ["rust_pixel/term"]
+"#;
+
+const PIXEL_TOML: &str = r#"# RustPixel
+# copyright zipxing@hotmail.com 2022~2024
+
+# pixel.toml is using for cargo-pixel
+# refer to tools/cargo-pixel/src/main.rs
+[pixel]
+standalone = true
+rust_pixel = "$RUST_PIXEL_ROOT"
+cargo_pixel = "0.5.1"
+"#;
+
+const MAIN_RS: &str = r#"fn main() {
+    $LONAME::run()
+}
+"#;
+
+const LIB_RS: &str = r#"mod model;
+mod render;
+
+use pixel_macro::pixel_game;
+pixel_game!($CAPNAME$GAME_ARGS, {
+    size: (model::$UPNAMEW, model::$UPNAMEH),
+    title: "$LONAME",
+});
+"#;
+
+const MODEL_RS: &str = r#"use rust_pixel::{
+    context::Context,
+    event::{Event, KeyCode},
+    game::Model,
+};
+
+pub const $UPNAMEW: u16 = 40;
+pub const $UPNAMEH: u16 = 20;
+
+pub struct $CAPNAMEModel {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl $CAPNAMEModel {
+    pub fn new() -> Self {
+        Self { x: 1, y: 1 }
+    }
+}
+
+impl Model for $CAPNAMEModel {
+    fn init(&mut self, _ctx: &mut Context) {}
+
+    fn handle_input(&mut self, ctx: &mut Context, _dt: f32) {
+        let es = ctx.input_events.clone();
+        for e in &es {
+            if let Event::Key(key) = e {
+                match key.code {
+                    KeyCode::Left => self.x = self.x.saturating_sub(1),
+                    KeyCode::Right => self.x = (self.x + 1).min($UPNAMEW - 2),
+                    KeyCode::Up => self.y = self.y.saturating_sub(1),
+                    KeyCode::Down => self.y = (self.y + 1).min($UPNAMEH - 2),
+                    _ => {}
+                }
+            }
+        }
+        ctx.input_events.clear();
+    }
+
+    fn handle_auto(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_event(&mut self, _ctx: &mut Context, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _dt: f32) {}
+}
+"#;
+
+const RENDER_RS: &str = r#"use crate::model::{$CAPNAMEModel, $UPNAMEH, $UPNAMEW};
+use rust_pixel::{
+    context::Context,
+    game::Render,
+    render::{panel::Panel, sprite::Sprite, style::Color},
+};
+
+pub struct $CAPNAMERender {
+    pub panel: Panel,
+}
+
+impl $CAPNAMERender {
+    pub fn new() -> Self {
+        let mut panel = Panel::new();
+        let mut msg = Sprite::new(0, $UPNAMEH - 1, $UPNAMEW, 1);
+        msg.set_default_str("$LONAME: arrow keys to move");
+        panel.add_sprite(msg, "msg");
+        panel.add_sprite(Sprite::new(0, 0, 1, 1), "hero");
+        Self { panel }
+    }
+}
+
+impl Render for $CAPNAMERender {
+    type Model = $CAPNAMEModel;
+
+    fn init(&mut self, ctx: &mut Context, _m: &mut Self::Model) {
+        self.panel.init(ctx);
+    }
+
+    fn handle_event(&mut self, _ctx: &mut Context, _m: &mut Self::Model, _dt: f32) {}
+    fn handle_timer(&mut self, _ctx: &mut Context, _m: &mut Self::Model, _dt: f32) {}
+
+    fn draw(&mut self, ctx: &mut Context, m: &mut Self::Model, _dt: f32) {
+        let hero = self.panel.get_sprite("hero");
+        hero.set_color_str(0, 0, "@", Color::Yellow, Color::Reset);
+        hero.set_pos(m.x, m.y);
+        self.panel.draw(ctx).unwrap();
+    }
+}
+"#;
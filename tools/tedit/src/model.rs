@@ -187,6 +187,7 @@ impl Model for TeditModel {
                         _ => {}
                     }
                 }
+                _ => {}
             }
         }
         context.input_events.clear();
@@ -0,0 +1,27 @@
+// RustPixel
+// copyright zipxing@hotmail.com 2022~2024
+
+// builds a pixel-pack bundle of an assets directory,
+// used by cargo pixel asset pack
+use rust_pixel::asset::{Pack, PackBuilder};
+use std::env;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: tpack <assets dir> <out.pxpk>");
+        return;
+    }
+    let mut pb = PackBuilder::new();
+    if let Err(e) = pb.add_dir(&args[1]) {
+        eprintln!("tpack: {}: {}", args[1], e);
+        std::process::exit(1);
+    }
+    let data = pb.build();
+    let files = Pack::from_bytes(&data).map(|p| p.names().len()).unwrap_or(0);
+    if let Err(e) = std::fs::write(&args[2], &data) {
+        eprintln!("tpack: {}: {}", args[2], e);
+        std::process::exit(1);
+    }
+    println!("{} files, {} bytes", files, data.len());
+}